@@ -17,8 +17,11 @@ fn create_test_pattern(stitch_count: usize) -> EmbPattern {
             pattern.color_change(0.0, 0.0);
         }
 
-        let x = (i % 100) as f64 * 10.0;
-        let y = (i / 100) as f64 * 10.0;
+        // Serpentine rows keep every stitch within DST's per-record range
+        let row = i / 100;
+        let col = if row % 2 == 0 { i % 100 } else { 99 - i % 100 };
+        let x = col as f64 * 10.0;
+        let y = row as f64 * 10.0;
         pattern.stitch_abs(x, y);
 
         // Add some jumps
@@ -179,12 +182,139 @@ fn bench_exp_io(c: &mut Criterion) {
     group.finish();
 }
 
+// Helper function to create a large pattern with short stitches that fit every format
+fn create_large_pattern(stitch_count: usize) -> EmbPattern {
+    let mut pattern = EmbPattern::new();
+    pattern.add_thread(EmbThread::from_string("red").unwrap());
+
+    for i in 0..stitch_count {
+        // Zig-zag up and down so every stitch stays short
+        let t = i % 400;
+        let x = ((i % 2) * 60) as f64;
+        let y = if t < 200 { t * 5 } else { (400 - t) * 5 } as f64;
+        pattern.stitch_abs(x, y);
+    }
+
+    pattern
+}
+
+// Benchmark: reading million-stitch DST/EXP files
+//
+// `memory` reads from an in-memory cursor; `file` reads from an unbuffered `File`,
+// where per-record reads used to cost one system call each.
+fn bench_large_reads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_reads");
+    group.sample_size(10);
+
+    let size = 1_000_000;
+    let pattern = create_large_pattern(size);
+    group.throughput(Throughput::Elements(size as u64));
+
+    let mut dst = Vec::new();
+    writers::dst::write(&mut dst, &pattern, false, 0).unwrap();
+    let mut exp = Vec::new();
+    writers::exp::write(&mut exp, &pattern).unwrap();
+
+    let dir = std::env::temp_dir();
+    let dst_path = dir.join("butabuti_bench_large.dst");
+    let exp_path = dir.join("butabuti_bench_large.exp");
+    std::fs::write(&dst_path, &dst).unwrap();
+    std::fs::write(&exp_path, &exp).unwrap();
+
+    group.bench_function("dst_memory", |b| {
+        b.iter(|| {
+            let pattern = readers::dst::read(&mut Cursor::new(&dst), None).unwrap();
+            black_box(pattern);
+        });
+    });
+    group.bench_function("dst_file", |b| {
+        b.iter(|| {
+            let mut file = std::fs::File::open(&dst_path).unwrap();
+            let pattern = readers::dst::read(&mut file, None).unwrap();
+            black_box(pattern);
+        });
+    });
+    group.bench_function("exp_memory", |b| {
+        b.iter(|| {
+            let pattern = readers::exp::read(&mut Cursor::new(&exp)).unwrap();
+            black_box(pattern);
+        });
+    });
+    group.bench_function("exp_file", |b| {
+        b.iter(|| {
+            let mut file = std::fs::File::open(&exp_path).unwrap();
+            let pattern = readers::exp::read(&mut file).unwrap();
+            black_box(pattern);
+        });
+    });
+
+    group.finish();
+
+    let _ = std::fs::remove_file(dst_path);
+    let _ = std::fs::remove_file(exp_path);
+}
+
+// Benchmark: writing large text-based exports
+//
+// `memory` writes into a `Vec`; `file` writes into an unbuffered `File`, where
+// every formatted field used to be a separate write call.
+fn bench_large_writes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large_writes");
+    group.sample_size(10);
+
+    let size = 200_000;
+    let mut pattern = create_large_pattern(size);
+    // Fractional coordinates exercise float formatting
+    pattern.translate(0.25, -0.5);
+    group.throughput(Throughput::Elements(size as u64));
+
+    let path = std::env::temp_dir().join("butabuti_bench_large_write.out");
+
+    type WriteFn = fn(&mut dyn std::io::Write, &EmbPattern);
+    let writers: [(&str, WriteFn); 4] = [
+        ("csv", |w, p| {
+            writers::csv::write(&mut &mut *w, p, CsvVersion::Full).unwrap();
+        }),
+        ("json", |w, p| {
+            writers::json::write(&mut &mut *w, p).unwrap();
+        }),
+        ("svg", |w, p| {
+            writers::svg::write(p, &mut &mut *w).unwrap();
+        }),
+        ("txt", |w, p| {
+            writers::txt::write(p, &mut &mut *w).unwrap();
+        }),
+    ];
+
+    for (name, write) in writers {
+        group.bench_function(format!("{}_memory", name), |b| {
+            b.iter(|| {
+                let mut buffer = Vec::new();
+                write(&mut buffer, &pattern);
+                black_box(buffer);
+            });
+        });
+        group.bench_function(format!("{}_file", name), |b| {
+            b.iter(|| {
+                let mut file = std::fs::File::create(&path).unwrap();
+                write(&mut file, &pattern);
+            });
+        });
+    }
+
+    group.finish();
+
+    let _ = std::fs::remove_file(path);
+}
+
 criterion_group!(
     benches,
     bench_dst_io,
     bench_json_io,
     bench_csv_io,
     bench_exp_io,
+    bench_large_reads,
+    bench_large_writes,
 );
 
 criterion_main!(benches);
@@ -20,7 +20,7 @@ fn create_multi_thread_pattern(stitch_count: usize, thread_count: usize) -> EmbP
     let mut pattern = EmbPattern::new();
 
     // Add threads
-    let colors = vec![
+    let colors = [
         "red", "blue", "green", "yellow", "purple", "orange", "pink", "brown",
     ];
     for i in 0..thread_count {
@@ -82,7 +82,7 @@ fn bench_transformations(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::new("rotate", size), &pattern, |b, pattern| {
             b.iter(|| {
                 let mut p = pattern.clone();
-                let _ = p.rotate(black_box(45.0));
+                p.rotate(black_box(45.0));
             });
         });
 
@@ -90,7 +90,7 @@ fn bench_transformations(c: &mut Criterion) {
         group.bench_with_input(BenchmarkId::new("scale", size), &pattern, |b, pattern| {
             b.iter(|| {
                 let mut p = pattern.clone();
-                let _ = p.scale(black_box(1.5), black_box(1.5));
+                p.scale(black_box(1.5), black_box(1.5));
             });
         });
 
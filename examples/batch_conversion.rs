@@ -16,7 +16,7 @@ fn main() -> Result<()> {
     let converter = BatchConverter::new()
         .input_dir("tests/DST Files")
         .output_dir("tests/Output/batch_pes")
-        .target_format("pes")
+        .target_format(Format::PES)
         .input_extensions(&["dst"])
         .overwrite(true)
         .parallel(true)
@@ -36,6 +36,7 @@ fn main() -> Result<()> {
                         output,
                         duration_ms,
                         file_size,
+                        warnings,
                     } => {
                         println!(
                             "  ✓ {} -> {} ({} KB, {} ms)",
@@ -44,6 +45,9 @@ fn main() -> Result<()> {
                             file_size / 1024,
                             duration_ms
                         );
+                        for warning in warnings {
+                            println!("      ⚠ {}", warning);
+                        }
                     }
                     ConversionResult::Failed { input, error, .. } => {
                         println!("  ✗ {} - Error: {}", input.display(), error);
@@ -74,7 +78,7 @@ fn main() -> Result<()> {
     let converter2 = BatchConverter::new()
         .input_files(&specific_files)
         .output_dir("tests/Output/batch_jef")
-        .target_format("jef")
+        .target_format(Format::JEF)
         .overwrite(true)
         .parallel(false) // Sequential processing
         .build();
@@ -98,7 +102,7 @@ fn main() -> Result<()> {
     let converter3 = BatchConverter::new()
         .input_dir("tests")
         .output_dir("tests/Output/recursive")
-        .target_format("exp")
+        .target_format(Format::EXP)
         .input_extensions(&["dst", "pes"])
         .recursive(true)
         .overwrite(true)
@@ -34,7 +34,15 @@ fn main() -> Result<()> {
     let exporter = MultiFormatExporter::new()
         .output_dir("tests/Output/multi_format")
         .base_name("sample_design")
-        .formats(&["dst", "pes", "jef", "vp3", "exp", "xxx", "pec"])
+        .formats(&[
+            Format::DST,
+            Format::PES,
+            Format::JEF,
+            Format::VP3,
+            Format::EXP,
+            Format::XXX,
+            Format::PEC,
+        ])
         .overwrite(true)
         .build();
 
@@ -68,7 +76,7 @@ fn main() -> Result<()> {
     let exporter2 = MultiFormatExporter::new()
         .output_dir("tests/Output/analysis")
         .base_name("pattern_analysis")
-        .formats(&["json", "csv", "txt", "svg"])
+        .formats(&[Format::JSON, Format::CSV, Format::TXT, Format::SVG])
         .overwrite(true)
         .build();
 
@@ -98,7 +106,7 @@ fn main() -> Result<()> {
     let exporter3 = MultiFormatExporter::new()
         .output_dir("tests/Output/modified")
         .base_name("translated_design")
-        .formats(&["dst", "pes", "jef"])
+        .formats(&[Format::DST, Format::PES, Format::JEF])
         .overwrite(true)
         .build();
 
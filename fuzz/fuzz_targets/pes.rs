@@ -0,0 +1,14 @@
+#![no_main]
+
+use butabuti::formats::io::options::ReadOptions;
+use butabuti::formats::io::traits::PatternReader;
+use butabuti::formats::Format;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+// Strict and lenient reads must fail cleanly, never panic or over-allocate
+fuzz_target!(|data: &[u8]| {
+    for options in [ReadOptions::new(), ReadOptions::new().lenient(true)] {
+        let _ = Format::PES.read_with_warnings(&mut Cursor::new(data), &options);
+    }
+});
@@ -0,0 +1,79 @@
+/*
+ * C interface to the butabuti embroidery library.
+ *
+ * Build the shared library with `cargo build --release --features ffi`.
+ * Coordinates are in 0.1mm. Strings are NUL-terminated UTF-8.
+ */
+#ifndef BUTABUTI_H
+#define BUTABUTI_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Stitch commands */
+#define BUTABUTI_STITCH 0u
+#define BUTABUTI_JUMP 1u
+#define BUTABUTI_TRIM 2u
+#define BUTABUTI_STOP 3u
+#define BUTABUTI_END 4u
+#define BUTABUTI_COLOR_CHANGE 5u
+#define BUTABUTI_SEQUIN_MODE 6u
+#define BUTABUTI_SEQUIN_EJECT 7u
+#define BUTABUTI_NEEDLE_SET 9u
+#define BUTABUTI_COMMAND_MASK 0xFFu
+
+typedef enum ButabutiStatus {
+    BUTABUTI_OK = 0,
+    BUTABUTI_NULL_POINTER = 1,
+    BUTABUTI_INVALID_UTF8 = 2,
+    BUTABUTI_IO = 3,
+    BUTABUTI_PARSE = 4,
+    BUTABUTI_UNSUPPORTED_FORMAT = 5,
+    BUTABUTI_INVALID_PATTERN = 6,
+    BUTABUTI_OUT_OF_RANGE = 7,
+    BUTABUTI_OTHER = 8,
+    BUTABUTI_PANIC = 9
+} ButabutiStatus;
+
+/* Opaque pattern handle */
+typedef struct ButabutiPattern ButabutiPattern;
+
+/* Return non-zero to stop iterating */
+typedef int32_t (*ButabutiStitchCallback)(void *user_data, double x, double y, uint32_t command);
+
+const char *butabuti_version(void);
+
+/* Last error of the calling thread, NULL if none; valid until its next failing call */
+const char *butabuti_last_error_message(void);
+
+ButabutiPattern *butabuti_pattern_new(void);
+void butabuti_pattern_free(ButabutiPattern *pattern);
+
+ButabutiStatus butabuti_read_file(const char *path, ButabutiPattern **out);
+ButabutiStatus butabuti_read_bytes(const uint8_t *data, size_t len, const char *format,
+                                   ButabutiPattern **out);
+ButabutiStatus butabuti_write_file(const ButabutiPattern *pattern, const char *path);
+
+size_t butabuti_pattern_stitch_count(const ButabutiPattern *pattern);
+size_t butabuti_pattern_thread_count(const ButabutiPattern *pattern);
+ButabutiStatus butabuti_pattern_thread_color(const ButabutiPattern *pattern, size_t index,
+                                             uint32_t *color);
+ButabutiStatus butabuti_pattern_bounds(const ButabutiPattern *pattern, double *min_x,
+                                       double *min_y, double *max_x, double *max_y);
+ButabutiStatus butabuti_pattern_for_each_stitch(const ButabutiPattern *pattern,
+                                                ButabutiStitchCallback callback,
+                                                void *user_data);
+
+ButabutiStatus butabuti_pattern_add_stitch(ButabutiPattern *pattern, uint32_t command, double x,
+                                           double y);
+ButabutiStatus butabuti_pattern_add_thread(ButabutiPattern *pattern, uint32_t color);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BUTABUTI_H */
@@ -6,10 +6,13 @@
 ///   butabuti validate <file>            - Validate pattern file
 ///   butabuti batch <input_dir> <output_dir> <format> - Batch convert files
 use butabuti::formats::registry::FormatRegistry;
+use butabuti::formats::Format;
 use butabuti::prelude::*;
 use butabuti::utils::batch::BatchConverter;
 use std::env;
 use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 use std::process;
 
 fn main() {
@@ -110,22 +113,11 @@ fn convert_file(input: &str, output: &str) -> Result<()> {
 
     let registry = FormatRegistry::new();
 
-    // Read input file
-    let mut input_file = File::open(input).map_err(Error::Io)?;
-
-    let input_format = registry
-        .get_format_from_path(input)
-        .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown input format: {}", input)))?;
-
-    if !input_format.can_read {
-        return Err(Error::UnsupportedFormat(format!(
-            "Format '{}' does not support reading",
-            input_format.name
-        )));
-    }
-
-    println!("  Reading {} format...", input_format.name.to_uppercase());
-    let pattern = registry.read_pattern(&mut input_file, input_format.name)?;
+    // Read input file, detecting the format from extension and content
+    let mut input_file = BufReader::new(File::open(input).map_err(Error::Io)?);
+    let (pattern, input_format) =
+        registry.read_pattern_detected(&mut input_file, Some(Path::new(input)))?;
+    println!("  Read {} format", input_format.to_uppercase());
 
     // Write output file
     let output_format = registry
@@ -156,24 +148,12 @@ fn show_info(filename: &str) -> Result<()> {
     println!("{}", "=".repeat(60));
 
     let registry = FormatRegistry::new();
-    let mut file = File::open(filename).map_err(Error::Io)?;
-
-    let format = registry
-        .get_format_from_path(filename)
-        .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown format: {}", filename)))?;
-
-    if !format.can_read {
-        return Err(Error::UnsupportedFormat(format!(
-            "Format '{}' does not support reading",
-            format.name
-        )));
-    }
-
-    let pattern = registry.read_pattern(&mut file, format.name)?;
+    let mut file = BufReader::new(File::open(filename).map_err(Error::Io)?);
+    let (pattern, format) = registry.read_pattern_detected(&mut file, Some(Path::new(filename)))?;
 
     // Basic info
     println!("\nBasic Information:");
-    println!("  Format: {}", format.name.to_uppercase());
+    println!("  Format: {}", format.to_uppercase());
     println!("  Stitches: {}", pattern.count_stitches());
     println!("  Colors: {}", pattern.threads().len());
     println!("  Jumps: {}", pattern.count_jumps());
@@ -227,21 +207,10 @@ fn validate_file(filename: &str) -> Result<()> {
     println!("Validating: {}", filename);
 
     let registry = FormatRegistry::new();
-    let mut file = File::open(filename).map_err(Error::Io)?;
-
-    let format = registry
-        .get_format_from_path(filename)
-        .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown format: {}", filename)))?;
-
-    if !format.can_read {
-        return Err(Error::UnsupportedFormat(format!(
-            "Format '{}' does not support reading",
-            format.name
-        )));
-    }
+    let mut file = BufReader::new(File::open(filename).map_err(Error::Io)?);
 
-    match registry.read_pattern(&mut file, format.name) {
-        Ok(pattern) => {
+    match registry.read_pattern_detected(&mut file, Some(Path::new(filename))) {
+        Ok((pattern, _)) => {
             println!("✓ File is valid");
 
             // Check for common issues
@@ -287,11 +256,9 @@ fn batch_convert(input_dir: &str, output_dir: &str, target_format: &str) -> Resu
     let registry = FormatRegistry::new();
 
     // Validate target format
-    let format_info = registry
-        .get_format(target_format)
-        .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown format: {}", target_format)))?;
+    let format: Format = target_format.parse()?;
 
-    if !format_info.can_write {
+    if !format.can_write() {
         return Err(Error::UnsupportedFormat(format!(
             "Format '{}' does not support writing",
             target_format
@@ -308,7 +275,7 @@ fn batch_convert(input_dir: &str, output_dir: &str, target_format: &str) -> Resu
     let converter = BatchConverter::new()
         .input_dir(input_dir)
         .output_dir(output_dir)
-        .target_format(target_format)
+        .target_format(format)
         .input_extensions(&input_extensions)
         .overwrite(true)
         .build();
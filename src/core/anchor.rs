@@ -0,0 +1,289 @@
+//! Named anchor points for positioning designs relative to each other
+//!
+//! Custom anchors are stored in pattern metadata under `anchor.<name>` as `x,y`
+//! (0.1mm units), so they survive JSON round-trips and move with the pattern when it
+//! is translated, rotated, scaled, flipped or transformed by a matrix.
+//!
+//! Built-in anchors (center, corners, first/last stitch) are computed from the
+//! stitches on demand.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::anchor::{Anchor, AnchorPair};
+//!
+//! let mut frame = EmbPattern::new();
+//! frame.stitch_abs(0.0, 0.0);
+//! frame.stitch_abs(400.0, 300.0);
+//! frame.set_anchor("slot", 200.0, 100.0);
+//!
+//! let mut badge = EmbPattern::new();
+//! badge.stitch_abs(0.0, 0.0);
+//! badge.stitch_abs(50.0, 50.0);
+//!
+//! // Put the badge's center on the frame's "slot" anchor
+//! badge.align_to(&frame, &AnchorPair::new(Anchor::Center, Anchor::named("slot")))?;
+//! assert_eq!(badge.anchor_point(&Anchor::Center), Some((200.0, 100.0)));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::{Error, Result};
+use std::fmt;
+
+/// Metadata key prefix of custom anchors
+pub const ANCHOR_PREFIX: &str = "anchor.";
+
+/// A reference point on a pattern
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    /// Center of the bounding box
+    Center,
+    /// Top-left corner of the bounding box (minimum X and Y)
+    TopLeft,
+    /// Top-right corner of the bounding box
+    TopRight,
+    /// Bottom-left corner of the bounding box
+    BottomLeft,
+    /// Bottom-right corner of the bounding box (maximum X and Y)
+    BottomRight,
+    /// Position of the first needle-down stitch
+    FirstStitch,
+    /// Position of the last needle-down stitch
+    LastStitch,
+    /// Custom anchor stored with `EmbPattern::set_anchor`
+    Named(String),
+}
+
+impl Anchor {
+    /// Reference a custom anchor by name
+    pub fn named(name: impl Into<String>) -> Self {
+        Anchor::Named(name.into())
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anchor::Center => write!(f, "center"),
+            Anchor::TopLeft => write!(f, "top-left"),
+            Anchor::TopRight => write!(f, "top-right"),
+            Anchor::BottomLeft => write!(f, "bottom-left"),
+            Anchor::BottomRight => write!(f, "bottom-right"),
+            Anchor::FirstStitch => write!(f, "first stitch"),
+            Anchor::LastStitch => write!(f, "last stitch"),
+            Anchor::Named(name) => write!(f, "'{}'", name),
+        }
+    }
+}
+
+/// Which anchor of the moved pattern snaps onto which anchor of the reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnchorPair {
+    /// Anchor on the pattern being moved
+    pub source: Anchor,
+    /// Anchor on the reference pattern
+    pub target: Anchor,
+    /// Extra offset applied after snapping (0.1mm units)
+    pub offset: (f64, f64),
+}
+
+impl AnchorPair {
+    /// Snap `source` exactly onto `target`
+    pub fn new(source: Anchor, target: Anchor) -> Self {
+        Self {
+            source,
+            target,
+            offset: (0.0, 0.0),
+        }
+    }
+
+    /// Keep a gap between the two anchors
+    pub fn with_offset(mut self, dx: f64, dy: f64) -> Self {
+        self.offset = (dx, dy);
+        self
+    }
+}
+
+/// Parse an `x,y` anchor value
+pub(crate) fn parse_point(value: &str) -> Option<(f64, f64)> {
+    let (x, y) = value.split_once(',')?;
+    let x: f64 = x.trim().parse().ok()?;
+    let y: f64 = y.trim().parse().ok()?;
+    (x.is_finite() && y.is_finite()).then_some((x, y))
+}
+
+/// Format an anchor value as `x,y`
+pub(crate) fn format_point(x: f64, y: f64) -> String {
+    format!("{},{}", x, y)
+}
+
+impl EmbPattern {
+    /// Store a custom anchor point
+    pub fn set_anchor(&mut self, name: &str, x: f64, y: f64) {
+        self.set_metadata(format!("{}{}", ANCHOR_PREFIX, name), format_point(x, y));
+    }
+
+    /// Get a custom anchor point
+    pub fn anchor(&self, name: &str) -> Option<(f64, f64)> {
+        self.get_metadata(&format!("{}{}", ANCHOR_PREFIX, name))
+            .and_then(|value| parse_point(value))
+    }
+
+    /// Remove a custom anchor point, returning its position
+    pub fn remove_anchor(&mut self, name: &str) -> Option<(f64, f64)> {
+        let point = self.anchor(name);
+        self.remove_metadata(&format!("{}{}", ANCHOR_PREFIX, name));
+        point
+    }
+
+    /// All custom anchors, sorted by name
+    pub fn anchors(&self) -> Vec<(String, (f64, f64))> {
+        let mut anchors: Vec<_> = self
+            .metadata()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix(ANCHOR_PREFIX)?;
+                Some((name.to_string(), parse_point(value)?))
+            })
+            .collect();
+        anchors.sort_by(|a, b| a.0.cmp(&b.0));
+        anchors
+    }
+
+    /// Resolve an anchor to a position
+    ///
+    /// Returns `None` for a missing custom anchor, or for built-in anchors of a
+    /// pattern without stitches.
+    pub fn anchor_point(&self, anchor: &Anchor) -> Option<(f64, f64)> {
+        if let Anchor::Named(name) = anchor {
+            return self.anchor(name);
+        }
+
+        if self.stitches().is_empty() {
+            return None;
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        let needle_down = || {
+            self.stitches()
+                .iter()
+                .filter(|s| s.command & COMMAND_MASK == STITCH)
+        };
+
+        match anchor {
+            Anchor::Center => Some(((min_x + max_x) / 2.0, (min_y + max_y) / 2.0)),
+            Anchor::TopLeft => Some((min_x, min_y)),
+            Anchor::TopRight => Some((max_x, min_y)),
+            Anchor::BottomLeft => Some((min_x, max_y)),
+            Anchor::BottomRight => Some((max_x, max_y)),
+            Anchor::FirstStitch => needle_down().next().map(|s| (s.x, s.y)),
+            Anchor::LastStitch => needle_down().next_back().map(|s| (s.x, s.y)),
+            Anchor::Named(_) => unreachable!(),
+        }
+    }
+
+    /// Move this pattern so its source anchor lands on the other pattern's target anchor
+    ///
+    /// Custom anchors move with the pattern. Returns the applied translation.
+    pub fn align_to(&mut self, other: &EmbPattern, pair: &AnchorPair) -> Result<(f64, f64)> {
+        let (sx, sy) = self.anchor_point(&pair.source).ok_or_else(|| {
+            Error::InvalidPattern(format!("Source anchor {} not found", pair.source))
+        })?;
+        let (tx, ty) = other.anchor_point(&pair.target).ok_or_else(|| {
+            Error::InvalidPattern(format!("Target anchor {} not found", pair.target))
+        })?;
+
+        let dx = tx - sx + pair.offset.0;
+        let dy = ty - sy + pair.offset.1;
+        self.translate(dx, dy);
+
+        Ok((dx, dy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::matrix::EmbMatrix;
+
+    fn square(size: f64) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(JUMP, -5.0, -5.0);
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, size, 0.0);
+        pattern.add_stitch_absolute(STITCH, size, size);
+        pattern
+    }
+
+    #[test]
+    fn test_builtin_anchors() {
+        let pattern = square(100.0);
+
+        assert_eq!(pattern.anchor_point(&Anchor::Center), Some((47.5, 47.5)));
+        assert_eq!(pattern.anchor_point(&Anchor::TopLeft), Some((-5.0, -5.0)));
+        assert_eq!(
+            pattern.anchor_point(&Anchor::BottomRight),
+            Some((100.0, 100.0))
+        );
+        assert_eq!(pattern.anchor_point(&Anchor::FirstStitch), Some((0.0, 0.0)));
+        assert_eq!(
+            pattern.anchor_point(&Anchor::LastStitch),
+            Some((100.0, 100.0))
+        );
+        assert_eq!(EmbPattern::new().anchor_point(&Anchor::Center), None);
+    }
+
+    #[test]
+    fn test_custom_anchors_follow_transforms() {
+        let mut pattern = square(100.0);
+        pattern.set_anchor("hole", 10.0, 20.0);
+        pattern.set_anchor("tab", 0.0, 0.0);
+
+        pattern.translate(5.0, -5.0);
+        assert_eq!(pattern.anchor("hole"), Some((15.0, 15.0)));
+
+        pattern.scale(2.0, 2.0);
+        assert_eq!(pattern.anchor("hole"), Some((30.0, 30.0)));
+
+        pattern.flip_horizontal();
+        assert_eq!(pattern.anchor("hole"), Some((-30.0, 30.0)));
+
+        let mut matrix = EmbMatrix::new();
+        matrix.post_translate(30.0, 0.0);
+        pattern.apply_matrix(&matrix);
+        assert_eq!(pattern.anchor("hole"), Some((0.0, 30.0)));
+
+        assert_eq!(
+            pattern.anchors(),
+            vec![
+                ("hole".to_string(), (0.0, 30.0)),
+                ("tab".to_string(), (20.0, -10.0)),
+            ]
+        );
+        assert_eq!(pattern.remove_anchor("tab"), Some((20.0, -10.0)));
+        assert_eq!(pattern.anchor("tab"), None);
+    }
+
+    #[test]
+    fn test_align_to() {
+        let mut base = square(200.0);
+        base.set_anchor("slot", 150.0, 50.0);
+
+        let mut part = square(20.0);
+        part.set_anchor("pin", 20.0, 0.0);
+
+        let pair =
+            AnchorPair::new(Anchor::named("pin"), Anchor::named("slot")).with_offset(0.0, 5.0);
+        let offset = part.align_to(&base, &pair).unwrap();
+
+        assert_eq!(offset, (130.0, 55.0));
+        assert_eq!(part.anchor("pin"), Some((150.0, 55.0)));
+        assert_eq!(part.anchor_point(&Anchor::FirstStitch), Some((130.0, 55.0)));
+
+        let missing = AnchorPair::new(Anchor::Center, Anchor::named("nope"));
+        let err = part.align_to(&base, &missing).unwrap_err();
+        assert!(err.to_string().contains("'nope'"));
+    }
+}
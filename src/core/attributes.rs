@@ -0,0 +1,279 @@
+//! Per-stitch extended attributes
+//!
+//! The `command` word of a [`Stitch`](crate::core::pattern::Stitch) has no
+//! room left for annotations like applique notes, foam flags or needle hints.
+//! [`StitchAttributes`] is an optional side-table on [`EmbPattern`] holding
+//! string key-value pairs per record, keyed by record index.
+//!
+//! Transforms that keep the record order keep the attributes with their
+//! records: coordinate transforms, record edits through the
+//! [editor](crate::core::editor) and selections, removing duplicate or small
+//! stitches, splitting long stitches, smoothing, lock stitches and encoding
+//! for a format. Other passes that rebuild the record list, like travel
+//! optimization or color sorting, drop them. The table is serialized with the
+//! pattern and written per stitch by the JSON writer.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.stitch_abs(100.0, 100.0);
+//! pattern.end();
+//!
+//! pattern.set_stitch_attribute(1, "needle", "4")?;
+//! pattern.set_stitch_attribute(2, "needle", "4")?;
+//! assert_eq!(pattern.stitch_attribute(1, "needle"), Some("4"));
+//! assert_eq!(pattern.stitches_with_attribute("needle"), vec![1, 2]);
+//!
+//! // Moving stitches keeps their attributes
+//! pattern.translate(50.0, 0.0);
+//! assert_eq!(pattern.stitch_attribute(2, "needle"), Some("4"));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Key-value attributes of one record
+pub type Attributes = BTreeMap<String, String>;
+
+/// Side-table of attributes keyed by record index
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StitchAttributes {
+    records: BTreeMap<usize, Attributes>,
+}
+
+impl StitchAttributes {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether no record has attributes
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Number of records with attributes
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Attributes of a record
+    pub fn get(&self, index: usize) -> Option<&Attributes> {
+        self.records.get(&index)
+    }
+
+    /// Value of one attribute of a record
+    pub fn value(&self, index: usize, key: &str) -> Option<&str> {
+        self.records.get(&index)?.get(key).map(String::as_str)
+    }
+
+    /// Set an attribute of a record, returning the previous value
+    pub fn set(
+        &mut self,
+        index: usize,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        self.records
+            .entry(index)
+            .or_default()
+            .insert(key.into(), value.into())
+    }
+
+    /// Remove an attribute of a record, returning its value
+    pub fn remove(&mut self, index: usize, key: &str) -> Option<String> {
+        let attributes = self.records.get_mut(&index)?;
+        let value = attributes.remove(key);
+        if attributes.is_empty() {
+            self.records.remove(&index);
+        }
+        value
+    }
+
+    /// Remove all attributes of a record
+    pub fn remove_record(&mut self, index: usize) -> Option<Attributes> {
+        self.records.remove(&index)
+    }
+
+    /// Records with attributes, in record order
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Attributes)> {
+        self.records
+            .iter()
+            .map(|(&index, attributes)| (index, attributes))
+    }
+
+    /// Remove all attributes
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// Move attributes to new record indices
+    ///
+    /// `map` gives the new index of each old one, or `None` when the record
+    /// was removed. Attributes mapped to the same record are merged, later
+    /// records winning.
+    pub(crate) fn remap(&mut self, mut map: impl FnMut(usize) -> Option<usize>) {
+        let records = std::mem::take(&mut self.records);
+        for (index, attributes) in records {
+            if let Some(new_index) = map(index) {
+                self.records
+                    .entry(new_index)
+                    .or_default()
+                    .extend(attributes);
+            }
+        }
+    }
+
+    /// Follow replacing `remove` records at `index` with `insert` records
+    ///
+    /// Attributes of removed records are dropped.
+    pub(crate) fn splice(&mut self, index: usize, remove: usize, insert: usize) {
+        if self.is_empty() {
+            return;
+        }
+        self.remap(|i| {
+            if i < index {
+                Some(i)
+            } else if i < index + remove {
+                None
+            } else {
+                Some(i - remove + insert)
+            }
+        });
+    }
+
+    /// Follow rebuilding the records, given the old index each new record comes from
+    ///
+    /// New records without an origin get no attributes, and attributes of old
+    /// records no new record comes from are dropped.
+    pub(crate) fn rebuild(&mut self, origins: &[Option<usize>]) {
+        if self.is_empty() {
+            return;
+        }
+        let new_indices: BTreeMap<usize, usize> = origins
+            .iter()
+            .enumerate()
+            .filter_map(|(new, old)| Some(((*old)?, new)))
+            .collect();
+        self.remap(|i| new_indices.get(&i).copied());
+    }
+}
+
+impl EmbPattern {
+    /// Set an attribute of a record
+    ///
+    /// Returns an error when the record doesn't exist.
+    pub fn set_stitch_attribute(
+        &mut self,
+        index: usize,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<()> {
+        if index >= self.stitches().len() {
+            return Err(Error::InvalidPattern(format!(
+                "Record {} is outside the {} records",
+                index,
+                self.stitches().len()
+            )));
+        }
+        self.attributes_mut().set(index, key, value);
+        Ok(())
+    }
+
+    /// Value of one attribute of a record
+    pub fn stitch_attribute(&self, index: usize, key: &str) -> Option<&str> {
+        self.attributes().value(index, key)
+    }
+
+    /// All attributes of a record
+    pub fn stitch_attributes(&self, index: usize) -> Option<&Attributes> {
+        self.attributes().get(index)
+    }
+
+    /// Remove an attribute of a record, returning its value
+    pub fn remove_stitch_attribute(&mut self, index: usize, key: &str) -> Option<String> {
+        self.attributes_mut().remove(index, key)
+    }
+
+    /// Indices of the records having an attribute, in record order
+    pub fn stitches_with_attribute(&self, key: &str) -> Vec<usize> {
+        self.attributes()
+            .iter()
+            .filter(|(_, attributes)| attributes.contains_key(key))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+    use crate::core::pattern::Stitch;
+    use crate::formats::io::memory::{read_from_bytes, write_to_bytes};
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for i in 0..6 {
+            pattern.stitch_abs(i as f64 * 10.0, 0.0);
+        }
+        pattern.end();
+        pattern.set_stitch_attribute(1, "note", "start").unwrap();
+        pattern.set_stitch_attribute(4, "note", "corner").unwrap();
+        pattern.set_stitch_attribute(4, "needle", "2").unwrap();
+        pattern
+    }
+
+    #[test]
+    fn test_stitch_attributes() {
+        let mut pattern = sample();
+        assert_eq!(pattern.attributes().len(), 2);
+        assert_eq!(pattern.stitch_attributes(4).map(|a| a.len()), Some(2));
+        assert_eq!(pattern.stitches_with_attribute("note"), vec![1, 4]);
+        assert!(pattern.set_stitch_attribute(7, "note", "x").is_err());
+
+        assert_eq!(
+            pattern.remove_stitch_attribute(1, "note"),
+            Some("start".to_string())
+        );
+        assert_eq!(pattern.stitch_attributes(1), None);
+        assert_eq!(pattern.attributes().len(), 1);
+    }
+
+    #[test]
+    fn test_stitch_attributes_follow_edits() {
+        let mut pattern = sample();
+        pattern.splice_stitches(0, 2, vec![Stitch::new(0.0, 5.0, STITCH)]);
+        assert_eq!(pattern.stitches_with_attribute("note"), vec![3]);
+
+        // A duplicate before the annotated stitch
+        let mut pattern = sample();
+        pattern.splice_stitches(2, 0, vec![Stitch::new(10.0, 0.0, STITCH)]);
+        pattern.remove_duplicates();
+        assert_eq!(pattern.stitch_attribute(4, "needle"), Some("2"));
+
+        let mut pattern = sample();
+        pattern.split_long_stitches(5.0).unwrap();
+        assert_eq!(pattern.stitch_attribute(8, "needle"), Some("2"));
+        assert_eq!(pattern.stitches()[8].x, 40.0);
+    }
+
+    #[test]
+    fn test_stitch_attributes_round_trip() {
+        let pattern = sample();
+        for format in ["json", "buta"] {
+            let bytes = write_to_bytes(&pattern, format).unwrap();
+            let read = read_from_bytes(&bytes, format).unwrap();
+            assert_eq!(read.attributes(), pattern.attributes(), "{}", format);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! Color blocks
+//!
+//! A [`StitchBlock`] is the run of records sewn with one thread: the thread
+//! change that starts it, the stitches, jumps, trims and stops in between, and
+//! the END record if it is the last block. [`EmbPattern::blocks`] splits a
+//! pattern into blocks and [`EmbPattern::from_blocks`] joins them back, so
+//! writers, optimizers and renderers can work block by block without
+//! re-deriving color boundaries.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.add_thread(EmbThread::new(0x0000FF));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.color_change(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 100.0);
+//! pattern.end();
+//!
+//! let mut blocks = pattern.blocks();
+//! assert_eq!(blocks.len(), 2);
+//! assert_eq!(blocks[1].thread_index, 1);
+//! assert_eq!(blocks[1].stitch_count(), 1);
+//!
+//! // Sew the blue block first
+//! blocks.swap(0, 1);
+//! let reordered = EmbPattern::from_blocks(&blocks, pattern.threads().to_vec());
+//! assert_eq!(reordered.stitches()[1].y, 100.0);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+
+/// Records sewn with one thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct StitchBlock {
+    /// Index of the block's thread in the pattern's thread list
+    pub thread_index: usize,
+    /// The COLOR_CHANGE or NEEDLE_SET record that starts the block
+    ///
+    /// `None` for a first block that starts without a thread change.
+    pub start_command: Option<Stitch>,
+    /// Stitches, jumps, trims and other records of the block, in order
+    pub stitches: Vec<Stitch>,
+    /// The END record that ends the pattern, on the last block
+    pub end_command: Option<Stitch>,
+}
+
+impl StitchBlock {
+    /// Create an empty block for a thread
+    pub fn new(thread_index: usize) -> Self {
+        Self {
+            thread_index,
+            start_command: None,
+            stitches: Vec::new(),
+            end_command: None,
+        }
+    }
+
+    /// Number of STITCH records in the block
+    pub fn stitch_count(&self) -> usize {
+        self.stitches
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .count()
+    }
+
+    /// Positions of the STITCH records in the block
+    pub fn points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.stitches
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| (s.x, s.y))
+    }
+
+    /// Bounds of the STITCH records as `(min_x, min_y, max_x, max_y)`
+    ///
+    /// Returns `None` when the block has no stitches.
+    pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        self.points().fold(None, |bounds, (x, y)| {
+            Some(match bounds {
+                None => (x, y, x, y),
+                Some((min_x, min_y, max_x, max_y)) => {
+                    (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+                }
+            })
+        })
+    }
+
+    /// The block's thread from a thread list
+    pub fn thread<'a>(&self, threads: &'a [EmbThread]) -> Option<&'a EmbThread> {
+        threads.get(self.thread_index)
+    }
+
+    /// Whether the block has no records at all
+    pub fn is_empty(&self) -> bool {
+        self.start_command.is_none() && self.stitches.is_empty() && self.end_command.is_none()
+    }
+}
+
+impl EmbPattern {
+    /// Split the pattern into color blocks
+    ///
+    /// Each COLOR_CHANGE starts a new block with the next thread. A NEEDLE_SET
+    /// starts one once the current block has stitches; before that it only
+    /// selects the current block's needle. Records after END are ignored.
+    /// Joining the blocks with [`from_blocks`](Self::from_blocks) gives back
+    /// the same records.
+    pub fn blocks(&self) -> Vec<StitchBlock> {
+        let mut blocks = Vec::new();
+        let mut block = StitchBlock::new(0);
+        let mut sewn = false;
+
+        for &stitch in self.stitches() {
+            let command = stitch.command & COMMAND_MASK;
+            let starts_block = match command {
+                COLOR_CHANGE => true,
+                NEEDLE_SET => sewn,
+                _ => false,
+            };
+            if starts_block {
+                let next = StitchBlock::new(block.thread_index + 1);
+                let previous = std::mem::replace(&mut block, next);
+                if !previous.is_empty() {
+                    blocks.push(previous);
+                }
+                block.start_command = Some(stitch);
+                sewn = false;
+                continue;
+            }
+
+            match command {
+                NEEDLE_SET if block.start_command.is_none() && block.stitches.is_empty() => {
+                    block.start_command = Some(stitch);
+                }
+                END => {
+                    block.end_command = Some(stitch);
+                    break;
+                }
+                _ => {
+                    sewn |= command == STITCH;
+                    block.stitches.push(stitch);
+                }
+            }
+        }
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    /// Build a pattern from color blocks and a thread list
+    ///
+    /// Blocks are joined in order. Only the last block's END record is kept,
+    /// so reordered blocks still end the pattern once.
+    pub fn from_blocks(blocks: &[StitchBlock], threads: Vec<EmbThread>) -> Self {
+        let mut pattern = EmbPattern::new();
+        pattern.replace_stitches(join_blocks(blocks), threads);
+        pattern
+    }
+}
+
+/// Records of blocks joined in order, ending with the last block's END record
+pub(crate) fn join_blocks(blocks: &[StitchBlock]) -> Vec<Stitch> {
+    let mut stitches: Vec<Stitch> =
+        Vec::with_capacity(blocks.iter().map(|b| b.stitches.len() + 2).sum::<usize>());
+    for block in blocks {
+        stitches.extend(block.start_command);
+        stitches.extend_from_slice(&block.stitches);
+    }
+    if let Some(end) = blocks.iter().rev().find_map(|b| b.end_command) {
+        let end = stitches
+            .last()
+            .map_or(end, |last| Stitch::new(last.x, last.y, end.command));
+        stitches.push(end);
+    }
+    stitches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_blocks() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.add_command(NEEDLE_SET, 0.0, 0.0);
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+        pattern.trim();
+        pattern.add_command(COLOR_CHANGE, 100.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 50.0, 50.0);
+        pattern.stitch_abs(50.0, 80.0);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_blocks() {
+        let pattern = two_blocks();
+        let blocks = pattern.blocks();
+
+        assert_eq!(blocks.len(), 2);
+        let (first, second) = (&blocks[0], &blocks[1]);
+        assert_eq!(first.thread_index, 0);
+        assert_eq!(first.start_command.map(|s| s.command), Some(NEEDLE_SET));
+        assert_eq!(first.stitches.len(), 3);
+        assert_eq!(first.stitch_count(), 2);
+        assert_eq!(first.end_command, None);
+        assert_eq!(first.bounds(), Some((0.0, 0.0, 100.0, 0.0)));
+        assert_eq!(
+            first.thread(pattern.threads()).map(|t| t.color),
+            Some(0xFF0000)
+        );
+
+        assert_eq!(second.thread_index, 1);
+        assert_eq!(second.start_command.map(|s| s.command), Some(COLOR_CHANGE));
+        assert_eq!(second.points().collect::<Vec<_>>(), vec![(50.0, 80.0)]);
+        assert_eq!(second.end_command.map(|s| s.command), Some(END));
+    }
+
+    #[test]
+    fn test_from_blocks_round_trip() {
+        let pattern = two_blocks();
+        let rebuilt = EmbPattern::from_blocks(&pattern.blocks(), pattern.threads().to_vec());
+
+        assert_eq!(rebuilt.stitches(), pattern.stitches());
+        assert_eq!(rebuilt.threads(), pattern.threads());
+    }
+
+    #[test]
+    fn test_needle_sets_split_sewn_blocks() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.add_command(NEEDLE_SET, 0.0, 0.0);
+        pattern.add_command(NEEDLE_SET, 0.0, 0.0);
+        pattern.stitch_abs(10.0, 0.0);
+
+        let blocks = pattern.blocks();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].stitches.len(), 2);
+        assert_eq!(blocks[1].stitches[0].command, NEEDLE_SET);
+    }
+
+    #[test]
+    fn test_reordered_blocks_end_once() {
+        let pattern = two_blocks();
+        let mut blocks = pattern.blocks();
+        blocks.reverse();
+
+        let rebuilt = EmbPattern::from_blocks(&blocks, pattern.threads().to_vec());
+        let ends = rebuilt
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == END)
+            .count();
+        assert_eq!(ends, 1);
+        let last = rebuilt.stitches().last().unwrap();
+        assert_eq!((last.x, last.y), (100.0, 0.0));
+    }
+
+    #[test]
+    fn test_empty_pattern() {
+        assert!(EmbPattern::new().blocks().is_empty());
+        assert!(EmbPattern::from_blocks(&[], Vec::new())
+            .stitches()
+            .is_empty());
+    }
+}
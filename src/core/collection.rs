@@ -3,9 +3,26 @@
 //! Some embroidery formats (like HUS and VP3) support multiple patterns/designs
 //! within a single file. This module provides a collection structure to manage
 //! multiple patterns with named access.
+//!
+//! Collections are read from and written to ZIP archives of designs, the
+//! usual way design sets are distributed: [`EmbPatternCollection::read`]
+//! returns every design in an archive, or the single design of any other
+//! file, and [`EmbPatternCollection::write`] writes all designs into one
+//! archive. The single-design readers don't split files into several designs;
+//! VP3 files holding several design blocks are read as one pattern per block.
+//!
+//! Designs of a collection can be placed in a shared hoop: each has an offset
+//! from the hoop center, set with [`EmbPatternCollection::add_at`].
 
 use crate::core::pattern::EmbPattern;
-use std::collections::HashMap;
+use crate::formats::format::Format;
+use crate::formats::io::memory::write_to_bytes;
+use crate::formats::io::zip::{self, ZipArchive, ZipWriter};
+use crate::formats::registry::FormatRegistry;
+use crate::utils::error::{Result, ResultExt};
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
 
 /// A collection of named embroidery patterns
 ///
@@ -43,6 +60,8 @@ use std::collections::HashMap;
 pub struct EmbPatternCollection {
     /// Named patterns in the collection
     patterns: HashMap<String, EmbPattern>,
+    /// Offsets of the patterns placed away from the hoop center
+    offsets: HashMap<String, (f64, f64)>,
 }
 
 impl EmbPatternCollection {
@@ -59,6 +78,7 @@ impl EmbPatternCollection {
     pub fn new() -> Self {
         Self {
             patterns: HashMap::new(),
+            offsets: HashMap::new(),
         }
     }
 
@@ -75,6 +95,7 @@ impl EmbPatternCollection {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             patterns: HashMap::with_capacity(capacity),
+            offsets: HashMap::new(),
         }
     }
 
@@ -100,9 +121,50 @@ impl EmbPatternCollection {
     /// assert!(old.is_some()); // Replaced existing pattern
     /// ```
     pub fn add(&mut self, name: String, pattern: EmbPattern) -> Option<EmbPattern> {
+        self.offsets.remove(&name);
         self.patterns.insert(name, pattern)
     }
 
+    /// Add a pattern placed at an offset from the hoop center (0.1mm)
+    ///
+    /// The pattern keeps its own coordinates; the offset says where its
+    /// origin sits in a hoop shared with the other designs, as for the design
+    /// blocks of a VP3 file.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::core::collection::EmbPatternCollection;
+    /// use butabuti::core::pattern::EmbPattern;
+    ///
+    /// let mut collection = EmbPatternCollection::new();
+    /// collection.add_at("left".to_string(), EmbPattern::new(), (-400.0, 0.0));
+    /// collection.add("center".to_string(), EmbPattern::new());
+    ///
+    /// assert_eq!(collection.offset("left"), Some((-400.0, 0.0)));
+    /// assert_eq!(collection.offset("center"), Some((0.0, 0.0)));
+    /// assert_eq!(collection.offset("missing"), None);
+    /// ```
+    pub fn add_at(
+        &mut self,
+        name: String,
+        pattern: EmbPattern,
+        offset: (f64, f64),
+    ) -> Option<EmbPattern> {
+        self.offsets.insert(name.clone(), offset);
+        self.patterns.insert(name, pattern)
+    }
+
+    /// Offset of a pattern from the hoop center (0.1mm)
+    ///
+    /// Patterns added without an offset sit at the center. Returns `None`
+    /// when the collection has no pattern of that name.
+    pub fn offset(&self, name: &str) -> Option<(f64, f64)> {
+        self.patterns
+            .contains_key(name)
+            .then(|| self.offsets.get(name).copied().unwrap_or((0.0, 0.0)))
+    }
+
     /// Get a reference to a pattern by name
     ///
     /// # Example
@@ -161,6 +223,7 @@ impl EmbPatternCollection {
     /// assert_eq!(collection.len(), 0);
     /// ```
     pub fn remove(&mut self, name: &str) -> Option<EmbPattern> {
+        self.offsets.remove(name);
         self.patterns.remove(name)
     }
 
@@ -313,13 +376,14 @@ impl EmbPatternCollection {
     /// ```
     pub fn clear(&mut self) {
         self.patterns.clear();
+        self.offsets.clear();
     }
 
     /// Merge another collection into this one
     ///
-    /// Patterns from the other collection will be added to this one.
-    /// If there are name conflicts, patterns from the other collection
-    /// will replace those in this collection.
+    /// Patterns from the other collection will be added to this one, with
+    /// their offsets. If there are name conflicts, patterns from the other
+    /// collection will replace those in this collection.
     ///
     /// # Example
     ///
@@ -337,8 +401,182 @@ impl EmbPatternCollection {
     /// assert_eq!(collection1.len(), 2);
     /// ```
     pub fn merge(&mut self, other: Self) {
+        for name in other.patterns.keys() {
+            self.offsets.remove(name);
+        }
         self.patterns.extend(other.patterns);
+        self.offsets.extend(other.offsets);
+    }
+
+    /// Read every design in a file
+    ///
+    /// A ZIP archive yields one pattern per design, named by its path in the
+    /// archive. Entries that aren't stitch designs are skipped: files of
+    /// unknown format, SVG artwork (usually previews) and thread charts that
+    /// read without stitches. A VP3 file with several design blocks yields one
+    /// pattern per block, at its offset; any other file is read as a single
+    /// design named by its file name.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use butabuti::core::collection::EmbPatternCollection;
+    ///
+    /// let designs = EmbPatternCollection::read("alphabet.zip")?;
+    /// for (name, pattern) in designs.iter() {
+    ///     println!("{}: {} stitches", name, pattern.count_stitches());
+    /// }
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    #[cfg(feature = "fs")]
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+        Self::read_from(&mut file, path)
+    }
+
+    /// Read every design in a stream, using `path` for format detection and
+    /// naming as in [`read`](Self::read)
+    pub fn read_from<R: Read + Seek>(reader: &mut R, path: &Path) -> Result<Self> {
+        let start = reader.stream_position()?;
+        let mut magic = [0u8; 4];
+        let is_zip = reader.read_exact(&mut magic).is_ok() && zip::is_zip(&magic);
+        reader.seek(SeekFrom::Start(start))?;
+
+        let registry = FormatRegistry::new();
+        let mut collection = Self::new();
+        if !is_zip {
+            let (format, _) = registry.resolve_read_format(reader, Some(path))?;
+            reader.seek(SeekFrom::Start(start))?;
+            if format.eq_ignore_ascii_case(Format::VP3.name()) {
+                let blocks = crate::formats::io::readers::vp3::read_blocks(reader)?;
+                if blocks.len() > 1 {
+                    return Ok(blocks);
+                }
+                reader.seek(SeekFrom::Start(start))?;
+            }
+            let (pattern, _) = registry.read_pattern_detected(reader, Some(path))?;
+            let name = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |n| n.to_string_lossy().into_owned(),
+            );
+            collection.add(name, pattern);
+            return Ok(collection);
+        }
+
+        let mut archive = ZipArchive::new(reader)?;
+        for entry in archive.entries().to_vec() {
+            if entry.is_dir() {
+                continue;
+            }
+            let data = archive.read(&entry)?;
+            if archive_design_format(&registry, &data, &entry.name).is_none() {
+                continue;
+            }
+            let mut design = Cursor::new(data);
+            let entry_path = Path::new(&entry.name);
+            let (pattern, _) = registry
+                .read_pattern_detected(&mut design, Some(entry_path))
+                .with_context(format!("Reading {} from archive", entry.name))?;
+            if !pattern.stitches().is_empty() {
+                collection.add(entry.name, pattern);
+            }
+        }
+        Ok(collection)
+    }
+
+    /// Write all designs into a ZIP archive, each in `format`
+    ///
+    /// Designs are stored in name order as `<name>.<extension>`; a format
+    /// extension already on the name is replaced. Names that collide after
+    /// that get a numeric suffix. A path without a `.zip` extension must hold
+    /// exactly one design, written as a plain file.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use butabuti::prelude::*;
+    /// use butabuti::core::collection::EmbPatternCollection;
+    ///
+    /// let mut designs = EmbPatternCollection::read("alphabet.zip")?;
+    /// designs.write("alphabet-dst.zip", Format::DST)?;
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    #[cfg(feature = "fs")]
+    pub fn write<P: AsRef<Path>>(&self, path: P, format: Format) -> Result<()> {
+        let path = path.as_ref();
+        let is_zip = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+        if is_zip {
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            return self.write_zip(file, format);
+        }
+
+        let mut designs = self.patterns.values();
+        match (designs.next(), designs.next()) {
+            (Some(pattern), None) => {
+                let data = write_to_bytes(pattern, format.name())?;
+                std::fs::write(path, data)?;
+                Ok(())
+            }
+            _ => Err(crate::utils::error::Error::Unsupported(format!(
+                "{} holds one design, the collection has {}; write a .zip archive instead",
+                format.name(),
+                self.len()
+            ))),
+        }
     }
+
+    /// Write all designs into a ZIP archive on a stream, as in
+    /// [`write`](Self::write)
+    pub fn write_zip<W: Write>(&self, writer: W, format: Format) -> Result<()> {
+        let mut names: Vec<&String> = self.patterns.keys().collect();
+        names.sort();
+
+        let extension = format.extensions().first().copied().unwrap_or("dat");
+        let mut used = HashSet::new();
+        let mut archive = ZipWriter::new(writer);
+        for name in names {
+            let data = write_to_bytes(&self.patterns[name], format.name())
+                .with_context(format!("Writing {}", name))?;
+            let entry = entry_name(name, extension, &mut used);
+            archive.add(&entry, &data)?;
+        }
+        archive.finish()?;
+        Ok(())
+    }
+}
+
+/// Format an archive entry is read as, or `None` if it isn't a design
+///
+/// Entries of unknown format and SVG artwork, usually previews, aren't
+/// designs.
+pub(crate) fn archive_design_format(
+    registry: &FormatRegistry,
+    data: &[u8],
+    name: &str,
+) -> Option<&'static str> {
+    let (first, _) = registry
+        .resolve_read_format(&mut Cursor::new(data), Some(Path::new(name)))
+        .ok()?;
+    (!first.eq_ignore_ascii_case(Format::SVG.name())).then_some(first)
+}
+
+/// Archive entry name for a design, unique among `used`
+pub(crate) fn entry_name(name: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let path = Path::new(name);
+    let stem = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if Format::from_extension(ext).is_some() => &name[..name.len() - ext.len() - 1],
+        _ => name,
+    };
+    let mut entry = format!("{}.{}", stem, extension);
+    let mut suffix = 2;
+    while !used.insert(entry.clone()) {
+        entry = format!("{}_{}.{}", stem, suffix, extension);
+        suffix += 1;
+    }
+    entry
 }
 
 #[cfg(test)]
@@ -380,6 +618,43 @@ mod tests {
         assert_eq!(collection.get("design1").unwrap().count_stitches(), 2);
     }
 
+    #[test]
+    fn test_offsets() {
+        let mut collection = EmbPatternCollection::new();
+        collection.add_at("left".to_string(), EmbPattern::new(), (-400.0, 0.0));
+        assert_eq!(collection.offset("left"), Some((-400.0, 0.0)));
+
+        // Adding again without an offset moves the design to the center
+        collection.add("left".to_string(), EmbPattern::new());
+        assert_eq!(collection.offset("left"), Some((0.0, 0.0)));
+
+        let mut other = EmbPatternCollection::new();
+        other.add_at("left".to_string(), EmbPattern::new(), (0.0, 250.0));
+        collection.merge(other);
+        assert_eq!(collection.offset("left"), Some((0.0, 250.0)));
+
+        collection.remove("left");
+        assert_eq!(collection.offset("left"), None);
+    }
+
+    #[test]
+    fn test_read_vp3_blocks() {
+        let mut block = EmbPattern::new();
+        block.stitch_abs(0.0, 0.0);
+        block.stitch_abs(40.0, 40.0);
+        block.end();
+        let mut blocks = EmbPatternCollection::new();
+        blocks.add_at("a".to_string(), block.clone(), (-300.0, 0.0));
+        blocks.add_at("b".to_string(), block, (300.0, 0.0));
+
+        let mut data = Vec::new();
+        crate::formats::io::writers::vp3::write_blocks(&mut data, &blocks).unwrap();
+        let read =
+            EmbPatternCollection::read_from(&mut Cursor::new(data), Path::new("set.vp3")).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read.offset("b"), Some((300.0, 0.0)));
+    }
+
     #[test]
     fn test_get_pattern() {
         let mut collection = EmbPatternCollection::new();
@@ -529,4 +804,91 @@ mod tests {
         let collection = EmbPatternCollection::with_capacity(10);
         assert!(collection.is_empty());
     }
+
+    fn design(length: f64) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(crate::core::thread::EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(length, 0.0);
+        pattern.stitch_abs(length, length);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_zip_round_trip_skips_non_designs() {
+        let mut collection = EmbPatternCollection::new();
+        collection.add("set/rose.pes".to_string(), design(50.0));
+        collection.add("tulip".to_string(), design(80.0));
+
+        let mut bytes = Vec::new();
+        collection.write_zip(&mut bytes, Format::DST).unwrap();
+
+        // Add entries a marketplace archive typically carries
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut writer = ZipWriter::new(Vec::new());
+        for entry in archive.entries().to_vec() {
+            writer
+                .add(&entry.name, &archive.read(&entry).unwrap())
+                .unwrap();
+        }
+        writer.add("readme.pdf", b"%PDF-1.4").unwrap();
+        writer
+            .add(
+                "preview.svg",
+                br#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0 L10 10"/></svg>"#,
+            )
+            .unwrap();
+        writer.add("colors.col", b"1\r\n0,255,0,0\r\n").unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let read =
+            EmbPatternCollection::read_from(&mut Cursor::new(bytes), Path::new("set.zip")).unwrap();
+        let mut names: Vec<&String> = read.names().collect();
+        names.sort();
+        assert_eq!(names, vec!["set/rose.dst", "tulip.dst"]);
+        assert_eq!(read.get("tulip.dst").unwrap().count_stitches(), 3);
+    }
+
+    #[test]
+    fn test_read_single_design() {
+        let bytes = write_to_bytes(&design(30.0), "pes").unwrap();
+        let read = EmbPatternCollection::read_from(&mut Cursor::new(bytes), Path::new("dir/a.pes"))
+            .unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read.get("a.pes").unwrap().count_stitches(), 3);
+    }
+
+    #[test]
+    fn test_entry_names_are_unique() {
+        let mut used = HashSet::new();
+        assert_eq!(entry_name("a.pes", "dst", &mut used), "a.dst");
+        assert_eq!(entry_name("a.jef", "dst", &mut used), "a_2.dst");
+        assert_eq!(entry_name("a", "dst", &mut used), "a_3.dst");
+        assert_eq!(entry_name("v1.2", "dst", &mut used), "v1.2.dst");
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_write_plain_file_needs_one_design() {
+        let dir = std::env::temp_dir().join(format!("butabuti_collection_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut collection = EmbPatternCollection::new();
+        collection.add("one".to_string(), design(40.0));
+        collection.write(dir.join("one.dst"), Format::DST).unwrap();
+        let read = EmbPatternCollection::read(dir.join("one.dst")).unwrap();
+        assert_eq!(read.get("one.dst").unwrap().count_stitches(), 3);
+
+        collection.add("two".to_string(), design(60.0));
+        assert!(collection.write(dir.join("two.dst"), Format::DST).is_err());
+        collection.write(dir.join("both.zip"), Format::JEF).unwrap();
+        assert_eq!(
+            EmbPatternCollection::read(dir.join("both.zip"))
+                .unwrap()
+                .len(),
+            2
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
@@ -0,0 +1,361 @@
+//! Color sorting
+//!
+//! Designs often return to a thread they already used, for example to add
+//! outlines after fills. When the blocks sewn in between don't touch such a
+//! block, it can be sewn together with the earlier block of the same thread,
+//! saving a color change.
+//!
+//! A block is only moved ahead of blocks whose stitch bounding boxes it doesn't
+//! overlap, so stitch layering is never changed. Threads are compared by color.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! // Red square, blue square next to it, red square below
+//! let mut pattern = EmbPattern::new();
+//! for (color, (x, y)) in [(0xFF0000, (0.0, 0.0)), (0x0000FF, (200.0, 0.0)), (0xFF0000, (0.0, 200.0))] {
+//!     if !pattern.threads().is_empty() {
+//!         pattern.trim();
+//!         pattern.color_change(0.0, 0.0);
+//!     }
+//!     pattern.add_thread(EmbThread::new(color));
+//!     pattern.jump_abs(x, y);
+//!     pattern.stitch_abs(x + 100.0, y);
+//!     pattern.stitch_abs(x + 100.0, y + 100.0);
+//! }
+//! pattern.end();
+//!
+//! let report = pattern.optimize_color_order();
+//! assert_eq!(report.color_changes_saved(), 1);
+//! assert_eq!(pattern.threads().len(), 2);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+
+/// Outcome of [`EmbPattern::optimize_color_order`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorSortReport {
+    /// Color blocks sewn together with an earlier block of the same thread
+    pub blocks_merged: usize,
+    /// Color changes before sorting
+    pub color_changes_before: usize,
+    /// Color changes after sorting
+    pub color_changes_after: usize,
+    /// Trims before sorting
+    pub trims_before: usize,
+    /// Trims after sorting
+    pub trims_after: usize,
+}
+
+impl ColorSortReport {
+    /// Number of color changes removed
+    pub fn color_changes_saved(&self) -> usize {
+        self.color_changes_before
+            .saturating_sub(self.color_changes_after)
+    }
+
+    /// Number of trims removed (negative if travel between merged blocks added trims)
+    pub fn trims_saved(&self) -> i64 {
+        self.trims_before as i64 - self.trims_after as i64
+    }
+}
+
+/// One color block while sorting
+struct Block {
+    /// Color change that starts the block (none for the first block)
+    change: Option<Stitch>,
+    stitches: Vec<Stitch>,
+    thread: Option<EmbThread>,
+    /// Original block indices sewn in this block
+    sources: Vec<usize>,
+    /// Bounding box of the needle-down stitches
+    bounds: Option<(f64, f64, f64, f64)>,
+}
+
+impl Block {
+    fn new(change: Option<Stitch>, thread: Option<EmbThread>, index: usize) -> Self {
+        Self {
+            change,
+            stitches: Vec::new(),
+            thread,
+            sources: vec![index],
+            bounds: None,
+        }
+    }
+
+    fn push(&mut self, stitch: Stitch) {
+        if stitch.command & COMMAND_MASK == STITCH {
+            self.bounds = union(self.bounds, Some((stitch.x, stitch.y, stitch.x, stitch.y)));
+        }
+        self.stitches.push(stitch);
+    }
+
+    /// Sew another block's stitches at the end of this one, traveling between them
+    fn absorb(&mut self, other: Block) {
+        if let Some(last) = self.stitches.last().copied() {
+            if last.command & COMMAND_MASK != TRIM {
+                self.stitches.push(Stitch::new(last.x, last.y, TRIM));
+            }
+        }
+        if let Some(first) = other.stitches.first() {
+            if first.command & COMMAND_MASK != JUMP {
+                self.stitches.push(Stitch::new(first.x, first.y, JUMP));
+            }
+        }
+        self.stitches.extend(other.stitches);
+        self.sources.extend(other.sources);
+        self.bounds = union(self.bounds, other.bounds);
+    }
+}
+
+fn union(
+    a: Option<(f64, f64, f64, f64)>,
+    b: Option<(f64, f64, f64, f64)>,
+) -> Option<(f64, f64, f64, f64)> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some((a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Whether two blocks may cover each other; touching boxes count as overlapping
+fn overlaps(a: Option<(f64, f64, f64, f64)>, b: Option<(f64, f64, f64, f64)>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.0 <= b.2 && b.0 <= a.2 && a.1 <= b.3 && b.1 <= a.3,
+        _ => false,
+    }
+}
+
+fn count_trims(stitches: &[Stitch]) -> usize {
+    stitches
+        .iter()
+        .filter(|s| s.command & COMMAND_MASK == TRIM)
+        .count()
+}
+
+impl EmbPattern {
+    /// Reorder color blocks to save color changes
+    ///
+    /// Each block is sewn together with the latest earlier block of the same
+    /// thread when none of the blocks in between overlap it; otherwise it stays
+    /// where it is. Travel between merged blocks uses a trim and a jump.
+    /// Color group thread indices follow their threads.
+    ///
+    /// Patterns using `NEEDLE_SET` instead of color changes are left unchanged.
+    pub fn optimize_color_order(&mut self) -> ColorSortReport {
+        let color_changes_before = self.count_color_changes();
+        let trims_before = count_trims(self.stitches());
+        let unchanged = ColorSortReport {
+            blocks_merged: 0,
+            color_changes_before,
+            color_changes_after: color_changes_before,
+            trims_before,
+            trims_after: trims_before,
+        };
+        if self
+            .stitches()
+            .iter()
+            .any(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+        {
+            return unchanged;
+        }
+
+        let mut stitches = self.stitches().to_vec();
+        let mut ending = Vec::new();
+        while stitches
+            .last()
+            .is_some_and(|s| s.command & COMMAND_MASK == END)
+        {
+            ending.insert(0, stitches.pop().unwrap());
+        }
+
+        let threads = self.threads();
+        let mut blocks = vec![Block::new(None, threads.first().cloned(), 0)];
+        for stitch in stitches {
+            if stitch.command & COMMAND_MASK == COLOR_CHANGE {
+                let index = blocks.len();
+                blocks.push(Block::new(Some(stitch), threads.get(index).cloned(), index));
+            } else {
+                blocks.last_mut().unwrap().push(stitch);
+            }
+        }
+        let block_count = blocks.len();
+
+        let mut sorted: Vec<Block> = Vec::with_capacity(block_count);
+        let mut blocks_merged = 0;
+        for block in blocks {
+            let target = block.thread.as_ref().and_then(|thread| {
+                sorted
+                    .iter()
+                    .rposition(|b| b.thread.as_ref() == Some(thread))
+            });
+            match target {
+                Some(target)
+                    if sorted[target + 1..]
+                        .iter()
+                        .all(|b| !overlaps(b.bounds, block.bounds)) =>
+                {
+                    sorted[target].absorb(block);
+                    blocks_merged += 1;
+                }
+                _ => sorted.push(block),
+            }
+        }
+        if blocks_merged == 0 {
+            return unchanged;
+        }
+
+        // Old thread index -> new thread index; threads beyond the last block keep their order
+        let mut thread_map: Vec<usize> = vec![0; threads.len().max(block_count)];
+        for (new_index, block) in sorted.iter().enumerate() {
+            for &source in &block.sources {
+                thread_map[source] = new_index;
+            }
+        }
+        for (offset, slot) in thread_map.iter_mut().skip(block_count).enumerate() {
+            *slot = sorted.len() + offset;
+        }
+
+        let mut new_threads: Vec<EmbThread> =
+            sorted.iter().filter_map(|b| b.thread.clone()).collect();
+        new_threads.extend(threads.iter().skip(block_count).cloned());
+
+        let mut new_stitches = Vec::new();
+        for block in sorted {
+            if let Some(change) = block.change {
+                let (x, y) = new_stitches
+                    .last()
+                    .map(|s: &Stitch| (s.x, s.y))
+                    .unwrap_or((change.x, change.y));
+                new_stitches.push(Stitch::new(x, y, change.command));
+            }
+            new_stitches.extend(block.stitches);
+        }
+        new_stitches.extend(ending);
+
+        self.replace_stitches(new_stitches, new_threads);
+        if let Some(grouping) = self.color_grouping_mut() {
+            let names: Vec<String> = grouping.group_names().cloned().collect();
+            for name in names {
+                if let Some(group) = grouping.get_group_mut(&name) {
+                    group.thread_indices = group
+                        .thread_indices
+                        .iter()
+                        .map(|&i| thread_map.get(i).copied().unwrap_or(i))
+                        .collect();
+                }
+            }
+        }
+
+        ColorSortReport {
+            blocks_merged,
+            color_changes_before,
+            color_changes_after: self.count_color_changes(),
+            trims_before,
+            trims_after: count_trims(self.stitches()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::color_group::ColorGroup;
+
+    /// Squares of 100 x 100 at the given corners, one color block each
+    fn blocks(squares: &[(u32, f64, f64)]) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for &(color, x, y) in squares {
+            if !pattern.threads().is_empty() {
+                pattern.trim();
+                pattern.color_change(0.0, 0.0);
+            }
+            pattern.add_thread(EmbThread::new(color));
+            pattern.jump_abs(x, y);
+            pattern.stitch_abs(x + 100.0, y);
+            pattern.stitch_abs(x + 100.0, y + 100.0);
+            pattern.stitch_abs(x, y + 100.0);
+            pattern.stitch_abs(x, y);
+        }
+        pattern.end();
+        pattern
+    }
+
+    fn colors(pattern: &EmbPattern) -> Vec<u32> {
+        pattern.threads().iter().map(|t| t.color).collect()
+    }
+
+    #[test]
+    fn test_merges_separate_blocks() {
+        let mut pattern = blocks(&[
+            (0xFF0000, 0.0, 0.0),
+            (0x00FF00, 200.0, 0.0),
+            (0xFF0000, 0.0, 200.0),
+            (0x00FF00, 200.0, 200.0),
+        ]);
+        let stitch_count = pattern.count_stitches();
+
+        let report = pattern.optimize_color_order();
+        assert_eq!(report.blocks_merged, 2);
+        assert_eq!(report.color_changes_before, 3);
+        assert_eq!(report.color_changes_after, 1);
+        assert_eq!(report.color_changes_saved(), 2);
+        assert_eq!(report.trims_saved(), 0);
+        assert_eq!(colors(&pattern), vec![0xFF0000, 0x00FF00]);
+        assert_eq!(pattern.count_stitches(), stitch_count);
+        assert_eq!(pattern.stitches().last().unwrap().command, END);
+
+        // The second red square is reached with a jump, not sewn to
+        let first_red: Vec<u32> = pattern
+            .stitches()
+            .iter()
+            .take_while(|s| s.command != COLOR_CHANGE)
+            .map(|s| s.command)
+            .collect();
+        assert_eq!(
+            first_red,
+            vec![
+                JUMP, STITCH, STITCH, STITCH, STITCH, TRIM, JUMP, STITCH, STITCH, STITCH, STITCH,
+                TRIM
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keeps_layering() {
+        // The green block covers the first red one, so the outline stays on top
+        let mut pattern = blocks(&[
+            (0xFF0000, 0.0, 0.0),
+            (0x00FF00, 50.0, 50.0),
+            (0xFF0000, 0.0, 50.0),
+        ]);
+        let original = pattern.stitches().to_vec();
+
+        let report = pattern.optimize_color_order();
+        assert_eq!(report.blocks_merged, 0);
+        assert_eq!(report.color_changes_saved(), 0);
+        assert_eq!(pattern.stitches(), original.as_slice());
+        assert_eq!(colors(&pattern), vec![0xFF0000, 0x00FF00, 0xFF0000]);
+    }
+
+    #[test]
+    fn test_color_groups_follow_threads() {
+        let mut pattern = blocks(&[
+            (0xFF0000, 0.0, 0.0),
+            (0x00FF00, 200.0, 0.0),
+            (0xFF0000, 0.0, 200.0),
+            (0x0000FF, 200.0, 200.0),
+        ]);
+        pattern.add_color_group(ColorGroup::with_threads("Blue", vec![3]));
+
+        pattern.optimize_color_order();
+        assert_eq!(colors(&pattern), vec![0xFF0000, 0x00FF00, 0x0000FF]);
+        let group = pattern.get_color_group("Blue").unwrap();
+        assert!(group.thread_indices.contains(&2));
+        assert_eq!(group.thread_indices.len(), 1);
+    }
+}
@@ -0,0 +1,364 @@
+//! Compact stitch storage
+//!
+//! A [`Stitch`] takes 24 bytes: two `f64` coordinates and a `u32` command.
+//! [`CompactPattern`] stores the same records as separate fixed-point `i32`
+//! coordinate arrays and one command byte per record, about 9 bytes per
+//! stitch, for workflows that read a large design, analyze it and discard it.
+//! Commands carrying thread, needle or order bits are kept in full in a side
+//! table, so every command survives the round trip.
+//!
+//! Coordinates are rounded to [`COMPACT_RESOLUTION`] (0.001 mm). Thread color
+//! groups are not kept.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.5, 25.0);
+//! pattern.end();
+//!
+//! let compact = pattern.to_compact()?;
+//! drop(pattern);
+//! assert_eq!(compact.count_stitches(), 2);
+//! assert_eq!(compact.bounds(), (0.0, 0.0, 100.5, 25.0));
+//!
+//! let restored = compact.to_pattern();
+//! assert_eq!(restored.stitches()[1].x, 100.5);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, Result};
+use std::collections::HashMap;
+
+/// Smallest coordinate step of a [`CompactPattern`], in 0.1mm units
+pub const COMPACT_RESOLUTION: f64 = 0.01;
+
+/// Fixed-point steps per 0.1mm unit
+const SCALE: f64 = 1.0 / COMPACT_RESOLUTION;
+
+/// Stitch records in fixed-point structure-of-arrays form
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompactPattern {
+    /// X coordinates in [`COMPACT_RESOLUTION`] steps
+    xs: Vec<i32>,
+    /// Y coordinates in [`COMPACT_RESOLUTION`] steps
+    ys: Vec<i32>,
+    /// Command byte (`command & COMMAND_MASK`) of each record
+    commands: Vec<u8>,
+    /// Full commands of records with bits outside `COMMAND_MASK`, by index
+    flagged: Vec<(usize, u32)>,
+    /// Threads used in the pattern
+    threads: Vec<EmbThread>,
+    /// Pattern metadata
+    metadata: HashMap<String, String>,
+}
+
+impl CompactPattern {
+    /// Create an empty compact pattern
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty compact pattern with room for `capacity` records
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            xs: Vec::with_capacity(capacity),
+            ys: Vec::with_capacity(capacity),
+            commands: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Convert a pattern's stitches, threads and metadata
+    ///
+    /// Fails if a coordinate is not finite or lies beyond the `i32` range of
+    /// [`COMPACT_RESOLUTION`] steps (about ±21 m).
+    pub fn from_pattern(pattern: &EmbPattern) -> Result<Self> {
+        let mut compact = Self::with_capacity(pattern.stitches().len());
+        for &stitch in pattern.stitches() {
+            compact.push(stitch)?;
+        }
+        compact.threads = pattern.threads().to_vec();
+        compact.metadata = pattern
+            .metadata()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Ok(compact)
+    }
+
+    /// Convert back to a pattern with `f64` coordinates
+    pub fn to_pattern(&self) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.replace_stitches(self.iter().collect(), self.threads.clone());
+        for (key, value) in &self.metadata {
+            pattern.set_metadata(key.clone(), value.clone());
+        }
+        pattern
+    }
+
+    /// Append a record
+    ///
+    /// Fails if a coordinate is not finite or out of range.
+    pub fn push(&mut self, stitch: Stitch) -> Result<()> {
+        let index = self.commands.len();
+        let x = to_fixed(stitch.x, index)?;
+        let y = to_fixed(stitch.y, index)?;
+        self.xs.push(x);
+        self.ys.push(y);
+        self.commands.push((stitch.command & COMMAND_MASK) as u8);
+        if stitch.command & !COMMAND_MASK != 0 {
+            self.flagged.push((index, stitch.command));
+        }
+        Ok(())
+    }
+
+    /// Add a thread
+    pub fn add_thread(&mut self, thread: EmbThread) {
+        self.threads.push(thread);
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Whether there are no records
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// The record at `index`
+    pub fn get(&self, index: usize) -> Option<Stitch> {
+        let command = self.command(index)?;
+        Some(Stitch::new(
+            from_fixed(self.xs[index]),
+            from_fixed(self.ys[index]),
+            command,
+        ))
+    }
+
+    /// The full command of the record at `index`
+    pub fn command(&self, index: usize) -> Option<u32> {
+        let byte = *self.commands.get(index)?;
+        Some(
+            self.flagged
+                .binary_search_by_key(&index, |&(i, _)| i)
+                .map_or(byte as u32, |i| self.flagged[i].1),
+        )
+    }
+
+    /// Records in order, decoded to [`Stitch`]
+    pub fn iter(&self) -> impl Iterator<Item = Stitch> + '_ {
+        let mut flagged = self.flagged.iter().peekable();
+        (0..self.len()).map(move |index| {
+            let command = match flagged.peek() {
+                Some(&&(i, command)) if i == index => {
+                    flagged.next();
+                    command
+                }
+                _ => self.commands[index] as u32,
+            };
+            Stitch::new(
+                from_fixed(self.xs[index]),
+                from_fixed(self.ys[index]),
+                command,
+            )
+        })
+    }
+
+    /// Threads used in the pattern
+    pub fn threads(&self) -> &[EmbThread] {
+        &self.threads
+    }
+
+    /// Get a metadata value
+    pub fn get_metadata(&self, key: &str) -> Option<&String> {
+        self.metadata.get(key)
+    }
+
+    /// All metadata
+    pub fn metadata(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.metadata.iter()
+    }
+
+    /// Number of STITCH records, counted like [`EmbPattern::count_stitches`]
+    pub fn count_stitches(&self) -> usize {
+        self.count_command(STITCH)
+    }
+
+    /// Number of COLOR_CHANGE records
+    pub fn count_color_changes(&self) -> usize {
+        self.count_command(COLOR_CHANGE)
+    }
+
+    /// Bounds of all records as `(min_x, min_y, max_x, max_y)`
+    ///
+    /// Returns zeros for an empty pattern, like [`EmbPattern::bounds`].
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        let range = |values: &[i32]| {
+            let min = values.iter().copied().min().unwrap_or(0);
+            let max = values.iter().copied().max().unwrap_or(0);
+            (from_fixed(min), from_fixed(max))
+        };
+        let (min_x, max_x) = range(&self.xs);
+        let (min_y, max_y) = range(&self.ys);
+        (min_x, min_y, max_x, max_y)
+    }
+
+    /// Heap bytes used by the stitch records
+    pub fn stitch_bytes(&self) -> usize {
+        self.xs.capacity() * size_of::<i32>()
+            + self.ys.capacity() * size_of::<i32>()
+            + self.commands.capacity()
+            + self.flagged.capacity() * size_of::<(usize, u32)>()
+    }
+
+    /// Records whose full command equals `command`
+    fn count_command(&self, command: u32) -> usize {
+        let flagged = self.flagged.iter().filter(|&&(_, c)| c == command).count();
+        if command & !COMMAND_MASK != 0 {
+            return flagged;
+        }
+        // Flagged records have this command byte but a different full command
+        let byte_matches = self
+            .commands
+            .iter()
+            .filter(|&&c| c as u32 == command)
+            .count();
+        let flagged_bytes = self
+            .flagged
+            .iter()
+            .filter(|&&(_, c)| c & COMMAND_MASK == command)
+            .count();
+        byte_matches - flagged_bytes + flagged
+    }
+}
+
+impl EmbPattern {
+    /// Convert to [`CompactPattern`] storage
+    ///
+    /// See [`CompactPattern::from_pattern`].
+    pub fn to_compact(&self) -> Result<CompactPattern> {
+        CompactPattern::from_pattern(self)
+    }
+}
+
+impl TryFrom<&EmbPattern> for CompactPattern {
+    type Error = Error;
+
+    fn try_from(pattern: &EmbPattern) -> Result<Self> {
+        Self::from_pattern(pattern)
+    }
+}
+
+impl From<&CompactPattern> for EmbPattern {
+    fn from(compact: &CompactPattern) -> Self {
+        compact.to_pattern()
+    }
+}
+
+/// Coordinate in fixed-point steps
+fn to_fixed(value: f64, index: usize) -> Result<i32> {
+    let scaled = (value * SCALE).round();
+    if !scaled.is_finite() || scaled < i32::MIN as f64 || scaled > i32::MAX as f64 {
+        return Err(Error::invalid_pattern(format!(
+            "Stitch {} coordinate {} is out of compact storage range",
+            index, value
+        ))
+        .at_stitch(index));
+    }
+    Ok(scaled as i32)
+}
+
+/// Coordinate in 0.1mm units
+fn from_fixed(value: i32) -> f64 {
+    value as f64 / SCALE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        pattern.set_metadata("name", "sample");
+        pattern.stitch_abs(-12.34, 5.0);
+        pattern.stitch_abs(100.0, -50.25);
+        pattern.trim();
+        // Thread bits outside COMMAND_MASK
+        pattern.add_command(COLOR_CHANGE | 0x0100, 0.0, 0.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(30.0, 30.0);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let pattern = sample();
+        let compact = pattern.to_compact().unwrap();
+
+        assert_eq!(compact.len(), pattern.stitches().len());
+        assert_eq!(compact.to_pattern().stitches(), pattern.stitches());
+        assert_eq!(compact.iter().collect::<Vec<_>>(), pattern.stitches());
+        assert_eq!(compact.get(3), Some(pattern.stitches()[3]));
+        assert_eq!(compact.get(99), None);
+        assert_eq!(compact.threads(), pattern.threads());
+        assert_eq!(
+            compact.get_metadata("name").map(String::as_str),
+            Some("sample")
+        );
+    }
+
+    #[test]
+    fn test_analysis_matches_pattern() {
+        let pattern = sample();
+        let compact = CompactPattern::try_from(&pattern).unwrap();
+
+        assert_eq!(compact.count_stitches(), pattern.count_stitches());
+        assert_eq!(compact.count_color_changes(), pattern.count_color_changes());
+        assert_eq!(compact.bounds(), pattern.bounds());
+        assert_eq!(CompactPattern::new().bounds(), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_rounds_to_resolution() {
+        let mut compact = CompactPattern::new();
+        compact.push(Stitch::new(1.23456, -0.004, STITCH)).unwrap();
+        let stitch = compact.get(0).unwrap();
+        assert!((stitch.x - 1.23).abs() < 1e-9);
+        assert_eq!(stitch.y, 0.0);
+    }
+
+    #[test]
+    fn test_rejects_unrepresentable_coordinates() {
+        let mut compact = CompactPattern::new();
+        compact.push(Stitch::new(0.0, 0.0, STITCH)).unwrap();
+        let err = compact
+            .push(Stitch::new(f64::NAN, 0.0, STITCH))
+            .unwrap_err();
+        assert_eq!(err.location().stitch_index, Some(1));
+        assert!(compact.push(Stitch::new(0.0, 1e12, STITCH)).is_err());
+        assert_eq!(compact.len(), 1);
+    }
+
+    #[test]
+    fn test_smaller_than_stitch_vec() {
+        let mut pattern = EmbPattern::new();
+        for i in 0..10_000 {
+            pattern.stitch_abs(i as f64, (i % 7) as f64);
+        }
+        let compact = pattern.to_compact().unwrap();
+        let full = std::mem::size_of_val(pattern.stitches());
+        assert!(compact.stitch_bytes() * 5 / 2 < full);
+    }
+}
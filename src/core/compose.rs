@@ -0,0 +1,224 @@
+//! Pattern composition
+//!
+//! [`EmbPattern::append_pattern`] places another pattern into a design, moved
+//! by a transform, so several motifs can share one hooping. The motif is
+//! reached by a trim and a jump, and its threads follow the design's.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::core::compose::ColorStrategy;
+//! use butabuti::prelude::*;
+//!
+//! let mut motif = EmbPattern::new();
+//! motif.add_thread(EmbThread::new(0xFF0000));
+//! motif.stitch_abs(0.0, 0.0);
+//! motif.stitch_abs(100.0, 0.0);
+//! motif.end();
+//!
+//! // The motif, then a copy turned a quarter turn and moved 30mm right
+//! let mut design = motif.clone();
+//! let mut placement = EmbMatrix::new();
+//! placement.post_translate(300.0, 0.0);
+//! placement.post_rotate(90.0, 0.0, 0.0);
+//! let bounds = design.append_pattern(&motif, &placement, ColorStrategy::MergeIdentical);
+//!
+//! assert!((bounds.max_y - 100.0).abs() < 1e-9);
+//! assert_eq!(design.threads().len(), 1);
+//! assert_eq!(design.count_color_changes(), 0);
+//! assert_eq!(design.count_stitches(), 4);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::matrix::EmbMatrix;
+use crate::core::pattern::{Bounds, EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+
+/// How the threads of an appended pattern join the design's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorStrategy {
+    /// Keep the appended pattern's color blocks separate, starting it with a
+    /// color change
+    #[default]
+    Separate,
+    /// Sew blocks of identical threads together
+    ///
+    /// The appended pattern continues without a color change when it starts
+    /// with the design's current thread, and color blocks are then merged with
+    /// [`EmbPattern::optimize_color_order`] where layering allows.
+    MergeIdentical,
+}
+
+impl EmbPattern {
+    /// Append another pattern, moved by `transform`
+    ///
+    /// END records are dropped and a single END is written last. When the
+    /// design already has stitches, it is trimmed and the needle jumps to the
+    /// start of the appended pattern. Its threads are added after the design's;
+    /// a pattern without threads is sewn in the design's current color.
+    ///
+    /// Returns the bounds of the placed pattern, empty if it has no stitches.
+    pub fn append_pattern(
+        &mut self,
+        other: &EmbPattern,
+        transform: &EmbMatrix,
+        colors: ColorStrategy,
+    ) -> Bounds {
+        let records: Vec<Stitch> = other
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK != END)
+            .map(|s| {
+                let (x, y) = transform.transform_point(s.x, s.y);
+                Stitch::new(x, y, s.command)
+            })
+            .collect();
+        let Some(first) = records.first().copied() else {
+            return Bounds::default();
+        };
+        let bounds =
+            records
+                .iter()
+                .skip(1)
+                .fold(Bounds::new(first.x, first.y, first.x, first.y), |b, s| {
+                    Bounds::new(
+                        b.min_x.min(s.x),
+                        b.min_y.min(s.y),
+                        b.max_x.max(s.x),
+                        b.max_y.max(s.y),
+                    )
+                });
+
+        self.strip_trailing_end();
+        let mut threads = other.threads();
+        if let Some(last) = self.stitches().last().copied() {
+            if last.command & COMMAND_MASK != TRIM {
+                self.add_command(TRIM, last.x, last.y);
+            }
+            let current = self.threads().get(self.count_color_changes());
+            let continues = colors == ColorStrategy::MergeIdentical
+                && current.is_some_and(|thread| Some(thread) == threads.first());
+            if continues {
+                threads = &threads[1..];
+            } else if !threads.is_empty() {
+                // Blocks sewn so far keep their colors
+                while self.threads().len() <= self.count_color_changes() {
+                    self.add_thread(EmbThread::new(0x000000));
+                }
+                self.add_command(COLOR_CHANGE, last.x, last.y);
+            }
+            self.add_stitch_absolute(JUMP, first.x, first.y);
+        }
+        for thread in threads {
+            self.add_thread(thread.clone());
+        }
+
+        for record in &records {
+            self.add_stitch_absolute(record.command, record.x, record.y);
+        }
+        let last = records[records.len() - 1];
+        self.add_command(END, last.x, last.y);
+
+        if colors == ColorStrategy::MergeIdentical {
+            self.optimize_color_order();
+        }
+        bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn motif(color: u32) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(color));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 0.0);
+        pattern.stitch_abs(50.0, 50.0);
+        pattern.end();
+        pattern
+    }
+
+    fn offset(dx: f64, dy: f64) -> EmbMatrix {
+        let mut matrix = EmbMatrix::new();
+        matrix.post_translate(dx, dy);
+        matrix
+    }
+
+    #[test]
+    fn test_append_separate_blocks() {
+        let mut design = motif(0xFF0000);
+        let bounds = design.append_pattern(
+            &motif(0xFF0000),
+            &offset(200.0, 0.0),
+            ColorStrategy::Separate,
+        );
+        assert_eq!(bounds, Bounds::new(200.0, 0.0, 250.0, 50.0));
+        assert_eq!(design.threads().len(), 2);
+
+        let commands: Vec<(u32, f64, f64)> = design
+            .stitches()
+            .iter()
+            .map(|s| (s.command, s.x, s.y))
+            .collect();
+        assert_eq!(
+            &commands[2..],
+            &[
+                (STITCH, 50.0, 50.0),
+                (TRIM, 50.0, 50.0),
+                (COLOR_CHANGE, 50.0, 50.0),
+                (JUMP, 200.0, 0.0),
+                (STITCH, 200.0, 0.0),
+                (STITCH, 250.0, 0.0),
+                (STITCH, 250.0, 50.0),
+                (END, 250.0, 50.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_append_merges_identical_threads() {
+        // Red, blue, then another red motif away from both
+        let mut design = motif(0xFF0000);
+        design.append_pattern(
+            &motif(0x0000FF),
+            &offset(0.0, 200.0),
+            ColorStrategy::MergeIdentical,
+        );
+        assert_eq!(design.threads().len(), 2);
+        design.append_pattern(
+            &motif(0xFF0000),
+            &offset(200.0, 0.0),
+            ColorStrategy::MergeIdentical,
+        );
+
+        let colors: Vec<u32> = design.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0xFF0000, 0x0000FF]);
+        assert_eq!(design.count_color_changes(), 1);
+        assert_eq!(design.count_stitches(), 9);
+    }
+
+    #[test]
+    fn test_append_edge_cases() {
+        // Into an empty design: no trim or jump, threads copied
+        let mut design = EmbPattern::new();
+        design.append_pattern(&motif(0x00FF00), &EmbMatrix::new(), ColorStrategy::Separate);
+        assert_eq!(design.stitches(), motif(0x00FF00).stitches());
+        assert_eq!(design.threads().len(), 1);
+
+        // A motif without threads is sewn in the current color
+        let mut bare = EmbPattern::new();
+        bare.stitch_abs(0.0, 0.0);
+        design.append_pattern(&bare, &offset(10.0, 10.0), ColorStrategy::Separate);
+        assert_eq!(design.count_color_changes(), 0);
+        assert_eq!(design.count_trims(), 1);
+
+        let empty = design.append_pattern(
+            &EmbPattern::new(),
+            &EmbMatrix::new(),
+            ColorStrategy::Separate,
+        );
+        assert_eq!(empty, Bounds::default());
+    }
+}
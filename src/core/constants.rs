@@ -72,6 +72,26 @@ pub const SLOW: u32 = 0x0B;
 /// Fast speed command (U01 format)
 pub const FAST: u32 = 0x0C;
 
+// Multi-decoration commands
+
+/// Chenille loop height change
+///
+/// The loop height (machine units, 0-255) is carried in the flags byte; use
+/// `encode_chenille_loop_height` and `chenille_loop_height` to build and read it.
+pub const CHENILLE_LOOP_HEIGHT: u32 = 0x13;
+
+/// Start cording (couching a cord fed through the cording device)
+pub const CORDING_ON: u32 = 0x14;
+
+/// Stop cording and return to regular stitching
+pub const CORDING_OFF: u32 = 0x15;
+
+/// Lower the borer, which cuts holes for eyelets and cutwork
+pub const BORER_ON: u32 = 0x16;
+
+/// Raise the borer and return to regular stitching
+pub const BORER_OFF: u32 = 0x17;
+
 // Middle-level commands
 
 /// Set change sequence - preset/postset thread change sequence
@@ -221,6 +241,12 @@ pub enum StitchType {
     Slow,
     /// Fast speed command
     Fast,
+    /// Chenille loop height change
+    ChenilleLoopHeight,
+    /// Cording on/off command
+    Cording,
+    /// Borer on/off command
+    Borer,
     /// Unknown or custom command
     Unknown,
 }
@@ -251,6 +277,9 @@ impl StitchType {
             NEEDLE_SET => StitchType::NeedleSet,
             SLOW => StitchType::Slow,
             FAST => StitchType::Fast,
+            CHENILLE_LOOP_HEIGHT => StitchType::ChenilleLoopHeight,
+            CORDING_ON | CORDING_OFF => StitchType::Cording,
+            BORER_ON | BORER_OFF => StitchType::Borer,
             _ => StitchType::Unknown,
         }
     }
@@ -293,6 +322,15 @@ impl StitchType {
     pub fn is_sequin(&self) -> bool {
         matches!(self, StitchType::SequinEject | StitchType::SequinMode)
     }
+
+    /// Check if this is a chenille, cording or borer command
+    #[inline]
+    pub fn is_decoration(&self) -> bool {
+        matches!(
+            self,
+            StitchType::ChenilleLoopHeight | StitchType::Cording | StitchType::Borer
+        )
+    }
 }
 
 impl std::fmt::Display for StitchType {
@@ -310,6 +348,9 @@ impl std::fmt::Display for StitchType {
             StitchType::NeedleSet => write!(f, "NeedleSet"),
             StitchType::Slow => write!(f, "Slow"),
             StitchType::Fast => write!(f, "Fast"),
+            StitchType::ChenilleLoopHeight => write!(f, "ChenilleLoopHeight"),
+            StitchType::Cording => write!(f, "Cording"),
+            StitchType::Borer => write!(f, "Borer"),
             StitchType::Unknown => write!(f, "Unknown"),
         }
     }
@@ -331,6 +372,11 @@ pub fn command_name(command: u32) -> &'static str {
         NEEDLE_SET => "NEEDLE_SET",
         SLOW => "SLOW",
         FAST => "FAST",
+        CHENILLE_LOOP_HEIGHT => "CHENILLE_LOOP_HEIGHT",
+        CORDING_ON => "CORDING_ON",
+        CORDING_OFF => "CORDING_OFF",
+        BORER_ON => "BORER_ON",
+        BORER_OFF => "BORER_OFF",
         SET_CHANGE_SEQUENCE => "SET_CHANGE_SEQUENCE",
         SEW_TO => "SEW_TO",
         NEEDLE_AT => "NEEDLE_AT",
@@ -350,6 +396,34 @@ pub fn command_name(command: u32) -> &'static str {
     }
 }
 
+/// Build a chenille loop height command
+///
+/// # Example
+///
+/// ```
+/// use butabuti::core::constants::*;
+///
+/// let cmd = encode_chenille_loop_height(40);
+/// assert_eq!(cmd & COMMAND_MASK, CHENILLE_LOOP_HEIGHT);
+/// assert_eq!(chenille_loop_height(cmd), Some(40));
+/// ```
+#[inline]
+pub fn encode_chenille_loop_height(height: u8) -> u32 {
+    CHENILLE_LOOP_HEIGHT | ((height as u32) << 8)
+}
+
+/// Get the loop height of a chenille loop height command
+///
+/// Returns `None` for any other command.
+#[inline]
+pub fn chenille_loop_height(command: u32) -> Option<u8> {
+    if command & COMMAND_MASK == CHENILLE_LOOP_HEIGHT {
+        Some(((command & FLAGS_MASK) >> 8) as u8)
+    } else {
+        None
+    }
+}
+
 /// Check if a command is valid (within the defined range)
 #[inline]
 pub fn is_valid_command(command: u32) -> bool {
@@ -477,6 +551,26 @@ mod tests {
         assert!(!StitchType::Jump.is_sequin());
     }
 
+    #[test]
+    fn test_decoration_commands() {
+        let cmd = encode_chenille_loop_height(200);
+        assert_eq!(
+            StitchType::from_command(cmd),
+            StitchType::ChenilleLoopHeight
+        );
+        assert_eq!(chenille_loop_height(cmd), Some(200));
+        assert_eq!(chenille_loop_height(STITCH), None);
+        assert_eq!(command_name(cmd), "CHENILLE_LOOP_HEIGHT");
+
+        assert_eq!(StitchType::from_command(CORDING_ON), StitchType::Cording);
+        assert_eq!(StitchType::from_command(CORDING_OFF), StitchType::Cording);
+        assert!(StitchType::Cording.is_decoration());
+        assert_eq!(StitchType::from_command(BORER_ON), StitchType::Borer);
+        assert_eq!(command_name(BORER_OFF), "BORER_OFF");
+        assert!(StitchType::Borer.is_decoration());
+        assert!(!StitchType::Normal.is_decoration());
+    }
+
     #[test]
     fn test_stitch_type_display() {
         assert_eq!(format!("{}", StitchType::Normal), "Normal");
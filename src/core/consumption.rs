@@ -0,0 +1,337 @@
+//! Thread consumption estimates
+//!
+//! Stitch length alone undercounts the thread a machine pulls: every needle
+//! penetration carries the top thread down through the fabric and back, satin
+//! stitches wrap over the edge of the column, and each trim leaves a tail.
+//! [`EmbPattern::estimate_thread_consumption`] applies a [`ConsumptionProfile`]
+//! to each stitch to estimate top and bobbin thread in meters, for costing
+//! designs.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::consumption::ConsumptionProfile;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(1000.0, 0.0);
+//!
+//! let profile = ConsumptionProfile::new(0.0).running_multiplier(1.0);
+//! let consumption = pattern.estimate_thread_consumption(&profile);
+//! assert!((consumption.top_thread_m - 0.1).abs() < 1e-9);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{EmbPattern, Stitch, ThreadUsage};
+use crate::core::thread::EmbThread;
+use std::collections::HashMap;
+
+/// Model parameters for thread consumption
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsumptionProfile {
+    /// Fabric thickness in millimeters; each stitch adds the top thread's trip
+    /// down through the fabric and back (default: 1.0)
+    pub fabric_thickness_mm: f64,
+    /// Top thread per millimeter of running and fill stitches (default: 1.2)
+    pub running_multiplier: f64,
+    /// Top thread per millimeter of satin stitches (default: 1.4)
+    ///
+    /// A stitch counts as satin when it doubles back on the previous stitch.
+    pub satin_multiplier: f64,
+    /// Bobbin thread per millimeter of stitch length (default: 0.4)
+    pub bobbin_ratio: f64,
+    /// Top thread left as a tail at each trim and color change, in millimeters
+    /// (default: 20.0)
+    pub tail_mm: f64,
+}
+
+impl Default for ConsumptionProfile {
+    fn default() -> Self {
+        Self {
+            fabric_thickness_mm: 1.0,
+            running_multiplier: 1.2,
+            satin_multiplier: 1.4,
+            bobbin_ratio: 0.4,
+            tail_mm: 20.0,
+        }
+    }
+}
+
+impl ConsumptionProfile {
+    /// Create a profile for the given fabric thickness in millimeters
+    pub fn new(fabric_thickness_mm: f64) -> Self {
+        Self {
+            fabric_thickness_mm,
+            ..Default::default()
+        }
+    }
+
+    /// Set the top thread multiplier for running and fill stitches
+    pub fn running_multiplier(mut self, multiplier: f64) -> Self {
+        self.running_multiplier = multiplier;
+        self
+    }
+
+    /// Set the top thread multiplier for satin stitches
+    pub fn satin_multiplier(mut self, multiplier: f64) -> Self {
+        self.satin_multiplier = multiplier;
+        self
+    }
+
+    /// Set the bobbin thread used per millimeter of stitch
+    pub fn bobbin_ratio(mut self, ratio: f64) -> Self {
+        self.bobbin_ratio = ratio;
+        self
+    }
+
+    /// Set the tail length left at trims and color changes
+    pub fn tail_mm(mut self, tail_mm: f64) -> Self {
+        self.tail_mm = tail_mm;
+        self
+    }
+}
+
+/// Estimated thread consumption of a pattern
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThreadConsumption {
+    /// Usage per thread
+    pub threads: Vec<ThreadUsage>,
+    /// Total top thread in meters
+    pub top_thread_m: f64,
+    /// Total bobbin thread in meters
+    pub bobbin_thread_m: f64,
+}
+
+impl ThreadConsumption {
+    /// Total top and bobbin thread in meters
+    pub fn total_m(&self) -> f64 {
+        self.top_thread_m + self.bobbin_thread_m
+    }
+}
+
+/// Thread used by one STITCH record; length in 0.1mm, thread in millimeters
+#[derive(Clone, Copy)]
+struct Sewn {
+    length: f64,
+    top: f64,
+    bobbin: f64,
+}
+
+/// Thread used by the record at `i`, or `None` if it isn't a STITCH
+///
+/// Depends only on the two records before it, so records can be measured
+/// independently.
+fn sewn_thread(stitches: &[Stitch], i: usize, profile: &ConsumptionProfile) -> Option<Sewn> {
+    if extract_command(stitches[i].command) != STITCH {
+        return None;
+    }
+    let position = |j: Option<usize>| j.map_or((0.0, 0.0), |j| (stitches[j].x, stitches[j].y));
+    let delta = |j: usize| {
+        let (x, y) = position(j.checked_sub(1));
+        (stitches[j].x - x, stitches[j].y - y)
+    };
+
+    let (dx, dy) = delta(i);
+    let length = dx.hypot(dy);
+    let satin = i
+        .checked_sub(1)
+        .filter(|&j| extract_command(stitches[j].command) == STITCH)
+        .map(delta)
+        .is_some_and(|(px, py)| {
+            // Doubles back: more than 120° from the previous stitch
+            px * dx + py * dy < -0.5 * px.hypot(py) * length
+        });
+    let multiplier = if satin {
+        profile.satin_multiplier
+    } else {
+        profile.running_multiplier
+    };
+    let length_mm = length / 10.0;
+    Some(Sewn {
+        length,
+        top: length_mm * multiplier + 2.0 * profile.fabric_thickness_mm,
+        bobbin: length_mm * profile.bobbin_ratio,
+    })
+}
+
+/// Thread used by every record, on the rayon pool for large patterns when the
+/// `parallel` feature is enabled
+fn measure(stitches: &[Stitch], profile: &ConsumptionProfile) -> Vec<Option<Sewn>> {
+    #[cfg(feature = "parallel")]
+    if stitches.len() >= crate::core::pattern::PARALLEL_THRESHOLD {
+        use rayon::prelude::*;
+        return (0..stitches.len())
+            .into_par_iter()
+            .map(|i| sewn_thread(stitches, i, profile))
+            .collect();
+    }
+    (0..stitches.len())
+        .map(|i| sewn_thread(stitches, i, profile))
+        .collect()
+}
+
+/// Running totals for one thread, lengths in millimeters
+#[derive(Default)]
+struct Totals {
+    stitches: usize,
+    length: f64,
+    top: f64,
+    bobbin: f64,
+}
+
+impl EmbPattern {
+    /// Estimate top and bobbin thread consumption
+    ///
+    /// Each stitch uses `length * multiplier + 2 * fabric_thickness` of top
+    /// thread, with the satin multiplier for stitches that double back on the
+    /// previous one, and `length * bobbin_ratio` of bobbin thread. Trims and
+    /// color changes add a tail to the thread in use.
+    pub fn estimate_thread_consumption(&self, profile: &ConsumptionProfile) -> ThreadConsumption {
+        let threads = self.thread_usage(profile);
+        ThreadConsumption {
+            top_thread_m: threads.iter().map(|t| t.top_thread_m).sum(),
+            bobbin_thread_m: threads.iter().map(|t| t.bobbin_thread_m).sum(),
+            threads,
+        }
+    }
+
+    /// Per-thread usage under a consumption profile, sorted by color
+    pub(crate) fn thread_usage(&self, profile: &ConsumptionProfile) -> Vec<ThreadUsage> {
+        let stitches = self.stitches();
+        let mut usage: HashMap<usize, Totals> = HashMap::new();
+        let mut thread_index = 0;
+        for (stitch, sewn) in stitches.iter().zip(measure(stitches, profile)) {
+            let command = extract_command(stitch.command);
+            match sewn {
+                Some(sewn) => {
+                    let totals = usage.entry(thread_index).or_default();
+                    totals.stitches += 1;
+                    totals.length += sewn.length;
+                    totals.top += sewn.top;
+                    totals.bobbin += sewn.bobbin;
+                }
+                None if matches!(command, TRIM | CUT | COLOR_CHANGE) => {
+                    if let Some(totals) = usage.get_mut(&thread_index) {
+                        totals.top += profile.tail_mm;
+                    }
+                    if command == COLOR_CHANGE {
+                        thread_index += 1;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let mut result: Vec<ThreadUsage> = usage
+            .into_iter()
+            .map(|(index, totals)| ThreadUsage {
+                thread: self
+                    .threads()
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_else(|| EmbThread::new(0x000000)),
+                length_mm: totals.length / 10.0,
+                stitch_count: totals.stitches,
+                top_thread_m: totals.top / 1000.0,
+                bobbin_thread_m: totals.bobbin / 1000.0,
+            })
+            .collect();
+        result.sort_by_key(|usage| usage.thread.color);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_running_and_satin() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(0.0, 40.0);
+        // Zigzag back and forth: two satin stitches
+        pattern.stitch_abs(10.0, 0.0);
+        pattern.stitch_abs(20.0, 40.0);
+
+        let profile = ConsumptionProfile::new(0.5)
+            .running_multiplier(1.0)
+            .satin_multiplier(2.0)
+            .bobbin_ratio(0.5)
+            .tail_mm(0.0);
+        let consumption = pattern.estimate_thread_consumption(&profile);
+
+        let satin = 10.0_f64.hypot(40.0) / 10.0;
+        let length = 4.0 + 2.0 * satin;
+        let top = 4.0 + 2.0 * satin * 2.0 + 4.0 * 2.0 * 0.5;
+        assert_eq!(consumption.threads.len(), 1);
+        assert_eq!(consumption.threads[0].stitch_count, 4);
+        assert!(approx(consumption.threads[0].length_mm, length));
+        assert!(approx(consumption.top_thread_m, top / 1000.0));
+        assert!(approx(consumption.bobbin_thread_m, length * 0.5 / 1000.0));
+        assert!(approx(consumption.total_m(), (top + length * 0.5) / 1000.0));
+    }
+
+    #[test]
+    fn test_tails_per_thread() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.end();
+
+        let profile = ConsumptionProfile::new(0.0).tail_mm(30.0);
+        let consumption = pattern.estimate_thread_consumption(&profile);
+
+        let tops: Vec<(u32, f64)> = consumption
+            .threads
+            .iter()
+            .map(|t| (t.thread.color, t.top_thread_m))
+            .collect();
+        assert_eq!(tops, vec![(0x0000FF, 0.06), (0x00FF00, 0.0)]);
+    }
+
+    #[test]
+    fn test_large_pattern() {
+        use crate::core::pattern::PARALLEL_TEST_STITCHES;
+
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        for i in 1..PARALLEL_TEST_STITCHES {
+            // Zigzag: every stitch after the second doubles back
+            pattern.stitch_abs(i as f64 * 10.0, if i % 2 == 0 { 0.0 } else { 100.0 });
+        }
+
+        let profile = ConsumptionProfile::new(0.0)
+            .running_multiplier(1.0)
+            .satin_multiplier(2.0);
+        let usage = &pattern.estimate_thread_consumption(&profile).threads[0];
+
+        let zig = 10.0_f64.hypot(100.0) / 10.0;
+        let expected = zig + (PARALLEL_TEST_STITCHES - 2) as f64 * zig * 2.0;
+        assert_eq!(usage.stitch_count, PARALLEL_TEST_STITCHES);
+        assert!((usage.top_thread_m - expected / 1000.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_statistics_use_default_profile() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+
+        let stats = pattern.calculate_statistics(800.0);
+        let consumption = pattern.estimate_thread_consumption(&ConsumptionProfile::default());
+        assert_eq!(stats.thread_usage, consumption.threads);
+        assert!(consumption.top_thread_m > stats.thread_usage[0].length_mm / 1000.0);
+    }
+}
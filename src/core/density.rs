@@ -0,0 +1,352 @@
+//! Stitch density analysis
+//!
+//! Too many needle penetrations in a small area perforate the fabric, pile up
+//! thread and break needles. [`EmbPattern::density_map`] counts stitches on a
+//! grid of square cells and reports each cell's density in stitches per cm²;
+//! [`DensityMap::hotspots`] groups neighbouring cells above a threshold into
+//! regions to check before production.
+//!
+//! Heat maps render to pixels with
+//! [`render_density_map`](crate::render::render_density_map) and to PNG with
+//! [`png::write_density_map`](crate::formats::io::writers::png::write_density_map).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! // 200 stitches piled into a 1mm square
+//! for i in 0..200 {
+//!     pattern.stitch_abs((i % 10) as f64, (i / 20) as f64);
+//! }
+//! pattern.stitch_abs(300.0, 300.0);
+//!
+//! let map = pattern.density_map(20.0)?;
+//! assert_eq!(map.max_density(), 5000.0); // 200 stitches in 0.04 cm²
+//!
+//! let hotspots = pattern.find_density_hotspots(150.0);
+//! assert_eq!(hotspots.len(), 1);
+//! assert_eq!(hotspots[0].stitches, 200);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::{COMMAND_MASK, STITCH};
+use crate::core::pattern::{Bounds, EmbPattern};
+use crate::utils::error::{Error, Result};
+
+/// Cell size used by [`EmbPattern::find_density_hotspots`] (0.1mm units)
+pub const DEFAULT_CELL_SIZE: f64 = 20.0;
+
+/// Largest grid the density map will allocate, in cells
+const MAX_CELLS: usize = 1 << 24;
+
+/// Square cells per cm² for a cell size in 0.1mm units
+fn cells_per_cm2(cell_size: f64) -> f64 {
+    10_000.0 / (cell_size * cell_size)
+}
+
+/// Stitch density on a grid of square cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMap {
+    /// Cell edge length (0.1mm units)
+    pub cell_size: f64,
+    /// Corner of the first cell, at the smallest stitch coordinates
+    pub origin: (f64, f64),
+    /// Cells per row
+    pub columns: usize,
+    /// Rows of cells
+    pub rows: usize,
+    /// Stitches in each cell, row by row from the origin
+    pub counts: Vec<usize>,
+}
+
+impl DensityMap {
+    /// Stitches in the cell, `None` outside the grid
+    pub fn count(&self, column: usize, row: usize) -> Option<usize> {
+        (column < self.columns && row < self.rows).then(|| self.counts[row * self.columns + column])
+    }
+
+    /// Density of the cell in stitches per cm², `None` outside the grid
+    pub fn density(&self, column: usize, row: usize) -> Option<f64> {
+        self.count(column, row)
+            .map(|count| count as f64 * cells_per_cm2(self.cell_size))
+    }
+
+    /// Highest cell density in stitches per cm²
+    pub fn max_density(&self) -> f64 {
+        self.counts.iter().copied().max().unwrap_or(0) as f64 * cells_per_cm2(self.cell_size)
+    }
+
+    /// Cell containing a point, `None` outside the grid
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let column = ((x - self.origin.0) / self.cell_size).floor();
+        let row = ((y - self.origin.1) / self.cell_size).floor();
+        if !(column >= 0.0 && row >= 0.0) {
+            return None;
+        }
+        let (column, row) = (column as usize, row as usize);
+        (column < self.columns && row < self.rows).then_some((column, row))
+    }
+
+    /// Area covered by the cell (0.1mm units)
+    pub fn cell_bounds(&self, column: usize, row: usize) -> Bounds {
+        let min_x = self.origin.0 + column as f64 * self.cell_size;
+        let min_y = self.origin.1 + row as f64 * self.cell_size;
+        Bounds::new(min_x, min_y, min_x + self.cell_size, min_y + self.cell_size)
+    }
+
+    /// Regions of touching cells denser than `threshold` stitches per cm²
+    ///
+    /// Cells sharing an edge belong to the same region. Regions are sorted
+    /// densest first.
+    pub fn hotspots(&self, threshold: f64) -> Vec<DensityHotspot> {
+        let hot =
+            |index: usize| self.counts[index] as f64 * cells_per_cm2(self.cell_size) > threshold;
+        let mut visited = vec![false; self.counts.len()];
+        let mut hotspots = Vec::new();
+
+        for start in 0..self.counts.len() {
+            if visited[start] || !hot(start) {
+                continue;
+            }
+            visited[start] = true;
+            let mut pending = vec![start];
+            let mut region: Option<DensityHotspot> = None;
+
+            while let Some(index) = pending.pop() {
+                let (column, row) = (index % self.columns, index / self.columns);
+                let bounds = self.cell_bounds(column, row);
+                let count = self.counts[index];
+                let density = count as f64 * cells_per_cm2(self.cell_size);
+                match region.as_mut() {
+                    None => {
+                        region = Some(DensityHotspot {
+                            bounds,
+                            cells: 1,
+                            stitches: count,
+                            peak_density: density,
+                        })
+                    }
+                    Some(hotspot) => {
+                        hotspot.bounds = Bounds::new(
+                            hotspot.bounds.min_x.min(bounds.min_x),
+                            hotspot.bounds.min_y.min(bounds.min_y),
+                            hotspot.bounds.max_x.max(bounds.max_x),
+                            hotspot.bounds.max_y.max(bounds.max_y),
+                        );
+                        hotspot.cells += 1;
+                        hotspot.stitches += count;
+                        hotspot.peak_density = hotspot.peak_density.max(density);
+                    }
+                }
+
+                let neighbours = [
+                    (column > 0).then(|| index - 1),
+                    (column + 1 < self.columns).then(|| index + 1),
+                    (row > 0).then(|| index - self.columns),
+                    (row + 1 < self.rows).then(|| index + self.columns),
+                ];
+                for neighbour in neighbours.into_iter().flatten() {
+                    if !visited[neighbour] && hot(neighbour) {
+                        visited[neighbour] = true;
+                        pending.push(neighbour);
+                    }
+                }
+            }
+            hotspots.extend(region);
+        }
+
+        hotspots.sort_by(|a, b| b.peak_density.total_cmp(&a.peak_density));
+        hotspots
+    }
+}
+
+/// A region of cells above a density threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityHotspot {
+    /// Area covered by the region's cells (0.1mm units)
+    pub bounds: Bounds,
+    /// Cells in the region
+    pub cells: usize,
+    /// Stitches in the region
+    pub stitches: usize,
+    /// Density of the region's densest cell in stitches per cm²
+    pub peak_density: f64,
+}
+
+/// Smallest and largest coordinates of a non-empty point list
+fn point_bounds(points: &[(f64, f64)], first: (f64, f64)) -> (f64, f64, f64, f64) {
+    let init = (first.0, first.1, first.0, first.1);
+    let merge = |a: (f64, f64, f64, f64), b: (f64, f64, f64, f64)| {
+        (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3))
+    };
+
+    #[cfg(feature = "parallel")]
+    if points.len() >= crate::core::pattern::PARALLEL_THRESHOLD {
+        use rayon::prelude::*;
+        return points
+            .par_iter()
+            .map(|&(x, y)| (x, y, x, y))
+            .reduce(|| init, merge);
+    }
+    points.iter().map(|&(x, y)| (x, y, x, y)).fold(init, merge)
+}
+
+/// Index into `map.counts` of the cell holding each point
+fn cell_indices(map: &DensityMap, points: &[(f64, f64)]) -> Vec<usize> {
+    let index = |&(x, y): &(f64, f64)| {
+        map.cell_at(x, y)
+            .map(|(column, row)| row * map.columns + column)
+    };
+
+    #[cfg(feature = "parallel")]
+    if points.len() >= crate::core::pattern::PARALLEL_THRESHOLD {
+        use rayon::prelude::*;
+        return points.par_iter().filter_map(index).collect();
+    }
+    points.iter().filter_map(index).collect()
+}
+
+impl EmbPattern {
+    /// Count stitches on a grid of square cells `cell_size` wide (0.1mm units)
+    ///
+    /// Only needle penetrations (STITCH commands) count. The grid starts at the
+    /// smallest stitch coordinates and covers every stitch; a pattern without
+    /// stitches gives an empty map.
+    ///
+    /// Fails if `cell_size` isn't positive or the grid would be unreasonably large.
+    pub fn density_map(&self, cell_size: f64) -> Result<DensityMap> {
+        if cell_size <= 0.0 || !cell_size.is_finite() {
+            return Err(Error::InvalidPattern(format!(
+                "Invalid cell_size: {}",
+                cell_size
+            )));
+        }
+
+        let points: Vec<(f64, f64)> = self
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| (s.x, s.y))
+            .collect();
+        let Some(&(first_x, first_y)) = points.first() else {
+            return Ok(DensityMap {
+                cell_size,
+                origin: (0.0, 0.0),
+                columns: 0,
+                rows: 0,
+                counts: Vec::new(),
+            });
+        };
+
+        let (min_x, min_y, max_x, max_y) = point_bounds(&points, (first_x, first_y));
+
+        let columns = ((max_x - min_x) / cell_size).floor() + 1.0;
+        let rows = ((max_y - min_y) / cell_size).floor() + 1.0;
+        if columns * rows > MAX_CELLS as f64 {
+            return Err(Error::InvalidPattern(format!(
+                "Density map of {} x {} cells is too large, use a larger cell_size",
+                columns, rows
+            )));
+        }
+        let (columns, rows) = (columns as usize, rows as usize);
+
+        let mut map = DensityMap {
+            cell_size,
+            origin: (min_x, min_y),
+            columns,
+            rows,
+            counts: vec![0; columns * rows],
+        };
+        for cell in cell_indices(&map, &points) {
+            map.counts[cell] += 1;
+        }
+        Ok(map)
+    }
+
+    /// Regions denser than `threshold` stitches per cm², densest first
+    ///
+    /// Uses cells of [`DEFAULT_CELL_SIZE`]; build a [`density_map`](Self::density_map)
+    /// and call [`DensityMap::hotspots`] for other cell sizes.
+    pub fn find_density_hotspots(&self, threshold: f64) -> Vec<DensityHotspot> {
+        self.density_map(DEFAULT_CELL_SIZE)
+            .map(|map| map.hotspots(threshold))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_density_map_large_pattern() {
+        use crate::core::pattern::PARALLEL_TEST_STITCHES;
+
+        // 200 stitches per row, so every 10mm cell holds 100 stitches
+        let mut pattern = EmbPattern::new();
+        for i in 0..PARALLEL_TEST_STITCHES {
+            pattern.stitch_abs((i % 200) as f64 - 50.0, (i / 200) as f64);
+        }
+
+        let map = pattern.density_map(10.0).unwrap();
+        assert_eq!(map.origin, (-50.0, 0.0));
+        assert_eq!(
+            (map.columns, map.rows),
+            (20, PARALLEL_TEST_STITCHES / 2_000)
+        );
+        assert!(map.counts.iter().all(|&count| count == 100));
+    }
+
+    #[test]
+    fn test_density_map_counts() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(5.0, 5.0);
+        pattern.jump_abs(15.0, 0.0);
+        pattern.stitch_abs(15.0, 0.0);
+        pattern.stitch_abs(25.0, 25.0);
+
+        let map = pattern.density_map(10.0).unwrap();
+        assert_eq!((map.columns, map.rows), (3, 3));
+        assert_eq!(map.count(0, 0), Some(2));
+        assert_eq!(map.count(1, 0), Some(1));
+        assert_eq!(map.count(2, 2), Some(1));
+        assert_eq!(map.count(3, 0), None);
+        // A 1mm cell is 1/100 cm²
+        assert_eq!(map.density(0, 0), Some(200.0));
+        assert_eq!(map.cell_bounds(1, 2), Bounds::new(10.0, 20.0, 20.0, 30.0));
+
+        assert!(pattern.density_map(0.0).is_err());
+        assert!(pattern.density_map(f64::NAN).is_err());
+        assert_eq!(EmbPattern::new().density_map(10.0).unwrap().counts.len(), 0);
+    }
+
+    #[test]
+    fn test_hotspots_group_touching_cells() {
+        let mut pattern = EmbPattern::new();
+        for (x, y, n) in [
+            (0.0, 0.0, 4),
+            (10.0, 0.0, 3),
+            (50.0, 50.0, 5),
+            (30.0, 0.0, 1),
+        ] {
+            for _ in 0..n {
+                pattern.stitch_abs(x, y);
+            }
+        }
+
+        let map = pattern.density_map(10.0).unwrap();
+        let hotspots = map.hotspots(150.0);
+        assert_eq!(hotspots.len(), 2);
+        assert_eq!(hotspots[0].peak_density, 500.0);
+        assert_eq!(hotspots[0].cells, 1);
+        assert_eq!(hotspots[1].cells, 2);
+        assert_eq!(hotspots[1].stitches, 7);
+        assert_eq!(hotspots[1].bounds, Bounds::new(0.0, 0.0, 20.0, 10.0));
+
+        assert!(map.hotspots(1000.0).is_empty());
+        assert!(EmbPattern::new().find_density_hotspots(0.0).is_empty());
+    }
+}
@@ -0,0 +1,505 @@
+//! Pattern comparison
+//!
+//! [`PatternDiff::compare`] lines up the stitches of two patterns in sewing
+//! order and reports which were added, removed or moved, together with the
+//! threads and metadata that differ. Use it to check that a pipeline still
+//! produces the same designs, allowing for rounding with [`DiffOptions`].
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::core::diff::{DiffOptions, PatternDiff, StitchChange};
+//! use butabuti::prelude::*;
+//!
+//! let mut before = EmbPattern::new();
+//! before.add_thread(EmbThread::new(0xFF0000));
+//! before.stitch_abs(0.0, 0.0);
+//! before.stitch_abs(50.0, 0.0);
+//! before.stitch_abs(100.0, 0.0);
+//!
+//! let mut after = before.clone();
+//! after.translate(0.2, 0.0); // rounding noise
+//! after.stitch_abs(150.0, 0.0);
+//!
+//! let diff = PatternDiff::compare_with(&before, &after, &DiffOptions::new().position_tolerance(0.5));
+//! assert_eq!(diff.stitches.len(), 1);
+//! assert!(matches!(diff.stitches[0], StitchChange::Added { index: 3, .. }));
+//! ```
+
+use crate::core::constants::{command_name, COMMAND_MASK};
+use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Tolerances for [`PatternDiff::compare_with`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOptions {
+    /// Largest distance at which stitches still count as equal (0.1mm, default: 0.0)
+    pub position_tolerance: f64,
+    /// Largest distance a stitch may move and still be reported as moved rather
+    /// than removed and added (0.1mm, default: 20.0)
+    pub move_radius: f64,
+    /// Stitches searched ahead to line the patterns up again after a
+    /// difference (default: 32)
+    pub lookahead: usize,
+    /// Largest delta-E at which thread colors still count as equal (default: 0.0)
+    pub color_tolerance: f32,
+    /// Metadata keys left out of the comparison
+    pub ignore_metadata: Vec<String>,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            position_tolerance: 0.0,
+            move_radius: 20.0,
+            lookahead: 32,
+            color_tolerance: 0.0,
+            ignore_metadata: Vec::new(),
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Create options that compare exactly
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the distance at which stitches still count as equal
+    pub fn position_tolerance(mut self, tolerance: f64) -> Self {
+        self.position_tolerance = tolerance;
+        self
+    }
+
+    /// Set the distance up to which a changed stitch is reported as moved
+    pub fn move_radius(mut self, radius: f64) -> Self {
+        self.move_radius = radius;
+        self
+    }
+
+    /// Set how far ahead to search when lining the patterns up again
+    pub fn lookahead(mut self, stitches: usize) -> Self {
+        self.lookahead = stitches;
+        self
+    }
+
+    /// Set the delta-E at which thread colors still count as equal
+    pub fn color_tolerance(mut self, delta_e: f32) -> Self {
+        self.color_tolerance = delta_e;
+        self
+    }
+
+    /// Leave a metadata key out of the comparison
+    pub fn ignore_metadata(mut self, key: impl Into<String>) -> Self {
+        self.ignore_metadata.push(key.into());
+        self
+    }
+}
+
+/// A stitch difference, with indices into the stitch lists
+#[derive(Debug, Clone, PartialEq)]
+pub enum StitchChange {
+    /// Only in the second pattern
+    Added {
+        /// Index in the second pattern
+        index: usize,
+        /// The added stitch
+        stitch: Stitch,
+    },
+    /// Only in the first pattern
+    Removed {
+        /// Index in the first pattern
+        index: usize,
+        /// The removed stitch
+        stitch: Stitch,
+    },
+    /// Same command at a different position
+    Moved {
+        /// Index in the first pattern
+        from_index: usize,
+        /// Index in the second pattern
+        to_index: usize,
+        /// The stitch in the first pattern
+        from: Stitch,
+        /// The stitch in the second pattern
+        to: Stitch,
+    },
+}
+
+/// A thread difference, by position in the thread lists
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThreadChange {
+    /// Only in the second pattern
+    Added {
+        /// Index in the second pattern's thread list
+        index: usize,
+        /// The added thread
+        thread: EmbThread,
+    },
+    /// Only in the first pattern
+    Removed {
+        /// Index in the first pattern's thread list
+        index: usize,
+        /// The removed thread
+        thread: EmbThread,
+    },
+    /// Different color or catalog details at the same index
+    Changed {
+        /// Index in both thread lists
+        index: usize,
+        /// The thread in the first pattern
+        from: EmbThread,
+        /// The thread in the second pattern
+        to: EmbThread,
+    },
+}
+
+/// A metadata key whose value differs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataChange {
+    /// Metadata key
+    pub key: String,
+    /// Value in the first pattern
+    pub from: Option<String>,
+    /// Value in the second pattern
+    pub to: Option<String>,
+}
+
+/// Differences between two patterns
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PatternDiff {
+    /// Stitch differences, in sewing order
+    pub stitches: Vec<StitchChange>,
+    /// Thread differences, by index
+    pub threads: Vec<ThreadChange>,
+    /// Metadata differences, sorted by key
+    pub metadata: Vec<MetadataChange>,
+}
+
+impl PatternDiff {
+    /// Compare two patterns exactly
+    pub fn compare(a: &EmbPattern, b: &EmbPattern) -> Self {
+        Self::compare_with(a, b, &DiffOptions::default())
+    }
+
+    /// Compare two patterns with tolerances
+    ///
+    /// Stitches are equal when their commands match (ignoring thread and
+    /// needle flags) and they lie within `position_tolerance`. After a
+    /// difference, the next `lookahead` stitches of each pattern are searched
+    /// for the nearest point where the two line up again; stitches skipped on
+    /// the way are added or removed. A stitch that lines up with nothing is
+    /// moved if the other pattern has the same command within `move_radius`.
+    pub fn compare_with(a: &EmbPattern, b: &EmbPattern, options: &DiffOptions) -> Self {
+        Self {
+            stitches: diff_stitches(a.stitches(), b.stitches(), options),
+            threads: diff_threads(a.threads(), b.threads(), options),
+            metadata: diff_metadata(a, b, options),
+        }
+    }
+
+    /// Whether the patterns are equal within the tolerances
+    pub fn is_identical(&self) -> bool {
+        self.stitches.is_empty() && self.threads.is_empty() && self.metadata.is_empty()
+    }
+
+    /// Number of added stitches
+    pub fn added(&self) -> usize {
+        self.count(|change| matches!(change, StitchChange::Added { .. }))
+    }
+
+    /// Number of removed stitches
+    pub fn removed(&self) -> usize {
+        self.count(|change| matches!(change, StitchChange::Removed { .. }))
+    }
+
+    /// Number of moved stitches
+    pub fn moved(&self) -> usize {
+        self.count(|change| matches!(change, StitchChange::Moved { .. }))
+    }
+
+    fn count(&self, filter: impl Fn(&StitchChange) -> bool) -> usize {
+        self.stitches.iter().filter(|change| filter(change)).count()
+    }
+}
+
+impl fmt::Display for PatternDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_identical() {
+            return f.write_str("identical");
+        }
+        write!(
+            f,
+            "{} added, {} removed, {} moved stitches",
+            self.added(),
+            self.removed(),
+            self.moved()
+        )?;
+        if !self.threads.is_empty() {
+            write!(f, ", {} thread changes", self.threads.len())?;
+        }
+        if !self.metadata.is_empty() {
+            let keys: Vec<&str> = self.metadata.iter().map(|m| m.key.as_str()).collect();
+            write!(f, ", metadata changed: {}", keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn diff_stitches(a: &[Stitch], b: &[Stitch], options: &DiffOptions) -> Vec<StitchChange> {
+    let same_command =
+        |s: &Stitch, t: &Stitch| s.command & COMMAND_MASK == t.command & COMMAND_MASK;
+    let equal = |s: &Stitch, t: &Stitch| {
+        same_command(s, t) && (s.x - t.x).hypot(s.y - t.y) <= options.position_tolerance
+    };
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if equal(&a[i], &b[j]) {
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        // Nearest resync: skip stitches added to b, or removed from a
+        let added = (1..=options.lookahead)
+            .take_while(|k| j + k < b.len())
+            .find(|&k| equal(&a[i], &b[j + k]));
+        let removed = (1..=options.lookahead)
+            .take_while(|k| i + k < a.len())
+            .find(|&k| equal(&a[i + k], &b[j]));
+        match (added, removed) {
+            (Some(k), r) if r.is_none_or(|r| k <= r) => {
+                changes.extend((j..j + k).map(|index| StitchChange::Added {
+                    index,
+                    stitch: b[index],
+                }));
+                j += k;
+            }
+            (_, Some(k)) => {
+                changes.extend((i..i + k).map(|index| StitchChange::Removed {
+                    index,
+                    stitch: a[index],
+                }));
+                i += k;
+            }
+            _ => {
+                if same_command(&a[i], &b[j])
+                    && (a[i].x - b[j].x).hypot(a[i].y - b[j].y) <= options.move_radius
+                {
+                    changes.push(StitchChange::Moved {
+                        from_index: i,
+                        to_index: j,
+                        from: a[i],
+                        to: b[j],
+                    });
+                } else {
+                    changes.push(StitchChange::Removed {
+                        index: i,
+                        stitch: a[i],
+                    });
+                    changes.push(StitchChange::Added {
+                        index: j,
+                        stitch: b[j],
+                    });
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    changes.extend((i..a.len()).map(|index| StitchChange::Removed {
+        index,
+        stitch: a[index],
+    }));
+    changes.extend((j..b.len()).map(|index| StitchChange::Added {
+        index,
+        stitch: b[index],
+    }));
+    changes
+}
+
+fn diff_threads(a: &[EmbThread], b: &[EmbThread], options: &DiffOptions) -> Vec<ThreadChange> {
+    let mut changes = Vec::new();
+    for index in 0..a.len().max(b.len()) {
+        match (a.get(index), b.get(index)) {
+            (Some(from), Some(to)) => {
+                let same = from.delta_e(to) <= options.color_tolerance
+                    && from.description == to.description
+                    && from.catalog_number == to.catalog_number
+                    && from.brand == to.brand
+                    && from.weight == to.weight;
+                if !same {
+                    changes.push(ThreadChange::Changed {
+                        index,
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
+                }
+            }
+            (Some(thread), None) => changes.push(ThreadChange::Removed {
+                index,
+                thread: thread.clone(),
+            }),
+            (None, Some(thread)) => changes.push(ThreadChange::Added {
+                index,
+                thread: thread.clone(),
+            }),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+fn diff_metadata(a: &EmbPattern, b: &EmbPattern, options: &DiffOptions) -> Vec<MetadataChange> {
+    let keys: BTreeSet<&String> = a
+        .metadata()
+        .chain(b.metadata())
+        .map(|(key, _)| key)
+        .collect();
+    keys.into_iter()
+        .filter(|key| !options.ignore_metadata.contains(key))
+        .filter_map(|key| {
+            let (from, to) = (a.get_metadata(key), b.get_metadata(key));
+            (from != to).then(|| MetadataChange {
+                key: key.clone(),
+                from: from.cloned(),
+                to: to.cloned(),
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for StitchChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StitchChange::Added { index, stitch } => write!(f, "+ [{}] {}", index, stitch),
+            StitchChange::Removed { index, stitch } => write!(f, "- [{}] {}", index, stitch),
+            StitchChange::Moved {
+                from_index,
+                to_index,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ [{} -> {}] {} ({:.2}, {:.2}) -> ({:.2}, {:.2})",
+                from_index,
+                to_index,
+                command_name(from.command),
+                from.x,
+                from.y,
+                to.x,
+                to.y
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::{STITCH, TRIM};
+
+    fn line(xs: &[f64]) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for &x in xs {
+            pattern.stitch_abs(x, 0.0);
+        }
+        pattern
+    }
+
+    #[test]
+    fn test_identical_and_tolerance() {
+        let a = line(&[0.0, 10.0, 20.0]);
+        assert!(PatternDiff::compare(&a, &a).is_identical());
+
+        let b = line(&[0.0, 10.3, 20.0]);
+        let exact = PatternDiff::compare(&a, &b);
+        assert_eq!(exact.moved(), 1);
+        assert_eq!(
+            exact.stitches[0],
+            StitchChange::Moved {
+                from_index: 1,
+                to_index: 1,
+                from: Stitch::new(10.0, 0.0, STITCH),
+                to: Stitch::new(10.3, 0.0, STITCH),
+            }
+        );
+        assert!(
+            PatternDiff::compare_with(&a, &b, &DiffOptions::new().position_tolerance(0.5))
+                .is_identical()
+        );
+    }
+
+    #[test]
+    fn test_added_removed_and_replaced() {
+        let a = line(&[0.0, 10.0, 20.0, 30.0]);
+        let b = line(&[0.0, 5.0, 10.0, 30.0, 40.0]);
+        let diff = PatternDiff::compare(&a, &b);
+        assert_eq!(
+            diff.stitches,
+            vec![
+                StitchChange::Added {
+                    index: 1,
+                    stitch: Stitch::new(5.0, 0.0, STITCH),
+                },
+                StitchChange::Removed {
+                    index: 2,
+                    stitch: Stitch::new(20.0, 0.0, STITCH),
+                },
+                StitchChange::Added {
+                    index: 4,
+                    stitch: Stitch::new(40.0, 0.0, STITCH),
+                },
+            ]
+        );
+
+        // A different command at the same place is not a move
+        let mut c = line(&[0.0]);
+        c.add_stitch_absolute(TRIM, 10.0, 0.0);
+        c.stitch_abs(20.0, 0.0);
+        c.stitch_abs(30.0, 0.0);
+        let diff = PatternDiff::compare(&a, &c);
+        assert_eq!((diff.added(), diff.removed(), diff.moved()), (1, 1, 0));
+        assert_eq!(diff.to_string(), "1 added, 1 removed, 0 moved stitches");
+    }
+
+    #[test]
+    fn test_threads_and_metadata() {
+        let mut a = line(&[0.0]);
+        a.add_thread(EmbThread::new(0xFF0000));
+        a.set_title("Rose");
+        a.set_metadata("build", "1");
+        let mut b = line(&[0.0]);
+        b.add_thread(EmbThread::new(0xFE0000));
+        b.add_thread(EmbThread::new(0x00FF00));
+        b.set_title("Rose v2");
+        b.set_metadata("build", "2");
+
+        let diff = PatternDiff::compare_with(&a, &b, &DiffOptions::new().ignore_metadata("build"));
+        assert!(matches!(
+            diff.threads[0],
+            ThreadChange::Changed { index: 0, .. }
+        ));
+        assert!(matches!(
+            diff.threads[1],
+            ThreadChange::Added { index: 1, .. }
+        ));
+        assert_eq!(
+            diff.metadata,
+            vec![MetadataChange {
+                key: "name".to_string(),
+                from: Some("Rose".to_string()),
+                to: Some("Rose v2".to_string()),
+            }]
+        );
+
+        let loose = PatternDiff::compare_with(&a, &b, &DiffOptions::new().color_tolerance(1.0));
+        assert_eq!(loose.threads.len(), 1);
+        assert_eq!(loose.metadata.len(), 2);
+    }
+}
@@ -0,0 +1,501 @@
+//! Undoable pattern editing
+//!
+//! [`PatternEditor`] owns a pattern and records each edit as its inverse, so
+//! undo and redo cost no more than the edit itself instead of a full pattern
+//! copy per step. Transforms store only their inverse matrix; stitch and thread
+//! edits store only the records they remove.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::editor::PatternEditor;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//!
+//! let mut editor = PatternEditor::new(pattern);
+//! editor.translate(50.0, 0.0);
+//! editor.delete_stitches(0..1)?;
+//! assert_eq!(editor.pattern().stitches().len(), 1);
+//!
+//! editor.undo();
+//! editor.undo();
+//! assert_eq!(editor.pattern().stitches()[1].x, 100.0);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::matrix::EmbMatrix;
+use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, Result};
+use std::fmt;
+use std::ops::Range;
+
+/// Kind of change made to the pattern
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditKind {
+    /// All stitches were transformed by a matrix
+    Transform,
+    /// `count` stitches were inserted at `index`
+    InsertStitches {
+        /// Index of the first inserted stitch
+        index: usize,
+        /// Number of stitches inserted
+        count: usize,
+    },
+    /// `count` stitches were deleted at `index`
+    DeleteStitches {
+        /// Index of the first deleted stitch
+        index: usize,
+        /// Number of stitches deleted
+        count: usize,
+    },
+    /// The thread at `index` was replaced
+    SetThread {
+        /// Thread index
+        index: usize,
+    },
+    /// A thread was inserted at `index`
+    InsertThread {
+        /// Thread index
+        index: usize,
+    },
+    /// The thread at `index` was removed
+    RemoveThread {
+        /// Thread index
+        index: usize,
+    },
+}
+
+/// Why a change was made
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditAction {
+    /// A new edit
+    Apply,
+    /// Undoing an earlier edit
+    Undo,
+    /// Redoing an undone edit
+    Redo,
+}
+
+/// Notification passed to change listeners
+///
+/// `kind` describes the change just made to the pattern: undoing an insert
+/// reports the deletion that reverses it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EditEvent {
+    /// The change made to the pattern
+    pub kind: EditKind,
+    /// What triggered it
+    pub action: EditAction,
+}
+
+/// Change listener registered with [`PatternEditor::on_change`]
+pub type ChangeListener = Box<dyn FnMut(&EmbPattern, &EditEvent) + Send>;
+
+/// A reversible edit, stored with the data needed to apply it
+#[derive(Debug, Clone)]
+enum Edit {
+    Transform(EmbMatrix),
+    InsertStitches(usize, Vec<Stitch>),
+    DeleteStitches(Range<usize>),
+    SetThread(usize, EmbThread),
+    InsertThread(usize, EmbThread),
+    RemoveThread(usize),
+}
+
+impl Edit {
+    /// Apply the edit, returning what was done and the edit that reverses it
+    fn apply(self, pattern: &mut EmbPattern) -> (EditKind, Edit) {
+        match self {
+            Edit::Transform(matrix) => {
+                pattern.apply_matrix(&matrix);
+                let mut inverse = matrix;
+                inverse.inverse();
+                (EditKind::Transform, Edit::Transform(inverse))
+            }
+            Edit::InsertStitches(index, stitches) => {
+                let count = stitches.len();
+                pattern.splice_stitches(index, 0, stitches);
+                (
+                    EditKind::InsertStitches { index, count },
+                    Edit::DeleteStitches(index..index + count),
+                )
+            }
+            Edit::DeleteStitches(range) => {
+                let (index, count) = (range.start, range.len());
+                let removed = pattern.splice_stitches(index, count, Vec::new());
+                (
+                    EditKind::DeleteStitches { index, count },
+                    Edit::InsertStitches(index, removed),
+                )
+            }
+            Edit::SetThread(index, thread) => {
+                let previous = std::mem::replace(&mut pattern.threads_mut()[index], thread);
+                (
+                    EditKind::SetThread { index },
+                    Edit::SetThread(index, previous),
+                )
+            }
+            Edit::InsertThread(index, thread) => {
+                pattern.insert_thread(index, thread);
+                (EditKind::InsertThread { index }, Edit::RemoveThread(index))
+            }
+            Edit::RemoveThread(index) => {
+                let thread = pattern.take_thread(index);
+                (
+                    EditKind::RemoveThread { index },
+                    Edit::InsertThread(index, thread),
+                )
+            }
+        }
+    }
+}
+
+/// Pattern wrapper with undo/redo history
+///
+/// Edits go through the editor's methods; read the pattern with
+/// [`pattern`](Self::pattern). A new edit clears the redo history. Transforms
+/// are undone by their inverse matrix, so coordinates may differ from the
+/// originals by floating-point rounding.
+pub struct PatternEditor {
+    pattern: EmbPattern,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+    history_limit: Option<usize>,
+    listeners: Vec<ChangeListener>,
+}
+
+impl fmt::Debug for PatternEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PatternEditor")
+            .field("pattern", &self.pattern)
+            .field("undo_depth", &self.undo_stack.len())
+            .field("redo_depth", &self.redo_stack.len())
+            .field("history_limit", &self.history_limit)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
+impl PatternEditor {
+    /// Start editing a pattern with unlimited history
+    pub fn new(pattern: EmbPattern) -> Self {
+        Self {
+            pattern,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            history_limit: None,
+            listeners: Vec::new(),
+        }
+    }
+
+    /// Keep at most `limit` undo steps, dropping the oldest first
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = Some(limit);
+        self.trim_history();
+        self
+    }
+
+    /// The pattern being edited
+    pub fn pattern(&self) -> &EmbPattern {
+        &self.pattern
+    }
+
+    /// Stop editing and return the pattern
+    pub fn into_pattern(self) -> EmbPattern {
+        self.pattern
+    }
+
+    /// Register a listener called after every change, including undo and redo
+    pub fn on_change(&mut self, listener: impl FnMut(&EmbPattern, &EditEvent) + Send + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Transform all stitches by a matrix
+    ///
+    /// Fails for singular matrices (e.g. a zero scale), which cannot be undone.
+    pub fn transform(&mut self, matrix: &EmbMatrix) -> Result<()> {
+        let det = matrix.determinant();
+        if det.abs() < 1e-10 || !det.is_finite() {
+            return Err(Error::InvalidPattern(
+                "Transform matrix is not invertible".to_string(),
+            ));
+        }
+        self.apply(Edit::Transform(matrix.clone()));
+        Ok(())
+    }
+
+    /// Move all stitches by an offset
+    pub fn translate(&mut self, dx: f64, dy: f64) {
+        let mut matrix = EmbMatrix::new();
+        matrix.post_translate(dx, dy);
+        self.apply(Edit::Transform(matrix));
+    }
+
+    /// Insert stitches before `index`
+    pub fn insert_stitches(&mut self, index: usize, stitches: Vec<Stitch>) -> Result<()> {
+        let len = self.pattern.stitches().len();
+        if index > len {
+            return Err(Error::InvalidPattern(format!(
+                "Stitch index {} out of range for {} stitches",
+                index, len
+            )));
+        }
+        self.apply(Edit::InsertStitches(index, stitches));
+        Ok(())
+    }
+
+    /// Delete a range of stitches
+    pub fn delete_stitches(&mut self, range: Range<usize>) -> Result<()> {
+        let len = self.pattern.stitches().len();
+        if range.start > range.end || range.end > len {
+            return Err(Error::InvalidPattern(format!(
+                "Stitch range {:?} out of range for {} stitches",
+                range, len
+            )));
+        }
+        self.apply(Edit::DeleteStitches(range));
+        Ok(())
+    }
+
+    /// Replace the thread at `index`
+    pub fn set_thread(&mut self, index: usize, thread: EmbThread) -> Result<()> {
+        if index >= self.pattern.threads().len() {
+            return Err(Error::ThreadIndexOutOfBounds(index));
+        }
+        self.apply(Edit::SetThread(index, thread));
+        Ok(())
+    }
+
+    /// Add a thread at the end of the thread list
+    pub fn add_thread(&mut self, thread: EmbThread) {
+        let index = self.pattern.threads().len();
+        self.apply(Edit::InsertThread(index, thread));
+    }
+
+    /// Remove the thread at `index`
+    pub fn remove_thread(&mut self, index: usize) -> Result<()> {
+        if index >= self.pattern.threads().len() {
+            return Err(Error::ThreadIndexOutOfBounds(index));
+        }
+        self.apply(Edit::RemoveThread(index));
+        Ok(())
+    }
+
+    /// Undo the most recent edit; returns `false` when there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo_stack.pop() else {
+            return false;
+        };
+        let (kind, inverse) = edit.apply(&mut self.pattern);
+        self.redo_stack.push(inverse);
+        self.notify(kind, EditAction::Undo);
+        true
+    }
+
+    /// Redo the most recently undone edit; returns `false` when there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo_stack.pop() else {
+            return false;
+        };
+        let (kind, inverse) = edit.apply(&mut self.pattern);
+        self.undo_stack.push(inverse);
+        self.notify(kind, EditAction::Redo);
+        true
+    }
+
+    /// Whether there is an edit to undo
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Whether there is an edit to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Forget all undo and redo history
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    fn apply(&mut self, edit: Edit) {
+        let (kind, inverse) = edit.apply(&mut self.pattern);
+        self.undo_stack.push(inverse);
+        self.redo_stack.clear();
+        self.trim_history();
+        self.notify(kind, EditAction::Apply);
+    }
+
+    fn trim_history(&mut self) {
+        if let Some(limit) = self.history_limit {
+            let excess = self.undo_stack.len().saturating_sub(limit);
+            self.undo_stack.drain(..excess);
+        }
+    }
+
+    fn notify(&mut self, kind: EditKind, action: EditAction) {
+        let event = EditEvent { kind, action };
+        for listener in &mut self.listeners {
+            listener(&self.pattern, &event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+    use std::sync::{Arc, Mutex};
+
+    fn pattern() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+        pattern.stitch_abs(100.0, 50.0);
+        pattern
+    }
+
+    fn points(editor: &PatternEditor) -> Vec<(f64, f64)> {
+        editor
+            .pattern()
+            .stitches()
+            .iter()
+            .map(|s| (s.x.round(), s.y.round()))
+            .collect()
+    }
+
+    #[test]
+    fn test_transform_undo_redo() {
+        let mut editor = PatternEditor::new(pattern());
+        let original = points(&editor);
+
+        let mut matrix = EmbMatrix::new();
+        matrix.post_rotate(90.0, 0.0, 0.0);
+        editor.transform(&matrix).unwrap();
+        editor.translate(10.0, 0.0);
+        let edited = points(&editor);
+        assert_eq!(edited[1], (10.0, 100.0));
+
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert!(!editor.undo());
+        assert_eq!(points(&editor), original);
+
+        assert!(editor.redo());
+        assert!(editor.redo());
+        assert!(!editor.can_redo());
+        assert_eq!(points(&editor), edited);
+
+        let mut singular = EmbMatrix::new();
+        singular.post_scale(0.0, None, 0.0, 0.0);
+        assert!(editor.transform(&singular).is_err());
+    }
+
+    #[test]
+    fn test_stitch_range_edits() {
+        let mut editor = PatternEditor::new(pattern());
+        let original = editor.pattern().stitches().to_vec();
+
+        editor
+            .insert_stitches(1, vec![Stitch::new(50.0, 50.0, JUMP)])
+            .unwrap();
+        assert_eq!(editor.pattern().stitches()[1].command, JUMP);
+        editor.delete_stitches(0..3).unwrap();
+        assert_eq!(editor.pattern().stitches().len(), 1);
+
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.pattern().stitches(), original.as_slice());
+
+        assert!(editor.insert_stitches(10, Vec::new()).is_err());
+        assert!(editor.delete_stitches(2..5).is_err());
+        assert!(!editor.can_undo());
+    }
+
+    #[test]
+    fn test_thread_edits() {
+        let mut editor = PatternEditor::new(pattern());
+
+        editor.set_thread(0, EmbThread::new(0x00FF00)).unwrap();
+        editor.add_thread(EmbThread::new(0x0000FF));
+        editor.remove_thread(0).unwrap();
+        let colors = |e: &PatternEditor| -> Vec<u32> {
+            e.pattern().threads().iter().map(|t| t.color).collect()
+        };
+        assert_eq!(colors(&editor), vec![0x0000FF]);
+
+        editor.undo();
+        assert_eq!(colors(&editor), vec![0x00FF00, 0x0000FF]);
+        editor.undo();
+        editor.undo();
+        assert_eq!(colors(&editor), vec![0xFF0000]);
+
+        assert!(editor.set_thread(3, EmbThread::new(0)).is_err());
+        assert!(editor.remove_thread(1).is_err());
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo_and_history_limit() {
+        let mut editor = PatternEditor::new(pattern()).with_history_limit(2);
+        editor.translate(1.0, 0.0);
+        editor.translate(2.0, 0.0);
+        editor.translate(3.0, 0.0);
+        assert!(editor.undo());
+        assert!(editor.undo());
+        assert!(!editor.undo());
+        assert_eq!(editor.pattern().stitches()[0].x, 1.0);
+
+        assert!(editor.can_redo());
+        editor.translate(5.0, 0.0);
+        assert!(!editor.can_redo());
+    }
+
+    #[test]
+    fn test_change_listener() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut editor = PatternEditor::new(pattern());
+        let log = Arc::clone(&events);
+        editor.on_change(move |pattern, event| {
+            log.lock().unwrap().push((pattern.stitches().len(), *event));
+        });
+
+        editor.delete_stitches(1..3).unwrap();
+        editor.undo();
+        editor.redo();
+
+        let kind = EditKind::DeleteStitches { index: 1, count: 2 };
+        let undo_kind = EditKind::InsertStitches { index: 1, count: 2 };
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (
+                    1,
+                    EditEvent {
+                        kind,
+                        action: EditAction::Apply
+                    }
+                ),
+                (
+                    3,
+                    EditEvent {
+                        kind: undo_kind,
+                        action: EditAction::Undo
+                    }
+                ),
+                (
+                    1,
+                    EditEvent {
+                        kind,
+                        action: EditAction::Redo
+                    }
+                ),
+            ]
+        );
+    }
+}
@@ -2,11 +2,115 @@
 //!
 //! This module provides functionality to encode patterns for writing to files,
 //! applying transformations, and handling various contingencies.
+//!
+//! [`EncoderBuilder`] assembles the settings for one target: start from
+//! [`EncoderBuilder::for_format`] to get what a format's writer needs, toggle
+//! individual constraints, then [`encode`](EncoderBuilder::encode) a pattern.
+//! The machine format writers behind
+//! [`PatternWriter`](crate::formats::io::traits::PatternWriter) run patterns
+//! through their format's encoder before writing.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::core::encoder::EncoderBuilder;
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(300.0, 0.0);
+//! pattern.add_command(CUT, 300.0, 0.0);
+//! pattern.end();
+//!
+//! let encoded = EncoderBuilder::for_format(Format::DST)
+//!     .tie_off(true)
+//!     .encode(&pattern)?;
+//! assert!(encoded.stitches().windows(2).all(|pair| (pair[1].x - pair[0].x).abs() <= 121.0));
+//! assert!(encoded.stitches().iter().all(|s| s.command & COMMAND_MASK != CUT));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
 
 use crate::core::constants::*;
 use crate::core::matrix::EmbMatrix;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteChange;
+use crate::formats::io::writers;
 use crate::utils::error::Result;
+use std::collections::BTreeMap;
+
+/// Length of tie-on and tie-off stitches (0.1mm units)
+const TIE_STITCH_LENGTH: f64 = 4.0;
+
+/// Longest move PEC long-form records encode (0.1mm units)
+const PEC_MAX_MOVE: f64 = 2047.0;
+
+/// Default longest stitch inside foam sections (0.1mm)
+pub const DEFAULT_FOAM_MAX_STITCH: f64 = 40.0;
+
+/// Action taken when a run of jumps reaches the consecutive jump limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JumpLimitAction {
+    /// Insert a trim, then continue with the remaining jumps
+    Trim,
+    /// Convert the next jump into a stitch (tack down), then continue jumping
+    Stitch,
+}
+
+/// Machine families with different tolerances for jump records
+///
+/// Used to derive sensible encoder defaults. Tajima-style machines treat a run of
+/// jumps as an implicit trim, while others need an explicit tack or trim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MachineProfile {
+    /// No machine-specific limits
+    #[default]
+    Generic,
+    /// Tajima and compatible industrial machines (DST)
+    Tajima,
+    /// Barudan industrial machines (U01, FDR)
+    Barudan,
+    /// Brother home machines (PES, PEC)
+    Brother,
+    /// Janome home machines (JEF)
+    Janome,
+    /// Melco industrial machines (EXP)
+    Melco,
+    /// Pfaff and Husqvarna Viking home machines (VP3)
+    Pfaff,
+}
+
+impl MachineProfile {
+    /// Maximum consecutive jump records the machine tolerates, if limited
+    pub fn max_consecutive_jumps(&self) -> Option<usize> {
+        match self {
+            MachineProfile::Generic => None,
+            MachineProfile::Tajima => Some(3),
+            MachineProfile::Barudan => Some(7),
+            MachineProfile::Brother | MachineProfile::Janome | MachineProfile::Pfaff => Some(6),
+            MachineProfile::Melco => Some(5),
+        }
+    }
+
+    /// Action to take when the consecutive jump limit is reached
+    pub fn jump_limit_action(&self) -> JumpLimitAction {
+        match self {
+            MachineProfile::Brother | MachineProfile::Janome | MachineProfile::Pfaff => {
+                JumpLimitAction::Stitch
+            }
+            _ => JumpLimitAction::Trim,
+        }
+    }
+
+    /// Maximum stitch and jump length in 0.1mm units
+    pub fn max_move_length(&self) -> f64 {
+        match self {
+            MachineProfile::Generic => f64::INFINITY,
+            MachineProfile::Tajima => 121.0,
+            _ => 127.0,
+        }
+    }
+}
 
 /// Encoder settings for pattern transcoding
 #[derive(Debug, Clone)]
@@ -46,6 +150,69 @@ pub struct EncoderSettings {
 
     /// Explicit trim before color change
     pub explicit_trim: bool,
+
+    /// Maximum number of consecutive jump records (None = unlimited)
+    pub max_consecutive_jumps: Option<usize>,
+
+    /// What to do when a run of jumps reaches `max_consecutive_jumps`
+    pub jump_limit_action: JumpLimitAction,
+
+    /// Convert CUT commands to TRIM
+    pub cut_to_trim: bool,
+
+    /// Commands kept besides STITCH, JUMP, END and sequins (None = keep all)
+    ///
+    /// Other commands are dropped. Sequin commands follow `sequin_contingency`.
+    pub supported_commands: Option<Vec<u32>>,
+
+    /// Longest stitch inside foam sections (0.1mm)
+    ///
+    /// Longer stitches are split so the needle perforates the foam more
+    /// densely. Trims and lock stitches are never placed inside foam sections.
+    pub foam_max_stitch: f64,
+}
+
+impl EncoderSettings {
+    /// Create settings with defaults for a specific machine profile
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::core::encoder::{EncoderSettings, JumpLimitAction, MachineProfile};
+    ///
+    /// let settings = EncoderSettings::for_machine(MachineProfile::Tajima);
+    /// assert_eq!(settings.max_consecutive_jumps, Some(3));
+    /// assert_eq!(settings.jump_limit_action, JumpLimitAction::Trim);
+    /// ```
+    pub fn for_machine(profile: MachineProfile) -> Self {
+        Self {
+            max_stitch: profile.max_move_length(),
+            max_jump: profile.max_move_length(),
+            max_consecutive_jumps: profile.max_consecutive_jumps(),
+            jump_limit_action: profile.jump_limit_action(),
+            ..Self::default()
+        }
+    }
+
+    /// Limit runs of consecutive jumps to `n` records
+    ///
+    /// Once a run reaches the limit, `then` decides whether a trim is inserted
+    /// before the remaining jumps or the next jump is converted into a stitch.
+    /// A limit of zero disables the policy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::core::encoder::{EncoderSettings, JumpLimitAction};
+    ///
+    /// let settings = EncoderSettings::default().max_consecutive_jumps(4, JumpLimitAction::Trim);
+    /// assert_eq!(settings.max_consecutive_jumps, Some(4));
+    /// ```
+    pub fn max_consecutive_jumps(mut self, n: usize, then: JumpLimitAction) -> Self {
+        self.max_consecutive_jumps = if n == 0 { None } else { Some(n) };
+        self.jump_limit_action = then;
+        self
+    }
 }
 
 impl Default for EncoderSettings {
@@ -63,14 +230,225 @@ impl Default for EncoderSettings {
             tie_off_contingency: CONTINGENCY_TIE_OFF_NONE,
             writes_speeds: true,
             explicit_trim: false,
+            max_consecutive_jumps: None,
+            jump_limit_action: JumpLimitAction::Trim,
+            cut_to_trim: false,
+            supported_commands: None,
+            foam_max_stitch: DEFAULT_FOAM_MAX_STITCH,
         }
     }
 }
 
+/// Builder for format-ready encoder settings
+///
+/// Each setter toggles one constraint of the underlying [`EncoderSettings`].
+#[derive(Debug, Clone, Default)]
+pub struct EncoderBuilder {
+    settings: EncoderSettings,
+}
+
+impl EncoderBuilder {
+    /// Create a builder that constrains nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from existing settings
+    pub fn from_settings(settings: EncoderSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Create a builder with the constraints of a format's writer
+    ///
+    /// Machine formats get their stitch and jump limits, CUT converted to TRIM
+    /// and commands the writer can't encode dropped; DST keeps sequins. U01,
+    /// XXX and TBF use their writers' `default_settings`. Other formats are
+    /// left unconstrained.
+    pub fn for_format(format: Format) -> Self {
+        let machine = |max_stitch: f64, max_jump: f64, commands: &[u32]| {
+            Self::new()
+                .max_stitch(max_stitch)
+                .max_jump(max_jump)
+                .cut_to_trim(true)
+                .supported_commands(commands)
+        };
+        match format {
+            Format::DST => machine(
+                121.0,
+                121.0,
+                &[TRIM, COLOR_CHANGE, STOP, SEQUIN_MODE, SEQUIN_EJECT],
+            )
+            .sequins(CONTINGENCY_SEQUIN_UTILIZE),
+            Format::PES | Format::PEC => machine(127.0, PEC_MAX_MOVE, &[TRIM, COLOR_CHANGE, STOP]),
+            Format::JEF | Format::EXP => machine(127.0, 127.0, &[TRIM, COLOR_CHANGE, STOP]),
+            Format::VP3 => machine(127.0, 127.0, &[TRIM, COLOR_CHANGE]),
+            Format::U01 => Self::from_settings(writers::u01::default_settings()),
+            Format::DSB => Self::from_settings(writers::dsb::default_settings()),
+            Format::DSZ => Self::from_settings(writers::dsz::default_settings()),
+            Format::ZXY => Self::from_settings(writers::zxy::default_settings()),
+            Format::HUS => Self::from_settings(writers::hus::default_settings()),
+            Format::SHV => Self::from_settings(writers::shv::default_settings()),
+            Format::XXX => Self::from_settings(writers::xxx::default_settings()),
+            Format::TBF => Self::from_settings(writers::tbf::default_settings()),
+            _ => Self::new(),
+        }
+    }
+
+    /// Set the longest stitch; longer ones follow the long stitch contingency
+    pub fn max_stitch(mut self, length: f64) -> Self {
+        self.settings.max_stitch = length;
+        self
+    }
+
+    /// Set the longest jump; longer moves are split into several jumps
+    pub fn max_jump(mut self, length: f64) -> Self {
+        self.settings.max_jump = length;
+        self
+    }
+
+    /// Set how long stitches are handled (`CONTINGENCY_LONG_STITCH_*`)
+    pub fn long_stitches(mut self, contingency: u32) -> Self {
+        self.settings.long_stitch_contingency = contingency;
+        self
+    }
+
+    /// Insert tie-on stitches where sewing starts or resumes
+    pub fn tie_on(mut self, enabled: bool) -> Self {
+        self.settings.tie_on_contingency = if enabled {
+            CONTINGENCY_TIE_ON_THREE_SMALL
+        } else {
+            CONTINGENCY_TIE_ON_NONE
+        };
+        self
+    }
+
+    /// Insert tie-off stitches before trims, color changes and the end
+    pub fn tie_off(mut self, enabled: bool) -> Self {
+        self.settings.tie_off_contingency = if enabled {
+            CONTINGENCY_TIE_OFF_THREE_SMALL
+        } else {
+            CONTINGENCY_TIE_OFF_NONE
+        };
+        self
+    }
+
+    /// Set how sequin commands are handled (`CONTINGENCY_SEQUIN_*`)
+    pub fn sequins(mut self, contingency: u32) -> Self {
+        self.settings.sequin_contingency = contingency;
+        self
+    }
+
+    /// Convert CUT commands to TRIM
+    pub fn cut_to_trim(mut self, enabled: bool) -> Self {
+        self.settings.cut_to_trim = enabled;
+        self
+    }
+
+    /// Keep only these commands besides STITCH, JUMP, END and sequins
+    pub fn supported_commands(mut self, commands: &[u32]) -> Self {
+        self.settings.supported_commands = Some(commands.to_vec());
+        self
+    }
+
+    /// Keep every command
+    pub fn keep_all_commands(mut self) -> Self {
+        self.settings.supported_commands = None;
+        self
+    }
+
+    /// Limit runs of consecutive jumps, see [`EncoderSettings::max_consecutive_jumps`]
+    pub fn max_consecutive_jumps(mut self, n: usize, then: JumpLimitAction) -> Self {
+        self.settings = self.settings.max_consecutive_jumps(n, then);
+        self
+    }
+
+    /// Set the longest stitch inside foam sections
+    pub fn foam_max_stitch(mut self, length: f64) -> Self {
+        self.settings.foam_max_stitch = length;
+        self
+    }
+
+    /// Round coordinates to whole units
+    pub fn round(mut self, round: bool) -> Self {
+        self.settings.round = round;
+        self
+    }
+
+    /// The assembled settings
+    pub fn settings(&self) -> &EncoderSettings {
+        &self.settings
+    }
+
+    /// Build a transcoder with the assembled settings
+    pub fn build(self) -> Transcoder {
+        Transcoder::with_settings(self.settings)
+    }
+
+    /// Encode a pattern into a new, format-ready pattern
+    pub fn encode(&self, pattern: &EmbPattern) -> Result<EmbPattern> {
+        self.encode_with_changes(pattern)
+            .map(|(encoded, _)| encoded)
+    }
+
+    /// Encode a pattern, returning the adjustments made along with it
+    ///
+    /// See [`Transcoder::changes`].
+    pub fn encode_with_changes(
+        &self,
+        pattern: &EmbPattern,
+    ) -> Result<(EmbPattern, Vec<WriteChange>)> {
+        let mut encoded = EmbPattern::new();
+        let mut transcoder = Transcoder::with_settings(self.settings.clone());
+        transcoder.transcode(pattern, &mut encoded)?;
+        Ok((encoded, transcoder.changes()))
+    }
+}
+
 /// Pattern encoder/transcoder
 pub struct Transcoder {
     settings: EncoderSettings,
     matrix: EmbMatrix,
+    /// Jump records emitted since the last non-jump command
+    consecutive_jumps: usize,
+    /// Whether the current jump run has already been trimmed
+    jump_run_trimmed: bool,
+    /// Last stitch of the current sewing run, `None` when not sewing
+    run_last: Option<(f64, f64)>,
+    /// Stitch before `run_last` in the current sewing run
+    run_previous: Option<(f64, f64)>,
+    /// Whether `run_last` is inside a foam section
+    run_in_foam: bool,
+    /// Adjustments of the current transcode
+    adjustments: Adjustments,
+}
+
+/// Adjustments made to fit the settings, counted as they are made
+#[derive(Debug, Clone, Default)]
+struct Adjustments {
+    /// Stitches longer than `max_stitch`
+    split_stitches: usize,
+    /// Commands written as another command, by source and written command
+    converted: BTreeMap<(u32, u32), usize>,
+    /// Commands left out
+    dropped: BTreeMap<u32, usize>,
+    /// Commands added
+    inserted: BTreeMap<u32, usize>,
+}
+
+impl Adjustments {
+    fn convert(&mut self, from: u32, to: u32) {
+        *self.converted.entry((from, to)).or_insert(0) += 1;
+    }
+
+    fn drop(&mut self, command: u32) {
+        *self.dropped.entry(command).or_insert(0) += 1;
+    }
+
+    fn insert(&mut self, command: u32, count: usize) {
+        if count > 0 {
+            *self.inserted.entry(command).or_insert(0) += count;
+        }
+    }
 }
 
 impl Transcoder {
@@ -79,6 +457,12 @@ impl Transcoder {
         Self {
             settings: EncoderSettings::default(),
             matrix: EmbMatrix::new(),
+            consecutive_jumps: 0,
+            jump_run_trimmed: false,
+            run_last: None,
+            run_previous: None,
+            run_in_foam: false,
+            adjustments: Adjustments::default(),
         }
     }
 
@@ -87,6 +471,12 @@ impl Transcoder {
         Self {
             settings,
             matrix: EmbMatrix::new(),
+            consecutive_jumps: 0,
+            jump_run_trimmed: false,
+            run_last: None,
+            run_previous: None,
+            run_in_foam: false,
+            adjustments: Adjustments::default(),
         }
     }
 
@@ -102,40 +492,79 @@ impl Transcoder {
             destination.add_thread(thread.clone());
         }
 
-        // Check if matrix is non-identity
-        let has_transform = !self.matrix.is_identity();
-
         // Process stitches with transformations
         let mut current_x = 0.0;
         let mut current_y = 0.0;
+        self.reset_jump_run();
+        self.run_last = None;
+        self.run_previous = None;
+        self.adjustments = Adjustments::default();
+
+        let foam = source.foam_sections();
+        let in_foam = |index: usize| foam.iter().any(|s| s.contains(index));
+        // Destination record count before each source record, to re-index foam sections
+        let mut offsets = Vec::with_capacity(source.stitches().len() + 1);
+
+        let stitches = source.stitches();
+        for (index, stitch) in stitches.iter().enumerate() {
+            offsets.push(destination.stitches().len());
+            let original = stitch.command & COMMAND_MASK;
+            let mut command = original;
+            if command == CUT && self.settings.cut_to_trim {
+                command = TRIM;
+            }
+            if !self.is_supported(command) {
+                self.adjustments.drop(original);
+                continue;
+            }
+            // Cut thread tails would be pulled through the foam
+            if matches!(command, TRIM | CUT) && in_foam(index) {
+                self.adjustments.drop(original);
+                continue;
+            }
+            if command != original {
+                self.adjustments.convert(original, command);
+            }
 
-        for stitch in source.stitches() {
-            let command = stitch.command & COMMAND_MASK;
-
-            // Apply matrix transformation if any
-            let (x, y) = if has_transform {
-                self.matrix.transform_point(stitch.x, stitch.y)
-            } else {
-                (stitch.x, stitch.y)
-            };
+            let (x, y) = self.position(stitch.x, stitch.y);
 
-            // Round coordinates if requested
-            let (x, y) = if self.settings.round {
-                (x.round(), y.round())
-            } else {
-                (x, y)
-            };
+            if matches!(command, TRIM | CUT | COLOR_CHANGE | NEEDLE_SET | END) {
+                self.tie_off(destination);
+            }
 
             match command {
                 STITCH => {
-                    self.handle_stitch(destination, &mut current_x, &mut current_y, x, y)?;
+                    let starting = self.run_last.is_none();
+                    let foam_stitch = in_foam(index);
+                    let max_foam = self.settings.foam_max_stitch.min(self.settings.max_stitch);
+                    if foam_stitch && (x - current_x).hypot(y - current_y) > max_foam {
+                        self.sew_to(destination, &mut current_x, &mut current_y, x, y, max_foam)?;
+                    } else {
+                        self.handle_stitch(destination, &mut current_x, &mut current_y, x, y)?;
+                    }
+                    self.run_previous = self.run_last.replace((x, y));
+                    self.run_in_foam = foam_stitch;
+                    if starting && !foam_stitch {
+                        let next = stitches[index + 1..]
+                            .iter()
+                            .filter(|s| s.command & COMMAND_MASK == STITCH)
+                            .map(|s| self.position(s.x, s.y))
+                            .find(|&(nx, ny)| nx != x || ny != y);
+                        self.tie_on(destination, x, y, next);
+                    }
                 }
                 JUMP => {
                     self.handle_move(destination, &mut current_x, &mut current_y, x, y)?;
                 }
+                TRIM | CUT => {
+                    destination.add_command(command, x, y);
+                    current_x = x;
+                    current_y = y;
+                }
                 COLOR_CHANGE => {
                     if self.settings.explicit_trim {
                         destination.add_command(TRIM, current_x, current_y);
+                        self.adjustments.insert(TRIM, 1);
                     }
                     destination.add_command(command, x, y);
                     current_x = x;
@@ -146,6 +575,12 @@ impl Transcoder {
                     current_x = x;
                     current_y = y;
                 }
+                CHENILLE_LOOP_HEIGHT | CORDING_ON | CORDING_OFF | BORER_ON | BORER_OFF => {
+                    // Keep the flags byte, it carries the loop height
+                    destination.add_command(stitch.command, x, y);
+                    current_x = x;
+                    current_y = y;
+                }
                 _ => {
                     // Handle other commands
                     if command == SEQUIN_MODE || command == SEQUIN_EJECT {
@@ -160,14 +595,159 @@ impl Transcoder {
                     current_y = y;
                 }
             }
+
+            if command != JUMP {
+                self.reset_jump_run();
+            }
+        }
+        offsets.push(destination.stitches().len());
+
+        // Attributes go to the record emitted at their source record's position
+        for (index, attributes) in source.attributes().iter() {
+            let emitted =
+                offsets[index.min(stitches.len())]..offsets[(index + 1).min(stitches.len())];
+            if emitted.is_empty() {
+                continue;
+            }
+            let stitch = stitches[index];
+            let (x, y) = self.position(stitch.x, stitch.y);
+            let mut command = stitch.command & COMMAND_MASK;
+            if command == CUT && self.settings.cut_to_trim {
+                command = TRIM;
+            }
+            let target = emitted
+                .clone()
+                .find(|&i| {
+                    let record = destination.stitches()[i];
+                    record.command & COMMAND_MASK == command && (record.x, record.y) == (x, y)
+                })
+                .unwrap_or(emitted.end - 1);
+            for (key, value) in attributes {
+                destination
+                    .attributes_mut()
+                    .set(target, key.clone(), value.clone());
+            }
         }
 
+        if !foam.is_empty() {
+            destination.clear_foam();
+            for section in &foam {
+                let start = offsets[section.start.min(stitches.len())];
+                let end = offsets[section.end.min(stitches.len())];
+                if start < end {
+                    destination.mark_foam(start..end, section.thickness)?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Position of a source point after the matrix and rounding
+    fn position(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = if self.matrix.is_identity() {
+            (x, y)
+        } else {
+            self.matrix.transform_point(x, y)
+        };
+        if self.settings.round {
+            (x.round(), y.round())
+        } else {
+            (x, y)
+        }
+    }
+
+    /// Whether a command survives the supported command filter
+    fn is_supported(&self, command: u32) -> bool {
+        match &self.settings.supported_commands {
+            None => true,
+            Some(commands) => {
+                matches!(command, STITCH | JUMP | END | SEQUIN_MODE | SEQUIN_EJECT)
+                    || commands.contains(&command)
+            }
+        }
+    }
+
+    /// Emit tie-on stitches at the start of a sewing run
+    ///
+    /// Steps a stitch length toward the next stitch and back.
+    fn tie_on(&mut self, destination: &mut EmbPattern, x: f64, y: f64, next: Option<(f64, f64)>) {
+        if self.settings.tie_on_contingency != CONTINGENCY_TIE_ON_THREE_SMALL {
+            return;
+        }
+        let (ux, uy) = next.map_or((1.0, 0.0), |(nx, ny)| unit(nx - x, ny - y));
+        destination.add_stitch_absolute(
+            STITCH,
+            x + ux * TIE_STITCH_LENGTH,
+            y + uy * TIE_STITCH_LENGTH,
+        );
+        destination.add_stitch_absolute(STITCH, x, y);
+        self.adjustments.insert(STITCH, 2);
+    }
+
+    /// Emit tie-off stitches at the end of a sewing run and close the run
+    ///
+    /// Steps a stitch length back along the last stitch and returns.
+    fn tie_off(&mut self, destination: &mut EmbPattern) {
+        let Some((x, y)) = self.run_last.take() else {
+            return;
+        };
+        let previous = self.run_previous.take();
+        if self.settings.tie_off_contingency != CONTINGENCY_TIE_OFF_THREE_SMALL || self.run_in_foam
+        {
+            return;
+        }
+        let (ux, uy) = previous.map_or((-1.0, 0.0), |(px, py)| unit(px - x, py - y));
+        destination.add_stitch_absolute(
+            STITCH,
+            x + ux * TIE_STITCH_LENGTH,
+            y + uy * TIE_STITCH_LENGTH,
+        );
+        destination.add_stitch_absolute(STITCH, x, y);
+        self.adjustments.insert(STITCH, 2);
+    }
+
+    /// Reset consecutive jump tracking after a non-jump command
+    fn reset_jump_run(&mut self) {
+        self.consecutive_jumps = 0;
+        self.jump_run_trimmed = false;
+    }
+
+    /// Emit a single jump record, applying the consecutive jump policy
+    fn emit_jump(
+        &mut self,
+        destination: &mut EmbPattern,
+        from_x: f64,
+        from_y: f64,
+        x: f64,
+        y: f64,
+    ) {
+        if let Some(limit) = self.settings.max_consecutive_jumps {
+            if self.consecutive_jumps >= limit {
+                match self.settings.jump_limit_action {
+                    JumpLimitAction::Trim => {
+                        if !self.jump_run_trimmed {
+                            destination.add_command(TRIM, from_x, from_y);
+                            self.adjustments.insert(TRIM, 1);
+                            self.jump_run_trimmed = true;
+                        }
+                    }
+                    JumpLimitAction::Stitch => {
+                        destination.add_stitch_absolute(STITCH, x, y);
+                        self.adjustments.convert(JUMP, STITCH);
+                        self.consecutive_jumps = 0;
+                        return;
+                    }
+                }
+            }
+        }
+
+        destination.add_stitch_absolute(JUMP, x, y);
+        self.consecutive_jumps += 1;
+    }
+
     /// Handle a stitch command with long stitch contingency
     fn handle_stitch(
-        &self,
+        &mut self,
         destination: &mut EmbPattern,
         current_x: &mut f64,
         current_y: &mut f64,
@@ -180,6 +760,7 @@ impl Transcoder {
 
         // Guard against NaN and infinity
         if !distance.is_finite() {
+            self.adjustments.drop(STITCH);
             *current_x = target_x;
             *current_y = target_y;
             return Ok(());
@@ -190,12 +771,23 @@ impl Transcoder {
             match self.settings.long_stitch_contingency {
                 CONTINGENCY_LONG_STITCH_JUMP_NEEDLE => {
                     // Jump to position with needle
+                    self.adjustments.split_stitches += 1;
+                    self.adjustments.insert(JUMP, 1);
                     self.handle_move(destination, current_x, current_y, target_x, target_y)?;
                     destination.add_stitch_absolute(STITCH, target_x, target_y);
                 }
                 CONTINGENCY_LONG_STITCH_SEW_TO => {
                     // Sew incrementally to target
-                    self.sew_to(destination, current_x, current_y, target_x, target_y)?;
+                    self.adjustments.split_stitches += 1;
+                    let max_stitch = self.settings.max_stitch;
+                    self.sew_to(
+                        destination,
+                        current_x,
+                        current_y,
+                        target_x,
+                        target_y,
+                        max_stitch,
+                    )?;
                 }
                 _ => {
                     // Default: just add the stitch
@@ -213,7 +805,7 @@ impl Transcoder {
 
     /// Handle a move/jump command
     fn handle_move(
-        &self,
+        &mut self,
         destination: &mut EmbPattern,
         current_x: &mut f64,
         current_y: &mut f64,
@@ -226,6 +818,7 @@ impl Transcoder {
 
         // Guard against NaN and infinity
         if !distance.is_finite() {
+            self.adjustments.drop(JUMP);
             *current_x = target_x;
             *current_y = target_y;
             return Ok(());
@@ -236,14 +829,17 @@ impl Transcoder {
             let steps = (distance / self.settings.max_jump).ceil() as usize;
             let step_x = dx / steps as f64;
             let step_y = dy / steps as f64;
+            self.adjustments.insert(JUMP, steps - 1);
 
             for i in 1..=steps {
+                let from_x = *current_x + step_x * (i - 1) as f64;
+                let from_y = *current_y + step_y * (i - 1) as f64;
                 let jump_x = *current_x + step_x * i as f64;
                 let jump_y = *current_y + step_y * i as f64;
-                destination.add_stitch_absolute(JUMP, jump_x, jump_y);
+                self.emit_jump(destination, from_x, from_y, jump_x, jump_y);
             }
         } else {
-            destination.add_stitch_absolute(JUMP, target_x, target_y);
+            self.emit_jump(destination, *current_x, *current_y, target_x, target_y);
         }
 
         *current_x = target_x;
@@ -251,7 +847,7 @@ impl Transcoder {
         Ok(())
     }
 
-    /// Sew incrementally to a target position
+    /// Sew incrementally to a target position in stitches of at most `max_stitch`
     fn sew_to(
         &self,
         destination: &mut EmbPattern,
@@ -259,20 +855,21 @@ impl Transcoder {
         current_y: &mut f64,
         target_x: f64,
         target_y: f64,
+        max_stitch: f64,
     ) -> Result<()> {
         let dx = target_x - *current_x;
         let dy = target_y - *current_y;
         let distance = (dx * dx + dy * dy).sqrt();
 
         // Guard against NaN, infinity, and division by zero
-        if !distance.is_finite() || distance == 0.0 || self.settings.max_stitch <= 0.0 {
+        if !distance.is_finite() || distance == 0.0 || max_stitch <= 0.0 {
             destination.add_stitch_absolute(STITCH, target_x, target_y);
             *current_x = target_x;
             *current_y = target_y;
             return Ok(());
         }
 
-        let steps = (distance / self.settings.max_stitch).ceil() as usize;
+        let steps = (distance / max_stitch).ceil() as usize;
         let steps = steps.clamp(1, 10000); // Prevent excessive loops
 
         if steps <= 1 {
@@ -295,7 +892,7 @@ impl Transcoder {
 
     /// Handle sequin commands based on contingency setting
     fn handle_sequin(
-        &self,
+        &mut self,
         destination: &mut EmbPattern,
         command: u32,
         x: f64,
@@ -310,16 +907,23 @@ impl Transcoder {
                 // Convert sequin to jump
                 if command == SEQUIN_EJECT {
                     destination.add_command(TRIM, x, y);
+                    self.adjustments.convert(command, TRIM);
+                } else {
+                    self.adjustments.drop(command);
                 }
             }
             CONTINGENCY_SEQUIN_STITCH => {
                 // Convert sequin to stitch
                 if command == SEQUIN_EJECT {
                     destination.add_stitch_absolute(STITCH, x, y);
+                    self.adjustments.convert(command, STITCH);
+                } else {
+                    self.adjustments.drop(command);
                 }
             }
             CONTINGENCY_SEQUIN_REMOVE => {
                 // Simply ignore sequin commands
+                self.adjustments.drop(command);
             }
             _ => {
                 destination.add_command(command, x, y);
@@ -342,6 +946,49 @@ impl Transcoder {
     pub fn settings(&self) -> &EncoderSettings {
         &self.settings
     }
+
+    /// Adjustments the last transcode made to fit the settings
+    ///
+    /// Long stitches split, commands converted, and commands dropped or
+    /// inserted, such as the jumps splitting a long move.
+    pub fn changes(&self) -> Vec<WriteChange> {
+        let adjustments = &self.adjustments;
+        let mut changes = Vec::new();
+        if adjustments.split_stitches > 0 {
+            changes.push(WriteChange::SplitStitches {
+                count: adjustments.split_stitches,
+                max_length: self.settings.max_stitch,
+            });
+        }
+        for (&(from, to), &count) in &adjustments.converted {
+            changes.push(WriteChange::Converted {
+                from: command_name(from),
+                to: command_name(to),
+                count,
+            });
+        }
+        let commands: std::collections::BTreeSet<u32> = adjustments
+            .dropped
+            .keys()
+            .chain(adjustments.inserted.keys())
+            .copied()
+            .collect();
+        for command in commands {
+            if let Some(&count) = adjustments.inserted.get(&command) {
+                changes.push(WriteChange::Inserted {
+                    command: command_name(command),
+                    count,
+                });
+            }
+            if let Some(&count) = adjustments.dropped.get(&command) {
+                changes.push(WriteChange::Dropped {
+                    command: command_name(command),
+                    count,
+                });
+            }
+        }
+        changes
+    }
 }
 
 impl Default for Transcoder {
@@ -350,6 +997,16 @@ impl Default for Transcoder {
     }
 }
 
+/// Unit vector of a direction, +x for a zero-length one
+fn unit(dx: f64, dy: f64) -> (f64, f64) {
+    let length = dx.hypot(dy);
+    if length > 0.0 && length.is_finite() {
+        (dx / length, dy / length)
+    } else {
+        (1.0, 0.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -453,4 +1110,218 @@ mod tests {
             Some("Test Pattern")
         );
     }
+
+    fn jump_run_source() -> EmbPattern {
+        let mut source = EmbPattern::new();
+        source.add_stitch_absolute(STITCH, 0.0, 0.0);
+        for i in 1..=6 {
+            source.add_stitch_absolute(JUMP, i as f64 * 10.0, 0.0);
+        }
+        source.add_stitch_absolute(STITCH, 70.0, 0.0);
+        source.end();
+        source
+    }
+
+    #[test]
+    fn test_max_consecutive_jumps_trim() {
+        let source = jump_run_source();
+        let mut destination = EmbPattern::new();
+        let settings = EncoderSettings::default().max_consecutive_jumps(3, JumpLimitAction::Trim);
+        let mut transcoder = Transcoder::with_settings(settings);
+
+        transcoder.transcode(&source, &mut destination).unwrap();
+
+        let commands: Vec<u32> = destination.stitches().iter().map(|s| s.command).collect();
+        assert_eq!(
+            commands,
+            vec![STITCH, JUMP, JUMP, JUMP, TRIM, JUMP, JUMP, JUMP, STITCH, END]
+        );
+    }
+
+    #[test]
+    fn test_max_consecutive_jumps_stitch() {
+        let source = jump_run_source();
+        let mut destination = EmbPattern::new();
+        let settings = EncoderSettings::default().max_consecutive_jumps(2, JumpLimitAction::Stitch);
+        let mut transcoder = Transcoder::with_settings(settings);
+
+        transcoder.transcode(&source, &mut destination).unwrap();
+
+        let commands: Vec<u32> = destination.stitches().iter().map(|s| s.command).collect();
+        assert_eq!(
+            commands,
+            vec![STITCH, JUMP, JUMP, STITCH, JUMP, JUMP, STITCH, STITCH, END]
+        );
+        // Positions are preserved
+        assert_eq!(destination.stitches()[3].x, 30.0);
+    }
+
+    #[test]
+    fn test_max_consecutive_jumps_counts_split_jumps() {
+        let mut source = EmbPattern::new();
+        source.add_stitch_absolute(STITCH, 0.0, 0.0);
+        source.add_stitch_absolute(JUMP, 500.0, 0.0);
+        source.add_stitch_absolute(STITCH, 500.0, 0.0);
+
+        let mut destination = EmbPattern::new();
+        let mut transcoder =
+            Transcoder::with_settings(EncoderSettings::for_machine(MachineProfile::Tajima));
+
+        transcoder.transcode(&source, &mut destination).unwrap();
+
+        let trims = destination
+            .stitches()
+            .iter()
+            .filter(|s| s.command == TRIM)
+            .count();
+        assert_eq!(trims, 1);
+    }
+
+    #[test]
+    fn test_machine_profile_defaults() {
+        assert_eq!(MachineProfile::Generic.max_consecutive_jumps(), None);
+        assert_eq!(MachineProfile::Tajima.max_consecutive_jumps(), Some(3));
+        assert_eq!(
+            MachineProfile::Brother.jump_limit_action(),
+            JumpLimitAction::Stitch
+        );
+
+        let settings = EncoderSettings::default();
+        assert_eq!(settings.max_consecutive_jumps, None);
+    }
+
+    #[test]
+    fn test_builder_for_format() {
+        let dst = EncoderBuilder::for_format(Format::DST);
+        assert_eq!(dst.settings().max_stitch, 121.0);
+        assert!(dst.settings().cut_to_trim);
+        assert_eq!(
+            dst.settings().sequin_contingency,
+            CONTINGENCY_SEQUIN_UTILIZE
+        );
+        assert_eq!(
+            EncoderBuilder::for_format(Format::PES).settings().max_jump,
+            PEC_MAX_MOVE
+        );
+        let json = EncoderBuilder::for_format(Format::JSON);
+        assert!(!json.settings().cut_to_trim);
+        assert_eq!(json.settings().supported_commands, None);
+
+        let settings = EncoderBuilder::new()
+            .max_stitch(50.0)
+            .tie_on(true)
+            .build()
+            .settings()
+            .clone();
+        assert_eq!(settings.max_stitch, 50.0);
+        assert_eq!(settings.tie_on_contingency, CONTINGENCY_TIE_ON_THREE_SMALL);
+    }
+
+    #[test]
+    fn test_encode_converts_and_strips_commands() {
+        let mut source = EmbPattern::new();
+        source.add_stitch_absolute(STITCH, 0.0, 0.0);
+        source.add_stitch_absolute(STITCH, 10.0, 0.0);
+        source.add_stitch_absolute(CUT, 10.0, 0.0);
+        source.add_stitch_absolute(STOP, 10.0, 0.0);
+        source.add_stitch_absolute(FRAME_EJECT, 10.0, 0.0);
+        source.add_stitch_absolute(STITCH, 20.0, 0.0);
+        source.add_stitch_absolute(END, 20.0, 0.0);
+
+        let encoded = EncoderBuilder::for_format(Format::VP3)
+            .encode(&source)
+            .unwrap();
+        let commands: Vec<u32> = encoded.stitches().iter().map(|s| s.command).collect();
+        assert_eq!(commands, vec![STITCH, STITCH, TRIM, STITCH, END]);
+
+        let kept = EncoderBuilder::new().encode(&source).unwrap();
+        assert_eq!(kept.count_stitches(), source.count_stitches());
+        assert!(kept.stitches().iter().any(|s| s.command == CUT));
+    }
+
+    #[test]
+    fn test_tie_on_and_tie_off() {
+        let mut source = EmbPattern::new();
+        source.add_stitch_absolute(STITCH, 0.0, 0.0);
+        source.add_stitch_absolute(STITCH, 0.0, 20.0);
+        source.add_stitch_absolute(TRIM, 0.0, 20.0);
+        source.add_stitch_absolute(STITCH, 50.0, 50.0);
+        source.add_stitch_absolute(STITCH, 50.0, 50.0);
+        source.add_stitch_absolute(STITCH, 80.0, 50.0);
+        source.add_stitch_absolute(END, 80.0, 50.0);
+
+        let encoded = EncoderBuilder::new()
+            .tie_on(true)
+            .tie_off(true)
+            .encode(&source)
+            .unwrap();
+        // Two runs, each gaining two tie-on and two tie-off stitches
+        assert_eq!(encoded.count_stitches(), 5 + 8);
+
+        let points: Vec<(f64, f64)> = encoded.stitches().iter().map(|s| (s.x, s.y)).collect();
+        // Tie-on steps toward the next stitch, tie-off back along the last one
+        assert_eq!(points[1], (0.0, TIE_STITCH_LENGTH));
+        assert_eq!(points[4], (0.0, 20.0 - TIE_STITCH_LENGTH));
+        assert_eq!(points[8], (50.0 + TIE_STITCH_LENGTH, 50.0));
+
+        let plain = EncoderBuilder::new().tie_on(true).encode(&source).unwrap();
+        assert_eq!(plain.count_stitches(), 5 + 4);
+    }
+
+    #[test]
+    fn test_foam_sections() {
+        let mut source = EmbPattern::new();
+        source.add_stitch_absolute(STITCH, 0.0, 0.0);
+        source.add_stitch_absolute(STITCH, 20.0, 0.0);
+        source.add_stitch_absolute(TRIM, 20.0, 0.0);
+        source.add_stitch_absolute(JUMP, 0.0, 50.0);
+        // Over foam: a 10mm stitch and a trim between two satin passes
+        source.add_stitch_absolute(STITCH, 0.0, 50.0);
+        source.add_stitch_absolute(STITCH, 100.0, 50.0);
+        source.add_stitch_absolute(TRIM, 100.0, 50.0);
+        source.add_stitch_absolute(STITCH, 100.0, 60.0);
+        source.add_stitch_absolute(END, 100.0, 60.0);
+        source.mark_foam(4..8, 30.0).unwrap();
+
+        let encoded = EncoderBuilder::new()
+            .tie_on(true)
+            .tie_off(true)
+            .encode(&source)
+            .unwrap();
+        // Only the flat run is trimmed and locked
+        assert_eq!(encoded.count_trims(), 1);
+        assert_eq!(encoded.count_stitches(), 2 + 4 + 1 + 3 + 1);
+
+        let sections = encoded.foam_sections();
+        assert_eq!(sections.len(), 1);
+        let section = sections[0];
+        assert_eq!((section.end - section.start, section.thickness), (5, 30.0));
+        let foam = &encoded.stitches()[section.start..section.end];
+        assert_eq!((foam[0].x, foam[0].y), (0.0, 50.0));
+        assert!(foam
+            .windows(2)
+            .all(|pair| pair[0].distance_to(&pair[1]) <= DEFAULT_FOAM_MAX_STITCH));
+        assert_eq!(encoded.stitches()[section.end].command, END);
+    }
+
+    #[test]
+    fn test_stitch_attributes_follow_encoding() {
+        let mut source = EmbPattern::new();
+        source.add_stitch_absolute(STITCH, 0.0, 0.0);
+        source.add_stitch_absolute(STITCH, 300.0, 0.0);
+        source.add_stitch_absolute(CUT, 300.0, 0.0);
+        source.add_stitch_absolute(END, 300.0, 0.0);
+        source.set_stitch_attribute(1, "note", "corner").unwrap();
+        source.set_stitch_attribute(2, "note", "cut").unwrap();
+
+        let encoded = EncoderBuilder::for_format(Format::DST)
+            .tie_off(true)
+            .encode(&source)
+            .unwrap();
+        let annotated = encoded.stitches_with_attribute("note");
+        assert_eq!(annotated.len(), 2);
+        let corner = encoded.stitches()[annotated[0]];
+        assert_eq!((corner.x, corner.y, corner.command), (300.0, 0.0, STITCH));
+        assert_eq!(encoded.stitches()[annotated[1]].command, TRIM);
+    }
 }
@@ -0,0 +1,486 @@
+//! Tatami fill stitch generation for closed shapes
+//!
+//! Fills a shape with parallel rows of running stitches. Rows run at a configurable
+//! angle and spacing, and needle points on neighbouring rows are staggered so they
+//! form the brick-like tatami texture instead of visible channels. An optional
+//! underlay of sparser rows at another angle is stitched first to stabilize the
+//! fabric; edge runs and other [`Underlay`] layers can be stitched before that.
+//!
+//! Shapes are lists of polygons in pattern units (0.1mm): the first polygon is the
+//! outer boundary and any further polygons are holes. Rows are clipped with the
+//! even-odd rule, so polygon orientation doesn't matter.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::fill::TatamiFill;
+//!
+//! let square = vec![(0.0, 0.0), (200.0, 0.0), (200.0, 200.0), (0.0, 200.0)];
+//! let hole = vec![(80.0, 80.0), (120.0, 80.0), (120.0, 120.0), (80.0, 120.0)];
+//!
+//! let mut pattern = EmbPattern::new();
+//! TatamiFill::new()
+//!     .angle(45.0)
+//!     .row_spacing(4.0)
+//!     .fill(&mut pattern, &[square, hole])?;
+//! assert!(pattern.count_stitches() > 500);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::core::underlay::Underlay;
+use crate::geometry::{offset_polyline, Point};
+use crate::utils::error::{Error, Result};
+
+/// Upper bound on rows per pass, to catch unit mistakes before allocating
+const MAX_FILL_ROWS: f64 = 1_000_000.0;
+
+/// Needle points closer than this fraction of the stitch length to a row end are dropped
+const MIN_STITCH_FRACTION: f64 = 0.25;
+
+/// Sparse rows stitched before the fill to hold the fabric flat
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillUnderlay {
+    /// Row angle relative to the fill angle in degrees (default: 90)
+    pub angle_offset: f64,
+    /// Distance between rows in 0.1mm (default: 20.0)
+    pub row_spacing: f64,
+    /// Stitch length in 0.1mm (default: 30.0)
+    pub stitch_length: f64,
+    /// Distance kept from the shape edge in 0.1mm (default: 10.0)
+    pub inset: f64,
+}
+
+impl Default for FillUnderlay {
+    fn default() -> Self {
+        Self {
+            angle_offset: 90.0,
+            row_spacing: 20.0,
+            stitch_length: 30.0,
+            inset: 10.0,
+        }
+    }
+}
+
+/// Tatami fill settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct TatamiFill {
+    /// Row angle in degrees, counter-clockwise from the X axis (default: 0)
+    pub angle: f64,
+    /// Distance between rows in 0.1mm (default: 4.0)
+    pub row_spacing: f64,
+    /// Stitch length in 0.1mm (default: 30.0)
+    pub stitch_length: f64,
+    /// Number of rows before the needle points line up again (default: 4)
+    pub stagger: u32,
+    /// Underlay stitched before the fill (default: `FillUnderlay::default()`)
+    pub underlay: Option<FillUnderlay>,
+    /// Further underlay layers stitched before `underlay`, in order (default: none)
+    pub underlay_layers: Vec<Underlay>,
+}
+
+impl Default for TatamiFill {
+    fn default() -> Self {
+        Self {
+            angle: 0.0,
+            row_spacing: 4.0,
+            stitch_length: 30.0,
+            stagger: 4,
+            underlay: Some(FillUnderlay::default()),
+            underlay_layers: Vec::new(),
+        }
+    }
+}
+
+impl TatamiFill {
+    /// Create a fill with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the row angle in degrees
+    pub fn angle(mut self, angle: f64) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    /// Set the distance between rows in 0.1mm
+    pub fn row_spacing(mut self, row_spacing: f64) -> Self {
+        self.row_spacing = row_spacing;
+        self
+    }
+
+    /// Set the stitch length in 0.1mm
+    pub fn stitch_length(mut self, stitch_length: f64) -> Self {
+        self.stitch_length = stitch_length;
+        self
+    }
+
+    /// Set the number of rows in one stagger cycle (1 disables staggering)
+    pub fn stagger(mut self, stagger: u32) -> Self {
+        self.stagger = stagger.max(1);
+        self
+    }
+
+    /// Set the underlay, or `None` to stitch the fill directly
+    pub fn underlay(mut self, underlay: Option<FillUnderlay>) -> Self {
+        self.underlay = underlay;
+        self
+    }
+
+    /// Add an underlay layer, stitched before the fill's own underlay
+    pub fn add_underlay(mut self, layer: Underlay) -> Self {
+        self.underlay_layers.push(layer);
+        self
+    }
+
+    /// Generate the needle points of a shape as connected runs
+    ///
+    /// Each run is stitched without lifting the needle; moving between runs needs a
+    /// jump. Underlay runs come first.
+    pub fn runs(&self, shape: &[Vec<Point>]) -> Result<Vec<Vec<Point>>> {
+        if shape.first().is_none_or(|outer| outer.len() < 3) {
+            return Err(Error::InvalidPattern(
+                "Fill shape needs an outer polygon with at least 3 points".to_string(),
+            ));
+        }
+
+        let mut runs = Vec::new();
+        for layer in &self.underlay_layers {
+            runs.extend(layer.shape_runs(shape)?);
+        }
+        if let Some(underlay) = &self.underlay {
+            check_spacing(underlay.row_spacing, underlay.stitch_length)?;
+            runs.extend(scan_runs(
+                &inset_shape(shape, underlay.inset),
+                self.angle + underlay.angle_offset,
+                underlay.row_spacing,
+                underlay.stitch_length,
+                1,
+            )?);
+        }
+
+        check_spacing(self.row_spacing, self.stitch_length)?;
+        runs.extend(scan_runs(
+            shape,
+            self.angle,
+            self.row_spacing,
+            self.stitch_length,
+            self.stagger.max(1),
+        )?);
+        Ok(runs)
+    }
+
+    /// Append the fill of a shape to a pattern
+    ///
+    /// Runs are joined with a trim and a jump; the first run jumps from the
+    /// pattern's last stitch unless it already starts there.
+    pub fn fill(&self, pattern: &mut EmbPattern, shape: &[Vec<Point>]) -> Result<()> {
+        for run in self.runs(shape)? {
+            append_run(pattern, &run);
+        }
+        Ok(())
+    }
+}
+
+/// Shrink a shape by `inset` (0.1mm): the outer polygon moves in, holes grow
+pub(crate) fn inset_shape(shape: &[Vec<Point>], inset: f64) -> Vec<Vec<Point>> {
+    if inset <= 0.0 {
+        return shape.to_vec();
+    }
+    let inset = inset / 10.0;
+    shape
+        .iter()
+        .enumerate()
+        .map(|(i, polygon)| offset_polyline(polygon, if i == 0 { -inset } else { inset }, true))
+        .collect()
+}
+
+/// Stitch a run of needle points, trimming and jumping to its start if needed
+pub(crate) fn append_run(pattern: &mut EmbPattern, run: &[Point]) {
+    let Some(&(x, y)) = run.first() else {
+        return;
+    };
+    match pattern.stitches().last() {
+        Some(last) if (last.x - x).hypot(last.y - y) < 0.01 => {}
+        Some(_) => {
+            pattern.trim();
+            pattern.jump_abs(x, y);
+        }
+        None => pattern.jump_abs(x, y),
+    }
+    for &(x, y) in run {
+        pattern.stitch_abs(x, y);
+    }
+}
+
+fn check_spacing(row_spacing: f64, stitch_length: f64) -> Result<()> {
+    if !(row_spacing > 0.0 && row_spacing.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Fill row spacing must be positive, got {}",
+            row_spacing
+        )));
+    }
+    if !(stitch_length > 0.0 && stitch_length.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Fill stitch length must be positive, got {}",
+            stitch_length
+        )));
+    }
+    Ok(())
+}
+
+/// Row segment `(row index, start, end)` in rotated coordinates
+type RowSegment = (i64, f64, f64);
+
+/// Scanline fill of a shape, rows grouped into boustrophedon runs
+pub(crate) fn scan_runs(
+    shape: &[Vec<Point>],
+    angle: f64,
+    spacing: f64,
+    stitch_length: f64,
+    stagger: u32,
+) -> Result<Vec<Vec<Point>>> {
+    // Rotate so rows are horizontal: u along the row, v across rows
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let to_row_space = |(x, y): Point| (x * cos + y * sin, -x * sin + y * cos);
+    let from_row_space = |(u, v): Point| (u * cos - v * sin, u * sin + v * cos);
+
+    let edges: Vec<(Point, Point)> = shape
+        .iter()
+        .filter(|polygon| polygon.len() >= 3)
+        .flat_map(|polygon| {
+            (0..polygon.len()).map(move |i| (polygon[i], polygon[(i + 1) % polygon.len()]))
+        })
+        .map(|(a, b)| (to_row_space(a), to_row_space(b)))
+        .filter(|(a, b)| a.1 != b.1)
+        .collect();
+    let (min_v, max_v) = edges
+        .iter()
+        .flat_map(|(a, b)| [a.1, b.1])
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+            (lo.min(v), hi.max(v))
+        });
+    if edges.is_empty() || !(min_v.is_finite() && max_v.is_finite()) {
+        return Ok(Vec::new());
+    }
+    if (max_v - min_v) / spacing > MAX_FILL_ROWS {
+        return Err(Error::InvalidPattern(format!(
+            "Fill needs more than {} rows; check the row spacing units",
+            MAX_FILL_ROWS
+        )));
+    }
+
+    // Rows sit on a global grid so neighbouring shapes line up
+    let first_row = ((min_v - spacing / 2.0) / spacing).ceil() as i64;
+    let mut rows: Vec<Vec<RowSegment>> = Vec::new();
+    let mut row = first_row;
+    loop {
+        let v = row as f64 * spacing + spacing / 2.0;
+        if v >= max_v {
+            break;
+        }
+        let mut crossings: Vec<f64> = edges
+            .iter()
+            .filter(|(a, b)| (a.1 <= v) != (b.1 <= v))
+            .map(|(a, b)| a.0 + (v - a.1) * (b.0 - a.0) / (b.1 - a.1))
+            .collect();
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        rows.push(
+            crossings
+                .chunks_exact(2)
+                .filter(|pair| pair[1] - pair[0] > 1e-6)
+                .map(|pair| (row, pair[0], pair[1]))
+                .collect(),
+        );
+        row += 1;
+    }
+
+    let mut runs = Vec::new();
+    for segments in group_rows(rows) {
+        let mut points: Vec<Point> = Vec::new();
+        for (i, &(row, start, end)) in segments.iter().enumerate() {
+            let v = row as f64 * spacing + spacing / 2.0;
+            let mut needle = row_needle_points(row, start, end, stitch_length, stagger);
+            if i % 2 == 1 {
+                needle.reverse();
+            }
+            // Step over to the next row along the edge
+            if let (Some(&(last_u, last_v)), Some(&first)) = (points.last(), needle.first()) {
+                let steps = ((first - last_u).hypot(spacing) / stitch_length).ceil() as usize;
+                for step in 1..steps {
+                    let t = step as f64 / steps as f64;
+                    points.push((last_u + (first - last_u) * t, last_v + (v - last_v) * t));
+                }
+            }
+            points.extend(needle.into_iter().map(|u| (u, v)));
+        }
+        runs.push(points.into_iter().map(from_row_space).collect());
+    }
+    Ok(runs)
+}
+
+/// Needle positions along one row, from `start` to `end`
+///
+/// Interior points sit on a grid shifted by `1 / stagger` of a stitch per row,
+/// which produces the diagonal tatami texture.
+fn row_needle_points(row: i64, start: f64, end: f64, stitch_length: f64, stagger: u32) -> Vec<f64> {
+    let offset = row.rem_euclid(stagger as i64) as f64 * stitch_length / stagger as f64;
+    let min_gap = stitch_length * MIN_STITCH_FRACTION;
+    let mut grid = Vec::new();
+    let mut k = ((start + min_gap - offset) / stitch_length).ceil();
+    loop {
+        let u = k * stitch_length + offset;
+        if u > end - min_gap {
+            break;
+        }
+        grid.push(u);
+        k += 1.0;
+    }
+
+    // The gaps next to the row ends can reach 1.25 stitches; split those evenly
+    let mut points = vec![start];
+    for u in grid.into_iter().chain(std::iter::once(end)) {
+        let last = points[points.len() - 1];
+        let steps = ((u - last) / stitch_length).ceil().max(1.0) as usize;
+        points.extend((1..=steps).map(|step| last + (u - last) * step as f64 / steps as f64));
+    }
+    points
+}
+
+/// Group row segments into runs of single overlapping segments on consecutive rows
+///
+/// A run continues while exactly one segment on the next row overlaps it and that
+/// segment overlaps nothing else; splits and merges around holes start new runs.
+fn group_rows(rows: Vec<Vec<RowSegment>>) -> Vec<Vec<RowSegment>> {
+    let overlaps = |a: &RowSegment, b: &RowSegment| a.1 < b.2 && b.1 < a.2;
+    let mut finished: Vec<Vec<RowSegment>> = Vec::new();
+    let mut open: Vec<Vec<RowSegment>> = Vec::new();
+
+    for segments in rows {
+        // Open runs each segment overlaps, decided before any run moves on
+        let touching: Vec<Vec<usize>> = segments
+            .iter()
+            .map(|segment| {
+                (0..open.len())
+                    .filter(|&i| overlaps(&open[i][open[i].len() - 1], segment))
+                    .collect()
+            })
+            .collect();
+        let overlap_count = |run: usize| touching.iter().filter(|t| t.contains(&run)).count();
+
+        let mut next_open = Vec::with_capacity(segments.len());
+        for (segment, touching) in segments.iter().zip(&touching) {
+            match touching[..] {
+                [i] if overlap_count(i) == 1 => {
+                    let mut run = std::mem::take(&mut open[i]);
+                    run.push(*segment);
+                    next_open.push(run);
+                }
+                _ => next_open.push(vec![*segment]),
+            }
+        }
+        finished.extend(open.into_iter().filter(|run| !run.is_empty()));
+        open = next_open;
+    }
+    finished.extend(open);
+    finished.sort_by_key(|run| run[0].0);
+    finished
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+
+    fn square(min: f64, max: f64) -> Vec<Point> {
+        vec![(min, min), (max, min), (max, max), (min, max)]
+    }
+
+    /// Longest needle-down stitch that doesn't follow a jump
+    fn longest_stitch(pattern: &EmbPattern) -> f64 {
+        pattern
+            .stitches()
+            .windows(2)
+            .filter(|w| w[0].command == STITCH && w[1].command == STITCH)
+            .map(|w| (w[1].x - w[0].x).hypot(w[1].y - w[0].y))
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_square_fill() {
+        let fill = TatamiFill::new().underlay(None);
+        let runs = fill.runs(&[square(0.0, 100.0)]).unwrap();
+        assert_eq!(runs.len(), 1);
+
+        // 25 rows at 0.4mm, needle points on the edges and the staggered grid
+        let rows: Vec<f64> = {
+            let mut ys: Vec<f64> = runs[0].iter().map(|p| p.1).collect();
+            ys.dedup();
+            ys
+        };
+        assert!(rows.len() >= 25);
+        assert!(runs[0]
+            .iter()
+            .all(|&(x, y)| (-1e-9..=100.0 + 1e-9).contains(&x) && (0.0..=100.0).contains(&y)));
+        assert!(runs[0].contains(&(30.0, 2.0)));
+        assert!(runs[0].contains(&(37.5, 6.0)));
+
+        let mut pattern = EmbPattern::new();
+        fill.fill(&mut pattern, &[square(0.0, 100.0)]).unwrap();
+        assert!(longest_stitch(&pattern) <= 30.0 + 1e-9);
+        assert_eq!(pattern.stitches()[0].command, JUMP);
+    }
+
+    #[test]
+    fn test_fill_with_hole_and_angle() {
+        let shape = vec![square(0.0, 200.0), square(80.0, 120.0)];
+        let fill = TatamiFill::new().angle(30.0).underlay(None);
+        let runs = fill.runs(&shape).unwrap();
+        // The hole splits the rows into separate runs
+        assert!(runs.len() >= 3);
+
+        let inside_hole = |&(x, y): &Point| x > 80.5 && x < 119.5 && y > 80.5 && y < 119.5;
+        assert!(!runs.iter().flatten().any(inside_hole));
+
+        let mut pattern = EmbPattern::new();
+        fill.fill(&mut pattern, &shape).unwrap();
+        assert!(longest_stitch(&pattern) <= 30.0 + 1e-9);
+        let (min_x, min_y, max_x, max_y) = pattern.bounds();
+        assert!(min_x >= -1e-6 && min_y >= -1e-6 && max_x <= 200.0 + 1e-6 && max_y <= 200.0 + 1e-6);
+    }
+
+    #[test]
+    fn test_underlay_runs_first() {
+        let plain = TatamiFill::new().underlay(None);
+        let with_underlay = TatamiFill::new();
+        let top = plain.runs(&[square(0.0, 100.0)]).unwrap();
+        let all = with_underlay.runs(&[square(0.0, 100.0)]).unwrap();
+        assert_eq!(all.len(), top.len() + 1);
+
+        // Underlay rows are vertical and stay 1mm inside the edge
+        let underlay = &all[0];
+        assert!(underlay
+            .iter()
+            .all(|&(x, y)| (10.0 - 1e-6..=90.0 + 1e-6).contains(&x)
+                && (10.0 - 1e-6..=90.0 + 1e-6).contains(&y)));
+        assert_eq!(all[1..], top[..]);
+    }
+
+    #[test]
+    fn test_invalid_fill() {
+        let fill = TatamiFill::new();
+        assert!(fill.runs(&[]).is_err());
+        assert!(fill.runs(&[vec![(0.0, 0.0), (1.0, 1.0)]]).is_err());
+        assert!(fill
+            .clone()
+            .row_spacing(0.0)
+            .runs(&[square(0.0, 10.0)])
+            .is_err());
+        assert!(fill
+            .stitch_length(f64::NAN)
+            .runs(&[square(0.0, 10.0)])
+            .is_err());
+    }
+}
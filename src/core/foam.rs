@@ -0,0 +1,223 @@
+//! 3D foam and puff sections
+//!
+//! Puff embroidery sews dense satin over a sheet of foam laid on the fabric;
+//! the needle perforates the foam along the column edges so the excess tears
+//! away, leaving raised stitching. Such sections need different handling from
+//! flat stitching: more needle penetrations, no trims or lock stitches on top
+//! of the foam.
+//!
+//! A [`FoamSection`] marks a range of records as sewn over foam. Sections can
+//! be marked by record range or for a whole color block, and are stored in
+//! pattern metadata under `foam.<n>` as `<start>..<end>, <thickness>`, so they
+//! survive JSON and BUTA round-trips. Record indices follow the pattern as it
+//! is: edits that insert or remove records before a section don't move it.
+//! The [encoder](crate::core::encoder) honours the sections and re-indexes
+//! them in its output.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0x000000));
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.color_change(0.0, 0.0);
+//! pattern.stitch_abs(0.0, 50.0);
+//! pattern.stitch_abs(0.0, 80.0);
+//! pattern.end();
+//!
+//! // The red block is sewn over 3mm foam
+//! pattern.mark_foam_block(1, 30.0)?;
+//! assert!(!pattern.is_foam(1));
+//! assert!(pattern.is_foam(3));
+//! assert_eq!(pattern.foam_sections()[0].end, 5);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::{Error, Result};
+use std::ops::Range;
+
+/// Metadata key prefix of foam sections
+pub const FOAM_PREFIX: &str = "foam.";
+
+/// Common puff foam thickness (0.1mm)
+pub const DEFAULT_FOAM_THICKNESS: f64 = 30.0;
+
+/// A range of records sewn over foam
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoamSection {
+    /// First record of the section
+    pub start: usize,
+    /// Record after the last one of the section
+    pub end: usize,
+    /// Foam thickness (0.1mm)
+    pub thickness: f64,
+}
+
+impl FoamSection {
+    /// Create a section over a record range
+    pub fn new(records: Range<usize>, thickness: f64) -> Self {
+        Self {
+            start: records.start,
+            end: records.end,
+            thickness,
+        }
+    }
+
+    /// Whether the section covers a record
+    pub fn contains(&self, index: usize) -> bool {
+        (self.start..self.end).contains(&index)
+    }
+
+    /// Parse a stored `<start>..<end>, <thickness>` value
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (range, thickness) = value.split_once(',')?;
+        let (start, end) = range.trim().split_once("..")?;
+        let section = Self {
+            start: start.parse().ok()?,
+            end: end.parse().ok()?,
+            thickness: thickness.trim().parse().ok()?,
+        };
+        (section.start < section.end).then_some(section)
+    }
+
+    /// Format as a stored value
+    pub(crate) fn to_value(self) -> String {
+        format!("{}..{}, {}", self.start, self.end, self.thickness)
+    }
+}
+
+impl EmbPattern {
+    /// Mark a range of records as sewn over foam
+    ///
+    /// Returns an error for an empty range, a range past the last record or a
+    /// thickness that isn't positive.
+    pub fn mark_foam(&mut self, records: Range<usize>, thickness: f64) -> Result<()> {
+        if records.is_empty() || records.end > self.stitches().len() {
+            return Err(Error::InvalidPattern(format!(
+                "Foam section {}..{} is outside the {} records",
+                records.start,
+                records.end,
+                self.stitches().len()
+            )));
+        }
+        if !(thickness > 0.0 && thickness.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Foam thickness must be positive, got {}",
+                thickness
+            )));
+        }
+        let next = self
+            .metadata()
+            .filter_map(|(key, _)| key.strip_prefix(FOAM_PREFIX)?.parse::<usize>().ok())
+            .max()
+            .map_or(0, |n| n + 1);
+        let section = FoamSection::new(records, thickness);
+        self.set_metadata(format!("{}{}", FOAM_PREFIX, next), section.to_value());
+        Ok(())
+    }
+
+    /// Mark every record of a color block, except its thread change and END, as sewn over foam
+    pub fn mark_foam_block(&mut self, block: usize, thickness: f64) -> Result<()> {
+        let mut start = 0;
+        for (index, b) in self.blocks().iter().enumerate() {
+            start += usize::from(b.start_command.is_some());
+            if index == block {
+                return self.mark_foam(start..start + b.stitches.len(), thickness);
+            }
+            start += b.stitches.len();
+        }
+        Err(Error::InvalidPattern(format!(
+            "Pattern has no color block {}",
+            block
+        )))
+    }
+
+    /// Foam sections, sorted by first record
+    ///
+    /// Stored values that don't parse are skipped.
+    pub fn foam_sections(&self) -> Vec<FoamSection> {
+        let mut sections: Vec<FoamSection> = self
+            .metadata()
+            .filter(|(key, _)| key.starts_with(FOAM_PREFIX))
+            .filter_map(|(_, value)| FoamSection::parse(value))
+            .collect();
+        sections.sort_by_key(|s| (s.start, s.end));
+        sections
+    }
+
+    /// Whether a record is sewn over foam
+    pub fn is_foam(&self, index: usize) -> bool {
+        self.foam_sections().iter().any(|s| s.contains(index))
+    }
+
+    /// Remove all foam sections
+    pub fn clear_foam(&mut self) {
+        let keys: Vec<String> = self
+            .metadata()
+            .filter(|(key, _)| key.starts_with(FOAM_PREFIX))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            self.remove_metadata(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::io::memory::{read_from_bytes, write_to_bytes};
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for i in 0..10 {
+            pattern.stitch_abs(i as f64 * 10.0, 0.0);
+        }
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_mark_foam() {
+        let mut pattern = sample();
+        pattern.mark_foam(6..9, 20.0).unwrap();
+        pattern.mark_foam(2..4, 30.0).unwrap();
+
+        let sections = pattern.foam_sections();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0], FoamSection::new(2..4, 30.0));
+        assert_eq!(
+            pattern.get_metadata("foam.0").map(String::as_str),
+            Some("6..9, 20")
+        );
+        assert!(pattern.is_foam(3));
+        assert!(!pattern.is_foam(4));
+
+        assert!(pattern.mark_foam(5..5, 30.0).is_err());
+        assert!(pattern.mark_foam(5..12, 30.0).is_err());
+        assert!(pattern.mark_foam(0..1, 0.0).is_err());
+        assert!(pattern.mark_foam_block(1, 30.0).is_err());
+
+        pattern.clear_foam();
+        assert!(pattern.foam_sections().is_empty());
+    }
+
+    #[test]
+    fn test_foam_sections_round_trip() {
+        let mut pattern = sample();
+        pattern.mark_foam(1..5, DEFAULT_FOAM_THICKNESS).unwrap();
+        pattern.set_metadata("foam.9", "not a section");
+
+        for format in ["json", "buta"] {
+            let bytes = write_to_bytes(&pattern, format).unwrap();
+            let read = read_from_bytes(&bytes, format).unwrap();
+            assert_eq!(read.foam_sections(), pattern.foam_sections(), "{}", format);
+            assert_eq!(read.foam_sections().len(), 1);
+        }
+    }
+}
@@ -0,0 +1,495 @@
+//! Text from TrueType and OpenType fonts
+//!
+//! Requires the `ttf` feature. [`TtfFont`] loads any TrueType or OpenType font,
+//! flattens the outlines of its glyphs into polygons and stitches text with
+//! them, for monogramming with fonts that have no hand-digitized stitch
+//! version.
+//!
+//! Each glyph is sewn on its own, joined by a trim and a jump. With
+//! [`TextStyle::Outline`] its contours are traced with running stitch. With
+//! [`TextStyle::Auto`], glyphs whose strokes are narrower than
+//! [`TextOptions::satin_width`] are covered with satin stitches across their
+//! main direction, which suits small lettering; wider glyphs are outlined.
+//!
+//! Text is laid out along a baseline at y = 0 with capital letters reaching up
+//! to `-height`, and `\n` starts a new line. Kerning is not applied.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use butabuti::core::font::{TextOptions, TextStyle, TtfFont};
+//! use butabuti::prelude::*;
+//!
+//! let font = TtfFont::open("DejaVuSans.ttf")?;
+//!
+//! // 8mm capitals, outlines only
+//! let options = TextOptions::new().height(80.0).style(TextStyle::Outline);
+//! let mut pattern = EmbPattern::new();
+//! font.add_text(&mut pattern, "ABC", &options)?;
+//! pattern.end();
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::fill::{append_run, scan_runs};
+use crate::core::path::running_stitch;
+use crate::core::pattern::EmbPattern;
+use crate::geometry::{polygon_area, Point};
+use crate::utils::error::{Error, Result};
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// Stitches across a satin glyph longer than this many satin widths are split
+const MAX_SATIN_SPAN: f64 = 2.0;
+
+/// How glyphs are stitched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextStyle {
+    /// Trace every contour with running stitch
+    Outline,
+    /// Satin stitch glyphs with narrow strokes, outline the others
+    #[default]
+    Auto,
+}
+
+/// Options for [`TtfFont::add_text`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOptions {
+    /// Height of capital letters (0.1mm units, default: 100.0)
+    pub height: f64,
+    /// Extra space between letters (0.1mm units, default: 0.0)
+    pub letter_spacing: f64,
+    /// How glyphs are stitched (default: auto)
+    pub style: TextStyle,
+    /// Running stitch length for outlines (0.1mm units, default: 20.0)
+    pub stitch_length: f64,
+    /// Widest stroke that is satin stitched in auto style (0.1mm units,
+    /// default: 40.0)
+    pub satin_width: f64,
+    /// Distance between satin stitches (0.1mm units, default: 4.0)
+    pub satin_spacing: f64,
+    /// Length of the straight segments curves are flattened into (0.1mm units,
+    /// default: 5.0)
+    pub curve_step: f64,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            height: 100.0,
+            letter_spacing: 0.0,
+            style: TextStyle::Auto,
+            stitch_length: 20.0,
+            satin_width: 40.0,
+            satin_spacing: 4.0,
+            curve_step: 5.0,
+        }
+    }
+}
+
+impl TextOptions {
+    /// Create default options: 10mm capitals in auto style
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the height of capital letters (0.1mm units)
+    pub fn height(mut self, height: f64) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Set the extra space between letters (0.1mm units)
+    pub fn letter_spacing(mut self, spacing: f64) -> Self {
+        self.letter_spacing = spacing;
+        self
+    }
+
+    /// Set how glyphs are stitched
+    pub fn style(mut self, style: TextStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the running stitch length for outlines (0.1mm units)
+    pub fn stitch_length(mut self, length: f64) -> Self {
+        self.stitch_length = length;
+        self
+    }
+
+    /// Set the widest stroke that is satin stitched (0.1mm units)
+    pub fn satin_width(mut self, width: f64) -> Self {
+        self.satin_width = width;
+        self
+    }
+
+    /// Set the distance between satin stitches (0.1mm units)
+    pub fn satin_spacing(mut self, spacing: f64) -> Self {
+        self.satin_spacing = spacing;
+        self
+    }
+
+    /// Set the length of flattened curve segments (0.1mm units)
+    pub fn curve_step(mut self, step: f64) -> Self {
+        self.curve_step = step;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("height", self.height),
+            ("stitch_length", self.stitch_length),
+            ("satin_spacing", self.satin_spacing),
+            ("curve_step", self.curve_step),
+        ] {
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(Error::InvalidPattern(format!(
+                    "Text {} must be positive, got {}",
+                    name, value
+                )));
+            }
+        }
+        if !(self.letter_spacing.is_finite() && self.satin_width.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Text letter_spacing and satin_width must be finite, got {} and {}",
+                self.letter_spacing, self.satin_width
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A TrueType or OpenType font
+#[derive(Debug, Clone)]
+pub struct TtfFont {
+    data: Vec<u8>,
+    index: u32,
+}
+
+impl TtfFont {
+    /// Load a font from the contents of a font file
+    ///
+    /// For font collections (.ttc), the first font is used.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        Self::from_collection(data, 0)
+    }
+
+    /// Load the font at `index` of a font collection
+    pub fn from_collection(data: Vec<u8>, index: u32) -> Result<Self> {
+        Face::parse(&data, index).map_err(|e| Error::Parse(format!("Invalid font: {}", e)))?;
+        Ok(Self { data, index })
+    }
+
+    /// Load a font file
+    #[cfg(feature = "fs")]
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    fn face(&self) -> Face<'_> {
+        // Checked when the font was loaded
+        Face::parse(&self.data, self.index).expect("font was validated on load")
+    }
+
+    /// Flatten text into one shape per glyph
+    ///
+    /// Each shape is a list of closed polygons in pattern units (0.1mm), laid
+    /// out as described in the [module docs](self). Glyphs without outlines,
+    /// such as spaces, only advance the position. Characters missing from the
+    /// font use its placeholder glyph.
+    pub fn text_shapes(&self, text: &str, options: &TextOptions) -> Result<Vec<Vec<Vec<Point>>>> {
+        options.validate()?;
+        let face = self.face();
+        // Older fonts don't record it; measure an "H" instead
+        let cap_height = face
+            .capital_height()
+            .filter(|&h| h > 0)
+            .or_else(|| {
+                let h = face.glyph_index('H')?;
+                Some(face.glyph_bounding_box(h)?.y_max)
+            })
+            .unwrap_or_else(|| face.ascender()) as f64;
+        if cap_height <= 0.0 {
+            return Err(Error::Parse(
+                "Font has no usable capital height".to_string(),
+            ));
+        }
+        let scale = options.height / cap_height;
+        let line_height =
+            (face.ascender() as f64 - face.descender() as f64 + face.line_gap() as f64) * scale;
+
+        let mut shapes = Vec::new();
+        let (mut x, mut y) = (0.0, 0.0);
+        for c in text.chars() {
+            if c == '\n' {
+                x = 0.0;
+                y += line_height;
+                continue;
+            }
+            let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+            let mut outline = GlyphOutline::new(scale, (x, y), options.curve_step);
+            face.outline_glyph(glyph, &mut outline);
+            let shape = outline.finish();
+            if !shape.is_empty() {
+                shapes.push(shape);
+            }
+            let advance = face.glyph_hor_advance(glyph).unwrap_or(0) as f64;
+            x += advance * scale + options.letter_spacing;
+        }
+        Ok(shapes)
+    }
+
+    /// Stitch text into a pattern
+    ///
+    /// Glyphs are sewn left to right in the current thread. Each one starts
+    /// with a jump, after a trim if the pattern already has stitches. No END
+    /// is added.
+    pub fn add_text(
+        &self,
+        pattern: &mut EmbPattern,
+        text: &str,
+        options: &TextOptions,
+    ) -> Result<()> {
+        for shape in self.text_shapes(text, options)? {
+            add_glyph(pattern, &shape, options)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stitch one glyph shape
+fn add_glyph(pattern: &mut EmbPattern, shape: &[Vec<Point>], options: &TextOptions) -> Result<()> {
+    if options.style == TextStyle::Auto && stroke_width(shape) <= options.satin_width {
+        let span = options.satin_width.max(options.satin_spacing) * MAX_SATIN_SPAN;
+        let angle = main_direction(shape) + 90.0;
+        for run in scan_runs(shape, angle, options.satin_spacing, span, 1)? {
+            append_run(pattern, &run);
+        }
+        return Ok(());
+    }
+    for contour in shape {
+        append_run(
+            pattern,
+            &running_stitch(contour, options.stitch_length, true)?,
+        );
+    }
+    Ok(())
+}
+
+/// Typical stroke width of a shape: twice its area over its perimeter
+///
+/// Exact for long strokes of even width, where both sides make up the
+/// perimeter. Holes are wound against the outer contours in font outlines,
+/// so their areas subtract.
+fn stroke_width(shape: &[Vec<Point>]) -> f64 {
+    let area = shape.iter().map(|c| polygon_area(c)).sum::<f64>().abs();
+    let perimeter: f64 = shape
+        .iter()
+        .map(|contour| {
+            (0..contour.len())
+                .map(|i| {
+                    let (a, b) = (contour[i], contour[(i + 1) % contour.len()]);
+                    (b.0 - a.0).hypot(b.1 - a.1)
+                })
+                .sum::<f64>()
+        })
+        .sum();
+    if perimeter > 0.0 {
+        2.0 * area / perimeter
+    } else {
+        0.0
+    }
+}
+
+/// Angle in degrees of the axis the shape's points spread along most
+fn main_direction(shape: &[Vec<Point>]) -> f64 {
+    let points: Vec<Point> = shape.iter().flatten().copied().collect();
+    let n = points.len().max(1) as f64;
+    let (mx, my) = points
+        .iter()
+        .fold((0.0, 0.0), |(sx, sy), p| (sx + p.0 / n, sy + p.1 / n));
+    let (mut xx, mut yy, mut xy) = (0.0, 0.0, 0.0);
+    for &(x, y) in &points {
+        xx += (x - mx) * (x - mx);
+        yy += (y - my) * (y - my);
+        xy += (x - mx) * (y - my);
+    }
+    (2.0 * xy).atan2(xx - yy).to_degrees() / 2.0
+}
+
+/// Collects a glyph's contours as polygons in pattern units
+///
+/// Font units have y pointing up; pattern y points down, so outlines are
+/// flipped about the baseline.
+struct GlyphOutline {
+    scale: f64,
+    origin: Point,
+    step: f64,
+    contours: Vec<Vec<Point>>,
+    current: Vec<Point>,
+}
+
+impl GlyphOutline {
+    fn new(scale: f64, origin: Point, step: f64) -> Self {
+        Self {
+            scale,
+            origin,
+            step,
+            contours: Vec::new(),
+            current: Vec::new(),
+        }
+    }
+
+    fn point(&self, x: f32, y: f32) -> Point {
+        (
+            self.origin.0 + x as f64 * self.scale,
+            self.origin.1 - y as f64 * self.scale,
+        )
+    }
+
+    fn last(&self) -> Point {
+        self.current.last().copied().unwrap_or(self.origin)
+    }
+
+    /// Add points along a curve, enough that no segment is much over the step
+    fn flatten(&mut self, control: &[Point], at: impl Fn(f64) -> Point) {
+        let mut length = 0.0;
+        let mut previous = self.last();
+        for &p in control {
+            length += (p.0 - previous.0).hypot(p.1 - previous.1);
+            previous = p;
+        }
+        let segments = (length / self.step).ceil().clamp(1.0, 1000.0) as usize;
+        for i in 1..=segments {
+            self.current.push(at(i as f64 / segments as f64));
+        }
+    }
+
+    fn finish(mut self) -> Vec<Vec<Point>> {
+        self.close();
+        self.contours
+    }
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        let p = self.point(x, y);
+        self.current.push(p);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.point(x, y);
+        self.current.push(p);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (self.last(), self.point(x1, y1), self.point(x, y));
+        self.flatten(&[p1, p2], |t| {
+            let u = 1.0 - t;
+            (
+                u * u * p0.0 + 2.0 * u * t * p1.0 + t * t * p2.0,
+                u * u * p0.1 + 2.0 * u * t * p1.1 + t * t * p2.1,
+            )
+        });
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (
+            self.last(),
+            self.point(x1, y1),
+            self.point(x2, y2),
+            self.point(x, y),
+        );
+        self.flatten(&[p1, p2, p3], |t| {
+            let u = 1.0 - t;
+            let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+            (
+                a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0,
+                a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1,
+            )
+        });
+    }
+
+    fn close(&mut self) {
+        let mut contour = std::mem::take(&mut self.current);
+        if contour.len() > 1 && contour.first() == contour.last() {
+            contour.pop();
+        }
+        if contour.len() >= 3 {
+            self.contours.push(contour);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::error::ErrorKind;
+
+    /// An "L" drawn like a font would: y up, 100 font units per pattern unit
+    fn letter_l(width: f32) -> Vec<Vec<Point>> {
+        let mut outline = GlyphOutline::new(0.01, (0.0, 0.0), 5.0);
+        outline.move_to(0.0, 0.0);
+        outline.line_to(10000.0, 0.0);
+        outline.line_to(10000.0, width);
+        outline.line_to(width, width);
+        outline.line_to(width, 20000.0);
+        outline.line_to(0.0, 20000.0);
+        outline.close();
+        outline.finish()
+    }
+
+    #[test]
+    fn test_outline_flattening() {
+        let l = letter_l(2000.0);
+        assert_eq!(l.len(), 1);
+        assert_eq!(l[0][0], (0.0, 0.0));
+        assert_eq!(l[0][4], (20.0, -200.0));
+
+        // A quarter circle of radius 100 split into steps of about 5
+        let mut outline = GlyphOutline::new(1.0, (10.0, 0.0), 5.0);
+        outline.move_to(100.0, 0.0);
+        outline.quad_to(100.0, 100.0, 0.0, 100.0);
+        outline.line_to(0.0, 0.0);
+        let contours = outline.finish();
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].len(), 1 + 40 + 1);
+        assert_eq!(contours[0][0], (110.0, 0.0));
+        assert_eq!(contours[0][40], (10.0, -100.0));
+    }
+
+    #[test]
+    fn test_auto_style_satins_narrow_strokes() {
+        assert!((stroke_width(&letter_l(2000.0)) - 20.0).abs() < 2.0);
+        assert!((main_direction(&[vec![(0.0, 0.0), (100.0, 10.0), (200.0, 0.0)]])).abs() < 1.0);
+
+        let options = TextOptions::new();
+        let mut thin = EmbPattern::new();
+        add_glyph(&mut thin, &letter_l(2000.0), &options).unwrap();
+        let mut thick = EmbPattern::new();
+        add_glyph(&mut thick, &letter_l(6000.0), &options).unwrap();
+        let mut outline = EmbPattern::new();
+        add_glyph(
+            &mut outline,
+            &letter_l(2000.0),
+            &options.style(TextStyle::Outline),
+        )
+        .unwrap();
+
+        // Satin rows every 0.4mm along 30mm of stroke, against a 2mm outline
+        assert!(thin.count_stitches() > 100);
+        assert!(thick.count_stitches() < 40);
+        assert_eq!(thick.count_stitches(), outline.count_stitches());
+    }
+
+    #[test]
+    fn test_invalid_fonts_and_options() {
+        let err = TtfFont::from_bytes(b"not a font".to_vec()).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Parse(_)));
+        assert!(TextOptions::new().height(0.0).validate().is_err());
+        assert!(TextOptions::new()
+            .satin_spacing(f64::NAN)
+            .validate()
+            .is_err());
+        assert!(TextOptions::new().validate().is_ok());
+    }
+}
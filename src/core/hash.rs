@@ -0,0 +1,155 @@
+//! Content hashing
+//!
+//! [`EmbPattern::content_hash`] identifies a design by its stitching, not by
+//! how it was saved: the hash covers the needle points of each color block,
+//! relative to the top-left of the stitches and rounded to whole units, so
+//! the same design hashes the same after moving it, renaming it or converting
+//! it between formats that keep the coordinates. Jumps, trims, repeated needle
+//! points, thread colors and metadata don't count.
+//!
+//! The hash is FNV-1a over a fixed byte encoding, so it is stable across
+//! platforms and releases and can be stored in a design index.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.stitch_abs(100.0, 100.0);
+//! pattern.end();
+//!
+//! let mut moved = pattern.clone();
+//! moved.translate(250.0, -40.0);
+//! assert_eq!(moved.content_hash(), pattern.content_hash());
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+
+/// 64-bit FNV-1a
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        self.write(&value.to_le_bytes());
+    }
+}
+
+impl EmbPattern {
+    /// Hash of the design's stitching, independent of position
+    ///
+    /// Designs with the same needle points in the same color blocks hash the
+    /// same; see the [module documentation](crate::core::hash).
+    pub fn content_hash(&self) -> u64 {
+        let is_stitch = |command: u32| command & COMMAND_MASK == STITCH;
+        let (min_x, min_y) = self
+            .stitches()
+            .iter()
+            .filter(|s| is_stitch(s.command))
+            .fold((f64::INFINITY, f64::INFINITY), |(x, y), s| {
+                (x.min(s.x), y.min(s.y))
+            });
+
+        let mut hasher = Fnv1a::new();
+        for block in self.blocks() {
+            if block.stitch_count() == 0 {
+                continue;
+            }
+            hasher.write(b"B");
+            let mut last = None;
+            for (x, y) in block.points() {
+                let point = ((x - min_x).round() as i64, (y - min_y).round() as i64);
+                if last != Some(point) {
+                    hasher.write_i64(point.0);
+                    hasher.write_i64(point.1);
+                    last = Some(point);
+                }
+            }
+        }
+        hasher.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pattern::Stitch;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::memory::{read_from_bytes, write_to_bytes};
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.stitch_abs(10.0, 10.0);
+        pattern.stitch_abs(60.0, 10.0);
+        pattern.stitch_abs(60.0, 60.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(20.0, 40.0);
+        pattern.stitch_abs(40.0, 20.0);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_content_hash_ignores_position_and_format() {
+        let pattern = sample();
+        let hash = pattern.content_hash();
+
+        for format in ["dst", "exp", "jef"] {
+            let bytes = write_to_bytes(&pattern, format).unwrap();
+            let read = read_from_bytes(&bytes, format).unwrap();
+            assert_eq!(read.content_hash(), hash, "{}", format);
+        }
+
+        // Renamed, with a repeated needle point and a jump
+        let mut edited = pattern.clone();
+        edited.add_metadata("name", "Renamed");
+        edited.splice_stitches(
+            2,
+            0,
+            vec![
+                Stitch::new(60.0, 10.0, STITCH),
+                Stitch::new(60.0, 30.0, JUMP),
+            ],
+        );
+        assert_eq!(edited.content_hash(), hash);
+    }
+
+    #[test]
+    fn test_content_hash_sees_geometry_and_blocks() {
+        let hash = sample().content_hash();
+
+        let mut moved_stitch = sample();
+        moved_stitch.splice_stitches(1, 1, vec![Stitch::new(65.0, 10.0, STITCH)]);
+        assert_ne!(moved_stitch.content_hash(), hash);
+
+        // The same points in one block
+        let mut merged = EmbPattern::new();
+        for (x, y) in [
+            (10.0, 10.0),
+            (60.0, 10.0),
+            (60.0, 60.0),
+            (20.0, 40.0),
+            (40.0, 20.0),
+        ] {
+            merged.stitch_abs(x, y);
+        }
+        assert_ne!(merged.content_hash(), hash);
+        assert_ne!(EmbPattern::new().content_hash(), hash);
+    }
+}
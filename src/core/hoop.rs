@@ -0,0 +1,260 @@
+//! Embroidery hoops and fit checking
+//!
+//! A hoop's size is its sewing field, the area the machine can reach with that
+//! hoop attached, not the outer size of the frame. Designs are placed centered in
+//! the hoop, as machines do by default; a design that only fits turned by 90
+//! degrees is reported as rotated.
+//!
+//! The built-in [`catalog`] lists common Brother, Janome and Pfaff home machine
+//! hoops and Tajima commercial frames.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::hoop;
+//!
+//! // 90 x 150mm design
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(900.0, 1500.0);
+//!
+//! let brother_4x4 = hoop::find("Brother 4x4").unwrap();
+//! assert!(!pattern.fits_hoop(&brother_4x4));
+//!
+//! let best = &pattern.suggest_hoops()[0];
+//! assert_eq!(best.hoop.name, "Brother 5x7");
+//! assert_eq!(best.margin_x_mm, 20.0);
+//! ```
+
+use crate::core::pattern::EmbPattern;
+
+/// Outline of a hoop's sewing field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoopShape {
+    /// Rectangular field of `width_mm` by `height_mm`
+    Rectangle,
+    /// Round field with a diameter of `width_mm`
+    Round,
+}
+
+/// An embroidery hoop or frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hoop {
+    /// Name, e.g. "Brother 5x7"
+    pub name: String,
+    /// Machine brand the hoop belongs to
+    pub brand: Option<String>,
+    /// Sewing field width in millimeters (diameter for round hoops)
+    pub width_mm: f64,
+    /// Sewing field height in millimeters (diameter for round hoops)
+    pub height_mm: f64,
+    /// Sewing field outline
+    pub shape: HoopShape,
+}
+
+impl Hoop {
+    /// Create a rectangular hoop
+    pub fn new(name: impl Into<String>, width_mm: f64, height_mm: f64) -> Self {
+        Self {
+            name: name.into(),
+            brand: None,
+            width_mm,
+            height_mm,
+            shape: HoopShape::Rectangle,
+        }
+    }
+
+    /// Create a round hoop
+    pub fn round(name: impl Into<String>, diameter_mm: f64) -> Self {
+        Self {
+            shape: HoopShape::Round,
+            ..Self::new(name, diameter_mm, diameter_mm)
+        }
+    }
+
+    /// Set the machine brand
+    pub fn with_brand(mut self, brand: impl Into<String>) -> Self {
+        self.brand = Some(brand.into());
+        self
+    }
+
+    /// Sewing field area in square millimeters
+    pub fn area_mm2(&self) -> f64 {
+        match self.shape {
+            HoopShape::Rectangle => self.width_mm * self.height_mm,
+            HoopShape::Round => std::f64::consts::PI * self.width_mm * self.width_mm / 4.0,
+        }
+    }
+}
+
+/// How a design sits in a hoop it fits
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoopFit {
+    /// The hoop
+    pub hoop: Hoop,
+    /// Clearance on the left and right in millimeters
+    pub margin_x_mm: f64,
+    /// Clearance at the top and bottom in millimeters
+    ///
+    /// For round hoops both margins are the smallest distance from any stitch
+    /// to the edge.
+    pub margin_y_mm: f64,
+    /// Whether the design has to be turned by 90 degrees to fit
+    pub rotated: bool,
+}
+
+/// Built-in hoop catalog, smallest first within each brand
+pub fn catalog() -> Vec<Hoop> {
+    vec![
+        Hoop::new("Brother 4x4", 100.0, 100.0).with_brand("Brother"),
+        Hoop::new("Brother 5x7", 130.0, 180.0).with_brand("Brother"),
+        Hoop::new("Brother 6x10", 160.0, 260.0).with_brand("Brother"),
+        Hoop::new("Brother 8x8", 200.0, 200.0).with_brand("Brother"),
+        Hoop::new("Brother 8x12", 200.0, 300.0).with_brand("Brother"),
+        Hoop::new("Janome B", 50.0, 50.0).with_brand("Janome"),
+        Hoop::new("Janome A", 126.0, 110.0).with_brand("Janome"),
+        Hoop::new("Janome SQ14", 140.0, 140.0).with_brand("Janome"),
+        Hoop::new("Janome C", 140.0, 200.0).with_brand("Janome"),
+        Hoop::new("Janome SQ20", 200.0, 200.0).with_brand("Janome"),
+        Hoop::new("Janome GR", 200.0, 280.0).with_brand("Janome"),
+        Hoop::new("Pfaff 120x115", 120.0, 115.0).with_brand("Pfaff"),
+        Hoop::new("Pfaff 240x150", 240.0, 150.0).with_brand("Pfaff"),
+        Hoop::new("Pfaff 260x200", 260.0, 200.0).with_brand("Pfaff"),
+        Hoop::new("Pfaff 360x200", 360.0, 200.0).with_brand("Pfaff"),
+        Hoop::round("Tajima 9cm round", 90.0).with_brand("Tajima"),
+        Hoop::round("Tajima 12cm round", 120.0).with_brand("Tajima"),
+        Hoop::round("Tajima 15cm round", 150.0).with_brand("Tajima"),
+        Hoop::round("Tajima 18cm round", 180.0).with_brand("Tajima"),
+        Hoop::round("Tajima 21cm round", 210.0).with_brand("Tajima"),
+        Hoop::round("Tajima 24cm round", 240.0).with_brand("Tajima"),
+        Hoop::round("Tajima 30cm round", 300.0).with_brand("Tajima"),
+        Hoop::new("Tajima 36x36 frame", 360.0, 360.0).with_brand("Tajima"),
+        Hoop::new("Tajima 45x40 frame", 450.0, 400.0).with_brand("Tajima"),
+    ]
+}
+
+/// Look up a catalog hoop by name (case-insensitive)
+pub fn find(name: &str) -> Option<Hoop> {
+    catalog()
+        .into_iter()
+        .find(|hoop| hoop.name.eq_ignore_ascii_case(name.trim()))
+}
+
+impl EmbPattern {
+    /// Whether the design fits in a hoop, centered and possibly rotated
+    pub fn fits_hoop(&self, hoop: &Hoop) -> bool {
+        self.hoop_fit(hoop).is_some()
+    }
+
+    /// Where the design sits in a hoop, or `None` if it doesn't fit
+    ///
+    /// The unrotated placement is preferred when both fit.
+    pub fn hoop_fit(&self, hoop: &Hoop) -> Option<HoopFit> {
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        let (width, height) = ((max_x - min_x) / 10.0, (max_y - min_y) / 10.0);
+
+        match hoop.shape {
+            HoopShape::Rectangle => {
+                let fit = |w: f64, h: f64, rotated: bool| {
+                    (w <= hoop.width_mm && h <= hoop.height_mm).then(|| HoopFit {
+                        hoop: hoop.clone(),
+                        margin_x_mm: (hoop.width_mm - w) / 2.0,
+                        margin_y_mm: (hoop.height_mm - h) / 2.0,
+                        rotated,
+                    })
+                };
+                fit(width, height, false).or_else(|| fit(height, width, true))
+            }
+            HoopShape::Round => {
+                let (cx, cy) = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+                let reach = self
+                    .stitches()
+                    .iter()
+                    .filter(|s| s.x.is_finite() && s.y.is_finite())
+                    .map(|s| (s.x - cx).hypot(s.y - cy) / 10.0)
+                    .fold(0.0, f64::max);
+                let margin = hoop.width_mm / 2.0 - reach;
+                (margin >= 0.0).then(|| HoopFit {
+                    hoop: hoop.clone(),
+                    margin_x_mm: margin,
+                    margin_y_mm: margin,
+                    rotated: false,
+                })
+            }
+        }
+    }
+
+    /// Catalog hoops the design fits, smallest sewing field first
+    pub fn suggest_hoops(&self) -> Vec<HoopFit> {
+        let mut fits: Vec<HoopFit> = catalog()
+            .iter()
+            .filter_map(|hoop| self.hoop_fit(hoop))
+            .collect();
+        fits.sort_by(|a, b| a.hoop.area_mm2().total_cmp(&b.hoop.area_mm2()));
+        fits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn design(width: f64, height: f64) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(-width * 5.0, -height * 5.0);
+        pattern.stitch_abs(width * 5.0, height * 5.0);
+        pattern
+    }
+
+    #[test]
+    fn test_rectangular_fit_and_rotation() {
+        let hoop = Hoop::new("5x7", 130.0, 180.0);
+        let fit = design(100.0, 150.0).hoop_fit(&hoop).unwrap();
+        assert_eq!(
+            (fit.margin_x_mm, fit.margin_y_mm, fit.rotated),
+            (15.0, 15.0, false)
+        );
+
+        let fit = design(170.0, 120.0).hoop_fit(&hoop).unwrap();
+        assert!(fit.rotated);
+        assert_eq!((fit.margin_x_mm, fit.margin_y_mm), (5.0, 5.0));
+
+        assert!(!design(140.0, 140.0).fits_hoop(&hoop));
+        assert!(design(130.0, 180.0).fits_hoop(&hoop));
+    }
+
+    #[test]
+    fn test_round_fit_uses_stitch_reach() {
+        let hoop = Hoop::round("Round", 100.0);
+
+        // The bounding box corners stick out of the circle, the stitches don't
+        let mut diamond = EmbPattern::new();
+        for (x, y) in [(0.0, -480.0), (480.0, 0.0), (0.0, 480.0), (-480.0, 0.0)] {
+            diamond.stitch_abs(x, y);
+        }
+        let fit = diamond.hoop_fit(&hoop).unwrap();
+        assert!((fit.margin_x_mm - 2.0).abs() < 1e-9);
+
+        assert!(!design(96.0, 96.0).fits_hoop(&hoop));
+    }
+
+    #[test]
+    fn test_catalog_and_suggestions() {
+        assert!(catalog().len() >= 20);
+        assert_eq!(
+            find("brother 5x7").unwrap().brand.as_deref(),
+            Some("Brother")
+        );
+        assert!(find("Nonexistent").is_none());
+
+        let suggestions = design(95.0, 95.0).suggest_hoops();
+        assert_eq!(suggestions[0].hoop.name, "Brother 4x4");
+        assert!(suggestions
+            .windows(2)
+            .all(|w| w[0].hoop.area_mm2() <= w[1].hoop.area_mm2()));
+        assert!(suggestions.iter().all(|fit| fit.hoop.name != "Janome B"));
+
+        assert!(design(500.0, 500.0).suggest_hoops().is_empty());
+    }
+}
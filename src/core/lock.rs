@@ -0,0 +1,322 @@
+//! Lock stitches
+//!
+//! Machines only knot the thread when told to. Without lock stitches, a run
+//! that starts after a trim or color change, or ends before one, can pull out
+//! and unravel. [`EmbPattern::add_lock_stitches`] inserts a few short stitches
+//! where each run starts (tie-in) and ends (tie-off).
+//!
+//! Tie-in stitches point toward the rest of the run and tie-off stitches back
+//! along it, so both are sewn over or lie under stitching already in place.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::core::lock::{LockStitchOptions, LockStyle};
+//! use butabuti::prelude::*;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.trim();
+//! pattern.jump_abs(0.0, 100.0);
+//! pattern.stitch_abs(0.0, 100.0);
+//! pattern.stitch_abs(100.0, 100.0);
+//! pattern.end();
+//!
+//! let options = LockStitchOptions::new().style(LockStyle::Triangle).size(6.0);
+//! let locks = pattern.add_lock_stitches(&options);
+//! assert_eq!(locks, 4); // a tie-in and a tie-off for each run
+//! assert_eq!(pattern.count_stitches(), 4 + 4 * 3);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{EmbPattern, Stitch};
+
+/// Shape of the stitches making up a lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LockStyle {
+    /// Two stitches out along the run and back, twice
+    #[default]
+    BackAndForth,
+    /// Three stitches around a small triangle beside the run
+    Triangle,
+    /// Four stitches crossing over the lock point, then back to it
+    Cross,
+}
+
+impl LockStyle {
+    /// Stitch offsets in units of the lock size
+    ///
+    /// Each offset is (along the run, across it). Every lock ends at its
+    /// starting point.
+    fn offsets(self) -> &'static [(f64, f64)] {
+        match self {
+            LockStyle::BackAndForth => &[(1.0, 0.0), (0.0, 0.0), (1.0, 0.0), (0.0, 0.0)],
+            LockStyle::Triangle => &[(1.0, 0.0), (0.5, 0.866), (0.0, 0.0)],
+            LockStyle::Cross => &[
+                (0.5, 0.5),
+                (-0.5, -0.5),
+                (0.5, -0.5),
+                (-0.5, 0.5),
+                (0.0, 0.0),
+            ],
+        }
+    }
+}
+
+/// Options for [`EmbPattern::add_lock_stitches`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockStitchOptions {
+    /// Shape of each lock
+    pub style: LockStyle,
+    /// Length of the lock stitches (0.1mm units)
+    pub size: f64,
+    /// Lock the start of each run
+    pub tie_in: bool,
+    /// Lock the end of each run
+    pub tie_off: bool,
+}
+
+impl Default for LockStitchOptions {
+    fn default() -> Self {
+        Self {
+            style: LockStyle::BackAndForth,
+            size: 5.0,
+            tie_in: true,
+            tie_off: true,
+        }
+    }
+}
+
+impl LockStitchOptions {
+    /// Create default options: back-and-forth locks of 0.5mm at both ends
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the lock style
+    pub fn style(mut self, style: LockStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the lock stitch length (0.1mm units)
+    pub fn size(mut self, size: f64) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set whether runs get a tie-in
+    pub fn tie_in(mut self, tie_in: bool) -> Self {
+        self.tie_in = tie_in;
+        self
+    }
+
+    /// Set whether runs get a tie-off
+    pub fn tie_off(mut self, tie_off: bool) -> Self {
+        self.tie_off = tie_off;
+        self
+    }
+}
+
+impl EmbPattern {
+    /// Insert lock stitches at the start and end of every stitch run
+    ///
+    /// A run starts at the first stitch of the design and the first stitch
+    /// after a trim, cut, color change or needle set. It ends at the last
+    /// stitch before one of those commands or the end. Jumps without a trim
+    /// don't cut the thread, so they don't split runs.
+    ///
+    /// Returns the number of locks inserted. Does nothing if the size isn't
+    /// positive.
+    pub fn add_lock_stitches(&mut self, options: &LockStitchOptions) -> usize {
+        if !(options.size > 0.0 && options.size.is_finite()) {
+            return 0;
+        }
+
+        let source = self.stitches().to_vec();
+        let mut stitches = Vec::with_capacity(source.len());
+        let mut origins = Vec::with_capacity(source.len());
+        let mut locks = 0;
+        // Last stitch of the current run and the distinct stitch before it
+        let mut run: Option<(Stitch, Option<Stitch>)> = None;
+
+        for (index, stitch) in source.iter().enumerate() {
+            let command = stitch.command & COMMAND_MASK;
+            if matches!(command, TRIM | CUT | COLOR_CHANGE | NEEDLE_SET | END) {
+                if let Some((last, previous)) = run.take() {
+                    if options.tie_off {
+                        let toward = previous.map(|p| (p.x, p.y));
+                        push_lock(&mut stitches, &last, toward, (-1.0, 0.0), options);
+                        locks += 1;
+                    }
+                }
+            }
+
+            origins.resize(stitches.len(), None);
+            origins.push(Some(index));
+            stitches.push(*stitch);
+            if command != STITCH {
+                continue;
+            }
+
+            match run.as_mut() {
+                None => {
+                    run = Some((*stitch, None));
+                    if options.tie_in {
+                        let toward = source[index + 1..]
+                            .iter()
+                            .take_while(|s| s.command & COMMAND_MASK == STITCH)
+                            .find(|s| s.x != stitch.x || s.y != stitch.y)
+                            .map(|s| (s.x, s.y));
+                        push_lock(&mut stitches, stitch, toward, (1.0, 0.0), options);
+                        locks += 1;
+                    }
+                }
+                Some((last, previous)) => {
+                    if last.x != stitch.x || last.y != stitch.y {
+                        *previous = Some(*last);
+                    }
+                    *last = *stitch;
+                }
+            }
+        }
+
+        if let Some((last, previous)) = run {
+            if options.tie_off {
+                let toward = previous.map(|p| (p.x, p.y));
+                push_lock(&mut stitches, &last, toward, (-1.0, 0.0), options);
+                locks += 1;
+            }
+        }
+
+        if locks > 0 {
+            origins.resize(stitches.len(), None);
+            self.rebuild_stitches(stitches, &origins);
+        }
+        locks
+    }
+}
+
+/// Append a lock at `at`, oriented toward `toward` (or `fallback` if none)
+fn push_lock(
+    stitches: &mut Vec<Stitch>,
+    at: &Stitch,
+    toward: Option<(f64, f64)>,
+    fallback: (f64, f64),
+    options: &LockStitchOptions,
+) {
+    let (ux, uy) = toward
+        .map(|(x, y)| (x - at.x, y - at.y))
+        .and_then(|(dx, dy)| {
+            let length = dx.hypot(dy);
+            (length > 0.0).then(|| (dx / length, dy / length))
+        })
+        .unwrap_or(fallback);
+    // Perpendicular, turned counterclockwise from the run direction
+    let (vx, vy) = (-uy, ux);
+    for &(along, across) in options.style.offsets() {
+        stitches.push(Stitch::new(
+            at.x + (ux * along + vx * across) * options.size,
+            at.y + (uy * along + vy * across) * options.size,
+            STITCH,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_runs() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.jump_abs(0.0, 100.0);
+        pattern.stitch_abs(0.0, 100.0);
+        pattern.stitch_abs(0.0, 200.0);
+        pattern.end();
+        pattern
+    }
+
+    fn points(pattern: &EmbPattern) -> Vec<(f64, f64, u32)> {
+        pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.x, s.y, s.command))
+            .collect()
+    }
+
+    #[test]
+    fn test_back_and_forth_locks() {
+        let mut pattern = two_runs();
+        let locks = pattern.add_lock_stitches(&LockStitchOptions::new().size(10.0));
+        assert_eq!(locks, 4);
+
+        let points = points(&pattern);
+        // Tie-in toward the second stitch, tie-off back along the run before the trim
+        assert_eq!(
+            &points[..11],
+            &[
+                (0.0, 0.0, STITCH),
+                (10.0, 0.0, STITCH),
+                (0.0, 0.0, STITCH),
+                (10.0, 0.0, STITCH),
+                (0.0, 0.0, STITCH),
+                (100.0, 0.0, STITCH),
+                (90.0, 0.0, STITCH),
+                (100.0, 0.0, STITCH),
+                (90.0, 0.0, STITCH),
+                (100.0, 0.0, STITCH),
+                (100.0, 0.0, TRIM),
+            ]
+        );
+        // Second run locks along +y, tie-off lands before the end
+        assert_eq!(points[13], (0.0, 100.0, STITCH));
+        assert_eq!(points[14], (0.0, 110.0, STITCH));
+        assert_eq!(points[points.len() - 3], (0.0, 190.0, STITCH));
+        assert_eq!(points.last().unwrap().2, END);
+    }
+
+    #[test]
+    fn test_styles_and_toggles() {
+        let mut triangle = two_runs();
+        triangle.add_lock_stitches(&LockStitchOptions::new().style(LockStyle::Triangle));
+        assert_eq!(triangle.count_stitches(), 4 + 4 * 3);
+
+        let mut cross = two_runs();
+        cross.add_lock_stitches(
+            &LockStitchOptions::new()
+                .style(LockStyle::Cross)
+                .tie_off(false),
+        );
+        assert_eq!(cross.count_stitches(), 4 + 2 * 5);
+        // Cross stitches stay within half the size of the lock point on each axis
+        let (min_x, min_y, _, _) = cross.bounds();
+        assert!((min_x + 2.5).abs() < 1e-9 && (min_y + 2.5).abs() < 1e-9);
+
+        let mut none = two_runs();
+        assert_eq!(
+            none.add_lock_stitches(&LockStitchOptions::new().tie_in(false).tie_off(false)),
+            0
+        );
+        assert_eq!(none.stitches(), two_runs().stitches());
+        assert_eq!(
+            none.add_lock_stitches(&LockStitchOptions::new().size(0.0)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_jumps_without_trims_keep_the_run() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 0.0);
+        pattern.jump_abs(80.0, 0.0);
+        pattern.stitch_abs(80.0, 0.0);
+        pattern.stitch_abs(120.0, 0.0);
+        assert_eq!(pattern.add_lock_stitches(&LockStitchOptions::new()), 2);
+    }
+}
@@ -202,6 +202,14 @@ impl EmbMatrix {
         point[1] = ny;
     }
 
+    /// Determinant of the matrix; zero for transforms that cannot be undone
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0] * (m[4] * m[8] - m[7] * m[5])
+            + m[1] * (m[5] * m[6] - m[3] * m[8])
+            + m[2] * (m[3] * m[7] - m[4] * m[6])
+    }
+
     /// Compute the inverse of this matrix
     ///
     /// Note: If the matrix is singular (determinant is 0), this will leave the matrix unchanged
@@ -5,23 +5,99 @@
 //! This module contains the fundamental types and functionality for working
 //! with embroidery patterns.
 
+/// Named anchor points for aligning patterns
+pub mod anchor;
+
+/// Per-stitch extended attributes
+pub mod attributes;
+
+/// Color blocks of a pattern
+pub mod block;
+
 /// Pattern collection for multi-pattern files
 pub mod collection;
 
+/// Color block reordering to save thread changes
+pub mod color_sort;
+
 /// Color group management for organizing threads
 pub mod color_group;
 
+/// Compact fixed-point stitch storage
+pub mod compact;
+
+/// Placing patterns into a design
+pub mod compose;
+
+/// Thread consumption estimates
+pub mod consumption;
+
 /// Command definitions and constants
 pub mod constants;
 
+/// Stitch density maps and hotspots
+pub mod density;
+
+/// Pattern comparison
+pub mod diff;
+
+/// Undoable pattern editing
+pub mod editor;
+
 /// Encoder for pattern transcoding
 pub mod encoder;
 
+/// Tatami fill stitch generation
+pub mod fill;
+
+/// 3D foam and puff sections
+pub mod foam;
+
+/// Text from TrueType and OpenType fonts
+#[cfg(feature = "ttf")]
+pub mod font;
+
+/// Content hashing of designs
+pub mod hash;
+
+/// Embroidery hoops and fit checking
+pub mod hoop;
+
+/// Lock stitches at the ends of stitch runs
+pub mod lock;
+
 /// Affine transformation matrix
 pub mod matrix;
 
+/// Running stitch paths
+pub mod path;
+
 /// Pattern structure and manipulation
 pub mod pattern;
 
+/// Photo-stitch generation from raster images
+pub mod photo;
+
+/// Thread remapping to a target thread chart
+pub mod remap;
+
+/// Satin column stitch generation
+pub mod satin;
+
+/// Stitch selection and region editing
+pub mod selection;
+
+/// Sequin runs
+pub mod sequin;
+
+/// Spatial index for nearest-stitch and range queries
+pub mod spatial;
+
+/// Operator notes on STOP commands
+pub mod stop_note;
+
 /// Thread color management
 pub mod thread;
+
+/// Underlay beneath fills and satin columns
+pub mod underlay;
@@ -0,0 +1,187 @@
+//! Running stitch paths
+//!
+//! Resamples polylines into evenly spaced needle points. Sharp corners are kept as
+//! needle points so outlines stay crisp; shallow bends, such as those in a densely
+//! sampled curve, are stitched across so the stitches stay even.
+//!
+//! Points are in pattern units (0.1mm).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//!
+//! // 20mm square outline with 2.5mm stitches
+//! let square = [(0.0, 0.0), (200.0, 0.0), (200.0, 200.0), (0.0, 200.0)];
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_running_stitch_loop(&square, 25.0)?;
+//!
+//! assert_eq!(pattern.count_stitches(), 33);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::geometry::Point;
+use crate::utils::error::{Error, Result};
+
+/// Turns sharper than this (degrees) always get a needle point at the vertex
+pub const CORNER_ANGLE: f64 = 30.0;
+
+/// Upper bound on stitches per path, to catch unit mistakes
+const MAX_PATH_STITCHES: f64 = 1_000_000.0;
+
+/// Resample a polyline into running stitch needle points
+///
+/// Returns the needle points including the start, with no gap longer than
+/// `stitch_length`. Closed paths end back at their first point.
+pub fn running_stitch(points: &[Point], stitch_length: f64, closed: bool) -> Result<Vec<Point>> {
+    if !(stitch_length > 0.0 && stitch_length.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Stitch length must be positive, got {}",
+            stitch_length
+        )));
+    }
+    if points.iter().any(|p| !p.0.is_finite() || !p.1.is_finite()) {
+        return Err(Error::InvalidPattern(
+            "Path contains non-finite coordinates".to_string(),
+        ));
+    }
+
+    let mut path: Vec<Point> = Vec::with_capacity(points.len() + 1);
+    for &p in points {
+        if path.last().is_none_or(|&last| distance(last, p) > 0.0) {
+            path.push(p);
+        }
+    }
+    if closed && path.len() > 1 && distance(path[0], path[path.len() - 1]) > 0.0 {
+        path.push(path[0]);
+    }
+    if path.len() < 2 {
+        return Err(Error::InvalidPattern(
+            "Path needs at least 2 distinct points".to_string(),
+        ));
+    }
+
+    let length: f64 = path.windows(2).map(|w| distance(w[0], w[1])).sum();
+    if length / stitch_length > MAX_PATH_STITCHES {
+        return Err(Error::InvalidPattern(format!(
+            "Path needs more than {} stitches; check the stitch length units",
+            MAX_PATH_STITCHES
+        )));
+    }
+
+    // Resample each stretch between corners on its own
+    let mut out = vec![path[0]];
+    let mut section_start = 0;
+    for i in 1..path.len() {
+        if i == path.len() - 1 || is_corner(path[i - 1], path[i], path[i + 1]) {
+            resample(&path[section_start..=i], stitch_length, &mut out);
+            section_start = i;
+        }
+    }
+    Ok(out)
+}
+
+/// Whether the path turns sharper than [`CORNER_ANGLE`] at `b`
+fn is_corner(a: Point, b: Point, c: Point) -> bool {
+    let (ux, uy) = (b.0 - a.0, b.1 - a.1);
+    let (vx, vy) = (c.0 - b.0, c.1 - b.1);
+    let turn = (ux * vy - uy * vx).atan2(ux * vx + uy * vy).abs();
+    turn > CORNER_ANGLE.to_radians()
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Append points evenly spaced by arc length along `samples`, excluding its start
+pub(crate) fn resample(samples: &[Point], stitch_length: f64, out: &mut Vec<Point>) {
+    let lengths: Vec<f64> = samples.windows(2).map(|w| distance(w[0], w[1])).collect();
+    let total: f64 = lengths.iter().sum();
+    if total == 0.0 {
+        return;
+    }
+    let count = (total / stitch_length).ceil().max(1.0) as usize;
+    let step = total / count as f64;
+
+    let mut index = 0;
+    let mut walked = 0.0;
+    for i in 1..count {
+        let target = step * i as f64;
+        while index + 1 < lengths.len() && walked + lengths[index] < target {
+            walked += lengths[index];
+            index += 1;
+        }
+        let t = ((target - walked) / lengths[index]).clamp(0.0, 1.0);
+        let (a, b) = (samples[index], samples[index + 1]);
+        out.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+    }
+    out.push(samples[samples.len() - 1]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_even_spacing_on_line() {
+        let points = running_stitch(&[(0.0, 0.0), (100.0, 0.0)], 30.0, false).unwrap();
+        assert_eq!(points.len(), 5);
+        for (i, p) in points.iter().enumerate() {
+            assert!((p.0 - i as f64 * 25.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_corners_are_kept() {
+        let points = running_stitch(&[(0.0, 0.0), (45.0, 0.0), (45.0, 45.0)], 20.0, false).unwrap();
+        assert!(points.contains(&(45.0, 0.0)));
+        assert_eq!(points.len(), 7);
+    }
+
+    #[test]
+    fn test_shallow_bends_are_smoothed() {
+        // A circle sampled every 5 degrees has no corners
+        let circle: Vec<Point> = (0..72)
+            .map(|k| {
+                let a = (k as f64 * 5.0).to_radians();
+                (100.0 * a.cos(), 100.0 * a.sin())
+            })
+            .collect();
+        let points = running_stitch(&circle, 30.0, true).unwrap();
+        assert_eq!(points.first(), points.last());
+
+        let gaps: Vec<f64> = points.windows(2).map(|w| distance(w[0], w[1])).collect();
+        let (min, max) = gaps
+            .iter()
+            .fold((f64::MAX, 0.0f64), |(lo, hi), &g| (lo.min(g), hi.max(g)));
+        assert!(max <= 30.0);
+        assert!(max - min < 0.5);
+    }
+
+    #[test]
+    fn test_closed_path() {
+        let open = running_stitch(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], 5.0, false).unwrap();
+        let closed = running_stitch(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], 5.0, true).unwrap();
+        assert_eq!(open.last(), Some(&(10.0, 10.0)));
+        assert_eq!(closed.last(), Some(&(0.0, 0.0)));
+        assert!(closed.len() > open.len());
+
+        // An explicitly closed path is not closed twice
+        let explicit = running_stitch(
+            &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 0.0)],
+            5.0,
+            true,
+        )
+        .unwrap();
+        assert_eq!(explicit, closed);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(running_stitch(&[(0.0, 0.0), (10.0, 0.0)], 0.0, false).is_err());
+        assert!(running_stitch(&[(1.0, 1.0), (1.0, 1.0)], 5.0, false).is_err());
+        assert!(running_stitch(&[(0.0, 0.0), (f64::NAN, 0.0)], 5.0, false).is_err());
+        assert!(running_stitch(&[(0.0, 0.0), (1e9, 0.0)], 1e-3, false).is_err());
+    }
+}
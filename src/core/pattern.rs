@@ -3,11 +3,22 @@
 //! The core `EmbPattern` type stores stitches, threads, and metadata for embroidery designs.
 //! Supports reading/writing multiple formats, transformations, and pattern analysis.
 
+use crate::core::attributes::StitchAttributes;
 use crate::core::constants::*;
+use crate::core::consumption::ConsumptionProfile;
 use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+#[cfg(feature = "fs")]
+use crate::formats::io::options::{ReadOptions, WriteOptions};
 use crate::utils::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::fs::File;
+#[cfg(feature = "fs")]
+use std::io::{BufReader, BufWriter, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
 
 /// A single stitch with position and command
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -146,6 +157,12 @@ pub struct ThreadUsage {
     pub length_mm: f64,
     /// Number of stitches using this thread
     pub stitch_count: usize,
+    /// Estimated top thread consumed, in meters
+    ///
+    /// See [`EmbPattern::estimate_thread_consumption`] for the model.
+    pub top_thread_m: f64,
+    /// Estimated bobbin thread consumed, in meters
+    pub bobbin_thread_m: f64,
 }
 
 /// Comprehensive pattern statistics
@@ -159,11 +176,13 @@ pub struct PatternStatistics {
     pub trim_count: usize,
     /// Number of color change commands
     pub color_change_count: usize,
+    /// Number of sequins dropped
+    pub sequin_count: usize,
     /// Total stitch length in millimeters
     pub total_length_mm: f64,
     /// Total stitch length in inches
     pub total_length_inches: f64,
-    /// Estimated sewing time in minutes (based on 800 stitches/min default machine speed)
+    /// Estimated sewing time in minutes, counting each sequin drop as a machine cycle
     pub estimated_time_minutes: f64,
     /// Per-thread usage statistics
     pub thread_usage: Vec<ThreadUsage>,
@@ -179,6 +198,284 @@ pub struct PatternStatistics {
     pub max_stitch_length_mm: f64,
 }
 
+/// Usage rights attached to a design
+///
+/// Stored in pattern metadata under the `license`, `license_uses` and
+/// `license_purchaser_id` keys so it travels with the design through any format
+/// that preserves metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct License {
+    /// License name (e.g., "Personal Use", "Commercial Small Business")
+    pub name: String,
+    /// Permitted uses (e.g., "personal", "sell-finished-items")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_uses: Vec<String>,
+    /// Identifier of the purchaser the design was licensed to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purchaser_id: Option<String>,
+}
+
+impl License {
+    /// Create a license with the given name
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a permitted use
+    pub fn with_use(mut self, allowed_use: impl Into<String>) -> Self {
+        self.allowed_uses.push(allowed_use.into());
+        self
+    }
+
+    /// Set the purchaser identifier
+    pub fn with_purchaser_id(mut self, purchaser_id: impl Into<String>) -> Self {
+        self.purchaser_id = Some(purchaser_id.into());
+        self
+    }
+
+    /// Encode the permitted uses as a single metadata value
+    ///
+    /// Uses are separated by `", "`. Commas and backslashes inside a use are
+    /// escaped with a backslash so that [`decode_uses`](Self::decode_uses)
+    /// restores the original list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::core::pattern::License;
+    ///
+    /// let license = License::new("Commercial")
+    ///     .with_use("personal")
+    ///     .with_use("sell up to 50 items, no files");
+    /// let encoded = license.encode_uses();
+    /// assert_eq!(encoded, r"personal, sell up to 50 items\, no files");
+    /// assert_eq!(License::decode_uses(&encoded), license.allowed_uses);
+    /// ```
+    pub fn encode_uses(&self) -> String {
+        self.allowed_uses
+            .iter()
+            .map(|u| u.replace('\\', "\\\\").replace(',', "\\,"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Decode a metadata value written by [`encode_uses`](Self::encode_uses)
+    pub fn decode_uses(value: &str) -> Vec<String> {
+        let mut uses = Vec::new();
+        let mut current = String::new();
+        let mut chars = value.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\\' => current.extend(chars.next()),
+                ',' => uses.push(std::mem::take(&mut current)),
+                _ => current.push(ch),
+            }
+        }
+        uses.push(current);
+
+        uses.into_iter()
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .collect()
+    }
+
+    /// Check whether a use is permitted (case-insensitive)
+    pub fn allows(&self, allowed_use: &str) -> bool {
+        self.allowed_uses
+            .iter()
+            .any(|u| u.eq_ignore_ascii_case(allowed_use))
+    }
+
+    /// Single-line notice suitable for free-text fields in file headers
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::core::pattern::License;
+    ///
+    /// let license = License::new("Personal Use")
+    ///     .with_use("personal")
+    ///     .with_purchaser_id("ORD-1042");
+    /// assert_eq!(
+    ///     license.notice(),
+    ///     "License: Personal Use; Uses: personal; Purchaser: ORD-1042"
+    /// );
+    /// ```
+    pub fn notice(&self) -> String {
+        let mut notice = format!("License: {}", self.name);
+        if !self.allowed_uses.is_empty() {
+            notice.push_str(&format!("; Uses: {}", self.allowed_uses.join(", ")));
+        }
+        if let Some(purchaser_id) = &self.purchaser_id {
+            notice.push_str(&format!("; Purchaser: {}", purchaser_id));
+        }
+        notice
+    }
+}
+
+/// Axis-aligned bounding box in 0.1mm units
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Bounds {
+    /// Minimum X coordinate
+    pub min_x: f64,
+    /// Minimum Y coordinate
+    pub min_y: f64,
+    /// Maximum X coordinate
+    pub max_x: f64,
+    /// Maximum Y coordinate
+    pub max_y: f64,
+}
+
+impl Bounds {
+    /// Create bounds from min/max coordinates
+    pub fn new(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Self {
+        Self {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    /// Width of the box
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    /// Height of the box
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    /// Center point of the box
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+
+    /// Bounds as a `(min_x, min_y, max_x, max_y)` tuple, as returned by `EmbPattern::bounds`
+    pub fn as_tuple(&self) -> (f64, f64, f64, f64) {
+        (self.min_x, self.min_y, self.max_x, self.max_y)
+    }
+}
+
+impl From<(f64, f64, f64, f64)> for Bounds {
+    fn from((min_x, min_y, max_x, max_y): (f64, f64, f64, f64)) -> Self {
+        Self::new(min_x, min_y, max_x, max_y)
+    }
+}
+
+/// Groups of metadata keys, used to select what survives `EmbPattern::strip_metadata`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataKey {
+    /// Design name/title
+    Name,
+    /// Author
+    Author,
+    /// Company/organization
+    Company,
+    /// Copyright notice
+    Copyright,
+    /// Description
+    Description,
+    /// Keywords
+    Keywords,
+    /// Creation date
+    Date,
+    /// Notes/comments
+    Notes,
+    /// Creating software and version
+    Software,
+    /// Hoop size
+    Hoop,
+    /// Fabric information
+    Fabric,
+    /// Design width/height
+    Dimensions,
+    /// Usage license (see `License`)
+    License,
+    /// Embedded preview images and references to them
+    Preview,
+    /// Custom anchor points (`anchor.<name>` keys)
+    Anchors,
+    /// Notes on STOP commands (`stop.<n>` keys)
+    StopNotes,
+    /// 3D foam sections (`foam.<n>` keys)
+    Foam,
+}
+
+impl MetadataKey {
+    /// Raw metadata keys covered by this group (lowercase)
+    pub fn metadata_keys(self) -> &'static [&'static str] {
+        match self {
+            MetadataKey::Name => &["name", "title"],
+            MetadataKey::Author => &["author"],
+            MetadataKey::Company => &["company", "organization"],
+            MetadataKey::Copyright => &["copyright"],
+            MetadataKey::Description => &["description"],
+            MetadataKey::Keywords => &["keywords"],
+            MetadataKey::Date => &["date"],
+            MetadataKey::Notes => &["notes", "comments"],
+            MetadataKey::Software => &["software", "software_version", "version"],
+            MetadataKey::Hoop => &["hoop_size", "hoop"],
+            MetadataKey::Fabric => &["fabric", "fabric_type"],
+            MetadataKey::Dimensions => &["design_width", "design_height"],
+            MetadataKey::License => &["license", "license_uses", "license_purchaser_id"],
+            MetadataKey::Preview => &["image_file", "preview", "thumbnail"],
+            MetadataKey::Anchors | MetadataKey::StopNotes | MetadataKey::Foam => &[],
+        }
+    }
+
+    /// Check whether a raw metadata key belongs to this group
+    ///
+    /// Matching is case-insensitive, and `<key>_raw` companions (original bytes of
+    /// legacy-encoded strings) belong to the same group as `<key>`.
+    pub fn matches(self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        if self == MetadataKey::Anchors {
+            return key.starts_with(crate::core::anchor::ANCHOR_PREFIX);
+        }
+        if self == MetadataKey::StopNotes {
+            return key.starts_with(crate::core::stop_note::STOP_NOTE_PREFIX);
+        }
+        if self == MetadataKey::Foam {
+            return key.starts_with(crate::core::foam::FOAM_PREFIX);
+        }
+        let key = key.strip_suffix("_raw").unwrap_or(&key);
+        self.metadata_keys().contains(&key)
+    }
+}
+
+/// Record count from which the `parallel` feature spreads per-stitch work across threads
+///
+/// Below it, thread start-up costs more than the loop.
+#[cfg(feature = "parallel")]
+pub(crate) const PARALLEL_THRESHOLD: usize = 1 << 14;
+
+/// Stitch count for tests that take the parallel path when the feature is enabled
+#[cfg(test)]
+pub(crate) const PARALLEL_TEST_STITCHES: usize = 40_000;
+
+#[cfg(all(test, feature = "parallel"))]
+const _: () = assert!(PARALLEL_TEST_STITCHES >= PARALLEL_THRESHOLD);
+
+/// Apply `f` to every stitch, on the rayon pool for large patterns when the
+/// `parallel` feature is enabled
+fn for_each_stitch_mut(stitches: &mut [Stitch], f: impl Fn(&mut Stitch) + Send + Sync) {
+    #[cfg(feature = "parallel")]
+    if stitches.len() >= PARALLEL_THRESHOLD {
+        use rayon::prelude::*;
+        stitches.par_iter_mut().for_each(f);
+        return;
+    }
+    stitches.iter_mut().for_each(f);
+}
+
 /// Main embroidery pattern structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbPattern {
@@ -200,6 +497,10 @@ pub struct EmbPattern {
     /// Thread color grouping (optional)
     #[serde(default, skip_serializing_if = "Option::is_none")]
     color_grouping: Option<crate::core::color_group::ThreadGrouping>,
+
+    /// Per-stitch extended attributes (optional)
+    #[serde(default, skip_serializing_if = "StitchAttributes::is_empty")]
+    attributes: StitchAttributes,
 }
 
 /// Command type for pattern iteration
@@ -296,6 +597,7 @@ impl EmbPattern {
             previous_x: 0.0,
             previous_y: 0.0,
             color_grouping: None,
+            attributes: StitchAttributes::new(),
         }
     }
 
@@ -308,6 +610,7 @@ impl EmbPattern {
             previous_x: 0.0,
             previous_y: 0.0,
             color_grouping: None,
+            attributes: StitchAttributes::new(),
         }
     }
 
@@ -321,6 +624,91 @@ impl EmbPattern {
         &self.thread_list
     }
 
+    /// Remove trailing END commands so more records can be appended
+    pub(crate) fn strip_trailing_end(&mut self) {
+        while self
+            .stitches
+            .last()
+            .is_some_and(|s| s.command & COMMAND_MASK == END)
+        {
+            self.stitches.pop();
+        }
+        let len = self.stitches.len();
+        self.attributes.remap(|i| (i < len).then_some(i));
+    }
+
+    /// Replace the stitches and threads, keeping metadata and color groups
+    ///
+    /// The new records may be in any order, so stitch attributes are dropped;
+    /// use [`rebuild_stitches`](Self::rebuild_stitches) to keep them.
+    pub(crate) fn replace_stitches(&mut self, stitches: Vec<Stitch>, threads: Vec<EmbThread>) {
+        if let Some(last) = stitches.last() {
+            self.previous_x = last.x;
+            self.previous_y = last.y;
+        }
+        self.stitches = stitches;
+        self.thread_list = threads;
+        self.attributes.clear();
+    }
+
+    /// Replace the stitches, moving stitch attributes along
+    ///
+    /// `origins` gives, for each new record, the index of the record it comes from.
+    pub(crate) fn rebuild_stitches(&mut self, stitches: Vec<Stitch>, origins: &[Option<usize>]) {
+        if let Some(last) = stitches.last() {
+            self.previous_x = last.x;
+            self.previous_y = last.y;
+        }
+        self.stitches = stitches;
+        self.attributes.rebuild(origins);
+    }
+
+    /// Per-stitch extended attributes
+    pub fn attributes(&self) -> &StitchAttributes {
+        &self.attributes
+    }
+
+    /// Per-stitch extended attributes, for editing
+    pub fn attributes_mut(&mut self) -> &mut StitchAttributes {
+        &mut self.attributes
+    }
+
+    /// Replace `remove` stitches at `index` with `insert`, returning the removed stitches
+    pub(crate) fn splice_stitches(
+        &mut self,
+        index: usize,
+        remove: usize,
+        insert: Vec<Stitch>,
+    ) -> Vec<Stitch> {
+        self.attributes.splice(index, remove, insert.len());
+        let removed = self
+            .stitches
+            .splice(index..index + remove, insert)
+            .collect();
+        if let Some(last) = self.stitches.last() {
+            self.previous_x = last.x;
+            self.previous_y = last.y;
+        }
+        removed
+    }
+
+    /// Insert a thread at `index` in the thread list
+    pub(crate) fn insert_thread(&mut self, index: usize, thread: EmbThread) {
+        self.thread_list.insert(index, thread);
+    }
+
+    /// Remove the thread at `index` from the thread list
+    pub(crate) fn take_thread(&mut self, index: usize) -> EmbThread {
+        self.thread_list.remove(index)
+    }
+
+    /// Get mutable reference to thread list
+    ///
+    /// Allows editing thread callouts in place; use `add_thread` to add threads.
+    pub fn threads_mut(&mut self) -> &mut [EmbThread] {
+        &mut self.thread_list
+    }
+
     /// Get reference to extras/metadata
     pub fn extras(&self) -> &HashMap<String, String> {
         &self.extras
@@ -372,6 +760,11 @@ impl EmbPattern {
         self.extras.get(key)
     }
 
+    /// Remove a metadata value, returning it
+    pub fn remove_metadata(&mut self, key: &str) -> Option<String> {
+        self.extras.remove(key)
+    }
+
     /// Get all metadata as an iterator
     pub fn metadata(&self) -> impl Iterator<Item = (&String, &String)> {
         self.extras.iter()
@@ -566,6 +959,99 @@ impl EmbPattern {
         self.set_metadata("company", company);
     }
 
+    /// Get license/usage-rights information
+    pub fn license(&self) -> Option<License> {
+        let name = self.get_metadata("license")?;
+        let allowed_uses = self
+            .get_metadata("license_uses")
+            .map(|s| License::decode_uses(s))
+            .unwrap_or_default();
+        let purchaser_id = self.get_metadata("license_purchaser_id").cloned();
+
+        Some(License {
+            name: name.clone(),
+            allowed_uses,
+            purchaser_id,
+        })
+    }
+
+    /// Set license/usage-rights information
+    pub fn set_license(&mut self, license: &License) {
+        self.set_metadata("license", license.name.clone());
+        if license.allowed_uses.is_empty() {
+            self.extras.remove("license_uses");
+        } else {
+            self.set_metadata("license_uses", license.encode_uses());
+        }
+        match &license.purchaser_id {
+            Some(id) => self.set_metadata("license_purchaser_id", id.clone()),
+            None => {
+                self.extras.remove("license_purchaser_id");
+            }
+        }
+    }
+
+    /// Remove license information
+    pub fn clear_license(&mut self) {
+        self.extras.remove("license");
+        self.extras.remove("license_uses");
+        self.extras.remove("license_purchaser_id");
+    }
+
+    /// Get notes/comments with the license notice appended
+    ///
+    /// Used by writers to stamp usage rights into formats that only offer a
+    /// free-text comment field. The notice is not repeated if already present.
+    pub fn comments_with_license(&self) -> Option<String> {
+        let notes = self.notes();
+        let notice = self.license().map(|l| l.notice());
+
+        match (notes, notice) {
+            (Some(notes), Some(notice)) if !notes.contains(&notice) => {
+                Some(format!("{} | {}", notes, notice))
+            }
+            (Some(notes), _) => Some(notes.to_string()),
+            (None, notice) => notice,
+        }
+    }
+
+    /// Remove all metadata except the given groups
+    ///
+    /// Author, company, notes, preview references and any unrecognized keys are
+    /// dropped unless listed in `keep`. Use this before sharing a design publicly.
+    /// Returns the removed keys, sorted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::core::pattern::{EmbPattern, MetadataKey};
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.set_title("Rose");
+    /// pattern.set_author("Jane Doe");
+    /// pattern.set_metadata("company", "Acme Stitching");
+    ///
+    /// let removed = pattern.strip_metadata(&[MetadataKey::Name]);
+    /// assert_eq!(removed, vec!["author", "company"]);
+    /// assert_eq!(pattern.title(), Some("Rose"));
+    /// assert_eq!(pattern.author(), None);
+    /// ```
+    pub fn strip_metadata(&mut self, keep: &[MetadataKey]) -> Vec<String> {
+        let mut removed: Vec<String> = self
+            .extras
+            .keys()
+            .filter(|key| !keep.iter().any(|k| k.matches(key)))
+            .cloned()
+            .collect();
+        removed.sort();
+
+        for key in &removed {
+            self.extras.remove(key);
+        }
+
+        removed
+    }
+
     /// Iterate over pattern commands
     ///
     /// Returns an iterator that yields high-level commands (Stitch, Jump, ColorChange, etc.)
@@ -643,12 +1129,28 @@ impl EmbPattern {
             return;
         }
 
-        for stitch in &mut self.stitches {
+        for_each_stitch_mut(&mut self.stitches, |stitch| {
             stitch.x += dx;
             stitch.y += dy;
-        }
+        });
         self.previous_x += dx;
         self.previous_y += dy;
+        self.transform_anchors(|x, y| (x + dx, y + dy));
+    }
+
+    /// Apply a point transform to the custom anchors stored in metadata
+    fn transform_anchors(&mut self, transform: impl Fn(f64, f64) -> (f64, f64)) {
+        use crate::core::anchor::{format_point, parse_point, ANCHOR_PREFIX};
+
+        for (key, value) in self.extras.iter_mut() {
+            if !key.starts_with(ANCHOR_PREFIX) {
+                continue;
+            }
+            if let Some((x, y)) = parse_point(value) {
+                let (x, y) = transform(x, y);
+                *value = format_point(x, y);
+            }
+        }
     }
 
     /// Move pattern center to origin
@@ -696,6 +1198,7 @@ impl EmbPattern {
         let prev_y = self.previous_y;
         self.previous_x = prev_x * cos_a - prev_y * sin_a;
         self.previous_y = prev_x * sin_a + prev_y * cos_a;
+        self.transform_anchors(|x, y| (x * cos_a - y * sin_a, x * sin_a + y * cos_a));
     }
 
     /// Rotate pattern around a specific point
@@ -745,6 +1248,7 @@ impl EmbPattern {
 
         self.previous_x *= sx;
         self.previous_y *= sy;
+        self.transform_anchors(|x, y| (x * sx, y * sy));
     }
 
     /// Scale pattern uniformly
@@ -773,6 +1277,7 @@ impl EmbPattern {
             stitch.x = -stitch.x;
         }
         self.previous_x = -self.previous_x;
+        self.transform_anchors(|x, y| (-x, y));
     }
 
     /// Flip pattern vertically (mirror across X axis)
@@ -792,6 +1297,7 @@ impl EmbPattern {
             stitch.y = -stitch.y;
         }
         self.previous_y = -self.previous_y;
+        self.transform_anchors(|x, y| (x, -y));
     }
 
     /// Apply an affine transformation matrix to all stitches
@@ -819,16 +1325,66 @@ impl EmbPattern {
     /// pattern.apply_matrix(&matrix);
     /// ```
     pub fn apply_matrix(&mut self, matrix: &crate::core::matrix::EmbMatrix) {
-        for stitch in &mut self.stitches {
-            let (new_x, new_y) = matrix.transform_point(stitch.x, stitch.y);
-            stitch.x = new_x;
-            stitch.y = new_y;
-        }
+        for_each_stitch_mut(&mut self.stitches, |stitch| {
+            (stitch.x, stitch.y) = matrix.transform_point(stitch.x, stitch.y);
+        });
 
         // Update previous position
         let (new_prev_x, new_prev_y) = matrix.transform_point(self.previous_x, self.previous_y);
         self.previous_x = new_prev_x;
         self.previous_y = new_prev_y;
+        self.transform_anchors(|x, y| matrix.transform_point(x, y));
+    }
+
+    /// Compute the bounds the pattern would have after `apply_matrix`
+    ///
+    /// Stitches are neither modified nor copied, so this is cheap enough to call
+    /// on every frame of an interactive transform preview.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    /// use butabuti::core::matrix::EmbMatrix;
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.stitch_abs(0.0, 0.0);
+    /// pattern.stitch_abs(100.0, 50.0);
+    ///
+    /// let mut matrix = EmbMatrix::new();
+    /// matrix.post_scale(2.0, None, 0.0, 0.0);
+    ///
+    /// let bounds = pattern.preview_transform(&matrix);
+    /// assert_eq!(bounds.as_tuple(), (0.0, 0.0, 200.0, 100.0));
+    /// assert_eq!(pattern.bounds(), (0.0, 0.0, 100.0, 50.0));
+    /// ```
+    pub fn preview_transform(&self, matrix: &crate::core::matrix::EmbMatrix) -> Bounds {
+        if matrix.is_identity() {
+            return self.bounds().into();
+        }
+
+        let mut min_x = f64::INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+
+        for stitch in &self.stitches {
+            // Match bounds(): skip non-finite coordinates
+            if !stitch.x.is_finite() || !stitch.y.is_finite() {
+                continue;
+            }
+            let (x, y) = matrix.transform_point(stitch.x, stitch.y);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+
+        if !min_x.is_finite() || !min_y.is_finite() || !max_x.is_finite() || !max_y.is_finite() {
+            return Bounds::default();
+        }
+
+        Bounds::new(min_x, min_y, max_x, max_y)
     }
 
     /// Split long stitches to comply with format constraints
@@ -862,10 +1418,11 @@ impl EmbPattern {
         }
 
         let mut new_stitches = Vec::new();
+        let mut origins = Vec::new();
         let mut prev_x = 0.0;
         let mut prev_y = 0.0;
 
-        for stitch in &self.stitches {
+        for (index, stitch) in self.stitches.iter().enumerate() {
             let dx = stitch.x - prev_x;
             let dy = stitch.y - prev_y;
             let length = (dx * dx + dy * dy).sqrt();
@@ -882,58 +1439,49 @@ impl EmbPattern {
                     let new_x = prev_x + segment_dx * i as f64;
                     let new_y = prev_y + segment_dy * i as f64;
                     new_stitches.push(Stitch::new(new_x, new_y, STITCH));
+                    origins.push(None);
                 }
+                // The last piece ends at the original needle point
+                *origins.last_mut().unwrap() = Some(index);
 
                 prev_x = stitch.x;
                 prev_y = stitch.y;
             } else {
                 // Keep stitch as-is
                 new_stitches.push(*stitch);
+                origins.push(Some(index));
                 prev_x = stitch.x;
                 prev_y = stitch.y;
             }
         }
 
-        self.stitches = new_stitches;
+        self.rebuild_stitches(new_stitches, &origins);
         Ok(())
     }
 
     /// Split stitches based on format-specific constraints
     ///
-    /// Automatically applies the correct max stitch length for the specified format.
-    ///
-    /// # Arguments
-    ///
-    /// * `format` - Format name (e.g., "dst", "pes", "jef")
+    /// Applies the format's [`max_stitch_length`](Format::max_stitch_length).
+    /// Fails for formats without a stitch length limit.
     ///
     /// # Example
     ///
     /// ```
     /// use butabuti::prelude::*;
+    /// use butabuti::formats::Format;
     ///
     /// let mut pattern = EmbPattern::new();
     /// pattern.stitch(200.0, 0.0);
-    /// pattern.split_to_format_limits("dst")?;  // Splits to DST's ±121 unit limit
+    /// pattern.split_to_format_limits(Format::DST)?;  // Splits to DST's ±121 unit limit
+    ///
+    /// // Format names still parse
+    /// pattern.split_to_format_limits("pes".parse()?)?;
     /// # Ok::<(), butabuti::utils::error::Error>(())
     /// ```
-    pub fn split_to_format_limits(&mut self, format: &str) -> crate::utils::error::Result<()> {
-        use crate::utils::error::Error;
-
-        let max_length = match format.to_lowercase().as_str() {
-            "dst" => 121.0,         // DST format: ±121 units (12.1mm)
-            "pes" | "pec" => 127.0, // PES/PEC: ±127 units (12.7mm)
-            "jef" => 127.0,         // JEF: ±127 units
-            "exp" => 127.0,         // EXP: ±127 units
-            "vp3" => 127.0,         // VP3: ±127 units
-            "xxx" => 127.0,         // XXX: ±127 units
-            "u01" => 127.0,         // U01: ±127 units
-            _ => {
-                return Err(Error::UnsupportedFormat(format!(
-                    "Unknown format for stitch splitting: {}",
-                    format
-                )))
-            }
-        };
+    pub fn split_to_format_limits(&mut self, format: Format) -> Result<()> {
+        let max_length = format.max_stitch_length().ok_or_else(|| {
+            Error::UnsupportedFormat(format!("{} has no stitch length limit to split to", format))
+        })?;
 
         self.split_long_stitches(max_length)
     }
@@ -962,6 +1510,7 @@ impl EmbPattern {
         }
 
         let mut new_stitches = Vec::new();
+        let mut origins = vec![Some(0)];
         new_stitches.push(self.stitches[0]);
 
         for i in 1..self.stitches.len() {
@@ -974,15 +1523,82 @@ impl EmbPattern {
                 || (current.command & !STITCH) != 0
             {
                 new_stitches.push(*current);
+                origins.push(Some(i));
             }
         }
 
-        self.stitches = new_stitches;
-        // Update previous position to match last stitch
-        if let Some(last) = self.stitches.last() {
-            self.previous_x = last.x;
-            self.previous_y = last.y;
+        self.rebuild_stitches(new_stitches, &origins);
+    }
+
+    /// Remove stitches shorter than `min_length` (0.1mm)
+    ///
+    /// Very short stitches pile thread into one hole and break it. A short stitch
+    /// in the middle of a run is dropped, so the next stitch closes the gap from
+    /// the previous needle point. When the short stitch ends a run, the run end is
+    /// kept and the stitch before it is dropped instead. The first stitch of every
+    /// run and all command stitches (jumps, trims, color changes) are preserved, so
+    /// a run of just two close stitches stays as it is.
+    ///
+    /// Returns the number of stitches removed. Run [`EmbPattern::remove_duplicates`]
+    /// first to drop zero-length stitches regardless of run position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.stitch_abs(0.0, 0.0);
+    /// pattern.stitch_abs(1.0, 0.0); // 0.1mm - removed
+    /// pattern.stitch_abs(30.0, 0.0);
+    /// pattern.remove_duplicates();
+    /// assert_eq!(pattern.remove_small_stitches(3.0)?, 1);
+    /// assert_eq!(pattern.count_stitches(), 2);
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    pub fn remove_small_stitches(&mut self, min_length: f64) -> Result<usize> {
+        if !(min_length >= 0.0 && min_length.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Minimum stitch length must be non-negative, got {}",
+                min_length
+            )));
+        }
+
+        let is_stitch = |s: &Stitch| s.command & COMMAND_MASK == STITCH;
+        let mut kept: Vec<Stitch> = Vec::with_capacity(self.stitches.len());
+        let mut origins = Vec::with_capacity(self.stitches.len());
+        let mut removed = 0;
+        for (i, current) in self.stitches.iter().enumerate() {
+            let short = is_stitch(current)
+                && kept
+                    .last()
+                    .is_some_and(|last| is_stitch(last) && last.distance_to(current) < min_length);
+            if !short {
+                kept.push(*current);
+                origins.push(Some(i));
+                continue;
+            }
+
+            let ends_run = self.stitches.get(i + 1).is_none_or(|next| !is_stitch(next));
+            if !ends_run {
+                removed += 1;
+                continue;
+            }
+            // Keep the run end; drop the stitch before it unless that starts the run
+            let before_last = kept.len().checked_sub(2).map(|k| &kept[k]);
+            if before_last.is_some_and(is_stitch) {
+                kept.pop();
+                origins.pop();
+                removed += 1;
+            }
+            kept.push(*current);
+            origins.push(Some(i));
         }
+
+        if removed > 0 {
+            self.rebuild_stitches(kept, &origins);
+        }
+        Ok(removed)
     }
 
     /// Count the number of stitches (excluding non-stitch commands)
@@ -1101,6 +1717,15 @@ impl EmbPattern {
         self.stitches.iter().filter(|s| s.command == TRIM).count()
     }
 
+    /// Count the number of sequins dropped
+    #[inline]
+    pub fn count_sequins(&self) -> usize {
+        self.stitches
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == SEQUIN_EJECT)
+            .count()
+    }
+
     /// Get pattern width in pattern units (0.1mm)
     #[inline]
     pub fn width(&self) -> f64 {
@@ -1135,6 +1760,77 @@ impl EmbPattern {
         self.add_stitch_absolute(JUMP, x, y);
     }
 
+    /// Convenience method: drop a sequin after moving by the offset
+    pub fn sequin_eject(&mut self, dx: f64, dy: f64) {
+        self.add_stitch_relative(dx, dy, SEQUIN_EJECT);
+    }
+
+    /// Drop sequins at a fixed pitch along a polyline
+    ///
+    /// See [`crate::core::sequin::SequinRun`]; travel to the start works like
+    /// [`EmbPattern::add_running_stitch_path`].
+    pub fn add_sequin_run(&mut self, points: &[(f64, f64)], spacing: f64) -> Result<()> {
+        crate::core::sequin::SequinRun::new(points.to_vec())
+            .spacing(spacing)
+            .add_to(self)
+    }
+
+    /// Stitch along a polyline with evenly spaced stitches
+    ///
+    /// Sharp corners get a needle point; see [`crate::core::path::running_stitch`].
+    /// Jumps to the start of the path unless the last stitch is already there,
+    /// trimming first if the pattern has stitches.
+    pub fn add_running_stitch_path(
+        &mut self,
+        points: &[(f64, f64)],
+        stitch_len: f64,
+    ) -> Result<()> {
+        let run = crate::core::path::running_stitch(points, stitch_len, false)?;
+        crate::core::fill::append_run(self, &run);
+        Ok(())
+    }
+
+    /// Stitch around a closed polyline, ending back at its first point
+    ///
+    /// Works like [`EmbPattern::add_running_stitch_path`].
+    pub fn add_running_stitch_loop(
+        &mut self,
+        points: &[(f64, f64)],
+        stitch_len: f64,
+    ) -> Result<()> {
+        let run = crate::core::path::running_stitch(points, stitch_len, true)?;
+        crate::core::fill::append_run(self, &run);
+        Ok(())
+    }
+
+    /// Insert a block of stitches with its own thread before the existing design
+    ///
+    /// A trim and color change separate the block from the design. Color group
+    /// thread indices are shifted so they keep pointing at the same threads.
+    pub(crate) fn prepend_color_block(&mut self, block: &[Stitch], thread: EmbThread) {
+        if self.thread_list.is_empty() && !self.stitches.is_empty() {
+            self.thread_list.push(EmbThread::new(0x000000));
+        }
+        let (x, y) = block.last().map(|s| (s.x, s.y)).unwrap_or_default();
+        let mut stitches = Vec::with_capacity(block.len() + 2 + self.stitches.len());
+        stitches.extend_from_slice(block);
+        stitches.push(Stitch::new(x, y, TRIM));
+        stitches.push(Stitch::new(x, y, COLOR_CHANGE));
+        stitches.append(&mut self.stitches);
+        self.attributes.splice(0, 0, block.len() + 2);
+        self.stitches = stitches;
+        self.thread_list.insert(0, thread);
+
+        if let Some(grouping) = &mut self.color_grouping {
+            let names: Vec<String> = grouping.group_names().cloned().collect();
+            for name in names {
+                if let Some(group) = grouping.get_group_mut(&name) {
+                    group.thread_indices = group.thread_indices.iter().map(|i| i + 1).collect();
+                }
+            }
+        }
+    }
+
     /// Convenience method: add a trim
     pub fn trim(&mut self) {
         self.add_stitch_relative(0.0, 0.0, TRIM);
@@ -1198,6 +1894,7 @@ impl EmbPattern {
         let jump_count = self.count_jumps();
         let trim_count = self.count_trims();
         let color_change_count = self.count_color_changes();
+        let sequin_count = self.count_sequins();
 
         // Total length in 0.1mm units, convert to mm
         let total_length_0_1mm = self.total_stitch_length();
@@ -1206,7 +1903,7 @@ impl EmbPattern {
 
         // Estimated time based on machine speed
         let estimated_time_minutes = if machine_speed_spm > 0.0 {
-            stitch_count as f64 / machine_speed_spm
+            (stitch_count + sequin_count) as f64 / machine_speed_spm
         } else {
             0.0
         };
@@ -1240,6 +1937,7 @@ impl EmbPattern {
             jump_count,
             trim_count,
             color_change_count,
+            sequin_count,
             total_length_mm,
             total_length_inches,
             estimated_time_minutes,
@@ -1254,58 +1952,10 @@ impl EmbPattern {
 
     /// Calculate thread usage statistics for each thread color
     ///
-    /// Returns a vector of `ThreadUsage` with stitch count and length per thread.
+    /// Returns a vector of `ThreadUsage` with stitch count and length per thread,
+    /// with consumption estimated by the default [`ConsumptionProfile`].
     fn calculate_thread_usage(&self) -> Vec<ThreadUsage> {
-        let mut usage_map: HashMap<usize, (usize, f64)> = HashMap::new();
-        let mut current_thread_index = 0;
-        let mut prev_x = 0.0;
-        let mut prev_y = 0.0;
-
-        for stitch in &self.stitches {
-            let command = extract_command(stitch.command);
-
-            // Track color changes
-            if command == COLOR_CHANGE {
-                current_thread_index += 1;
-                prev_x = stitch.x;
-                prev_y = stitch.y;
-                continue;
-            }
-
-            // Only count actual stitches (not jumps, trims, etc.)
-            if command == STITCH {
-                let dx = stitch.x - prev_x;
-                let dy = stitch.y - prev_y;
-                let length = (dx * dx + dy * dy).sqrt();
-
-                let entry = usage_map.entry(current_thread_index).or_insert((0, 0.0));
-                entry.0 += 1; // stitch count
-                entry.1 += length; // total length in 0.1mm
-            }
-
-            prev_x = stitch.x;
-            prev_y = stitch.y;
-        }
-
-        // Convert to ThreadUsage vector
-        let mut result = Vec::new();
-        for (thread_idx, (count, length_0_1mm)) in usage_map {
-            let thread = self
-                .thread_list
-                .get(thread_idx)
-                .cloned()
-                .unwrap_or_else(|| EmbThread::new(0x000000));
-
-            result.push(ThreadUsage {
-                thread,
-                length_mm: length_0_1mm / 10.0,
-                stitch_count: count,
-            });
-        }
-
-        // Sort by thread index for consistent ordering
-        result.sort_by_key(|usage| usage.thread.color);
-        result
+        self.thread_usage(&ConsumptionProfile::default())
     }
 
     /// Add metadata
@@ -1326,53 +1976,39 @@ impl EmbPattern {
             return;
         }
 
-        let mut new_stitches: Vec<Stitch> = Vec::new();
+        // Each trim replaces the jump that triggered it, so records are rewritten in place
         let mut jump_count = 0;
-        let mut last_was_jump = false;
 
-        for &stitch in &self.stitches {
-            let is_jump = stitch.command == JUMP;
-
-            if is_jump {
-                jump_count += 1;
-                last_was_jump = true;
-
-                // Check if we should add a trim after consecutive jumps
-                if jump_count >= trim_at {
-                    // Optionally check distance threshold
-                    let should_trim = if let Some(dist) = trim_distance {
-                        if let Some(last) = new_stitches.last() {
-                            let dx = stitch.x - last.x;
-                            let dy = stitch.y - last.y;
-                            (dx * dx + dy * dy).sqrt() >= dist
-                        } else {
-                            true
-                        }
-                    } else {
-                        true
-                    };
-
-                    if should_trim {
-                        // Insert trim and reset jump counter
-                        new_stitches.push(Stitch::new(stitch.x, stitch.y, TRIM));
-                        jump_count = 0;
-                        last_was_jump = false;
-                        continue;
+        for i in 0..self.stitches.len() {
+            let stitch = self.stitches[i];
+            if stitch.command != JUMP {
+                // Reset jump counter on non-jump commands
+                jump_count = 0;
+                continue;
+            }
+
+            jump_count += 1;
+
+            // Check if we should add a trim after consecutive jumps
+            if jump_count >= trim_at {
+                // Optionally check distance threshold
+                let should_trim = match (trim_distance, i.checked_sub(1)) {
+                    (Some(dist), Some(prev)) => {
+                        let last = self.stitches[prev];
+                        let dx = stitch.x - last.x;
+                        let dy = stitch.y - last.y;
+                        (dx * dx + dy * dy).sqrt() >= dist
                     }
-                }
+                    _ => true,
+                };
 
-                new_stitches.push(stitch);
-            } else {
-                // Reset jump counter on non-jump commands
-                if last_was_jump && jump_count > 0 {
+                if should_trim {
+                    // Replace the jump with a trim and reset jump counter
+                    self.stitches[i] = Stitch::new(stitch.x, stitch.y, TRIM);
                     jump_count = 0;
                 }
-                last_was_jump = false;
-                new_stitches.push(stitch);
             }
         }
-
-        self.stitches = new_stitches;
     }
 
     /// Interpolate duplicate color changes as stops
@@ -1407,24 +2043,137 @@ impl EmbPattern {
         self.stitches = new_stitches;
     }
 
-    /// Read a pattern from file (stub - to be implemented with readers)
-    pub fn read(_filename: &str) -> Result<Self> {
-        Err(Error::Unsupported(
-            "Reading not yet implemented".to_string(),
-        ))
+    /// Read a pattern from a file
+    ///
+    /// The format is chosen from the file extension (including formats registered
+    /// with [`register_global`](crate::formats::plugin::register_global)). When the
+    /// extension is missing or unknown, or the file doesn't parse as that format
+    /// (or yields no stitches), the format is detected from the file's magic bytes
+    /// instead. Files with an unambiguous signature (PES, PEC, VP3) are read by
+    /// their signature even when the extension says otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use butabuti::prelude::*;
+    ///
+    /// let pattern = EmbPattern::read("design.pes")?;
+    /// println!("{} stitches", pattern.count_stitches());
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    ///
+    /// Designs without thread colors get the threads of a sibling `.col`,
+    /// `.inf` or `.edr` file with the same name, if there is one; see
+    /// [`color_file`](crate::formats::io::color_file).
+    #[cfg(feature = "fs")]
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_with_options(path, &ReadOptions::default())
+    }
+
+    /// Read a pattern from a file with reader options
+    ///
+    /// The format is resolved as in [`read`](Self::read).
+    #[cfg(feature = "fs")]
+    pub fn read_with_options<P: AsRef<Path>>(path: P, options: &ReadOptions) -> Result<Self> {
+        use crate::formats::io::color_file::load_color_file;
+        use crate::formats::registry::FormatRegistry;
+
+        let path = path.as_ref();
+        let mut file = BufReader::new(File::open(path)?);
+        let (mut pattern, _) = FormatRegistry::new().read_pattern_detected_with_options(
+            &mut file,
+            Some(path),
+            options,
+        )?;
+        if options.color_file {
+            load_color_file(&mut pattern, path)?;
+        }
+        Ok(pattern)
+    }
+
+    /// Write the pattern to a file, choosing the format from the extension
+    ///
+    /// Patterns exceeding the format's writer limits are rejected before the file
+    /// is created.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use butabuti::prelude::*;
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.stitch_abs(0.0, 0.0);
+    /// pattern.stitch_abs(100.0, 100.0);
+    /// pattern.write("design.dst")?;
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    #[cfg(feature = "fs")]
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        use crate::formats::registry::FormatRegistry;
+
+        let path = path.as_ref();
+        let registry = FormatRegistry::new();
+        let info = registry
+            .get_format_from_path(path)
+            .filter(|info| info.can_write)
+            .ok_or_else(|| {
+                Error::UnsupportedFormat(format!(
+                    "No writer for the extension of {}",
+                    path.display()
+                ))
+            })?;
+
+        registry.check_limits(self, info.name)?;
+
+        let mut file = BufWriter::new(File::create(path)?);
+        registry.write_pattern(self, &mut file, info.name)?;
+        file.flush()?;
+        Ok(())
     }
 
-    /// Write a pattern to file (stub - to be implemented with writers)
-    pub fn write(&self, _filename: &str) -> Result<()> {
-        Err(Error::Unsupported(
-            "Writing not yet implemented".to_string(),
-        ))
+    /// Write the pattern to a file with writer options
+    ///
+    /// The format is chosen from the extension among the built-in formats.
+    /// With [`WriteOptions::color_file`] set, a color file with the same name
+    /// is written next to the design.
+    #[cfg(feature = "fs")]
+    pub fn write_with_options<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        use crate::formats::io::color_file::write_color_file;
+        use crate::formats::io::traits::PatternWriter;
+        use crate::formats::registry::FormatRegistry;
+
+        let path = path.as_ref();
+        let format = Format::from_path(path)
+            .filter(|format| format.can_write())
+            .ok_or_else(|| {
+                Error::UnsupportedFormat(format!(
+                    "No writer for the extension of {}",
+                    path.display()
+                ))
+            })?;
+
+        FormatRegistry::new().check_limits(self, format.name())?;
+
+        let mut file = BufWriter::new(File::create(path)?);
+        format.write(self, &mut file, options)?;
+        file.flush()?;
+        if let Some(color_format) = options.color_file {
+            write_color_file(self, path, color_format)?;
+        }
+        Ok(())
     }
 
     /// Get stitches grouped by color with their associated thread
     ///
     /// Returns an iterator of (stitch_block, thread) tuples where each block
     /// contains stitches of the same color
+    ///
+    /// Runs are also split at jumps and trims. See [`blocks`](Self::blocks) for
+    /// whole color blocks with their commands.
     pub fn get_as_stitchblock(&self) -> Vec<(Vec<(f64, f64)>, EmbThread)> {
         use crate::core::constants::*;
 
@@ -1867,6 +2616,51 @@ impl EmbPattern {
         Ok(())
     }
 
+    /// Validate pattern for the constraints of a format
+    ///
+    /// Runs the format's `validate_for_*` check, then the format's
+    /// [writer limits](Format::writer_limits). Formats without specific
+    /// constraints only get the writer limit check.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    /// use butabuti::formats::Format;
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.stitch_abs(0.0, 0.0);
+    /// pattern.stitch_abs(125.0, 0.0);
+    /// assert!(pattern.validate_for_format(Format::PES).is_ok());
+    /// assert!(pattern.validate_for_format(Format::DST).is_err());
+    /// ```
+    pub fn validate_for_format(&self, format: Format) -> Result<()> {
+        match format {
+            Format::DST => self.validate_for_dst()?,
+            Format::PES => self.validate_for_pes()?,
+            Format::JEF => self.validate_for_jef()?,
+            Format::EXP => self.validate_for_exp()?,
+            Format::VP3 => self.validate_for_vp3()?,
+            Format::XXX => self.validate_for_xxx()?,
+            Format::U01 => self.validate_for_u01()?,
+            _ => {}
+        }
+
+        let violations = format.writer_limits().violations(self);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let details = violations
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(Error::Encoding(format!(
+            "{} writer limits exceeded: {}",
+            format, details
+        )))
+    }
+
     // ============================================================================
     // Color Group Management
     // ============================================================================
@@ -2252,6 +3046,49 @@ mod tests {
         assert_eq!(pattern.stitches()[3].command, COLOR_CHANGE);
     }
 
+    #[test]
+    fn test_running_stitch_path() {
+        let mut pattern = EmbPattern::new();
+        pattern
+            .add_running_stitch_path(&[(0.0, 0.0), (100.0, 0.0)], 30.0)
+            .unwrap();
+        assert_eq!(pattern.stitches()[0].command, JUMP);
+        assert_eq!(pattern.count_stitches(), 5);
+
+        // Continuing from the last needle point needs no trim
+        pattern
+            .add_running_stitch_loop(&[(100.0, 0.0), (100.0, 50.0), (50.0, 50.0)], 30.0)
+            .unwrap();
+        assert_eq!(pattern.count_trims(), 0);
+        assert!(pattern.max_stitch_length() <= 30.0);
+        let last = pattern.stitches().last().unwrap();
+        assert_eq!((last.x, last.y), (100.0, 0.0));
+
+        assert!(pattern
+            .add_running_stitch_path(&[(0.0, 0.0)], 30.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sequin_statistics() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(10.0, 0.0);
+        pattern.sequin_eject(30.0, 0.0);
+        pattern.sequin_eject(30.0, 0.0);
+        pattern
+            .add_sequin_run(&[(70.0, 0.0), (130.0, 0.0)], 30.0)
+            .unwrap();
+
+        assert_eq!(pattern.count_sequins(), 5);
+        assert_eq!(pattern.count_trims(), 0);
+        let stats = pattern.calculate_statistics(100.0);
+        assert_eq!(stats.sequin_count, 5);
+        assert_eq!(stats.stitch_count, 2);
+        assert_eq!(stats.jump_count, 0);
+        assert!((stats.estimated_time_minutes - 0.07).abs() < 1e-9);
+    }
+
     #[test]
     fn test_validate_basic() {
         let pattern = EmbPattern::new();
@@ -3035,6 +3872,30 @@ mod tests {
         assert_eq!(pattern.previous_y, 20.0);
     }
 
+    #[test]
+    fn test_transform_large_pattern() {
+        use crate::core::matrix::EmbMatrix;
+
+        let mut pattern = EmbPattern::new();
+        for i in 0..PARALLEL_TEST_STITCHES {
+            pattern.stitch_abs(i as f64, -(i as f64));
+        }
+
+        pattern.translate(5.0, 5.0);
+        let mut matrix = EmbMatrix::new();
+        matrix.post_scale(2.0, None, 0.0, 0.0);
+        pattern.apply_matrix(&matrix);
+
+        for (i, stitch) in pattern.stitches().iter().enumerate() {
+            assert_eq!(stitch.x, (i as f64 + 5.0) * 2.0);
+            assert_eq!(stitch.y, (5.0 - i as f64) * 2.0);
+        }
+        assert_eq!(
+            pattern.previous_x,
+            ((PARALLEL_TEST_STITCHES - 1) as f64 + 5.0) * 2.0
+        );
+    }
+
     // Stitch splitting tests
     #[test]
     fn test_split_long_stitches_no_split_needed() {
@@ -3133,7 +3994,7 @@ mod tests {
     fn test_split_to_format_limits_dst() {
         let mut pattern = EmbPattern::new();
         pattern.stitch(250.0, 0.0); // Exceeds DST limit of 121
-        pattern.split_to_format_limits("dst").unwrap();
+        pattern.split_to_format_limits(Format::DST).unwrap();
         // Should be split into at least 3 segments
         assert!(pattern.stitches.len() >= 3);
     }
@@ -3142,7 +4003,7 @@ mod tests {
     fn test_split_to_format_limits_pes() {
         let mut pattern = EmbPattern::new();
         pattern.stitch(250.0, 0.0); // Exceeds PES limit of 127
-        pattern.split_to_format_limits("pes").unwrap();
+        pattern.split_to_format_limits(Format::PES).unwrap();
         assert!(pattern.stitches.len() >= 2);
     }
 
@@ -3150,12 +4011,16 @@ mod tests {
     fn test_split_to_format_limits_case_insensitive() {
         let mut pattern = EmbPattern::new();
         pattern.stitch(250.0, 0.0);
-        pattern.split_to_format_limits("DST").unwrap(); // Uppercase
+        pattern
+            .split_to_format_limits("DST".parse().unwrap())
+            .unwrap(); // Uppercase
         assert!(pattern.stitches.len() >= 3);
 
         let mut pattern2 = EmbPattern::new();
         pattern2.stitch(250.0, 0.0);
-        pattern2.split_to_format_limits("PeS").unwrap(); // Mixed case
+        pattern2
+            .split_to_format_limits("PeS".parse().unwrap())
+            .unwrap(); // Mixed case
         assert!(pattern2.stitches.len() >= 2);
     }
 
@@ -3163,7 +4028,8 @@ mod tests {
     fn test_split_to_format_limits_unknown_format() {
         let mut pattern = EmbPattern::new();
         pattern.stitch(250.0, 0.0);
-        assert!(pattern.split_to_format_limits("unknown").is_err());
+        assert!("unknown".parse::<Format>().is_err());
+        assert!(pattern.split_to_format_limits(Format::JSON).is_err());
     }
 
     #[test]
@@ -3192,6 +4058,44 @@ mod tests {
         assert_eq!(last.y, 100.0);
     }
 
+    #[test]
+    fn test_remove_small_stitches() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(1.0, 0.0); // Short, mid-run - dropped
+        pattern.stitch_abs(2.0, 0.0); // Still short from the previous needle point
+        pattern.stitch_abs(30.0, 0.0);
+        pattern.stitch_abs(31.0, 0.0); // Short run end - kept, 30,0 dropped
+        pattern.trim();
+        pattern.jump_abs(100.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0); // Run start - kept
+        pattern.stitch_abs(101.0, 0.0); // Two-stitch run stays
+        pattern.end();
+
+        assert_eq!(pattern.remove_small_stitches(3.0).unwrap(), 3);
+        let points: Vec<(f64, f64, u32)> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.x, s.y, s.command))
+            .collect();
+        assert_eq!(
+            points,
+            vec![
+                (0.0, 0.0, STITCH),
+                (31.0, 0.0, STITCH),
+                (31.0, 0.0, TRIM),
+                (100.0, 0.0, JUMP),
+                (100.0, 0.0, STITCH),
+                (101.0, 0.0, STITCH),
+                (101.0, 0.0, END),
+            ]
+        );
+
+        // Nothing left to remove, and bad lengths are rejected
+        assert_eq!(pattern.remove_small_stitches(3.0).unwrap(), 0);
+        assert!(pattern.remove_small_stitches(f64::NAN).is_err());
+    }
+
     // Remove duplicates tests
     #[test]
     fn test_remove_duplicates_empty_pattern() {
@@ -3825,6 +4729,125 @@ mod tests {
         assert_eq!(pattern.company(), Some("Acme Embroidery")); // "company" takes precedence
     }
 
+    #[test]
+    fn test_license_metadata() {
+        let mut pattern = EmbPattern::new();
+        assert!(pattern.license().is_none());
+        assert!(pattern.comments_with_license().is_none());
+
+        let license = License::new("Commercial")
+            .with_use("personal")
+            .with_use("sell-finished-items")
+            .with_purchaser_id("ORD-7");
+        pattern.set_license(&license);
+
+        assert_eq!(pattern.license(), Some(license.clone()));
+        assert!(license.allows("Sell-Finished-Items"));
+        assert!(!license.allows("resell-files"));
+        assert_eq!(
+            pattern.get_metadata("license_uses"),
+            Some(&"personal, sell-finished-items".to_string())
+        );
+
+        assert_eq!(pattern.comments_with_license(), Some(license.notice()));
+        pattern.set_notes("Stitch on denim");
+        let stamped = pattern.comments_with_license().unwrap();
+        assert!(stamped.starts_with("Stitch on denim | License: Commercial"));
+
+        pattern.set_notes(stamped.clone());
+        assert_eq!(pattern.comments_with_license(), Some(stamped));
+
+        let with_comma = License::new("Commercial")
+            .with_use("sell up to 50 items, no files")
+            .with_use(r"C:\designs");
+        pattern.set_license(&with_comma);
+        assert_eq!(pattern.license(), Some(with_comma));
+
+        pattern.set_license(&License::new("Personal"));
+        assert!(pattern.get_metadata("license_uses").is_none());
+        assert!(pattern.get_metadata("license_purchaser_id").is_none());
+
+        pattern.clear_license();
+        assert!(pattern.license().is_none());
+    }
+
+    #[test]
+    fn test_preview_transform_matches_apply_matrix() {
+        use crate::core::matrix::EmbMatrix;
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 40.0);
+        pattern.add_stitch_absolute(STITCH, f64::NAN, 10.0);
+
+        let mut matrix = EmbMatrix::new();
+        matrix.post_rotate(30.0, 50.0, 20.0);
+        matrix.post_translate(-10.0, 5.0);
+
+        let preview = pattern.preview_transform(&matrix);
+        let original = pattern.bounds();
+
+        let mut transformed = pattern.clone();
+        transformed.apply_matrix(&matrix);
+        let (min_x, min_y, max_x, max_y) = transformed.bounds();
+
+        assert!((preview.min_x - min_x).abs() < 1e-9);
+        assert!((preview.min_y - min_y).abs() < 1e-9);
+        assert!((preview.max_x - max_x).abs() < 1e-9);
+        assert!((preview.max_y - max_y).abs() < 1e-9);
+        assert_eq!(pattern.bounds(), original);
+
+        assert_eq!(
+            EmbPattern::new().preview_transform(&matrix),
+            Bounds::default()
+        );
+        assert_eq!(
+            pattern.preview_transform(&EmbMatrix::new()).as_tuple(),
+            original
+        );
+    }
+
+    #[test]
+    fn test_strip_metadata() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 1.0, 2.0);
+        pattern.set_title("Rose");
+        pattern.set_author("Jane Doe");
+        pattern.set_notes("Call me at 555-0100");
+        pattern.set_metadata("Name", "Rose");
+        pattern.set_metadata("author_raw", "8341");
+        pattern.set_metadata("image_file", "C:/Users/jane/rose.png");
+        pattern.set_metadata("internal_ticket", "T-17");
+        pattern.set_license(&License::new("Personal"));
+        pattern.set_anchor("hole", 1.0, 1.0);
+
+        let removed = pattern.strip_metadata(&[
+            MetadataKey::Name,
+            MetadataKey::License,
+            MetadataKey::Anchors,
+        ]);
+
+        assert_eq!(
+            removed,
+            vec![
+                "author",
+                "author_raw",
+                "image_file",
+                "internal_ticket",
+                "notes"
+            ]
+        );
+        assert_eq!(pattern.title(), Some("Rose"));
+        assert!(pattern.get_metadata("Name").is_some());
+        assert!(pattern.license().is_some());
+        assert_eq!(pattern.anchor("hole"), Some((1.0, 1.0)));
+        assert_eq!(pattern.stitches().len(), 1);
+
+        assert_eq!(pattern.strip_metadata(&[]).len(), 4);
+        assert_eq!(pattern.metadata().count(), 0);
+    }
+
     #[test]
     fn test_comprehensive_metadata() {
         let mut pattern = EmbPattern::new();
@@ -4506,4 +5529,49 @@ mod tests {
         assert_eq!(stats.thread_usage[0].stitch_count, 1);
         assert_eq!(stats.thread_usage[0].thread.color, 0x000000); // Default black
     }
+
+    #[test]
+    fn test_read_write_dispatch() {
+        let dir = std::env::temp_dir().join(format!("butabuti_dispatch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 50.0);
+        pattern.stitch_abs(200.0, 0.0);
+        pattern.end();
+
+        for ext in ["dst", "exp", "jef", "json"] {
+            let path = dir.join(format!("design.{}", ext));
+            pattern.write(&path).unwrap();
+            let read = EmbPattern::read(&path).unwrap();
+            assert_eq!(read.count_stitches(), 3, "{}", ext);
+        }
+
+        // Missing extension: detected from the DST header
+        std::fs::copy(dir.join("design.dst"), dir.join("design")).unwrap();
+        assert_eq!(
+            EmbPattern::read(dir.join("design"))
+                .unwrap()
+                .count_stitches(),
+            3
+        );
+
+        // Wrong extension: the CSV reader finds no stitches, the content is JSON
+        std::fs::copy(dir.join("design.json"), dir.join("json.csv")).unwrap();
+        assert_eq!(
+            EmbPattern::read(dir.join("json.csv"))
+                .unwrap()
+                .count_stitches(),
+            3
+        );
+
+        std::fs::write(dir.join("garbage"), [0xFFu8; 64]).unwrap();
+        assert!(EmbPattern::read(dir.join("garbage")).is_err());
+        assert!(pattern.write(dir.join("design.xyz")).is_err());
+        assert!(!dir.join("design.xyz").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
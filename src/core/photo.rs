@@ -0,0 +1,570 @@
+//! Photo-stitch generation from raster images
+//!
+//! Stitches an image as zigzag lines whose width and density follow the image:
+//! dark areas get wide, closely spaced zigzags and light areas thin, sparse ones or
+//! none at all. The lines run as parallel scanlines or as one spiral out from the
+//! image center.
+//!
+//! Colors are separated into thread layers. [`PhotoSeparation::Mono`] stitches the
+//! image darkness in one thread; [`PhotoSeparation::Cmyk`] separates it into cyan,
+//! magenta, yellow and black layers, each at its own screen angle like printed
+//! halftones, stitched light to dark. Layer threads can be any color; to match a
+//! thread chart, remap the result with [`crate::core::remap`].
+//!
+//! The image is an [`RgbaImage`], as produced by the renderer or decoded with
+//! [`RgbaImage::decode`] (`graphics` feature). Transparent pixels are not stitched.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::core::photo::{PhotoPath, PhotoStitch};
+//! use butabuti::render::RgbaImage;
+//!
+//! // Horizontal gradient from white to black
+//! let (width, height) = (40, 20);
+//! let mut pixels = Vec::new();
+//! for _ in 0..height {
+//!     for x in 0..width {
+//!         let v = 255 - (x * 255 / (width - 1)) as u8;
+//!         pixels.extend_from_slice(&[v, v, v, 255]);
+//!     }
+//! }
+//! let image = RgbaImage { width, height, pixels };
+//!
+//! let pattern = PhotoStitch::new()
+//!     .width(400.0)
+//!     .path(PhotoPath::Spiral)
+//!     .generate(&image)?;
+//! assert!(pattern.count_stitches() > 100);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::fill::append_run;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::geometry::Point;
+use crate::render::RgbaImage;
+use crate::utils::error::{Error, Result};
+
+/// Upper bound on needle points per layer, to catch unit mistakes before allocating
+const MAX_PHOTO_STITCHES: f64 = 5_000_000.0;
+
+/// Distance between samples of a guide line in 0.1mm
+const GUIDE_STEP: f64 = 2.0;
+
+/// Shape of the lines the zigzag follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PhotoPath {
+    /// Parallel lines, alternating direction
+    Scanline,
+    /// An Archimedean spiral out from the image center
+    Spiral,
+}
+
+/// How image colors are split into thread layers
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhotoSeparation {
+    /// One layer covering the image darkness relative to white fabric
+    Mono(EmbThread),
+    /// Cyan, magenta, yellow and black layers, in that order
+    Cmyk(Box<[EmbThread; 4]>),
+}
+
+impl PhotoSeparation {
+    /// Black thread on white fabric
+    pub fn mono() -> Self {
+        PhotoSeparation::Mono(EmbThread::from_rgb(0, 0, 0))
+    }
+
+    /// Pure cyan, magenta, yellow and black threads
+    pub fn cmyk() -> Self {
+        PhotoSeparation::Cmyk(Box::new([
+            EmbThread::from_rgb(0, 174, 239).with_description("Cyan"),
+            EmbThread::from_rgb(236, 0, 140).with_description("Magenta"),
+            EmbThread::from_rgb(255, 242, 0).with_description("Yellow"),
+            EmbThread::from_rgb(0, 0, 0).with_description("Black"),
+        ]))
+    }
+}
+
+/// One thread layer of a photo-stitch
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhotoLayer {
+    /// Thread of the layer
+    pub thread: EmbThread,
+    /// Connected runs of needle points; moving between runs needs a jump
+    pub runs: Vec<Vec<Point>>,
+}
+
+/// Photo-stitch settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhotoStitch {
+    /// Width of the stitched image in 0.1mm; the height follows the aspect ratio
+    /// (default: 1000.0)
+    pub width: f64,
+    /// Line shape (default: `PhotoPath::Scanline`)
+    pub path: PhotoPath,
+    /// Scanline angle in degrees, counter-clockwise from the X axis (default: 0)
+    pub angle: f64,
+    /// Distance between lines in 0.1mm, also the widest zigzag (default: 8.0)
+    pub line_spacing: f64,
+    /// Stitch length in the darkest areas in 0.1mm (default: 8.0)
+    pub min_stitch_length: f64,
+    /// Stitch length in the lightest stitched areas in 0.1mm (default: 30.0)
+    pub max_stitch_length: f64,
+    /// Layer coverage below which nothing is stitched, 0.0 to 1.0 (default: 0.1)
+    pub min_coverage: f64,
+    /// Color separation (default: black mono)
+    pub separation: PhotoSeparation,
+}
+
+impl Default for PhotoStitch {
+    fn default() -> Self {
+        Self {
+            width: 1000.0,
+            path: PhotoPath::Scanline,
+            angle: 0.0,
+            line_spacing: 8.0,
+            min_stitch_length: 8.0,
+            max_stitch_length: 30.0,
+            min_coverage: 0.1,
+            separation: PhotoSeparation::mono(),
+        }
+    }
+}
+
+impl PhotoStitch {
+    /// Create a photo-stitch with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the stitched width in 0.1mm
+    pub fn width(mut self, width: f64) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set the line shape
+    pub fn path(mut self, path: PhotoPath) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Set the scanline angle in degrees
+    pub fn angle(mut self, angle: f64) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    /// Set the distance between lines in 0.1mm
+    pub fn line_spacing(mut self, line_spacing: f64) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// Set the stitch lengths used in the darkest and lightest areas in 0.1mm
+    pub fn stitch_length(mut self, min: f64, max: f64) -> Self {
+        self.min_stitch_length = min;
+        self.max_stitch_length = max;
+        self
+    }
+
+    /// Set the coverage below which nothing is stitched
+    pub fn min_coverage(mut self, min_coverage: f64) -> Self {
+        self.min_coverage = min_coverage.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the color separation
+    pub fn separation(mut self, separation: PhotoSeparation) -> Self {
+        self.separation = separation;
+        self
+    }
+
+    /// Generate the stitch runs of each thread layer, in stitching order
+    ///
+    /// Layers without any stitches are left out.
+    pub fn layers(&self, image: &RgbaImage) -> Result<Vec<PhotoLayer>> {
+        self.validate(image)?;
+        let scale = self.width / image.width as f64;
+        let size = (self.width, image.height as f64 * scale);
+
+        let passes: Vec<(EmbThread, f64, CoverageMap)> = match &self.separation {
+            PhotoSeparation::Mono(thread) => {
+                let thread_light = luminance([thread.red(), thread.green(), thread.blue()]);
+                let range = (1.0 - thread_light).max(f64::EPSILON);
+                let map = CoverageMap::new(image, scale, |rgb| {
+                    ((1.0 - luminance(rgb)) / range).clamp(0.0, 1.0)
+                });
+                vec![(thread.clone(), 0.0, map)]
+            }
+            PhotoSeparation::Cmyk(threads) => {
+                // Stitched light to dark, at the usual halftone screen angles
+                [(2, 0.0), (0, 15.0), (1, 75.0), (3, 45.0)]
+                    .into_iter()
+                    .map(|(channel, screen)| {
+                        let map = CoverageMap::new(image, scale, |rgb| cmyk(rgb)[channel]);
+                        (threads[channel].clone(), screen, map)
+                    })
+                    .collect()
+            }
+        };
+
+        let mut layers = Vec::new();
+        for (thread, screen, map) in passes {
+            let guides = match self.path {
+                PhotoPath::Scanline => scanlines(size, self.angle + screen, self.line_spacing),
+                PhotoPath::Spiral => spiral(size, screen, self.line_spacing),
+            };
+            let runs: Vec<Vec<Point>> = guides
+                .iter()
+                .flat_map(|guide| self.modulate(guide, &map))
+                .collect();
+            if !runs.is_empty() {
+                layers.push(PhotoLayer { thread, runs });
+            }
+        }
+        Ok(layers)
+    }
+
+    /// Generate a pattern with one color block per thread layer
+    ///
+    /// Runs are joined with a trim and a jump.
+    pub fn generate(&self, image: &RgbaImage) -> Result<EmbPattern> {
+        let mut pattern = EmbPattern::new();
+        for (i, layer) in self.layers(image)?.into_iter().enumerate() {
+            if i > 0 {
+                pattern.trim();
+                pattern.color_change(0.0, 0.0);
+            }
+            pattern.add_thread(layer.thread);
+            for run in &layer.runs {
+                append_run(&mut pattern, run);
+            }
+        }
+        pattern.end();
+        Ok(pattern)
+    }
+
+    fn validate(&self, image: &RgbaImage) -> Result<()> {
+        if image.width == 0
+            || image.height == 0
+            || image.pixels.len() < image.width * image.height * 4
+        {
+            return Err(Error::InvalidPattern(format!(
+                "Photo-stitch needs a non-empty image, got {}x{} with {} bytes",
+                image.width,
+                image.height,
+                image.pixels.len()
+            )));
+        }
+        for (name, value) in [
+            ("width", self.width),
+            ("line spacing", self.line_spacing),
+            ("minimum stitch length", self.min_stitch_length),
+            ("maximum stitch length", self.max_stitch_length),
+        ] {
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(Error::InvalidPattern(format!(
+                    "Photo-stitch {} must be positive, got {}",
+                    name, value
+                )));
+            }
+        }
+        if self.max_stitch_length < self.min_stitch_length {
+            return Err(Error::InvalidPattern(format!(
+                "Photo-stitch maximum stitch length {} is below the minimum {}",
+                self.max_stitch_length, self.min_stitch_length
+            )));
+        }
+
+        let height = self.width * image.height as f64 / image.width as f64;
+        let stitches = self.width * height / self.line_spacing / self.min_stitch_length;
+        if stitches > MAX_PHOTO_STITCHES {
+            return Err(Error::limit_exceeded(
+                "Photo-stitch needle points per layer",
+                MAX_PHOTO_STITCHES as usize,
+                stitches as usize,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Zigzag along a guide line, breaking it where the coverage is too low
+    fn modulate(&self, guide: &[Point], map: &CoverageMap) -> Vec<Vec<Point>> {
+        let mut runs = Vec::new();
+        let mut run: Vec<Point> = Vec::new();
+        let mut finish = |run: &mut Vec<Point>| {
+            if run.len() >= 2 {
+                runs.push(std::mem::take(run));
+            } else {
+                run.clear();
+            }
+        };
+
+        let mut travelled = 0.0;
+        let mut next = 0.0;
+        let mut side = 1.0;
+        for pair in guide.windows(2) {
+            let ((ax, ay), (bx, by)) = (pair[0], pair[1]);
+            let length = (bx - ax).hypot(by - ay);
+            if length <= 0.0 {
+                continue;
+            }
+            let (nx, ny) = (-(by - ay) / length, (bx - ax) / length);
+
+            while next <= travelled + length {
+                let t = (next - travelled) / length;
+                let (x, y) = (ax + (bx - ax) * t, ay + (by - ay) * t);
+                let coverage = map.sample(x, y);
+                if coverage < self.min_coverage {
+                    finish(&mut run);
+                    next += self.min_stitch_length;
+                    continue;
+                }
+
+                let offset = side * coverage * self.line_spacing / 2.0;
+                side = -side;
+                run.push((x + nx * offset, y + ny * offset));
+                next += self.max_stitch_length
+                    - coverage * (self.max_stitch_length - self.min_stitch_length);
+            }
+            travelled += length;
+        }
+        finish(&mut run);
+        runs
+    }
+}
+
+/// Per-pixel layer coverage, sampled bilinearly in pattern units
+struct CoverageMap {
+    width: usize,
+    height: usize,
+    scale: f64,
+    values: Vec<f64>,
+}
+
+impl CoverageMap {
+    fn new(image: &RgbaImage, scale: f64, coverage: impl Fn([u8; 3]) -> f64) -> Self {
+        let values = image.pixels[..image.width * image.height * 4]
+            .chunks_exact(4)
+            .map(|p| coverage([p[0], p[1], p[2]]) * p[3] as f64 / 255.0)
+            .collect();
+        Self {
+            width: image.width,
+            height: image.height,
+            scale,
+            values,
+        }
+    }
+
+    /// Coverage at a pattern position; zero outside the image
+    fn sample(&self, x: f64, y: f64) -> f64 {
+        // Pixel centers sit at half-pixel offsets
+        let px = x / self.scale - 0.5;
+        let py = y / self.scale - 0.5;
+        if px < -0.5 || py < -0.5 || px > self.width as f64 - 0.5 || py > self.height as f64 - 0.5 {
+            return 0.0;
+        }
+        let (x0, y0) = (px.floor(), py.floor());
+        let (fx, fy) = (px - x0, py - y0);
+        let at = |ix: f64, iy: f64| {
+            let ix = (ix.max(0.0) as usize).min(self.width - 1);
+            let iy = (iy.max(0.0) as usize).min(self.height - 1);
+            self.values[iy * self.width + ix]
+        };
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1.0, y0) * fx;
+        let bottom = at(x0, y0 + 1.0) * (1.0 - fx) + at(x0 + 1.0, y0 + 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Relative luminance of an sRGB color, 0.0 (black) to 1.0 (white)
+fn luminance([r, g, b]: [u8; 3]) -> f64 {
+    (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) / 255.0
+}
+
+/// Naive CMYK separation with full black generation
+fn cmyk([r, g, b]: [u8; 3]) -> [f64; 4] {
+    let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+    let k = 1.0 - r.max(g).max(b);
+    if k >= 1.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let ink = |channel: f64| (1.0 - channel - k) / (1.0 - k);
+    [ink(r), ink(g), ink(b), k]
+}
+
+/// Parallel lines covering a `size` rectangle at `angle`, alternating direction
+fn scanlines((width, height): (f64, f64), angle: f64, spacing: f64) -> Vec<Vec<Point>> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let center = (width / 2.0, height / 2.0);
+    // Half extents of the rectangle along and across the lines
+    let half_along = (width * cos.abs() + height * sin.abs()) / 2.0;
+    let half_across = (width * sin.abs() + height * cos.abs()) / 2.0;
+
+    let count = (2.0 * half_across / spacing).floor() as usize + 1;
+    let offset = half_across - (count - 1) as f64 * spacing / 2.0;
+    (0..count)
+        .map(|i| {
+            let v = -half_across + offset + i as f64 * spacing;
+            let (start, end) = if i % 2 == 0 {
+                (-half_along, half_along)
+            } else {
+                (half_along, -half_along)
+            };
+            let steps = ((2.0 * half_along) / GUIDE_STEP).ceil().max(1.0) as usize;
+            (0..=steps)
+                .map(|s| {
+                    let u = start + (end - start) * s as f64 / steps as f64;
+                    (center.0 + u * cos - v * sin, center.1 + u * sin + v * cos)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Archimedean spiral out from the center of a `size` rectangle to its corners
+fn spiral((width, height): (f64, f64), start_angle: f64, spacing: f64) -> Vec<Vec<Point>> {
+    let center = (width / 2.0, height / 2.0);
+    let max_radius = center.0.hypot(center.1);
+    let growth = spacing / std::f64::consts::TAU;
+    let start = start_angle.to_radians();
+
+    let mut points = Vec::new();
+    let mut theta: f64 = 0.0;
+    loop {
+        let radius = growth * theta;
+        let (sin, cos) = (theta + start).sin_cos();
+        points.push((center.0 + radius * cos, center.1 + radius * sin));
+        if radius > max_radius {
+            break;
+        }
+        theta += GUIDE_STEP / radius.max(spacing);
+    }
+    vec![points]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+
+    /// Left half black, right half white
+    fn half_black(width: usize, height: usize) -> RgbaImage {
+        let mut pixels = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let v = if x < width / 2 { 0 } else { 255 };
+                pixels.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        RgbaImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn needle_points(pattern: &EmbPattern) -> Vec<Point> {
+        pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| (s.x, s.y))
+            .collect()
+    }
+
+    #[test]
+    fn test_scanline_follows_darkness() {
+        let image = half_black(20, 10);
+        let pattern = PhotoStitch::new().width(200.0).generate(&image).unwrap();
+
+        let points = needle_points(&pattern);
+        assert!(!points.is_empty());
+        // White fabric is left bare; the zigzag stays within a line spacing of the dark half
+        assert!(points
+            .iter()
+            .all(|&(x, y)| x <= 104.0 && (-4.0..=104.0).contains(&y)));
+        assert_eq!(pattern.threads().len(), 1);
+        assert_eq!(pattern.count_color_changes(), 0);
+    }
+
+    #[test]
+    fn test_darker_areas_get_more_stitches() {
+        // Left half mid grey, right half black
+        let mut image = half_black(20, 10);
+        for (i, pixel) in image.pixels.chunks_exact_mut(4).enumerate() {
+            if i % 20 < 10 {
+                pixel[..3].copy_from_slice(&[128, 128, 128]);
+            } else {
+                pixel[..3].copy_from_slice(&[0, 0, 0]);
+            }
+        }
+        let pattern = PhotoStitch::new()
+            .width(200.0)
+            .path(PhotoPath::Spiral)
+            .generate(&image)
+            .unwrap();
+
+        let points = needle_points(&pattern);
+        let grey = points.iter().filter(|p| p.0 < 100.0).count();
+        let black = points.len() - grey;
+        assert!(black > grey * 3 / 2, "black {} grey {}", black, grey);
+    }
+
+    #[test]
+    fn test_cmyk_layers() {
+        // Pure red needs magenta and yellow only
+        let image = RgbaImage {
+            width: 4,
+            height: 4,
+            pixels: [255, 0, 0, 255].repeat(16),
+        };
+        let photo = PhotoStitch::new()
+            .width(100.0)
+            .separation(PhotoSeparation::cmyk());
+        let layers = photo.layers(&image).unwrap();
+        let names: Vec<_> = layers
+            .iter()
+            .map(|l| l.thread.description.clone().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Yellow", "Magenta"]);
+
+        let pattern = photo.generate(&image).unwrap();
+        assert_eq!(pattern.threads().len(), 2);
+        assert_eq!(pattern.count_color_changes(), 1);
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        let image = half_black(4, 4);
+        assert!(PhotoStitch::new()
+            .line_spacing(0.0)
+            .generate(&image)
+            .is_err());
+        assert!(PhotoStitch::new()
+            .stitch_length(20.0, 10.0)
+            .generate(&image)
+            .is_err());
+        assert!(PhotoStitch::new()
+            .width(1e7)
+            .line_spacing(1.0)
+            .generate(&image)
+            .is_err());
+        let empty = RgbaImage {
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+        };
+        assert!(PhotoStitch::new().generate(&empty).is_err());
+    }
+
+    #[test]
+    fn test_cmyk_separation() {
+        assert_eq!(cmyk([0, 0, 0]), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(cmyk([255, 255, 255]), [0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(cmyk([0, 255, 255]), [1.0, 0.0, 0.0, 0.0]);
+    }
+}
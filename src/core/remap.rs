@@ -0,0 +1,307 @@
+//! Thread remapping to a target thread chart
+//!
+//! Replaces every thread of a design with the perceptually nearest entry of a
+//! thread chart (CIE76 delta-E in LAB space), for example before exporting for
+//! a machine brand or ordering threads of a particular line.
+//!
+//! Distinct source colors often collapse to the same chart entry. Consecutive
+//! color blocks that end up with the same entry can be merged by dropping the
+//! color change between them. Blocks of the same entry that are separated by
+//! other colors stay apart; [`EmbPattern::optimize_color_order`] can merge
+//! those afterwards.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::remap::RemapOptions;
+//!
+//! let mut pattern = EmbPattern::new();
+//! for color in [0xC8102E, 0xC5122F, 0x1F4FA3] {
+//!     if !pattern.threads().is_empty() {
+//!         pattern.color_change(0.0, 0.0);
+//!     }
+//!     pattern.add_thread(EmbThread::new(color));
+//!     pattern.stitch_abs(0.0, 0.0);
+//!     pattern.stitch_abs(100.0, 0.0);
+//! }
+//! pattern.end();
+//!
+//! let chart = PaletteLibrary::isacord();
+//! let report = pattern
+//!     .remap_threads_to_palette(&chart.threads, &RemapOptions::default())
+//!     .unwrap();
+//! assert_eq!(report.threads_merged, 1);
+//! assert_eq!(pattern.threads().len(), 2);
+//! assert_eq!(pattern.threads()[0].catalog_number.as_deref(), Some("1902"));
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, Result};
+
+/// Options for [`EmbPattern::remap_threads_to_palette`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemapOptions {
+    /// Merge consecutive color blocks that map to the same chart entry (default: true)
+    pub merge_same_threads: bool,
+    /// Keep the original thread's description when the chart entry has none (default: false)
+    pub keep_descriptions: bool,
+}
+
+impl Default for RemapOptions {
+    fn default() -> Self {
+        Self {
+            merge_same_threads: true,
+            keep_descriptions: false,
+        }
+    }
+}
+
+impl RemapOptions {
+    /// Create options with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether consecutive blocks of the same chart entry are merged
+    pub fn merge_same_threads(mut self, merge: bool) -> Self {
+        self.merge_same_threads = merge;
+        self
+    }
+
+    /// Set whether original descriptions fill in missing chart descriptions
+    pub fn keep_descriptions(mut self, keep: bool) -> Self {
+        self.keep_descriptions = keep;
+        self
+    }
+}
+
+/// How one original thread was remapped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThreadMapping {
+    /// Index of the original thread
+    pub original: usize,
+    /// Index of the chosen chart entry
+    pub palette_index: usize,
+    /// Delta-E between the original color and the chart entry
+    pub delta_e: f32,
+}
+
+/// Outcome of [`EmbPattern::remap_threads_to_palette`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RemapReport {
+    /// Chart entry chosen for each original thread, in thread order
+    pub mappings: Vec<ThreadMapping>,
+    /// Threads dropped because their block was merged into the previous one
+    pub threads_merged: usize,
+    /// Color changes before remapping
+    pub color_changes_before: usize,
+    /// Color changes after remapping
+    pub color_changes_after: usize,
+}
+
+impl RemapReport {
+    /// Largest delta-E of any thread, 0 for a pattern without threads
+    pub fn max_delta_e(&self) -> f32 {
+        self.mappings.iter().map(|m| m.delta_e).fold(0.0, f32::max)
+    }
+}
+
+impl EmbPattern {
+    /// Replace every thread with the nearest entry of a thread chart
+    ///
+    /// Colors are matched by delta-E. With
+    /// [`merge_same_threads`](RemapOptions::merge_same_threads), a color change
+    /// between two blocks that map to the same chart entry is removed together
+    /// with the second block's thread. Color group thread indices follow their
+    /// threads. Patterns using `NEEDLE_SET` keep one thread per needle and are
+    /// never merged.
+    ///
+    /// Fails if the chart is empty.
+    pub fn remap_threads_to_palette(
+        &mut self,
+        palette: &[EmbThread],
+        options: &RemapOptions,
+    ) -> Result<RemapReport> {
+        if palette.is_empty() {
+            return Err(Error::InvalidPattern(
+                "Cannot remap threads to an empty palette".to_string(),
+            ));
+        }
+
+        let mut mappings = Vec::with_capacity(self.threads().len());
+        let mut remapped = Vec::with_capacity(self.threads().len());
+        for (original, thread) in self.threads().iter().enumerate() {
+            let (palette_index, delta_e) = thread
+                .find_closest_delta_e(palette)
+                .expect("palette is not empty");
+            let mut target = palette[palette_index].clone();
+            if options.keep_descriptions && target.description.is_none() {
+                target.description = thread.description.clone();
+            }
+            mappings.push(ThreadMapping {
+                original,
+                palette_index,
+                delta_e,
+            });
+            remapped.push(target);
+        }
+
+        let color_changes_before = self.count_color_changes();
+        let merge = options.merge_same_threads
+            && !self
+                .stitches()
+                .iter()
+                .any(|s| s.command & COMMAND_MASK == NEEDLE_SET);
+
+        // Old thread index -> new thread index
+        let mut thread_map: Vec<usize> = (0..remapped.len()).collect();
+        let mut threads_merged = 0;
+        let (new_stitches, new_threads) = if merge {
+            let same = |a: usize, b: usize| {
+                matches!((mappings.get(a), mappings.get(b)),
+                    (Some(a), Some(b)) if a.palette_index == b.palette_index)
+            };
+            let mut new_stitches = Vec::with_capacity(self.stitches().len());
+            let mut block = 0;
+            for stitch in self.stitches() {
+                if stitch.command & COMMAND_MASK == COLOR_CHANGE {
+                    block += 1;
+                    if same(block - 1, block) {
+                        threads_merged += 1;
+                        continue;
+                    }
+                }
+                new_stitches.push(*stitch);
+            }
+
+            let mut new_threads: Vec<EmbThread> = Vec::with_capacity(remapped.len());
+            for (index, thread) in remapped.into_iter().enumerate() {
+                if index > 0 && index <= block && same(index - 1, index) {
+                    thread_map[index] = new_threads.len() - 1;
+                } else {
+                    thread_map[index] = new_threads.len();
+                    new_threads.push(thread);
+                }
+            }
+            (new_stitches, new_threads)
+        } else {
+            (self.stitches().to_vec(), remapped)
+        };
+
+        self.replace_stitches(new_stitches, new_threads);
+        if let Some(grouping) = self.color_grouping_mut() {
+            let names: Vec<String> = grouping.group_names().cloned().collect();
+            for name in names {
+                if let Some(group) = grouping.get_group_mut(&name) {
+                    group.thread_indices = group
+                        .thread_indices
+                        .iter()
+                        .map(|&i| thread_map.get(i).copied().unwrap_or(i))
+                        .collect();
+                }
+            }
+        }
+
+        Ok(RemapReport {
+            mappings,
+            threads_merged,
+            color_changes_before,
+            color_changes_after: self.count_color_changes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::color_group::ColorGroup;
+
+    fn chart() -> Vec<EmbThread> {
+        vec![
+            EmbThread::new(0xFF0000)
+                .with_description("Red")
+                .with_catalog_number("100"),
+            EmbThread::new(0x0000FF).with_catalog_number("200"),
+            EmbThread::new(0x000000)
+                .with_description("Black")
+                .with_catalog_number("300"),
+        ]
+    }
+
+    fn blocks(colors: &[u32]) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for (i, &color) in colors.iter().enumerate() {
+            if i > 0 {
+                pattern.trim();
+                pattern.color_change(0.0, 0.0);
+            }
+            pattern.add_thread(EmbThread::new(color).with_description(format!("Original {}", i)));
+            pattern.jump_abs(i as f64 * 100.0, 0.0);
+            pattern.stitch_abs(i as f64 * 100.0 + 50.0, 0.0);
+        }
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_merges_consecutive_blocks() {
+        let mut pattern = blocks(&[0xEE1111, 0xDD0000, 0x1010EE, 0xFF2020]);
+        let stitches_before = pattern.count_stitches();
+        let report = pattern
+            .remap_threads_to_palette(&chart(), &RemapOptions::default())
+            .unwrap();
+
+        let indices: Vec<usize> = report.mappings.iter().map(|m| m.palette_index).collect();
+        assert_eq!(indices, vec![0, 0, 1, 0]);
+        assert_eq!(report.threads_merged, 1);
+        assert_eq!(
+            (report.color_changes_before, report.color_changes_after),
+            (3, 2)
+        );
+        assert!(report.max_delta_e() > 0.0);
+
+        let colors: Vec<u32> = pattern.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0xFF0000, 0x0000FF, 0xFF0000]);
+        assert_eq!(pattern.count_stitches(), stitches_before);
+    }
+
+    #[test]
+    fn test_without_merging_and_descriptions() {
+        let mut pattern = blocks(&[0xEE1111, 0xDD0000, 0x1010EE]);
+        let options = RemapOptions::new()
+            .merge_same_threads(false)
+            .keep_descriptions(true);
+        let report = pattern
+            .remap_threads_to_palette(&chart(), &options)
+            .unwrap();
+
+        assert_eq!(report.threads_merged, 0);
+        assert_eq!(pattern.count_color_changes(), 2);
+        assert_eq!(pattern.threads().len(), 3);
+        assert_eq!(pattern.threads()[0].description.as_deref(), Some("Red"));
+        assert_eq!(
+            pattern.threads()[2].description.as_deref(),
+            Some("Original 2")
+        );
+        assert_eq!(pattern.threads()[2].catalog_number.as_deref(), Some("200"));
+    }
+
+    #[test]
+    fn test_empty_palette_and_color_groups() {
+        let mut pattern = blocks(&[0xEE1111, 0xDD0000, 0x1010EE]);
+        assert!(pattern
+            .remap_threads_to_palette(&[], &RemapOptions::default())
+            .is_err());
+
+        pattern.add_color_group(ColorGroup::with_threads("Outline", vec![2]));
+        pattern
+            .remap_threads_to_palette(&chart(), &RemapOptions::default())
+            .unwrap();
+        let group = pattern.get_color_group("Outline").unwrap();
+        assert!(group.thread_indices.contains(&1));
+        assert_eq!(group.thread_indices.len(), 1);
+    }
+}
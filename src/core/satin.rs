@@ -0,0 +1,391 @@
+//! Satin column stitch generation
+//!
+//! A satin column is a dense zig-zag between two rails, the polylines that mark the
+//! column's edges. Needle points alternate between the rails at matching fractions
+//! of their length, so the column follows curves and tapers with the rails.
+//! [`Underlay`] layers, such as an edge run or a center walk with a zig-zag, are
+//! stitched before the column.
+//!
+//! Points are in pattern units (0.1mm).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::satin::SatinColumn;
+//!
+//! // A 4mm wide column, 30mm long
+//! let left = vec![(0.0, 0.0), (0.0, 300.0)];
+//! let right = vec![(40.0, 0.0), (40.0, 300.0)];
+//!
+//! let mut pattern = EmbPattern::new();
+//! SatinColumn::new(left, right)
+//!     .density(4.0)
+//!     .pull_compensation(2.0)
+//!     .add_to(&mut pattern)?;
+//!
+//! assert_eq!(pattern.count_stitches(), 76);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::fill::append_run;
+use crate::core::pattern::EmbPattern;
+use crate::core::underlay::Underlay;
+use crate::geometry::Point;
+use crate::utils::error::{Error, Result};
+
+/// Upper bound on zig-zag stitches per column, to catch unit mistakes
+const MAX_SATIN_STITCHES: f64 = 1_000_000.0;
+
+/// Satin column between two rails
+#[derive(Debug, Clone, PartialEq)]
+pub struct SatinColumn {
+    /// First edge of the column
+    pub rail_a: Vec<Point>,
+    /// Second edge of the column
+    pub rail_b: Vec<Point>,
+    /// Distance between neighbouring stitches in 0.1mm, measured along the longer
+    /// rail (default: 4.0)
+    pub density: f64,
+    /// Extra width per stitch in 0.1mm, split between both rails, to make up for
+    /// the fabric pulling in (default: 0.0)
+    pub pull_compensation: f64,
+    /// Shortening of alternate stitches on the crowded side of curves
+    /// (default: `None`)
+    pub short_stitches: Option<ShortStitches>,
+    /// Underlay layers stitched before the column, in order (default: none)
+    pub underlay_layers: Vec<Underlay>,
+}
+
+/// Shortens every other stitch where needle points crowd on the inside of curves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShortStitches {
+    /// Fraction of the column width the shortened stitches stop short by (default: 0.25)
+    pub inset: f64,
+    /// Needle points closer than this on one rail (0.1mm) are shortened (default: 2.5)
+    pub distance: f64,
+}
+
+impl Default for ShortStitches {
+    fn default() -> Self {
+        Self {
+            inset: 0.25,
+            distance: 2.5,
+        }
+    }
+}
+
+impl SatinColumn {
+    /// Create a column between two rails with default settings
+    ///
+    /// If the rails run in opposite directions, the second one is reversed.
+    pub fn new(rail_a: Vec<Point>, rail_b: Vec<Point>) -> Self {
+        Self {
+            rail_a,
+            rail_b,
+            density: 4.0,
+            pull_compensation: 0.0,
+            short_stitches: None,
+            underlay_layers: Vec::new(),
+        }
+    }
+
+    /// Set the distance between stitches in 0.1mm
+    pub fn density(mut self, density: f64) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Set the extra stitch width in 0.1mm
+    pub fn pull_compensation(mut self, pull_compensation: f64) -> Self {
+        self.pull_compensation = pull_compensation;
+        self
+    }
+
+    /// Set the short-stitch behavior, or `None` to keep every stitch full width
+    pub fn short_stitches(mut self, short_stitches: Option<ShortStitches>) -> Self {
+        self.short_stitches = short_stitches;
+        self
+    }
+
+    /// Add an underlay layer, stitched before the column
+    pub fn add_underlay(mut self, layer: Underlay) -> Self {
+        self.underlay_layers.push(layer);
+        self
+    }
+
+    /// Needle points of the column, alternating between the rails
+    pub fn stitches(&self) -> Result<Vec<Point>> {
+        if !(self.density > 0.0 && self.density.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Satin density must be positive, got {}",
+                self.density
+            )));
+        }
+        let (rail_a, rail_b) = self.rails()?;
+
+        let count = (rail_a.length.max(rail_b.length) / self.density).ceil();
+        if count > MAX_SATIN_STITCHES {
+            return Err(Error::InvalidPattern(format!(
+                "Satin column needs more than {} stitches; check the density units",
+                MAX_SATIN_STITCHES
+            )));
+        }
+        let count = count.max(1.0) as usize;
+
+        let t = |i: usize| i as f64 / count as f64;
+        let mut points = Vec::with_capacity(count + 1);
+        for i in 0..=count {
+            let (a, b) = (rail_a.at(t(i)), rail_b.at(t(i)));
+            // Even stitches land on rail A, odd ones on rail B
+            let (rail, from, to) = if i % 2 == 0 {
+                (&rail_a, b, a)
+            } else {
+                (&rail_b, a, b)
+            };
+            let width = distance(from, to);
+            if width == 0.0 {
+                points.push(to);
+                continue;
+            }
+            let mut reach = width + self.pull_compensation / 2.0;
+
+            if let Some(short) = &self.short_stitches {
+                // Gap to the neighbouring needle point on the same rail
+                let neighbour = if i >= 2 { i - 2 } else { (i + 2).min(count) };
+                let spacing = distance(rail.at(t(i)), rail.at(t(neighbour)));
+                if (i / 2) % 2 == 1 && spacing < short.distance {
+                    reach -= width * short.inset;
+                }
+            }
+
+            let (dx, dy) = ((to.0 - from.0) / width, (to.1 - from.1) / width);
+            points.push((from.0 + dx * reach, from.1 + dy * reach));
+        }
+        Ok(points)
+    }
+
+    /// Append the underlay and the column to a pattern
+    ///
+    /// Jumps to the first needle point unless the pattern's last stitch is already
+    /// there, trimming first if the pattern has stitches. Underlay layers that end
+    /// within the column's width of the next layer's start are stitched straight
+    /// over to it instead.
+    pub fn add_to(&self, pattern: &mut EmbPattern) -> Result<()> {
+        let (rail_a, rail_b) = self.rails()?;
+        let width = distance(rail_a.at(0.0), rail_b.at(0.0));
+        let top = self.stitches()?;
+        let mut runs = Vec::new();
+        for layer in &self.underlay_layers {
+            runs.extend(layer.satin_runs(self)?);
+        }
+
+        let mut sewing = false;
+        for run in runs.iter().chain(std::iter::once(&top)) {
+            let joined = sewing
+                && run
+                    .first()
+                    .zip(pattern.stitches().last())
+                    .is_some_and(|(&p, last)| distance(p, (last.x, last.y)) <= width);
+            if joined {
+                for &(x, y) in run {
+                    pattern.stitch_abs(x, y);
+                }
+            } else {
+                append_run(pattern, run);
+            }
+            sewing = !run.is_empty() || sewing;
+        }
+        Ok(())
+    }
+
+    /// Both rails, with rail B turned to run the same way as rail A
+    fn rails(&self) -> Result<(Rail, Rail)> {
+        let rail_a = Rail::new(self.rail_a.clone())?;
+        let mut rail_b = Rail::new(self.rail_b.clone())?;
+
+        let (a0, a1) = (rail_a.at(0.0), rail_a.at(1.0));
+        let (b0, b1) = (rail_b.at(0.0), rail_b.at(1.0));
+        if distance(a0, b1) + distance(a1, b0) < distance(a0, b0) + distance(a1, b1) {
+            rail_b = Rail::new(self.rail_b.iter().rev().copied().collect())?;
+        }
+        Ok((rail_a, rail_b))
+    }
+
+    /// Rails moved towards each other by `inset` (0.1mm), at most to the center line
+    ///
+    /// Both rails are sampled at matching fractions of their length, about every
+    /// `step` along the longer one.
+    pub(crate) fn inset_rails(&self, inset: f64, step: f64) -> Result<(Vec<Point>, Vec<Point>)> {
+        let (rail_a, rail_b) = self.rails()?;
+        let count = (rail_a.length.max(rail_b.length) / step).ceil();
+        if count > MAX_SATIN_STITCHES {
+            return Err(Error::InvalidPattern(format!(
+                "Satin underlay needs more than {} points; check the spacing units",
+                MAX_SATIN_STITCHES
+            )));
+        }
+        let count = count.max(1.0) as usize;
+
+        let mut inset_a = Vec::with_capacity(count + 1);
+        let mut inset_b = Vec::with_capacity(count + 1);
+        for i in 0..=count {
+            let t = i as f64 / count as f64;
+            let (a, b) = (rail_a.at(t), rail_b.at(t));
+            let width = distance(a, b);
+            let f = if width > 0.0 {
+                (inset / width).min(0.5)
+            } else {
+                0.0
+            };
+            inset_a.push((a.0 + (b.0 - a.0) * f, a.1 + (b.1 - a.1) * f));
+            inset_b.push((b.0 + (a.0 - b.0) * f, b.1 + (a.1 - b.1) * f));
+        }
+        Ok((inset_a, inset_b))
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Polyline with cumulative lengths for arc-length lookup
+struct Rail {
+    points: Vec<Point>,
+    cumulative: Vec<f64>,
+    length: f64,
+}
+
+impl Rail {
+    fn new(points: Vec<Point>) -> Result<Self> {
+        if points.len() < 2 || points.iter().any(|p| !p.0.is_finite() || !p.1.is_finite()) {
+            return Err(Error::InvalidPattern(
+                "Satin rails need at least 2 finite points".to_string(),
+            ));
+        }
+        let mut cumulative = Vec::with_capacity(points.len());
+        let mut length = 0.0;
+        cumulative.push(0.0);
+        for pair in points.windows(2) {
+            length += distance(pair[0], pair[1]);
+            cumulative.push(length);
+        }
+        Ok(Self {
+            points,
+            cumulative,
+            length,
+        })
+    }
+
+    /// Point at fraction `t` of the rail's length
+    fn at(&self, t: f64) -> Point {
+        if self.length == 0.0 {
+            return self.points[0];
+        }
+        let target = t.clamp(0.0, 1.0) * self.length;
+        let index = self
+            .cumulative
+            .partition_point(|&l| l < target)
+            .clamp(1, self.points.len() - 1);
+        let (start, end) = (self.cumulative[index - 1], self.cumulative[index]);
+        let (a, b) = (self.points[index - 1], self.points[index]);
+        let f = if end > start {
+            (target - start) / (end - start)
+        } else {
+            0.0
+        };
+        (a.0 + (b.0 - a.0) * f, a.1 + (b.1 - a.1) * f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn straight() -> SatinColumn {
+        SatinColumn::new(
+            vec![(0.0, 0.0), (0.0, 100.0)],
+            vec![(40.0, 0.0), (40.0, 100.0)],
+        )
+    }
+
+    #[test]
+    fn test_zigzag_alternates_rails() {
+        let points = straight().stitches().unwrap();
+        assert_eq!(points.len(), 26);
+        for (i, &(x, y)) in points.iter().enumerate() {
+            let expected_x = if i % 2 == 0 { 0.0 } else { 40.0 };
+            assert!((x - expected_x).abs() < 1e-9);
+            assert!((y - i as f64 * 4.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_opposite_rail_is_reversed() {
+        let reversed = SatinColumn::new(
+            vec![(0.0, 0.0), (0.0, 100.0)],
+            vec![(40.0, 100.0), (40.0, 0.0)],
+        );
+        assert_eq!(reversed.stitches().unwrap(), straight().stitches().unwrap());
+    }
+
+    #[test]
+    fn test_pull_compensation_widens_column() {
+        let points = straight().pull_compensation(4.0).stitches().unwrap();
+        assert!((points[0].0 + 2.0).abs() < 1e-9);
+        assert!((points[1].0 - 42.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_short_stitches_on_inner_curve() {
+        // Quarter ring: rail A is the inner radius
+        let arc = |r: f64| -> Vec<Point> {
+            (0..=16)
+                .map(|k| {
+                    let a = k as f64 / 16.0 * std::f64::consts::FRAC_PI_2;
+                    (r * a.cos(), r * a.sin())
+                })
+                .collect()
+        };
+        let column = SatinColumn::new(arc(10.0), arc(60.0)).density(3.0);
+        let full = column.stitches().unwrap();
+        let short = column
+            .short_stitches(Some(ShortStitches::default()))
+            .stitches()
+            .unwrap();
+        assert_eq!(full.len(), short.len());
+
+        let radius = |p: &Point| p.0.hypot(p.1);
+        let mut shortened = 0;
+        for (i, (f, s)) in full.iter().zip(&short).enumerate() {
+            if i % 2 == 1 {
+                // Outer rail points are far apart and stay full width
+                assert!((radius(f) - radius(s)).abs() < 1e-9);
+            } else if (radius(s) - radius(f)).abs() > 1e-9 {
+                assert!((radius(s) - 22.5).abs() < 0.1);
+                shortened += 1;
+            }
+        }
+        assert!(shortened > 0 && shortened < full.len() / 2);
+    }
+
+    #[test]
+    fn test_add_to_pattern() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(500.0, 500.0);
+        straight().add_to(&mut pattern).unwrap();
+        assert_eq!(pattern.count_stitches(), 27);
+        assert_eq!(pattern.count_trims(), 1);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(straight().density(0.0).stitches().is_err());
+        assert!(
+            SatinColumn::new(vec![(0.0, 0.0)], vec![(1.0, 0.0), (1.0, 5.0)])
+                .stitches()
+                .is_err()
+        );
+        assert!(straight().density(1e-9).stitches().is_err());
+    }
+}
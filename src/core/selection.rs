@@ -0,0 +1,427 @@
+//! Stitch selection and region editing
+//!
+//! [`EmbPattern::select`] picks the STITCH records inside a rectangle or
+//! polygon, or sewn with a thread color, as a [`Selection`] of record indices.
+//! The `*_selected` methods then delete, transform, recolor or re-sequence
+//! only those stitches. Recoloring and re-sequencing split color blocks where
+//! the selection starts and ends.
+//!
+//! A selection holds indices into the pattern it was made from; select again
+//! after any edit that adds or removes records.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::selection::Region;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! for i in 0..10 {
+//!     pattern.stitch_abs(i as f64 * 10.0, 0.0);
+//! }
+//!
+//! let selection = pattern.select(&Region::Rect(Bounds::new(25.0, -5.0, 65.0, 5.0)));
+//! assert_eq!(selection.len(), 4);
+//!
+//! pattern.set_selected_thread(&selection, EmbThread::new(0x0000FF));
+//! assert_eq!(pattern.count_color_changes(), 2);
+//! assert_eq!(pattern.threads().len(), 3);
+//! ```
+
+use crate::core::block::{join_blocks, StitchBlock};
+use crate::core::constants::*;
+use crate::core::matrix::EmbMatrix;
+use crate::core::pattern::{Bounds, EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+use crate::geometry::{point_in_polygon, Point};
+
+/// Area or color used to select stitches
+#[derive(Debug, Clone, PartialEq)]
+pub enum Region {
+    /// Stitches inside an axis-aligned rectangle, edges included
+    Rect(Bounds),
+    /// Stitches inside a closed polygon
+    Polygon(Vec<Point>),
+    /// Stitches sewn with a thread of this `0xRRGGBB` color
+    Color(u32),
+}
+
+/// Where re-sequenced stitches are sewn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequencePosition {
+    /// Before everything else
+    First,
+    /// After everything else
+    Last,
+}
+
+/// Sorted indices of selected stitch records
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    indices: Vec<usize>,
+}
+
+impl Selection {
+    /// Create a selection from record indices
+    pub fn from_indices(indices: impl IntoIterator<Item = usize>) -> Self {
+        let mut indices: Vec<usize> = indices.into_iter().collect();
+        indices.sort_unstable();
+        indices.dedup();
+        Self { indices }
+    }
+
+    /// Selected record indices in ascending order
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Number of selected records
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Whether nothing is selected
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Whether a record is selected
+    pub fn contains(&self, index: usize) -> bool {
+        self.indices.binary_search(&index).is_ok()
+    }
+
+    /// Records selected in either selection
+    pub fn union(&self, other: &Selection) -> Selection {
+        Selection::from_indices(self.indices.iter().chain(&other.indices).copied())
+    }
+
+    /// Records selected in both selections
+    pub fn intersection(&self, other: &Selection) -> Selection {
+        Selection {
+            indices: self
+                .indices
+                .iter()
+                .copied()
+                .filter(|&i| other.contains(i))
+                .collect(),
+        }
+    }
+
+    /// Bounds of the selected records in a pattern
+    pub fn bounds(&self, pattern: &EmbPattern) -> Option<Bounds> {
+        self.indices
+            .iter()
+            .filter_map(|&i| pattern.stitches().get(i))
+            .fold(None, |bounds: Option<Bounds>, s| {
+                Some(match bounds {
+                    None => Bounds::new(s.x, s.y, s.x, s.y),
+                    Some(b) => Bounds::new(
+                        b.min_x.min(s.x),
+                        b.min_y.min(s.y),
+                        b.max_x.max(s.x),
+                        b.max_y.max(s.y),
+                    ),
+                })
+            })
+    }
+}
+
+impl EmbPattern {
+    /// Select the STITCH records in a region
+    pub fn select(&self, region: &Region) -> Selection {
+        let is_stitch = |s: &Stitch| s.command & COMMAND_MASK == STITCH;
+        let indices = match region {
+            Region::Rect(b) => self
+                .stitches()
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| {
+                    is_stitch(s)
+                        && s.x >= b.min_x
+                        && s.x <= b.max_x
+                        && s.y >= b.min_y
+                        && s.y <= b.max_y
+                })
+                .map(|(i, _)| i)
+                .collect(),
+            Region::Polygon(polygon) => self
+                .stitches()
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| is_stitch(s) && point_in_polygon((s.x, s.y), polygon))
+                .map(|(i, _)| i)
+                .collect(),
+            Region::Color(color) => {
+                let mut indices = Vec::new();
+                for (offset, block) in block_offsets(&self.blocks()) {
+                    let matches = block
+                        .thread(self.threads())
+                        .is_some_and(|t| t.color & 0xFF_FFFF == color & 0xFF_FFFF);
+                    if matches {
+                        indices.extend(
+                            block
+                                .stitches
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, s)| is_stitch(s))
+                                .map(|(i, _)| offset + i),
+                        );
+                    }
+                }
+                indices
+            }
+        };
+        Selection { indices }
+    }
+
+    /// Delete the selected records
+    pub fn delete_selected(&mut self, selection: &Selection) {
+        let (stitches, origins): (Vec<Stitch>, Vec<Option<usize>>) = self
+            .stitches()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !selection.contains(*i))
+            .map(|(i, s)| (*s, Some(i)))
+            .unzip();
+        self.rebuild_stitches(stitches, &origins);
+    }
+
+    /// Transform the selected records by a matrix, leaving the rest in place
+    pub fn transform_selected(&mut self, selection: &Selection, matrix: &EmbMatrix) {
+        let mut stitches = self.stitches().to_vec();
+        for &i in selection.indices() {
+            if let Some(stitch) = stitches.get_mut(i) {
+                (stitch.x, stitch.y) = matrix.transform_point(stitch.x, stitch.y);
+            }
+        }
+        let origins: Vec<Option<usize>> = (0..stitches.len()).map(Some).collect();
+        self.rebuild_stitches(stitches, &origins);
+    }
+
+    /// Sew the selected stitches with another thread
+    ///
+    /// Each selected run becomes its own color block with `thread`; the rest
+    /// keep their threads.
+    pub fn set_selected_thread(&mut self, selection: &Selection, thread: EmbThread) {
+        let segments = self.split_selected(selection);
+        let (blocks, threads): (Vec<_>, Vec<_>) = segments
+            .into_iter()
+            .map(|(block, original, selected)| {
+                let thread = if selected { thread.clone() } else { original };
+                (block, thread)
+            })
+            .unzip();
+        self.rebuild_from(blocks, threads);
+    }
+
+    /// Move the selected stitches to the start or end of the sewing order
+    ///
+    /// Selected runs keep their threads and their relative order.
+    pub fn resequence_selected(&mut self, selection: &Selection, position: SequencePosition) {
+        let (selected, rest): (Vec<_>, Vec<_>) = self
+            .split_selected(selection)
+            .into_iter()
+            .partition(|(_, _, selected)| *selected);
+        let ordered = match position {
+            SequencePosition::First => selected.into_iter().chain(rest),
+            SequencePosition::Last => rest.into_iter().chain(selected),
+        };
+        let (blocks, threads): (Vec<_>, Vec<_>) =
+            ordered.map(|(block, thread, _)| (block, thread)).unzip();
+        self.rebuild_from(blocks, threads);
+    }
+
+    /// Split color blocks into runs of selected and unselected records
+    ///
+    /// Only STITCH records switch between runs; jumps, trims and other records
+    /// stay with the run they follow. Returns each run with its thread and
+    /// whether it is selected.
+    fn split_selected(&self, selection: &Selection) -> Vec<(StitchBlock, EmbThread, bool)> {
+        let mut segments = Vec::new();
+        for (offset, block) in block_offsets(&self.blocks()) {
+            let thread = block
+                .thread(self.threads())
+                .cloned()
+                .unwrap_or_else(|| EmbThread::new(0x000000));
+            let mut current = StitchBlock {
+                start_command: block.start_command,
+                ..StitchBlock::new(block.thread_index)
+            };
+            let mut selected = false;
+            for (i, &stitch) in block.stitches.iter().enumerate() {
+                if stitch.command & COMMAND_MASK == STITCH {
+                    let is_selected = selection.contains(offset + i);
+                    if is_selected != selected && !current.stitches.is_empty() {
+                        let next = StitchBlock::new(block.thread_index);
+                        segments.push((
+                            std::mem::replace(&mut current, next),
+                            thread.clone(),
+                            selected,
+                        ));
+                    }
+                    selected = is_selected;
+                }
+                current.stitches.push(stitch);
+            }
+            current.end_command = block.end_command;
+            segments.push((current, thread, selected));
+        }
+        segments
+    }
+
+    /// Replace the stitches with re-joined blocks, one thread per block
+    ///
+    /// Every block after the first starts with a color change.
+    fn rebuild_from(&mut self, mut blocks: Vec<StitchBlock>, threads: Vec<EmbThread>) {
+        let mut last_position = None;
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let starts_with_change = block
+                .start_command
+                .is_some_and(|s| s.command & COMMAND_MASK == COLOR_CHANGE);
+            if index == 0 && starts_with_change {
+                block.start_command = None;
+            } else if index > 0 && !starts_with_change {
+                // Change thread where the previous block ended
+                let (x, y) = last_position
+                    .or(block.stitches.first().copied())
+                    .map_or((0.0, 0.0), |s| (s.x, s.y));
+                if let Some(needle) = block.start_command.take() {
+                    block.stitches.insert(0, needle);
+                }
+                block.start_command = Some(Stitch::new(x, y, COLOR_CHANGE));
+            }
+            if let Some(last) = block.stitches.last() {
+                last_position = Some(*last);
+            }
+        }
+        self.replace_stitches(join_blocks(&blocks), threads);
+    }
+}
+
+/// Blocks paired with the index of their first stitch record in the pattern
+fn block_offsets(blocks: &[StitchBlock]) -> Vec<(usize, &StitchBlock)> {
+    let mut offset = 0;
+    blocks
+        .iter()
+        .map(|block| {
+            let start = offset + usize::from(block.start_command.is_some());
+            offset = start + block.stitches.len() + usize::from(block.end_command.is_some());
+            (start, block)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        for i in 0..5 {
+            pattern.stitch_abs(i as f64 * 10.0, 0.0);
+        }
+        pattern.color_change(0.0, 0.0);
+        for i in 5..8 {
+            pattern.stitch_abs(i as f64 * 10.0, 10.0);
+        }
+        pattern.end();
+        pattern
+    }
+
+    fn xs(pattern: &EmbPattern) -> Vec<f64> {
+        pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| s.x)
+            .collect()
+    }
+
+    #[test]
+    fn test_select_regions() {
+        let pattern = row();
+
+        let rect = pattern.select(&Region::Rect(Bounds::new(15.0, -1.0, 55.0, 11.0)));
+        assert_eq!(rect.indices(), &[2, 3, 4, 6]);
+
+        let quad = vec![(35.0, -5.0), (80.0, -5.0), (80.0, 20.0), (45.0, 20.0)];
+        let polygon = pattern.select(&Region::Polygon(quad));
+        assert_eq!(polygon.indices(), &[4, 6, 7, 8]);
+
+        let green = pattern.select(&Region::Color(0x00FF00));
+        assert_eq!(green.indices(), &[6, 7, 8]);
+        assert!(pattern.select(&Region::Color(0x123456)).is_empty());
+
+        assert_eq!(rect.intersection(&green).indices(), &[6]);
+        assert_eq!(rect.union(&green).len(), 6);
+        assert_eq!(
+            green.bounds(&pattern),
+            Some(Bounds::new(50.0, 10.0, 70.0, 10.0))
+        );
+    }
+
+    #[test]
+    fn test_delete_and_transform_selected() {
+        let mut pattern = row();
+        let selection = Selection::from_indices([1, 2]);
+
+        let mut matrix = EmbMatrix::new();
+        matrix.post_translate(0.0, 100.0);
+        pattern.transform_selected(&selection, &matrix);
+        assert_eq!(pattern.stitches()[1].y, 100.0);
+        assert_eq!(pattern.stitches()[3].y, 0.0);
+
+        pattern.delete_selected(&selection);
+        assert_eq!(xs(&pattern), vec![0.0, 30.0, 40.0, 50.0, 60.0, 70.0]);
+        assert_eq!(pattern.count_color_changes(), 1);
+    }
+
+    #[test]
+    fn test_set_selected_thread_splits_blocks() {
+        let mut pattern = row();
+        pattern.add_metadata("name", "Row");
+        let selection = pattern.select(&Region::Rect(Bounds::new(15.0, -1.0, 35.0, 1.0)));
+
+        pattern.set_selected_thread(&selection, EmbThread::new(0x0000FF));
+
+        let colors: Vec<u32> = pattern.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0xFF0000, 0x0000FF, 0xFF0000, 0x00FF00]);
+        assert_eq!(pattern.count_color_changes(), 3);
+        assert_eq!(xs(&pattern), xs(&row()));
+        let blocks = pattern.blocks();
+        assert_eq!(
+            blocks[1].points().collect::<Vec<_>>(),
+            [(20.0, 0.0), (30.0, 0.0)]
+        );
+        assert_eq!(
+            pattern.get_metadata("name").map(String::as_str),
+            Some("Row")
+        );
+    }
+
+    #[test]
+    fn test_resequence_selected() {
+        let mut pattern = row();
+        let green = pattern.select(&Region::Color(0x00FF00));
+
+        pattern.resequence_selected(&green, SequencePosition::First);
+        assert_eq!(
+            xs(&pattern),
+            vec![50.0, 60.0, 70.0, 0.0, 10.0, 20.0, 30.0, 40.0]
+        );
+        let colors: Vec<u32> = pattern.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0x00FF00, 0xFF0000]);
+        assert_eq!(pattern.count_color_changes(), 1);
+        assert_eq!(pattern.stitches()[0].command, STITCH);
+        let last = pattern.stitches().last().unwrap();
+        assert_eq!(last.command, END);
+
+        let first_red = pattern.select(&Region::Rect(Bounds::new(-1.0, -1.0, 1.0, 1.0)));
+        pattern.resequence_selected(&first_red, SequencePosition::Last);
+        assert_eq!(xs(&pattern)[7], 0.0);
+        assert_eq!(pattern.count_color_changes(), 2);
+    }
+}
@@ -0,0 +1,195 @@
+//! Sequin runs
+//!
+//! A sequin run drops sequins at a fixed pitch along a path. Each drop is a
+//! `SEQUIN_EJECT` at the sequin's center; with the pitch a little under the sequin
+//! diameter the sequins overlap like scales. Writers switch the machine into
+//! sequin mode where the format needs it.
+//!
+//! Points are in pattern units (0.1mm).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::sequin::SequinRun;
+//!
+//! // 5mm sequins every 4mm along a 40mm line
+//! let mut pattern = EmbPattern::new();
+//! SequinRun::new(vec![(0.0, 0.0), (400.0, 0.0)])
+//!     .spacing(40.0)
+//!     .add_to(&mut pattern)?;
+//!
+//! assert_eq!(pattern.count_sequins(), 11);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::geometry::Point;
+use crate::utils::error::{Error, Result};
+
+/// Upper bound on sequins per run, to catch unit mistakes
+const MAX_SEQUINS: f64 = 1_000_000.0;
+
+/// Sequins dropped at a fixed pitch along a path
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequinRun {
+    /// Polyline the sequins follow
+    pub path: Vec<Point>,
+    /// Distance between sequin centers along the path in 0.1mm (default: 30.0)
+    pub spacing: f64,
+}
+
+impl SequinRun {
+    /// Create a run along a path with the default 3mm pitch
+    pub fn new(path: Vec<Point>) -> Self {
+        Self {
+            path,
+            spacing: 30.0,
+        }
+    }
+
+    /// Set the distance between sequin centers (0.1mm)
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Sequin centers, starting at the first point of the path
+    ///
+    /// Sequins are exactly `spacing` apart along the path; a remainder shorter
+    /// than the pitch at the end of the path is left empty.
+    pub fn drops(&self) -> Result<Vec<Point>> {
+        if !(self.spacing > 0.0 && self.spacing.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Sequin spacing must be positive, got {}",
+                self.spacing
+            )));
+        }
+        if self.path.len() < 2
+            || self
+                .path
+                .iter()
+                .any(|p| !p.0.is_finite() || !p.1.is_finite())
+        {
+            return Err(Error::InvalidPattern(
+                "Sequin path needs at least 2 finite points".to_string(),
+            ));
+        }
+
+        let lengths: Vec<f64> = self
+            .path
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+            .collect();
+        let total: f64 = lengths.iter().sum();
+        if total / self.spacing > MAX_SEQUINS {
+            return Err(Error::InvalidPattern(format!(
+                "Sequin run needs more than {} sequins; check the spacing units",
+                MAX_SEQUINS
+            )));
+        }
+
+        // Tolerate rounding so a path of whole pitches gets its last sequin
+        let count = (total / self.spacing + 1e-9).floor() as usize + 1;
+        let mut drops = Vec::with_capacity(count);
+        let mut index = 0;
+        let mut walked = 0.0;
+        for i in 0..count {
+            let target = (i as f64 * self.spacing).min(total);
+            while index + 1 < lengths.len() && walked + lengths[index] < target {
+                walked += lengths[index];
+                index += 1;
+            }
+            let t = if lengths[index] > 0.0 {
+                ((target - walked) / lengths[index]).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let (a, b) = (self.path[index], self.path[index + 1]);
+            drops.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+        }
+        Ok(drops)
+    }
+
+    /// Append the run to a pattern
+    ///
+    /// Jumps to the first sequin unless the last stitch is already there,
+    /// trimming first if the pattern has stitches.
+    pub fn add_to(&self, pattern: &mut EmbPattern) -> Result<()> {
+        let drops = self.drops()?;
+        let (x, y) = drops[0];
+        match pattern.stitches().last() {
+            Some(last) if (last.x - x).hypot(last.y - y) < 0.01 => {}
+            Some(_) => {
+                pattern.trim();
+                pattern.jump_abs(x, y);
+            }
+            None => pattern.jump_abs(x, y),
+        }
+        for (x, y) in drops {
+            pattern.add_stitch_absolute(SEQUIN_EJECT, x, y);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_spacing_along_corner() {
+        let run = SequinRun::new(vec![(0.0, 0.0), (50.0, 0.0), (50.0, 50.0)]).spacing(20.0);
+        let drops = run.drops().unwrap();
+        assert_eq!(
+            drops,
+            vec![
+                (0.0, 0.0),
+                (20.0, 0.0),
+                (40.0, 0.0),
+                (50.0, 10.0),
+                (50.0, 30.0),
+                (50.0, 50.0)
+            ]
+        );
+
+        // The remainder after the last whole pitch stays empty
+        let drops = run.spacing(30.0).drops().unwrap();
+        assert_eq!(drops.len(), 4);
+        assert_eq!(drops[3], (50.0, 40.0));
+    }
+
+    #[test]
+    fn test_add_to_pattern() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 100.0);
+        SequinRun::new(vec![(0.0, 0.0), (90.0, 0.0)])
+            .add_to(&mut pattern)
+            .unwrap();
+
+        let commands: Vec<u32> = pattern.stitches().iter().map(|s| s.command).collect();
+        assert_eq!(
+            commands,
+            vec![
+                STITCH,
+                TRIM,
+                JUMP,
+                SEQUIN_EJECT,
+                SEQUIN_EJECT,
+                SEQUIN_EJECT,
+                SEQUIN_EJECT
+            ]
+        );
+        assert_eq!(pattern.count_sequins(), 4);
+        assert_eq!(pattern.count_stitches(), 1);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let line = vec![(0.0, 0.0), (100.0, 0.0)];
+        assert!(SequinRun::new(line.clone()).spacing(0.0).drops().is_err());
+        assert!(SequinRun::new(line).spacing(1e-6).drops().is_err());
+        assert!(SequinRun::new(vec![(0.0, 0.0)]).drops().is_err());
+    }
+}
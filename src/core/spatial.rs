@@ -0,0 +1,364 @@
+//! Spatial index over stitch positions
+//!
+//! [`StitchIndex`] is a static 2-d tree over the positions of a pattern's
+//! records, for hit-testing and nearest-neighbour searches that would
+//! otherwise scan every stitch. The tree is built once in `O(n log n)` and
+//! answers nearest-stitch queries in `O(log n)` on typical designs.
+//!
+//! The index does not follow later edits: check it with
+//! [`is_current`](StitchIndex::is_current) or call
+//! [`refresh`](StitchIndex::refresh) after changing the pattern.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::spatial::StitchIndex;
+//!
+//! let mut pattern = EmbPattern::new();
+//! for i in 0..100 {
+//!     pattern.stitch_abs((i % 10) as f64 * 10.0, (i / 10) as f64 * 10.0);
+//! }
+//!
+//! let index = StitchIndex::new(&pattern);
+//! let (nearest, distance) = index.nearest(31.0, 42.0).unwrap();
+//! assert_eq!(nearest, 43);
+//! assert!((distance - 2.236).abs() < 0.001);
+//!
+//! assert_eq!(index.within_radius(0.0, 0.0, 10.0).len(), 3);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{Bounds, EmbPattern};
+use std::cmp::Ordering;
+
+/// An indexed record: position and index in the pattern
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Entry {
+    x: f64,
+    y: f64,
+    index: usize,
+}
+
+impl Entry {
+    fn coordinate(&self, axis: usize) -> f64 {
+        if axis == 0 {
+            self.x
+        } else {
+            self.y
+        }
+    }
+
+    fn distance_squared(&self, x: f64, y: f64) -> f64 {
+        let (dx, dy) = (self.x - x, self.y - y);
+        dx * dx + dy * dy
+    }
+}
+
+/// 2-d tree over record positions for nearest-neighbour and range queries
+///
+/// Nodes are stored implicitly: each subslice's median splits it on X at even
+/// depths and Y at odd depths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StitchIndex {
+    entries: Vec<Entry>,
+    commands: Vec<u32>,
+    record_count: usize,
+}
+
+impl StitchIndex {
+    /// Index the STITCH records of a pattern
+    pub fn new(pattern: &EmbPattern) -> Self {
+        Self::with_commands(pattern, &[STITCH])
+    }
+
+    /// Index the records of a pattern whose command is in `commands`
+    pub fn with_commands(pattern: &EmbPattern, commands: &[u32]) -> Self {
+        let mut index = Self {
+            entries: Vec::new(),
+            commands: commands.to_vec(),
+            record_count: 0,
+        };
+        index.rebuild(pattern);
+        index
+    }
+
+    /// Rebuild the index from the current records of a pattern
+    pub fn rebuild(&mut self, pattern: &EmbPattern) {
+        self.entries = self.collect(pattern).collect();
+        self.record_count = pattern.stitches().len();
+        build(&mut self.entries, 0);
+    }
+
+    /// Whether the index still matches the pattern's records
+    ///
+    /// Compares every indexed position, without allocating.
+    pub fn is_current(&self, pattern: &EmbPattern) -> bool {
+        if pattern.stitches().len() != self.record_count {
+            return false;
+        }
+        let stitches = pattern.stitches();
+        let indexed = self.collect(pattern).count();
+        indexed == self.entries.len()
+            && self.entries.iter().all(|e| {
+                let s = &stitches[e.index];
+                s.x == e.x && s.y == e.y && self.commands.contains(&(s.command & COMMAND_MASK))
+            })
+    }
+
+    /// Rebuild the index if the pattern changed; returns whether it rebuilt
+    pub fn refresh(&mut self, pattern: &EmbPattern) -> bool {
+        if self.is_current(pattern) {
+            return false;
+        }
+        self.rebuild(pattern);
+        true
+    }
+
+    /// Number of indexed records
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no records are indexed
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Closest indexed record to a point, with its distance
+    pub fn nearest(&self, x: f64, y: f64) -> Option<(usize, f64)> {
+        self.k_nearest(x, y, 1).into_iter().next()
+    }
+
+    /// Up to `k` closest indexed records to a point, nearest first
+    pub fn k_nearest(&self, x: f64, y: f64, k: usize) -> Vec<(usize, f64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        // Sorted by squared distance, at most k long
+        let mut best: Vec<(f64, usize)> = Vec::with_capacity(k + 1);
+        nearest_in(&self.entries, 0, x, y, k, &mut best);
+        best.into_iter()
+            .map(|(distance_squared, index)| (index, distance_squared.sqrt()))
+            .collect()
+    }
+
+    /// Indexed records within `radius` of a point, in pattern order
+    pub fn within_radius(&self, x: f64, y: f64, radius: f64) -> Vec<usize> {
+        let bounds = Bounds::new(x - radius, y - radius, x + radius, y + radius);
+        let radius_squared = radius * radius;
+        let mut found = Vec::new();
+        range_in(&self.entries, 0, &bounds, &mut |e| {
+            if e.distance_squared(x, y) <= radius_squared {
+                found.push(e.index);
+            }
+        });
+        found.sort_unstable();
+        found
+    }
+
+    /// Indexed records inside a rectangle (edges included), in pattern order
+    pub fn in_bounds(&self, bounds: &Bounds) -> Vec<usize> {
+        let mut found = Vec::new();
+        range_in(&self.entries, 0, bounds, &mut |e| found.push(e.index));
+        found.sort_unstable();
+        found
+    }
+
+    fn collect<'a>(&'a self, pattern: &'a EmbPattern) -> impl Iterator<Item = Entry> + 'a {
+        pattern
+            .stitches()
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| {
+                self.commands.contains(&(s.command & COMMAND_MASK))
+                    && s.x.is_finite()
+                    && s.y.is_finite()
+            })
+            .map(|(index, s)| Entry {
+                x: s.x,
+                y: s.y,
+                index,
+            })
+    }
+}
+
+/// Arrange entries so each subslice's median splits it on the depth's axis
+fn build(entries: &mut [Entry], depth: usize) {
+    if entries.len() <= 1 {
+        return;
+    }
+    let axis = depth % 2;
+    let mid = entries.len() / 2;
+    entries.select_nth_unstable_by(mid, |a, b| {
+        a.coordinate(axis)
+            .partial_cmp(&b.coordinate(axis))
+            .unwrap_or(Ordering::Equal)
+    });
+    let (left, right) = entries.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+fn nearest_in(
+    entries: &[Entry],
+    depth: usize,
+    x: f64,
+    y: f64,
+    k: usize,
+    best: &mut Vec<(f64, usize)>,
+) {
+    if entries.is_empty() {
+        return;
+    }
+    let axis = depth % 2;
+    let mid = entries.len() / 2;
+    let node = &entries[mid];
+
+    let distance_squared = node.distance_squared(x, y);
+    if best.len() < k || distance_squared < best[best.len() - 1].0 {
+        let position = best.partition_point(|&(d, _)| d <= distance_squared);
+        best.insert(position, (distance_squared, node.index));
+        best.truncate(k);
+    }
+
+    let offset = if axis == 0 { x } else { y } - node.coordinate(axis);
+    let (near, far) = if offset < 0.0 {
+        (&entries[..mid], &entries[mid + 1..])
+    } else {
+        (&entries[mid + 1..], &entries[..mid])
+    };
+    nearest_in(near, depth + 1, x, y, k, best);
+    if best.len() < k || offset * offset < best[best.len() - 1].0 {
+        nearest_in(far, depth + 1, x, y, k, best);
+    }
+}
+
+fn range_in(entries: &[Entry], depth: usize, bounds: &Bounds, visit: &mut impl FnMut(&Entry)) {
+    if entries.is_empty() {
+        return;
+    }
+    let axis = depth % 2;
+    let mid = entries.len() / 2;
+    let node = &entries[mid];
+    if node.x >= bounds.min_x
+        && node.x <= bounds.max_x
+        && node.y >= bounds.min_y
+        && node.y <= bounds.max_y
+    {
+        visit(node);
+    }
+
+    let (min, max) = if axis == 0 {
+        (bounds.min_x, bounds.max_x)
+    } else {
+        (bounds.min_y, bounds.max_y)
+    };
+    let split = node.coordinate(axis);
+    if min <= split {
+        range_in(&entries[..mid], depth + 1, bounds, visit);
+    }
+    if max >= split {
+        range_in(&entries[mid + 1..], depth + 1, bounds, visit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random scatter
+    fn scatter(count: usize) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        let mut state = 0x2545_F491_u64;
+        for _ in 0..count {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let x = (state >> 33) as f64 % 1000.0;
+            let y = (state >> 17) as f64 % 1000.0;
+            pattern.stitch_abs(x, y);
+        }
+        pattern
+    }
+
+    fn brute_nearest(pattern: &EmbPattern, x: f64, y: f64, k: usize) -> Vec<f64> {
+        let mut distances: Vec<f64> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.x - x).hypot(s.y - y))
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        distances.truncate(k);
+        distances
+    }
+
+    #[test]
+    fn test_nearest_matches_linear_scan() {
+        let pattern = scatter(2000);
+        let index = StitchIndex::new(&pattern);
+        assert_eq!(index.len(), 2000);
+
+        for (x, y) in [(0.0, 0.0), (500.0, 500.0), (999.0, 3.0), (-50.0, 1200.0)] {
+            let (nearest, distance) = index.nearest(x, y).unwrap();
+            let stitch = pattern.stitches()[nearest];
+            assert_eq!((stitch.x - x).hypot(stitch.y - y), distance);
+            assert_eq!(distance, brute_nearest(&pattern, x, y, 1)[0]);
+
+            let k: Vec<f64> = index.k_nearest(x, y, 5).iter().map(|&(_, d)| d).collect();
+            assert_eq!(k, brute_nearest(&pattern, x, y, 5));
+        }
+    }
+
+    #[test]
+    fn test_range_queries_match_linear_scan() {
+        let pattern = scatter(2000);
+        let index = StitchIndex::new(&pattern);
+
+        let bounds = Bounds::new(100.0, 200.0, 300.0, 250.0);
+        let expected: Vec<usize> = (0..pattern.stitches().len())
+            .filter(|&i| {
+                let s = pattern.stitches()[i];
+                s.x >= 100.0 && s.x <= 300.0 && s.y >= 200.0 && s.y <= 250.0
+            })
+            .collect();
+        assert_eq!(index.in_bounds(&bounds), expected);
+
+        let expected: Vec<usize> = (0..pattern.stitches().len())
+            .filter(|&i| {
+                let s = pattern.stitches()[i];
+                (s.x - 400.0).hypot(s.y - 600.0) <= 75.0
+            })
+            .collect();
+        assert_eq!(index.within_radius(400.0, 600.0, 75.0), expected);
+    }
+
+    #[test]
+    fn test_commands_and_refresh() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 100.0, 0.0);
+        pattern.stitch_abs(200.0, 0.0);
+
+        let mut index = StitchIndex::new(&pattern);
+        assert_eq!(index.nearest(90.0, 0.0), Some((0, 90.0)));
+        let all = StitchIndex::with_commands(&pattern, &[STITCH, JUMP]);
+        assert_eq!(all.nearest(90.0, 0.0), Some((1, 10.0)));
+
+        assert!(index.is_current(&pattern));
+        assert!(!index.refresh(&pattern));
+        pattern.translate(5.0, 0.0);
+        assert!(!index.is_current(&pattern));
+        assert!(index.refresh(&pattern));
+        assert_eq!(index.nearest(0.0, 0.0), Some((0, 5.0)));
+    }
+
+    #[test]
+    fn test_empty_index() {
+        let index = StitchIndex::new(&EmbPattern::new());
+        assert!(index.is_empty());
+        assert_eq!(index.nearest(0.0, 0.0), None);
+        assert!(index.k_nearest(0.0, 0.0, 0).is_empty());
+        assert!(index.within_radius(0.0, 0.0, 10.0).is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+//! Operator notes on STOP commands
+//!
+//! A STOP pauses the machine without saying why. For applique and similar
+//! techniques the operator needs to know what to do during the pause, so a note
+//! can be attached to each stop. Notes are stored in pattern metadata under
+//! `stop.<n>`, where `n` counts STOP commands from 0 in sewing order, as
+//! `<action>: <text>`. They survive JSON round-trips and are printed by the CSV,
+//! TXT and PDF worksheet writers.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::stop_note::{StopAction, StopNote};
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.stop_with_note(StopNote::place_applique("Lay the red twill over the outline"));
+//! pattern.stitch_abs(100.0, 100.0);
+//! pattern.stop_with_note(StopNote::trim_applique("Cut close to the tack-down"));
+//! pattern.end();
+//!
+//! let note = pattern.stop_note(1).unwrap();
+//! assert_eq!(note.action, StopAction::TrimApplique);
+//! assert_eq!(pattern.stop_notes().len(), 2);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use std::fmt;
+
+/// Metadata key prefix of stop notes
+pub const STOP_NOTE_PREFIX: &str = "stop.";
+
+/// What the operator does while the machine is stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StopAction {
+    /// Place the applique fabric over the placement line
+    PlaceApplique,
+    /// Trim the applique fabric around the tack-down
+    TrimApplique,
+    /// Anything else, described by the note text
+    Other,
+}
+
+impl StopAction {
+    /// Token stored in metadata
+    fn token(self) -> &'static str {
+        match self {
+            StopAction::PlaceApplique => "place_applique",
+            StopAction::TrimApplique => "trim_applique",
+            StopAction::Other => "note",
+        }
+    }
+
+    /// Parse a stored token
+    fn from_token(token: &str) -> Option<Self> {
+        match token.trim() {
+            "place_applique" => Some(StopAction::PlaceApplique),
+            "trim_applique" => Some(StopAction::TrimApplique),
+            "note" => Some(StopAction::Other),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for StopAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StopAction::PlaceApplique => write!(f, "Place applique"),
+            StopAction::TrimApplique => write!(f, "Trim applique"),
+            StopAction::Other => write!(f, "Note"),
+        }
+    }
+}
+
+/// Note attached to a STOP command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StopNote {
+    /// What to do during the stop
+    pub action: StopAction,
+    /// Free-form instructions, may be empty
+    pub text: String,
+}
+
+impl StopNote {
+    /// Create a note
+    pub fn new(action: StopAction, text: impl Into<String>) -> Self {
+        Self {
+            action,
+            text: text.into(),
+        }
+    }
+
+    /// Place the applique fabric
+    pub fn place_applique(text: impl Into<String>) -> Self {
+        Self::new(StopAction::PlaceApplique, text)
+    }
+
+    /// Trim the applique fabric
+    pub fn trim_applique(text: impl Into<String>) -> Self {
+        Self::new(StopAction::TrimApplique, text)
+    }
+
+    /// Free-form note
+    pub fn other(text: impl Into<String>) -> Self {
+        Self::new(StopAction::Other, text)
+    }
+
+    /// Parse a stored `<action>: <text>` value
+    ///
+    /// Values without a known action are kept whole as an [`StopAction::Other`] note.
+    pub(crate) fn parse(value: &str) -> Self {
+        if let Some((token, text)) = value.split_once(':') {
+            if let Some(action) = StopAction::from_token(token) {
+                return Self::new(action, text.trim());
+            }
+        }
+        Self::other(value.trim())
+    }
+
+    /// Format as a stored `<action>: <text>` value
+    pub(crate) fn to_value(&self) -> String {
+        format!("{}: {}", self.action.token(), self.text)
+    }
+}
+
+impl fmt::Display for StopNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.action, self.text.is_empty()) {
+            (_, true) => write!(f, "{}", self.action),
+            (StopAction::Other, false) => write!(f, "{}", self.text),
+            (action, false) => write!(f, "{}: {}", action, self.text),
+        }
+    }
+}
+
+impl EmbPattern {
+    /// Add a STOP with a note for the operator
+    pub fn stop_with_note(&mut self, note: StopNote) {
+        let index = self.stop_positions().len();
+        self.stop();
+        self.set_stop_note(index, note);
+    }
+
+    /// Attach a note to the `index`-th STOP command (0-based)
+    pub fn set_stop_note(&mut self, index: usize, note: StopNote) {
+        self.set_metadata(format!("{}{}", STOP_NOTE_PREFIX, index), note.to_value());
+    }
+
+    /// Note of the `index`-th STOP command
+    pub fn stop_note(&self, index: usize) -> Option<StopNote> {
+        self.get_metadata(&format!("{}{}", STOP_NOTE_PREFIX, index))
+            .map(|value| StopNote::parse(value))
+    }
+
+    /// Remove the note of the `index`-th STOP command, returning it
+    pub fn remove_stop_note(&mut self, index: usize) -> Option<StopNote> {
+        let note = self.stop_note(index);
+        self.remove_metadata(&format!("{}{}", STOP_NOTE_PREFIX, index));
+        note
+    }
+
+    /// All stop notes, sorted by stop index
+    pub fn stop_notes(&self) -> Vec<(usize, StopNote)> {
+        let mut notes: Vec<_> = self
+            .metadata()
+            .filter_map(|(key, value)| {
+                let index = key.strip_prefix(STOP_NOTE_PREFIX)?.parse().ok()?;
+                Some((index, StopNote::parse(value)))
+            })
+            .collect();
+        notes.sort_by_key(|(index, _)| *index);
+        notes
+    }
+
+    /// Stitch indices of the STOP commands, in sewing order
+    pub fn stop_positions(&self) -> Vec<usize> {
+        self.stitches()
+            .iter()
+            .enumerate()
+            .filter(|(_, stitch)| stitch.command & COMMAND_MASK == STOP)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stop_notes() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stop_with_note(StopNote::place_applique("Blue felt"));
+        pattern.stitch_abs(10.0, 0.0);
+        pattern.stop();
+        pattern.stitch_abs(20.0, 0.0);
+        pattern.stop_with_note(StopNote::trim_applique(""));
+
+        assert_eq!(pattern.stop_positions(), vec![1, 3, 5]);
+        assert_eq!(
+            pattern.get_metadata("stop.0").map(String::as_str),
+            Some("place_applique: Blue felt")
+        );
+        assert_eq!(pattern.stop_note(1), None);
+        assert_eq!(
+            pattern.stop_notes(),
+            vec![
+                (0, StopNote::place_applique("Blue felt")),
+                (2, StopNote::trim_applique("")),
+            ]
+        );
+
+        assert_eq!(
+            pattern.remove_stop_note(0),
+            Some(StopNote::place_applique("Blue felt"))
+        );
+        assert_eq!(pattern.stop_notes().len(), 1);
+    }
+
+    #[test]
+    fn test_stop_notes_json_round_trip() {
+        use crate::formats::io::memory::{read_from_bytes, write_to_bytes};
+
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stop_with_note(StopNote::place_applique("Tackle twill, shiny side up"));
+        pattern.end();
+
+        let bytes = write_to_bytes(&pattern, "json").unwrap();
+        let read = read_from_bytes(&bytes, "json").unwrap();
+        assert_eq!(read.stop_notes(), pattern.stop_notes());
+    }
+
+    #[test]
+    fn test_stop_note_parse_and_display() {
+        let note = StopNote::parse("trim_applique:  close to the satin ");
+        assert_eq!(note, StopNote::trim_applique("close to the satin"));
+        assert_eq!(note.to_string(), "Trim applique: close to the satin");
+
+        // Hand-written values without a known action stay whole
+        let note = StopNote::parse("Hoop: check tension");
+        assert_eq!(note, StopNote::other("Hoop: check tension"));
+        assert_eq!(note.to_string(), "Hoop: check tension");
+        assert_eq!(StopNote::place_applique("").to_string(), "Place applique");
+    }
+}
@@ -405,6 +405,38 @@ impl EmbThread {
 
         Some((closest_index, closest_distance))
     }
+
+    /// Fill in a missing description with a human-friendly color name
+    ///
+    /// Threads that already have a non-empty description are left unchanged.
+    /// Returns the description.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    ///
+    /// let mut thread = EmbThread::new(0x000080);
+    /// assert_eq!(thread.auto_describe(), "Navy Blue");
+    /// ```
+    pub fn auto_describe(&mut self) -> &str {
+        if self
+            .description
+            .as_deref()
+            .is_none_or(|d| d.trim().is_empty())
+        {
+            self.description = Some(color_name(self.color).to_string());
+        }
+        self.description.as_deref().unwrap_or_default()
+    }
+
+    /// Name to show operators: the description, or the nearest color name
+    pub fn display_name(&self) -> String {
+        match self.description.as_deref().map(str::trim) {
+            Some(desc) if !desc.is_empty() => desc.to_string(),
+            _ => color_name(self.color).to_string(),
+        }
+    }
 }
 
 impl Default for EmbThread {
@@ -454,6 +486,12 @@ pub fn color_rgb(r: u8, g: u8, b: u8) -> u32 {
 /// Parse hex color string (with or without #)
 pub fn parse_color_hex(hex_string: &str) -> Result<u32> {
     let h = hex_string.trim_start_matches('#');
+    if !h.is_ascii() {
+        return Err(Error::InvalidColor(format!(
+            "Invalid hex color: {}",
+            hex_string
+        )));
+    }
     let size = h.len();
 
     match size {
@@ -560,6 +598,87 @@ pub fn find_nearest_color_index(color: u32, palette: &[EmbThread]) -> Option<usi
     Some(closest_index)
 }
 
+/// Nearest human-friendly name for a color
+///
+/// Picks from a curated vocabulary of common embroidery thread color names
+/// ("Kelly Green", "Royal Blue", ...) by perceptual (Lab) distance.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::core::thread::color_name;
+///
+/// assert_eq!(color_name(0xFE0102), "Red");
+/// ```
+pub fn color_name(color: u32) -> &'static str {
+    use palette::color_difference::EuclideanDistance;
+
+    let lab = EmbThread::new(color).to_lab();
+    THREAD_COLOR_NAMES
+        .iter()
+        .map(|(name, reference)| (*name, lab.distance(*reference)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or("Black", |(name, _)| name)
+}
+
+// Curated thread color vocabulary, with Lab values precomputed for `color_name`
+lazy_static! {
+    static ref THREAD_COLOR_NAMES: Vec<(&'static str, palette::Lab)> = [
+        ("Black", 0x000000),
+        ("Charcoal", 0x36454F),
+        ("Dark Gray", 0x5A5A5A),
+        ("Silver Gray", 0xA8A9AD),
+        ("Light Gray", 0xD3D3D3),
+        ("White", 0xFFFFFF),
+        ("Ivory", 0xFFFFF0),
+        ("Cream", 0xF3E5AB),
+        ("Beige", 0xD9C7A3),
+        ("Tan", 0xD2B48C),
+        ("Khaki", 0xBDB76B),
+        ("Light Brown", 0xA0703C),
+        ("Brown", 0x6B3E1E),
+        ("Dark Brown", 0x3D2314),
+        ("Rust", 0xB7410E),
+        ("Red", 0xFF0000),
+        ("Cardinal Red", 0xC41E3A),
+        ("Burgundy", 0x800020),
+        ("Maroon", 0x5C0A1E),
+        ("Salmon", 0xFA8072),
+        ("Coral", 0xFF7F50),
+        ("Peach", 0xFFCBA4),
+        ("Orange", 0xFF8000),
+        ("Dark Orange", 0xD2691E),
+        ("Gold", 0xFFC000),
+        ("Old Gold", 0xCFB53B),
+        ("Lemon Yellow", 0xFFF44F),
+        ("Yellow", 0xFFFF00),
+        ("Pale Yellow", 0xFFF9B0),
+        ("Lime Green", 0x32CD32),
+        ("Kelly Green", 0x4CBB17),
+        ("Green", 0x008000),
+        ("Forest Green", 0x0B4F2C),
+        ("Olive", 0x6B6B1E),
+        ("Mint", 0x98E0B4),
+        ("Teal", 0x008080),
+        ("Turquoise", 0x30D5C8),
+        ("Sky Blue", 0x87CEEB),
+        ("Light Blue", 0xADD8E6),
+        ("Blue", 0x0000FF),
+        ("Royal Blue", 0x2750C0),
+        ("Navy Blue", 0x000080),
+        ("Lavender", 0xC8A2F0),
+        ("Purple", 0x800080),
+        ("Violet", 0x8F00FF),
+        ("Magenta", 0xFF00FF),
+        ("Hot Pink", 0xFF69B4),
+        ("Pink", 0xFFC0CB),
+        ("Rose", 0xE8A0B0),
+    ]
+    .iter()
+    .map(|&(name, color)| (name, EmbThread::new(color).to_lab()))
+    .collect();
+}
+
 // X11/CSS/SVG Named colors
 lazy_static! {
     static ref NAMED_COLORS: HashMap<&'static str, u32> = {
@@ -1191,4 +1310,27 @@ mod tests {
         assert!(hsl.saturation >= 0.0 && hsl.saturation <= 1.0);
         assert!(hsl.lightness >= 0.0 && hsl.lightness <= 1.0);
     }
+
+    #[test]
+    fn test_color_name_picks_nearest() {
+        assert_eq!(color_name(0x000000), "Black");
+        assert_eq!(color_name(0xFFFFFF), "White");
+        assert_eq!(color_name(0x0A0A85), "Navy Blue");
+        assert_eq!(color_name(0x0C5030), "Forest Green");
+        assert_eq!(color_name(0xFF6AB0), "Hot Pink");
+    }
+
+    #[test]
+    fn test_auto_describe_keeps_existing_description() {
+        let mut named = EmbThread::new(0xFF0000).with_description("Poppy");
+        assert_eq!(named.auto_describe(), "Poppy");
+
+        let mut blank = EmbThread::new(0xFF0000).with_description("  ");
+        assert_eq!(blank.auto_describe(), "Red");
+        assert_eq!(blank.description.as_deref(), Some("Red"));
+
+        let plain = EmbThread::new(0xFFC000);
+        assert_eq!(plain.display_name(), "Gold");
+        assert!(plain.description.is_none());
+    }
 }
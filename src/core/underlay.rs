@@ -0,0 +1,414 @@
+//! Underlay beneath fills and satin columns
+//!
+//! Underlay is sparse stitching sewn before the top stitches to hold the fabric
+//! down and lift the top stitches off it. Three kinds are supported:
+//!
+//! - **Edge run**: a running stitch just inside the edges
+//! - **Zig-zag**: long stitches from edge to edge; under satin columns a center
+//!   walk goes out first and the zig-zag comes back
+//! - **Tatami**: rows of running stitches
+//!
+//! Layers are added to [`TatamiFill`](crate::core::fill::TatamiFill) and
+//! [`SatinColumn`] with `add_underlay`, or put under an existing design with
+//! [`Underlay::apply_to`].
+//!
+//! Distances are in pattern units (0.1mm).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::core::satin::SatinColumn;
+//! use butabuti::core::underlay::Underlay;
+//!
+//! // 5mm wide column with an edge run and a zig-zag underneath
+//! let mut pattern = EmbPattern::new();
+//! SatinColumn::new(vec![(0.0, 0.0), (0.0, 300.0)], vec![(50.0, 0.0), (50.0, 300.0)])
+//!     .add_underlay(Underlay::edge_run().inset(5.0))
+//!     .add_underlay(Underlay::zigzag().spacing(25.0))
+//!     .add_to(&mut pattern)?;
+//!
+//! assert_eq!(pattern.count_trims(), 0);
+//! assert!(pattern.count_stitches() > 76);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::fill::{inset_shape, scan_runs};
+use crate::core::path::running_stitch;
+use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::satin::SatinColumn;
+use crate::geometry::{convex_hull, polygon_area, Point};
+use crate::utils::error::{Error, Result};
+
+/// Rails of satin columns are sampled this often (0.1mm) for inset underlay
+const RAIL_STEP: f64 = 10.0;
+
+/// Underlay stitch arrangement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlayKind {
+    /// Running stitch just inside the edges
+    EdgeRun,
+    /// Edge-to-edge zig-zag
+    ZigZag,
+    /// Rows of running stitches
+    Tatami,
+}
+
+/// One underlay layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Underlay {
+    /// Stitch arrangement
+    pub kind: UnderlayKind,
+    /// Distance kept from the edges in 0.1mm (default: 10.0)
+    pub inset: f64,
+    /// Distance between zig-zag stitches or tatami rows in 0.1mm (default: 20.0)
+    pub spacing: f64,
+    /// Running stitch length in 0.1mm (default: 30.0)
+    pub stitch_length: f64,
+    /// Row angle of zig-zag and tatami underlay in fills, and of tatami underlay
+    /// in satin columns, in degrees (default: 90.0)
+    pub angle: f64,
+}
+
+impl Underlay {
+    /// Create a layer of the given kind with default settings
+    pub fn new(kind: UnderlayKind) -> Self {
+        Self {
+            kind,
+            inset: 10.0,
+            spacing: 20.0,
+            stitch_length: 30.0,
+            angle: 90.0,
+        }
+    }
+
+    /// Edge run layer
+    pub fn edge_run() -> Self {
+        Self::new(UnderlayKind::EdgeRun)
+    }
+
+    /// Zig-zag layer
+    pub fn zigzag() -> Self {
+        Self::new(UnderlayKind::ZigZag)
+    }
+
+    /// Tatami layer
+    pub fn tatami() -> Self {
+        Self::new(UnderlayKind::Tatami)
+    }
+
+    /// Set the distance kept from the edges in 0.1mm
+    pub fn inset(mut self, inset: f64) -> Self {
+        self.inset = inset;
+        self
+    }
+
+    /// Set the zig-zag or row spacing in 0.1mm
+    pub fn spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Set the running stitch length in 0.1mm
+    pub fn stitch_length(mut self, stitch_length: f64) -> Self {
+        self.stitch_length = stitch_length;
+        self
+    }
+
+    /// Set the row angle in degrees
+    pub fn angle(mut self, angle: f64) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(self.inset >= 0.0 && self.inset.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Underlay inset must be non-negative, got {}",
+                self.inset
+            )));
+        }
+        if !(self.spacing > 0.0 && self.spacing.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Underlay spacing must be positive, got {}",
+                self.spacing
+            )));
+        }
+        if !(self.stitch_length > 0.0 && self.stitch_length.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Underlay stitch length must be positive, got {}",
+                self.stitch_length
+            )));
+        }
+        if !self.angle.is_finite() {
+            return Err(Error::InvalidPattern(
+                "Underlay angle must be finite".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Needle points of the layer under a fill shape, as connected runs
+    ///
+    /// The shape is an outer polygon followed by holes, as for
+    /// [`TatamiFill`](crate::core::fill::TatamiFill). Shapes too narrow for the
+    /// inset get no underlay.
+    pub fn shape_runs(&self, shape: &[Vec<Point>]) -> Result<Vec<Vec<Point>>> {
+        self.validate()?;
+        let Some(outer) = shape.first().filter(|outer| outer.len() >= 3) else {
+            return Err(Error::InvalidPattern(
+                "Underlay shape needs an outer polygon with at least 3 points".to_string(),
+            ));
+        };
+
+        let inset = inset_shape(shape, self.inset);
+        // Deflating past the inner radius turns the outline inside out
+        let (before, after) = (polygon_area(outer), polygon_area(&inset[0]));
+        if after.abs() < 1e-6 || before.signum() != after.signum() {
+            return Ok(Vec::new());
+        }
+
+        match self.kind {
+            UnderlayKind::EdgeRun => inset
+                .iter()
+                .filter(|polygon| polygon.len() >= 3)
+                .map(|polygon| running_stitch(polygon, self.stitch_length, true))
+                .collect(),
+            UnderlayKind::ZigZag => {
+                // One stitch per row: no row is longer than the shape's diagonal
+                let (min_x, min_y, max_x, max_y) = bounds(outer);
+                let across = (max_x - min_x).hypot(max_y - min_y) + 1.0;
+                scan_runs(&inset, self.angle, self.spacing, across, 1)
+            }
+            UnderlayKind::Tatami => {
+                scan_runs(&inset, self.angle, self.spacing, self.stitch_length, 1)
+            }
+        }
+    }
+
+    /// Needle points of the layer under a satin column, as connected runs
+    ///
+    /// Edge runs go out along one rail and back along the other, and zig-zags
+    /// come back along a center walk, so both end near the column's start.
+    pub fn satin_runs(&self, column: &SatinColumn) -> Result<Vec<Vec<Point>>> {
+        self.validate()?;
+        let (rail_a, rail_b) = column.inset_rails(self.inset, RAIL_STEP)?;
+
+        match self.kind {
+            UnderlayKind::EdgeRun => {
+                let path: Vec<Point> = rail_a.iter().chain(rail_b.iter().rev()).copied().collect();
+                Ok(vec![running_stitch(&path, self.stitch_length, false)?])
+            }
+            UnderlayKind::ZigZag => {
+                let center: Vec<Point> = rail_a
+                    .iter()
+                    .zip(&rail_b)
+                    .map(|(a, b)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0))
+                    .collect();
+                let mut run = running_stitch(&center, self.stitch_length, false)?;
+                let back = SatinColumn::new(
+                    rail_a.iter().rev().copied().collect(),
+                    rail_b.iter().rev().copied().collect(),
+                )
+                .density(self.spacing)
+                .stitches()?;
+                run.extend(back);
+                Ok(vec![run])
+            }
+            UnderlayKind::Tatami => {
+                let outline: Vec<Point> =
+                    rail_a.iter().chain(rail_b.iter().rev()).copied().collect();
+                if polygon_area(&outline).abs() < 1e-6 {
+                    return Ok(Vec::new());
+                }
+                scan_runs(&[outline], self.angle, self.spacing, self.stitch_length, 1)
+            }
+        }
+    }
+
+    /// Put the layer under each stitch run of an existing design
+    ///
+    /// A run is a stretch of needle-down stitches between other commands. Each
+    /// run's underlay fills the convex hull of its needle points, so this works
+    /// best on designs made of compact shapes; runs too narrow for the inset are
+    /// left alone. The underlay is sewn right before its run, joined with jumps.
+    ///
+    /// Returns the number of runs that got underlay.
+    pub fn apply_to(&self, pattern: &mut EmbPattern) -> Result<usize> {
+        self.validate()?;
+        let stitches = pattern.stitches();
+        let mut output: Vec<Stitch> = Vec::with_capacity(stitches.len());
+        let mut underlaid = 0;
+        let mut i = 0;
+        while i < stitches.len() {
+            if stitches[i].command & COMMAND_MASK != STITCH {
+                output.push(stitches[i]);
+                i += 1;
+                continue;
+            }
+            let end = i + stitches[i..]
+                .iter()
+                .take_while(|s| s.command & COMMAND_MASK == STITCH)
+                .count();
+            let run = &stitches[i..end];
+            let start = output
+                .last()
+                .map(|s| (s.x, s.y))
+                .unwrap_or((run[0].x, run[0].y));
+
+            let points: Vec<Point> = std::iter::once(start)
+                .chain(run.iter().map(|s| (s.x, s.y)))
+                .collect();
+            let hull = convex_hull(&points);
+            let layer = if hull.len() >= 3 {
+                self.shape_runs(&[hull])?
+            } else {
+                Vec::new()
+            };
+            if layer.iter().any(|points| !points.is_empty()) {
+                underlaid += 1;
+                for points in layer.iter().filter(|points| !points.is_empty()) {
+                    output.push(Stitch::new(points[0].0, points[0].1, JUMP));
+                    output.extend(points.iter().map(|&(x, y)| Stitch::new(x, y, STITCH)));
+                }
+                output.push(Stitch::new(start.0, start.1, JUMP));
+            }
+            output.extend_from_slice(run);
+            i = end;
+        }
+
+        if underlaid > 0 {
+            let threads = pattern.threads().to_vec();
+            pattern.replace_stitches(output, threads);
+        }
+        Ok(underlaid)
+    }
+}
+
+fn bounds(points: &[Point]) -> (f64, f64, f64, f64) {
+    points.iter().fold(
+        (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+        |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::fill::TatamiFill;
+
+    fn square(min: f64, max: f64) -> Vec<Point> {
+        vec![(min, min), (max, min), (max, max), (min, max)]
+    }
+
+    fn inside(points: &[Point], min: f64, max: f64) -> bool {
+        points
+            .iter()
+            .all(|&(x, y)| x >= min - 1e-6 && x <= max + 1e-6 && y >= min - 1e-6 && y <= max + 1e-6)
+    }
+
+    #[test]
+    fn test_shape_underlay_kinds() {
+        let shape = [square(0.0, 200.0)];
+
+        let edge = Underlay::edge_run().inset(20.0).shape_runs(&shape).unwrap();
+        assert_eq!(edge.len(), 1);
+        assert!(inside(&edge[0], 20.0, 180.0));
+        assert_eq!(edge[0].first(), edge[0].last());
+
+        // Vertical rows, one stitch each, alternating between top and bottom
+        let zigzag = Underlay::zigzag().spacing(40.0).shape_runs(&shape).unwrap();
+        assert_eq!(zigzag.len(), 1);
+        assert!(inside(&zigzag[0], 10.0, 190.0));
+        let row_ends = zigzag[0]
+            .iter()
+            .filter(|p| (p.1 - 10.0).abs() < 1e-6 || (p.1 - 190.0).abs() < 1e-6)
+            .count();
+        assert_eq!(row_ends, zigzag[0].len());
+
+        let tatami = Underlay::tatami().shape_runs(&shape).unwrap();
+        assert!(tatami[0].len() > zigzag[0].len());
+
+        // Too narrow for the inset
+        let sliver = [vec![(0.0, 0.0), (200.0, 0.0), (200.0, 10.0), (0.0, 10.0)]];
+        assert!(Underlay::edge_run().shape_runs(&sliver).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fill_with_edge_run() {
+        let shape = [square(0.0, 200.0)];
+        let plain = TatamiFill::new().runs(&shape).unwrap();
+        let runs = TatamiFill::new()
+            .add_underlay(Underlay::edge_run())
+            .runs(&shape)
+            .unwrap();
+        assert_eq!(runs.len(), plain.len() + 1);
+        assert_eq!(runs[1..], plain[..]);
+    }
+
+    #[test]
+    fn test_satin_underlay() {
+        let column = SatinColumn::new(
+            vec![(0.0, 0.0), (0.0, 300.0)],
+            vec![(60.0, 0.0), (60.0, 300.0)],
+        );
+
+        let edge = Underlay::edge_run().satin_runs(&column).unwrap();
+        // Along both inset rails, crossing over at the far end
+        assert!(edge[0]
+            .iter()
+            .filter(|p| p.1 < 300.0 - 1e-6)
+            .all(|p| (p.0 - 10.0).abs() < 1e-6 || (p.0 - 50.0).abs() < 1e-6));
+        assert!(edge[0].last().unwrap().1.abs() < 1e-6);
+
+        // Center walk out, zig-zag back to the start
+        let zigzag = Underlay::zigzag().satin_runs(&column).unwrap();
+        assert_eq!(zigzag[0][0], (30.0, 0.0));
+        assert!(zigzag[0].last().unwrap().1.abs() < 1e-6);
+
+        let mut pattern = EmbPattern::new();
+        column
+            .clone()
+            .add_underlay(Underlay::zigzag())
+            .add_to(&mut pattern)
+            .unwrap();
+        assert_eq!(pattern.count_trims(), 0);
+        assert_eq!(
+            pattern.count_stitches(),
+            zigzag[0].len() + column.stitches().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_apply_to_design() {
+        let mut pattern = EmbPattern::new();
+        pattern.jump_abs(0.0, 0.0);
+        for y in (0..=200).step_by(4) {
+            let x = if y % 8 == 0 { 0.0 } else { 200.0 };
+            pattern.stitch_abs(x, y as f64);
+        }
+        pattern.trim();
+        pattern.jump_abs(500.0, 0.0);
+        pattern.stitch_abs(600.0, 0.0);
+        pattern.end();
+        let top = pattern.count_stitches();
+
+        let count = Underlay::edge_run().apply_to(&mut pattern).unwrap();
+        assert_eq!(count, 1);
+        assert!(pattern.count_stitches() > top);
+        assert_eq!(pattern.stitches()[0].command, JUMP);
+        assert_eq!(pattern.stitches()[1].command, JUMP);
+        let (x, y) = (pattern.stitches()[2].x, pattern.stitches()[2].y);
+        assert!(x >= 10.0 && y >= 10.0 && x <= 190.0 && y <= 190.0);
+
+        assert!(Underlay::tatami()
+            .spacing(0.0)
+            .apply_to(&mut pattern)
+            .is_err());
+        assert!(Underlay::edge_run()
+            .inset(-1.0)
+            .shape_runs(&[square(0.0, 10.0)])
+            .is_err());
+    }
+}
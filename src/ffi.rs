@@ -0,0 +1,509 @@
+//! C ABI for Butabuti
+//!
+//! A small, stable C interface for integrating with C and C++ software. The
+//! declarations are in `include/butabuti.h`.
+//!
+//! - Patterns are opaque `ButabutiPattern` handles created by
+//!   `butabuti_pattern_new`, `butabuti_read_file` or `butabuti_read_bytes`, and
+//!   released with `butabuti_pattern_free`.
+//! - Fallible functions return a [`ButabutiStatus`]; on failure,
+//!   `butabuti_last_error_message` describes the error. The message belongs to
+//!   the calling thread and stays valid until its next failing call.
+//! - Strings are NUL-terminated UTF-8. Coordinates are in 0.1mm and commands use
+//!   the values of [`crate::core::constants`].
+//! - Panics never cross the boundary; they are reported as
+//!   [`ButabutiStatus::Panic`].
+//!
+//! # Example
+//!
+//! ```c
+//! #include "butabuti.h"
+//!
+//! static int print_stitch(void *user_data, double x, double y, uint32_t command) {
+//!     printf("%f %f %u\n", x, y, command);
+//!     return 0;
+//! }
+//!
+//! ButabutiPattern *pattern = NULL;
+//! if (butabuti_read_file("design.pes", &pattern) != BUTABUTI_OK) {
+//!     fprintf(stderr, "%s\n", butabuti_last_error_message());
+//!     return 1;
+//! }
+//! butabuti_pattern_for_each_stitch(pattern, print_stitch, NULL);
+//! butabuti_write_file(pattern, "design.dst");
+//! butabuti_pattern_free(pattern);
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, ErrorKind};
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Result of a fallible C API call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButabutiStatus {
+    /// Success
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// Reading or writing a file failed
+    Io = 3,
+    /// The input could not be parsed
+    Parse = 4,
+    /// The format is unknown or not supported for this operation
+    UnsupportedFormat = 5,
+    /// The pattern is invalid or exceeds the format's limits
+    InvalidPattern = 6,
+    /// An index was out of range
+    OutOfRange = 7,
+    /// Any other error
+    Other = 8,
+    /// A panic was caught
+    Panic = 9,
+}
+
+impl From<&Error> for ButabutiStatus {
+    fn from(err: &Error) -> Self {
+        match err.kind() {
+            ErrorKind::Io(_) => Self::Io,
+            ErrorKind::Parse(_)
+            | ErrorKind::Truncated(_)
+            | ErrorKind::UnexpectedValue { .. }
+            | ErrorKind::LimitExceeded { .. }
+            | ErrorKind::Json(_)
+            | ErrorKind::InvalidColor(_) => Self::Parse,
+            ErrorKind::UnsupportedFormat(_) | ErrorKind::Unsupported(_) => Self::UnsupportedFormat,
+            ErrorKind::InvalidPattern(_) | ErrorKind::Encoding(_) => Self::InvalidPattern,
+            ErrorKind::ThreadIndexOutOfBounds(_) => Self::OutOfRange,
+        }
+    }
+}
+
+/// Opaque pattern handle
+pub struct ButabutiPattern {
+    pattern: EmbPattern,
+}
+
+/// Called for each stitch by [`butabuti_pattern_for_each_stitch`]
+///
+/// Returning non-zero stops the iteration.
+pub type ButabutiStitchCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, x: f64, y: f64, command: u32) -> i32>;
+
+fn set_last_error(status: ButabutiStatus, message: impl Into<String>) -> ButabutiStatus {
+    let message =
+        CString::new(message.into().replace('\0', " ")).unwrap_or_else(|_| CString::from(c"error"));
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+    status
+}
+
+fn fail(err: Error) -> ButabutiStatus {
+    set_last_error(ButabutiStatus::from(&err), err.to_string())
+}
+
+/// Run a call body, turning panics into [`ButabutiStatus::Panic`]
+fn guard(body: impl FnOnce() -> ButabutiStatus) -> ButabutiStatus {
+    catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| set_last_error(ButabutiStatus::Panic, "panic inside butabuti"))
+}
+
+/// Borrow a C string argument
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, ButabutiStatus> {
+    if ptr.is_null() {
+        return Err(set_last_error(
+            ButabutiStatus::NullPointer,
+            format!("{} is null", name),
+        ));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| {
+        set_last_error(
+            ButabutiStatus::InvalidUtf8,
+            format!("{} is not valid UTF-8", name),
+        )
+    })
+}
+
+fn null_pointer(name: &str) -> ButabutiStatus {
+    set_last_error(ButabutiStatus::NullPointer, format!("{} is null", name))
+}
+
+fn into_handle(pattern: EmbPattern) -> *mut ButabutiPattern {
+    Box::into_raw(Box::new(ButabutiPattern { pattern }))
+}
+
+/// Library version as a static NUL-terminated string
+#[no_mangle]
+pub extern "C" fn butabuti_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Message of the calling thread's last error, or null if there was none
+///
+/// The string stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn butabuti_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Create an empty pattern
+#[no_mangle]
+pub extern "C" fn butabuti_pattern_new() -> *mut ButabutiPattern {
+    into_handle(EmbPattern::new())
+}
+
+/// Release a pattern; null is ignored
+///
+/// # Safety
+///
+/// `pattern` must be null or a handle from this library that was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_free(pattern: *mut ButabutiPattern) {
+    if !pattern.is_null() {
+        drop(Box::from_raw(pattern));
+    }
+}
+
+/// Read a file, choosing the format from its extension and contents
+///
+/// On success `*out` receives a new handle.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_read_file(
+    path: *const c_char,
+    out: *mut *mut ButabutiPattern,
+) -> ButabutiStatus {
+    guard(|| {
+        if out.is_null() {
+            return null_pointer("out");
+        }
+        let path = match str_arg(path, "path") {
+            Ok(path) => path,
+            Err(status) => return status,
+        };
+        match EmbPattern::read(path) {
+            Ok(pattern) => {
+                *out = into_handle(pattern);
+                ButabutiStatus::Ok
+            }
+            Err(err) => fail(err),
+        }
+    })
+}
+
+/// Read a pattern of the given format ("dst", "pes", ...) from memory
+///
+/// On success `*out` receives a new handle.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `format` must be a NUL-terminated
+/// string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_read_bytes(
+    data: *const u8,
+    len: usize,
+    format: *const c_char,
+    out: *mut *mut ButabutiPattern,
+) -> ButabutiStatus {
+    guard(|| {
+        if out.is_null() {
+            return null_pointer("out");
+        }
+        if data.is_null() && len > 0 {
+            return null_pointer("data");
+        }
+        let format = match str_arg(format, "format") {
+            Ok(format) => format,
+            Err(status) => return status,
+        };
+        let data = if len == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(data, len)
+        };
+        match crate::formats::io::read_from_bytes(data, format) {
+            Ok(pattern) => {
+                *out = into_handle(pattern);
+                ButabutiStatus::Ok
+            }
+            Err(err) => fail(err),
+        }
+    })
+}
+
+/// Write a pattern to a file, choosing the format from its extension
+///
+/// # Safety
+///
+/// `pattern` must be a valid handle and `path` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_write_file(
+    pattern: *const ButabutiPattern,
+    path: *const c_char,
+) -> ButabutiStatus {
+    guard(|| {
+        let Some(pattern) = pattern.as_ref() else {
+            return null_pointer("pattern");
+        };
+        let path = match str_arg(path, "path") {
+            Ok(path) => path,
+            Err(status) => return status,
+        };
+        match pattern.pattern.write(path) {
+            Ok(()) => ButabutiStatus::Ok,
+            Err(err) => fail(err),
+        }
+    })
+}
+
+/// Number of stitch records, including jumps, trims and other commands
+///
+/// Returns 0 for a null handle.
+///
+/// # Safety
+///
+/// `pattern` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_stitch_count(pattern: *const ButabutiPattern) -> usize {
+    pattern.as_ref().map_or(0, |p| p.pattern.stitches().len())
+}
+
+/// Number of threads
+///
+/// Returns 0 for a null handle.
+///
+/// # Safety
+///
+/// `pattern` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_thread_count(pattern: *const ButabutiPattern) -> usize {
+    pattern.as_ref().map_or(0, |p| p.pattern.threads().len())
+}
+
+/// Color (0xRRGGBB) of the thread at `index`
+///
+/// # Safety
+///
+/// `pattern` must be a valid handle and `color` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_thread_color(
+    pattern: *const ButabutiPattern,
+    index: usize,
+    color: *mut u32,
+) -> ButabutiStatus {
+    let Some(pattern) = pattern.as_ref() else {
+        return null_pointer("pattern");
+    };
+    if color.is_null() {
+        return null_pointer("color");
+    }
+    match pattern.pattern.threads().get(index) {
+        Some(thread) => {
+            *color = thread.color;
+            ButabutiStatus::Ok
+        }
+        None => fail(Error::ThreadIndexOutOfBounds(index)),
+    }
+}
+
+/// Bounding box of all stitches in 0.1mm
+///
+/// # Safety
+///
+/// `pattern` must be a valid handle and the output pointers valid.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_bounds(
+    pattern: *const ButabutiPattern,
+    min_x: *mut f64,
+    min_y: *mut f64,
+    max_x: *mut f64,
+    max_y: *mut f64,
+) -> ButabutiStatus {
+    let Some(pattern) = pattern.as_ref() else {
+        return null_pointer("pattern");
+    };
+    if min_x.is_null() || min_y.is_null() || max_x.is_null() || max_y.is_null() {
+        return null_pointer("bounds output");
+    }
+    let bounds = pattern.pattern.bounds();
+    (*min_x, *min_y, *max_x, *max_y) = bounds;
+    ButabutiStatus::Ok
+}
+
+/// Call `callback` with each stitch record in order
+///
+/// Iteration stops early when the callback returns non-zero; that still counts
+/// as success. `user_data` is passed through unchanged.
+///
+/// # Safety
+///
+/// `pattern` must be a valid handle and `callback` safe to call with
+/// `user_data`.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_for_each_stitch(
+    pattern: *const ButabutiPattern,
+    callback: ButabutiStitchCallback,
+    user_data: *mut c_void,
+) -> ButabutiStatus {
+    let Some(pattern) = pattern.as_ref() else {
+        return null_pointer("pattern");
+    };
+    let Some(callback) = callback else {
+        return null_pointer("callback");
+    };
+    for stitch in pattern.pattern.stitches() {
+        if callback(user_data, stitch.x, stitch.y, stitch.command) != 0 {
+            break;
+        }
+    }
+    ButabutiStatus::Ok
+}
+
+/// Append a stitch record at absolute coordinates
+///
+/// # Safety
+///
+/// `pattern` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_add_stitch(
+    pattern: *mut ButabutiPattern,
+    command: u32,
+    x: f64,
+    y: f64,
+) -> ButabutiStatus {
+    let Some(pattern) = pattern.as_mut() else {
+        return null_pointer("pattern");
+    };
+    pattern.pattern.add_stitch_absolute(command, x, y);
+    ButabutiStatus::Ok
+}
+
+/// Append a thread of the given color (0xRRGGBB)
+///
+/// # Safety
+///
+/// `pattern` must be a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn butabuti_pattern_add_thread(
+    pattern: *mut ButabutiPattern,
+    color: u32,
+) -> ButabutiStatus {
+    let Some(pattern) = pattern.as_mut() else {
+        return null_pointer("pattern");
+    };
+    pattern.pattern.add_thread(EmbThread::new(color & 0xFFFFFF));
+    ButabutiStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+
+    unsafe extern "C" fn collect(user_data: *mut c_void, x: f64, y: f64, command: u32) -> i32 {
+        let stitches = &mut *(user_data as *mut Vec<(f64, f64, u32)>);
+        stitches.push((x, y, command));
+        i32::from(stitches.len() == 2)
+    }
+
+    #[test]
+    fn test_build_write_and_read_back() {
+        let dir = std::env::temp_dir().join(format!("butabuti_ffi_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = CString::new(dir.join("ffi.dst").to_str().unwrap()).unwrap();
+
+        unsafe {
+            let pattern = butabuti_pattern_new();
+            assert_eq!(
+                butabuti_pattern_add_thread(pattern, 0xFF0000),
+                ButabutiStatus::Ok
+            );
+            butabuti_pattern_add_stitch(pattern, STITCH, 0.0, 0.0);
+            butabuti_pattern_add_stitch(pattern, STITCH, 50.0, 20.0);
+            butabuti_pattern_add_stitch(pattern, END, 50.0, 20.0);
+            assert_eq!(
+                butabuti_write_file(pattern, path.as_ptr()),
+                ButabutiStatus::Ok
+            );
+            butabuti_pattern_free(pattern);
+
+            let mut read = std::ptr::null_mut();
+            assert_eq!(
+                butabuti_read_file(path.as_ptr(), &mut read),
+                ButabutiStatus::Ok
+            );
+            assert!(butabuti_pattern_stitch_count(read) >= 3);
+            assert_eq!(butabuti_pattern_thread_count(read), 1);
+
+            let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0, 0.0, 0.0, 0.0);
+            butabuti_pattern_bounds(read, &mut min_x, &mut min_y, &mut max_x, &mut max_y);
+            assert_eq!((max_x - min_x, max_y - min_y), (50.0, 20.0));
+
+            let mut stitches: Vec<(f64, f64, u32)> = Vec::new();
+            let status = butabuti_pattern_for_each_stitch(
+                read,
+                Some(collect),
+                &mut stitches as *mut _ as *mut c_void,
+            );
+            assert_eq!(status, ButabutiStatus::Ok);
+            assert_eq!(stitches.len(), 2);
+            butabuti_pattern_free(read);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_errors_and_messages() {
+        unsafe {
+            let mut out = std::ptr::null_mut();
+            let missing = CString::new("/nonexistent/butabuti/design.dst").unwrap();
+            assert_eq!(
+                butabuti_read_file(missing.as_ptr(), &mut out),
+                ButabutiStatus::Io
+            );
+            assert!(out.is_null());
+            let message = CStr::from_ptr(butabuti_last_error_message());
+            assert!(!message.to_bytes().is_empty());
+
+            let format = CString::new("nope").unwrap();
+            let data = [0u8; 8];
+            assert_eq!(
+                butabuti_read_bytes(data.as_ptr(), data.len(), format.as_ptr(), &mut out),
+                ButabutiStatus::UnsupportedFormat
+            );
+            assert_eq!(
+                butabuti_read_file(std::ptr::null(), &mut out),
+                ButabutiStatus::NullPointer
+            );
+
+            let pattern = butabuti_pattern_new();
+            let mut color = 0;
+            assert_eq!(
+                butabuti_pattern_thread_color(pattern, 3, &mut color),
+                ButabutiStatus::OutOfRange
+            );
+            butabuti_pattern_free(pattern);
+            butabuti_pattern_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,442 @@
+//! Built-in embroidery file formats
+//!
+//! [`Format`] names every built-in format together with its capabilities:
+//! whether it can be read and written, its file extensions, the longest stitch
+//! it can encode and the limits its writer enforces. APIs that work with
+//! built-in formats take a `Format` so a misspelled name is caught when it is
+//! parsed rather than deep inside a conversion. Names and extensions still
+//! parse through [`FromStr`], case-insensitively.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::formats::Format;
+//!
+//! let format: Format = "dst".parse()?;
+//! assert_eq!(format, Format::DST);
+//! assert!(format.can_read() && format.can_write());
+//! assert_eq!(format.max_stitch_length(), Some(121.0));
+//! assert_eq!(format.max_colors(), Some(1000));
+//!
+//! assert!("xyz".parse::<Format>().is_err());
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::formats::registry::WriterLimits;
+use crate::utils::error::{Error, Result};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Supported embroidery file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// Tajima DST (no magic bytes, 512-byte header)
+    DST,
+    /// Brother PES (#PES or #PEC prefix)
+    PES,
+    /// Pfaff VP3 (%vsm% signature)
+    VP3,
+    /// Janome JEF (0x74 at offset 0)
+    JEF,
+    /// Melco EXP
+    EXP,
+    /// Brother PEC (#PEC prefix)
+    PEC,
+    /// Singer XXX
+    XXX,
+    /// Barudan U01
+    U01,
+    /// Barudan DSB (DST header, B-stitch records)
+    DSB,
+    /// ZSK DSZ (DST header, Z-stitch records)
+    DSZ,
+    /// ZSK TC (`.zxy` or numbered `.z??` files)
+    ZXY,
+    /// Happy TAP (DST records without a header)
+    TAP,
+    /// Tajima TBF
+    TBF,
+    /// ThredWorks THR ("thr" signature)
+    THR,
+    /// Thread color list (COL)
+    COL,
+    /// Embird color (EDR)
+    EDR,
+    /// Thread information (INF)
+    INF,
+    /// JSON embroidery data
+    JSON,
+    /// CSV embroidery data
+    CSV,
+    /// G-code embroidery data
+    GCODE,
+    /// Husqvarna Viking HUS (compressed stitch streams)
+    HUS,
+    /// Husqvarna Viking SHV
+    SHV,
+    /// HPGL plotter file (starts with "IN;")
+    PLT,
+    /// SVG vector graphics
+    SVG,
+    /// Wilcom EMB (OLE compound document signature)
+    EMB,
+    /// Human-readable text dump
+    TXT,
+    /// ButaButi lossless archive (BUTA signature)
+    BUTA,
+    /// Unknown/unsupported format
+    Unknown,
+}
+
+impl Format {
+    /// Every known format, in registry order (`Unknown` excluded)
+    pub const ALL: &'static [Format] = &[
+        Format::DST,
+        Format::PES,
+        Format::JEF,
+        Format::EXP,
+        Format::VP3,
+        Format::PEC,
+        Format::XXX,
+        Format::U01,
+        Format::DSB,
+        Format::DSZ,
+        Format::ZXY,
+        Format::TBF,
+        Format::COL,
+        Format::EDR,
+        Format::INF,
+        Format::JSON,
+        Format::CSV,
+        Format::GCODE,
+        Format::PLT,
+        Format::SVG,
+        Format::EMB,
+        Format::TXT,
+        Format::BUTA,
+        Format::HUS,
+        Format::SHV,
+        Format::TAP,
+        Format::THR,
+    ];
+
+    /// Display name of the format (uppercase, e.g. "DST")
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::DST => "DST",
+            Format::PES => "PES",
+            Format::VP3 => "VP3",
+            Format::JEF => "JEF",
+            Format::EXP => "EXP",
+            Format::PEC => "PEC",
+            Format::XXX => "XXX",
+            Format::U01 => "U01",
+            Format::DSB => "DSB",
+            Format::DSZ => "DSZ",
+            Format::ZXY => "ZXY",
+            Format::TAP => "TAP",
+            Format::TBF => "TBF",
+            Format::THR => "THR",
+            Format::COL => "COL",
+            Format::EDR => "EDR",
+            Format::INF => "INF",
+            Format::JSON => "JSON",
+            Format::CSV => "CSV",
+            Format::GCODE => "GCODE",
+            Format::HUS => "HUS",
+            Format::SHV => "SHV",
+            Format::PLT => "PLT",
+            Format::SVG => "SVG",
+            Format::EMB => "EMB",
+            Format::TXT => "TXT",
+            Format::BUTA => "BUTA",
+            Format::Unknown => "Unknown",
+        }
+    }
+
+    /// Registry name of the format (lowercase), `None` for `Unknown`
+    pub fn registry_name(self) -> Option<&'static str> {
+        match self {
+            Format::DST => Some("dst"),
+            Format::PES => Some("pes"),
+            Format::VP3 => Some("vp3"),
+            Format::JEF => Some("jef"),
+            Format::EXP => Some("exp"),
+            Format::PEC => Some("pec"),
+            Format::XXX => Some("xxx"),
+            Format::U01 => Some("u01"),
+            Format::DSB => Some("dsb"),
+            Format::DSZ => Some("dsz"),
+            Format::ZXY => Some("zxy"),
+            Format::TAP => Some("tap"),
+            Format::TBF => Some("tbf"),
+            Format::THR => Some("thr"),
+            Format::COL => Some("col"),
+            Format::EDR => Some("edr"),
+            Format::INF => Some("inf"),
+            Format::JSON => Some("json"),
+            Format::CSV => Some("csv"),
+            Format::GCODE => Some("gcode"),
+            Format::HUS => Some("hus"),
+            Format::SHV => Some("shv"),
+            Format::PLT => Some("plt"),
+            Format::SVG => Some("svg"),
+            Format::EMB => Some("emb"),
+            Format::TXT => Some("txt"),
+            Format::BUTA => Some("buta"),
+            Format::Unknown => None,
+        }
+    }
+
+    /// Human-readable description
+    pub fn description(self) -> &'static str {
+        match self {
+            Format::DST => "Tajima DST format",
+            Format::PES => "Brother PES format",
+            Format::VP3 => "Pfaff VP3 format",
+            Format::JEF => "Janome JEF format",
+            Format::EXP => "Melco EXP format",
+            Format::PEC => "Brother PEC format",
+            Format::XXX => "Singer XXX format",
+            Format::U01 => "Barudan U01 format",
+            Format::DSB => "Barudan DSB format",
+            Format::DSZ => "ZSK DSZ format",
+            Format::ZXY => "ZSK TC format",
+            Format::TAP => "Happy TAP format (read-only)",
+            Format::TBF => "Tajima TBF format",
+            Format::THR => "ThredWorks THR format (read-only)",
+            Format::COL => "Thread color list",
+            Format::EDR => "Embird color format",
+            Format::INF => "Thread information format",
+            Format::JSON => "JSON embroidery data",
+            Format::CSV => "CSV embroidery data",
+            Format::GCODE => "G-code embroidery format",
+            Format::HUS => "Husqvarna Viking HUS format (VIP read without colors)",
+            Format::SHV => "Husqvarna Viking SHV format",
+            Format::PLT => "HPGL plotter format (sequin devices)",
+            Format::SVG => "SVG vector graphics (outlines read as running stitches)",
+            Format::EMB => "Wilcom EMB (experimental, read-only)",
+            Format::TXT => "Human-readable text (write-only)",
+            Format::BUTA => "ButaButi lossless archive",
+            Format::Unknown => "Unknown format",
+        }
+    }
+
+    /// File extensions of the format, lowercase without the dot
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::DST => &["dst"],
+            Format::PES => &["pes"],
+            Format::VP3 => &["vp3"],
+            Format::JEF => &["jef"],
+            Format::EXP => &["exp"],
+            Format::PEC => &["pec"],
+            Format::XXX => &["xxx"],
+            Format::U01 => &["u01"],
+            Format::DSB => &["dsb"],
+            Format::DSZ => &["dsz"],
+            Format::ZXY => &["zxy"],
+            Format::TAP => &["tap"],
+            Format::TBF => &["tbf"],
+            Format::THR => &["thr"],
+            Format::COL => &["col"],
+            Format::EDR => &["edr"],
+            Format::INF => &["inf"],
+            Format::JSON => &["json"],
+            Format::CSV => &["csv"],
+            Format::GCODE => &["gcode", "nc"],
+            Format::HUS => &["hus", "vip"],
+            Format::SHV => &["shv"],
+            Format::PLT => &["plt", "hpgl"],
+            Format::SVG => &["svg"],
+            Format::EMB => &["emb"],
+            Format::TXT => &["txt"],
+            Format::BUTA => &["buta"],
+            Format::Unknown => &[],
+        }
+    }
+
+    /// Whether a reader exists for the format
+    pub fn can_read(self) -> bool {
+        !matches!(self, Format::TXT | Format::Unknown)
+    }
+
+    /// Whether a writer exists for the format
+    pub fn can_write(self) -> bool {
+        !matches!(
+            self,
+            Format::EMB | Format::TAP | Format::THR | Format::Unknown
+        )
+    }
+
+    /// Whether the format was recognized by an unambiguous file signature
+    ///
+    /// Signature matches win over a conflicting file extension.
+    pub fn has_signature(self) -> bool {
+        matches!(
+            self,
+            Format::PES
+                | Format::PEC
+                | Format::VP3
+                | Format::EMB
+                | Format::HUS
+                | Format::THR
+                | Format::BUTA
+        )
+    }
+
+    /// Longest stitch the format can encode per axis (0.1mm units)
+    ///
+    /// `None` for formats storing absolute coordinates, which have no limit.
+    pub fn max_stitch_length(self) -> Option<f64> {
+        match self {
+            Format::DST | Format::TAP => Some(121.0),
+            Format::PES
+            | Format::PEC
+            | Format::JEF
+            | Format::EXP
+            | Format::VP3
+            | Format::XXX
+            | Format::U01
+            | Format::DSB
+            | Format::DSZ
+            | Format::ZXY
+            | Format::HUS
+            | Format::SHV
+            | Format::TBF => Some(127.0),
+            _ => None,
+        }
+    }
+
+    /// Limits enforced before writing
+    pub fn writer_limits(self) -> WriterLimits {
+        match self {
+            Format::DST => WriterLimits {
+                // CO: header field holds 3 digits, ST: holds 7
+                max_colors: Some(1000),
+                max_needles: None,
+                max_stitches: Some(9_999_999),
+            },
+            // Embedded PEC color table count is a single byte
+            Format::PES | Format::PEC => WriterLimits {
+                max_colors: Some(256),
+                ..WriterLimits::UNLIMITED
+            },
+            Format::U01 | Format::DSB => WriterLimits {
+                // Needle set command encodes needles 1-15
+                max_needles: Some(15),
+                ..WriterLimits::UNLIMITED
+            },
+            Format::DSZ => WriterLimits {
+                // Needle set command encodes needles 1-12
+                max_needles: Some(12),
+                ..WriterLimits::UNLIMITED
+            },
+            Format::SHV => WriterLimits {
+                // Color count is a single byte
+                max_colors: Some(255),
+                ..WriterLimits::UNLIMITED
+            },
+            Format::TBF => WriterLimits {
+                // Thread order table has 256 entries
+                max_colors: Some(256),
+                ..WriterLimits::UNLIMITED
+            },
+            _ => WriterLimits::UNLIMITED,
+        }
+    }
+
+    /// Maximum number of colors the writer accepts, `None` if unlimited
+    pub fn max_colors(self) -> Option<usize> {
+        self.writer_limits().max_colors
+    }
+
+    /// Format of a file extension (with or without the dot), case-insensitive
+    ///
+    /// ZSK TC files numbered `.z00` to `.z99` are recognized as [`Format::ZXY`].
+    pub fn from_extension(extension: &str) -> Option<Format> {
+        let extension = extension.trim_start_matches('.');
+        if let [b'z' | b'Z', a, b] = extension.as_bytes() {
+            if a.is_ascii_digit() && b.is_ascii_digit() {
+                return Some(Format::ZXY);
+            }
+        }
+        Self::ALL.iter().copied().find(|format| {
+            format
+                .extensions()
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+    }
+
+    /// Format of a file path from its extension
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+        path.as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    /// Parse a format name or extension, case-insensitively
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .or_else(|| Self::from_extension(s))
+            .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown format: {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names_and_extensions() {
+        assert_eq!("PES".parse::<Format>().unwrap(), Format::PES);
+        assert_eq!("gcode".parse::<Format>().unwrap(), Format::GCODE);
+        assert_eq!(".NC".parse::<Format>().unwrap(), Format::GCODE);
+        assert_eq!(" hpgl ".parse::<Format>().unwrap(), Format::PLT);
+        assert!("unknown".parse::<Format>().is_err());
+        assert!("".parse::<Format>().is_err());
+
+        assert_eq!(Format::from_path("design.Jef"), Some(Format::JEF));
+        assert_eq!(Format::from_path("design"), None);
+        assert_eq!(Format::from_path("head.Z03"), Some(Format::ZXY));
+        assert_eq!(Format::from_extension("zip"), None);
+
+        for &format in Format::ALL {
+            assert_eq!(format.to_string().parse::<Format>().unwrap(), format);
+            assert_eq!(
+                format.registry_name().unwrap(),
+                format.name().to_lowercase()
+            );
+        }
+    }
+
+    #[test]
+    fn test_capabilities() {
+        assert!(Format::EMB.can_read() && !Format::EMB.can_write());
+        assert!(!Format::TXT.can_read() && Format::TXT.can_write());
+        assert!(Format::THR.can_read() && !Format::THR.can_write());
+        assert!(Format::HUS.can_read() && Format::HUS.can_write());
+        assert_eq!(Format::from_extension("vip"), Some(Format::HUS));
+        assert_eq!(Format::PES.max_colors(), Some(256));
+        assert_eq!(Format::JSON.max_colors(), None);
+        assert_eq!(Format::U01.writer_limits().max_needles, Some(15));
+        assert_eq!(Format::CSV.max_stitch_length(), None);
+    }
+}
@@ -0,0 +1,258 @@
+//! Async reading, writing and conversion
+//!
+//! Web services converting uploads can't block their runtime on file I/O or
+//! on decoding a large design. With the `tokio` feature, patterns are read and
+//! written through `tokio::fs`, and decoding and encoding run on the blocking
+//! thread pool with `spawn_blocking`:
+//!
+//! - [`EmbPattern::read_async`] and [`EmbPattern::read_with_options_async`]
+//! - [`EmbPattern::write_async`] and [`EmbPattern::write_with_options_async`]
+//! - [`BatchConverterExecutor::convert_all_async`]
+//! - [`convert_bytes_async`](crate::service::convert_bytes_async) for uploads
+//!   held in memory
+//!
+//! They behave as their blocking counterparts, sibling color files included,
+//! and need a tokio runtime.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use butabuti::prelude::*;
+//!
+//! # async fn convert() -> Result<()> {
+//! let pattern = EmbPattern::read_async("upload.pes").await?;
+//! pattern.write_async("converted.dst").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::color_file::{color_file_candidates, color_file_path};
+use crate::formats::io::options::{ReadOptions, WriteOptions};
+use crate::formats::io::traits::{PatternReader, PatternWriter};
+use crate::formats::registry::FormatRegistry;
+use crate::utils::batch::{BatchConverterExecutor, ConversionResults};
+use crate::utils::error::{Error, Result};
+use std::io::{self, Cursor};
+use std::path::Path;
+
+/// Run CPU-bound work on the blocking thread pool
+pub(crate) async fn blocking<T, F>(work: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| Error::from(io::Error::other(e)))?
+}
+
+impl EmbPattern {
+    /// Read a pattern from a file without blocking the runtime
+    ///
+    /// The format is resolved as in [`read`](Self::read), and a sibling color
+    /// file is loaded the same way.
+    pub async fn read_async<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_with_options_async(path, &ReadOptions::default()).await
+    }
+
+    /// Read a pattern from a file with reader options without blocking the runtime
+    ///
+    /// See [`read_with_options`](Self::read_with_options).
+    pub async fn read_with_options_async<P: AsRef<Path>>(
+        path: P,
+        options: &ReadOptions,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = tokio::fs::read(&path).await?;
+
+        let design = path.clone();
+        let read_options = options.clone();
+        let mut pattern = blocking(move || {
+            let (pattern, _) = FormatRegistry::new().read_pattern_detected_with_options(
+                &mut Cursor::new(data),
+                Some(&design),
+                &read_options,
+            )?;
+            Ok(pattern)
+        })
+        .await?;
+
+        if options.color_file && pattern.threads().is_empty() {
+            for (color_path, format) in color_file_candidates(&path) {
+                if !tokio::fs::metadata(&color_path)
+                    .await
+                    .is_ok_and(|metadata| metadata.is_file())
+                {
+                    continue;
+                }
+                let data = tokio::fs::read(&color_path).await?;
+                let colors =
+                    blocking(move || format.read(&mut Cursor::new(data), &ReadOptions::default()))
+                        .await?;
+                for thread in colors.threads() {
+                    pattern.add_thread(thread.clone());
+                }
+                break;
+            }
+        }
+        Ok(pattern)
+    }
+
+    /// Write the pattern to a file without blocking the runtime
+    ///
+    /// The format is chosen from the extension as in [`write`](Self::write).
+    /// The pattern is encoded in memory, so nothing is written when encoding
+    /// fails.
+    pub async fn write_async<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let registry = FormatRegistry::new();
+        let name = registry
+            .get_format_from_path(path)
+            .filter(|info| info.can_write)
+            .map(|info| info.name)
+            .ok_or_else(|| {
+                Error::UnsupportedFormat(format!(
+                    "No writer for the extension of {}",
+                    path.display()
+                ))
+            })?;
+
+        let pattern = self.clone();
+        let data = blocking(move || {
+            let registry = FormatRegistry::new();
+            registry.check_limits(&pattern, name)?;
+            let mut buffer = Cursor::new(Vec::new());
+            registry.write_pattern(&pattern, &mut buffer, name)?;
+            Ok(buffer.into_inner())
+        })
+        .await?;
+        tokio::fs::write(path, data).await?;
+        Ok(())
+    }
+
+    /// Write the pattern to a file with writer options without blocking the runtime
+    ///
+    /// See [`write_with_options`](Self::write_with_options); the color file
+    /// is written as well when the options ask for one.
+    pub async fn write_with_options_async<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        let path = path.as_ref();
+        let format = Format::from_path(path)
+            .filter(|format| format.can_write())
+            .ok_or_else(|| {
+                Error::UnsupportedFormat(format!(
+                    "No writer for the extension of {}",
+                    path.display()
+                ))
+            })?;
+        let color_file = match options.color_file {
+            Some(color_format) => Some((color_file_path(path, color_format)?, color_format)),
+            None => None,
+        };
+
+        let pattern = self.clone();
+        let write_options = options.clone();
+        let color_format = color_file.as_ref().map(|(_, format)| *format);
+        let (design, colors) = blocking(move || {
+            FormatRegistry::new().check_limits(&pattern, format.name())?;
+            let mut design = Cursor::new(Vec::new());
+            format.write(&pattern, &mut design, &write_options)?;
+            let colors = color_format
+                .map(|color_format| {
+                    let mut colors = Cursor::new(Vec::new());
+                    color_format.write(&pattern, &mut colors, &WriteOptions::default())?;
+                    Ok::<_, Error>(colors.into_inner())
+                })
+                .transpose()?;
+            Ok((design.into_inner(), colors))
+        })
+        .await?;
+
+        tokio::fs::write(path, design).await?;
+        if let (Some((color_path, _)), Some(colors)) = (color_file, colors) {
+            tokio::fs::write(color_path, colors).await?;
+        }
+        Ok(())
+    }
+}
+
+impl BatchConverterExecutor {
+    /// Convert all input files without blocking the runtime
+    ///
+    /// Runs [`convert_all`](Self::convert_all) on the blocking thread pool;
+    /// progress callbacks and cancellation tokens work as there.
+    pub async fn convert_all_async(self) -> Result<ConversionResults> {
+        blocking(move || self.convert_all()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use crate::utils::batch::BatchConverter;
+
+    fn run<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_async_read_write() {
+        let dir = std::env::temp_dir().join(format!("butabuti_async_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xCC2233));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 0.0);
+        pattern.stitch_abs(50.0, 50.0);
+        pattern.end();
+
+        run(async {
+            let design = dir.join("rose.dst");
+            let options = WriteOptions::new()
+                .dst_extended_header(false)
+                .color_file(Some(Format::COL));
+            pattern
+                .write_with_options_async(&design, &options)
+                .await
+                .unwrap();
+            assert!(dir.join("rose.col").is_file());
+
+            // The sibling color file gives the DST its thread
+            let read = EmbPattern::read_async(&design).await.unwrap();
+            assert_eq!(read.count_stitches(), 3);
+            assert_eq!(read.threads()[0].color, 0xCC2233);
+            assert_eq!(
+                EmbPattern::read(&design).unwrap().stitches(),
+                read.stitches()
+            );
+
+            pattern.write_async(dir.join("rose.pes")).await.unwrap();
+            let results = BatchConverter::new()
+                .input_files(&[dir.join("rose.pes")])
+                .output_dir(dir.join("out"))
+                .target_format(Format::JEF)
+                .build()
+                .convert_all_async()
+                .await
+                .unwrap();
+            assert_eq!(results.success_count(), 1);
+
+            assert!(pattern.write_async(dir.join("rose.unknown")).await.is_err());
+            assert!(EmbPattern::read_async(dir.join("missing.dst"))
+                .await
+                .is_err());
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+//! Sibling color files
+//!
+//! DST, EXP and other machine formats store no thread colors, so digitizers
+//! ship a color sequence file next to the design: `rose.dst` with `rose.col`,
+//! `rose.inf` or `rose.edr`. [`load_color_file`] gives a design read without
+//! threads the threads of such a sibling, and [`write_color_file`] writes one
+//! next to a converted design.
+//!
+//! Path-based reads ([`EmbPattern::read`], batch conversion) load siblings
+//! unless [`ReadOptions::color_file`] is off; path-based writes
+//! ([`EmbPattern::write_with_options`], batch conversion) write one when
+//! [`WriteOptions::color_file`] names a color file format.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use butabuti::prelude::*;
+//! use butabuti::formats::io::options::WriteOptions;
+//!
+//! // Picks up rose.col, rose.inf or rose.edr for the thread colors
+//! let pattern = EmbPattern::read("rose.dst")?;
+//!
+//! // Writes tulip.dst and tulip.inf
+//! let options = WriteOptions::new().color_file(Some(Format::INF));
+//! pattern.write_with_options("tulip.dst", &options)?;
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+//!
+//! [`ReadOptions::color_file`]: crate::formats::io::options::ReadOptions::color_file
+//! [`WriteOptions::color_file`]: crate::formats::io::options::WriteOptions::color_file
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, WriteOptions};
+use crate::formats::io::traits::{PatternReader, PatternWriter};
+use crate::utils::error::{Error, Result};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Color file formats, in the order siblings are looked for
+pub const COLOR_FILE_FORMATS: [Format; 3] = [Format::COL, Format::INF, Format::EDR];
+
+/// Find the color file next to a design, with the same name
+///
+/// Both lowercase and uppercase extensions are tried, as files from older
+/// machines are often named `ROSE.DST` and `ROSE.COL`.
+pub fn find_color_file<P: AsRef<Path>>(design: P) -> Option<(PathBuf, Format)> {
+    color_file_candidates(design.as_ref()).find(|(path, _)| path.is_file())
+}
+
+/// Paths a sibling color file may have, in the order they are looked for
+pub(crate) fn color_file_candidates(design: &Path) -> impl Iterator<Item = (PathBuf, Format)> + '_ {
+    COLOR_FILE_FORMATS.iter().flat_map(move |&format| {
+        let extension = format.extensions()[0];
+        [extension.to_string(), extension.to_uppercase()]
+            .into_iter()
+            .map(move |extension| (design.with_extension(extension), format))
+            .filter(move |(path, _)| path != design)
+    })
+}
+
+/// Give a pattern without threads the threads of its sibling color file
+///
+/// Returns the color file used, or `None` when the pattern already has
+/// threads or no sibling exists.
+pub fn load_color_file<P: AsRef<Path>>(
+    pattern: &mut EmbPattern,
+    design: P,
+) -> Result<Option<PathBuf>> {
+    if !pattern.threads().is_empty() {
+        return Ok(None);
+    }
+    let Some((path, format)) = find_color_file(design) else {
+        return Ok(None);
+    };
+    let mut file = BufReader::new(File::open(&path)?);
+    let colors = format.read(&mut file, &ReadOptions::default())?;
+    for thread in colors.threads() {
+        pattern.add_thread(thread.clone());
+    }
+    Ok(Some(path))
+}
+
+/// Write the pattern's threads to a color file next to a design
+///
+/// The color file gets the design's name with the extension of `format`,
+/// uppercase when the design's is, and `format` must be one of
+/// [`COLOR_FILE_FORMATS`]. Returns its path.
+pub fn write_color_file<P: AsRef<Path>>(
+    pattern: &EmbPattern,
+    design: P,
+    format: Format,
+) -> Result<PathBuf> {
+    let path = color_file_path(design.as_ref(), format)?;
+    let mut file = BufWriter::new(File::create(&path)?);
+    format.write(pattern, &mut file, &WriteOptions::default())?;
+    file.flush()?;
+    Ok(path)
+}
+
+/// Path of the `format` color file next to a design
+pub(crate) fn color_file_path(design: &Path, format: Format) -> Result<PathBuf> {
+    if !COLOR_FILE_FORMATS.contains(&format) {
+        return Err(Error::UnsupportedFormat(format!(
+            "{} is not a color file format",
+            format
+        )));
+    }
+    // Match the case of the design's extension, like ROSE.DST and ROSE.COL
+    let uppercase = design
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.chars().all(|c| !c.is_ascii_lowercase()));
+    let extension = format.extensions()[0];
+    Ok(if uppercase {
+        design.with_extension(extension.to_uppercase())
+    } else {
+        design.with_extension(extension)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use std::fs;
+
+    #[test]
+    fn test_color_file_pairing() {
+        let dir = std::env::temp_dir().join(format!("butabuti_color_file_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xCC2233));
+        pattern.add_thread(EmbThread::new(0x22AA44));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 0.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(50.0, 50.0);
+        pattern.end();
+
+        let design = dir.join("ROSE.DST");
+        let options = WriteOptions::new()
+            .dst_extended_header(false)
+            .color_file(Some(Format::INF));
+        pattern.write_with_options(&design, &options).unwrap();
+        assert!(dir.join("ROSE.INF").is_file());
+
+        let read = EmbPattern::read(&design).unwrap();
+        let colors: Vec<u32> = read.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0xCC2233, 0x22AA44]);
+
+        let read =
+            EmbPattern::read_with_options(&design, &ReadOptions::new().color_file(false)).unwrap();
+        assert!(read.threads().is_empty());
+
+        // Threads of the design itself win
+        let mut other = EmbPattern::new();
+        other.add_thread(EmbThread::new(0x000000));
+        assert_eq!(load_color_file(&mut other, &design).unwrap(), None);
+        assert_eq!(other.threads().len(), 1);
+
+        assert!(write_color_file(&pattern, &design, Format::DST).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
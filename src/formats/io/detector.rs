@@ -4,47 +4,31 @@
 //! and extension-based fallback detection.
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::io::options::ReadOptions;
+use crate::formats::io::traits::PatternReader;
 use crate::utils::error::{Error, Result};
 use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
 
-/// Supported embroidery file formats with detection capabilities
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Format {
-    /// Tajima DST (no magic bytes, 512-byte header)
-    DST,
-    /// Brother PES (#PES or #PEC prefix)
-    PES,
-    /// Pfaff VP3 (%vsm% signature)
-    VP3,
-    /// Janome JEF (0x74 at offset 0)
-    JEF,
-    /// Melco EXP
-    EXP,
-    /// Brother PEC (#PEC prefix)
-    PEC,
-    /// Singer XXX
-    XXX,
-    /// Barudan U01
-    U01,
-    /// Tajima TBF
-    TBF,
-    /// Thread color list (COL)
-    COL,
-    /// Embird color (EDR)
-    EDR,
-    /// Thread information (INF)
-    INF,
-    /// JSON embroidery data
-    JSON,
-    /// CSV embroidery data
-    CSV,
-    /// G-code embroidery data
-    GCODE,
-    /// Husqvarna Viking HUS
-    HUS,
-    /// Unknown/unsupported format
-    Unknown,
+pub use crate::formats::format::Format;
+
+/// Detect a stream's format from its magic bytes
+///
+/// Shorthand for [`FormatDetector::detect_from_content`]; the reader position is
+/// restored afterwards. Returns [`Format::Unknown`] when no signature matches.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::io::detector::{detect_format, Format};
+/// use std::io::Cursor;
+///
+/// let format = detect_format(&mut Cursor::new(b"#PEC0001\x00\x00\x00\x00"))?;
+/// assert_eq!(format, Format::PEC);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn detect_format<R: Read + Seek>(reader: &mut R) -> Result<Format> {
+    FormatDetector::detect_from_content(reader)
 }
 
 /// Format detector for automatic format recognition
@@ -58,8 +42,10 @@ impl FormatDetector {
     ///
     /// - **PES/PEC**: Starts with "#PES" or "#PEC"
     /// - **VP3**: Starts with "%vsm%"
+    /// - **EMB**: Starts with the OLE compound document signature
     /// - **JEF**: First byte is 0x74
     /// - **JSON**: Starts with '{'
+    /// - **PLT**: Starts with the HPGL "IN;" initialize instruction
     /// - **CSV**: Contains commas in first line
     ///
     /// # Example
@@ -111,6 +97,30 @@ impl FormatDetector {
             }
         }
 
+        // BUTA: native archive
+        if buffer[..4] == crate::formats::io::writers::buta::BUTA_SIGNATURE {
+            return Ok(Format::BUTA);
+        }
+
+        // HUS and VIP: little-endian magic numbers
+        if buffer[..4] == crate::formats::io::writers::hus::HUS_MAGIC.to_le_bytes()
+            || buffer[..4] == crate::formats::io::readers::hus::VIP_MAGIC.to_le_bytes()
+        {
+            return Ok(Format::HUS);
+        }
+
+        // THR: "thr" signature followed by the version byte
+        if u32::from_le_bytes([buffer[0], buffer[1], buffer[2], 0])
+            == crate::formats::io::readers::thr::THR_SIGNATURE
+        {
+            return Ok(Format::THR);
+        }
+
+        // EMB: OLE compound document
+        if bytes_read >= 8 && buffer[..8] == crate::formats::io::readers::emb::CFB_SIGNATURE {
+            return Ok(Format::EMB);
+        }
+
         // JEF: First byte 0x74 + additional validation to reduce false positives
         // JEF files have a specific structure with stitch count at offset 0x74
         if buffer[0] == 0x74 && bytes_read >= 128 {
@@ -129,6 +139,13 @@ impl FormatDetector {
             }
         }
 
+        // PLT: HPGL initialize instruction (before CSV, coordinates use commas)
+        if let Some(start) = content_start {
+            if buffer[start..bytes_read].starts_with(b"IN;") {
+                return Ok(Format::PLT);
+            }
+        }
+
         // CSV: Check for commas in first line
         if bytes_read >= 20 {
             let first_line = buffer[..bytes_read.min(100)]
@@ -181,25 +198,7 @@ impl FormatDetector {
             .and_then(|e| e.to_str())
             .ok_or_else(|| Error::Parse("File has no extension".to_string()))?;
 
-        let format = match extension.to_lowercase().as_str() {
-            "dst" => Format::DST,
-            "pes" => Format::PES,
-            "pec" => Format::PEC,
-            "vp3" => Format::VP3,
-            "jef" => Format::JEF,
-            "exp" => Format::EXP,
-            "xxx" => Format::XXX,
-            "u01" => Format::U01,
-            "tbf" => Format::TBF,
-            "col" => Format::COL,
-            "edr" => Format::EDR,
-            "inf" => Format::INF,
-            "json" => Format::JSON,
-            "csv" => Format::CSV,
-            "gcode" | "nc" => Format::GCODE,
-            "hus" | "vip" => Format::HUS,
-            _ => Format::Unknown,
-        };
+        let format = Format::from_extension(extension).unwrap_or(Format::Unknown);
 
         Ok(format)
     }
@@ -246,61 +245,10 @@ impl FormatDetector {
         }
 
         // Read pattern using detected format
-        Self::read_with_format(reader, pattern, format)?;
+        *pattern = format.read(reader, &ReadOptions::default())?;
 
         Ok(format)
     }
-
-    /// Read pattern using a specific format
-    ///
-    /// Internal helper to read a pattern once format is known.
-    fn read_with_format<R: Read + Seek>(
-        reader: &mut R,
-        pattern: &mut EmbPattern,
-        format: Format,
-    ) -> Result<()> {
-        match format {
-            // Formats with old API that return Result<EmbPattern>
-            Format::DST => {
-                *pattern = crate::formats::io::readers::dst::read(reader, None)?;
-                Ok(())
-            }
-            Format::EXP => {
-                *pattern = crate::formats::io::readers::exp::read(reader)?;
-                Ok(())
-            }
-            Format::JSON => {
-                *pattern = crate::formats::io::readers::json::read(reader)?;
-                Ok(())
-            }
-            Format::JEF => {
-                *pattern = crate::formats::io::readers::jef::read(reader, None)?;
-                Ok(())
-            }
-            Format::PEC => {
-                *pattern = crate::formats::io::readers::pec::read(reader)?;
-                Ok(())
-            }
-            // Formats with new API that mutate pattern and return Result<()>
-            Format::PES => crate::formats::io::readers::pes::read(reader, pattern),
-            Format::VP3 => crate::formats::io::readers::vp3::read(reader, pattern),
-            Format::XXX => crate::formats::io::readers::xxx::read(reader, pattern),
-            Format::U01 => crate::formats::io::readers::u01::read(reader, pattern),
-            Format::TBF => crate::formats::io::readers::tbf::read(reader, pattern),
-            Format::COL => crate::formats::io::readers::col::read(reader, pattern),
-            Format::EDR => crate::formats::io::readers::edr::read(reader, pattern),
-            Format::INF => crate::formats::io::readers::inf::read(reader, pattern),
-            Format::CSV => crate::formats::io::readers::csv::read(reader, pattern),
-            Format::GCODE => crate::formats::io::readers::gcode::read(reader, pattern),
-            // HUS not yet supported (reader not exported)
-            Format::HUS => Err(Error::UnsupportedFormat(
-                "HUS format reader not yet available".to_string(),
-            )),
-            Format::Unknown => Err(Error::UnsupportedFormat(
-                "Unknown format cannot be read".to_string(),
-            )),
-        }
-    }
 }
 
 #[cfg(test)]
@@ -334,6 +282,24 @@ mod tests {
         assert_eq!(format, Format::VP3);
     }
 
+    #[test]
+    fn test_detect_emb_from_content() {
+        let mut data = vec![0u8; 512];
+        data[..8].copy_from_slice(&crate::formats::io::readers::emb::CFB_SIGNATURE);
+        let mut reader = Cursor::new(data);
+        let format = FormatDetector::detect_from_content(&mut reader).unwrap();
+        assert_eq!(format, Format::EMB);
+        assert!(format.has_signature());
+    }
+
+    #[test]
+    fn test_detect_plt_from_content() {
+        let data = b"IN;SP1;PU0,0;PD40,40;PD80,0;";
+        let mut reader = Cursor::new(data);
+        let format = FormatDetector::detect_from_content(&mut reader).unwrap();
+        assert_eq!(format, Format::PLT);
+    }
+
     #[test]
     fn test_detect_jef_from_content() {
         let data = [0x74u8; 512];
@@ -21,13 +21,11 @@ macro_rules! read_magic {
             .read_exact(&mut actual)
             .map_err(|e| $crate::utils::error::Error::Io(e))?;
         if actual != expected {
-            return Err($crate::utils::error::Error::Parse(format!(
-                "Magic bytes mismatch at {}:{}. Expected {:?}, got {:?}",
-                file!(),
-                line!(),
-                expected,
-                actual
-            )));
+            return Err($crate::utils::error::Error::unexpected_value(
+                format!("magic bytes at {}:{}", file!(), line!()),
+                format!("{:?}", expected),
+                format!("{:?}", actual),
+            ));
         }
         actual
     }};
@@ -48,8 +46,8 @@ macro_rules! read_bytes {
         let mut buffer = vec![0u8; $count];
         $reader.read_exact(&mut buffer).map_err(|e| {
             if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                $crate::utils::error::Error::Parse(format!(
-                    "Unexpected EOF reading {} bytes at {}:{}",
+                $crate::utils::error::Error::truncated(format!(
+                    "{} bytes at {}:{}",
                     $count,
                     file!(),
                     line!()
@@ -64,10 +62,10 @@ macro_rules! read_bytes {
         let mut buffer = vec![0u8; $count];
         $reader.read_exact(&mut buffer).map_err(|e| {
             if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                $crate::utils::error::Error::Parse(format!(
-                    "Unexpected EOF while {}: {} bytes at {}:{}",
-                    $context,
+                $crate::utils::error::Error::truncated(format!(
+                    "{} bytes while {} at {}:{}",
                     $count,
+                    $context,
                     file!(),
                     line!()
                 ))
@@ -96,8 +94,8 @@ macro_rules! read_with_context {
     ($operation:expr, $context:expr) => {{
         $operation.map_err(|e| {
             if e.kind() == std::io::ErrorKind::UnexpectedEof {
-                $crate::utils::error::Error::Parse(format!(
-                    "Unexpected EOF while {} at {}:{}",
+                $crate::utils::error::Error::truncated(format!(
+                    "while {} at {}:{}",
                     $context,
                     file!(),
                     line!()
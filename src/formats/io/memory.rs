@@ -0,0 +1,135 @@
+//! In-memory reading and writing
+//!
+//! Reads and writes patterns from byte buffers instead of files, for
+//! environments without a filesystem such as `wasm32-unknown-unknown`. Formats
+//! are named as in the [`FormatRegistry`], case-insensitively, and formats
+//! registered as plugins are included.
+//!
+//! With the `mmap` feature, `read_path_mmap` reads a file through a memory
+//! map, so large files (PES files with big embedded images) are parsed in
+//! place rather than copied through a read buffer.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::formats::io::{read_from_bytes, write_to_bytes};
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 50.0);
+//! pattern.end();
+//!
+//! let bytes = write_to_bytes(&pattern, "dst")?;
+//! let read = read_from_bytes(&bytes, "DST")?;
+//! assert_eq!(read.count_stitches(), pattern.count_stitches());
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::registry::FormatRegistry;
+use crate::utils::error::Result;
+use std::io::Cursor;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+/// Read a pattern of the given format from a byte buffer
+pub fn read_from_bytes(data: &[u8], format: &str) -> Result<EmbPattern> {
+    FormatRegistry::new().read_pattern(&mut Cursor::new(data), format)
+}
+
+/// Read a pattern from a memory-mapped file
+///
+/// The format is chosen like [`EmbPattern::read`]: by extension, with the
+/// magic bytes taking over for missing or mismatched extensions.
+///
+/// The file must not be truncated or rewritten while it is read; on most
+/// platforms that crashes the process. Use [`EmbPattern::read`] for files
+/// that may change underneath.
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::formats::io::read_path_mmap;
+///
+/// let pattern = read_path_mmap("large.pes")?;
+/// println!("{} stitches", pattern.count_stitches());
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+#[cfg(feature = "mmap")]
+pub fn read_path_mmap<P: AsRef<Path>>(path: P) -> Result<EmbPattern> {
+    let path = path.as_ref();
+    let map = map_file(path)?;
+    FormatRegistry::new()
+        .read_pattern_detected(&mut Cursor::new(&map[..]), Some(path))
+        .map(|(pattern, _)| pattern)
+}
+
+/// Map a file read-only
+#[cfg(feature = "mmap")]
+fn map_file(path: &Path) -> Result<memmap2::Mmap> {
+    let file = std::fs::File::open(path)?;
+    // SAFETY: the map is only read through shared slices. Its contents change
+    // if another process modifies the file, which callers are warned against.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    Ok(map)
+}
+
+/// Write a pattern in the given format to a byte buffer
+///
+/// Patterns exceeding the format's writer limits are rejected.
+pub fn write_to_bytes(pattern: &EmbPattern, format: &str) -> Result<Vec<u8>> {
+    let mut cursor = Cursor::new(Vec::new());
+    FormatRegistry::new().write_pattern(pattern, &mut cursor, format)?;
+    Ok(cursor.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+
+    #[test]
+    fn test_round_trip_through_bytes() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(120.0, 40.0);
+        pattern.stitch_abs(60.0, 90.0);
+        pattern.end();
+
+        for format in ["dst", "pes", "jef", "exp", "json"] {
+            let bytes = write_to_bytes(&pattern, format).unwrap();
+            assert!(!bytes.is_empty(), "{}", format);
+            let read = read_from_bytes(&bytes, format).unwrap();
+            assert_eq!(read.count_stitches(), 3, "{}", format);
+        }
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_read_path_mmap() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(120.0, 40.0);
+        pattern.end();
+
+        let dir = std::env::temp_dir().join(format!("butabuti_mmap_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Extension says DST, content is PES: detected by magic bytes
+        let path = dir.join("design.dst");
+        std::fs::write(&path, write_to_bytes(&pattern, "pes").unwrap()).unwrap();
+
+        let read = read_path_mmap(&path).unwrap();
+        assert_eq!(read.count_stitches(), 2);
+        assert!(read_path_mmap(dir.join("missing.pes")).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        let pattern = EmbPattern::new();
+        assert!(write_to_bytes(&pattern, "nope").is_err());
+        assert!(read_from_bytes(&[0u8; 16], "nope").is_err());
+    }
+}
@@ -9,14 +9,54 @@
 #[macro_use]
 pub mod macros;
 
+/// Async reading, writing and conversion
+#[cfg(feature = "tokio")]
+pub mod async_io;
+
+/// Sibling color files
+#[cfg(feature = "fs")]
+pub mod color_file;
+
 /// Format detection and auto-loading
 pub mod detector;
 
+/// In-memory reading and writing
+pub mod memory;
+
+/// Reader and writer options, and warnings
+pub mod options;
+
+/// Header-only design scans
+pub mod probe;
+
 /// Format readers
 pub mod readers;
 
+/// Reports of lossy writes
+pub mod report;
+
+/// Streaming stitch readers
+pub mod stream;
+
+/// Unified reader and writer traits
+pub mod traits;
+
 /// Common I/O utilities
 pub mod utils;
 
+/// Round-trip fidelity checks
+pub mod verify;
+
 /// Format writers
 pub mod writers;
+
+/// ZIP archives of designs
+pub mod zip;
+
+pub use memory::{read_from_bytes, write_to_bytes};
+#[cfg(feature = "fs")]
+pub use probe::probe;
+pub use probe::DesignInfo;
+
+#[cfg(feature = "mmap")]
+pub use memory::read_path_mmap;
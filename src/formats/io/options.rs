@@ -0,0 +1,497 @@
+//! Reader and writer options, and warning collection
+//!
+//! Provides `ReadOptions` for controlling how readers handle damaged or out-of-spec
+//! data, `ReadWarning` for reporting the adjustments a reader made while
+//! recovering from it, and `WriteOptions` for the choices writers leave open.
+
+use crate::formats::format::Format;
+use crate::formats::io::writers::csv::CsvVersion;
+use crate::formats::io::writers::dst::DstHeaderFields;
+use crate::formats::io::writers::gcode::GcodeProfile;
+use crate::formats::io::writers::jef::{JefHoop, JefWriteOptions};
+use crate::formats::io::writers::pes::PesVersion;
+use crate::utils::error::{Error, Result};
+use crate::utils::string::TextEncoding;
+use std::fmt;
+
+/// How readers handle color indices that fall outside of the color table
+///
+/// Corrupt files sometimes reference, e.g., color 200 from a 64-entry palette.
+/// The default wraps the index, which is how these readers have always
+/// resolved it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteIndexPolicy {
+    /// Clamp the index to the last valid palette entry
+    Clamp,
+    /// Wrap the index around the palette length (index % len, default)
+    #[default]
+    Wrap,
+    /// Fail the read with a parse error
+    Reject,
+}
+
+/// Decimal separator convention for numeric fields in text formats
+///
+/// Spreadsheet exports from European locales typically write `1,25` instead of
+/// `1.25` and separate CSV fields with `;` or tabs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberLocale {
+    /// `.` is the decimal separator (default)
+    #[default]
+    Dot,
+    /// `,` is the decimal separator and `.` is a grouping separator
+    Comma,
+    /// Detect per value: the last `.` or `,` is taken as the decimal separator
+    Auto,
+}
+
+impl NumberLocale {
+    /// Parse a floating point value according to this locale
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::formats::io::options::NumberLocale;
+    ///
+    /// assert_eq!(NumberLocale::Comma.parse_f64("1,25"), Some(1.25));
+    /// assert_eq!(NumberLocale::Auto.parse_f64("1.234,5"), Some(1234.5));
+    /// assert_eq!(NumberLocale::Dot.parse_f64("1,25"), None);
+    /// ```
+    pub fn parse_f64(self, value: &str) -> Option<f64> {
+        let value = value.trim();
+        let comma_decimal = || value.replace('.', "").replace(',', ".").parse().ok();
+
+        match self {
+            NumberLocale::Dot => value.parse().ok(),
+            NumberLocale::Comma => comma_decimal(),
+            NumberLocale::Auto => match (value.rfind(','), value.rfind('.')) {
+                (Some(comma), Some(dot)) if comma > dot => comma_decimal(),
+                (Some(_), Some(_)) => value.replace(',', "").parse().ok(),
+                (Some(_), None) => value.replace(',', ".").parse().ok(),
+                _ => value.parse().ok(),
+            },
+        }
+    }
+
+    /// Whether `ch` may appear inside a numeric value in this locale
+    pub fn is_numeric_char(self, ch: char) -> bool {
+        ch.is_ascii_digit()
+            || ch == '.'
+            || ch == '-'
+            || ch == '+'
+            || (ch == ',' && self != NumberLocale::Dot)
+    }
+}
+
+/// A non-fatal problem encountered while reading a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadWarning {
+    /// Byte offset in the source file, if known
+    pub offset: Option<u64>,
+    /// Human-readable description of the problem and how it was handled
+    pub message: String,
+}
+
+impl ReadWarning {
+    /// Create a warning without offset information
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            offset: None,
+            message: message.into(),
+        }
+    }
+
+    /// Create a warning at a specific byte offset
+    pub fn at(offset: u64, message: impl Into<String>) -> Self {
+        Self {
+            offset: Some(offset),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ReadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "offset 0x{:X}: {}", offset, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Options controlling reader behavior
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::io::options::{PaletteIndexPolicy, ReadOptions};
+///
+/// let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Clamp);
+/// assert_eq!(options.palette_index_policy, PaletteIndexPolicy::Clamp);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// Handling of out-of-range color table indices
+    pub palette_index_policy: PaletteIndexPolicy,
+    /// Decimal separator convention for text formats (CSV, G-code)
+    pub number_locale: NumberLocale,
+    /// Encoding of metadata and thread strings in binary formats (PES, PEC)
+    pub text_encoding: TextEncoding,
+    /// Skip damaged records and keep what can be read instead of failing
+    /// (DST, PES, PEC)
+    ///
+    /// Each recovery is reported as a [`ReadWarning`] with the offset of the
+    /// damage.
+    pub lenient: bool,
+    /// Give designs read from a path without thread colors the threads of a
+    /// sibling `.col`, `.inf` or `.edr` file (default: true)
+    ///
+    /// See [`color_file`](crate::formats::io::color_file).
+    pub color_file: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            palette_index_policy: PaletteIndexPolicy::default(),
+            number_locale: NumberLocale::default(),
+            text_encoding: TextEncoding::default(),
+            lenient: false,
+            color_file: true,
+        }
+    }
+}
+
+impl ReadOptions {
+    /// Create default read options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the policy for out-of-range color indices
+    pub fn palette_index_policy(mut self, policy: PaletteIndexPolicy) -> Self {
+        self.palette_index_policy = policy;
+        self
+    }
+
+    /// Set the decimal separator convention for text formats
+    pub fn number_locale(mut self, locale: NumberLocale) -> Self {
+        self.number_locale = locale;
+        self
+    }
+
+    /// Set the encoding used to decode metadata strings
+    pub fn text_encoding(mut self, encoding: TextEncoding) -> Self {
+        self.text_encoding = encoding;
+        self
+    }
+
+    /// Recover partial patterns from damaged files
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::formats::io::options::ReadOptions;
+    /// use butabuti::formats::io::traits::PatternReader;
+    /// use butabuti::formats::Format;
+    /// use std::io::Cursor;
+    ///
+    /// // DST header with an unreadable label, one stitch and no end record
+    /// let mut data = vec![0xFFu8; 512];
+    /// data.extend_from_slice(&[0x01, 0x00, 0x03]);
+    ///
+    /// let options = ReadOptions::new().lenient(true);
+    /// let (pattern, warnings) = Format::DST.read_with_warnings(&mut Cursor::new(data), &options)?;
+    /// assert_eq!(pattern.count_stitches(), 1);
+    /// assert_eq!(warnings.len(), 2);
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Set whether sibling color files are loaded by path-based reads
+    pub fn color_file(mut self, color_file: bool) -> Self {
+        self.color_file = color_file;
+        self
+    }
+
+    /// Resolve a color index against a palette of `palette_len` entries
+    ///
+    /// In-range indices are returned unchanged. Out-of-range indices are adjusted
+    /// according to the palette index policy and a warning is recorded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::formats::io::options::ReadOptions;
+    ///
+    /// let options = ReadOptions::new();
+    /// let mut warnings = Vec::new();
+    /// assert_eq!(options.resolve_palette_index(200, 10, &mut warnings)?, 0);
+    /// assert_eq!(warnings.len(), 1);
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    pub fn resolve_palette_index(
+        &self,
+        index: usize,
+        palette_len: usize,
+        warnings: &mut Vec<ReadWarning>,
+    ) -> Result<usize> {
+        if palette_len == 0 {
+            return Err(Error::Parse(format!(
+                "Color index {} references an empty color table",
+                index
+            )));
+        }
+
+        if index < palette_len {
+            return Ok(index);
+        }
+
+        let resolved = match self.palette_index_policy {
+            PaletteIndexPolicy::Clamp => palette_len - 1,
+            PaletteIndexPolicy::Wrap => index % palette_len,
+            PaletteIndexPolicy::Reject => {
+                return Err(Error::Parse(format!(
+                    "Color index {} out of range for {}-entry color table",
+                    index, palette_len
+                )));
+            }
+        };
+
+        warnings.push(ReadWarning::new(format!(
+            "Color index {} out of range for {}-entry color table, using {}",
+            index, palette_len, resolved
+        )));
+
+        Ok(resolved)
+    }
+}
+
+/// Options controlling writer behavior
+///
+/// Each field applies to the formats named in its description and is ignored
+/// by the others. The defaults are what
+/// [`FormatRegistry::write_pattern`](crate::formats::registry::FormatRegistry::write_pattern)
+/// writes.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::io::options::WriteOptions;
+/// use butabuti::formats::io::writers::pes::PesVersion;
+///
+/// let options = WriteOptions::new()
+///     .pes_version(PesVersion::V6)
+///     .dst_extended_header(false);
+/// assert_eq!(options.pes_version, PesVersion::V6);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteOptions {
+    /// DST: write the extended header fields (AU, CP, LI, TC)
+    pub dst_extended_header: bool,
+    /// DST: which extended header fields to write
+    pub dst_header_fields: DstHeaderFields,
+    /// DST: jump records written for each trim
+    pub dst_trim_jumps: usize,
+    /// JEF: hoop, trims and date
+    pub jef: JefWriteOptions,
+    /// PES: file version
+    pub pes_version: PesVersion,
+    /// PES: write only the header and PEC section, without the design objects
+    pub pes_truncated: bool,
+    /// PES: encoding of header and thread strings (`Auto` writes UTF-8)
+    pub text_encoding: TextEncoding,
+    /// CSV: column layout
+    pub csv_version: CsvVersion,
+    /// G-code: machine dialect
+    pub gcode_profile: GcodeProfile,
+    /// Run machine formats through their format's encoder before writing
+    ///
+    /// See [`EncoderBuilder::for_format`](crate::core::encoder::EncoderBuilder::for_format).
+    pub encode: bool,
+    /// Color file (COL, INF or EDR) written next to designs written to a path
+    ///
+    /// See [`color_file`](crate::formats::io::color_file).
+    pub color_file: Option<Format>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            dst_extended_header: true,
+            dst_header_fields: DstHeaderFields::ALL,
+            dst_trim_jumps: 512,
+            jef: JefWriteOptions::default(),
+            pes_version: PesVersion::V1,
+            pes_truncated: false,
+            text_encoding: TextEncoding::Utf8,
+            csv_version: CsvVersion::Default,
+            gcode_profile: GcodeProfile::default(),
+            encode: true,
+            color_file: None,
+        }
+    }
+}
+
+impl WriteOptions {
+    /// Create default write options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether DST files get the extended header fields
+    pub fn dst_extended_header(mut self, extended: bool) -> Self {
+        self.dst_extended_header = extended;
+        self
+    }
+
+    /// Set which extended header fields DST files get
+    ///
+    /// Only applies while the extended header is enabled.
+    pub fn dst_header_fields(mut self, fields: DstHeaderFields) -> Self {
+        self.dst_header_fields = fields;
+        self
+    }
+
+    /// Set the number of DST jump records per trim
+    pub fn dst_trim_jumps(mut self, jumps: usize) -> Self {
+        self.dst_trim_jumps = jumps;
+        self
+    }
+
+    /// Set the JEF options
+    pub fn jef(mut self, jef: JefWriteOptions) -> Self {
+        self.jef = jef;
+        self
+    }
+
+    /// Set the hoop recorded in JEF headers, or `None` to select it from the design
+    pub fn jef_hoop(mut self, hoop: Option<JefHoop>) -> Self {
+        self.jef.hoop = hoop;
+        self
+    }
+
+    /// Set whether JEF trims are written, and how many records each takes
+    pub fn jef_trims(mut self, trims: bool, records: usize) -> Self {
+        self.jef = self.jef.trims(trims, records);
+        self
+    }
+
+    /// Set the date string stored in JEF headers
+    pub fn jef_date(mut self, date: impl Into<String>) -> Self {
+        self.jef = self.jef.date(date);
+        self
+    }
+
+    /// Set the PES file version
+    pub fn pes_version(mut self, version: PesVersion) -> Self {
+        self.pes_version = version;
+        self
+    }
+
+    /// Set whether PES files are written truncated
+    pub fn pes_truncated(mut self, truncated: bool) -> Self {
+        self.pes_truncated = truncated;
+        self
+    }
+
+    /// Set the encoding of PES header and thread strings
+    pub fn text_encoding(mut self, encoding: TextEncoding) -> Self {
+        self.text_encoding = encoding;
+        self
+    }
+
+    /// Set the CSV column layout
+    pub fn csv_version(mut self, version: CsvVersion) -> Self {
+        self.csv_version = version;
+        self
+    }
+
+    /// Set the G-code machine profile
+    pub fn gcode_profile(mut self, profile: GcodeProfile) -> Self {
+        self.gcode_profile = profile;
+        self
+    }
+
+    /// Set whether patterns are encoded for the target format before writing
+    pub fn encode(mut self, encode: bool) -> Self {
+        self.encode = encode;
+        self
+    }
+
+    /// Set the color file written next to designs written to a path
+    pub fn color_file(mut self, format: Option<Format>) -> Self {
+        self.color_file = format;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_in_range() {
+        let mut warnings = Vec::new();
+        let options = ReadOptions::new();
+        assert_eq!(
+            options.resolve_palette_index(5, 10, &mut warnings).unwrap(),
+            5
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_policies() {
+        let mut warnings = Vec::new();
+
+        let clamp = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Clamp);
+        assert_eq!(
+            clamp.resolve_palette_index(200, 64, &mut warnings).unwrap(),
+            63
+        );
+
+        let wrap = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Wrap);
+        assert_eq!(
+            wrap.resolve_palette_index(200, 64, &mut warnings).unwrap(),
+            8
+        );
+
+        let reject = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Reject);
+        assert!(reject
+            .resolve_palette_index(200, 64, &mut warnings)
+            .is_err());
+
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].message.contains("200"));
+    }
+
+    #[test]
+    fn test_resolve_empty_palette() {
+        let mut warnings = Vec::new();
+        assert!(ReadOptions::new()
+            .resolve_palette_index(0, 0, &mut warnings)
+            .is_err());
+    }
+
+    #[test]
+    fn test_number_locale_parse() {
+        assert_eq!(NumberLocale::Dot.parse_f64("-12.5"), Some(-12.5));
+        assert_eq!(NumberLocale::Dot.parse_f64("12,5"), None);
+        assert_eq!(NumberLocale::Comma.parse_f64("-12,5"), Some(-12.5));
+        assert_eq!(NumberLocale::Comma.parse_f64("1.000,5"), Some(1000.5));
+        assert_eq!(NumberLocale::Auto.parse_f64("12,5"), Some(12.5));
+        assert_eq!(NumberLocale::Auto.parse_f64("12.5"), Some(12.5));
+        assert_eq!(NumberLocale::Auto.parse_f64("1,000.5"), Some(1000.5));
+        assert_eq!(NumberLocale::Auto.parse_f64("abc"), None);
+    }
+
+    #[test]
+    fn test_warning_display() {
+        assert_eq!(ReadWarning::new("bad").to_string(), "bad");
+        assert_eq!(ReadWarning::at(16, "bad").to_string(), "offset 0x10: bad");
+    }
+}
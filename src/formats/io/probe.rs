@@ -0,0 +1,299 @@
+//! Fast metadata scan of design files
+//!
+//! [`probe`] reports what an index of a design library needs: stitch count,
+//! color count, extents and metadata. Formats that record these in their
+//! header (DST, PES/PEC, JEF) are probed without decoding any stitches; other
+//! formats, and headers that turn out to be unusable, are read in full.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use butabuti::formats::io::probe;
+//!
+//! let info = probe("design.dst")?;
+//! println!(
+//!     "{}: {:?} stitches, {:?} colors",
+//!     info.format,
+//!     info.stitch_count,
+//!     info.color_count
+//! );
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::options::ReadOptions;
+use crate::formats::io::readers::{dst, jef, pec, pes};
+use crate::formats::io::utils::ReadHelper;
+use crate::formats::registry::FormatRegistry;
+use crate::utils::error::{Error, Result};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Summary of a design file from [`probe`]
+///
+/// Coordinates are in 0.1 mm, like [`EmbPattern`]. Fields a header does not
+/// record are `None`; after a full read every field is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignInfo {
+    /// Format the file was read as
+    pub format: Format,
+    /// Number of stitches
+    pub stitch_count: Option<usize>,
+    /// Number of color blocks (color changes plus one)
+    pub color_count: Option<usize>,
+    /// Extents as (min_x, min_y, max_x, max_y)
+    pub bounds: Option<(f64, f64, f64, f64)>,
+    /// Width and height
+    pub size: Option<(f64, f64)>,
+    /// Metadata from the header, or from the pattern after a full read
+    pub metadata: HashMap<String, String>,
+    /// Whether only the header was read
+    pub header_only: bool,
+}
+
+impl DesignInfo {
+    /// Summarize a fully read pattern
+    fn from_pattern(format: Format, pattern: &EmbPattern) -> Self {
+        let (min_x, min_y, max_x, max_y) = pattern.bounds();
+        let color_count = if pattern.stitches().is_empty() {
+            0
+        } else {
+            pattern.count_color_changes() + 1
+        };
+        Self {
+            format,
+            stitch_count: Some(pattern.count_stitches()),
+            color_count: Some(color_count),
+            bounds: Some((min_x, min_y, max_x, max_y)),
+            size: Some((max_x - min_x, max_y - min_y)),
+            metadata: collect_metadata(pattern),
+            header_only: false,
+        }
+    }
+}
+
+/// Probe a design file, reading only its header where the format allows
+///
+/// The format is resolved like [`EmbPattern::read`]: by extension, with the
+/// magic bytes taking over for missing or mismatched extensions.
+#[cfg(feature = "fs")]
+pub fn probe<P: AsRef<Path>>(path: P) -> Result<DesignInfo> {
+    let path = path.as_ref();
+    let mut file = std::io::BufReader::new(std::fs::File::open(path)?);
+    probe_reader(&mut file, Some(path))
+}
+
+/// Probe a design stream, using `path` only to help detect the format
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::formats::io::probe::probe_reader;
+/// use butabuti::formats::io::write_to_bytes;
+/// use std::io::Cursor;
+///
+/// let mut pattern = EmbPattern::new();
+/// pattern.stitch_abs(0.0, 0.0);
+/// pattern.stitch_abs(100.0, 50.0);
+/// pattern.end();
+///
+/// let bytes = write_to_bytes(&pattern, "dst")?;
+/// let info = probe_reader(&mut Cursor::new(bytes), None)?;
+/// assert!(info.header_only);
+/// assert_eq!(info.stitch_count, Some(2));
+/// assert_eq!(info.size, Some((100.0, 50.0)));
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn probe_reader<R: Read + Seek>(reader: &mut R, path: Option<&Path>) -> Result<DesignInfo> {
+    let registry = FormatRegistry::new();
+    let start = reader.stream_position()?;
+    let (first, _) = registry.resolve_read_format(reader, path)?;
+
+    let header = match parse_format(first)? {
+        Format::DST => probe_dst(reader),
+        Format::PES | Format::PEC => probe_pes(reader),
+        Format::JEF => probe_jef(reader),
+        _ => Ok(None),
+    };
+    if let Ok(Some(info)) = header {
+        return Ok(info);
+    }
+
+    reader.seek(SeekFrom::Start(start))?;
+    let (pattern, format) = registry.read_pattern_detected(reader, path)?;
+    Ok(DesignInfo::from_pattern(parse_format(format)?, &pattern))
+}
+
+/// Built-in format for a registry name
+fn parse_format(name: &str) -> Result<Format> {
+    name.parse()
+        .map_err(|_| Error::UnsupportedFormat(format!("Cannot probe {} files", name)))
+}
+
+/// Metadata of a pattern as an owned map
+fn collect_metadata(pattern: &EmbPattern) -> HashMap<String, String> {
+    pattern
+        .metadata()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// DST records counts and extents in its text header
+///
+/// Returns `None` when the header lacks the stitch count or extents.
+fn probe_dst<R: Read>(reader: &mut R) -> Result<Option<DesignInfo>> {
+    let mut pattern = EmbPattern::new();
+    dst::read_header(reader, &mut pattern)?;
+
+    let field = |key: &str| {
+        pattern
+            .get_metadata(key)
+            .and_then(|value| value.trim().parse::<u32>().ok())
+    };
+    let (Some(stitches), Some(plus_x), Some(minus_x), Some(plus_y), Some(minus_y)) = (
+        field("ST"),
+        field("+X"),
+        field("-X"),
+        field("+Y"),
+        field("-Y"),
+    ) else {
+        return Ok(None);
+    };
+    let (plus_x, minus_x, plus_y, minus_y) =
+        (plus_x as f64, minus_x as f64, plus_y as f64, minus_y as f64);
+
+    Ok(Some(DesignInfo {
+        format: Format::DST,
+        stitch_count: Some(stitches as usize),
+        color_count: field("CO").map(|changes| changes as usize + 1),
+        bounds: Some((-minus_x, -minus_y, plus_x, plus_y)),
+        size: Some((plus_x + minus_x, plus_y + minus_y)),
+        metadata: collect_metadata(&pattern),
+        header_only: true,
+    }))
+}
+
+/// PES keeps metadata in its header and the color table and size in the PEC header
+///
+/// Neither records the stitch count or the design position.
+fn probe_pes<R: Read + Seek>(reader: &mut R) -> Result<Option<DesignInfo>> {
+    let options = ReadOptions::default();
+    let mut pattern = EmbPattern::new();
+    let mut chart = Vec::new();
+    let pec_position = pes::read_header(
+        &mut ReadHelper::new(&mut *reader),
+        &mut pattern,
+        &mut chart,
+        options.text_encoding,
+    )?;
+    let format = match pec_position {
+        Some(position) => {
+            reader.seek(SeekFrom::Start(position))?;
+            Format::PES
+        }
+        None => Format::PEC,
+    };
+    let header = pec::read_pec_header(
+        reader,
+        &mut pattern,
+        Some(&mut chart),
+        &options,
+        &mut Vec::new(),
+    )?;
+
+    Ok(Some(DesignInfo {
+        format,
+        stitch_count: None,
+        color_count: Some(header.color_count),
+        bounds: None,
+        size: Some((header.width as f64, header.height as f64)),
+        metadata: collect_metadata(&pattern),
+        header_only: true,
+    }))
+}
+
+/// JEF records the stitch record count, colors and extents from the hoop center
+fn probe_jef<R: Read>(reader: &mut R) -> Result<Option<DesignInfo>> {
+    let header = jef::read_header(&mut ReadHelper::new(reader))?;
+    let [left, top, right, bottom] = header.extents.map(f64::from);
+
+    Ok(Some(DesignInfo {
+        format: Format::JEF,
+        stitch_count: Some(header.point_count),
+        color_count: Some(header.color_count),
+        bounds: Some((-left, -top, right, bottom)),
+        size: Some((left + right, top + bottom)),
+        metadata: HashMap::new(),
+        header_only: true,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::memory::write_to_bytes;
+    use std::io::Cursor;
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Rose");
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.stitch_abs(-20.0, -10.0);
+        pattern.stitch_abs(60.0, 30.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(40.0, 50.0);
+        pattern.end();
+        pattern
+    }
+
+    fn probe_as(pattern: &EmbPattern, format: &str) -> DesignInfo {
+        let bytes = write_to_bytes(pattern, format).unwrap();
+        let path = format!("design.{}", format);
+        probe_reader(&mut Cursor::new(bytes), Some(Path::new(&path))).unwrap()
+    }
+
+    #[test]
+    fn test_probe_dst_header() {
+        let info = probe_as(&sample(), "dst");
+        assert_eq!(info.format, Format::DST);
+        assert!(info.header_only);
+        assert_eq!(info.stitch_count, Some(3));
+        assert_eq!(info.color_count, Some(2));
+        assert_eq!(info.bounds, Some((-20.0, -10.0, 60.0, 50.0)));
+        assert_eq!(info.size, Some((80.0, 60.0)));
+        assert_eq!(info.metadata.get("name").map(String::as_str), Some("Rose"));
+    }
+
+    #[test]
+    fn test_probe_pes_header() {
+        let info = probe_as(&sample(), "pes");
+        assert_eq!(info.format, Format::PES);
+        assert!(info.header_only);
+        assert_eq!(info.stitch_count, None);
+        assert_eq!(info.color_count, Some(2));
+        assert_eq!(info.size, Some((80.0, 60.0)));
+    }
+
+    #[test]
+    fn test_probe_jef_header() {
+        let info = probe_as(&sample(), "jef");
+        assert_eq!(info.format, Format::JEF);
+        assert!(info.header_only);
+        assert_eq!(info.color_count, Some(2));
+        assert_eq!(info.size, Some((80.0, 60.0)));
+    }
+
+    #[test]
+    fn test_probe_falls_back_to_full_read() {
+        let info = probe_as(&sample(), "exp");
+        assert_eq!(info.format, Format::EXP);
+        assert!(!info.header_only);
+        assert_eq!(info.stitch_count, Some(3));
+        assert_eq!(info.color_count, Some(2));
+    }
+}
@@ -3,14 +3,22 @@
 //! Provides readers for embroidery file formats with full read/write support.
 //! Each reader module exposes a `read()` function that parses the format into an `EmbPattern`.
 
+/// BUTA lossless archive reader
+pub mod buta;
 /// COL (Embroidery Thread Color) format reader
 pub mod col;
 /// CSV embroidery format reader (lossless debug format)
 pub mod csv;
+/// DSB (Barudan) format reader
+pub mod dsb;
 /// DST (Tajima) format reader
 pub mod dst;
+/// DSZ (ZSK) format reader
+pub mod dsz;
 /// EDR (Embird Color) format reader
 pub mod edr;
+/// EMB (Wilcom) format reader (experimental)
+pub mod emb;
 /// EXP (Melco) format reader
 pub mod exp;
 /// GCode format reader
@@ -27,14 +35,26 @@ pub mod json;
 pub mod pec;
 /// PES (Brother) format reader
 pub mod pes;
+/// PLT (HPGL) format reader
+pub mod plt;
+/// SHV (Husqvarna Viking) format reader
+pub mod shv;
+/// SVG vector graphics reader (outlines to running stitches)
+pub mod svg;
+/// TAP (Happy paper tape) format reader
+pub mod tap;
 /// TBF (Tajima) format reader
 pub mod tbf;
+/// THR (ThredWorks) format reader
+pub mod thr;
 /// U01 (Barudan) format reader
 pub mod u01;
 /// VP3 (Pfaff) format reader
 pub mod vp3;
 /// XXX (Singer) format reader
 pub mod xxx;
+/// ZXY (ZSK TC) format reader
+pub mod zxy;
 
 #[cfg(test)]
 mod tests;
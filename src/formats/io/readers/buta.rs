@@ -0,0 +1,91 @@
+//! BUTA archive reader
+//!
+//! Reads the crate's native lossless container written by
+//! [`writers::buta`](crate::formats::io::writers::buta).
+//!
+//! ## Format Limitations
+//!
+//! - **Max size**: Decompressed pattern data is limited to 1 GiB (safety limit)
+//! - **Versions**: Only version 1 archives are understood
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::io::writers::buta::{BUTA_SIGNATURE, BUTA_VERSION};
+use crate::utils::error::{Error, Result};
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+
+// Format constants
+const MAX_BUTA_SIZE: u64 = 1 << 30; // Safety limit for decompressed data
+
+/// Read a BUTA archive
+pub fn read(file: &mut impl Read) -> Result<EmbPattern> {
+    let mut header = [0u8; 5];
+    file.read_exact(&mut header)
+        .map_err(|_| Error::Parse("BUTA: File too small for header".to_string()))?;
+
+    if header[..4] != BUTA_SIGNATURE {
+        return Err(Error::Parse("BUTA: Missing BUTA signature".to_string()));
+    }
+    if header[4] != BUTA_VERSION {
+        return Err(Error::UnsupportedFormat(format!(
+            "BUTA: Unsupported archive version {} (expected {})",
+            header[4], BUTA_VERSION
+        )));
+    }
+
+    let mut data = Vec::new();
+    ZlibDecoder::new(file)
+        .take(MAX_BUTA_SIZE + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| Error::Parse(format!("BUTA: Corrupt compressed data: {}", e)))?;
+    if data.len() as u64 > MAX_BUTA_SIZE {
+        return Err(Error::Parse(format!(
+            "BUTA: Pattern data exceeds maximum of {} bytes",
+            MAX_BUTA_SIZE
+        )));
+    }
+
+    serde_json::from_slice(&data)
+        .map_err(|e| Error::Parse(format!("BUTA: Invalid pattern data: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::writers::buta::write;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip_is_exact() {
+        let mut pattern = EmbPattern::new();
+        let mut thread = EmbThread::new(0x123456).with_description("Teal");
+        thread.weight = Some("40wt".to_string());
+        pattern.add_thread(thread);
+        pattern.set_title("Archive");
+        pattern.set_metadata("custom_key", "kept");
+        pattern.add_stitch_absolute(STITCH, 0.1, 1.0 / 3.0);
+        pattern.add_stitch_absolute(JUMP | 0x0300_0000, -12.345678901, 7e-12);
+        pattern.add_stitch_absolute(SEQUIN_EJECT, 5.0, 5.0);
+        pattern.end();
+
+        let mut buffer = Vec::new();
+        write(&mut buffer, &pattern).unwrap();
+        assert_eq!(&buffer[..4], b"BUTA");
+
+        let read = read(&mut Cursor::new(&buffer)).unwrap();
+        assert_eq!(read.stitches(), pattern.stitches());
+        assert_eq!(read.threads()[0].weight.as_deref(), Some("40wt"));
+        assert_eq!(read.get_metadata("custom_key"), Some(&"kept".to_string()));
+        assert_eq!(read.title(), Some("Archive"));
+    }
+
+    #[test]
+    fn test_rejects_bad_headers() {
+        assert!(read(&mut Cursor::new(b"BUT")).is_err());
+        assert!(read(&mut Cursor::new(b"JSON\x01")).is_err());
+        assert!(read(&mut Cursor::new(b"BUTA\x09")).is_err());
+        assert!(read(&mut Cursor::new(b"BUTA\x01not zlib")).is_err());
+    }
+}
@@ -14,6 +14,7 @@
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::io::options::{NumberLocale, ReadOptions, ReadWarning};
 use crate::utils::error::{Error, Result};
 use std::io::{BufRead, BufReader, Read};
 
@@ -31,9 +32,45 @@ const MAX_CSV_LINE_LENGTH: usize = 10_000; // Safety limit for line length
 /// - `@,key,value` - Metadata
 /// - `$,index,color[,description,brand,catalog,details,weight]` - Thread
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    read_with_options(file, pattern, &ReadOptions::default())?;
+    Ok(())
+}
+
+/// Read CSV embroidery format with explicit read options
+///
+/// With a [`NumberLocale::Comma`] or [`NumberLocale::Auto`] locale, fields may be
+/// separated by `;` or tabs (as written by spreadsheet exports using comma
+/// decimals) and coordinates such as `1,25` are accepted.
+///
+/// Lines that are not a comment, stitch, metadata or thread record, or that
+/// have too few fields, are skipped and reported as warnings.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::core::pattern::EmbPattern;
+/// use butabuti::formats::io::options::{NumberLocale, ReadOptions};
+/// use butabuti::formats::io::readers::csv;
+/// use std::io::Cursor;
+///
+/// let data = "*;0;STITCH;12,5;-3,0\n";
+/// let options = ReadOptions::new().number_locale(NumberLocale::Comma);
+/// let mut pattern = EmbPattern::new();
+/// csv::read_with_options(&mut Cursor::new(data), &mut pattern, &options)?;
+/// assert_eq!(pattern.stitches()[0].x, 12.5);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn read_with_options(
+    file: &mut impl Read,
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+) -> Result<Vec<ReadWarning>> {
+    let locale = options.number_locale;
     let reader = BufReader::new(file);
     let mut stitch_count = 0;
     let mut thread_count = 0;
+    let mut warnings = Vec::new();
+    let mut skipped = None;
 
     for (line_num, line) in reader.lines().enumerate() {
         let line_num = line_num + 1; // Make it 1-indexed for better UX
@@ -55,21 +92,29 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
             continue;
         }
 
-        let parts: Vec<&str> = trimmed.split(',').map(|s| s.trim()).collect();
-        if parts.is_empty() {
+        let parts = split_fields(trimmed, locale);
+        let record = parts[0];
+        let min_fields = if record.starts_with('#') {
+            // Comment line
+            continue;
+        } else if record.starts_with(['*', '@', '$']) {
+            3
+        } else {
+            usize::MAX
+        };
+        if parts.len() < min_fields {
+            // A comma separated line read with the comma decimal locale
+            // arrives as a single field
+            let wrong_separator = locale == NumberLocale::Comma && trimmed.contains(',');
+            let (first, count, any_wrong) = skipped.unwrap_or((line_num, 0, false));
+            skipped = Some((first, count + 1, any_wrong || wrong_separator));
             continue;
         }
+        flush_skipped(&mut skipped, &mut warnings);
 
-        match parts[0] {
-            // Comment line
-            s if s.starts_with('#') => continue,
-
+        match record {
             // Stitch or command line: *,index,command_name [modifiers],x,y
             s if s.starts_with('*') => {
-                if parts.len() < 3 {
-                    continue;
-                }
-
                 // Validate stitch count
                 stitch_count += 1;
                 if stitch_count > MAX_CSV_STITCHES {
@@ -84,13 +129,13 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
 
                 if parts.len() >= 5 {
                     // Stitch with coordinates
-                    let x = parts[3].parse::<f64>().map_err(|_| {
+                    let x = locale.parse_f64(parts[3]).ok_or_else(|| {
                         crate::utils::error::Error::Parse(format!(
                             "CSV line {}: Invalid X coordinate '{}' (expected floating point number)",
                             line_num, parts[3]
                         ))
                     })?;
-                    let y = parts[4].parse::<f64>().map_err(|_| {
+                    let y = locale.parse_f64(parts[4]).ok_or_else(|| {
                         crate::utils::error::Error::Parse(format!(
                             "CSV line {}: Invalid Y coordinate '{}' (expected floating point number)",
                             line_num, parts[4]
@@ -105,17 +150,11 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
 
             // Metadata line: @,key,value
             s if s.starts_with('@') => {
-                if parts.len() >= 3 {
-                    pattern.add_metadata(parts[1], parts[2]);
-                }
+                pattern.add_metadata(parts[1], parts[2]);
             }
 
             // Thread line: $,index,color[,description,brand,catalog,details,weight]
             s if s.starts_with('$') => {
-                if parts.len() < 3 {
-                    continue;
-                }
-
                 // Validate thread count
                 thread_count += 1;
                 if thread_count > MAX_CSV_THREADS {
@@ -176,11 +215,46 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
                 pattern.add_thread(thread);
             }
 
-            _ => continue,
+            _ => unreachable!("unrecognized records are skipped above"),
         }
     }
+    flush_skipped(&mut skipped, &mut warnings);
 
-    Ok(())
+    Ok(warnings)
+}
+
+/// Push a warning for a run of skipped lines
+///
+/// `skipped` holds the first line of the run, its length, and whether any of
+/// its lines looked comma separated under the comma decimal locale.
+fn flush_skipped(skipped: &mut Option<(usize, usize, bool)>, warnings: &mut Vec<ReadWarning>) {
+    if let Some((first, count, wrong_separator)) = skipped.take() {
+        let mut message = format!(
+            "Skipped {} unrecognized line(s) starting at line {}",
+            count, first
+        );
+        if wrong_separator {
+            message.push_str("; the comma decimal locale expects ';' or tab separated fields");
+        }
+        warnings.push(ReadWarning::new(message));
+    }
+}
+
+/// Split a CSV line into trimmed fields
+///
+/// Dot-decimal files are comma separated. Comma-decimal files are separated by
+/// `;` (surrounding spaces and tabs are ignored) or, failing that, by tabs.
+fn split_fields(line: &str, locale: NumberLocale) -> Vec<&str> {
+    let delimiter = match locale {
+        NumberLocale::Dot => ',',
+        NumberLocale::Comma if line.contains(';') => ';',
+        NumberLocale::Comma => '\t',
+        NumberLocale::Auto if line.contains(';') => ';',
+        NumberLocale::Auto if line.contains('\t') => '\t',
+        NumberLocale::Auto => ',',
+    };
+
+    line.split(delimiter).map(|s| s.trim()).collect()
 }
 
 /// Parse command string like "STITCH n1 t2" into command code
@@ -289,6 +363,95 @@ $,1,#00FF00,Green Thread
         assert_eq!((cmd >> 8) & 0xFF, 4); // thread 3 + 1
     }
 
+    #[test]
+    fn test_comma_decimal_locale() {
+        let csv_data = "\
+$;0;#FF0000;Red
+*;0;STITCH;12,5;-3,25
+*\t1\tJUMP\t 1.000,5\t0
+*;2; STITCH ;\t7,0\t;8
+";
+        let options = ReadOptions::new().number_locale(NumberLocale::Comma);
+        let mut pattern = EmbPattern::new();
+        read_with_options(&mut Cursor::new(csv_data), &mut pattern, &options)
+            .expect("Failed to read CSV");
+
+        assert_eq!(pattern.threads().len(), 1);
+        let stitches = pattern.stitches();
+        assert_eq!(stitches.len(), 3);
+        assert_eq!((stitches[0].x, stitches[0].y), (12.5, -3.25));
+        assert_eq!(stitches[1].x, 1000.5);
+        assert_eq!((stitches[2].x, stitches[2].y), (7.0, 8.0));
+    }
+
+    #[test]
+    fn test_auto_locale_accepts_both() {
+        let csv_data = "*,0,STITCH,1.5,2.5\n*;1;STITCH;3,5;4,5\n";
+        let options = ReadOptions::new().number_locale(NumberLocale::Auto);
+        let mut pattern = EmbPattern::new();
+        read_with_options(&mut Cursor::new(csv_data), &mut pattern, &options)
+            .expect("Failed to read CSV");
+
+        let stitches = pattern.stitches();
+        assert_eq!((stitches[0].x, stitches[0].y), (1.5, 2.5));
+        assert_eq!((stitches[1].x, stitches[1].y), (3.5, 4.5));
+    }
+
+    #[test]
+    fn test_dot_locale_ignores_comma_decimals() {
+        let csv_data = "*;0;STITCH;12,5;3\n";
+        let mut pattern = EmbPattern::new();
+        // Default locale splits on ',' so the line is not recognized
+        let warnings = read_with_options(
+            &mut Cursor::new(csv_data),
+            &mut pattern,
+            &ReadOptions::new(),
+        )
+        .unwrap();
+        assert!(pattern.stitches().is_empty());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_skipped_lines_are_reported() {
+        let csv_data = "\
+index,command,x,y
+*,0,STITCH,1.0,2.0
+*,1
+@
+*,2,STITCH,3.0,4.0
+";
+        let mut pattern = EmbPattern::new();
+        let warnings = read_with_options(
+            &mut Cursor::new(csv_data),
+            &mut pattern,
+            &ReadOptions::new(),
+        )
+        .unwrap();
+        assert_eq!(pattern.stitches().len(), 2);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Skipped 1 unrecognized line(s) starting at line 1",
+                "Skipped 2 unrecognized line(s) starting at line 3",
+            ]
+        );
+
+        // Comma separated input read with the comma decimal locale
+        let options = ReadOptions::new().number_locale(NumberLocale::Comma);
+        let mut pattern = EmbPattern::new();
+        let warnings = read_with_options(
+            &mut Cursor::new("*,0,STITCH,1.0,2.0\n*,1,STITCH,3.0,4.0\n"),
+            &mut pattern,
+            &options,
+        )
+        .unwrap();
+        assert!(pattern.stitches().is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("';' or tab separated"));
+    }
+
     #[test]
     fn test_embroidermodder_format() {
         let csv_data = "$,0,255,128,64,Orange Thread,EM001\n";
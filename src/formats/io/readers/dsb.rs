@@ -1,51 +1,64 @@
 //! Barudan DSB format reader
 //!
-//! DSB is a DST variant supporting sequin stitching for Barudan machines.
-//! Uses DST reader with modified header handling for sequin information.
+//! DSB is a DST header followed by Barudan B-stitch records, as written for
+//! older Barudan machines. Each 3-byte record is a control byte, then the dy
+//! and dx magnitudes. Bits 0x40 and 0x20 of the control byte flip the y and x
+//! directions; its low five bits pick the command.
+//!
+//! ## Format Limitations
+//! - Fixed header size: 512 bytes (DST header)
+//! - Stitch range: ±127 per record on each axis
+//! - Needles 1-15 (control bytes 0xE9-0xF7)
+//! - Maximum 1,000,000 stitches per file
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::formats::io::readers::dst;
-use crate::utils::error::Result;
+use crate::utils::error::{Error, Result};
 use crate::utils::functions::encode_thread_change;
 use std::io::Read;
 
+/// Maximum allowed stitch count
+const MAX_STITCHES: usize = 1_000_000;
+
+/// Trim control byte
+pub(crate) const DSB_TRIM: u8 = 0xE7;
+/// Stop control byte
+pub(crate) const DSB_STOP: u8 = 0xE8;
+/// End of design control byte
+pub(crate) const DSB_END: u8 = 0xF8;
+
 /// Read DSB (Barudan B-stitch) format
 ///
-/// DSB uses DST header followed by B-stitch encoding.
-/// B-stitch encoding is similar to Z-stitch but with different byte order.
+/// Header fields are read like DST (name, author, threads in an extended
+/// header).
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
-    // Read DST header
-    let dst_pattern = dst::read(file, None)?;
-
-    // Copy header data
-    for (key, value) in dst_pattern.metadata() {
-        pattern.add_metadata(key, value);
-    }
-
-    // Read B-stitch encoded data
-    read_b_stitch_encoding(file, pattern)?;
-
-    Ok(())
+    dst::read_header(file, pattern)?;
+    read_b_stitches(file, pattern)
 }
 
-/// Read B-stitch encoded stitch data
-fn read_b_stitch_encoding(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+/// Read B-stitch records up to the end marker or the end of the file
+fn read_b_stitches(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
     let mut buffer = [0u8; 3];
+    let mut count = 0;
 
     loop {
-        // Try to read 3 bytes
         match file.read_exact(&mut buffer) {
             Ok(_) => {}
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
             Err(e) => return Err(e.into()),
         }
+        count += 1;
+        if count > MAX_STITCHES {
+            return Err(Error::Parse(format!(
+                "DSB file exceeds maximum stitch count of {}",
+                MAX_STITCHES
+            )));
+        }
 
         let ctrl = buffer[0];
-        let mut y = -(buffer[1] as i8) as i32;
-        let mut x = buffer[2] as i32;
-
-        // Apply sign bits
+        let mut y = -(buffer[1] as f64);
+        let mut x = buffer[2] as f64;
         if ctrl & 0x40 != 0 {
             y = -y;
         }
@@ -53,35 +66,25 @@ fn read_b_stitch_encoding(file: &mut impl Read, pattern: &mut EmbPattern) -> Res
             x = -x;
         }
 
-        let command_bits = ctrl & 0b11111;
-
-        if command_bits == 0 {
-            // Stitch
-            pattern.add_stitch_relative(x as f64, y as f64, STITCH);
-        } else if command_bits == 1 {
-            // Jump/Move
-            pattern.add_stitch_relative(x as f64, y as f64, JUMP);
-        } else if ctrl == 0xF8 {
-            // End of pattern
-            break;
-        } else if ctrl == 0xE7 {
-            // Trim
-            pattern.add_command(TRIM, 0.0, 0.0);
-        } else if ctrl == 0xE8 {
-            // Stop
-            pattern.add_command(STOP, 0.0, 0.0);
-        } else if (0xE9..0xF8).contains(&ctrl) {
-            // Needle change
-            let needle = ctrl - 0xE8;
-            let command = encode_thread_change(NEEDLE_SET, None, Some(needle), None);
-            pattern.add_command(command, 0.0, 0.0);
-        } else {
-            // Unknown command - stop reading
-            break;
+        match ctrl & 0b11111 {
+            0 => pattern.add_stitch_relative(x, y, STITCH),
+            1 => pattern.add_stitch_relative(x, y, JUMP),
+            _ => match ctrl {
+                DSB_END => break,
+                DSB_TRIM => pattern.trim(),
+                DSB_STOP => pattern.add_stitch_relative(0.0, 0.0, STOP),
+                0xE9..=0xF7 => {
+                    let needle = ctrl - DSB_STOP;
+                    let command = encode_thread_change(NEEDLE_SET, None, Some(needle), None);
+                    pattern.add_stitch_relative(0.0, 0.0, command);
+                }
+                // Unknown command
+                _ => break,
+            },
         }
     }
 
-    pattern.add_command(END, 0.0, 0.0);
+    pattern.add_stitch_relative(0.0, 0.0, END);
     Ok(())
 }
 
@@ -90,73 +93,78 @@ mod tests {
     use super::*;
     use std::io::Cursor;
 
-    #[test]
-    fn test_read_dsb_basic() {
-        // Create minimal DST header (512 bytes) + B-stitch data
-        let mut dsb_data = vec![0u8; 512];
-
-        // Add B-stitch encoded stitches
-        // Stitch at (10, -10): ctrl=0x00, y=10 (negated), x=10
-        dsb_data.extend_from_slice(&[0x00, 10, 10]);
-
-        // Jump at (5, -5)
-        dsb_data.extend_from_slice(&[0x01, 5, 5]);
-
-        // Trim
-        dsb_data.extend_from_slice(&[0xE7, 0, 0]);
-
-        // End
-        dsb_data.extend_from_slice(&[0xF8, 0, 0]);
+    fn header() -> Vec<u8> {
+        let mut data = b"LA:Barudan        \r".to_vec();
+        data.resize(512, b' ');
+        data
+    }
 
-        let mut cursor = Cursor::new(dsb_data);
+    fn read_bytes(data: Vec<u8>) -> EmbPattern {
         let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(data), &mut pattern).expect("Failed to read DSB");
+        pattern
+    }
 
-        read(&mut cursor, &mut pattern).expect("Failed to read DSB");
-
-        // Should complete without error
-        assert!(!pattern.stitches().is_empty());
+    #[test]
+    fn test_read_dsb_basic() {
+        let mut data = header();
+        // Stitch 10 right and 10 up, jump 5 right and 5 down, trim, end
+        data.extend_from_slice(&[0x80, 10, 10]);
+        data.extend_from_slice(&[0xC1, 5, 5]);
+        data.extend_from_slice(&[DSB_TRIM, 0, 0]);
+        data.extend_from_slice(&[DSB_END, 0, 0]);
+        data.extend_from_slice(&[0x80, 10, 10]);
+
+        let pattern = read_bytes(data);
+        assert_eq!(
+            pattern.get_metadata("name").map(|s| s.as_str()),
+            Some("Barudan")
+        );
+        let records: Vec<(u32, f64, f64)> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.command & COMMAND_MASK, s.x, s.y))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (STITCH, 10.0, -10.0),
+                (JUMP, 15.0, -5.0),
+                (TRIM, 15.0, -5.0),
+                (END, 15.0, -5.0),
+            ]
+        );
     }
 
     #[test]
     fn test_b_stitch_negative_coordinates() {
-        // Test negative coordinate handling with sign bits
-        let mut dsb_data = vec![0u8; 512];
-
-        // Stitch with x negative (bit 0x20 set): ctrl=0x20, y=5, x=10
-        dsb_data.extend_from_slice(&[0x20, 5, 10]);
-
-        // Stitch with y negative (bit 0x40 set): ctrl=0x40, y=5, x=10
-        dsb_data.extend_from_slice(&[0x40, 5, 10]);
-
-        // End
-        dsb_data.extend_from_slice(&[0xF8, 0, 0]);
-
-        let mut cursor = Cursor::new(dsb_data);
-        let mut pattern = EmbPattern::new();
-
-        read(&mut cursor, &mut pattern).expect("Failed to read DSB");
-
-        assert!(!pattern.stitches().is_empty());
+        let mut data = header();
+        // x flipped, then y flipped; magnitudes above 127 stay positive
+        data.extend_from_slice(&[0x20, 5, 10]);
+        data.extend_from_slice(&[0x40, 200, 10]);
+
+        let pattern = read_bytes(data);
+        assert_eq!(pattern.stitches()[0].x, -10.0);
+        assert_eq!(pattern.stitches()[0].y, -5.0);
+        assert_eq!(pattern.stitches()[1].y, 195.0);
     }
 
     #[test]
     fn test_b_stitch_needle_change() {
-        let mut dsb_data = vec![0u8; 512];
-
-        // Needle change to needle 1: ctrl=0xE9
-        dsb_data.extend_from_slice(&[0xE9, 0, 0]);
-
-        // Needle change to needle 5: ctrl=0xED
-        dsb_data.extend_from_slice(&[0xED, 0, 0]);
-
-        // End
-        dsb_data.extend_from_slice(&[0xF8, 0, 0]);
-
-        let mut cursor = Cursor::new(dsb_data);
-        let mut pattern = EmbPattern::new();
-
-        read(&mut cursor, &mut pattern).expect("Failed to read DSB");
-
-        assert!(!pattern.stitches().is_empty());
+        let mut data = header();
+        data.extend_from_slice(&[0xE9, 0, 0]);
+        data.extend_from_slice(&[0xED, 0, 0]);
+        data.extend_from_slice(&[DSB_END, 0, 0]);
+
+        let pattern = read_bytes(data);
+        let needles: Vec<Option<u8>> = pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+            .map(|s| crate::utils::functions::decode_embroidery_command(s.command).2)
+            .collect();
+        assert_eq!(needles, vec![Some(1), Some(5)]);
+
+        assert!(read(&mut Cursor::new(vec![0u8; 100]), &mut EmbPattern::new()).is_err());
     }
 }
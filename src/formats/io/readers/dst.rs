@@ -18,6 +18,10 @@
 //! - Header contains DST markers (LA:, ST:, CO:) or valid ASCII text
 //! - Stitch count does not exceed safety limit
 //!
+//! With [`ReadOptions::lenient`] an unrecognized header and records without the
+//! DST marker bits are skipped, and reading stops at the stitch limit instead of
+//! failing. Every recovery is reported as a [`ReadWarning`].
+//!
 //! ## Example
 //!
 //! ```no_run
@@ -33,6 +37,9 @@
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, ReadWarning};
+use crate::formats::io::utils::read_block;
 use crate::utils::error::{Error, Result};
 use std::collections::HashMap;
 use std::io::Read;
@@ -43,14 +50,17 @@ const DST_HEADER_SIZE: usize = 512;
 /// Maximum allowed stitches for safety
 const MAX_STITCHES: usize = 1_000_000;
 
+/// Records decoded per block read
+const BLOCK_RECORDS: usize = 4096;
+
 /// Get bit value at position
 #[inline]
-fn get_bit(b: u8, pos: u8) -> i32 {
+const fn get_bit(b: u8, pos: u8) -> i32 {
     ((b >> pos) & 1) as i32
 }
 
 /// Decode X coordinate from 3 bytes
-fn decode_dx(b0: u8, b1: u8, b2: u8) -> i32 {
+const fn decode_dx(b0: u8, b1: u8, b2: u8) -> i32 {
     let mut x = 0;
     x += get_bit(b2, 2) * 81;
     x += get_bit(b2, 3) * -81;
@@ -66,7 +76,7 @@ fn decode_dx(b0: u8, b1: u8, b2: u8) -> i32 {
 }
 
 /// Decode Y coordinate from 3 bytes
-fn decode_dy(b0: u8, b1: u8, b2: u8) -> i32 {
+const fn decode_dy(b0: u8, b1: u8, b2: u8) -> i32 {
     let mut y = 0;
     y += get_bit(b2, 5) * 81;
     y += get_bit(b2, 4) * -81;
@@ -81,6 +91,37 @@ fn decode_dy(b0: u8, b1: u8, b2: u8) -> i32 {
     -y
 }
 
+/// Per-byte displacement lookup: `DELTA_TABLE[position][byte] = (dx, dy)`
+///
+/// Each byte of a record contributes independently to the displacement, so a
+/// record decodes as the sum of three table lookups.
+static DELTA_TABLE: [[(i8, i8); 256]; 3] = build_delta_table();
+
+const fn build_delta_table() -> [[(i8, i8); 256]; 3] {
+    let mut table = [[(0i8, 0i8); 256]; 3];
+    let mut b = 0;
+    while b < 256 {
+        let v = b as u8;
+        table[0][b] = (decode_dx(v, 0, 0) as i8, decode_dy(v, 0, 0) as i8);
+        table[1][b] = (decode_dx(0, v, 0) as i8, decode_dy(0, v, 0) as i8);
+        table[2][b] = (decode_dx(0, 0, v) as i8, decode_dy(0, 0, v) as i8);
+        b += 1;
+    }
+    table
+}
+
+/// Decode the displacement of a record with table lookups
+#[inline(always)]
+pub(crate) fn decode_delta(b0: u8, b1: u8, b2: u8) -> (i32, i32) {
+    let (x0, y0) = DELTA_TABLE[0][b0 as usize];
+    let (x1, y1) = DELTA_TABLE[1][b1 as usize];
+    let (x2, y2) = DELTA_TABLE[2][b2 as usize];
+    (
+        x0 as i32 + x1 as i32 + x2 as i32,
+        y0 as i32 + y1 as i32 + y2 as i32,
+    )
+}
+
 /// Process a header line
 fn process_header_info(pattern: &mut EmbPattern, prefix: &str, value: &str) {
     match prefix {
@@ -93,6 +134,15 @@ fn process_header_info(pattern: &mut EmbPattern, prefix: &str, value: &str) {
         "CP" => {
             pattern.add_metadata("copyright", value);
         }
+        "LI" => {
+            pattern.add_metadata("license", value);
+        }
+        "LU" => {
+            pattern.add_metadata("license_uses", value);
+        }
+        "LP" => {
+            pattern.add_metadata("license_purchaser_id", value);
+        }
         "TC" => {
             // Thread color: hex, description, catalog
             let parts: Vec<&str> = value.split(',').map(|s| s.trim()).collect();
@@ -120,14 +170,23 @@ fn process_header_info(pattern: &mut EmbPattern, prefix: &str, value: &str) {
 }
 
 /// Read DST header (512 bytes)
-fn read_header<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()> {
+pub(crate) fn read_header<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()> {
+    read_header_with_options(reader, pattern, &ReadOptions::default(), &mut Vec::new())
+}
+
+/// Read DST header (512 bytes), accepting unrecognized headers in lenient mode
+fn read_header_with_options<R: Read>(
+    reader: &mut R,
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<()> {
     let mut header = vec![0u8; DST_HEADER_SIZE];
     reader.read_exact(&mut header).map_err(|e| {
         if e.kind() == std::io::ErrorKind::UnexpectedEof {
-            Error::Parse(format!(
-                "DST file too small: header must be {} bytes",
-                DST_HEADER_SIZE
-            ))
+            Error::truncated(format!("DST header of {} bytes", DST_HEADER_SIZE))
+                .in_format(Format::DST)
+                .at_offset(0)
         } else {
             Error::from(e)
         }
@@ -144,9 +203,18 @@ fn read_header<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()>
             .filter(|&&b| (32..127).contains(&b) || b == 0 || b == 13 || b == 10)
             .count();
         if printable_count < 24 {
-            return Err(Error::Parse(
-                "Invalid DST header: expected DST text markers (LA:, ST:, CO:) or ASCII text"
-                    .to_string(),
+            if !options.lenient {
+                return Err(Error::unexpected_value(
+                    "DST header",
+                    "DST text markers (LA:, ST:, CO:) or ASCII text",
+                    "binary data",
+                )
+                .in_format(Format::DST)
+                .at_offset(0));
+            }
+            warnings.push(ReadWarning::at(
+                0,
+                "DST header has no text markers or ASCII text, reading records anyway",
             ));
         }
     }
@@ -159,10 +227,12 @@ fn read_header<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()>
                 let data = &header[start..i];
                 if let Ok(line) = String::from_utf8(data.to_vec()) {
                     let line = line.trim();
-                    if line.len() > 3 {
-                        let prefix = &line[0..2].trim();
-                        let value = &line[3..].trim();
-                        process_header_info(pattern, prefix, value);
+                    // Fields are a two-character prefix, a colon and the value
+                    if let (Some(prefix), Some(value)) = (line.get(0..2), line.get(3..)) {
+                        let value = value.trim();
+                        if !value.is_empty() {
+                            process_header_info(pattern, prefix.trim(), value);
+                        }
                     }
                 }
             }
@@ -173,59 +243,131 @@ fn read_header<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()>
     Ok(())
 }
 
-/// Read DST stitches
-fn read_stitches<R: Read>(
+/// Read DST stitch records up to the end marker (also used by TAP)
+pub(crate) fn read_stitches<R: Read>(
     reader: &mut R,
     pattern: &mut EmbPattern,
     settings: &HashMap<String, String>,
+) -> Result<()> {
+    let options = ReadOptions::default();
+    read_stitches_with_options(reader, pattern, settings, &options, &mut Vec::new(), 0)
+}
+
+/// Push a warning for a run of damaged records skipped in lenient mode
+fn flush_skipped(skipped: &mut Option<(u64, usize)>, warnings: &mut Vec<ReadWarning>) {
+    if let Some((offset, count)) = skipped.take() {
+        warnings.push(ReadWarning::at(
+            offset,
+            format!(
+                "Skipped {} damaged record(s) without DST marker bits",
+                count
+            ),
+        ));
+    }
+}
+
+/// Read DST stitch records starting at byte `start` of the file
+fn read_stitches_with_options<R: Read>(
+    reader: &mut R,
+    pattern: &mut EmbPattern,
+    settings: &HashMap<String, String>,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+    start: u64,
 ) -> Result<()> {
     let mut sequin_mode = false;
-    let mut buffer = [0u8; 3];
+    let mut block = vec![0u8; BLOCK_RECORDS * 3];
     let mut stitch_count = 0;
+    let mut offset = start;
+    let mut skipped = None;
+    let mut ended = false;
+
+    'blocks: loop {
+        let len = read_block(reader, &mut block)?;
+
+        for record in block[..len].chunks_exact(3) {
+            let record_offset = offset;
+            offset += 3;
+
+            // Check for excessive stitch count
+            stitch_count += 1;
+            if stitch_count > MAX_STITCHES {
+                if !options.lenient {
+                    return Err(Error::limit_exceeded(
+                        "DST stitch count",
+                        MAX_STITCHES,
+                        stitch_count,
+                    )
+                    .in_format(Format::DST)
+                    .at_offset(record_offset)
+                    .at_stitch(stitch_count - 1));
+                }
+                flush_skipped(&mut skipped, warnings);
+                warnings.push(ReadWarning::at(
+                    record_offset,
+                    format!(
+                        "Stitch count exceeds the limit of {}, ignoring the rest",
+                        MAX_STITCHES
+                    ),
+                ));
+                ended = true;
+                break 'blocks;
+            }
 
-    loop {
-        match reader.read_exact(&mut buffer) {
-            Ok(_) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(Error::from(e)),
-        }
+            let (b0, b1, b2) = (record[0], record[1], record[2]);
 
-        // Check for excessive stitch count
-        stitch_count += 1;
-        if stitch_count > MAX_STITCHES {
-            return Err(Error::Parse(format!(
-                "DST file exceeds maximum stitch count of {}",
-                MAX_STITCHES
-            )));
+            // Every DST record has the two low bits of its third byte set
+            if options.lenient && b2 & 0b11 != 0b11 {
+                let (first, count) = skipped.unwrap_or((record_offset, 0));
+                skipped = Some((first, count + 1));
+                continue;
+            }
+            flush_skipped(&mut skipped, warnings);
+
+            let (dx, dy) = decode_delta(b0, b1, b2);
+            let (dx, dy) = (dx as f64, dy as f64);
+
+            // Check control bits
+            if b2 & 0b11110011 == 0b11110011 {
+                // End pattern
+                ended = true;
+                break 'blocks;
+            } else if b2 & 0b11000011 == 0b11000011 {
+                // Color change
+                pattern.color_change(dx, dy);
+            } else if b2 & 0b01000011 == 0b01000011 {
+                // Sequin mode toggle
+                pattern.add_stitch_relative(dx, dy, SEQUIN_MODE);
+                sequin_mode = !sequin_mode;
+            } else if b2 & 0b10000011 == 0b10000011 {
+                // Move or sequin eject
+                if sequin_mode {
+                    pattern.add_stitch_relative(dx, dy, SEQUIN_EJECT);
+                } else {
+                    pattern.jump(dx, dy);
+                }
+            } else {
+                // Normal stitch
+                pattern.stitch(dx, dy);
+            }
         }
 
-        let dx = decode_dx(buffer[0], buffer[1], buffer[2]) as f64;
-        let dy = decode_dy(buffer[0], buffer[1], buffer[2]) as f64;
-
-        // Check control bits
-        if buffer[2] & 0b11110011 == 0b11110011 {
-            // End pattern
-            break;
-        } else if buffer[2] & 0b11000011 == 0b11000011 {
-            // Color change
-            pattern.color_change(dx, dy);
-        } else if buffer[2] & 0b01000011 == 0b01000011 {
-            // Sequin mode toggle
-            pattern.add_stitch_relative(dx, dy, SEQUIN_MODE);
-            sequin_mode = !sequin_mode;
-        } else if buffer[2] & 0b10000011 == 0b10000011 {
-            // Move or sequin eject
-            if sequin_mode {
-                pattern.add_stitch_relative(dx, dy, SEQUIN_EJECT);
-            } else {
-                pattern.jump(dx, dy);
+        if len < block.len() {
+            // A trailing partial record is ignored
+            if len % 3 != 0 {
+                warnings.push(ReadWarning::at(
+                    offset,
+                    "Ignored a partial record at the end of the file",
+                ));
             }
-        } else {
-            // Normal stitch
-            pattern.stitch(dx, dy);
+            break;
         }
     }
 
+    flush_skipped(&mut skipped, warnings);
+    if !ended {
+        warnings.push(ReadWarning::at(offset, "File ends without an end record"));
+    }
     pattern.end();
 
     // Interpolate trims based on settings
@@ -258,16 +400,38 @@ pub fn read<R: Read>(
     reader: &mut R,
     settings: Option<HashMap<String, String>>,
 ) -> Result<EmbPattern> {
+    let (pattern, _warnings) = read_with_options(reader, settings, &ReadOptions::default())?;
+    Ok(pattern)
+}
+
+/// Read a DST file with explicit read options
+///
+/// Returns the pattern together with any warnings raised while reading, such
+/// as a missing end record or damaged records skipped in lenient mode.
+pub fn read_with_options<R: Read>(
+    reader: &mut R,
+    settings: Option<HashMap<String, String>>,
+    options: &ReadOptions,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
     let mut pattern = EmbPattern::new();
+    let mut warnings = Vec::new();
     let settings = settings.unwrap_or_default();
 
-    read_header(reader, &mut pattern)?;
-    read_stitches(reader, &mut pattern, &settings)?;
-
-    Ok(pattern)
+    read_header_with_options(reader, &mut pattern, options, &mut warnings)?;
+    read_stitches_with_options(
+        reader,
+        &mut pattern,
+        &settings,
+        options,
+        &mut warnings,
+        DST_HEADER_SIZE as u64,
+    )?;
+
+    Ok((pattern, warnings))
 }
 
 /// Read a DST file from path
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let mut reader = std::io::BufReader::new(file);
@@ -298,6 +462,87 @@ mod tests {
         assert_eq!(decode_dy(0b10000000, 0, 0), -1);
     }
 
+    #[test]
+    fn test_delta_tables_match_bit_decoding() {
+        // Spot-check records across all byte positions, including control bits
+        for &(b0, b1, b2) in &[
+            (0x00, 0x00, 0x03),
+            (0xFF, 0x00, 0x03),
+            (0x00, 0xFF, 0x03),
+            (0x00, 0x00, 0xFF),
+            (0xA5, 0x5A, 0xC3),
+            (0x12, 0x34, 0x56),
+            (0xFF, 0xFF, 0xF3),
+        ] {
+            assert_eq!(
+                decode_delta(b0, b1, b2),
+                (decode_dx(b0, b1, b2), decode_dy(b0, b1, b2))
+            );
+        }
+        for b in 0..=255u8 {
+            assert_eq!(decode_delta(b, b, b).0, decode_dx(b, b, b));
+            assert_eq!(decode_delta(b, b, b).1, decode_dy(b, b, b));
+        }
+    }
+
+    #[test]
+    fn test_block_boundaries() {
+        // More records than fit in one block, plus a trailing partial record
+        let mut data = vec![0u8; DST_HEADER_SIZE];
+        data[..3].copy_from_slice(b"LA:");
+        for _ in 0..BLOCK_RECORDS + 10 {
+            data.extend_from_slice(&[0x01, 0x00, 0x03]); // stitch +1, 0
+        }
+        data.extend_from_slice(&[0x01, 0x00]);
+
+        let pattern = read(&mut &data[..], None).unwrap();
+        let stitches = pattern.stitches();
+        assert_eq!(stitches.len(), BLOCK_RECORDS + 11);
+        assert_eq!(stitches[BLOCK_RECORDS + 9].x, (BLOCK_RECORDS + 10) as f64);
+        assert_eq!(stitches.last().unwrap().command, END);
+    }
+
+    #[test]
+    fn test_lenient_skips_damaged_records() {
+        let mut data = vec![0u8; DST_HEADER_SIZE];
+        data[..3].copy_from_slice(b"LA:");
+        data.extend_from_slice(&[0x01, 0x00, 0x03]); // stitch +1, 0
+        data.extend_from_slice(&[0x55, 0xAA, 0x00]); // damaged
+        data.extend_from_slice(&[0x55, 0xAA, 0x10]); // damaged
+        data.extend_from_slice(&[0x01, 0x00, 0x03]);
+        data.extend_from_slice(&[0x00, 0x00, 0xF3]); // end
+
+        // Strict reading decodes the damaged records as stitches
+        let (strict, warnings) =
+            read_with_options(&mut &data[..], None, &ReadOptions::new()).unwrap();
+        assert_eq!(strict.count_stitches(), 4);
+        assert!(warnings.is_empty());
+
+        let options = ReadOptions::new().lenient(true);
+        let (pattern, warnings) = read_with_options(&mut &data[..], None, &options).unwrap();
+        assert_eq!(pattern.count_stitches(), 2);
+        assert_eq!(pattern.stitches()[1].x, 2.0);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].offset, Some(DST_HEADER_SIZE as u64 + 3));
+        assert!(warnings[0].message.contains("2 damaged"));
+    }
+
+    #[test]
+    fn test_lenient_header_and_missing_end() {
+        let mut data = vec![0xFFu8; DST_HEADER_SIZE];
+        data.extend_from_slice(&[0x01, 0x00, 0x03]);
+        data.push(0x01);
+
+        assert!(read(&mut &data[..], None).is_err());
+
+        let options = ReadOptions::new().lenient(true);
+        let (pattern, warnings) = read_with_options(&mut &data[..], None, &options).unwrap();
+        assert_eq!(pattern.count_stitches(), 1);
+        let offsets: Vec<_> = warnings.iter().map(|w| w.offset).collect();
+        assert_eq!(offsets, vec![Some(0), Some(515), Some(515)]);
+        assert!(warnings[2].message.contains("end record"));
+    }
+
     #[test]
     fn test_get_bit() {
         assert_eq!(get_bit(0b00000001, 0), 1);
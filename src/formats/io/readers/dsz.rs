@@ -1,53 +1,65 @@
 //! ZSK DSZ format reader
 //!
-//! DSZ is a ZSK variant of DST format with header differences and Z-stitch encoding.
-//! Compatible with DST reader after skipping the ZSK-specific header.
+//! DSZ is a DST header followed by ZSK Z-stitch records, as used by ZSK USA
+//! design software. Each 3-byte record holds the dy and dx magnitudes, then a
+//! control byte. Bits 0x40 and 0x20 of the control byte flip the x and y
+//! directions; its low five bits pick the command.
+//!
+//! ## Format Limitations
+//! - Fixed header size: 512 bytes (DST header)
+//! - Stitch range: ±127 per record on each axis
+//! - Needles 1-12 (control bytes 0x83-0x99, every other value)
+//! - No end marker: stitch data runs to the end of the file
+//! - Maximum 1,000,000 stitches per file
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::formats::io::readers::dst;
-use crate::utils::error::Result;
+use crate::utils::error::{Error, Result};
 use crate::utils::functions::encode_thread_change;
 use std::io::Read;
 
+/// Maximum allowed stitch count
+const MAX_STITCHES: usize = 1_000_000;
+
+/// Stop control byte
+pub(crate) const DSZ_STOP: u8 = 0x82;
+/// Control byte of the first needle; needle n is `0x83 + 2 * (n - 1)`
+pub(crate) const DSZ_NEEDLE: u8 = 0x83;
+/// Trim control byte
+pub(crate) const DSZ_TRIM: u8 = 0x9B;
+
 /// Read DSZ (ZSK USA Design) format
 ///
-/// DSZ format uses a DST header followed by Z-stitch encoding.
+/// Header fields are read like DST (name, author, threads in an extended
+/// header).
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
-    // Read DST header (512 bytes)
-    let dst_pattern = dst::read(file, None)?;
-
-    // Copy header metadata
-    for (key, value) in dst_pattern.metadata() {
-        pattern.add_metadata(key, value);
-    }
-
-    // Read Z-stitch encoded stitches
-    read_z_stitches(file, pattern)?;
-
-    Ok(())
+    dst::read_header(file, pattern)?;
+    read_z_stitches(file, pattern)
 }
 
-/// Read Z-stitch encoding (used by DSZ, GT formats)
+/// Read Z-stitch records up to the end of the file (used by DSZ, GT formats)
 pub fn read_z_stitches(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
     let mut buffer = [0u8; 3];
+    let mut count = 0;
 
     loop {
-        // Try to read 3 bytes
         match file.read_exact(&mut buffer) {
             Ok(_) => {}
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
             Err(e) => return Err(e.into()),
         }
+        count += 1;
+        if count > MAX_STITCHES {
+            return Err(Error::Parse(format!(
+                "DSZ file exceeds maximum stitch count of {}",
+                MAX_STITCHES
+            )));
+        }
 
-        let y_byte = buffer[0];
-        let x_byte = buffer[1];
         let ctrl = buffer[2];
-
-        // Decode coordinates
-        let mut x = x_byte as i32;
-        let mut y = -(y_byte as i32);
-
+        let mut x = buffer[1] as f64;
+        let mut y = -(buffer[0] as f64);
         if ctrl & 0x40 != 0 {
             x = -x;
         }
@@ -55,95 +67,89 @@ pub fn read_z_stitches(file: &mut impl Read, pattern: &mut EmbPattern) -> Result
             y = -y;
         }
 
-        // Process command
-        let command_bits = ctrl & 0b11111;
-
-        if command_bits == 0 {
-            // Stitch
-            pattern.add_stitch_relative(x as f64, y as f64, STITCH);
-        } else if command_bits == 1 {
-            // Jump
-            pattern.add_stitch_relative(x as f64, y as f64, JUMP);
-        } else if ctrl == 0x82 {
-            // Stop
-            pattern.add_command(STOP, 0.0, 0.0);
-        } else if ctrl == 0x9B {
-            // Trim
-            pattern.add_command(TRIM, 0.0, 0.0);
-        } else if (0x83..=0x9A).contains(&ctrl) {
-            // Needle change
-            let needle = (ctrl - 0x83) >> 1;
-            let command = encode_thread_change(NEEDLE_SET, None, Some(needle), None);
-            pattern.add_command(command, 0.0, 0.0);
-        } else {
-            // Unknown command - stop reading
-            break;
+        match ctrl & 0b11111 {
+            0 => pattern.add_stitch_relative(x, y, STITCH),
+            1 => pattern.add_stitch_relative(x, y, JUMP),
+            _ => match ctrl {
+                DSZ_STOP => pattern.add_stitch_relative(0.0, 0.0, STOP),
+                DSZ_TRIM => pattern.trim(),
+                0x83..=0x9A => {
+                    let needle = ((ctrl - DSZ_NEEDLE) >> 1) + 1;
+                    let command = encode_thread_change(NEEDLE_SET, None, Some(needle), None);
+                    pattern.add_stitch_relative(0.0, 0.0, command);
+                }
+                // Unknown command
+                _ => break,
+            },
         }
     }
 
-    pattern.add_command(END, 0.0, 0.0);
+    pattern.add_stitch_relative(0.0, 0.0, END);
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::functions::decode_embroidery_command;
     use std::io::Cursor;
 
     #[test]
     fn test_read_dsz_basic() {
-        // Create minimal DST header (512 bytes) + Z-stitch data
-        let mut dsz_data = vec![0u8; 512];
-
-        // Add Z-stitch encoded stitches
-        // Stitch at (10, -10)
-        dsz_data.extend_from_slice(&[10, 10, 0x00]); // y=-10, x=10, stitch
-
-        // Jump at (5, -5)
-        dsz_data.extend_from_slice(&[5, 5, 0x01]); // y=-5, x=5, jump
+        let mut data = b"LA:ZSK             \r".to_vec();
+        data.resize(512, b' ');
+        // Stitch 10 right and 10 up, jump 5 right and 5 down, trim
+        data.extend_from_slice(&[10, 10, 0x00]);
+        data.extend_from_slice(&[5, 5, 0x21]);
+        data.extend_from_slice(&[0, 0, DSZ_TRIM]);
 
-        // Trim
-        dsz_data.extend_from_slice(&[0, 0, 0x9B]);
-
-        let mut cursor = Cursor::new(dsz_data);
         let mut pattern = EmbPattern::new();
-
-        read(&mut cursor, &mut pattern).expect("Failed to read DSZ");
-
-        // Should complete without error
-        assert!(!pattern.stitches().is_empty());
+        read(&mut Cursor::new(data), &mut pattern).expect("Failed to read DSZ");
+        assert_eq!(
+            pattern.get_metadata("name").map(|s| s.as_str()),
+            Some("ZSK")
+        );
+        let records: Vec<(u32, f64, f64)> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.command & COMMAND_MASK, s.x, s.y))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (STITCH, 10.0, -10.0),
+                (JUMP, 15.0, -5.0),
+                (TRIM, 15.0, -5.0),
+                (END, 15.0, -5.0),
+            ]
+        );
     }
 
     #[test]
     fn test_z_stitch_negative_coordinates() {
-        // Test negative x and y flags
-        let z_data = vec![
-            10, 10, 0x60, // y=-10 (negated), x=-10 (0x40), stitch
-        ];
+        // Both directions flipped
+        let z_data = vec![10, 10, 0x60];
 
-        let mut cursor = Cursor::new(z_data);
         let mut pattern = EmbPattern::new();
-
-        read_z_stitches(&mut cursor, &mut pattern).expect("Failed to read Z-stitches");
-
-        // Should process the stitch
-        assert!(!pattern.stitches().is_empty());
+        read_z_stitches(&mut Cursor::new(z_data), &mut pattern).expect("Failed to read Z-stitches");
+        assert_eq!(
+            (pattern.stitches()[0].x, pattern.stitches()[0].y),
+            (-10.0, 10.0)
+        );
     }
 
     #[test]
     fn test_z_stitch_needle_change() {
-        // Test needle change command
-        let z_data = vec![
-            0, 0, 0x83, // Needle 0
-            0, 0, 0x85, // Needle 1
-        ];
+        let z_data = vec![0, 0, 0x83, 0, 0, 0x85, 0, 0, 0x99];
 
-        let mut cursor = Cursor::new(z_data);
         let mut pattern = EmbPattern::new();
-
-        read_z_stitches(&mut cursor, &mut pattern).expect("Failed to read Z-stitches");
-
-        // Should have needle change commands
-        assert!(pattern.stitches().len() >= 2);
+        read_z_stitches(&mut Cursor::new(z_data), &mut pattern).expect("Failed to read Z-stitches");
+        let needles: Vec<Option<u8>> = pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+            .map(|s| decode_embroidery_command(s.command).2)
+            .collect();
+        assert_eq!(needles, vec![Some(1), Some(2), Some(12)]);
     }
 }
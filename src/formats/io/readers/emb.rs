@@ -0,0 +1,610 @@
+//! Wilcom EMB format reader (experimental)
+//!
+//! EMB files are OLE compound documents (the container used by legacy Office
+//! files). The design objects inside are proprietary and not decoded. This
+//! reader walks the container and takes title/author/comments from the standard
+//! `SummaryInformation` stream. Stitches are only loaded when some stream in the
+//! container is itself a PES, PEC, DST, or EXP file; whether real Wilcom files
+//! carry such a stream is unconfirmed, as the tests only cover synthetic
+//! containers.
+//!
+//! ## Format Limitations
+//! - Stitch data is not decoded from Wilcom's own streams
+//! - Files without an embedded machine-format stream fail with `Error::Unsupported`
+//! - Object properties (fill types, densities, lettering) are ignored
+//! - Container size is limited to 256MB
+
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::{Error, Result};
+use std::io::{Cursor, Read};
+
+/// Compound document signature
+pub const CFB_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Largest container read into memory
+const MAX_CONTAINER_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Sector chain terminator
+const END_OF_CHAIN: u32 = 0xFFFF_FFFE;
+
+/// Sector numbers at or above this are markers, not sectors
+const MAX_REGULAR_SECTOR: u32 = 0xFFFF_FFFA;
+
+/// Unused directory sibling/child
+const NO_STREAM: u32 = 0xFFFF_FFFF;
+
+/// Read an EMB file
+pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    let mut data = Vec::new();
+    file.take(MAX_CONTAINER_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_CONTAINER_SIZE {
+        return Err(Error::Parse("EMB file exceeds 256MB".to_string()));
+    }
+
+    let container = CompoundFile::parse(&data)?;
+    let streams = container.streams()?;
+
+    let embedded = streams
+        .iter()
+        .find_map(|(name, data)| read_embedded(name, data));
+    let Some(embedded) = embedded else {
+        return Err(Error::Unsupported(
+            "EMB file has no embedded machine-format stream; Wilcom design objects are not supported"
+                .to_string(),
+        ));
+    };
+    *pattern = embedded;
+
+    if let Some((_, summary)) = streams
+        .iter()
+        .find(|(name, _)| name.ends_with("\u{5}SummaryInformation"))
+    {
+        for (id, value) in summary_properties(summary) {
+            match id {
+                2 => pattern.set_title(value),
+                3 => pattern.set_metadata("subject", value),
+                4 => pattern.set_author(value),
+                5 => pattern.set_metadata("keywords", value),
+                6 => pattern.set_description(value),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode a stream holding a machine format, if it has stitches
+fn read_embedded(name: &str, data: &[u8]) -> Option<EmbPattern> {
+    let mut cursor = Cursor::new(data);
+    let lower = name.to_ascii_lowercase();
+    let pattern = if data.starts_with(b"#PES") {
+        let mut pattern = EmbPattern::new();
+        crate::formats::io::readers::pes::read(&mut cursor, &mut pattern).ok()?;
+        pattern
+    } else if data.starts_with(b"#PEC") {
+        crate::formats::io::readers::pec::read(&mut cursor).ok()?
+    } else if data.len() >= 512 && data.starts_with(b"LA:") {
+        crate::formats::io::readers::dst::read(&mut cursor, None).ok()?
+    } else if lower.ends_with("exp") || lower.contains("exp_") {
+        // EXP has no signature; only trust streams named for it
+        crate::formats::io::readers::exp::read(&mut cursor).ok()?
+    } else {
+        return None;
+    };
+    (pattern.count_stitches() > 0).then_some(pattern)
+}
+
+/// String properties of the first section of a property set stream
+///
+/// Returns `(property id, value)` for `VT_LPSTR` and `VT_LPWSTR` properties.
+fn summary_properties(data: &[u8]) -> Vec<(u32, String)> {
+    let u32_at = |pos: usize| -> Option<u32> {
+        data.get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut result = Vec::new();
+    let Some(section) = u32_at(44).map(|o| o as usize) else {
+        return result;
+    };
+    let count = u32_at(section + 4).unwrap_or(0).min(1024) as usize;
+
+    // Code page (property 1) decides how 8-bit strings are decoded
+    let mut utf8 = false;
+    for i in 0..count {
+        let (Some(id), Some(offset)) = (u32_at(section + 8 + i * 8), u32_at(section + 12 + i * 8))
+        else {
+            break;
+        };
+        let pos = section + offset as usize;
+        if id == 1 && u32_at(pos) == Some(2) {
+            utf8 = data.get(pos + 4..pos + 6) == Some(&65001u16.to_le_bytes()[..]);
+        }
+    }
+
+    for i in 0..count {
+        let (Some(id), Some(offset)) = (u32_at(section + 8 + i * 8), u32_at(section + 12 + i * 8))
+        else {
+            break;
+        };
+        let pos = section + offset as usize;
+        let (Some(kind), Some(len)) = (u32_at(pos), u32_at(pos + 4)) else {
+            continue;
+        };
+        let start = pos + 8;
+        let value = match kind {
+            // VT_LPSTR: byte count including the terminator
+            0x1E => data.get(start..start + len as usize).map(|bytes| {
+                let bytes = bytes.split(|&b| b == 0).next().unwrap_or(&[]);
+                if utf8 {
+                    String::from_utf8_lossy(bytes).into_owned()
+                } else {
+                    encoding_rs::WINDOWS_1252.decode(bytes).0.into_owned()
+                }
+            }),
+            // VT_LPWSTR: character count including the terminator
+            0x1F => data.get(start..start + len as usize * 2).map(|bytes| {
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .take_while(|&u| u != 0)
+                    .collect();
+                String::from_utf16_lossy(&units)
+            }),
+            _ => None,
+        };
+        if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+            result.push((id, value.trim().to_string()));
+        }
+    }
+    result
+}
+
+/// Directory entry of a compound document
+struct DirEntry {
+    name: String,
+    kind: u8,
+    left: u32,
+    right: u32,
+    child: u32,
+    start: u32,
+    size: u64,
+}
+
+/// In-memory compound document (MS-CFB)
+struct CompoundFile<'a> {
+    data: &'a [u8],
+    sector_size: usize,
+    mini_sector_size: usize,
+    mini_cutoff: u64,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    entries: Vec<DirEntry>,
+    mini_stream: Vec<u8>,
+}
+
+impl<'a> CompoundFile<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 512 || data[..8] != CFB_SIGNATURE {
+            return Err(Error::Parse(
+                "Not an EMB file: missing compound document signature".to_string(),
+            ));
+        }
+        let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+        let u32_at = |pos: usize| {
+            u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+        };
+
+        let sector_shift = u16_at(0x1E);
+        let mini_shift = u16_at(0x20);
+        if !(sector_shift == 9 || sector_shift == 12) || mini_shift != 6 {
+            return Err(Error::Parse(format!(
+                "Unsupported compound document sector size 2^{}",
+                sector_shift
+            )));
+        }
+
+        let mut file = Self {
+            data,
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_shift,
+            mini_cutoff: u32_at(0x38) as u64,
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            entries: Vec::new(),
+            mini_stream: Vec::new(),
+        };
+
+        // FAT sector list: 109 entries in the header, then the DIFAT chain
+        let mut fat_sectors: Vec<u32> = (0..109)
+            .map(|i| u32_at(0x4C + i * 4))
+            .filter(|&s| s < MAX_REGULAR_SECTOR)
+            .collect();
+        let mut difat = u32_at(0x44);
+        let per_sector = file.sector_size / 4;
+        let mut guard = 0;
+        while difat < MAX_REGULAR_SECTOR && guard < data.len() / file.sector_size {
+            let sector = file.sector(difat)?;
+            for i in 0..per_sector - 1 {
+                let s = le32(sector, i * 4);
+                if s < MAX_REGULAR_SECTOR {
+                    fat_sectors.push(s);
+                }
+            }
+            difat = le32(sector, (per_sector - 1) * 4);
+            guard += 1;
+        }
+        for s in fat_sectors {
+            let sector = file.sector(s)?;
+            file.fat
+                .extend((0..per_sector).map(|i| le32(sector, i * 4)));
+        }
+
+        let directory = file.read_chain(u32_at(0x30), None)?;
+        file.entries = directory
+            .chunks_exact(128)
+            .map(|e| {
+                let name_len = (u16::from_le_bytes([e[64], e[65]]) as usize).min(64);
+                let units: Vec<u16> = e[..name_len.saturating_sub(2)]
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                DirEntry {
+                    name: String::from_utf16_lossy(&units),
+                    kind: e[66],
+                    left: le32(e, 68),
+                    right: le32(e, 72),
+                    child: le32(e, 76),
+                    start: le32(e, 116),
+                    size: u64::from_le_bytes(e[120..128].try_into().unwrap_or_default()),
+                }
+            })
+            .collect();
+        let root = file
+            .entries
+            .first()
+            .filter(|e| e.kind == 5)
+            .ok_or_else(|| Error::Parse("Compound document has no root entry".to_string()))?;
+        let (root_start, root_size) = (root.start, root.size);
+
+        let mini_fat = file.read_chain(u32_at(0x3C), None)?;
+        file.mini_fat = mini_fat.chunks_exact(4).map(|c| le32(c, 0)).collect();
+        file.mini_stream = file.read_chain(root_start, Some(root_size))?;
+        Ok(file)
+    }
+
+    /// Contents of a regular sector
+    fn sector(&self, index: u32) -> Result<&'a [u8]> {
+        let start = (index as usize + 1) * self.sector_size;
+        self.data
+            .get(start..start + self.sector_size)
+            .ok_or_else(|| Error::Parse(format!("Compound document sector {} out of range", index)))
+    }
+
+    /// Follow a FAT chain, truncating to `size` when given
+    fn read_chain(&self, start: u32, size: Option<u64>) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut sector = start;
+        let mut steps = 0;
+        while sector != END_OF_CHAIN && sector < MAX_REGULAR_SECTOR {
+            if steps > self.fat.len() {
+                return Err(Error::Parse(
+                    "Compound document FAT chain loops".to_string(),
+                ));
+            }
+            out.extend_from_slice(self.sector(sector)?);
+            if size.is_some_and(|s| out.len() as u64 >= s) {
+                break;
+            }
+            sector = *self.fat.get(sector as usize).unwrap_or(&END_OF_CHAIN);
+            steps += 1;
+        }
+        if let Some(size) = size {
+            out.truncate(size as usize);
+        }
+        Ok(out)
+    }
+
+    /// Follow a mini FAT chain in the mini stream
+    fn read_mini_chain(&self, start: u32, size: u64) -> Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(size.min(self.mini_stream.len() as u64) as usize);
+        let mut sector = start;
+        let mut steps = 0;
+        while sector != END_OF_CHAIN && (out.len() as u64) < size {
+            if steps > self.mini_fat.len() {
+                return Err(Error::Parse(
+                    "Compound document mini FAT chain loops".to_string(),
+                ));
+            }
+            let begin = sector as usize * self.mini_sector_size;
+            let chunk = self
+                .mini_stream
+                .get(begin..begin + self.mini_sector_size)
+                .ok_or_else(|| Error::Parse("Mini sector out of range".to_string()))?;
+            out.extend_from_slice(chunk);
+            sector = *self.mini_fat.get(sector as usize).unwrap_or(&END_OF_CHAIN);
+            steps += 1;
+        }
+        out.truncate(size as usize);
+        Ok(out)
+    }
+
+    /// All streams as `(path, contents)`, paths joined with `/`
+    fn streams(&self) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut result = Vec::new();
+        let mut pending = vec![(self.entries[0].child, String::new())];
+        let mut visited = vec![false; self.entries.len()];
+
+        while let Some((index, prefix)) = pending.pop() {
+            if index == NO_STREAM || index as usize >= self.entries.len() {
+                continue;
+            }
+            if std::mem::replace(&mut visited[index as usize], true) {
+                continue;
+            }
+            let entry = &self.entries[index as usize];
+            pending.push((entry.left, prefix.clone()));
+            pending.push((entry.right, prefix.clone()));
+
+            let path = format!("{}{}", prefix, entry.name);
+            match entry.kind {
+                1 => pending.push((entry.child, format!("{}/", path))),
+                2 => {
+                    let contents = if entry.size < self.mini_cutoff {
+                        self.read_mini_chain(entry.start, entry.size)?
+                    } else {
+                        self.read_chain(entry.start, Some(entry.size))?
+                    };
+                    result.push((path, contents));
+                }
+                _ => {}
+            }
+        }
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(result)
+    }
+}
+
+fn le32(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::writers::pes::PesVersion;
+    use crate::utils::error::ErrorKind;
+
+    const SECTOR: usize = 512;
+
+    /// Minimal version 3 compound document with the given root-level streams
+    fn build_cfb(streams: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        // Sector 0: FAT, 1: directory, 2: mini FAT, then mini stream, then big streams
+        let mut mini_stream = Vec::new();
+        let mut mini_fat: Vec<u32> = Vec::new();
+        let mut big: Vec<(usize, &Vec<u8>)> = Vec::new();
+        let mut starts = Vec::new();
+        for (_, data) in streams {
+            if data.len() < 4096 {
+                let first = mini_fat.len() as u32;
+                let count = data.len().div_ceil(64).max(1);
+                for i in 0..count {
+                    mini_fat.push(if i + 1 == count {
+                        END_OF_CHAIN
+                    } else {
+                        first + i as u32 + 1
+                    });
+                }
+                let mut padded = data.clone();
+                padded.resize(count * 64, 0);
+                mini_stream.extend_from_slice(&padded);
+                starts.push(first);
+            } else {
+                big.push((starts.len(), data));
+                starts.push(0);
+            }
+        }
+
+        let mut fat = vec![0xFFFF_FFFDu32, END_OF_CHAIN, END_OF_CHAIN];
+        let mut sectors: Vec<Vec<u8>> = Vec::new();
+        let mut add_chain = |fat: &mut Vec<u32>, data: &[u8]| -> u32 {
+            let first = fat.len() as u32;
+            let count = data.len().div_ceil(SECTOR).max(1);
+            for i in 0..count {
+                fat.push(if i + 1 == count {
+                    END_OF_CHAIN
+                } else {
+                    first + i as u32 + 1
+                });
+                let mut sector =
+                    data[(i * SECTOR).min(data.len())..((i + 1) * SECTOR).min(data.len())].to_vec();
+                sector.resize(SECTOR, 0);
+                sectors.push(sector);
+            }
+            first
+        };
+        let mini_start = add_chain(&mut fat, &mini_stream);
+        for (index, data) in &big {
+            starts[*index] = add_chain(&mut fat, data);
+        }
+        assert!(fat.len() <= 128);
+        fat.resize(128, 0xFFFF_FFFF);
+
+        let entry = |name: &str, kind: u8, right: u32, child: u32, start: u32, size: u64| {
+            let mut e = vec![0u8; 128];
+            let units: Vec<u16> = name.encode_utf16().collect();
+            for (i, u) in units.iter().enumerate() {
+                e[i * 2..i * 2 + 2].copy_from_slice(&u.to_le_bytes());
+            }
+            e[64..66].copy_from_slice(&((units.len() as u16 + 1) * 2).to_le_bytes());
+            e[66] = kind;
+            e[68..72].copy_from_slice(&NO_STREAM.to_le_bytes());
+            e[72..76].copy_from_slice(&right.to_le_bytes());
+            e[76..80].copy_from_slice(&child.to_le_bytes());
+            e[116..120].copy_from_slice(&start.to_le_bytes());
+            e[120..128].copy_from_slice(&size.to_le_bytes());
+            e
+        };
+        let mut directory = entry(
+            "Root Entry",
+            5,
+            NO_STREAM,
+            1,
+            mini_start,
+            mini_stream.len() as u64,
+        );
+        for (i, (name, data)) in streams.iter().enumerate() {
+            let right = if i + 1 < streams.len() {
+                i as u32 + 2
+            } else {
+                NO_STREAM
+            };
+            directory.extend(entry(
+                name,
+                2,
+                right,
+                NO_STREAM,
+                starts[i],
+                data.len() as u64,
+            ));
+        }
+        assert!(directory.len() <= SECTOR);
+        directory.resize(SECTOR, 0);
+
+        let mut mini_fat_bytes: Vec<u8> = mini_fat.iter().flat_map(|v| v.to_le_bytes()).collect();
+        mini_fat_bytes.resize(SECTOR, 0xFF);
+
+        let mut header = vec![0u8; SECTOR];
+        header[..8].copy_from_slice(&CFB_SIGNATURE);
+        header[0x18..0x1A].copy_from_slice(&0x3Eu16.to_le_bytes());
+        header[0x1A..0x1C].copy_from_slice(&3u16.to_le_bytes());
+        header[0x1C..0x1E].copy_from_slice(&0xFFFEu16.to_le_bytes());
+        header[0x1E..0x20].copy_from_slice(&9u16.to_le_bytes());
+        header[0x20..0x22].copy_from_slice(&6u16.to_le_bytes());
+        header[0x2C..0x30].copy_from_slice(&1u32.to_le_bytes());
+        header[0x30..0x34].copy_from_slice(&1u32.to_le_bytes());
+        header[0x38..0x3C].copy_from_slice(&4096u32.to_le_bytes());
+        header[0x3C..0x40].copy_from_slice(&2u32.to_le_bytes());
+        header[0x40..0x44].copy_from_slice(&1u32.to_le_bytes());
+        header[0x44..0x48].copy_from_slice(&END_OF_CHAIN.to_le_bytes());
+        for i in 0..109 {
+            let value = if i == 0 { 0 } else { 0xFFFF_FFFF };
+            header[0x4C + i * 4..0x50 + i * 4].copy_from_slice(&u32::to_le_bytes(value));
+        }
+
+        let mut file = header;
+        file.extend(fat.iter().flat_map(|v| v.to_le_bytes()));
+        file.extend(directory);
+        file.extend(mini_fat_bytes);
+        for sector in sectors {
+            file.extend(sector);
+        }
+        file
+    }
+
+    /// SummaryInformation stream with a code page and string properties
+    fn summary(properties: &[(u32, &str)]) -> Vec<u8> {
+        let mut values: Vec<(u32, Vec<u8>)> =
+            vec![(1, [2u32.to_le_bytes(), [0xE4, 0x04, 0, 0]].concat())];
+        for &(id, text) in properties {
+            let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(text);
+            let mut value = 0x1Eu32.to_le_bytes().to_vec();
+            value.extend(((bytes.len() + 1) as u32).to_le_bytes());
+            value.extend_from_slice(&bytes);
+            value.push(0);
+            value.resize(value.len().div_ceil(4) * 4, 0);
+            values.push((id, value));
+        }
+
+        let mut section = Vec::new();
+        let table_len = 8 + values.len() * 8;
+        let mut offset = table_len;
+        let mut table = Vec::new();
+        for (id, value) in &values {
+            table.extend(id.to_le_bytes());
+            table.extend((offset as u32).to_le_bytes());
+            offset += value.len();
+        }
+        section.extend((offset as u32).to_le_bytes());
+        section.extend((values.len() as u32).to_le_bytes());
+        section.extend(table);
+        for (_, value) in values {
+            section.extend(value);
+        }
+
+        let mut stream = vec![0xFE, 0xFF, 0, 0, 0, 0, 0, 0];
+        stream.extend([0u8; 16]);
+        stream.extend(1u32.to_le_bytes());
+        stream.extend([0u8; 16]);
+        stream.extend(48u32.to_le_bytes());
+        stream.extend(section);
+        stream
+    }
+
+    // No real Wilcom file is available as a fixture yet, so these containers
+    // are built by hand around a PES stream.
+    fn embedded_pes() -> Vec<u8> {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::from_rgb(255, 0, 0));
+        pattern.add_thread(EmbThread::from_rgb(0, 0, 255));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 50.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(200.0, 50.0);
+        pattern.end();
+        let mut out = Cursor::new(Vec::new());
+        crate::formats::io::writers::pes::write_pes(&pattern, &mut out, PesVersion::V1, false)
+            .unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_read_embedded_stitches_and_summary() {
+        let data = build_cfb(&[
+            ("Contents", vec![0xAB; 5000]),
+            (
+                "\u{5}SummaryInformation",
+                summary(&[(2, "Caf\u{e9} Logo"), (4, "Jo"), (6, "Left chest")]),
+            ),
+            ("Embroidery", embedded_pes()),
+        ]);
+
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(data), &mut pattern).unwrap();
+
+        assert_eq!(pattern.count_stitches(), 3);
+        assert_eq!(pattern.threads().len(), 2);
+        assert_eq!(pattern.title(), Some("Caf\u{e9} Logo"));
+        assert_eq!(pattern.author(), Some("Jo"));
+        assert_eq!(pattern.description(), Some("Left chest"));
+    }
+
+    #[test]
+    fn test_container_streams() {
+        let big: Vec<u8> = (0..6000).map(|i| (i % 251) as u8).collect();
+        let data = build_cfb(&[("Small", b"hello".to_vec()), ("Big", big.clone())]);
+        let container = CompoundFile::parse(&data).unwrap();
+        let streams = container.streams().unwrap();
+        assert_eq!(
+            streams,
+            vec![
+                ("Big".to_string(), big),
+                ("Small".to_string(), b"hello".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_without_stitches_or_container() {
+        let data = build_cfb(&[("Contents", vec![1, 2, 3])]);
+        let err = read(&mut Cursor::new(data), &mut EmbPattern::new()).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unsupported(_)));
+        let err = read(&mut Cursor::new(vec![0u8; 600]), &mut EmbPattern::new()).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Parse(_)));
+
+        // Truncated container
+        let mut data = build_cfb(&[("Embroidery", embedded_pes())]);
+        data.truncate(1100);
+        assert!(read(&mut Cursor::new(data), &mut EmbPattern::new()).is_err());
+    }
+}
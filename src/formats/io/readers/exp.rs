@@ -12,19 +12,35 @@
 const MAX_STITCHES: usize = 1_000_000;
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::io::utils::read_block;
 use crate::utils::error::{Error, Result};
 use std::io::Read;
 
+/// Bytes decoded per block read (a multiple of the 2-byte record size)
+const BLOCK_SIZE: usize = 8192;
+
 /// Read EXP stitches
 fn read_stitches<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()> {
-    let mut buffer = [0u8; 2];
+    let mut block = vec![0u8; BLOCK_SIZE];
+    let mut start = 0;
+    let mut end = 0;
+    let mut eof = false;
     let mut stitch_count = 0;
 
     loop {
-        match reader.read_exact(&mut buffer) {
-            Ok(_) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(Error::from(e)),
+        // Control records are 4 bytes; refill before one could straddle the block end
+        if end - start < 4 && !eof {
+            block.copy_within(start..end, 0);
+            end -= start;
+            start = 0;
+            let n = read_block(reader, &mut block[end..])?;
+            eof = end + n < block.len();
+            end += n;
+        }
+
+        let record = &block[start..end];
+        if record.len() < 2 {
+            break;
         }
 
         // Check for excessive stitch count
@@ -36,25 +52,23 @@ fn read_stitches<R: Read>(reader: &mut R, pattern: &mut EmbPattern) -> Result<()
             )));
         }
 
-        if buffer[0] != 0x80 {
+        if record[0] != 0x80 {
             // Normal stitch
-            let x = buffer[0] as i8 as f64;
-            let y = -(buffer[1] as i8 as f64);
+            let x = record[0] as i8 as f64;
+            let y = -(record[1] as i8 as f64);
             pattern.stitch(x, y);
+            start += 2;
             continue;
         }
 
-        let control = buffer[1];
-
-        // Read next 2 bytes for coordinates
-        match reader.read_exact(&mut buffer) {
-            Ok(_) => {}
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(e) => return Err(Error::from(e)),
+        // Control byte is followed by a 2-byte coordinate record
+        if record.len() < 4 {
+            break;
         }
-
-        let x = buffer[0] as i8 as f64;
-        let y = -(buffer[1] as i8 as f64);
+        let control = record[1];
+        let x = record[2] as i8 as f64;
+        let y = -(record[3] as i8 as f64);
+        start += 4;
 
         match control {
             0x80 => {
@@ -95,6 +109,7 @@ pub fn read<R: Read>(reader: &mut R) -> Result<EmbPattern> {
 }
 
 /// Read an EXP file from path
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let mut reader = std::io::BufReader::new(file);
@@ -104,6 +119,7 @@ pub fn read_file(path: &str) -> Result<EmbPattern> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::constants::*;
 
     #[test]
     fn test_exp_basic() {
@@ -120,4 +136,22 @@ mod tests {
         let pattern = result.unwrap();
         assert!(!pattern.stitches().is_empty());
     }
+
+    #[test]
+    fn test_control_record_across_block_boundary() {
+        // Odd number of leading stitches puts the 4-byte jump record across the block end
+        let mut data = Vec::new();
+        for _ in 0..BLOCK_SIZE / 2 - 1 {
+            data.extend_from_slice(&[0x01, 0x00]);
+        }
+        data.extend_from_slice(&[0x80, 0x04, 0x10, 0xF0]); // Jump (16, 16)
+        data.extend_from_slice(&[0x02, 0x00]);
+
+        let pattern = read(&mut &data[..]).unwrap();
+        let stitches = pattern.stitches();
+        let jump = stitches[BLOCK_SIZE / 2 - 1];
+        assert_eq!(jump.command, JUMP);
+        assert_eq!((jump.x, jump.y), ((BLOCK_SIZE / 2 - 1 + 16) as f64, 16.0));
+        assert_eq!(stitches.len(), BLOCK_SIZE / 2 + 2);
+    }
 }
@@ -13,6 +13,7 @@
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::io::options::{NumberLocale, ReadOptions, ReadWarning};
 use crate::utils::error::{Error, Result};
 use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Read};
@@ -22,8 +23,11 @@ const MAX_GCODE_STITCHES: usize = 10_000_000; // Safety limit for stitch count
 const MAX_GCODE_LINES: usize = 20_000_000; // Safety limit for line count
 
 /// Parse a single line of G-code
-fn parse_gcode_line(line: &str) -> HashMap<String, f64> {
+///
+/// Returns the word values and the words whose numbers could not be parsed.
+fn parse_gcode_line(line: &str, locale: NumberLocale) -> (HashMap<String, f64>, Vec<String>) {
     let mut map = HashMap::new();
+    let mut invalid = Vec::new();
     let mut comment = String::new();
     let mut in_comment = false;
     let mut code = String::new();
@@ -49,9 +53,7 @@ fn parse_gcode_line(line: &str) -> HashMap<String, f64> {
 
         if ch == ' ' || ch == '\t' || ch == '\n' || ch == '\r' {
             if !code.is_empty() && !value.is_empty() {
-                if let Ok(v) = value.parse::<f64>() {
-                    map.insert(code.to_lowercase(), v);
-                }
+                insert_word(&mut map, &mut invalid, &code, &value, locale);
                 code.clear();
                 value.clear();
             }
@@ -60,22 +62,19 @@ fn parse_gcode_line(line: &str) -> HashMap<String, f64> {
 
         if ch.is_ascii_alphabetic() {
             if !code.is_empty() && !value.is_empty() {
-                if let Ok(v) = value.parse::<f64>() {
-                    map.insert(code.to_lowercase(), v);
-                }
+                insert_word(&mut map, &mut invalid, &code, &value, locale);
                 value.clear();
             }
             code = ch.to_lowercase().to_string();
-        } else if ch.is_ascii_digit() || ch == '.' || ch == '-' || ch == '+' {
+        } else if locale.is_numeric_char(ch) || (ch == ',' && !value.is_empty()) {
+            // A comma inside a dot-locale number makes it unparseable
             value.push(ch);
         }
     }
 
     // Don't forget the last code/value pair
     if !code.is_empty() && !value.is_empty() {
-        if let Ok(v) = value.parse::<f64>() {
-            map.insert(code.to_lowercase(), v);
-        }
+        insert_word(&mut map, &mut invalid, &code, &value, locale);
     }
 
     // Store comment text if we found one
@@ -96,7 +95,33 @@ fn parse_gcode_line(line: &str) -> HashMap<String, f64> {
         }
     }
 
-    map
+    (map, invalid)
+}
+
+/// Store a word's value, or record the word if its number does not parse
+fn insert_word(
+    map: &mut HashMap<String, f64>,
+    invalid: &mut Vec<String>,
+    code: &str,
+    value: &str,
+    locale: NumberLocale,
+) {
+    match locale.parse_f64(value) {
+        Some(v) => {
+            map.insert(code.to_lowercase(), v);
+        }
+        None => invalid.push(format!("{}{}", code.to_uppercase(), value)),
+    }
+}
+
+/// Push a warning for a run of lines with unparseable words
+fn flush_invalid(invalid: &mut Option<(usize, usize, String)>, warnings: &mut Vec<ReadWarning>) {
+    if let Some((first, count, word)) = invalid.take() {
+        warnings.push(ReadWarning::new(format!(
+            "Ignored unparseable values on {} line(s) starting at line {} (first: '{}')",
+            count, first, word
+        )));
+    }
 }
 
 /// Read G-code format file into a pattern
@@ -117,6 +142,20 @@ fn parse_gcode_line(line: &str) -> HashMap<String, f64> {
 /// butabuti::formats::io::readers::gcode::read(&mut file, &mut pattern).unwrap();
 /// ```
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    read_with_options(file, pattern, &ReadOptions::default())?;
+    Ok(())
+}
+
+/// Read G-code format file with explicit read options
+///
+/// The number locale controls whether coordinates such as `X1,25` are accepted.
+/// Words whose numbers cannot be parsed are ignored and reported as warnings;
+/// a move that loses its X or Y this way is skipped.
+pub fn read_with_options(
+    file: &mut impl Read,
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+) -> Result<Vec<ReadWarning>> {
     let reader = BufReader::new(file);
     let mut absolute_mode = true;
     let flip_x = -1.0; // G-code typically uses flipped X
@@ -124,6 +163,8 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
     let mut scale = 10.0; // Default to mm mode (10 units per mm)
     let mut line_count = 0;
     let mut stitch_count = 0;
+    let mut warnings = Vec::new();
+    let mut invalid_run = None;
 
     for line in reader.lines() {
         line_count += 1;
@@ -135,7 +176,14 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
         }
 
         let line = line?;
-        let gc = parse_gcode_line(&line);
+        let (gc, invalid) = parse_gcode_line(&line, options.number_locale);
+        match invalid.into_iter().next() {
+            Some(word) => {
+                let (first, count, word) = invalid_run.unwrap_or((line_count, 0, word));
+                invalid_run = Some((first, count + 1, word));
+            }
+            None => flush_invalid(&mut invalid_run, &mut warnings),
+        }
 
         if gc.is_empty() {
             continue;
@@ -188,14 +236,15 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
             if m_val == 30.0 || m_val == 2.0 {
                 pattern.end();
             }
-            // M00/M01 - Stop/Optional stop (color change)
-            else if m_val == 0.0 || m_val == 1.0 {
+            // M00/M01 - Stop/Optional stop, M600 - Marlin filament change (color change)
+            else if m_val == 0.0 || m_val == 1.0 || m_val == 600.0 {
                 pattern.add_stitch_relative(0.0, 0.0, COLOR_CHANGE);
             }
         }
     }
+    flush_invalid(&mut invalid_run, &mut warnings);
 
-    Ok(())
+    Ok(warnings)
 }
 
 #[cfg(test)]
@@ -206,13 +255,55 @@ mod tests {
     #[test]
     fn test_parse_gcode_line() {
         let line = "G00 X10.5 Y20.3";
-        let parsed = parse_gcode_line(line);
+        let (parsed, invalid) = parse_gcode_line(line, NumberLocale::Dot);
+        assert!(invalid.is_empty());
 
         assert_eq!(parsed.get("g"), Some(&0.0));
         assert_eq!(parsed.get("x"), Some(&10.5));
         assert_eq!(parsed.get("y"), Some(&20.3));
     }
 
+    #[test]
+    fn test_parse_gcode_line_comma_decimals() {
+        let line = "G01\tX10,5  Y-20,25 ; comment";
+        let (parsed, _) = parse_gcode_line(line, NumberLocale::Comma);
+
+        assert_eq!(parsed.get("g"), Some(&1.0));
+        assert_eq!(parsed.get("x"), Some(&10.5));
+        assert_eq!(parsed.get("y"), Some(&-20.25));
+    }
+
+    #[test]
+    fn test_read_gcode_comma_locale() {
+        let gcode = "G21\nG90\nG00 X1,5 Y2,0\nM30\n";
+        let options = ReadOptions::new().number_locale(NumberLocale::Auto);
+        let mut pattern = EmbPattern::new();
+
+        read_with_options(&mut Cursor::new(gcode), &mut pattern, &options).unwrap();
+
+        assert_eq!(pattern.stitches()[0].x, -15.0);
+        assert_eq!(pattern.stitches()[0].y, -20.0);
+    }
+
+    #[test]
+    fn test_unparseable_values_are_reported() {
+        let gcode = "G21\nG00 X1,5 Y2,0\nG00 X3,5 Y4\nG00 X1.0 Y1.0\nG00 X1..2 Y1.0\nM30\n";
+        let mut pattern = EmbPattern::new();
+        let warnings =
+            read_with_options(&mut Cursor::new(gcode), &mut pattern, &ReadOptions::new()).unwrap();
+
+        // Only the fully parsed move becomes a stitch
+        assert_eq!(pattern.count_stitches(), 1);
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Ignored unparseable values on 2 line(s) starting at line 2 (first: 'X1,5')",
+                "Ignored unparseable values on 1 line(s) starting at line 5 (first: 'X1..2')",
+            ]
+        );
+    }
+
     #[test]
     fn test_read_gcode_basic() {
         let gcode = "\
@@ -13,20 +13,55 @@
 //!   - Y coordinates (delta-encoded, signed 8-bit after decompression)
 //!
 //! All coordinates are in 0.1mm units (same as DST).
+//!
+//! VIP files share the header and stitch streams but store their colors in an
+//! XOR-obfuscated table. They are recognized by their magic code and read
+//! without threads; the colors are not decoded.
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
 use crate::palettes::thread_hus;
 use crate::utils::compress;
-use crate::utils::error::{ErrorWithContext, Result, ResultExt};
+use crate::utils::error::{Error, ErrorWithContext, Result, ResultExt};
 use std::io::{Read, Seek, SeekFrom};
 
+/// VIP file signature (same layout as HUS apart from the color table)
+pub(crate) const VIP_MAGIC: u32 = 0x0190_FC5D;
+
+/// Maximum allowed stitch count
+const MAX_STITCHES: u32 = 1_000_000;
+
+/// Maximum allowed thread count
+const MAX_COLORS: u32 = 1_000;
+
 /// Read HUS (Husqvarna Viking) format
+///
+/// VIP files are read through the same path; their obfuscated color table is
+/// skipped, so the pattern has no threads.
 pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<()> {
     // Read header
-    let _magic_code = read_int_32le(file).with_context("Reading HUS magic code")?;
+    let magic_code = read_int_32le(file).with_context("Reading HUS magic code")?;
     let number_of_stitches = read_int_32le(file).with_context("Reading stitch count")?;
     let number_of_colors = read_int_32le(file).with_context("Reading color count")?;
+    if number_of_stitches > MAX_STITCHES {
+        return Err(Error::limit_exceeded(
+            "HUS stitch count",
+            MAX_STITCHES as usize,
+            number_of_stitches as usize,
+        )
+        .in_format(Format::HUS)
+        .at_offset(4));
+    }
+    if number_of_colors > MAX_COLORS {
+        return Err(Error::limit_exceeded(
+            "HUS color count",
+            MAX_COLORS as usize,
+            number_of_colors as usize,
+        )
+        .in_format(Format::HUS)
+        .at_offset(8));
+    }
 
     let _extend_pos_x = read_int_16le(file)? as i16;
     let _extend_pos_y = read_int_16le(file)? as i16;
@@ -43,16 +78,22 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
 
     let _unknown_16_bit = read_int_16le(file)?;
 
-    // Read thread palette
+    // Read thread palette; the VIP color table is skipped since the stitch
+    // streams are located by the header offsets
     let hus_thread_set = thread_hus::get_thread_set();
-    for color_index in 0..number_of_colors {
+    let palette_size = if magic_code == VIP_MAGIC {
+        0
+    } else {
+        number_of_colors
+    };
+    for color_index in 0..palette_size {
         let index = read_int_16le(file)
             .with_context(format!("Reading thread index {}", color_index))?
             as usize;
         if index < hus_thread_set.len() {
             pattern.add_thread(hus_thread_set[index].clone());
         } else {
-            return Err(crate::utils::error::Error::parse(format!(
+            return Err(Error::parse(format!(
                 "Invalid thread palette index: {} (max: {})",
                 index,
                 hus_thread_set.len() - 1
@@ -62,25 +103,31 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
     }
 
     // Read compressed data sections
+    let section_size = |start: u32, end: u32| {
+        end.checked_sub(start).map(u64::from).ok_or_else(|| {
+            Error::parse(format!("Section offsets out of order: {} > {}", start, end))
+                .with_context("Reading HUS header offsets")
+        })
+    };
+
     file.seek(SeekFrom::Start(command_offset as u64))?;
-    let command_size = (x_offset - command_offset) as usize;
-    let mut command_compressed = vec![0u8; command_size];
-    file.read_exact(&mut command_compressed).map_err(|e| {
-        crate::utils::error::Error::from(e).with_context("Reading compressed command data")
-    })?;
+    let command_size = section_size(command_offset, x_offset)?;
+    let mut command_compressed = Vec::new();
+    file.take(command_size)
+        .read_to_end(&mut command_compressed)
+        .map_err(|e| Error::from(e).with_context("Reading compressed command data"))?;
 
     file.seek(SeekFrom::Start(x_offset as u64))?;
-    let x_size = (y_offset - x_offset) as usize;
-    let mut x_compressed = vec![0u8; x_size];
-    file.read_exact(&mut x_compressed).map_err(|e| {
-        crate::utils::error::Error::from(e).with_context("Reading compressed X coordinate data")
-    })?;
+    let x_size = section_size(x_offset, y_offset)?;
+    let mut x_compressed = Vec::new();
+    file.take(x_size)
+        .read_to_end(&mut x_compressed)
+        .map_err(|e| Error::from(e).with_context("Reading compressed X coordinate data"))?;
 
     file.seek(SeekFrom::Start(y_offset as u64))?;
     let mut y_compressed = Vec::new();
-    file.read_to_end(&mut y_compressed).map_err(|e| {
-        crate::utils::error::Error::from(e).with_context("Reading compressed Y coordinate data")
-    })?;
+    file.read_to_end(&mut y_compressed)
+        .map_err(|e| Error::from(e).with_context("Reading compressed Y coordinate data"))?;
 
     // Decompress data using Level 4 Huffman (matching embroidery-rust implementation)
     let command_decompressed =
@@ -111,7 +158,7 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
         || x_decompressed.len() != number_of_stitches as usize
         || y_decompressed.len() != number_of_stitches as usize
     {
-        return Err(crate::utils::error::Error::parse(format!(
+        return Err(Error::parse(format!(
             "Decompressed data size mismatch: commands={}, x={}, y={}, expected={}",
             command_decompressed.len(),
             x_decompressed.len(),
@@ -146,14 +193,14 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
                 if x != 0.0 || y != 0.0 {
                     pattern.add_stitch_relative(x, y, STITCH);
                 }
-                pattern.add_command(COLOR_CHANGE, 0.0, 0.0);
+                pattern.add_stitch_relative(0.0, 0.0, COLOR_CHANGE);
             }
             0x88 => {
                 // TRIM
                 if x != 0.0 || y != 0.0 {
                     pattern.add_stitch_relative(x, y, JUMP);
                 }
-                pattern.add_command(TRIM, 0.0, 0.0);
+                pattern.add_stitch_relative(0.0, 0.0, TRIM);
             }
             0x90 => {
                 // END
@@ -161,7 +208,7 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
             }
             _ => {
                 // Unknown command - log warning and stop
-                return Err(crate::utils::error::Error::parse(format!(
+                return Err(Error::parse(format!(
                     "Unknown command byte 0x{:02X} at stitch {}",
                     cmd, i
                 ))
@@ -170,7 +217,7 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
         }
     }
 
-    pattern.add_command(END, 0.0, 0.0);
+    pattern.add_stitch_relative(0.0, 0.0, END);
     Ok(())
 }
 
@@ -238,6 +285,32 @@ mod tests {
         assert_eq!(pattern.threads().len(), 1);
     }
 
+    #[test]
+    fn test_absurd_header_counts() {
+        let header = |stitches: u32, colors: u32| {
+            let mut data = vec![0u8; 4];
+            data.extend_from_slice(&stitches.to_le_bytes());
+            data.extend_from_slice(&colors.to_le_bytes());
+            data
+        };
+
+        let err = read(
+            &mut Cursor::new(header(u32::MAX, 1)),
+            &mut EmbPattern::new(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code().as_str(), "parse.limit_exceeded");
+        assert_eq!(err.location().offset, Some(4));
+
+        let err = read(
+            &mut Cursor::new(header(10, u32::MAX)),
+            &mut EmbPattern::new(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code().as_str(), "parse.limit_exceeded");
+        assert_eq!(err.location().offset, Some(8));
+    }
+
     #[test]
     fn test_hus_basic_structure() {
         // Test the basic structure parsing
@@ -365,6 +438,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_vip_skips_color_table() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(thread_hus::get_thread_set()[3].clone());
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, -50.0);
+        pattern.end();
+        let mut hus = Vec::new();
+        crate::formats::io::writers::hus::write(&pattern, &mut hus).unwrap();
+
+        // Replace the one-entry HUS palette with an opaque 4-byte color entry
+        // behind a 32-bit length, shifting the stream offsets to match
+        let mut vip = VIP_MAGIC.to_le_bytes().to_vec();
+        vip.extend_from_slice(&hus[4..0x14]);
+        for offset in hus[0x14..0x20].chunks(4) {
+            let offset = u32::from_le_bytes(offset.try_into().unwrap()) + 6;
+            vip.extend_from_slice(&offset.to_le_bytes());
+        }
+        vip.extend_from_slice(&hus[0x20..0x2A]);
+        vip.extend_from_slice(&4u32.to_le_bytes());
+        vip.extend_from_slice(&[0xE5, 0x17, 0x9A, 0x42]);
+        vip.extend_from_slice(&hus[0x2C..]);
+
+        let mut read_pattern = EmbPattern::new();
+        read(&mut Cursor::new(vip), &mut read_pattern).unwrap();
+        assert!(read_pattern.threads().is_empty());
+        assert_eq!(read_pattern.count_stitches(), pattern.count_stitches());
+        assert_eq!(read_pattern.bounds(), pattern.bounds());
+    }
+
     #[test]
     fn test_hus_command_types() {
         // Test that all HUS command types are recognized
@@ -403,6 +506,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_hus_decompression_size_validation() {
         // Test that mismatched decompressed sizes are caught
         // This would require creating valid compressed data that decompresses to wrong size
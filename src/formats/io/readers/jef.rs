@@ -1,7 +1,8 @@
 //! Janome JEF format reader
 //!
 //! JEF is the Janome Embroidery Format with a binary header containing design bounds,
-//! hoop information, and thread colors from the predefined JEF palette.
+//! hoop information, and thread colors from the predefined JEF palette. The hoop
+//! is kept as the `jef_hoop` metadata (e.g. `126x110`), which the writer reuses.
 //!
 //! ## Format Limitations
 //! - Stitch offset must be within 0-100MB range (100,000,000 bytes)
@@ -18,7 +19,10 @@ const MAX_COLORS: usize = 1000;
 const MAX_STITCHES: usize = 1_000_000;
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, ReadWarning};
 use crate::formats::io::utils::ReadHelper;
+use crate::formats::io::writers::jef::JefHoop;
 use crate::palettes::thread_jef::JEF_THREADS;
 use crate::utils::error::{Error, Result};
 use std::collections::HashMap;
@@ -45,10 +49,11 @@ fn read_stitches<R: Read>(
         // Check for excessive stitch count
         stitch_count += 1;
         if stitch_count > MAX_STITCHES {
-            return Err(Error::Parse(format!(
-                "JEF file exceeds maximum stitch count of {}",
-                MAX_STITCHES
-            )));
+            return Err(
+                Error::limit_exceeded("JEF stitch count", MAX_STITCHES, stitch_count)
+                    .in_format(Format::JEF)
+                    .at_stitch(stitch_count - 1),
+            );
         }
 
         if buffer[0] != 0x80 {
@@ -131,53 +136,112 @@ fn read_stitches<R: Read>(
     Ok(())
 }
 
-/// Read a JEF file
-pub fn read<R: Read + Seek>(
-    reader: &mut R,
-    settings: Option<HashMap<String, String>>,
-) -> Result<EmbPattern> {
-    let mut pattern = EmbPattern::new();
-    let settings = settings.unwrap_or_default();
-
-    let mut helper = ReadHelper::new(reader);
+/// Fixed fields at the start of a JEF file
+pub(crate) struct JefHeader {
+    /// Offset of the stitch records
+    pub stitch_offset: u64,
+    /// Number of entries in the thread table
+    pub color_count: usize,
+    /// Number of stitch records, as recorded by the writer
+    pub point_count: usize,
+    /// Hoop code
+    pub hoop: i32,
+    /// Design extents from the hoop center: left, top, right, bottom
+    pub extents: [i32; 4],
+}
 
+/// Read the JEF header up to the design extents
+///
+/// Leaves the reader at the hoop edge distances, 64 bytes before the thread table.
+pub(crate) fn read_header<R: Read>(helper: &mut ReadHelper<R>) -> Result<JefHeader> {
     // Read stitch offset
     let stitch_offset = helper.read_i32_le()?;
 
     // Validate stitch offset is reasonable
     if !(0..=MAX_STITCH_OFFSET).contains(&stitch_offset) {
-        return Err(Error::Parse(format!(
-            "Invalid JEF stitch offset: {} (must be between 0 and {})",
-            stitch_offset, MAX_STITCH_OFFSET
-        )));
+        return Err(Error::unexpected_value(
+            "JEF stitch offset",
+            format!("0 to {}", MAX_STITCH_OFFSET),
+            stitch_offset.to_string(),
+        )
+        .in_format(Format::JEF)
+        .at_offset(0));
     }
 
-    // Skip 20 bytes
+    // Skip flags and date
     helper.read_bytes(20)?;
 
     // Read color count
-    let count_colors = helper.read_i32_le()? as usize;
+    let color_count = helper.read_i32_le()? as usize;
 
     // Validate color count is reasonable
-    if count_colors > MAX_COLORS {
-        return Err(Error::Parse(format!(
-            "Invalid JEF color count: {} (must be <= {})",
-            count_colors, MAX_COLORS
-        )));
+    if color_count > MAX_COLORS {
+        return Err(
+            Error::limit_exceeded("JEF color count", MAX_COLORS, color_count)
+                .in_format(Format::JEF)
+                .at_offset(24),
+        );
     }
 
-    // Skip 88 bytes
-    helper.read_bytes(88)?;
+    let point_count = helper.read_i32_le()?.max(0) as usize;
+
+    let hoop = helper.read_i32_le()?;
+
+    let mut extents = [0; 4];
+    for extent in &mut extents {
+        *extent = helper.read_i32_le()?;
+    }
+
+    Ok(JefHeader {
+        stitch_offset: stitch_offset as u64,
+        color_count,
+        point_count,
+        hoop,
+        extents,
+    })
+}
+
+/// Read a JEF file
+pub fn read<R: Read + Seek>(
+    reader: &mut R,
+    settings: Option<HashMap<String, String>>,
+) -> Result<EmbPattern> {
+    let (pattern, _warnings) = read_with_options(reader, settings, &ReadOptions::default())?;
+    Ok(pattern)
+}
+
+/// Read a JEF file with explicit read options
+///
+/// Thread indices outside the JEF palette are handled according to `options`.
+/// Returns the pattern together with any warnings raised while reading.
+pub fn read_with_options<R: Read + Seek>(
+    reader: &mut R,
+    settings: Option<HashMap<String, String>>,
+    options: &ReadOptions,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+    let mut pattern = EmbPattern::new();
+    let mut warnings = Vec::new();
+    let settings = settings.unwrap_or_default();
+
+    let mut helper = ReadHelper::new(reader);
+    let header = read_header(&mut helper)?;
+    if let Some(hoop) = JefHoop::from_code(header.hoop) {
+        pattern.add_metadata("jef_hoop", hoop.to_string());
+    }
+
+    // Skip the hoop edge distances
+    helper.read_bytes(64)?;
 
     // Read thread indices
-    for _ in 0..count_colors {
+    for _ in 0..header.color_count {
         let index = helper.read_i32_le()?.unsigned_abs() as usize;
 
         if index == 0 {
             // Color 0 is a placeholder/stop - skip adding thread
             // but we need to track it for color changes
         } else {
-            let thread_idx = index % JEF_THREADS.len();
+            let thread_idx =
+                options.resolve_palette_index(index, JEF_THREADS.len(), &mut warnings)?;
             if let Some(thread_ref) = &JEF_THREADS[thread_idx] {
                 pattern.add_thread(thread_ref.clone());
             }
@@ -186,14 +250,15 @@ pub fn read<R: Read + Seek>(
 
     // Seek to stitch data
     let mut reader = helper.into_inner();
-    reader.seek(SeekFrom::Start(stitch_offset as u64))?;
+    reader.seek(SeekFrom::Start(header.stitch_offset))?;
 
-    read_stitches(&mut reader, &mut pattern, count_colors, &settings)?;
+    read_stitches(&mut reader, &mut pattern, header.color_count, &settings)?;
 
-    Ok(pattern)
+    Ok((pattern, warnings))
 }
 
 /// Read a JEF file from path
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let mut reader = std::io::BufReader::new(file);
@@ -203,6 +268,8 @@ pub fn read_file(path: &str) -> Result<EmbPattern> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::formats::io::options::PaletteIndexPolicy;
+    use std::io::Cursor;
 
     #[test]
     fn test_jef_threads() {
@@ -211,4 +278,52 @@ mod tests {
         assert!(JEF_THREADS[0].is_none()); // Placeholder
         assert!(JEF_THREADS[1].is_some()); // Black
     }
+
+    /// Build a minimal JEF file with the given thread indices
+    fn malformed_jef(indices: &[i32]) -> Vec<u8> {
+        let stitch_offset = 116 + 4 * indices.len() as i32;
+        let mut data = Vec::new();
+        data.extend_from_slice(&stitch_offset.to_le_bytes());
+        data.extend_from_slice(&[0u8; 20]);
+        data.extend_from_slice(&(indices.len() as i32).to_le_bytes());
+        data.extend_from_slice(&[0u8; 88]);
+        for index in indices {
+            data.extend_from_slice(&index.to_le_bytes());
+        }
+        data.extend_from_slice(&[0x0A, 0x0A, 0x05, 0x05, 0x80, 0x10]);
+        data
+    }
+
+    #[test]
+    fn test_out_of_range_thread_clamped() {
+        let data = malformed_jef(&[2, 200]);
+        let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Clamp);
+        let (pattern, warnings) =
+            read_with_options(&mut Cursor::new(data), None, &options).unwrap();
+
+        let last = JEF_THREADS[JEF_THREADS.len() - 1].as_ref().unwrap();
+        assert_eq!(pattern.threads().len(), 2);
+        assert_eq!(pattern.threads()[1].color, last.color);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_out_of_range_thread_wrapped() {
+        let data = malformed_jef(&[200]);
+        let (pattern, warnings) =
+            read_with_options(&mut Cursor::new(data), None, &ReadOptions::default()).unwrap();
+
+        let expected = JEF_THREADS[200 % JEF_THREADS.len()]
+            .as_ref()
+            .map(|t| t.color);
+        assert_eq!(pattern.threads().first().map(|t| t.color), expected);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_out_of_range_thread_rejected() {
+        let data = malformed_jef(&[200]);
+        let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Reject);
+        assert!(read_with_options(&mut Cursor::new(data), None, &options).is_err());
+    }
 }
@@ -12,8 +12,9 @@
 //! - **Precision**: Floating-point coordinates may lose precision
 //! - **File size**: Typically 5-10x larger than equivalent binary formats
 
+use crate::core::attributes::Attributes;
 use crate::core::constants::*;
-use crate::core::pattern::EmbPattern;
+use crate::core::pattern::{EmbPattern, License};
 use crate::core::thread::EmbThread;
 use crate::utils::error::{Error, Result};
 use serde::{Deserialize, Serialize};
@@ -30,6 +31,9 @@ struct JsonPattern {
     #[serde(default)]
     metadata: HashMap<String, String>,
 
+    #[serde(default)]
+    license: Option<License>,
+
     #[serde(default)]
     threads: Vec<JsonThread>,
 
@@ -61,6 +65,14 @@ struct JsonStitch {
     command: String,
     x: f64,
     y: f64,
+
+    /// Extended command bits (thread, needle, order, chenille loop height)
+    #[serde(default)]
+    flags: Option<u32>,
+
+    /// Extended attributes of the stitch
+    #[serde(default)]
+    attributes: Option<Attributes>,
 }
 
 /// Read a JSON embroidery pattern
@@ -92,6 +104,10 @@ pub fn read<R: Read>(reader: &mut R) -> Result<EmbPattern> {
         pattern.add_metadata(&key, &value);
     }
 
+    if let Some(license) = &json_pattern.license {
+        pattern.set_license(license);
+    }
+
     // Add threads
     for json_thread in json_pattern.threads {
         let color = parse_color(&json_thread.color)?;
@@ -115,8 +131,13 @@ pub fn read<R: Read>(reader: &mut R) -> Result<EmbPattern> {
 
     // Add stitches
     for json_stitch in json_pattern.stitches {
-        let command = parse_command(&json_stitch.command)?;
+        let command =
+            parse_command(&json_stitch.command)? | (json_stitch.flags.unwrap_or(0) & !COMMAND_MASK);
         pattern.add_stitch_absolute(command, json_stitch.x, json_stitch.y);
+        let index = pattern.stitches().len() - 1;
+        for (key, value) in json_stitch.attributes.into_iter().flatten() {
+            pattern.attributes_mut().set(index, key, value);
+        }
     }
 
     Ok(pattern)
@@ -164,11 +185,17 @@ fn parse_command(cmd_str: &str) -> Result<u32> {
         "FAST" => Ok(FAST),
         "SEQUIN_MODE" => Ok(SEQUIN_MODE),
         "SEQUIN_EJECT" => Ok(SEQUIN_EJECT),
+        "CHENILLE_LOOP_HEIGHT" => Ok(CHENILLE_LOOP_HEIGHT),
+        "CORDING_ON" => Ok(CORDING_ON),
+        "CORDING_OFF" => Ok(CORDING_OFF),
+        "BORER_ON" => Ok(BORER_ON),
+        "BORER_OFF" => Ok(BORER_OFF),
         _ => Err(Error::Parse(format!("Unknown command: {}", cmd_str))),
     }
 }
 
 /// Read JSON file from path
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let reader = std::io::BufReader::new(file);
@@ -7,23 +7,30 @@
 //! - Uses 64-color PEC thread palette (indices 0-63)
 //! - Maximum 1,000,000 stitches per file
 //! - Stitch encoding: 7-bit or 12-bit signed deltas with control flags
+//!
+//! With [`ReadOptions::lenient`] a stitch block cut off before its end marker,
+//! a stitch count over the limit and truncated preview graphics are reported
+//! as warnings and the stitches read so far are kept.
 
 /// Maximum allowed stitch count
 const MAX_STITCHES: usize = 1_000_000;
 
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, ReadWarning};
 use crate::formats::io::utils::ReadHelper;
 use crate::palettes::thread_pec::PEC_THREADS;
 use crate::utils::error::{Error, Result};
-use std::io::{Read, Seek};
+use crate::utils::string::decode_text;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
 
-const JUMP_CODE: u8 = 0x10;
-const TRIM_CODE: u8 = 0x20;
-const FLAG_LONG: u8 = 0x80;
+pub(crate) const JUMP_CODE: u8 = 0x10;
+pub(crate) const TRIM_CODE: u8 = 0x20;
+pub(crate) const FLAG_LONG: u8 = 0x80;
 
 /// Convert 12-bit signed value
-fn signed12(b: u16) -> i32 {
+pub(crate) fn signed12(b: u16) -> i32 {
     let b = b & 0xFFF;
     if b > 0x7FF {
         (b as i32) - 0x1000
@@ -33,7 +40,7 @@ fn signed12(b: u16) -> i32 {
 }
 
 /// Convert 7-bit signed value
-fn signed7(b: u8) -> i32 {
+pub(crate) fn signed7(b: u8) -> i32 {
     if b > 63 {
         (b as i32) - 128
     } else {
@@ -42,18 +49,22 @@ fn signed7(b: u8) -> i32 {
 }
 
 /// Process PEC color bytes using the PEC palette
-fn process_pec_colors(color_bytes: &[u8], pattern: &mut EmbPattern) -> Vec<EmbThread> {
-    let max_value = PEC_THREADS.len();
+fn process_pec_colors(
+    color_bytes: &[u8],
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<Vec<EmbThread>> {
     let mut threads = Vec::new();
 
     for &byte in color_bytes {
-        let idx = (byte as usize) % max_value;
+        let idx = options.resolve_palette_index(byte as usize, PEC_THREADS.len(), warnings)?;
         let thread = PEC_THREADS[idx].clone();
         pattern.add_thread(thread.clone());
         threads.push(thread);
     }
 
-    threads
+    Ok(threads)
 }
 
 /// Process PEC color bytes with PES thread chart
@@ -61,14 +72,16 @@ fn process_pec_table(
     color_bytes: &[u8],
     pattern: &mut EmbPattern,
     chart: &mut Vec<EmbThread>,
-) -> Vec<EmbThread> {
-    let max_value = PEC_THREADS.len();
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<Vec<EmbThread>> {
     let mut thread_map: std::collections::HashMap<usize, EmbThread> =
         std::collections::HashMap::new();
     let mut threads = Vec::new();
 
     for &byte in color_bytes {
-        let color_index = (byte as usize) % max_value;
+        let color_index =
+            options.resolve_palette_index(byte as usize, PEC_THREADS.len(), warnings)?;
 
         let thread = if let Some(t) = thread_map.get(&color_index) {
             t.clone()
@@ -86,7 +99,7 @@ fn process_pec_table(
         threads.push(thread);
     }
 
-    threads
+    Ok(threads)
 }
 
 /// Map PEC colors to threads
@@ -94,10 +107,12 @@ fn map_pec_colors(
     color_bytes: &[u8],
     pattern: &mut EmbPattern,
     pes_chart: Option<&mut Vec<EmbThread>>,
-) -> Vec<EmbThread> {
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<Vec<EmbThread>> {
     if let Some(chart) = pes_chart {
         if chart.is_empty() {
-            process_pec_colors(color_bytes, pattern)
+            process_pec_colors(color_bytes, pattern, options, warnings)
         } else if chart.len() >= color_bytes.len() {
             // 1:1 mode
             let mut threads = Vec::new();
@@ -108,40 +123,87 @@ fn map_pec_colors(
                     threads.push(thread);
                 }
             }
-            threads
+            Ok(threads)
         } else {
-            process_pec_table(color_bytes, pattern, chart)
+            process_pec_table(color_bytes, pattern, chart, options, warnings)
         }
     } else {
-        process_pec_colors(color_bytes, pattern)
+        process_pec_colors(color_bytes, pattern, options, warnings)
+    }
+}
+
+/// Read the next byte of a stitch block, `None` where the stream ends
+///
+/// Reading leniently, the end of the stream is reported as a warning. Otherwise
+/// it is an error at a record boundary and ends the block quietly mid-record.
+fn next_byte<R: Read + Seek>(
+    reader: &mut ReadHelper<R>,
+    boundary: bool,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<Option<u8>> {
+    match reader.read_u8() {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof && options.lenient => {
+            warnings.push(ReadWarning::at(
+                reader.inner_mut().stream_position()?,
+                "Stitch block ends without an end marker, keeping the stitches read",
+            ));
+            Ok(None)
+        }
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof && !boundary => Ok(None),
+        Err(e) => Err(e.into()),
     }
 }
 
 /// Read PEC stitches
-fn read_pec_stitches<R: Read>(reader: &mut ReadHelper<R>, pattern: &mut EmbPattern) -> Result<()> {
+fn read_pec_stitches<R: Read + Seek>(
+    reader: &mut ReadHelper<R>,
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<()> {
     let mut stitch_count = 0;
     loop {
         // Check for excessive stitch count
         stitch_count += 1;
         if stitch_count > MAX_STITCHES {
-            return Err(Error::Parse(format!(
-                "PEC file exceeds maximum stitch count of {}",
-                MAX_STITCHES
-            )));
+            let offset = reader.inner_mut().stream_position()?;
+            if !options.lenient {
+                return Err(
+                    Error::limit_exceeded("PEC stitch count", MAX_STITCHES, stitch_count)
+                        .in_format(Format::PEC)
+                        .at_offset(offset)
+                        .at_stitch(stitch_count - 1),
+                );
+            }
+            warnings.push(ReadWarning::at(
+                offset,
+                format!(
+                    "Stitch count exceeds the limit of {}, ignoring the rest",
+                    MAX_STITCHES
+                ),
+            ));
+            break;
         }
 
-        let val1 = reader.read_u8()?;
-        let val2 = match reader.read_u8() {
-            Ok(v) => v,
-            Err(_) => break,
+        let Some(val1) = next_byte(reader, true, options, warnings)? else {
+            break;
+        };
+        let Some(val2) = next_byte(reader, false, options, warnings)? else {
+            break;
         };
 
-        if val2 == 0x00 {
+        // 0xFF 0x00 ends the block; 0x00 0x00 is a zero-length stitch
+        if val1 == 0xFF && val2 == 0x00 {
             break;
         }
 
         if val1 == 0xFE && val2 == 0xB0 {
-            reader.read_u8()?; // Skip 1 byte
+            // Skip 1 byte
+            if next_byte(reader, true, options, warnings)?.is_none() {
+                break;
+            }
             pattern.color_change(0.0, 0.0);
             continue;
         }
@@ -159,11 +221,9 @@ fn read_pec_stitches<R: Read>(reader: &mut ReadHelper<R>, pattern: &mut EmbPatte
             }
             let code = ((val1 as u16) << 8) | (val2 as u16);
             x = signed12(code);
-            let val2_new = match reader.read_u8() {
-                Ok(v) => v,
-                Err(_) => break,
+            let Some(val2) = next_byte(reader, false, options, warnings)? else {
+                break;
             };
-            let val2 = val2_new;
 
             let y: i32;
             if val2 & FLAG_LONG != 0 {
@@ -173,9 +233,8 @@ fn read_pec_stitches<R: Read>(reader: &mut ReadHelper<R>, pattern: &mut EmbPatte
                 if val2 & JUMP_CODE != 0 {
                     jump = true;
                 }
-                let val3 = match reader.read_u8() {
-                    Ok(v) => v,
-                    Err(_) => break,
+                let Some(val3) = next_byte(reader, false, options, warnings)? else {
+                    break;
                 };
                 let code = ((val2 as u16) << 8) | (val3 as u16);
                 y = signed12(code);
@@ -202,9 +261,8 @@ fn read_pec_stitches<R: Read>(reader: &mut ReadHelper<R>, pattern: &mut EmbPatte
                 if val2 & JUMP_CODE != 0 {
                     jump = true;
                 }
-                let val3 = match reader.read_u8() {
-                    Ok(v) => v,
-                    Err(_) => break,
+                let Some(val3) = next_byte(reader, false, options, warnings)? else {
+                    break;
                 };
                 let code = ((val2 as u16) << 8) | (val3 as u16);
                 y = signed12(code);
@@ -250,6 +308,44 @@ pub fn read_pec<R: Read + Seek>(
     pattern: &mut EmbPattern,
     pes_chart: Option<&mut Vec<EmbThread>>,
 ) -> Result<()> {
+    let mut warnings = Vec::new();
+    read_pec_with_options(
+        reader,
+        pattern,
+        pes_chart,
+        &ReadOptions::default(),
+        &mut warnings,
+    )
+}
+
+/// Layout information from a PEC section header
+pub(crate) struct PecHeader {
+    /// Threads mapped from the color table
+    pub threads: Vec<EmbThread>,
+    /// Number of colors in the color table
+    pub color_count: usize,
+    /// Bytes per row of the preview bitmaps
+    pub graphic_stride: usize,
+    /// Rows of the preview bitmaps
+    pub graphic_height: usize,
+    /// Length of the stitch block, counted from the two bytes before the length field
+    pub stitch_block_length: u32,
+    /// Design width recorded by the writer
+    pub width: i16,
+    /// Design height recorded by the writer
+    pub height: i16,
+}
+
+/// Read a PEC section header, leaving the reader at the first stitch record
+///
+/// The label and the color table are added to `pattern`.
+pub(crate) fn read_pec_header<R: Read>(
+    reader: &mut R,
+    pattern: &mut EmbPattern,
+    pes_chart: Option<&mut Vec<EmbThread>>,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<PecHeader> {
     let mut helper = ReadHelper::new(reader);
 
     // Skip 3 bytes (LA:)
@@ -257,27 +353,26 @@ pub fn read_pec<R: Read + Seek>(
 
     // Read label (16 bytes)
     let label_bytes = helper.read_bytes(16)?;
-    if let Ok(label) = String::from_utf8(label_bytes) {
-        let label = label.trim_matches('\0').trim();
-        if !label.is_empty() {
-            pattern.add_metadata("Name", label);
-        }
+    let label = decode_text(&label_bytes, options.text_encoding).text;
+    let label = label.trim_matches('\0').trim();
+    if !label.is_empty() {
+        pattern.add_metadata("Name", label);
     }
 
     // Skip 15 bytes
     helper.read_bytes(15)?;
 
-    let pec_graphic_byte_stride = helper.read_u8()? as usize;
-    let pec_graphic_icon_height = helper.read_u8()? as usize;
+    let graphic_stride = helper.read_u8()? as usize;
+    let graphic_height = helper.read_u8()? as usize;
 
     // Skip 12 bytes
     helper.read_bytes(12)?;
 
     let color_changes = helper.read_u8()?;
-    let count_colors = (color_changes as usize) + 1;
+    let color_count = (color_changes as usize) + 1;
 
-    let color_bytes = helper.read_bytes(count_colors)?;
-    let threads = map_pec_colors(&color_bytes, pattern, pes_chart);
+    let color_bytes = helper.read_bytes(color_count)?;
+    let threads = map_pec_colors(&color_bytes, pattern, pes_chart, options, warnings)?;
 
     // Skip to stitch data
     helper.read_bytes(0x1D0 - color_changes as usize)?;
@@ -286,26 +381,71 @@ pub fn read_pec<R: Read + Seek>(
     let byte1 = helper.read_u8()? as u32;
     let byte2 = helper.read_u8()? as u32;
     let byte3 = helper.read_u8()? as u32;
-    let _stitch_block_length = byte1 | (byte2 << 8) | (byte3 << 16);
+    let stitch_block_length = byte1 | (byte2 << 8) | (byte3 << 16);
 
-    // Current position is already 3 bytes into calculation
-    // Skip 8 more bytes (total 11 bytes: 3 already read + 8 more)
-    helper.read_bytes(8)?;
+    // Block header: 0x31 0xFF 0xF0, width, height, 0x1E0, 0x1B0
+    helper.read_bytes(3)?;
+    let width = helper.read_i16_le()?;
+    let height = helper.read_i16_le()?;
+    helper.read_bytes(4)?;
+
+    Ok(PecHeader {
+        threads,
+        color_count,
+        graphic_stride,
+        graphic_height,
+        stitch_block_length,
+        width,
+        height,
+    })
+}
+
+/// Read PEC section with explicit read options
+///
+/// Out-of-range color indices are handled according to `options` and any
+/// adjustments are appended to `warnings`.
+pub fn read_pec_with_options<R: Read + Seek>(
+    reader: &mut R,
+    pattern: &mut EmbPattern,
+    pes_chart: Option<&mut Vec<EmbThread>>,
+    options: &ReadOptions,
+    warnings: &mut Vec<ReadWarning>,
+) -> Result<()> {
+    let header = read_pec_header(reader, pattern, pes_chart, options, warnings)?;
+    // The block starts 16 bytes before the first stitch record
+    let graphics_start =
+        (reader.stream_position()? + header.stitch_block_length as u64).saturating_sub(16);
 
     // Read stitches
-    read_pec_stitches(&mut helper, pattern)?;
+    read_pec_stitches(
+        &mut ReadHelper::new(&mut *reader),
+        pattern,
+        options,
+        warnings,
+    )?;
 
     // Read graphics if available
-    let byte_size = pec_graphic_byte_stride * pec_graphic_icon_height;
+    let byte_size = header.graphic_stride * header.graphic_height;
     if byte_size > 0 {
-        read_pec_graphics(
-            &mut helper,
+        if header.stitch_block_length > 0 {
+            reader.seek(SeekFrom::Start(graphics_start))?;
+        }
+        let graphics_offset = reader.stream_position()?;
+        let graphics = read_pec_graphics(
+            &mut ReadHelper::new(&mut *reader),
             pattern,
             byte_size,
-            pec_graphic_byte_stride,
-            count_colors + 1,
-            &threads,
-        )?;
+            header.graphic_stride,
+            header.color_count + 1,
+            &header.threads,
+        );
+        match graphics {
+            Err(e) if options.lenient => warnings.push(ReadWarning::at(
+                graphics_offset,
+                format!("Skipped damaged preview graphics: {}", e),
+            )),
+            result => result?,
+        }
     }
 
     Ok(())
@@ -313,27 +453,59 @@ pub fn read_pec<R: Read + Seek>(
 
 /// Read a standalone PEC file
 pub fn read<R: Read + Seek>(reader: &mut R) -> Result<EmbPattern> {
+    let (pattern, _warnings) = read_with_options(reader, &ReadOptions::default())?;
+    Ok(pattern)
+}
+
+/// Read a standalone PEC file with explicit read options
+///
+/// Returns the pattern together with any warnings raised while reading.
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::formats::io::options::{PaletteIndexPolicy, ReadOptions};
+/// use butabuti::formats::io::readers::pec;
+/// use std::fs::File;
+///
+/// let mut file = File::open("design.pec")?;
+/// let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Reject);
+/// let (pattern, warnings) = pec::read_with_options(&mut file, &options)?;
+/// for warning in &warnings {
+///     eprintln!("warning: {}", warning);
+/// }
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn read_with_options<R: Read + Seek>(
+    reader: &mut R,
+    options: &ReadOptions,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
     let mut helper = ReadHelper::new(reader);
 
     // Read header
     let pec_string = helper.read_string(8)?;
     if pec_string != "#PEC0001" {
-        return Err(Error::Parse(format!(
-            "Invalid PEC header: expected '#PEC0001', got '{}'",
-            pec_string
-        )));
+        return Err(Error::unexpected_value(
+            "PEC header",
+            "'#PEC0001'",
+            format!("'{}'", pec_string),
+        )
+        .in_format(Format::PEC)
+        .at_offset(0));
     }
 
     let mut pattern = EmbPattern::new();
+    let mut warnings = Vec::new();
     let mut reader = helper.into_inner();
 
-    read_pec(&mut reader, &mut pattern, None)?;
+    read_pec_with_options(&mut reader, &mut pattern, None, options, &mut warnings)?;
     pattern.interpolate_duplicate_color_as_stop();
 
-    Ok(pattern)
+    Ok((pattern, warnings))
 }
 
 /// Read a PEC file from path
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let mut reader = std::io::BufReader::new(file);
@@ -343,6 +515,8 @@ pub fn read_file(path: &str) -> Result<EmbPattern> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::formats::io::options::PaletteIndexPolicy;
+    use std::io::Cursor;
 
     #[test]
     fn test_signed12() {
@@ -364,4 +538,90 @@ mod tests {
     fn test_pec_threads() {
         assert_eq!(PEC_THREADS.len(), 64);
     }
+
+    /// Build a minimal PEC file whose color table references `color_bytes`
+    fn malformed_pec(color_bytes: &[u8]) -> Vec<u8> {
+        let mut data = b"#PEC0001".to_vec();
+        data.extend_from_slice(b"LA:");
+        data.extend_from_slice(b"corrupt         ");
+        data.extend_from_slice(&[0u8; 15]);
+        data.extend_from_slice(&[0, 0]); // no graphics
+        data.extend_from_slice(&[0u8; 12]);
+        data.push((color_bytes.len() - 1) as u8);
+        data.extend_from_slice(color_bytes);
+        data.resize(data.len() + 0x1D0 - (color_bytes.len() - 1), 0x20);
+        data.extend_from_slice(&[0u8; 14]); // block length and block header
+        data.extend_from_slice(&[0x0A, 0x0A, 0xFE, 0xB0, 0x02, 0x05, 0x05, 0xFF, 0x00]);
+        data
+    }
+
+    #[test]
+    fn test_lenient_truncated_stitch_block() {
+        let mut data = malformed_pec(&[5, 6]);
+        data.truncate(data.len() - 2); // drop the end marker
+        let length = data.len() as u64;
+
+        assert!(read_with_options(&mut Cursor::new(data.clone()), &ReadOptions::new()).is_err());
+
+        let options = ReadOptions::new().lenient(true);
+        let (pattern, warnings) = read_with_options(&mut Cursor::new(data), &options).unwrap();
+        assert_eq!(pattern.count_stitches(), 2);
+        assert_eq!(pattern.count_color_changes(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].offset, Some(length));
+    }
+
+    #[test]
+    fn test_lenient_truncated_graphics() {
+        let mut data = malformed_pec(&[5]);
+        // 6x38 preview bitmaps that are missing
+        data[8 + 3 + 16 + 15] = 6;
+        data[8 + 3 + 16 + 16] = 38;
+
+        assert!(read_with_options(&mut Cursor::new(data.clone()), &ReadOptions::new()).is_err());
+
+        let options = ReadOptions::new().lenient(true);
+        let (pattern, warnings) = read_with_options(&mut Cursor::new(data), &options).unwrap();
+        assert_eq!(pattern.count_stitches(), 2);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("preview graphics"));
+    }
+
+    #[test]
+    fn test_out_of_range_color_clamped() {
+        let data = malformed_pec(&[5, 200]);
+        let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Clamp);
+        let (pattern, warnings) = read_with_options(&mut Cursor::new(data), &options).unwrap();
+
+        assert_eq!(pattern.threads().len(), 2);
+        assert_eq!(pattern.threads()[1].color, PEC_THREADS[63].color);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("200"));
+    }
+
+    #[test]
+    fn test_out_of_range_color_wrapped() {
+        let data = malformed_pec(&[200]);
+        let (pattern, warnings) =
+            read_with_options(&mut Cursor::new(data), &ReadOptions::default()).unwrap();
+
+        assert_eq!(pattern.threads()[0].color, PEC_THREADS[200 % 64].color);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_out_of_range_color_rejected() {
+        let data = malformed_pec(&[200]);
+        let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Reject);
+        assert!(read_with_options(&mut Cursor::new(data), &options).is_err());
+    }
+
+    #[test]
+    fn test_in_range_colors_no_warnings() {
+        let data = malformed_pec(&[1, 2, 3]);
+        let (pattern, warnings) =
+            read_with_options(&mut Cursor::new(data), &ReadOptions::default()).unwrap();
+        assert_eq!(pattern.threads().len(), 3);
+        assert!(warnings.is_empty());
+    }
 }
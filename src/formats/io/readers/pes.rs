@@ -13,57 +13,84 @@ const MAX_PEC_OFFSET: i32 = 100_000_000;
 
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, ReadWarning};
 use crate::formats::io::readers::pec;
 use crate::formats::io::utils::ReadHelper;
-use crate::utils::error::Result;
+use crate::utils::error::{Error, Result};
+use crate::utils::string::{bytes_to_hex, decode_text, TextEncoding};
 use std::io::{Read, Seek, SeekFrom};
 
-/// Read a PES string (length-prefixed)
-fn read_pes_string<R: Read>(helper: &mut ReadHelper<R>) -> Result<Option<String>> {
+/// PES metadata fields in file order
+const PES_METADATA_KEYS: [&str; 5] = ["name", "category", "author", "keywords", "comments"];
+
+/// Read a PES string (length-prefixed) as raw bytes
+fn read_pes_bytes<R: Read>(helper: &mut ReadHelper<R>) -> Result<Option<Vec<u8>>> {
     let length = helper.read_u8()? as usize;
     if length == 0 {
         return Ok(None);
     }
-    let s = helper.read_string(length)?;
-    Ok(Some(s))
+    Ok(Some(helper.read_bytes(length)?))
+}
+
+/// Read a PES string (length-prefixed)
+fn read_pes_string<R: Read>(
+    helper: &mut ReadHelper<R>,
+    encoding: TextEncoding,
+) -> Result<Option<String>> {
+    Ok(read_pes_bytes(helper)?.map(|bytes| decode_text(&bytes, encoding).text))
 }
 
 /// Read PES metadata fields
-fn read_pes_metadata<R: Read>(helper: &mut ReadHelper<R>, pattern: &mut EmbPattern) -> Result<()> {
-    if let Some(v) = read_pes_string(helper)? {
-        if !v.is_empty() {
-            pattern.add_metadata("name", v);
-        }
-    }
-    if let Some(v) = read_pes_string(helper)? {
-        if !v.is_empty() {
-            pattern.add_metadata("category", v);
-        }
-    }
-    if let Some(v) = read_pes_string(helper)? {
-        if !v.is_empty() {
-            pattern.add_metadata("author", v);
-        }
-    }
-    if let Some(v) = read_pes_string(helper)? {
-        if !v.is_empty() {
-            pattern.add_metadata("keywords", v);
-        }
-    }
-    if let Some(v) = read_pes_string(helper)? {
-        if !v.is_empty() {
-            pattern.add_metadata("comments", v);
+///
+/// Non-ASCII values are decoded with `encoding` and the original bytes are kept
+/// as hex under `<key>_raw` so the writer can reproduce them exactly.
+fn read_pes_metadata<R: Read>(
+    helper: &mut ReadHelper<R>,
+    pattern: &mut EmbPattern,
+    encoding: TextEncoding,
+) -> Result<()> {
+    for key in PES_METADATA_KEYS {
+        if let Some(bytes) = read_pes_bytes(helper)? {
+            let value = decode_text(&bytes, encoding).text;
+            if !value.is_empty() {
+                pattern.add_metadata(key, value);
+            }
+            if !bytes.is_ascii() {
+                pattern.add_metadata(format!("{}_raw", key), bytes_to_hex(&bytes));
+            }
         }
     }
     Ok(())
 }
 
+/// Read a thread string, keeping non-ASCII bytes as hex under `<field>_raw`
+fn read_pes_thread_string<R: Read>(
+    helper: &mut ReadHelper<R>,
+    field: &str,
+    raw: &mut Vec<(String, String)>,
+    encoding: TextEncoding,
+) -> Result<Option<String>> {
+    let Some(bytes) = read_pes_bytes(helper)? else {
+        return Ok(None);
+    };
+    if !bytes.is_ascii() {
+        raw.push((format!("{}_raw", field), bytes_to_hex(&bytes)));
+    }
+    Ok(Some(decode_text(&bytes, encoding).text))
+}
+
 /// Read a PES thread definition
+///
+/// Like metadata, non-ASCII strings keep their original bytes, here as thread
+/// attributes.
 fn read_pes_thread<R: Read>(
     helper: &mut ReadHelper<R>,
     threadlist: &mut Vec<EmbThread>,
+    encoding: TextEncoding,
 ) -> Result<()> {
-    let catalog_number = read_pes_string(helper)?;
+    let mut raw = Vec::new();
+    let catalog_number = read_pes_thread_string(helper, "catalog_number", &mut raw, encoding)?;
 
     // Read color as 24-bit big-endian
     let b1 = helper.read_u8()? as u32;
@@ -74,9 +101,9 @@ fn read_pes_thread<R: Read>(
     // Skip 5 bytes
     helper.read_bytes(5)?;
 
-    let description = read_pes_string(helper)?;
-    let brand = read_pes_string(helper)?;
-    let chart = read_pes_string(helper)?;
+    let description = read_pes_thread_string(helper, "description", &mut raw, encoding)?;
+    let brand = read_pes_thread_string(helper, "brand", &mut raw, encoding)?;
+    let chart = read_pes_thread_string(helper, "chart", &mut raw, encoding)?;
 
     let mut thread = EmbThread::new(color);
     if let Some(cat) = catalog_number {
@@ -91,6 +118,9 @@ fn read_pes_thread<R: Read>(
     if let Some(c) = chart {
         thread = thread.with_chart(&c);
     }
+    for (key, value) in raw {
+        thread.set_attribute(key, value);
+    }
 
     threadlist.push(thread);
     Ok(())
@@ -109,9 +139,10 @@ fn read_pes_header_version_1<R: Read>(
 fn read_pes_header_version_4<R: Read>(
     helper: &mut ReadHelper<R>,
     pattern: &mut EmbPattern,
+    encoding: TextEncoding,
 ) -> Result<()> {
     helper.read_bytes(4)?;
-    read_pes_metadata(helper, pattern)?;
+    read_pes_metadata(helper, pattern, encoding)?;
     Ok(())
 }
 
@@ -120,12 +151,13 @@ fn read_pes_header_version_5<R: Read>(
     helper: &mut ReadHelper<R>,
     pattern: &mut EmbPattern,
     threadlist: &mut Vec<EmbThread>,
+    encoding: TextEncoding,
 ) -> Result<()> {
     helper.read_bytes(4)?;
-    read_pes_metadata(helper, pattern)?;
+    read_pes_metadata(helper, pattern, encoding)?;
     helper.read_bytes(24)?;
 
-    if let Some(v) = read_pes_string(helper)? {
+    if let Some(v) = read_pes_string(helper, encoding)? {
         if !v.is_empty() {
             pattern.add_metadata("image_file", v);
         }
@@ -150,7 +182,7 @@ fn read_pes_header_version_5<R: Read>(
 
     let count_threads = helper.read_u16_le()?;
     for _ in 0..count_threads {
-        read_pes_thread(helper, threadlist)?;
+        read_pes_thread(helper, threadlist, encoding)?;
     }
 
     Ok(())
@@ -161,12 +193,13 @@ fn read_pes_header_version_6<R: Read>(
     helper: &mut ReadHelper<R>,
     pattern: &mut EmbPattern,
     threadlist: &mut Vec<EmbThread>,
+    encoding: TextEncoding,
 ) -> Result<()> {
     helper.read_bytes(4)?;
-    read_pes_metadata(helper, pattern)?;
+    read_pes_metadata(helper, pattern, encoding)?;
     helper.read_bytes(36)?; // Different from v5
 
-    if let Some(v) = read_pes_string(helper)? {
+    if let Some(v) = read_pes_string(helper, encoding)? {
         if !v.is_empty() {
             pattern.add_metadata("image_file", v);
         }
@@ -191,7 +224,7 @@ fn read_pes_header_version_6<R: Read>(
 
     let count_threads = helper.read_u16_le()?;
     for _ in 0..count_threads {
-        read_pes_thread(helper, threadlist)?;
+        read_pes_thread(helper, threadlist, encoding)?;
     }
 
     Ok(())
@@ -220,34 +253,45 @@ fn read_pes_header_version_6<R: Read>(
 /// # Ok::<(), butabuti::utils::error::Error>(())
 /// ```
 pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<()> {
-    let mut helper = ReadHelper::new(file);
-    let mut loaded_thread_values = Vec::new();
+    read_with_options(file, pattern, &ReadOptions::default())?;
+    Ok(())
+}
 
+/// Read the PES header and version-specific metadata
+///
+/// Returns the position of the PEC section, or `None` for a standalone PEC
+/// file whose section follows the magic directly.
+pub(crate) fn read_header<R: Read>(
+    helper: &mut ReadHelper<R>,
+    pattern: &mut EmbPattern,
+    loaded_thread_values: &mut Vec<EmbThread>,
+    encoding: TextEncoding,
+) -> Result<Option<u64>> {
     // Read PES header string (8 bytes)
     let pes_string = helper.read_string(8).map_err(|e| {
         if e.kind() == std::io::ErrorKind::UnexpectedEof {
-            crate::utils::error::Error::Parse(
-                "PES file too small: header must be at least 8 bytes".to_string(),
-            )
+            Error::truncated("PES header of 8 bytes")
+                .in_format(Format::PES)
+                .at_offset(0)
         } else {
-            crate::utils::error::Error::from(e)
+            Error::from(e)
         }
     })?;
 
     // Validate PES/PEC magic bytes
     if !pes_string.starts_with("#PES") && !pes_string.starts_with("#PEC") {
-        return Err(crate::utils::error::Error::Parse(format!(
-            "Invalid PES/PEC header: expected '#PES' or '#PEC', got '{}'",
-            pes_string
-        )));
+        return Err(Error::unexpected_value(
+            "PES/PEC header",
+            "'#PES' or '#PEC'",
+            format!("'{}'", pes_string),
+        )
+        .in_format(Format::PES)
+        .at_offset(0));
     }
 
     // Check if it's actually a standalone PEC file
     if pes_string == "#PEC0001" {
-        let mut reader = helper.into_inner();
-        pec::read_pec(&mut reader, pattern, None)?;
-        pattern.interpolate_duplicate_color_as_stop();
-        return Ok(());
+        return Ok(None);
     }
 
     // Read PEC block position
@@ -255,41 +299,44 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
 
     // Validate PEC block position is reasonable
     if !(0..=MAX_PEC_OFFSET).contains(&pec_block_position) {
-        return Err(crate::utils::error::Error::Parse(format!(
-            "Invalid PEC block position: {} (must be between 0 and {})",
-            pec_block_position, MAX_PEC_OFFSET
-        )));
+        return Err(Error::unexpected_value(
+            "PEC block position",
+            format!("0 to {}", MAX_PEC_OFFSET),
+            pec_block_position.to_string(),
+        )
+        .in_format(Format::PES)
+        .at_offset(8));
     }
 
     // Parse version and read appropriate header
     match pes_string.as_str() {
         "#PES0100" => {
             pattern.add_metadata("version", "10");
-            read_pes_header_version_6(&mut helper, pattern, &mut loaded_thread_values)?;
+            read_pes_header_version_6(helper, pattern, loaded_thread_values, encoding)?;
         }
         "#PES0090" => {
             pattern.add_metadata("version", "9");
-            read_pes_header_version_6(&mut helper, pattern, &mut loaded_thread_values)?;
+            read_pes_header_version_6(helper, pattern, loaded_thread_values, encoding)?;
         }
         "#PES0080" => {
             pattern.add_metadata("version", "8");
-            read_pes_header_version_6(&mut helper, pattern, &mut loaded_thread_values)?;
+            read_pes_header_version_6(helper, pattern, loaded_thread_values, encoding)?;
         }
         "#PES0070" => {
             pattern.add_metadata("version", "7");
-            read_pes_header_version_6(&mut helper, pattern, &mut loaded_thread_values)?;
+            read_pes_header_version_6(helper, pattern, loaded_thread_values, encoding)?;
         }
         "#PES0060" => {
             pattern.add_metadata("version", "6");
-            read_pes_header_version_6(&mut helper, pattern, &mut loaded_thread_values)?;
+            read_pes_header_version_6(helper, pattern, loaded_thread_values, encoding)?;
         }
         "#PES0050" | "#PES0055" | "#PES0056" => {
             pattern.add_metadata("version", "5");
-            read_pes_header_version_5(&mut helper, pattern, &mut loaded_thread_values)?;
+            read_pes_header_version_5(helper, pattern, loaded_thread_values, encoding)?;
         }
         "#PES0040" => {
             pattern.add_metadata("version", "4");
-            read_pes_header_version_4(&mut helper, pattern)?;
+            read_pes_header_version_4(helper, pattern, encoding)?;
         }
         "#PES0030" => {
             pattern.add_metadata("version", "3");
@@ -302,21 +349,56 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
         }
         "#PES0001" => {
             pattern.add_metadata("version", "1");
-            read_pes_header_version_1(&mut helper, pattern)?;
+            read_pes_header_version_1(helper, pattern)?;
         }
         _ => {
             // Unknown version, skip header
         }
     }
 
-    // Seek to PEC block and read it
+    Ok(Some(pec_block_position as u64))
+}
+
+/// Read PES format with explicit read options
+///
+/// Behaves like [`read`], but handles damaged color tables according to
+/// `options` and returns the warnings raised while reading.
+pub fn read_with_options(
+    file: &mut (impl Read + Seek),
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+) -> Result<Vec<ReadWarning>> {
+    let mut warnings = Vec::new();
+    let mut loaded_thread_values = Vec::new();
+    let mut helper = ReadHelper::new(file);
+    let pec_block_position = read_header(
+        &mut helper,
+        pattern,
+        &mut loaded_thread_values,
+        options.text_encoding,
+    )?;
     let mut reader = helper.into_inner();
-    reader.seek(SeekFrom::Start(pec_block_position as u64))?;
 
-    pec::read_pec(&mut reader, pattern, Some(&mut loaded_thread_values))?;
+    // A standalone PEC file continues right after its magic
+    let Some(pec_block_position) = pec_block_position else {
+        pec::read_pec_with_options(&mut reader, pattern, None, options, &mut warnings)?;
+        pattern.interpolate_duplicate_color_as_stop();
+        return Ok(warnings);
+    };
+
+    // Seek to PEC block and read it
+    reader.seek(SeekFrom::Start(pec_block_position))?;
+
+    pec::read_pec_with_options(
+        &mut reader,
+        pattern,
+        Some(&mut loaded_thread_values),
+        options,
+        &mut warnings,
+    )?;
     pattern.interpolate_duplicate_color_as_stop();
 
-    Ok(())
+    Ok(warnings)
 }
 
 /// Read a PES file from path
@@ -331,6 +413,7 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
 /// let pattern = pes::read_file("design.pes")?;
 /// # Ok::<(), butabuti::utils::error::Error>(())
 /// ```
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let mut reader = std::io::BufReader::new(file);
@@ -341,6 +424,100 @@ pub fn read_file(path: &str) -> Result<EmbPattern> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::utils::string::hex_to_bytes;
+    use std::io::Cursor;
+
+    /// Encode metadata fields as length-prefixed PES strings
+    fn pes_strings(fields: &[&[u8]]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for field in fields {
+            data.push(field.len() as u8);
+            data.extend_from_slice(field);
+        }
+        data
+    }
+
+    #[test]
+    fn test_read_metadata_shift_jis() {
+        // "日本" in Shift-JIS, "Café" in Latin-1
+        let sjis: &[u8] = &[0x93, 0xfa, 0x96, 0x7b];
+        let latin1: &[u8] = b"Caf\xe9";
+        let data = pes_strings(&[sjis, b"", latin1, b"", b"plain"]);
+
+        let mut helper = ReadHelper::new(Cursor::new(data));
+        let mut pattern = EmbPattern::new();
+        read_pes_metadata(&mut helper, &mut pattern, TextEncoding::Auto).unwrap();
+
+        assert_eq!(pattern.get_metadata("name"), Some(&"日本".to_string()));
+        assert_eq!(pattern.get_metadata("author"), Some(&"Café".to_string()));
+        assert_eq!(pattern.get_metadata("comments"), Some(&"plain".to_string()));
+        assert_eq!(
+            hex_to_bytes(pattern.get_metadata("name_raw").unwrap()),
+            Some(sjis.to_vec())
+        );
+        assert!(pattern.get_metadata("comments_raw").is_none());
+    }
+
+    #[test]
+    fn test_read_metadata_explicit_encoding() {
+        let data = pes_strings(&[&[0x93, 0xfa], b"", b"", b"", b""]);
+
+        let mut helper = ReadHelper::new(Cursor::new(data));
+        let mut pattern = EmbPattern::new();
+        read_pes_metadata(&mut helper, &mut pattern, TextEncoding::Latin1).unwrap();
+
+        assert_eq!(pattern.get_metadata("name"), Some(&"\u{93}ú".to_string()));
+    }
+
+    #[test]
+    fn test_read_thread_shift_jis() {
+        // "赤" in Shift-JIS
+        let sjis: &[u8] = &[0x90, 0xd4];
+        let mut data = pes_strings(&[b"001"]);
+        data.extend_from_slice(&[0xFF, 0x00, 0x00, 0, 0, 0, 0, 0]);
+        data.extend(pes_strings(&[sjis, b"Brother", b""]));
+
+        let mut helper = ReadHelper::new(Cursor::new(data));
+        let mut threads = Vec::new();
+        read_pes_thread(&mut helper, &mut threads, TextEncoding::ShiftJis).unwrap();
+
+        let thread = &threads[0];
+        assert_eq!(thread.description.as_deref(), Some("赤"));
+        assert_eq!(thread.brand.as_deref(), Some("Brother"));
+        assert_eq!(
+            hex_to_bytes(thread.get_attribute("description_raw").unwrap()),
+            Some(sjis.to_vec())
+        );
+        assert_eq!(thread.get_attribute("brand_raw"), None);
+    }
+
+    #[test]
+    fn test_structured_header_errors() {
+        use crate::utils::error::{ErrorCode, ErrorKind};
+
+        let mut pattern = EmbPattern::new();
+        let err = read(&mut Cursor::new(b"#XYZ0001\0\0\0\0".to_vec()), &mut pattern).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnexpectedValue);
+        assert_eq!(err.location().format, Some(Format::PES));
+        assert_eq!(err.location().offset, Some(0));
+        assert!(
+            matches!(err.kind(), ErrorKind::UnexpectedValue { found, .. } if found == "'#XYZ0001'")
+        );
+        assert!(err
+            .to_string()
+            .starts_with("Parse error: Invalid PES/PEC header: expected '#PES' or '#PEC'"));
+
+        let mut data = b"#PES0001".to_vec();
+        data.extend_from_slice(&(-5i32).to_le_bytes());
+        let err = read(&mut Cursor::new(data), &mut pattern).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::UnexpectedValue);
+        assert_eq!(err.location().offset, Some(8));
+
+        let err = read(&mut Cursor::new(b"#PES".to_vec()), &mut pattern).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Truncated);
+    }
+
     #[test]
     fn test_pes_version_strings() {
         let versions = vec![
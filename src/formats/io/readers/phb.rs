@@ -4,6 +4,7 @@
 //! stitch data and thread colors using the PEC palette.
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::io::options::{ReadOptions, ReadWarning};
 use crate::formats::io::readers::pec;
 use crate::palettes::thread_pec::PEC_THREADS;
 use crate::utils::error::Result;
@@ -13,6 +14,21 @@ use std::io::{Read, Seek, SeekFrom};
 ///
 /// PHB format uses PEC stitch encoding with a custom header.
 pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<()> {
+    read_with_options(file, pattern, &ReadOptions::default())?;
+    Ok(())
+}
+
+/// Read PHB format with explicit read options
+///
+/// Color indices outside the PEC palette are handled according to `options`,
+/// and the warnings raised while reading are returned.
+pub fn read_with_options(
+    file: &mut (impl Read + Seek),
+    pattern: &mut EmbPattern,
+    options: &ReadOptions,
+) -> Result<Vec<ReadWarning>> {
+    let mut warnings = Vec::new();
+
     // Read color count at offset 0x71
     file.seek(SeekFrom::Start(0x71))?;
     let color_count = read_u16_le(file)?;
@@ -20,7 +36,11 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
     // Read thread indices
     for _ in 0..color_count {
         let color_index = read_u8(file)?;
-        let thread_index = (color_index as usize) % PEC_THREADS.len();
+        let thread_index = options.resolve_palette_index(
+            color_index as usize,
+            PEC_THREADS.len(),
+            &mut warnings,
+        )?;
         pattern.add_thread(PEC_THREADS[thread_index].clone());
     }
 
@@ -49,7 +69,7 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
         pattern.add_stitch_absolute(stitch.command, stitch.x, stitch.y);
     }
 
-    Ok(())
+    Ok(warnings)
 }
 
 /// Read unsigned 8-bit integer
@@ -102,4 +122,40 @@ mod tests {
         // Check that thread was added
         assert_eq!(pattern.threads().len(), 1);
     }
+
+    /// Header with a single color index past the end of the PEC palette
+    fn out_of_range_phb() -> Vec<u8> {
+        let mut phb_data = vec![0u8; 0x71];
+        phb_data.extend_from_slice(&1u16.to_le_bytes());
+        phb_data.push(200);
+        phb_data.resize(0x100, 0);
+        phb_data
+    }
+
+    #[test]
+    fn test_out_of_range_color_wrapped_by_default() {
+        let mut pattern = EmbPattern::new();
+
+        // The header has no PEC block, so only the color table is read
+        let _ = read_with_options(
+            &mut Cursor::new(out_of_range_phb()),
+            &mut pattern,
+            &ReadOptions::default(),
+        );
+
+        assert_eq!(pattern.threads().len(), 1);
+        assert_eq!(pattern.threads()[0].color, PEC_THREADS[200 % 64].color);
+    }
+
+    #[test]
+    fn test_out_of_range_color_rejected() {
+        use crate::formats::io::options::PaletteIndexPolicy;
+
+        let options = ReadOptions::new().palette_index_policy(PaletteIndexPolicy::Reject);
+        let mut pattern = EmbPattern::new();
+        let result =
+            read_with_options(&mut Cursor::new(out_of_range_phb()), &mut pattern, &options);
+        assert!(result.is_err());
+        assert!(pattern.threads().is_empty());
+    }
 }
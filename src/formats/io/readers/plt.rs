@@ -0,0 +1,225 @@
+//! PLT (HPGL) format reader
+//!
+//! Reads HPGL plotter files as consumed by older Happy and Tajima sequin devices.
+//! Pen-down moves become stitches, pen-up moves become jumps, and selecting a new
+//! pen (`SP`) becomes a color change. HPGL carries no thread colors.
+//!
+//! ## Format Limitations
+//!
+//! - **Units**: Plotter units are 0.025mm (40 per mm) unless another scale is given
+//! - **Instructions**: Only IN, PU, PD, PA, PR and SP are interpreted; others are ignored
+//! - **Max stitches**: Limited to 10,000,000 stitches (safety limit)
+//! - **Max size**: Files larger than 256MB are rejected
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::{Error, Result};
+use std::io::Read;
+
+/// HPGL plotter units per millimeter
+pub const PLT_UNITS_PER_MM: f64 = 40.0;
+
+const MAX_PLT_STITCHES: usize = 10_000_000; // Safety limit for stitch count
+const MAX_PLT_SIZE: u64 = 256 * 1024 * 1024; // Safety limit for file size
+
+/// Read a PLT file in standard plotter units
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::prelude::*;
+/// use std::fs::File;
+///
+/// let mut file = File::open("design.plt").unwrap();
+/// let mut pattern = EmbPattern::new();
+/// butabuti::formats::io::readers::plt::read(&mut file, &mut pattern).unwrap();
+/// ```
+pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    read_with_scale(file, pattern, PLT_UNITS_PER_MM)
+}
+
+/// Read a PLT file whose coordinates use `units_per_mm` file units per millimeter
+///
+/// Some devices write coordinates in 0.1mm (10 per mm) rather than plotter units.
+pub fn read_with_scale(
+    file: &mut impl Read,
+    pattern: &mut EmbPattern,
+    units_per_mm: f64,
+) -> Result<()> {
+    if !(units_per_mm > 0.0 && units_per_mm.is_finite()) {
+        return Err(Error::Parse(format!(
+            "PLT: Units per mm must be positive, got {}",
+            units_per_mm
+        )));
+    }
+
+    let mut data = Vec::new();
+    file.take(MAX_PLT_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_PLT_SIZE {
+        return Err(Error::Parse("PLT: File exceeds 256MB".to_string()));
+    }
+    let text = String::from_utf8_lossy(&data);
+
+    // File units to 0.1mm; HPGL's Y axis points up
+    let scale = 10.0 / units_per_mm;
+    let mut pen_down = false;
+    let mut relative = false;
+    let mut pen: Option<u32> = None;
+    let mut stitch_count = 0;
+    let (mut x, mut y) = (0.0, 0.0);
+
+    for (mnemonic, params) in instructions(&text) {
+        match mnemonic.as_str() {
+            "IN" => {
+                pen_down = false;
+                relative = false;
+                (x, y) = (0.0, 0.0);
+            }
+            "PU" | "PD" | "PA" | "PR" => {
+                match mnemonic.as_str() {
+                    "PU" => pen_down = false,
+                    "PD" => pen_down = true,
+                    "PA" => relative = false,
+                    _ => relative = true,
+                }
+                for pair in params.chunks_exact(2) {
+                    if relative {
+                        x += pair[0];
+                        y += pair[1];
+                    } else {
+                        (x, y) = (pair[0], pair[1]);
+                    }
+                    stitch_count += 1;
+                    if stitch_count > MAX_PLT_STITCHES {
+                        return Err(Error::Parse(format!(
+                            "PLT: Stitch count exceeds maximum of {}",
+                            MAX_PLT_STITCHES
+                        )));
+                    }
+                    let command = if pen_down { STITCH } else { JUMP };
+                    pattern.add_stitch_absolute(command, x * scale, -y * scale);
+                }
+            }
+            "SP" => {
+                // Plotters store the old pen, so the new one starts raised
+                pen_down = false;
+                let selected = params.first().map_or(1, |&p| p.max(0.0) as u32);
+                if selected > 0 {
+                    if pen.is_some_and(|p| p != selected) && pattern.count_stitches() > 0 {
+                        pattern.color_change(0.0, 0.0);
+                    }
+                    pen = Some(selected);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pattern.end();
+    Ok(())
+}
+
+/// Split HPGL text into `(mnemonic, numeric parameters)` instructions
+///
+/// Instructions start with two letters; `;`, commas, whitespace and the start of
+/// the next mnemonic all act as separators. Label text (`LB`) is skipped up to its
+/// terminator.
+fn instructions(text: &str) -> Vec<(String, Vec<f64>)> {
+    let mut result: Vec<(String, Vec<f64>)> = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut number = String::new();
+
+    let flush = |number: &mut String, result: &mut Vec<(String, Vec<f64>)>| {
+        if let (Some(last), Ok(value)) = (result.last_mut(), number.parse::<f64>()) {
+            if value.is_finite() {
+                last.1.push(value);
+            }
+        }
+        number.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_alphabetic() {
+            flush(&mut number, &mut result);
+            let Some(&second) = chars.peek() else {
+                break;
+            };
+            if !second.is_ascii_alphabetic() {
+                continue;
+            }
+            chars.next();
+            let mnemonic: String = [c, second].iter().collect::<String>().to_uppercase();
+            if mnemonic == "LB" {
+                for c in chars.by_ref() {
+                    if c == '\u{3}' {
+                        break;
+                    }
+                }
+            }
+            result.push((mnemonic, Vec::new()));
+        } else if c.is_ascii_digit() || c == '.' || ((c == '-' || c == '+') && number.is_empty()) {
+            number.push(c);
+        } else {
+            flush(&mut number, &mut result);
+        }
+    }
+    flush(&mut number, &mut result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_instructions() {
+        let parsed = instructions("IN;SP1;PU0,0;PD40,40 80 0;pa-4.5,+8;LBA;B\u{3}PU;");
+        let mnemonics: Vec<&str> = parsed.iter().map(|(m, _)| m.as_str()).collect();
+        assert_eq!(mnemonics, vec!["IN", "SP", "PU", "PD", "PA", "LB", "PU"]);
+        assert_eq!(parsed[3].1, vec![40.0, 40.0, 80.0, 0.0]);
+        assert_eq!(parsed[4].1, vec![-4.5, 8.0]);
+
+        // Instructions without separators
+        let parsed = instructions("PU0 0PD10 10");
+        assert_eq!(parsed[1], ("PD".to_string(), vec![10.0, 10.0]));
+    }
+
+    #[test]
+    fn test_read_plt_moves_and_pens() {
+        let plt = "IN;SP1;PU0,0;PD400,0,400,400;SP2;PR-40,0;PD;PR0,-40;SP2;PA;PD0,0;PU;SP0;";
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(plt), &mut pattern).unwrap();
+
+        let stitches: Vec<(u32, f64, f64)> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.command & COMMAND_MASK, s.x, s.y))
+            .collect();
+        assert_eq!(
+            stitches,
+            vec![
+                (JUMP, 0.0, 0.0),
+                (STITCH, 100.0, 0.0),
+                (STITCH, 100.0, -100.0),
+                (COLOR_CHANGE, 100.0, -100.0),
+                (JUMP, 90.0, -100.0),
+                (STITCH, 90.0, -90.0),
+                (STITCH, 0.0, 0.0),
+                (END, 0.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_plt_scale() {
+        let mut pattern = EmbPattern::new();
+        read_with_scale(&mut Cursor::new("PD100,50;"), &mut pattern, 10.0).unwrap();
+        assert_eq!(
+            (pattern.stitches()[0].x, pattern.stitches()[0].y),
+            (100.0, -50.0)
+        );
+
+        assert!(read_with_scale(&mut Cursor::new(""), &mut EmbPattern::new(), 0.0).is_err());
+    }
+}
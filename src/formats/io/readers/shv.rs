@@ -87,7 +87,7 @@ fn read_shv_stitches(
 
         // Check if we need to change color
         if stitches_since_stop >= max_stitches {
-            pattern.add_command(COLOR_CHANGE, 0.0, 0.0);
+            pattern.add_stitch_relative(0.0, 0.0, COLOR_CHANGE);
             stitches_since_stop = 0;
             current_color_index += 1;
             max_stitches = stitch_per_color
@@ -127,7 +127,7 @@ fn read_shv_stitches(
         pattern.add_stitch_relative(dx, dy, flags);
     }
 
-    pattern.add_command(END, 0.0, 0.0);
+    pattern.add_stitch_relative(0.0, 0.0, END);
     Ok(())
 }
 
@@ -0,0 +1,1135 @@
+//! SVG vector graphics reader
+//!
+//! Converts SVG paths, polylines and basic shapes (line, rect, circle, ellipse,
+//! polygon) into running-stitch sequences. Each shape is stitched along its outline
+//! in its `stroke` color (falling back to `fill`), and every color switch starts a
+//! new thread. Curves and arcs are split into stitches of roughly the configured
+//! stitch length.
+//!
+//! ## Format Limitations
+//!
+//! - **Outlines only**: Shapes are stitched along their outline; fills are not digitized
+//! - **Units**: Documents with an absolute `width`/`height` (mm, cm, in, pt, pc, px)
+//!   are scaled to their physical size; otherwise one user unit is one embroidery
+//!   unit (0.1 mm), which matches the SVG writer
+//! - **Ignored content**: Text, images, `<use>` references, clip paths, masks and gradients
+//! - **Max stitches**: Limited to 10,000,000 stitches (safety limit)
+//! - **Coordinates**: Limited to 1 km from the origin after transforms; larger or
+//!   non-finite numbers are rejected
+
+use crate::core::constants::*;
+use crate::core::matrix::EmbMatrix;
+use crate::core::path::resample;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::{color_rgb, parse_color_string, EmbThread};
+use crate::utils::error::{Error, Result};
+use std::f64::consts::PI;
+use std::io::Read;
+
+// Format constants
+const MAX_SVG_STITCHES: usize = 10_000_000; // Safety limit for stitch count
+
+/// Largest coordinate after transforms, in embroidery units (1 km)
+const MAX_SVG_COORDINATE: f64 = 10_000_000.0;
+
+/// Embroidery units (0.1 mm) per CSS pixel
+const UNITS_PER_PX: f64 = 254.0 / 96.0;
+
+/// Settings for the SVG reader
+#[derive(Debug, Clone)]
+pub struct SvgSettings {
+    /// Running stitch length in embroidery units (default: 25, i.e. 2.5 mm)
+    pub stitch_length: f64,
+    /// Extra scale applied after the document's own units (default: 1.0)
+    pub scale: f64,
+    /// Color for shapes without a usable stroke or fill color (default: black)
+    pub default_color: u32,
+}
+
+impl Default for SvgSettings {
+    fn default() -> Self {
+        Self {
+            stitch_length: 25.0,
+            scale: 1.0,
+            default_color: 0x000000,
+        }
+    }
+}
+
+/// Read an SVG file as running stitches with default settings
+pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    read_with_settings(file, pattern, &SvgSettings::default())
+}
+
+/// Read an SVG file as running stitches
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::formats::io::readers::svg::{self, SvgSettings};
+///
+/// let svg = r##"<svg xmlns="http://www.w3.org/2000/svg">
+///     <path d="M 0 0 H 100" stroke="#ff0000"/>
+/// </svg>"##;
+///
+/// let settings = SvgSettings { stitch_length: 20.0, ..SvgSettings::default() };
+/// let mut pattern = EmbPattern::new();
+/// svg::read_with_settings(&mut svg.as_bytes(), &mut pattern, &settings)?;
+///
+/// assert_eq!(pattern.threads()[0].color, 0xFF0000);
+/// assert_eq!(pattern.count_stitches(), 6);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn read_with_settings(
+    file: &mut impl Read,
+    pattern: &mut EmbPattern,
+    settings: &SvgSettings,
+) -> Result<()> {
+    if !(settings.stitch_length > 0.0 && settings.stitch_length.is_finite()) {
+        return Err(Error::Parse(format!(
+            "SVG: Invalid stitch length {}",
+            settings.stitch_length
+        )));
+    }
+
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let document = roxmltree::Document::parse(&text)
+        .map_err(|e| Error::Parse(format!("SVG parse error: {}", e)))?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != "svg" {
+        return Err(Error::Parse(format!(
+            "SVG: Root element is <{}>, expected <svg>",
+            root.tag_name().name()
+        )));
+    }
+
+    let mut scale = EmbMatrix::new();
+    scale.post_scale(settings.scale, None, 0.0, 0.0);
+    // `a.composed_with(b)` applies `b` first
+    let base = scale.composed_with(&document_transform(root));
+
+    let mut digitizer = Digitizer {
+        pattern,
+        settings,
+        color: None,
+        position: None,
+        stitches: 0,
+    };
+    visit(root, &Style::default(), &base, &mut digitizer)?;
+
+    if digitizer.color.is_some() {
+        digitizer.pattern.end();
+    }
+    Ok(())
+}
+
+/// Paint value of a `stroke` or `fill` property
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Paint {
+    None,
+    Color(u32),
+    /// Gradients, patterns and other paint servers
+    Other,
+}
+
+/// Inherited presentation properties; `None` means unspecified
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    stroke: Option<Paint>,
+    fill: Option<Paint>,
+}
+
+impl Style {
+    /// Properties of `node`, inheriting anything it doesn't set
+    fn inherit(&self, node: roxmltree::Node) -> (Self, bool) {
+        let mut style = *self;
+        let mut hidden = false;
+        let declarations = node.attributes().map(|a| (a.name(), a.value())).chain(
+            node.attribute("style").into_iter().flat_map(|css| {
+                css.split(';').filter_map(|declaration| {
+                    let (name, value) = declaration.split_once(':')?;
+                    Some((name.trim(), value.trim()))
+                })
+            }),
+        );
+        for (name, value) in declarations {
+            match name {
+                "stroke" => style.stroke = parse_paint(value).or(style.stroke),
+                "fill" => style.fill = parse_paint(value).or(style.fill),
+                "display" => hidden = value.trim() == "none",
+                _ => {}
+            }
+        }
+        (style, hidden)
+    }
+
+    /// Thread color of a shape, or `None` if it isn't drawn at all
+    fn color(&self, default: u32) -> Option<u32> {
+        match (self.stroke, self.fill) {
+            (Some(Paint::Color(color)), _) => Some(color),
+            (Some(Paint::Other), _) => Some(default),
+            (_, Some(Paint::Color(color))) => Some(color),
+            (_, Some(Paint::None)) => None,
+            // Fill defaults to black
+            (_, Some(Paint::Other) | None) => Some(default),
+        }
+    }
+}
+
+/// Parse a paint value; `None` for `inherit`
+fn parse_paint(value: &str) -> Option<Paint> {
+    let value = value.trim();
+    match value {
+        "inherit" => None,
+        "none" | "transparent" => Some(Paint::None),
+        _ => Some(parse_color(value).map_or(Paint::Other, Paint::Color)),
+    }
+}
+
+/// Parse a hex, named or `rgb()` color
+fn parse_color(value: &str) -> Option<u32> {
+    let Some(args) = value
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return parse_color_string(value).ok();
+    };
+    let channels: Vec<u8> = args
+        .split(',')
+        .map(|channel| {
+            let channel = channel.trim();
+            let level = match channel.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f64>().ok()? * 2.55,
+                None => channel.parse::<f64>().ok()?,
+            };
+            Some(level.round().clamp(0.0, 255.0) as u8)
+        })
+        .collect::<Option<_>>()?;
+    match channels[..] {
+        [r, g, b] => Some(color_rgb(r, g, b)),
+        _ => None,
+    }
+}
+
+/// Map the document's user units to embroidery units
+///
+/// Only documents sized in absolute units are scaled; unitless documents keep
+/// one user unit per embroidery unit.
+fn document_transform(root: roxmltree::Node) -> EmbMatrix {
+    let mut matrix = EmbMatrix::new();
+    let width = root.attribute("width").and_then(physical_length);
+    let height = root.attribute("height").and_then(physical_length);
+    if width.is_none() && height.is_none() {
+        return matrix;
+    }
+
+    let view_box = root
+        .attribute("viewBox")
+        .map(parse_numbers)
+        .filter(|v| v.len() == 4 && v[2] > 0.0 && v[3] > 0.0);
+    match view_box {
+        Some(view_box) => {
+            let sx = width.map(|w| w / view_box[2]);
+            let sy = height.map(|h| h / view_box[3]);
+            let sx = sx.or(sy).unwrap_or(1.0);
+            // Post-multiplied transforms apply first: translate, then scale
+            matrix.post_scale(sx, Some(sy.unwrap_or(sx)), 0.0, 0.0);
+            matrix.post_translate(-view_box[0], -view_box[1]);
+        }
+        None => matrix.post_scale(UNITS_PER_PX, None, 0.0, 0.0),
+    }
+    matrix
+}
+
+/// Length with an absolute unit, in embroidery units
+fn physical_length(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let per_unit = match unit {
+        "mm" => 10.0,
+        "cm" => 100.0,
+        "in" => 254.0,
+        "pt" => 254.0 / 72.0,
+        "pc" => 254.0 / 6.0,
+        "px" => UNITS_PER_PX,
+        _ => return None,
+    };
+    Some(number.trim().parse::<f64>().ok()? * per_unit)
+}
+
+/// Parse a whitespace or comma separated list of numbers
+fn parse_numbers(text: &str) -> Vec<f64> {
+    let mut lexer = Lexer::new(text);
+    let mut numbers = Vec::new();
+    while let Some(number) = lexer.number() {
+        numbers.push(number);
+    }
+    numbers
+}
+
+/// Parse a `transform` attribute
+fn parse_transform(text: &str) -> EmbMatrix {
+    let mut matrix = EmbMatrix::new();
+    for item in text.split_inclusive(')') {
+        let Some((name, args)) = item.split_once('(') else {
+            break;
+        };
+        let args = parse_numbers(args.trim_end_matches(')'));
+        let arg = |i: usize| args.get(i).copied();
+        let m = match (
+            name.trim_matches(|c: char| c.is_whitespace() || c == ','),
+            arg(0),
+        ) {
+            ("matrix", _) if args.len() == 6 => [
+                args[0], args[1], 0.0, args[2], args[3], 0.0, args[4], args[5], 1.0,
+            ],
+            ("translate", Some(tx)) => {
+                [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, tx, arg(1).unwrap_or(0.0), 1.0]
+            }
+            ("scale", Some(sx)) => {
+                let sy = arg(1).unwrap_or(sx);
+                [sx, 0.0, 0.0, 0.0, sy, 0.0, 0.0, 0.0, 1.0]
+            }
+            ("rotate", Some(degrees)) => {
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                let (cx, cy) = (arg(1).unwrap_or(0.0), arg(2).unwrap_or(0.0));
+                [
+                    cos,
+                    sin,
+                    0.0,
+                    -sin,
+                    cos,
+                    0.0,
+                    cx - cx * cos + cy * sin,
+                    cy - cx * sin - cy * cos,
+                    1.0,
+                ]
+            }
+            ("skewX", Some(degrees)) => [
+                1.0,
+                0.0,
+                0.0,
+                degrees.to_radians().tan(),
+                1.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ],
+            ("skewY", Some(degrees)) => [
+                1.0,
+                degrees.to_radians().tan(),
+                0.0,
+                0.0,
+                1.0,
+                0.0,
+                0.0,
+                0.0,
+                1.0,
+            ],
+            _ => break,
+        };
+        // The rightmost transform applies to points first
+        matrix = matrix.composed_with(&EmbMatrix::from_values(m));
+    }
+    matrix
+}
+
+/// Walk the element tree and stitch every visible shape
+fn visit(
+    node: roxmltree::Node,
+    parent_style: &Style,
+    parent_transform: &EmbMatrix,
+    digitizer: &mut Digitizer,
+) -> Result<()> {
+    let (style, hidden) = parent_style.inherit(node);
+    if hidden {
+        return Ok(());
+    }
+    let transform = match node.attribute("transform") {
+        Some(text) => parent_transform.composed_with(&parse_transform(text)),
+        None => parent_transform.clone(),
+    };
+
+    match node.tag_name().name() {
+        "svg" | "g" | "a" | "switch" => {
+            for child in node.children().filter(|n| n.is_element()) {
+                visit(child, &style, &transform, digitizer)?;
+            }
+        }
+        name => {
+            let subpaths = shape_outline(name, node);
+            if let Some(color) = style.color(digitizer.settings.default_color) {
+                for subpath in subpaths {
+                    let points = subpath.transformed(&transform).stitch_points(
+                        digitizer.settings.stitch_length,
+                        MAX_SVG_STITCHES - digitizer.stitches,
+                    )?;
+                    digitizer.add_run(color, &points)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Outline of a basic shape or path, in user units
+fn shape_outline(name: &str, node: roxmltree::Node) -> Vec<Subpath> {
+    let number = |attribute: &str| {
+        node.attribute(attribute)
+            .and_then(|v| parse_numbers(v).first().copied())
+            .unwrap_or(0.0)
+    };
+    match name {
+        "path" => parse_path(node.attribute("d").unwrap_or("")),
+        "line" => {
+            let mut line = Subpath::new((number("x1"), number("y1")));
+            line.line_to((number("x2"), number("y2")));
+            vec![line]
+        }
+        "polyline" | "polygon" => {
+            let numbers = parse_numbers(node.attribute("points").unwrap_or(""));
+            let mut points = numbers.chunks_exact(2).map(|p| (p[0], p[1]));
+            let Some(start) = points.next() else {
+                return Vec::new();
+            };
+            let mut outline = Subpath::new(start);
+            points.for_each(|p| outline.line_to(p));
+            if name == "polygon" {
+                outline.close();
+            }
+            vec![outline]
+        }
+        "rect" => {
+            let (x, y) = (number("x"), number("y"));
+            let (width, height) = (number("width"), number("height"));
+            if width <= 0.0 || height <= 0.0 {
+                return Vec::new();
+            }
+            let (rx, ry) = match (node.attribute("rx"), node.attribute("ry")) {
+                (None, None) => (0.0, 0.0),
+                (Some(_), None) => (number("rx"), number("rx")),
+                (None, Some(_)) => (number("ry"), number("ry")),
+                (Some(_), Some(_)) => (number("rx"), number("ry")),
+            };
+            let (rx, ry) = (rx.clamp(0.0, width / 2.0), ry.clamp(0.0, height / 2.0));
+            let mut outline = Subpath::new((x + rx, y));
+            outline.line_to((x + width - rx, y));
+            outline.arc_to((rx, ry), 0.0, false, true, (x + width, y + ry));
+            outline.line_to((x + width, y + height - ry));
+            outline.arc_to((rx, ry), 0.0, false, true, (x + width - rx, y + height));
+            outline.line_to((x + rx, y + height));
+            outline.arc_to((rx, ry), 0.0, false, true, (x, y + height - ry));
+            outline.line_to((x, y + ry));
+            outline.arc_to((rx, ry), 0.0, false, true, (x + rx, y));
+            outline.close();
+            vec![outline]
+        }
+        "circle" | "ellipse" => {
+            let (cx, cy) = (number("cx"), number("cy"));
+            let (rx, ry) = if name == "circle" {
+                (number("r"), number("r"))
+            } else {
+                (number("rx"), number("ry"))
+            };
+            if rx <= 0.0 || ry <= 0.0 {
+                return Vec::new();
+            }
+            let mut outline = Subpath::new((cx + rx, cy));
+            outline.arc_to((rx, ry), 0.0, false, true, (cx, cy + ry));
+            outline.arc_to((rx, ry), 0.0, false, true, (cx - rx, cy));
+            outline.arc_to((rx, ry), 0.0, false, true, (cx, cy - ry));
+            outline.arc_to((rx, ry), 0.0, false, true, (cx + rx, cy));
+            vec![outline]
+        }
+        _ => Vec::new(),
+    }
+}
+
+type Point = (f64, f64);
+
+/// Outline segment; arcs are stored as cubic curves
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    Line(Point),
+    Quad(Point, Point),
+    Cubic(Point, Point, Point),
+}
+
+/// Connected run of segments starting at `start`
+#[derive(Debug, Clone)]
+struct Subpath {
+    start: Point,
+    segments: Vec<Segment>,
+}
+
+impl Subpath {
+    fn new(start: Point) -> Self {
+        Self {
+            start,
+            segments: Vec::new(),
+        }
+    }
+
+    fn end(&self) -> Point {
+        match self.segments.last() {
+            Some(Segment::Line(p) | Segment::Quad(_, p) | Segment::Cubic(_, _, p)) => *p,
+            None => self.start,
+        }
+    }
+
+    fn line_to(&mut self, point: Point) {
+        self.segments.push(Segment::Line(point));
+    }
+
+    fn close(&mut self) {
+        if self.end() != self.start {
+            self.line_to(self.start);
+        }
+    }
+
+    /// Append an elliptical arc (SVG endpoint parameterization) as cubic curves
+    fn arc_to(&mut self, radii: Point, rotation: f64, large: bool, sweep: bool, to: Point) {
+        let from = self.end();
+        let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+        if from == to {
+            return;
+        }
+        if rx == 0.0 || ry == 0.0 {
+            self.line_to(to);
+            return;
+        }
+
+        // Center parameterization, SVG 1.1 appendix F.6.5
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (hx, hy) = ((from.0 - to.0) / 2.0, (from.1 - to.1) / 2.0);
+        let x1 = cos * hx + sin * hy;
+        let y1 = -sin * hx + cos * hy;
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut factor = (numerator / denominator).max(0.0).sqrt();
+        if large == sweep {
+            factor = -factor;
+        }
+        let (cx1, cy1) = (factor * rx * y1 / ry, -factor * ry * x1 / rx);
+        let cx = cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.0;
+        let cy = sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.0;
+
+        let angle =
+            |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let start = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut delta = angle(
+            (x1 - cx1) / rx,
+            (y1 - cy1) / ry,
+            (-x1 - cx1) / rx,
+            (-y1 - cy1) / ry,
+        );
+        if !sweep && delta > 0.0 {
+            delta -= 2.0 * PI;
+        } else if sweep && delta < 0.0 {
+            delta += 2.0 * PI;
+        }
+
+        // One cubic per quarter turn at most
+        let pieces = (delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+        let step = delta / pieces as f64;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        let point = |theta: f64| {
+            let (s, c) = theta.sin_cos();
+            (
+                cx + rx * c * cos - ry * s * sin,
+                cy + rx * c * sin + ry * s * cos,
+            )
+        };
+        let tangent = |theta: f64| {
+            let (s, c) = theta.sin_cos();
+            (-rx * s * cos - ry * c * sin, -rx * s * sin + ry * c * cos)
+        };
+        for i in 0..pieces {
+            let (a, b) = (start + step * i as f64, start + step * (i + 1) as f64);
+            let (p0, p1) = (point(a), if i + 1 == pieces { to } else { point(b) });
+            let (t0, t1) = (tangent(a), tangent(b));
+            self.segments.push(Segment::Cubic(
+                (p0.0 + k * t0.0, p0.1 + k * t0.1),
+                (p1.0 - k * t1.0, p1.1 - k * t1.1),
+                p1,
+            ));
+        }
+    }
+
+    /// Apply an affine transform; curves stay curves
+    fn transformed(&self, matrix: &EmbMatrix) -> Self {
+        let map = |(x, y): Point| matrix.transform_point(x, y);
+        Self {
+            start: map(self.start),
+            segments: self
+                .segments
+                .iter()
+                .map(|segment| match *segment {
+                    Segment::Line(p) => Segment::Line(map(p)),
+                    Segment::Quad(c, p) => Segment::Quad(map(c), map(p)),
+                    Segment::Cubic(c1, c2, p) => Segment::Cubic(map(c1), map(c2), map(p)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Start, segment ends and curve control points
+    fn control_points(&self) -> Vec<Point> {
+        let mut points = vec![self.start];
+        for segment in &self.segments {
+            match *segment {
+                Segment::Line(p) => points.push(p),
+                Segment::Quad(c, p) => points.extend([c, p]),
+                Segment::Cubic(c1, c2, p) => points.extend([c1, c2, p]),
+            }
+        }
+        points
+    }
+
+    /// Needle positions along the outline, at most `stitch_length` apart
+    ///
+    /// Segment ends are kept so corners stay sharp; each segment is split into
+    /// equal-length stitches. Fails before sampling when the outline lies out
+    /// of range or would need more than `max_stitches` stitches.
+    fn stitch_points(&self, stitch_length: f64, max_stitches: usize) -> Result<Vec<Point>> {
+        let control = self.control_points();
+        if control
+            .iter()
+            .any(|p| !(p.0.abs() <= MAX_SVG_COORDINATE && p.1.abs() <= MAX_SVG_COORDINATE))
+        {
+            return Err(Error::Parse(format!(
+                "SVG: Coordinate outside +/-{} units",
+                MAX_SVG_COORDINATE
+            )));
+        }
+        // The control polygon is at least as long as the curves it bounds
+        let length: f64 = control.windows(2).map(|w| distance(w[0], w[1])).sum();
+        let estimate = (length / stitch_length).ceil() + control.len() as f64;
+        if estimate > max_stitches as f64 {
+            return Err(Error::limit_exceeded(
+                "SVG stitch count",
+                MAX_SVG_STITCHES,
+                (MAX_SVG_STITCHES - max_stitches).saturating_add(estimate as usize),
+            ));
+        }
+
+        let mut points = vec![self.start];
+        let mut from = self.start;
+        for segment in &self.segments {
+            // Dense polyline of the segment
+            let samples: Vec<Point> = match *segment {
+                Segment::Line(p) => vec![from, p],
+                Segment::Quad(c, p) => {
+                    let n = curve_samples(&[from, c, p], stitch_length);
+                    (0..=n)
+                        .map(|i| quad_point(from, c, p, i as f64 / n as f64))
+                        .collect()
+                }
+                Segment::Cubic(c1, c2, p) => {
+                    let n = curve_samples(&[from, c1, c2, p], stitch_length);
+                    (0..=n)
+                        .map(|i| cubic_point(from, c1, c2, p, i as f64 / n as f64))
+                        .collect()
+                }
+            };
+            resample(&samples, stitch_length, &mut points);
+            from = *samples.last().unwrap_or(&from);
+        }
+        Ok(points)
+    }
+}
+
+/// Number of samples for a curve, from its control polygon length
+fn curve_samples(control: &[Point], stitch_length: f64) -> usize {
+    let length: f64 = control.windows(2).map(|w| distance(w[0], w[1])).sum();
+    ((length / stitch_length).ceil() as usize * 8).clamp(8, 10_000)
+}
+
+fn quad_point(p0: Point, c: Point, p1: Point, t: f64) -> Point {
+    let u = 1.0 - t;
+    (
+        u * u * p0.0 + 2.0 * u * t * c.0 + t * t * p1.0,
+        u * u * p0.1 + 2.0 * u * t * c.1 + t * t * p1.1,
+    )
+}
+
+fn cubic_point(p0: Point, c1: Point, c2: Point, p1: Point, t: f64) -> Point {
+    let u = 1.0 - t;
+    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+    (
+        a * p0.0 + b * c1.0 + c * c2.0 + d * p1.0,
+        a * p0.1 + b * c1.1 + c * c2.1 + d * p1.1,
+    )
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Tokenizer for path data and number lists
+struct Lexer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace() || *b == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Next command letter, if the next token is one
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let byte = *self.bytes.get(self.pos)?;
+        if byte.is_ascii_alphabetic() && byte != b'e' && byte != b'E' {
+            self.pos += 1;
+            Some(byte)
+        } else {
+            None
+        }
+    }
+
+    fn at_number(&mut self) -> bool {
+        self.skip_separators();
+        self.bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.'))
+    }
+
+    /// Next number; handles forms like `1.5.5` and `10-2`
+    fn number(&mut self) -> Option<f64> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |lexer: &mut Self| {
+            while lexer.bytes.get(lexer.pos).is_some_and(u8::is_ascii_digit) {
+                lexer.pos += 1;
+            }
+        };
+        if matches!(self.bytes.get(self.pos), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+        digits(self);
+        if self.bytes.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            digits(self);
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.bytes.get(self.pos), Some(b'-' | b'+')) {
+                self.pos += 1;
+            }
+            let exponent_start = self.pos;
+            digits(self);
+            if self.pos == exponent_start {
+                self.pos = mantissa_end;
+            }
+        }
+        let number = std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+            .filter(|n: &f64| n.is_finite());
+        if number.is_none() {
+            self.pos = start;
+        }
+        number
+    }
+
+    /// Arc flag, which may be written without a separator (`a1 1 0 01 5 5`)
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.bytes.get(self.pos)? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+
+    fn point(&mut self) -> Option<Point> {
+        Some((self.number()?, self.number()?))
+    }
+}
+
+/// Parse path data into subpaths
+///
+/// Like browsers, parsing stops at the first error and keeps what came before.
+fn parse_path(data: &str) -> Vec<Subpath> {
+    let mut subpaths: Vec<Subpath> = Vec::new();
+    let mut lexer = Lexer::new(data);
+    let mut current = (0.0, 0.0);
+    let mut command = None;
+    // Last control point for S/T reflection
+    let mut last_control: Option<(u8, Point)> = None;
+
+    loop {
+        if let Some(next) = lexer.command() {
+            command = Some(next);
+        } else if !lexer.at_number() {
+            break;
+        }
+        let Some(cmd) = command else {
+            break;
+        };
+        let relative = cmd.is_ascii_lowercase();
+        let offset = |p: Point| {
+            if relative {
+                (current.0 + p.0, current.1 + p.1)
+            } else {
+                p
+            }
+        };
+        let reflected = |kinds: &[u8]| match last_control {
+            Some((kind, c)) if kinds.contains(&kind) => {
+                (2.0 * current.0 - c.0, 2.0 * current.1 - c.1)
+            }
+            _ => current,
+        };
+        // Segments need an open subpath
+        if !matches!(cmd.to_ascii_uppercase(), b'M' | b'Z') && subpaths.is_empty() {
+            subpaths.push(Subpath::new(current));
+        }
+
+        let mut control = None;
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                let Some(p) = lexer.point() else { break };
+                current = offset(p);
+                subpaths.push(Subpath::new(current));
+                // Further coordinate pairs are implicit line-tos
+                command = Some(if relative { b'l' } else { b'L' });
+            }
+            b'L' => {
+                let Some(p) = lexer.point() else { break };
+                current = offset(p);
+                subpaths.last_mut().unwrap().line_to(current);
+            }
+            b'H' => {
+                let Some(x) = lexer.number() else { break };
+                current.0 = if relative { current.0 + x } else { x };
+                subpaths.last_mut().unwrap().line_to(current);
+            }
+            b'V' => {
+                let Some(y) = lexer.number() else { break };
+                current.1 = if relative { current.1 + y } else { y };
+                subpaths.last_mut().unwrap().line_to(current);
+            }
+            b'C' | b'S' => {
+                let c1 = if cmd.eq_ignore_ascii_case(&b'C') {
+                    let Some(c1) = lexer.point() else { break };
+                    offset(c1)
+                } else {
+                    reflected(b"CS")
+                };
+                let (Some(c2), Some(p)) = (lexer.point(), lexer.point()) else {
+                    break;
+                };
+                let (c2, p) = (offset(c2), offset(p));
+                subpaths
+                    .last_mut()
+                    .unwrap()
+                    .segments
+                    .push(Segment::Cubic(c1, c2, p));
+                control = Some((cmd.to_ascii_uppercase(), c2));
+                current = p;
+            }
+            b'Q' | b'T' => {
+                let c = if cmd.eq_ignore_ascii_case(&b'Q') {
+                    let Some(c) = lexer.point() else { break };
+                    offset(c)
+                } else {
+                    reflected(b"QT")
+                };
+                let Some(p) = lexer.point() else { break };
+                let p = offset(p);
+                subpaths
+                    .last_mut()
+                    .unwrap()
+                    .segments
+                    .push(Segment::Quad(c, p));
+                control = Some((cmd.to_ascii_uppercase(), c));
+                current = p;
+            }
+            b'A' => {
+                let (Some(rx), Some(ry), Some(rotation)) =
+                    (lexer.number(), lexer.number(), lexer.number())
+                else {
+                    break;
+                };
+                let (Some(large), Some(sweep), Some(p)) =
+                    (lexer.flag(), lexer.flag(), lexer.point())
+                else {
+                    break;
+                };
+                let p = offset(p);
+                subpaths
+                    .last_mut()
+                    .unwrap()
+                    .arc_to((rx, ry), rotation, large, sweep, p);
+                current = p;
+            }
+            b'Z' => {
+                if let Some(subpath) = subpaths.last_mut() {
+                    subpath.close();
+                    current = subpath.start;
+                }
+                // A segment after Z starts a new subpath at the same point
+                subpaths.push(Subpath::new(current));
+                command = None;
+            }
+            _ => break,
+        }
+        // S and T only reflect the control point of a directly preceding curve
+        last_control = control;
+    }
+
+    subpaths.retain(|subpath| !subpath.segments.is_empty());
+    subpaths
+}
+
+/// Turns needle positions into pattern stitches
+struct Digitizer<'a> {
+    pattern: &'a mut EmbPattern,
+    settings: &'a SvgSettings,
+    /// Color of the current thread
+    color: Option<u32>,
+    /// Last needle position
+    position: Option<Point>,
+    stitches: usize,
+}
+
+impl Digitizer<'_> {
+    /// Stitch a run of needle positions in `color`
+    fn add_run(&mut self, color: u32, points: &[Point]) -> Result<()> {
+        let Some(&(x, y)) = points.first() else {
+            return Ok(());
+        };
+        self.stitches += points.len();
+        if self.stitches > MAX_SVG_STITCHES {
+            return Err(Error::limit_exceeded(
+                "SVG stitch count",
+                MAX_SVG_STITCHES,
+                self.stitches,
+            ));
+        }
+
+        if self.color != Some(color) {
+            if let Some((px, py)) = self.position {
+                self.pattern.add_stitch_absolute(COLOR_CHANGE, px, py);
+            }
+            self.pattern.add_thread(EmbThread::new(color));
+            self.color = Some(color);
+        } else if let Some(position) = self.position {
+            if distance(position, (x, y)) > 0.01 {
+                self.pattern
+                    .add_stitch_absolute(TRIM, position.0, position.1);
+            }
+        }
+        if self.position.is_none_or(|p| distance(p, (x, y)) > 0.01) {
+            self.pattern.add_stitch_absolute(JUMP, x, y);
+        }
+
+        for &(x, y) in points {
+            self.pattern.add_stitch_absolute(STITCH, x, y);
+        }
+        self.position = points.last().copied();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::error::ErrorKind;
+
+    fn read_str(svg: &str, settings: &SvgSettings) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        read_with_settings(&mut svg.as_bytes(), &mut pattern, settings).unwrap();
+        pattern
+    }
+
+    fn stitch_points(pattern: &EmbPattern) -> Vec<Point> {
+        pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| ((s.x * 100.0).round() / 100.0, (s.y * 100.0).round() / 100.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_path_commands() {
+        let subpaths = parse_path("M10,10 h20 v-5 L0 0 z m5 5 l1-1.5.5.5 Q 0 0 4 4 T 8 8");
+        assert_eq!(subpaths.len(), 2);
+        assert_eq!(subpaths[0].start, (10.0, 10.0));
+        assert_eq!(subpaths[0].end(), (10.0, 10.0));
+        assert_eq!(subpaths[0].segments.len(), 4);
+        // Relative move after Z is from the subpath start
+        assert_eq!(subpaths[1].start, (15.0, 15.0));
+        assert_eq!(subpaths[1].end(), (8.0, 8.0));
+        assert!(matches!(
+            subpaths[1].segments[2],
+            Segment::Quad((0.0, 0.0), (4.0, 4.0))
+        ));
+        // T reflects the previous quadratic control point
+        assert!(matches!(
+            subpaths[1].segments[3],
+            Segment::Quad((8.0, 8.0), (8.0, 8.0))
+        ));
+
+        // Parsing stops at the first error
+        assert_eq!(parse_path("M0 0 L10 0 L 5").len(), 1);
+        assert!(parse_path("L10 x").is_empty());
+    }
+
+    #[test]
+    fn test_transform_lists() {
+        let apply = |text: &str, x: f64, y: f64| {
+            let (x, y) = parse_transform(text).transform_point(x, y);
+            ((x * 1e6).round() / 1e6, (y * 1e6).round() / 1e6)
+        };
+        assert_eq!(apply("translate(10,0) scale(2)", 1.0, 1.0), (12.0, 2.0));
+        assert_eq!(apply("scale(2) translate(10)", 1.0, 1.0), (22.0, 2.0));
+        assert_eq!(apply("rotate(90)", 1.0, 0.0), (0.0, 1.0));
+        assert_eq!(apply("rotate(90 10 10)", 20.0, 10.0), (10.0, 20.0));
+        assert_eq!(apply("matrix(1 0 0 1 5 6)", 0.0, 0.0), (5.0, 6.0));
+        assert_eq!(apply("skewX(45)", 0.0, 1.0), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_running_stitch_length() {
+        let svg = r#"<svg><line x1="0" y1="0" x2="100" y2="0" stroke="blue"/></svg>"#;
+        let pattern = read_str(svg, &SvgSettings::default());
+        assert_eq!(pattern.threads()[0].color, 0x0000FF);
+        assert_eq!(
+            stitch_points(&pattern),
+            vec![
+                (0.0, 0.0),
+                (25.0, 0.0),
+                (50.0, 0.0),
+                (75.0, 0.0),
+                (100.0, 0.0)
+            ]
+        );
+
+        // Arcs are split into stitches no longer than the stitch length
+        let svg = r#"<svg><circle cx="0" cy="0" r="50" stroke="rgb(255, 0, 0)"/></svg>"#;
+        let settings = SvgSettings {
+            stitch_length: 10.0,
+            ..SvgSettings::default()
+        };
+        let pattern = read_str(svg, &settings);
+        assert_eq!(pattern.threads()[0].color, 0xFF0000);
+        let points = stitch_points(&pattern);
+        assert_eq!(points.len(), 33); // ceil(2 * PI * 50 / 10) + 1
+        assert!(points.windows(2).all(|w| distance(w[0], w[1]) <= 10.0));
+        assert!(points
+            .iter()
+            .all(|&p| (distance(p, (0.0, 0.0)) - 50.0).abs() < 0.5));
+    }
+
+    #[test]
+    fn test_colors_transforms_and_units() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20mm" height="10mm" viewBox="0 0 200 100">
+            <g stroke="#00ff00" transform="translate(10 0)">
+                <rect x="0" y="0" width="20" height="10"/>
+                <path d="M0 50 H20" style="stroke: none; fill: #123456"/>
+                <polyline points="0,90 20,90" transform="scale(2)"/>
+                <line x1="0" y1="0" x2="1" y2="1" display="none"/>
+            </g>
+            <path d="M0 0 H10" stroke="none" fill="none"/>
+        </svg>"##;
+        let pattern = read_str(svg, &SvgSettings::default());
+
+        let colors: Vec<u32> = pattern.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0x00FF00, 0x123456, 0x00FF00]);
+        assert_eq!(pattern.count_color_changes(), 2);
+
+        // 200 user units span 20 mm, i.e. one user unit per embroidery unit
+        let points = stitch_points(&pattern);
+        assert_eq!(points[0], (10.0, 0.0));
+        assert!(points.contains(&(30.0, 10.0)));
+        assert!(points.contains(&(10.0, 50.0)));
+        // The polyline is scaled, then translated
+        assert_eq!(points[points.len() - 1], (50.0, 180.0));
+    }
+
+    #[test]
+    fn test_physical_units() {
+        let svg = r#"<svg width="10mm" height="10mm"><line x1="0" y1="0" x2="10" y2="0"/></svg>"#;
+        let pattern = read_str(svg, &SvgSettings::default());
+        let points = stitch_points(&pattern);
+        assert_eq!(
+            points.last().unwrap().0,
+            (10.0 * UNITS_PER_PX * 100.0).round() / 100.0
+        );
+
+        assert_eq!(physical_length("1in"), Some(254.0));
+        assert_eq!(physical_length("100%"), None);
+        assert_eq!(physical_length("100"), None);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let mut pattern = EmbPattern::new();
+        assert!(read(&mut "<svg><path d=".as_bytes(), &mut pattern).is_err());
+        assert!(read(&mut "<html></html>".as_bytes(), &mut pattern).is_err());
+
+        let settings = SvgSettings {
+            stitch_length: 0.0,
+            ..SvgSettings::default()
+        };
+        assert!(read_with_settings(&mut "<svg/>".as_bytes(), &mut pattern, &settings).is_err());
+
+        read(&mut "<svg><text>Hi</text></svg>".as_bytes(), &mut pattern).unwrap();
+        assert!(pattern.stitches().is_empty());
+    }
+
+    #[test]
+    fn test_huge_coordinates_rejected() {
+        let mut pattern = EmbPattern::new();
+        let svg = r#"<svg><line x1="0" y1="0" x2="1e12" y2="0" stroke="red"/></svg>"#;
+        assert!(read(&mut svg.as_bytes(), &mut pattern).is_err());
+        assert!(pattern.stitches().is_empty());
+
+        let svg = r#"<svg><path d="M 0 0 L 1e400 0" stroke="red"/></svg>"#;
+        read(&mut svg.as_bytes(), &mut pattern).unwrap();
+        assert!(pattern.stitches().is_empty());
+
+        // In range, but too many stitches to sample
+        let svg = r#"<svg><line x1="0" y1="0" x2="1000000" y2="0" stroke="red"/></svg>"#;
+        let settings = SvgSettings {
+            stitch_length: 0.01,
+            ..SvgSettings::default()
+        };
+        let err = read_with_settings(&mut svg.as_bytes(), &mut pattern, &settings).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::LimitExceeded { .. }));
+    }
+}
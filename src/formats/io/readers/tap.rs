@@ -1,76 +1,55 @@
 //! Happy TAP format reader
 //!
-//! TAP is a DST variant format from Happy embroidery machines. Delegates to DST reader
-//! for parsing 3-byte stitch records with standard DST encoding.
+//! TAP is the paper tape image used by Happy (and older Tajima) machines: DST
+//! stitch records with no header. Records are decoded by the DST reader, so
+//! colors are not stored and trims are interpolated from jump runs the same
+//! way.
 
 use crate::core::pattern::EmbPattern;
 use crate::formats::io::readers::dst;
 use crate::utils::error::Result;
+use std::collections::HashMap;
 use std::io::Read;
 
-/// Read TAP (Happy Embroidery Tap) format
-///
-/// TAP format is essentially DST format with a different extension.
-/// It uses the same stitch encoding as DST.
+/// Read TAP (Happy paper tape) format
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
-    // TAP uses DST stitch encoding
-    let dst_pattern = dst::read(file, None)?;
-
-    // Copy stitches and threads to the target pattern
-    for thread in dst_pattern.threads() {
-        pattern.add_thread(thread.clone());
-    }
-
-    for stitch in dst_pattern.stitches() {
-        pattern.add_stitch_absolute(stitch.command, stitch.x, stitch.y);
-    }
-
-    // Copy metadata
-    for (key, value) in dst_pattern.metadata() {
-        pattern.add_metadata(key, value);
-    }
-
-    Ok(())
+    dst::read_stitches(file, pattern, &HashMap::new())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::constants::*;
     use std::io::Cursor;
 
     #[test]
     fn test_read_tap() {
-        // TAP uses DST encoding, so we can use DST-formatted data
-        // Create minimal DST header (512 bytes) + stitches
-        let mut tap_data = vec![0u8; 512];
+        let mut tap_data = Vec::new();
+        tap_data.extend_from_slice(&[0x01, 0x00, 0x03]); // stitch x +1
+        tap_data.extend_from_slice(&[0x00, 0x00, 0xC3]); // color change
+        tap_data.extend_from_slice(&[0x80, 0x00, 0x03]); // stitch y +1
+        tap_data.extend_from_slice(&[0x00, 0x00, 0xF3]); // end
 
-        // Add some DST-encoded stitches
-        // Stitch at (10, 10)
-        tap_data.extend_from_slice(&[0x03, 0x03, 0x03]); // Small move
-                                                         // End
-        tap_data.extend_from_slice(&[0xF3, 0x00, 0x00]);
-
-        let mut cursor = Cursor::new(tap_data);
         let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(tap_data), &mut pattern).expect("Failed to read TAP");
 
-        read(&mut cursor, &mut pattern).expect("Failed to read TAP");
-
-        // Should have at least end command
-        assert!(!pattern.stitches().is_empty());
+        assert_eq!(pattern.count_stitches(), 2);
+        assert_eq!(pattern.count_color_changes(), 1);
+        assert_eq!(pattern.stitches().last().unwrap().command, END);
+        assert_eq!(pattern.metadata().count(), 0);
     }
 
     #[test]
-    fn test_tap_compatibility_with_dst() {
-        // TAP should read exactly like DST
-        let dst_data = vec![0u8; 512]; // Minimal DST header
+    fn test_tap_matches_dst_records() {
+        let records = [0x05, 0x0A, 0x83, 0x22, 0x11, 0x03, 0x00, 0x00, 0xF3];
 
-        let pattern1 =
-            dst::read(&mut Cursor::new(dst_data.clone()), None).expect("DST read failed");
+        let mut dst_data = vec![0x20u8; 512];
+        dst_data.extend_from_slice(&records);
+        let dst_pattern = dst::read(&mut Cursor::new(dst_data), None).unwrap();
 
-        let mut pattern2 = EmbPattern::new();
-        read(&mut Cursor::new(dst_data), &mut pattern2).expect("TAP read failed");
+        let mut tap_pattern = EmbPattern::new();
+        read(&mut Cursor::new(records), &mut tap_pattern).unwrap();
 
-        // Both should produce identical results
-        assert_eq!(pattern1.stitches().len(), pattern2.stitches().len());
+        assert_eq!(tap_pattern.stitches(), dst_pattern.stitches());
     }
 }
@@ -31,7 +31,8 @@ const MAX_THREADS: usize = 256;
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
-use crate::utils::error::Result;
+use crate::formats::format::Format;
+use crate::utils::error::{Error, Result};
 use crate::utils::functions::encode_thread_change;
 use byteorder::ReadBytesExt;
 use std::io::{Read, Seek, SeekFrom};
@@ -84,10 +85,10 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
             // Validate thread count
             thread_count += 1;
             if thread_count > MAX_THREADS {
-                return Err(crate::utils::error::Error::Parse(format!(
-                    "TBF file exceeds maximum thread count of {}",
-                    MAX_THREADS
-                )));
+                return Err(
+                    Error::limit_exceeded("TBF thread count", MAX_THREADS, thread_count)
+                        .in_format(Format::TBF),
+                );
             }
 
             // Thread definition: 0x45 + R + G + B + 0x20
@@ -117,10 +118,11 @@ pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<(
         // Check for excessive stitch count
         stitch_count += 1;
         if stitch_count > MAX_STITCHES {
-            return Err(crate::utils::error::Error::Parse(format!(
-                "TBF file exceeds maximum stitch count of {}",
-                MAX_STITCHES
-            )));
+            return Err(
+                Error::limit_exceeded("TBF stitch count", MAX_STITCHES, stitch_count)
+                    .in_format(Format::TBF)
+                    .at_stitch(stitch_count - 1),
+            );
         }
 
         let x = byte[0];
@@ -7,6 +7,7 @@
 //! - Out-of-bounds values
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use crate::core::pattern::EmbPattern;
     use std::io::Cursor;
@@ -167,10 +168,7 @@ mod tests {
             let result = vp3::read(&mut cursor, &mut pattern);
             // VP3 may succeed with minimal data or fail - either is acceptable
             // The key is it shouldn't panic
-            match result {
-                Ok(_) => {}  // Handled gracefully
-                Err(_) => {} // Or returned error
-            }
+            let _ = result;
         }
     }
 
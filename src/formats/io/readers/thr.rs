@@ -0,0 +1,146 @@
+//! ThredWorks THR format reader
+//!
+//! THR is the native format of the open-source Thred/ThredWorks editor. The
+//! header starts with the signature `0x746872` ("thr") and a version byte;
+//! versions 1 and 2 follow it with a 144-byte extension (hoop size, creator and
+//! modifier names). Stitches are absolute little-endian `f32` coordinates in
+//! 0.1mm with Y pointing up, each followed by an attribute word whose low four
+//! bits select one of 16 palette colors. After the stitches come a 16-byte
+//! background bitmap name, the background color and the 16-color palette
+//! (`0x00BBGGRR`).
+//!
+//! ## Format Limitations
+//! - Forms, textures and clipboard data are ignored; only stitches are read
+//! - A change of palette color becomes a color change; there are no trims
+//! - Stitch count is a 16-bit header field (at most 65535 stitches)
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::formats::io::utils::ReadHelper;
+use crate::utils::error::{Error, Result};
+use std::io::Read;
+
+/// Signature in the low three bytes of the first header word
+pub const THR_SIGNATURE: u32 = 0x0074_6872;
+
+/// Size of the header extension of versions 1 and 2
+const EXTENSION_SIZE: usize = 144;
+
+/// Attribute bits holding the palette color of a stitch
+const COLOR_MASK: u32 = 0x0F;
+
+/// Read THR (ThredWorks) format
+pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    let mut helper = ReadHelper::new(file);
+
+    let signature = helper.read_u32_le()?;
+    if signature & 0x00FF_FFFF != THR_SIGNATURE {
+        return Err(Error::Parse("Missing THR signature".to_string()));
+    }
+    let version = signature >> 24;
+    let _file_length = helper.read_u32_le()?;
+    let stitch_count = helper.read_u16_le()?;
+    let _hoop_size = helper.read_u16_le()?;
+    helper.read_bytes(14)?;
+    match version {
+        0 => {}
+        1 | 2 => {
+            helper.read_bytes(EXTENSION_SIZE)?;
+        }
+        _ => return Err(Error::Parse(format!("Unsupported THR version {}", version))),
+    }
+
+    let mut stitches = Vec::with_capacity(stitch_count as usize);
+    for _ in 0..stitch_count {
+        let x = helper.read_f32_le()? as f64;
+        let y = helper.read_f32_le()? as f64;
+        let attribute = helper.read_u32_le()?;
+        if !x.is_finite() || !y.is_finite() {
+            return Err(Error::Parse("Non-finite THR stitch coordinate".to_string()));
+        }
+        stitches.push((x, -y, (attribute & COLOR_MASK) as usize));
+    }
+
+    // Background bitmap name and background color
+    helper.read_bytes(20)?;
+    let mut palette = [0u32; 16];
+    for color in &mut palette {
+        let colorref = helper.read_u32_le()?;
+        let (r, g, b) = (
+            colorref & 0xFF,
+            (colorref >> 8) & 0xFF,
+            (colorref >> 16) & 0xFF,
+        );
+        *color = (r << 16) | (g << 8) | b;
+    }
+
+    let mut current = None;
+    for (x, y, color) in stitches {
+        if current != Some(color) {
+            if current.is_some() {
+                pattern.add_stitch_absolute(COLOR_CHANGE, x, y);
+            }
+            pattern.add_thread(EmbThread::new(palette[color]));
+            current = Some(color);
+        }
+        pattern.add_stitch_absolute(STITCH, x, y);
+    }
+    pattern.add_stitch_relative(0.0, 0.0, END);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn thr_file(version: u32, stitches: &[(f32, f32, u32)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(THR_SIGNATURE | (version << 24)).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&(stitches.len() as u16).to_le_bytes());
+        data.extend_from_slice(&[0; 16]);
+        if version > 0 {
+            data.extend_from_slice(&[0; EXTENSION_SIZE]);
+        }
+        for &(x, y, attribute) in stitches {
+            data.extend_from_slice(&x.to_le_bytes());
+            data.extend_from_slice(&y.to_le_bytes());
+            data.extend_from_slice(&attribute.to_le_bytes());
+        }
+        data.extend_from_slice(&[0; 20]);
+        for i in 0..16u32 {
+            // COLORREF: red in the low byte
+            data.extend_from_slice(&((i * 0x10) | 0x00AA_0000).to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_read_thr() {
+        let data = thr_file(2, &[(0.0, 0.0, 3), (10.0, 5.0, 3), (20.0, 5.0, 0x100 | 5)]);
+
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(data), &mut pattern).unwrap();
+
+        assert_eq!(pattern.count_stitches(), 3);
+        assert_eq!(pattern.count_color_changes(), 1);
+        assert_eq!(pattern.bounds(), (0.0, -5.0, 20.0, 0.0));
+        let colors: Vec<u32> = pattern.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0x3000AA, 0x5000AA]);
+    }
+
+    #[test]
+    fn test_read_thr_rejects_bad_input() {
+        let mut pattern = EmbPattern::new();
+        assert!(read(&mut Cursor::new(vec![0u8; 64]), &mut pattern).is_err());
+
+        let data = thr_file(7, &[]);
+        assert!(read(&mut Cursor::new(data), &mut EmbPattern::new()).is_err());
+
+        let mut data = thr_file(0, &[(1.0, 1.0, 0)]);
+        data.truncate(40);
+        assert!(read(&mut Cursor::new(data), &mut EmbPattern::new()).is_err());
+    }
+}
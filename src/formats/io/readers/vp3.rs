@@ -3,6 +3,10 @@
 //! VP3 is Pfaff's proprietary format with compressed stitch data and extensive metadata
 //! including hoop information, thread colors, and design properties.
 //!
+//! A file may hold several design blocks, each placed at an offset in a large
+//! hoop. [`read`] places them all in one pattern; [`read_blocks`] keeps one
+//! pattern per block in an [`EmbPatternCollection`] with its offset.
+//!
 //! ## Format Limitations
 //! - String sections (metadata) limited to 10KB each
 //! - Stitch data sections limited to 30MB
@@ -21,6 +25,7 @@ const MAX_STITCH_SECTION: usize = 30_000_000;
 /// Maximum allowed stitch count
 const MAX_STITCHES: usize = 1_000_000;
 
+use crate::core::collection::EmbPatternCollection;
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::formats::io::utils::ReadHelper;
@@ -52,6 +57,93 @@ const VP3_SIGNATURE: &[u8] = b"%vsm%";
 /// # Ok::<(), butabuti::utils::error::Error>(())
 /// ```
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
+    let blocks = read_vp3(file, pattern)?;
+
+    // Place every block at its offset; blocks are separate designs, so trim between them
+    let mut ended = false;
+    let mut first = true;
+    for block in blocks {
+        if block.pattern.stitches().is_empty() {
+            continue;
+        }
+        if !first {
+            pattern.trim();
+        }
+        first = false;
+        let (dx, dy) = block.offset;
+        for stitch in block.pattern.stitches() {
+            if stitch.command & COMMAND_MASK == END {
+                ended = true;
+                continue;
+            }
+            pattern.add_stitch_absolute(stitch.command, stitch.x + dx, stitch.y + dy);
+        }
+    }
+    if ended {
+        pattern.end();
+    }
+
+    Ok(())
+}
+
+/// Read the design blocks of a VP3 file
+///
+/// Returns one pattern per block, placed at the block's offset from the hoop
+/// center, with the file's metadata. Blocks without a name are named
+/// `block_01`, `block_02` and so on; a file without blocks yields one.
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::formats::io::readers::vp3;
+/// use std::fs::File;
+///
+/// let blocks = vp3::read_blocks(&mut File::open("designs.vp3")?)?;
+/// for (name, pattern) in blocks.iter() {
+///     let (x, y) = blocks.offset(name).unwrap();
+///     println!("{} at {}, {}: {} stitches", name, x, y, pattern.count_stitches());
+/// }
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn read_blocks(file: &mut impl Read) -> Result<EmbPatternCollection> {
+    let mut metadata = EmbPattern::new();
+    let blocks = read_vp3(file, &mut metadata)?;
+
+    let mut collection = EmbPatternCollection::new();
+    for (index, block) in blocks.into_iter().enumerate() {
+        if index > 0 && block.pattern.stitches().is_empty() {
+            continue;
+        }
+        let base = block
+            .name
+            .unwrap_or_else(|| format!("block_{:02}", index + 1));
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while collection.contains(&name) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        let mut pattern = block.pattern;
+        for (key, value) in metadata.metadata() {
+            pattern.add_metadata(key, value);
+        }
+        collection.add_at(name, pattern, block.offset);
+    }
+    Ok(collection)
+}
+
+/// Design block of a VP3 file
+struct Vp3Block {
+    /// Name given in the file
+    name: Option<String>,
+    /// Offset from the hoop center (0.1mm)
+    offset: (f64, f64),
+    /// Stitches, relative to the offset
+    pattern: EmbPattern,
+}
+
+/// Check the signature and read the sections, with metadata into `pattern`
+fn read_vp3(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<Vec<Vp3Block>> {
     let mut helper = ReadHelper::new(file);
 
     // Read and verify signature
@@ -68,13 +160,14 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
 
     // Read file content until we find specific sections
     // VP3 format is quite complex with multiple sections
-    read_vp3_sections(&mut helper, pattern)?;
-
-    Ok(())
+    read_vp3_sections(&mut helper, pattern)
 }
 
 /// Read VP3 file sections
-fn read_vp3_sections<R: Read>(helper: &mut ReadHelper<R>, pattern: &mut EmbPattern) -> Result<()> {
+fn read_vp3_sections<R: Read>(
+    helper: &mut ReadHelper<R>,
+    pattern: &mut EmbPattern,
+) -> Result<Vec<Vp3Block>> {
     // VP3 files contain various sections marked by specific strings
     // We need to find and parse:
     // - %nam% - design name
@@ -82,9 +175,17 @@ fn read_vp3_sections<R: Read>(helper: &mut ReadHelper<R>, pattern: &mut EmbPatte
     // - %aut% - author
     // - %cop% - copyright
     // - %hst% - history
+    // - %blk% - design block: offset and name, for the stitch sections after it
     // - %xxs% - stitch data section
     // - %fcn% - color data
 
+    // Stitches before the first block header belong to a block at the center
+    let mut blocks = vec![Vp3Block {
+        name: None,
+        offset: (0.0, 0.0),
+        pattern: EmbPattern::new(),
+    }];
+
     while let Ok(marker) = helper.read_bytes(5) {
         if marker.starts_with(b"%") && marker.ends_with(b"%") {
             let marker_str = String::from_utf8_lossy(&marker[1..4]);
@@ -94,7 +195,18 @@ fn read_vp3_sections<R: Read>(helper: &mut ReadHelper<R>, pattern: &mut EmbPatte
                 "com" => read_string_section(helper, pattern, "comments")?,
                 "aut" => read_string_section(helper, pattern, "author")?,
                 "cop" => read_string_section(helper, pattern, "copyright")?,
-                "xxs" => read_stitch_section(helper, pattern)?,
+                "blk" => {
+                    let block = read_block_section(helper)?;
+                    // The implicit first block is dropped when nothing was stitched in it
+                    if blocks.len() == 1 && blocks[0].pattern.stitches().is_empty() {
+                        blocks.clear();
+                    }
+                    blocks.push(block);
+                }
+                "xxs" => {
+                    let block = blocks.last_mut().expect("at least one block");
+                    read_stitch_section(helper, &mut block.pattern)?
+                }
                 _ => {
                     // Unknown section, skip it
                     skip_section(helper)?;
@@ -103,7 +215,31 @@ fn read_vp3_sections<R: Read>(helper: &mut ReadHelper<R>, pattern: &mut EmbPatte
         }
     }
 
-    Ok(())
+    Ok(blocks)
+}
+
+/// Read a design block header: offset and name
+fn read_block_section<R: Read>(helper: &mut ReadHelper<R>) -> Result<Vp3Block> {
+    let length = helper.read_u16_le()? as usize;
+    if !(8..=MAX_STRING_SIZE).contains(&length) {
+        return Err(Error::Parse(format!(
+            "VP3 block section has invalid size: {} bytes (8 to {})",
+            length, MAX_STRING_SIZE
+        )));
+    }
+
+    let x = helper.read_i32_le()? as f64;
+    let y = helper.read_i32_le()? as f64;
+    let name = String::from_utf8_lossy(&helper.read_bytes(length - 8)?)
+        .trim_end_matches('\0')
+        .trim()
+        .to_string();
+
+    Ok(Vp3Block {
+        name: (!name.is_empty()).then_some(name),
+        offset: (x, y),
+        pattern: EmbPattern::new(),
+    })
 }
 
 /// Read a string metadata section
@@ -189,11 +325,7 @@ fn read_stitch_section<R: Read>(
         x += dx;
         y += dy;
 
-        let command = decode_vp3_command(flags);
-
-        if command != 0 {
-            pattern.add_stitch_absolute(command, x, y);
-        }
+        pattern.add_stitch_absolute(decode_vp3_command(flags), x, y);
     }
 
     Ok(())
@@ -223,6 +355,7 @@ fn decode_vp3_command(flags: u8) -> u32 {
 /// let pattern = vp3::read_file("design.vp3")?;
 /// # Ok::<(), butabuti::utils::error::Error>(())
 /// ```
+#[cfg(feature = "fs")]
 pub fn read_file(path: &str) -> Result<EmbPattern> {
     let file = std::fs::File::open(path)?;
     let mut reader = std::io::BufReader::new(file);
@@ -248,6 +381,47 @@ mod tests {
         assert_eq!(decode_vp3_command(0x80), END);
     }
 
+    #[test]
+    fn test_design_blocks() {
+        use crate::formats::io::writers::vp3::{write, write_blocks};
+        use std::io::Cursor;
+
+        let mut left = EmbPattern::new();
+        left.stitch_abs(0.0, 0.0);
+        left.stitch_abs(50.0, 0.0);
+        left.end();
+        let mut right = EmbPattern::new();
+        right.stitch_abs(0.0, 0.0);
+        right.stitch_abs(0.0, 30.0);
+        right.end();
+
+        let mut collection = EmbPatternCollection::new();
+        collection.add_at("left".to_string(), left.clone(), (-400.0, 0.0));
+        collection.add_at("right".to_string(), right, (400.0, 100.0));
+
+        let mut buffer = Vec::new();
+        write_blocks(&mut buffer, &collection).unwrap();
+
+        let blocks = read_blocks(&mut Cursor::new(&buffer)).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks.offset("left"), Some((-400.0, 0.0)));
+        assert_eq!(blocks.offset("right"), Some((400.0, 100.0)));
+        assert_eq!(blocks.get("left").unwrap().count_stitches(), 2);
+
+        // Reading as one design places the blocks in the hoop
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(&buffer), &mut pattern).unwrap();
+        assert_eq!(pattern.count_stitches(), 4);
+        assert_eq!(pattern.bounds(), (-400.0, 0.0, 400.0, 130.0));
+        assert_eq!(pattern.count_trims(), 1);
+
+        // Files without blocks read as one block at the center
+        let mut buffer = Vec::new();
+        write(&mut buffer, &left).unwrap();
+        let blocks = read_blocks(&mut Cursor::new(&buffer)).unwrap();
+        assert_eq!(blocks.offset("block_01"), Some((0.0, 0.0)));
+    }
+
     #[test]
     fn test_invalid_signature() {
         let data = b"Invalid data";
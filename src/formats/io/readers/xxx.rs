@@ -1,13 +1,22 @@
 //! Singer XXX format reader
 //!
-//! XXX uses variable-length encoding with 2-byte normal stitches, 5-byte long stitches,
-//! and 4-byte special commands. Colors are stored at the end after all stitches.
+//! XXX uses variable-length encoding with 2-byte normal stitches, 5-byte long
+//! stitches (0x7D) and moves (0x7E), and 4-byte control records starting with
+//! 0x7F: `7F 01` moves, `7F 03` trims, `7F 08` and `7F 0A`-`7F 17` change
+//! color, and `7F 7F` or `7F 18` end the stitches. Colors are stored at the end
+//! after all stitches.
 //!
 //! ## Format Limitations
 //! - Minimum file size: 256 bytes (header size)
 //! - Maximum 1,000 colors allowed
 //! - Maximum 1,000,000 stitches per file
 //! - Header at offset 0x00-0x100, stitches start at 0x100
+//!
+//! The color table is found through the end-of-stitches pointer at 0xFC, so
+//! records the reader doesn't know between the stitches and the table don't
+//! lose the colors. Files whose header gives no color count have their table
+//! read up to its `FF FF FF 00` terminator, and a missing table leaves the
+//! stitches without threads rather than failing the read.
 
 /// Minimum valid file size in bytes
 const MIN_FILE_SIZE: usize = 256;
@@ -18,10 +27,38 @@ const MAX_COLORS: u16 = 1000;
 /// Maximum allowed stitch count
 const MAX_STITCHES: usize = 1_000_000;
 
+/// Offset of the end-of-stitches pointer in the header
+const END_POINTER_OFFSET: usize = 0xFC;
+
+/// Color table terminator
+const COLOR_TABLE_END: u32 = 0xFFFF_FF00;
+
+/// Long stitch: 0x7D followed by 16-bit dx and dy
+pub(crate) const XXX_LONG_STITCH: u8 = 0x7D;
+
+/// Long move: 0x7E followed by 16-bit dx and dy
+pub(crate) const XXX_LONG_JUMP: u8 = 0x7E;
+
+/// First byte of a 4-byte control record
+pub(crate) const XXX_CONTROL: u8 = 0x7F;
+
+/// Control code of a move
+pub(crate) const XXX_JUMP: u8 = 0x01;
+
+/// Control code of a trim
+pub(crate) const XXX_TRIM: u8 = 0x03;
+
+/// Control code of a color change
+pub(crate) const XXX_COLOR_CHANGE: u8 = 0x08;
+
+/// Control code of the end marker
+pub(crate) const XXX_END: u8 = 0x7F;
+
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
 use crate::utils::error::Result;
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt};
 use std::io::Read;
 
 /// Read a signed 8-bit value as f64
@@ -52,8 +89,7 @@ fn read_signed_i16(value: u16) -> f64 {
 /// butabuti::formats::io::readers::xxx::read(&mut file, &mut pattern).unwrap();
 /// ```
 pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
-    // Skip to color count at offset 0x27
-    let mut header = vec![0u8; 0x27];
+    let mut header = vec![0u8; 0x100];
     file.read_exact(&mut header).map_err(|e| {
         if e.kind() == std::io::ErrorKind::UnexpectedEof {
             crate::utils::error::Error::Parse(format!(
@@ -65,7 +101,7 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
         }
     })?;
 
-    let num_colors = file.read_u16::<LittleEndian>()?;
+    let num_colors = u16::from_le_bytes([header[0x27], header[0x28]]);
 
     // Validate color count
     if num_colors > MAX_COLORS {
@@ -75,12 +111,16 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
         )));
     }
 
-    // Skip to stitch data at offset 0x100
-    let mut skip_bytes = vec![0u8; 0x100 - 0x27 - 2];
-    file.read_exact(&mut skip_bytes)?;
+    let end_pointer = u32::from_le_bytes([
+        header[END_POINTER_OFFSET],
+        header[END_POINTER_OFFSET + 1],
+        header[END_POINTER_OFFSET + 2],
+        header[END_POINTER_OFFSET + 3],
+    ]) as usize;
 
-    // Read stitches
+    // Read stitches, tracking the offset to find the color table
     let mut stitch_count = 0;
+    let mut position = 0x100;
 
     loop {
         // Check for excessive stitch count
@@ -93,19 +133,23 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
         }
 
         let b1 = file.read_u8()?;
+        position += 1;
 
-        // Long stitch/jump (0x7D or 0x7E)
-        if b1 == 0x7D || b1 == 0x7E {
+        // Long stitch (0x7D) or move (0x7E)
+        if b1 == XXX_LONG_STITCH || b1 == XXX_LONG_JUMP {
             let x = file.read_u16::<LittleEndian>()?;
             let y = file.read_u16::<LittleEndian>()?;
-            pattern.add_stitch_relative(read_signed_i16(x), -read_signed_i16(y), JUMP);
+            position += 4;
+            let command = if b1 == XXX_LONG_STITCH { STITCH } else { JUMP };
+            pattern.add_stitch_relative(read_signed_i16(x), -read_signed_i16(y), command);
             continue;
         }
 
         let b2 = file.read_u8()?;
+        position += 1;
 
         // Normal stitch (not starting with 0x7F)
-        if b1 != 0x7F {
+        if b1 != XXX_CONTROL {
             pattern.add_stitch_relative(read_signed_i8(b1), -read_signed_i8(b2), STITCH);
             continue;
         }
@@ -113,26 +157,27 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
         // Special command (starting with 0x7F)
         let b3 = file.read_u8()?;
         let b4 = file.read_u8()?;
+        position += 2;
 
         match b2 {
             // Move (0x7F 01 dx dy)
-            0x01 => {
+            XXX_JUMP => {
                 pattern.add_stitch_relative(read_signed_i8(b3), -read_signed_i8(b4), JUMP);
             }
             // Trim (0x7F 03 dx dy)
-            0x03 => {
+            XXX_TRIM => {
                 let x = read_signed_i8(b3);
                 let y = -read_signed_i8(b4);
                 pattern.add_stitch_relative(x, y, TRIM);
             }
             // Color change (0x7F 08 or 0x7F 0A-17)
-            0x08 | 0x0A..=0x17 => {
+            XXX_COLOR_CHANGE | 0x0A..=0x17 => {
                 let x = read_signed_i8(b3);
                 let y = -read_signed_i8(b4);
                 pattern.add_stitch_relative(x, y, COLOR_CHANGE);
             }
             // End (0x7F 7F or 0x7F 18)
-            0x7F | 0x18 => {
+            XXX_END | 0x18 => {
                 break;
             }
             _ => {
@@ -143,24 +188,63 @@ pub fn read(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
 
     pattern.end();
 
-    // Skip 2 bytes before color data
-    let mut skip = [0u8; 2];
-    file.read_exact(&mut skip)?;
-
-    // Read thread colors
-    for _ in 0..num_colors {
-        let color = file.read_u32::<BigEndian>()?;
-        let thread = crate::core::thread::EmbThread::from_rgb(
-            ((color >> 16) & 0xFF) as u8,
-            ((color >> 8) & 0xFF) as u8,
-            (color & 0xFF) as u8,
-        );
+    // The end-of-stitches pointer gives the end marker; skip anything before it
+    if end_pointer + 4 > position {
+        std::io::copy(
+            &mut file.take((end_pointer + 4 - position) as u64),
+            &mut std::io::sink(),
+        )?;
+    }
+
+    for thread in read_colors(file, num_colors)? {
         pattern.add_thread(thread);
     }
 
     Ok(())
 }
 
+/// Read the color table after the end marker
+///
+/// Reads `count` entries, or up to the terminator when `count` is 0. A table
+/// cut short ends the colors read so far.
+fn read_colors(file: &mut impl Read, count: u16) -> Result<Vec<EmbThread>> {
+    let mut threads = Vec::new();
+
+    // Skip 2 bytes before color data
+    let mut skip = [0u8; 2];
+    if !read_all(file, &mut skip)? {
+        return Ok(threads);
+    }
+
+    let limit = if count > 0 { count } else { MAX_COLORS };
+    let mut entry = [0u8; 4];
+    while threads.len() < limit as usize && read_all(file, &mut entry)? {
+        let color = u32::from_be_bytes(entry);
+        if count == 0 && color == COLOR_TABLE_END {
+            break;
+        }
+        threads.push(EmbThread::from_rgb(entry[1], entry[2], entry[3]));
+    }
+
+    // Without a count, the zero slots padding the table aren't colors
+    if count == 0 {
+        while threads.last().is_some_and(|thread| thread.color == 0) {
+            threads.pop();
+        }
+    }
+
+    Ok(threads)
+}
+
+/// Fill `buffer`, or return false at the end of the file
+fn read_all(file: &mut impl Read, buffer: &mut [u8]) -> Result<bool> {
+    match file.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,9 +297,70 @@ mod tests {
         read(&mut cursor, &mut pattern).unwrap();
 
         let stitches = pattern.stitches();
-        assert!(!stitches.is_empty());
         // Y is flipped, so 100 becomes -100
-        assert_eq!((stitches[0].command & COMMAND_MASK), JUMP);
+        assert_eq!(
+            (stitches[0].command, stitches[0].x, stitches[0].y),
+            (STITCH, 200.0, -100.0)
+        );
+    }
+
+    #[test]
+    fn test_read_xxx_long_jump() {
+        let mut data = vec![0u8; 0x100];
+        data.extend_from_slice(&[
+            0x7E, // Long move marker
+            0x2C, 0x01, // dx = 300
+            0x38, 0xFF, // dy = -200
+            0x7F, 0x03, 0, 0, // Trim
+            0x7F, 0x7F, 0x02, 0x14, // End
+        ]);
+
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(data), &mut pattern).unwrap();
+
+        let records: Vec<(u32, f64, f64)> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.command & COMMAND_MASK, s.x, s.y))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (JUMP, 300.0, 200.0),
+                (TRIM, 300.0, 200.0),
+                (END, 300.0, 200.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_xxx_colors_past_unknown_records() {
+        let mut data = vec![0u8; 0x100];
+        // No color count; end-of-stitches pointer at 0x10A
+        data[0xFC] = 0x0A;
+        data[0xFD] = 0x01;
+
+        data.extend_from_slice(&[
+            5, 5, // Normal stitch
+            0x7F, 0x18, 0x00, 0x00, // End of the stitches
+            0x7F, 0x46, 0x00, 0x00, // Unknown record
+            0x7F, 0x7F, 0x02, 0x14, // End marker
+            0x00, 0x00, // Skip
+            0x00, 0xCC, 0x22, 0x33, // Color
+            0x00, 0x00, 0x00, 0x00, // Empty slot
+            0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x01, // Terminator
+        ]);
+
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(&data), &mut pattern).unwrap();
+        let colors: Vec<u32> = pattern.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0xCC2233]);
+
+        // Without a color table the stitches are kept
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(&data[..0x10E]), &mut pattern).unwrap();
+        assert_eq!(pattern.count_stitches(), 1);
+        assert!(pattern.threads().is_empty());
     }
 
     #[test]
@@ -6,6 +6,7 @@
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::io::utils::read_exact_vec;
 use crate::utils::error::Result;
 use std::io::{Read, Seek, SeekFrom};
 
@@ -45,8 +46,7 @@ fn read_zhs_header(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()>
 
     // Read thread metadata string
     let length = read_u16_le(file)?;
-    let mut string_buf = vec![0u8; length as usize];
-    file.read_exact(&mut string_buf)?;
+    let string_buf = read_exact_vec(file, length as usize)?;
 
     // Try to parse thread metadata (best effort)
     if let Ok(thread_data) = String::from_utf8(string_buf) {
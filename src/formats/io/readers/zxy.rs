@@ -1,75 +1,95 @@
-//! ZSK USA ZXY format reader
+//! ZSK TC (ZXY) format reader
 //!
-//! ZXY is ZSK USA's embroidery format with coordinate-based stitches and colors,
-//! designed for ZSK industrial embroidery equipment.
+//! ZSK TC punch data, written by ZSK machine software as `.zxy` or with a
+//! numbered `.z??` extension. A big-endian 16-bit value at offset 1 gives the
+//! length of the header that follows it; stitch data then runs in 3-byte
+//! records of a control byte and the x and y magnitudes. Control bits 0x08 and
+//! 0x04 flip the x and y directions and 0x02 marks a move.
+//!
+//! Records with control bit 0x20 are machine functions. A second byte of 0xFF
+//! ends the design; any other function is read as a change to the needle in
+//! the third byte.
+//!
+//! ## Format Limitations
+//! - Stitch range: ±127 per record on each axis
+//! - No trim or stop records
+//! - Maximum 1,000,000 records per file
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
-use crate::utils::error::Result;
+use crate::utils::error::{Error, Result};
 use crate::utils::functions::encode_thread_change;
 use std::io::{Read, Seek, SeekFrom};
 
+/// Maximum allowed record count
+const MAX_STITCHES: usize = 1_000_000;
+
+/// Control bit of a move record
+pub(crate) const ZXY_MOVE: u8 = 0x02;
+/// Control bit flipping the y direction
+pub(crate) const ZXY_Y_SIGN: u8 = 0x04;
+/// Control bit flipping the x direction
+pub(crate) const ZXY_X_SIGN: u8 = 0x08;
+/// Control bit of a function record
+pub(crate) const ZXY_FUNCTION: u8 = 0x20;
+
+/// Function code: change to the needle in the argument byte
+pub(crate) const ZXY_NEEDLE: u8 = 0x00;
+/// Function code: end of design
+pub(crate) const ZXY_END: u8 = 0xFF;
+
 /// Read ZXY (ZSK TC) format
 pub fn read(file: &mut (impl Read + Seek), pattern: &mut EmbPattern) -> Result<()> {
-    // Seek to offset 0x01
     file.seek(SeekFrom::Start(0x01))?;
-
     let stitch_start_distance = read_u16_be(file)?;
-
-    // Skip to stitch data
     file.seek(SeekFrom::Current(stitch_start_distance as i64))?;
 
-    read_zxy_stitches(file, pattern)?;
-
-    Ok(())
+    read_zxy_stitches(file, pattern)
 }
 
-/// Read ZXY stitches
+/// Read ZXY stitch and function records
 fn read_zxy_stitches(file: &mut impl Read, pattern: &mut EmbPattern) -> Result<()> {
     let mut buffer = [0u8; 3];
+    let mut count = 0;
 
     loop {
-        // Read 3 bytes
         match file.read_exact(&mut buffer) {
             Ok(_) => {}
             Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
             Err(e) => return Err(e.into()),
         }
+        count += 1;
+        if count > MAX_STITCHES {
+            return Err(Error::Parse(format!(
+                "ZXY file exceeds maximum record count of {}",
+                MAX_STITCHES
+            )));
+        }
 
-        let mut ctrl = buffer[0];
-        let mut x = buffer[1] as i8 as i32;
-        let mut y = -(buffer[2] as i8 as i32);
+        let ctrl = buffer[0];
+        if ctrl & ZXY_FUNCTION != 0 {
+            if buffer[1] == ZXY_END {
+                break;
+            }
+            let command = encode_thread_change(NEEDLE_SET, None, Some(buffer[2]), None);
+            pattern.add_stitch_relative(0.0, 0.0, command);
+            continue;
+        }
 
-        // Check sign bits
-        if ctrl & 0x08 != 0 {
+        let mut x = buffer[1] as i8 as f64;
+        let mut y = -(buffer[2] as i8 as f64);
+        if ctrl & ZXY_X_SIGN != 0 {
             x = -x;
         }
-        if ctrl & 0x04 != 0 {
+        if ctrl & ZXY_Y_SIGN != 0 {
             y = -y;
         }
 
-        // Clear sign bits
-        ctrl &= !0x0C;
-
-        if ctrl == 0 {
-            // Stitch
-            pattern.add_stitch_relative(x as f64, y as f64, STITCH);
-        } else if ctrl & 0x02 != 0 {
-            // Move/Jump
-            pattern.add_stitch_relative(x as f64, y as f64, JUMP);
-        } else if ctrl & 0x20 != 0 {
-            // Needle change or end
-            if buffer[1] == 0xFF {
-                // End marker
-                break;
-            }
-            let needle = buffer[2];
-            let command = encode_thread_change(NEEDLE_SET, None, Some(needle), None);
-            pattern.add_command(command, 0.0, 0.0);
-        }
+        let command = if ctrl & ZXY_MOVE != 0 { JUMP } else { STITCH };
+        pattern.add_stitch_relative(x, y, command);
     }
 
-    pattern.add_command(END, 0.0, 0.0);
+    pattern.add_stitch_relative(0.0, 0.0, END);
     Ok(())
 }
 
@@ -145,4 +165,42 @@ mod tests {
 
         assert!(!pattern.stitches().is_empty());
     }
+
+    #[test]
+    fn test_zxy_function_records() {
+        let mut zxy_data = vec![0u8, 0, 0];
+        zxy_data.extend_from_slice(&[0, 10, 0]);
+        zxy_data.extend_from_slice(&[ZXY_FUNCTION, ZXY_NEEDLE, 2]);
+        zxy_data.extend_from_slice(&[0, 0, 10]);
+        zxy_data.extend_from_slice(&[ZXY_FUNCTION, 0x03, 4]); // other codes change needle too
+        zxy_data.extend_from_slice(&[ZXY_FUNCTION, ZXY_END, 0]);
+        zxy_data.extend_from_slice(&[0, 50, 50]); // after the end marker
+
+        let mut pattern = EmbPattern::new();
+        read(&mut Cursor::new(zxy_data), &mut pattern).unwrap();
+
+        let records: Vec<(u32, f64, f64)> = pattern
+            .stitches()
+            .iter()
+            .map(|s| (s.command, s.x, s.y))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (STITCH, 10.0, 0.0),
+                (
+                    encode_thread_change(NEEDLE_SET, None, Some(2), None),
+                    10.0,
+                    0.0
+                ),
+                (STITCH, 10.0, -10.0),
+                (
+                    encode_thread_change(NEEDLE_SET, None, Some(4), None),
+                    10.0,
+                    -10.0
+                ),
+                (END, 10.0, -10.0),
+            ]
+        );
+    }
 }
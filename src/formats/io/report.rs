@@ -0,0 +1,395 @@
+//! Reports of lossy writes
+//!
+//! Formats can't hold everything a pattern does: DST has no cut, EXP has no
+//! metadata, most machine formats limit how far one record may move. Writers
+//! adjust the pattern to fit, and
+//! [`PatternWriter::write_with_report`](crate::formats::io::traits::PatternWriter::write_with_report)
+//! returns a [`WriteReport`] listing those adjustments: split stitches,
+//! converted, dropped and inserted commands, and metadata, threads and stitch
+//! attributes the file doesn't store.
+//!
+//! The report describes what the writer did, without reading the file back;
+//! see [`verify`](crate::formats::io::verify) for a round-trip comparison.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::formats::io::options::WriteOptions;
+//! use butabuti::formats::io::report::WriteChange;
+//! use butabuti::formats::io::traits::PatternWriter;
+//! use std::io::Cursor;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.set_title("Rose");
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.cut();
+//! pattern.stitch_abs(100.0, 50.0);
+//! pattern.end();
+//!
+//! let mut buffer = Cursor::new(Vec::new());
+//! let report = Format::EXP.write_with_report(&pattern, &mut buffer, &WriteOptions::default())?;
+//! assert_eq!(report.converted_commands(), 1);
+//! assert!(report.changes.contains(&WriteChange::MetadataDropped {
+//!     keys: vec!["name".to_string()],
+//! }));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::{command_name, STITCH};
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use std::fmt;
+
+/// Metadata keys of a pattern license, for writers that store the license
+pub(crate) const LICENSE_KEYS: [&str; 3] = ["license", "license_uses", "license_purchaser_id"];
+
+/// One adjustment a writer made to fit a pattern into its format
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteChange {
+    /// Stitches longer than the format allows, split into shorter ones
+    SplitStitches {
+        /// Stitches split
+        count: usize,
+        /// Longest stitch of the format (0.1mm)
+        max_length: f64,
+    },
+    /// Commands written as another command
+    Converted {
+        /// Command in the pattern, e.g. "CUT"
+        from: &'static str,
+        /// Command written, e.g. "TRIM"
+        to: &'static str,
+        /// Commands converted
+        count: usize,
+    },
+    /// Commands the format can't hold, left out
+    Dropped {
+        /// Command name
+        command: &'static str,
+        /// Commands dropped
+        count: usize,
+    },
+    /// Commands added to fit the format, like jumps splitting a long move
+    Inserted {
+        /// Command name
+        command: &'static str,
+        /// Commands added
+        count: usize,
+    },
+    /// Metadata keys the format has no field for, sorted
+    MetadataDropped {
+        /// Keys not written
+        keys: Vec<String>,
+    },
+    /// Metadata value cut to fit its field
+    MetadataTruncated {
+        /// Key of the value
+        key: String,
+        /// Bytes kept
+        length: usize,
+    },
+    /// Thread colors and descriptions not written
+    ThreadsDropped {
+        /// Threads of the pattern
+        count: usize,
+    },
+    /// Per-stitch attributes not written
+    AttributesDropped {
+        /// Records with attributes
+        count: usize,
+    },
+}
+
+impl fmt::Display for WriteChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteChange::SplitStitches { count, max_length } => {
+                write!(f, "{} stitches longer than {} split", count, max_length)
+            }
+            WriteChange::Converted { from, to, count } => {
+                write!(f, "{} {} converted to {}", count, from, to)
+            }
+            WriteChange::Dropped { command, count } => write!(f, "{} {} dropped", count, command),
+            WriteChange::Inserted { command, count } => {
+                write!(f, "{} {} inserted", count, command)
+            }
+            WriteChange::MetadataDropped { keys } => {
+                write!(f, "metadata not stored: {}", keys.join(", "))
+            }
+            WriteChange::MetadataTruncated { key, length } => {
+                write!(f, "{} truncated to {} bytes", key, length)
+            }
+            WriteChange::ThreadsDropped { count } => write!(f, "{} threads not stored", count),
+            WriteChange::AttributesDropped { count } => {
+                write!(f, "attributes of {} stitches not stored", count)
+            }
+        }
+    }
+}
+
+/// Adjustments a writer made to a pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteReport {
+    /// Format written
+    pub format: Format,
+    /// Adjustments, empty when the pattern was written as it is
+    pub changes: Vec<WriteChange>,
+}
+
+impl WriteReport {
+    /// Report without adjustments
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Whether the pattern was written without adjustments
+    pub fn is_lossless(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of commands written as another command
+    pub fn converted_commands(&self) -> usize {
+        self.changes
+            .iter()
+            .map(|change| match change {
+                WriteChange::Converted { count, .. } => *count,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Number of commands left out
+    pub fn dropped_commands(&self) -> usize {
+        self.changes
+            .iter()
+            .map(|change| match change {
+                WriteChange::Dropped { count, .. } => *count,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Record one command written as another command
+    pub(crate) fn convert_command(&mut self, from: u32, to: u32) {
+        let (from, to) = (command_name(from), command_name(to));
+        for change in &mut self.changes {
+            if let WriteChange::Converted {
+                from: was,
+                to: is,
+                count,
+            } = change
+            {
+                if (*was, *is) == (from, to) {
+                    *count += 1;
+                    return;
+                }
+            }
+        }
+        self.changes
+            .push(WriteChange::Converted { from, to, count: 1 });
+    }
+
+    /// Record one command left out
+    pub(crate) fn drop_command(&mut self, command: u32) {
+        let name = command_name(command);
+        for change in &mut self.changes {
+            if let WriteChange::Dropped { command, count } = change {
+                if *command == name {
+                    *count += 1;
+                    return;
+                }
+            }
+        }
+        self.changes.push(WriteChange::Dropped {
+            command: name,
+            count: 1,
+        });
+    }
+
+    /// Record the metadata of `pattern` outside the keys the writer stores
+    pub(crate) fn drop_metadata(&mut self, pattern: &EmbPattern, stored: &[&str]) {
+        // `<key>_raw` entries only mirror the bytes of another key
+        let mut keys: Vec<String> = pattern
+            .metadata()
+            .map(|(key, _)| key)
+            .filter(|key| !key.ends_with("_raw") && !stored.contains(&key.as_str()))
+            .cloned()
+            .collect();
+        if !keys.is_empty() {
+            keys.sort_unstable();
+            self.changes.push(WriteChange::MetadataDropped { keys });
+        }
+    }
+
+    /// Record a metadata value cut to `length` bytes to fit its field
+    pub(crate) fn truncate_metadata(&mut self, key: &str, length: usize) {
+        self.changes.push(WriteChange::MetadataTruncated {
+            key: key.to_string(),
+            length,
+        });
+    }
+
+    /// Record `count` threads as not written
+    pub(crate) fn drop_threads(&mut self, count: usize) {
+        if count > 0 {
+            self.changes.push(WriteChange::ThreadsDropped { count });
+        }
+    }
+
+    /// Record the stitch attributes of `pattern` as not written
+    pub(crate) fn drop_attributes(&mut self, pattern: &EmbPattern) {
+        let count = pattern.attributes().len();
+        if count > 0 {
+            self.changes.push(WriteChange::AttributesDropped { count });
+        }
+    }
+
+    /// Record the stitches of `pattern` as not written
+    pub(crate) fn drop_stitches(&mut self, pattern: &EmbPattern) {
+        let count = pattern.count_stitches();
+        if count > 0 {
+            self.changes.push(WriteChange::Dropped {
+                command: command_name(STITCH),
+                count,
+            });
+        }
+    }
+}
+
+impl fmt::Display for WriteReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.format)?;
+        if self.changes.is_empty() {
+            return write!(f, "written as is");
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::SLOW;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::options::WriteOptions;
+    use crate::formats::io::traits::PatternWriter;
+    use std::io::Cursor;
+
+    fn write(pattern: &EmbPattern, format: Format, options: &WriteOptions) -> WriteReport {
+        let mut buffer = Cursor::new(Vec::new());
+        format
+            .write_with_report(pattern, &mut buffer, options)
+            .unwrap()
+    }
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.set_title("Rose");
+        pattern.set_author("Jane Doe");
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(300.0, 0.0);
+        pattern.cut();
+        pattern.add_command(SLOW, 300.0, 0.0);
+        pattern.stitch_abs(300.0, 50.0);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_write_report_dst() {
+        let pattern = sample();
+        let report = write(&pattern, Format::DST, &WriteOptions::default());
+        assert_eq!(
+            report.changes,
+            vec![
+                WriteChange::SplitStitches {
+                    count: 1,
+                    max_length: 121.0
+                },
+                WriteChange::Converted {
+                    from: "CUT",
+                    to: "TRIM",
+                    count: 1
+                },
+                WriteChange::Inserted {
+                    command: "JUMP",
+                    count: 3
+                },
+                WriteChange::Dropped {
+                    command: "SLOW",
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(report.converted_commands(), 1);
+        assert_eq!(report.dropped_commands(), 1);
+
+        let plain = WriteOptions::new().dst_extended_header(false);
+        let report = write(&pattern, Format::DST, &plain);
+        assert!(report.changes.contains(&WriteChange::MetadataDropped {
+            keys: vec!["author".to_string()]
+        }));
+        assert!(report
+            .changes
+            .contains(&WriteChange::ThreadsDropped { count: 1 }));
+    }
+
+    #[test]
+    fn test_write_report_other_formats() {
+        let mut pattern = sample();
+        pattern.set_stitch_attribute(1, "needle", "2").unwrap();
+
+        let report = write(&pattern, Format::JSON, &WriteOptions::default());
+        assert!(report.is_lossless());
+        assert_eq!(report.to_string(), "JSON: written as is");
+
+        // U01 writers encode internally
+        let report = write(&pattern, Format::U01, &WriteOptions::default());
+        assert!(matches!(
+            report.changes[0],
+            WriteChange::SplitStitches { count: 1, .. }
+        ));
+        assert!(report.changes.contains(&WriteChange::MetadataDropped {
+            keys: vec!["author".to_string(), "name".to_string()]
+        }));
+        assert!(report
+            .changes
+            .contains(&WriteChange::AttributesDropped { count: 1 }));
+
+        // DSB keeps every command through encoding; its writer leaves these out
+        let report = write(&pattern, Format::DSB, &WriteOptions::default());
+        assert_eq!(report.dropped_commands(), 2);
+        assert!(report.changes.contains(&WriteChange::Dropped {
+            command: "CUT",
+            count: 1
+        }));
+
+        pattern.set_title("Rose garden");
+        let report = write(&pattern, Format::PEC, &WriteOptions::default());
+        assert!(report.changes.contains(&WriteChange::MetadataTruncated {
+            key: "name".to_string(),
+            length: 8
+        }));
+
+        let report = write(&pattern, Format::COL, &WriteOptions::default());
+        assert_eq!(
+            report.changes[0],
+            WriteChange::Dropped {
+                command: "STITCH",
+                count: 3
+            }
+        );
+    }
+}
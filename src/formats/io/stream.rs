@@ -0,0 +1,583 @@
+//! Streaming stitch readers
+//!
+//! [`StitchStream`] decodes stitch records lazily from a `Read` source instead of
+//! collecting them into an [`EmbPattern`], so statistics over very large files
+//! (bounds, stitch length, command counts) can be computed in constant memory.
+//!
+//! Supported formats: DST, EXP, PEC, and PES (through its embedded PEC section).
+//!
+//! The stream yields the records as they are stored in the file, in absolute
+//! coordinates (0.1mm units), always finishing with an `END` record. Unlike the
+//! full readers it applies no post-processing: DST jump runs are not converted to
+//! trims, PEC duplicate colors are not converted to stops, and PES thread charts
+//! are ignored in favor of the PEC palette colors. Nothing is buffered beyond one
+//! read block, so the readers' stitch count limits don't apply.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use butabuti::formats::io::stream::StitchStream;
+//!
+//! let summary = StitchStream::open("industrial.dst")?.summarize()?;
+//! println!(
+//!     "{} stitches, {:.1} mm of thread, {:.1} x {:.1} mm",
+//!     summary.stitches,
+//!     summary.stitch_length / 10.0,
+//!     summary.bounds.width() / 10.0,
+//!     summary.bounds.height() / 10.0
+//! );
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::{Bounds, EmbPattern, Stitch};
+#[cfg(feature = "fs")]
+use crate::formats::io::detector::detect_format;
+use crate::formats::io::options::ReadOptions;
+use crate::formats::io::readers::{dst, pec};
+use crate::formats::io::utils::read_block;
+use crate::utils::error::{Error, Result};
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{self, Read};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Bytes read from the source at a time
+const BLOCK_SIZE: usize = 16 * 1024;
+
+/// Largest PEC section offset accepted in a PES header
+const MAX_PEC_OFFSET: u32 = 100_000_000;
+
+/// Record decoder of a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decoder {
+    Dst { sequin_mode: bool },
+    Exp,
+    Pec,
+}
+
+/// Lazily decoded stitch records of an embroidery file
+///
+/// Iterates over `Result<Stitch>`; a read error ends the stream after it is
+/// returned.
+pub struct StitchStream<R: Read> {
+    reader: R,
+    decoder: Decoder,
+    header: EmbPattern,
+    block: Vec<u8>,
+    start: usize,
+    end: usize,
+    eof: bool,
+    x: f64,
+    y: f64,
+    pending: Option<Stitch>,
+    finished: bool,
+}
+
+#[cfg(feature = "fs")]
+impl StitchStream<File> {
+    /// Open a file, choosing the decoder from its extension
+    ///
+    /// Files with a missing or unstreamable extension, or whose magic bytes
+    /// identify another format (PES or PEC saved under a different extension),
+    /// are decoded by content.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        let detected = detect_format(&mut file)
+            .ok()
+            .filter(|format| {
+                format.has_signature()
+                    || !matches!(extension.as_deref(), Some("dst" | "exp" | "pec" | "pes"))
+            })
+            .and_then(|format| format.registry_name());
+
+        let format = detected
+            .or(extension.as_deref())
+            .ok_or_else(|| Error::UnsupportedFormat("No file extension".to_string()))?
+            .to_string();
+        Self::new(file, &format)
+    }
+}
+
+impl<R: Read> StitchStream<R> {
+    /// Stream a file of the given format ("dst", "exp", "pec" or "pes")
+    pub fn new(reader: R, format: &str) -> Result<Self> {
+        match format.to_lowercase().as_str() {
+            "dst" => Self::dst(reader),
+            "exp" => Ok(Self::exp(reader)),
+            "pec" => Self::pec(reader),
+            "pes" => Self::pes(reader),
+            _ => Err(Error::UnsupportedFormat(format!(
+                "Streaming is not supported for format: {}",
+                format
+            ))),
+        }
+    }
+
+    /// Stream a DST file, reading its 512-byte header first
+    pub fn dst(mut reader: R) -> Result<Self> {
+        let mut header = EmbPattern::new();
+        dst::read_header(&mut reader, &mut header)?;
+        Ok(Self::with_header(
+            reader,
+            Decoder::Dst { sequin_mode: false },
+            header,
+        ))
+    }
+
+    /// Stream an EXP file (EXP has no header)
+    pub fn exp(reader: R) -> Self {
+        Self::with_header(reader, Decoder::Exp, EmbPattern::new())
+    }
+
+    /// Stream a standalone PEC file
+    pub fn pec(mut reader: R) -> Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != b"#PEC0001" {
+            return Err(Error::Parse(format!(
+                "Invalid PEC header: expected '#PEC0001', got '{}'",
+                String::from_utf8_lossy(&magic)
+            )));
+        }
+        Self::pec_section(reader)
+    }
+
+    /// Stream the PEC section embedded in a PES file
+    ///
+    /// The PES section before it is skipped without seeking.
+    pub fn pes(mut reader: R) -> Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != b"#PES" {
+            return Err(Error::Parse(format!(
+                "Invalid PES header: expected '#PES', got '{}'",
+                String::from_utf8_lossy(&header[..4])
+            )));
+        }
+
+        let offset = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if !(12..=MAX_PEC_OFFSET).contains(&offset) {
+            return Err(Error::Parse(format!(
+                "Invalid PEC block offset: {}",
+                offset
+            )));
+        }
+        let skip = u64::from(offset) - 12;
+        if io::copy(&mut (&mut reader).take(skip), &mut io::sink())? < skip {
+            return Err(Error::Parse(
+                "PES file ends before its PEC section".to_string(),
+            ));
+        }
+
+        Self::pec_section(reader)
+    }
+
+    fn pec_section(mut reader: R) -> Result<Self> {
+        let mut header = EmbPattern::new();
+        let mut warnings = Vec::new();
+        pec::read_pec_header(
+            &mut reader,
+            &mut header,
+            None,
+            &ReadOptions::default(),
+            &mut warnings,
+        )?;
+        Ok(Self::with_header(reader, Decoder::Pec, header))
+    }
+
+    fn with_header(reader: R, decoder: Decoder, header: EmbPattern) -> Self {
+        Self {
+            reader,
+            decoder,
+            header,
+            block: vec![0u8; BLOCK_SIZE],
+            start: 0,
+            end: 0,
+            eof: false,
+            x: 0.0,
+            y: 0.0,
+            pending: None,
+            finished: false,
+        }
+    }
+
+    /// Metadata and threads read from the file header (the pattern has no stitches)
+    pub fn header(&self) -> &EmbPattern {
+        &self.header
+    }
+
+    /// Consume the stream and summarize its records
+    pub fn summarize(self) -> Result<StreamSummary> {
+        let mut summary = StreamSummary::default();
+        for stitch in self {
+            summary.add(&stitch?);
+        }
+        Ok(summary)
+    }
+
+    /// Up to `len` unread bytes; fewer only at the end of the source
+    fn peek(&mut self, len: usize) -> io::Result<&[u8]> {
+        if self.end - self.start < len && !self.eof {
+            self.block.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+            let n = read_block(&mut self.reader, &mut self.block[self.end..])?;
+            self.eof = self.end + n < self.block.len();
+            self.end += n;
+        }
+        let available = (self.end - self.start).min(len);
+        Ok(&self.block[self.start..self.start + available])
+    }
+
+    /// Record a stitch at an offset from the current position
+    fn relative(&mut self, dx: f64, dy: f64, command: u32) -> Stitch {
+        self.x += dx;
+        self.y += dy;
+        Stitch::new(self.x, self.y, command)
+    }
+
+    /// Decode the next record, `None` at the end of the stitch data
+    fn decode(&mut self) -> io::Result<Option<Stitch>> {
+        match self.decoder {
+            Decoder::Dst { sequin_mode } => self.decode_dst(sequin_mode),
+            Decoder::Exp => self.decode_exp(),
+            Decoder::Pec => self.decode_pec(),
+        }
+    }
+
+    fn decode_dst(&mut self, sequin_mode: bool) -> io::Result<Option<Stitch>> {
+        let (b0, b1, b2) = match *self.peek(3)? {
+            [b0, b1, b2] => (b0, b1, b2),
+            _ => return Ok(None),
+        };
+        self.start += 3;
+
+        let (dx, dy) = dst::decode_delta(b0, b1, b2);
+        let (dx, dy) = (dx as f64, dy as f64);
+
+        let command = if b2 & 0b11110011 == 0b11110011 {
+            return Ok(None);
+        } else if b2 & 0b11000011 == 0b11000011 {
+            COLOR_CHANGE
+        } else if b2 & 0b01000011 == 0b01000011 {
+            self.decoder = Decoder::Dst {
+                sequin_mode: !sequin_mode,
+            };
+            SEQUIN_MODE
+        } else if b2 & 0b10000011 == 0b10000011 {
+            if sequin_mode {
+                SEQUIN_EJECT
+            } else {
+                JUMP
+            }
+        } else {
+            STITCH
+        };
+        Ok(Some(self.relative(dx, dy, command)))
+    }
+
+    fn decode_exp(&mut self) -> io::Result<Option<Stitch>> {
+        let (b0, b1) = match *self.peek(2)? {
+            [b0, b1] => (b0, b1),
+            _ => return Ok(None),
+        };
+        if b0 != 0x80 {
+            self.start += 2;
+            return Ok(Some(self.relative(
+                b0 as i8 as f64,
+                -(b1 as i8 as f64),
+                STITCH,
+            )));
+        }
+
+        // Control byte is followed by a 2-byte coordinate record
+        let (control, x, y) = match *self.peek(4)? {
+            [_, control, x, y] => (control, x as i8 as f64, -(y as i8 as f64)),
+            _ => return Ok(None),
+        };
+        self.start += 4;
+
+        let stitch = match control {
+            0x80 => self.relative(0.0, 0.0, TRIM),
+            0x02 => self.relative(x, y, STITCH),
+            0x04 => self.relative(x, y, JUMP),
+            0x01 => {
+                let change = self.relative(0.0, 0.0, COLOR_CHANGE);
+                if x != 0.0 || y != 0.0 {
+                    self.pending = Some(self.relative(x, y, JUMP));
+                }
+                change
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(stitch))
+    }
+
+    fn decode_pec(&mut self) -> io::Result<Option<Stitch>> {
+        // Records are 2-4 bytes; copy them out so `start` can advance afterwards
+        let mut bytes = [0u8; 4];
+        let available = self.peek(4)?.len();
+        bytes[..available].copy_from_slice(&self.block[self.start..self.start + available]);
+        let record = &bytes[..available];
+        if record.len() < 2 || (record[0] == 0xFF && record[1] == 0x00) {
+            return Ok(None);
+        }
+        let (val1, val2) = (record[0], record[1]);
+
+        if val1 == 0xFE && val2 == 0xB0 {
+            if record.len() < 3 {
+                return Ok(None);
+            }
+            self.start += 3;
+            return Ok(Some(self.relative(0.0, 0.0, COLOR_CHANGE)));
+        }
+
+        let mut jump = false;
+        let mut trim = false;
+        let mut flags = |byte: u8| {
+            trim |= byte & pec::TRIM_CODE != 0;
+            jump |= byte & pec::JUMP_CODE != 0;
+        };
+
+        let (x, y_byte, mut len) = if val1 & pec::FLAG_LONG != 0 {
+            flags(val1);
+            let Some(&y_byte) = record.get(2) else {
+                return Ok(None);
+            };
+            (pec::signed12(u16::from_be_bytes([val1, val2])), y_byte, 3)
+        } else {
+            (pec::signed7(val1), val2, 2)
+        };
+
+        let y = if y_byte & pec::FLAG_LONG != 0 {
+            flags(y_byte);
+            let Some(&low) = record.get(len) else {
+                return Ok(None);
+            };
+            len += 1;
+            pec::signed12(u16::from_be_bytes([y_byte, low]))
+        } else {
+            pec::signed7(y_byte)
+        };
+        self.start += len;
+
+        let (dx, dy) = (x as f64, y as f64);
+        if jump {
+            Ok(Some(self.relative(dx, dy, JUMP)))
+        } else {
+            let stitch = self.relative(dx, dy, STITCH);
+            if trim {
+                self.pending = Some(self.relative(0.0, 0.0, TRIM));
+            }
+            Ok(Some(stitch))
+        }
+    }
+}
+
+impl<R: Read> Iterator for StitchStream<R> {
+    type Item = Result<Stitch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(stitch) = self.pending.take() {
+            return Some(Ok(stitch));
+        }
+        if self.finished {
+            return None;
+        }
+
+        match self.decode() {
+            Ok(Some(stitch)) => Some(Ok(stitch)),
+            Ok(None) => {
+                self.finished = true;
+                Some(Ok(Stitch::new(self.x, self.y, END)))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+/// Statistics accumulated over a stream of stitch records
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StreamSummary {
+    /// Total records, including commands and the final `END`
+    pub records: usize,
+    /// Needle-down stitches
+    pub stitches: usize,
+    /// Jumps
+    pub jumps: usize,
+    /// Trims
+    pub trims: usize,
+    /// Color changes
+    pub color_changes: usize,
+    /// Bounding box of all records (zero when empty)
+    pub bounds: Bounds,
+    /// Total length of needle-down stitches in 0.1mm units
+    pub stitch_length: f64,
+    last: Option<(f64, f64)>,
+}
+
+impl StreamSummary {
+    /// Add one record
+    ///
+    /// Counts and length follow `EmbPattern::count_stitches` and
+    /// `EmbPattern::total_stitch_length`, bounds follow `EmbPattern::bounds`.
+    pub fn add(&mut self, stitch: &Stitch) {
+        self.records += 1;
+        match stitch.command {
+            STITCH => {
+                self.stitches += 1;
+                let (px, py) = self.last.unwrap_or((0.0, 0.0));
+                self.stitch_length += (stitch.x - px).hypot(stitch.y - py);
+            }
+            JUMP => self.jumps += 1,
+            TRIM => self.trims += 1,
+            COLOR_CHANGE => self.color_changes += 1,
+            _ => {}
+        }
+        self.last = Some((stitch.x, stitch.y));
+
+        if stitch.x.is_finite() && stitch.y.is_finite() {
+            if self.records == 1 {
+                self.bounds = Bounds::new(stitch.x, stitch.y, stitch.x, stitch.y);
+            } else {
+                self.bounds.min_x = self.bounds.min_x.min(stitch.x);
+                self.bounds.min_y = self.bounds.min_y.min(stitch.y);
+                self.bounds.max_x = self.bounds.max_x.max(stitch.x);
+                self.bounds.max_y = self.bounds.max_y.max(stitch.y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::readers;
+    use crate::formats::io::writers;
+    use std::io::Cursor;
+
+    fn sample_pattern() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        // Serpentine rows keep every move within DST range
+        for i in 0..40 {
+            let (row, col) = (i / 10, i % 10);
+            let col = if row % 2 == 0 { col } else { 9 - col };
+            pattern.add_stitch_absolute(STITCH, (col * 20) as f64, (row * 30) as f64);
+        }
+        pattern.add_stitch_absolute(JUMP, 100.0, 150.0);
+        pattern.add_stitch_absolute(COLOR_CHANGE, 100.0, 150.0);
+        pattern.add_stitch_absolute(STITCH, 110.0, 140.0);
+        pattern.add_stitch_absolute(STITCH, 50.0, 40.0);
+        pattern.add_stitch_absolute(END, 50.0, 40.0);
+        pattern
+    }
+
+    fn collect<R: Read>(stream: StitchStream<R>) -> Vec<Stitch> {
+        stream.collect::<Result<Vec<_>>>().unwrap()
+    }
+
+    #[test]
+    fn test_dst_stream_matches_reader() {
+        let mut data = Cursor::new(Vec::new());
+        writers::dst::write(&mut data, &sample_pattern(), false, 0).unwrap();
+        let data = data.into_inner();
+
+        let pattern = readers::dst::read(&mut &data[..], None).unwrap();
+        let stream = StitchStream::dst(&data[..]).unwrap();
+        assert_eq!(
+            stream.header().get_metadata("name"),
+            pattern.get_metadata("name")
+        );
+        assert_eq!(collect(stream), pattern.stitches());
+    }
+
+    #[test]
+    fn test_exp_stream_matches_reader() {
+        let mut data = Cursor::new(Vec::new());
+        writers::exp::write(&mut data, &sample_pattern()).unwrap();
+        let data = data.into_inner();
+
+        let pattern = readers::exp::read(&mut &data[..]).unwrap();
+        assert_eq!(collect(StitchStream::exp(&data[..])), pattern.stitches());
+    }
+
+    #[test]
+    fn test_pec_stream_records() {
+        let mut data = b"#PEC0001LA:Test".to_vec();
+        data.resize(8 + 3 + 16 + 15 + 2 + 12, b' ');
+        data.push(1); // Two colors
+        data.extend_from_slice(&[1, 5]);
+        data.resize(data.len() + 0x1D0 - 1 + 14, 0);
+        data.extend_from_slice(&[0x0A, 0x76]); // Short stitch (10, -10)
+        data.extend_from_slice(&[0x90, 0x20, 0x05]); // Long jump (32, 5)
+        data.extend_from_slice(&[0xFE, 0xB0, 0x02]); // Color change
+        data.extend_from_slice(&[0x01, 0xAF, 0xFF]); // Stitch (1, -1) with trim
+        data.extend_from_slice(&[0xFF, 0x00]); // End
+
+        let stream = StitchStream::pec(&data[..]).unwrap();
+        assert_eq!(
+            stream.header().get_metadata("Name").map(String::as_str),
+            Some("Test")
+        );
+        assert_eq!(stream.header().threads().len(), 2);
+        assert_eq!(
+            collect(stream),
+            vec![
+                Stitch::new(10.0, -10.0, STITCH),
+                Stitch::new(42.0, -5.0, JUMP),
+                Stitch::new(42.0, -5.0, COLOR_CHANGE),
+                Stitch::new(43.0, -6.0, STITCH),
+                Stitch::new(43.0, -6.0, TRIM),
+                Stitch::new(43.0, -6.0, END),
+            ]
+        );
+
+        // Embedded in a PES file
+        let mut pes = b"#PES0001".to_vec();
+        pes.extend_from_slice(&20u32.to_le_bytes());
+        pes.extend_from_slice(&[0u8; 8]);
+        pes.extend_from_slice(&data[8..]);
+        assert_eq!(collect(StitchStream::pes(&pes[..]).unwrap()).len(), 6);
+    }
+
+    #[test]
+    fn test_summary_matches_pattern() {
+        let pattern = sample_pattern();
+        let mut summary = StreamSummary::default();
+        for stitch in pattern.stitches() {
+            summary.add(stitch);
+        }
+
+        let (min_x, min_y, max_x, max_y) = pattern.bounds();
+        assert_eq!(summary.bounds, Bounds::new(min_x, min_y, max_x, max_y));
+        assert_eq!(summary.stitches, pattern.count_stitches());
+        assert_eq!(summary.color_changes, 1);
+        assert_eq!(summary.jumps, 1);
+        assert!((summary.stitch_length - pattern.total_stitch_length()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_unsupported_and_truncated() {
+        assert!(StitchStream::new(&b""[..], "vp3").is_err());
+        assert!(StitchStream::dst(&[0u8; 100][..]).is_err());
+
+        let stitches = collect(StitchStream::exp(&[0x05, 0x05, 0x80][..]));
+        assert_eq!(
+            stitches,
+            vec![Stitch::new(5.0, -5.0, STITCH), Stitch::new(5.0, -5.0, END)]
+        );
+    }
+}
@@ -0,0 +1,340 @@
+//! Unified reader and writer traits
+//!
+//! The format modules grew different signatures: some readers fill a pattern
+//! passed in, some return one, some take a settings map or options, and writers
+//! take their arguments in either order with format-specific extras.
+//! [`PatternReader`] and [`PatternWriter`] put one signature in front of all of
+//! them, with [`ReadOptions`] and [`WriteOptions`] carrying the choices the
+//! individual formats expose.
+//!
+//! Every built-in [`Format`] implements both traits; formats without a reader
+//! or writer return an unsupported-format error. Machine formats encode the
+//! pattern with [`EncoderBuilder::for_format`] first, splitting long stitches
+//! and dropping commands the format can't hold, unless
+//! [`WriteOptions::encode`] is off. The traits are object safe, so
+//! readers and writers can be stored as `&dyn PatternReader`.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::formats::io::options::{ReadOptions, WriteOptions};
+//! use butabuti::formats::io::traits::{PatternReader, PatternWriter};
+//! use std::io::Cursor;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(40.0, 30.0);
+//! pattern.end();
+//!
+//! let mut buffer = Cursor::new(Vec::new());
+//! for format in [Format::DST, Format::PES, Format::JEF] {
+//!     buffer.get_mut().clear();
+//!     buffer.set_position(0);
+//!     format.write(&pattern, &mut buffer, &WriteOptions::default())?;
+//!
+//!     buffer.set_position(0);
+//!     let read = format.read(&mut buffer, &ReadOptions::default())?;
+//!     assert_eq!(read.count_stitches(), 2);
+//! }
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::encoder::EncoderBuilder;
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, ReadWarning, WriteOptions};
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::{readers, writers};
+use crate::utils::error::{Error, Result};
+use std::io::{Read, Seek, Write};
+
+/// A seekable input stream, usable as `&mut dyn ReadSeek`
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// A seekable output stream, usable as `&mut dyn WriteSeek`
+pub trait WriteSeek: Write + Seek {}
+
+impl<T: Write + Seek + ?Sized> WriteSeek for T {}
+
+/// Reads a pattern from a stream
+pub trait PatternReader {
+    /// Read a pattern, returning it with the warnings raised while reading
+    ///
+    /// Formats that don't report warnings return an empty list.
+    fn read_with_warnings(
+        &self,
+        reader: &mut dyn ReadSeek,
+        options: &ReadOptions,
+    ) -> Result<(EmbPattern, Vec<ReadWarning>)>;
+
+    /// Read a pattern, discarding warnings
+    fn read(&self, reader: &mut dyn ReadSeek, options: &ReadOptions) -> Result<EmbPattern> {
+        self.read_with_warnings(reader, options)
+            .map(|(pattern, _)| pattern)
+    }
+}
+
+/// Writes a pattern to a stream
+pub trait PatternWriter {
+    /// Write a pattern, returning the adjustments made to fit the format
+    ///
+    /// Writer limits are not checked here; see
+    /// [`FormatRegistry::check_limits`](crate::formats::registry::FormatRegistry::check_limits).
+    fn write_with_report(
+        &self,
+        pattern: &EmbPattern,
+        writer: &mut dyn WriteSeek,
+        options: &WriteOptions,
+    ) -> Result<WriteReport>;
+
+    /// Write a pattern, discarding the report
+    fn write(
+        &self,
+        pattern: &EmbPattern,
+        writer: &mut dyn WriteSeek,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        self.write_with_report(pattern, writer, options).map(|_| ())
+    }
+}
+
+/// Run a reader that fills a pattern passed in
+fn fill(
+    read: impl FnOnce(&mut EmbPattern) -> Result<()>,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+    let mut pattern = EmbPattern::new();
+    read(&mut pattern)?;
+    Ok((pattern, Vec::new()))
+}
+
+/// Run a reader that fills a pattern passed in and returns warnings
+fn fill_with_warnings(
+    read: impl FnOnce(&mut EmbPattern) -> Result<Vec<ReadWarning>>,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+    let mut pattern = EmbPattern::new();
+    let warnings = read(&mut pattern)?;
+    Ok((pattern, warnings))
+}
+
+impl PatternReader for Format {
+    fn read_with_warnings(
+        &self,
+        mut reader: &mut dyn ReadSeek,
+        options: &ReadOptions,
+    ) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+        let file = &mut reader;
+        match self {
+            Format::DST => readers::dst::read_with_options(file, None, options),
+            Format::PES => {
+                fill_with_warnings(|p| readers::pes::read_with_options(file, p, options))
+            }
+            Format::JEF => readers::jef::read_with_options(file, None, options),
+            Format::EXP => Ok((readers::exp::read(file)?, Vec::new())),
+            Format::VP3 => fill(|p| readers::vp3::read(file, p)),
+            Format::PEC => readers::pec::read_with_options(file, options),
+            Format::XXX => fill(|p| readers::xxx::read(file, p)),
+            Format::U01 => fill(|p| readers::u01::read(file, p)),
+            Format::DSB => fill(|p| readers::dsb::read(file, p)),
+            Format::DSZ => fill(|p| readers::dsz::read(file, p)),
+            Format::ZXY => fill(|p| readers::zxy::read(file, p)),
+            Format::HUS => fill(|p| readers::hus::read(file, p)),
+            Format::SHV => fill(|p| readers::shv::read(file, p)),
+            Format::TAP => fill(|p| readers::tap::read(file, p)),
+            Format::TBF => fill(|p| readers::tbf::read(file, p)),
+            Format::THR => fill(|p| readers::thr::read(file, p)),
+            Format::COL => fill(|p| readers::col::read(file, p)),
+            Format::EDR => fill(|p| readers::edr::read(file, p)),
+            Format::INF => fill(|p| readers::inf::read(file, p)),
+            Format::JSON => Ok((readers::json::read(file)?, Vec::new())),
+            Format::CSV => {
+                fill_with_warnings(|p| readers::csv::read_with_options(file, p, options))
+            }
+            Format::GCODE => {
+                fill_with_warnings(|p| readers::gcode::read_with_options(file, p, options))
+            }
+            Format::PLT => fill(|p| readers::plt::read(file, p)),
+            Format::SVG => fill(|p| readers::svg::read(file, p)),
+            Format::EMB => fill(|p| readers::emb::read(file, p)),
+            Format::BUTA => Ok((readers::buta::read(file)?, Vec::new())),
+            Format::TXT | Format::Unknown => Err(Error::UnsupportedFormat(format!(
+                "No reader for format: {}",
+                self
+            ))),
+        }
+    }
+}
+
+impl PatternWriter for Format {
+    fn write_with_report(
+        &self,
+        pattern: &EmbPattern,
+        writer: &mut dyn WriteSeek,
+        options: &WriteOptions,
+    ) -> Result<WriteReport> {
+        let (encoded, mut changes) = match self {
+            Format::DST | Format::PES | Format::PEC | Format::JEF | Format::EXP | Format::VP3
+                if options.encode =>
+            {
+                let (encoded, changes) =
+                    EncoderBuilder::for_format(*self).encode_with_changes(pattern)?;
+                (Some(encoded), changes)
+            }
+            _ => (None, Vec::new()),
+        };
+        let mut report = write_format(*self, encoded.as_ref().unwrap_or(pattern), writer, options)?;
+
+        // What the encoder changed comes before what the writer left out
+        changes.append(&mut report.changes);
+        report.changes = changes;
+        Ok(report)
+    }
+}
+
+/// Write a pattern, already encoded when the format needs it, with the format's writer
+fn write_format(
+    format: Format,
+    pattern: &EmbPattern,
+    mut writer: &mut dyn WriteSeek,
+    options: &WriteOptions,
+) -> Result<WriteReport> {
+    let file = &mut writer;
+    match format {
+        Format::DST => writers::dst::write_with_fields(
+            file,
+            pattern,
+            if options.dst_extended_header {
+                options.dst_header_fields
+            } else {
+                writers::dst::DstHeaderFields::NONE
+            },
+            options.dst_trim_jumps,
+        ),
+        Format::PES => writers::pes::write_pes_with_encoding(
+            pattern,
+            file,
+            options.pes_version,
+            options.pes_truncated,
+            options.text_encoding,
+        ),
+        Format::JEF => writers::jef::write(file, pattern, &options.jef),
+        Format::EXP => writers::exp::write(file, pattern),
+        Format::VP3 => writers::vp3::write(file, pattern),
+        Format::PEC => writers::pec::write(file, pattern),
+        Format::XXX => writers::xxx::write(pattern, file),
+        Format::U01 => writers::u01::write(pattern, file),
+        Format::DSB => writers::dsb::write(pattern, file),
+        Format::DSZ => writers::dsz::write(pattern, file),
+        Format::ZXY => writers::zxy::write(pattern, file),
+        Format::HUS => writers::hus::write(pattern, file),
+        Format::SHV => writers::shv::write(pattern, file),
+        Format::TBF => writers::tbf::write(pattern, file),
+        Format::COL => writers::col::write(pattern, file),
+        Format::EDR => writers::edr::write(pattern, file),
+        Format::INF => writers::inf::write(pattern, file),
+        Format::JSON => writers::json::write(file, pattern),
+        Format::CSV => writers::csv::write(file, pattern, options.csv_version),
+        Format::GCODE => writers::gcode::write_with_profile(pattern, file, &options.gcode_profile),
+        Format::PLT => writers::plt::write(pattern, file),
+        Format::SVG => writers::svg::write(pattern, file),
+        Format::TXT => writers::txt::write(pattern, file),
+        Format::BUTA => writers::buta::write(file, pattern),
+        Format::EMB | Format::TAP | Format::THR | Format::Unknown => Err(Error::UnsupportedFormat(
+            format!("No writer for format: {}", format),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_round_trip_every_readable_writable_format() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0x3366CC));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 20.0);
+        pattern.stitch_abs(80.0, 60.0);
+        pattern.end();
+
+        // Thread-only formats and lossy vector formats keep no stitches, and the
+        // CSV writer's column layouts differ from the reader's `*` lines
+        let skipped = [
+            Format::COL,
+            Format::EDR,
+            Format::INF,
+            Format::SVG,
+            Format::CSV,
+        ];
+        let readers: Vec<&dyn PatternReader> = Format::ALL
+            .iter()
+            .filter(|format| format.can_read() && format.can_write())
+            .map(|format| format as &dyn PatternReader)
+            .collect();
+        assert_eq!(readers.len(), 23);
+
+        for &format in Format::ALL {
+            if !(format.can_read() && format.can_write()) || skipped.contains(&format) {
+                continue;
+            }
+            let mut buffer = Cursor::new(Vec::new());
+            format
+                .write(&pattern, &mut buffer, &WriteOptions::default())
+                .unwrap_or_else(|e| panic!("{} write failed: {}", format, e));
+            buffer.set_position(0);
+            let (read, _) = format
+                .read_with_warnings(&mut buffer, &ReadOptions::default())
+                .unwrap_or_else(|e| panic!("{} read failed: {}", format, e));
+            assert!(read.count_stitches() >= 3, "{} lost stitches", format);
+        }
+    }
+
+    #[test]
+    fn test_writers_encode_long_stitches() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0x3366CC));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(300.0, 0.0);
+        pattern.end();
+
+        for format in [Format::DST, Format::JEF, Format::EXP] {
+            let mut buffer = Cursor::new(Vec::new());
+            format
+                .write(&pattern, &mut buffer, &WriteOptions::default())
+                .unwrap_or_else(|e| panic!("{} write failed: {}", format, e));
+            buffer.set_position(0);
+            let read = format.read(&mut buffer, &ReadOptions::default()).unwrap();
+            let (min_x, _, max_x, _) = read.bounds();
+            assert!(
+                (max_x - min_x - 300.0).abs() < 1e-9,
+                "{} moved stitches",
+                format
+            );
+        }
+
+        let mut buffer = Cursor::new(Vec::new());
+        assert!(Format::DST
+            .write(&pattern, &mut buffer, &WriteOptions::new().encode(false))
+            .is_err());
+    }
+
+    #[test]
+    fn test_unsupported_directions() {
+        let mut empty = Cursor::new(Vec::new());
+        assert!(Format::TXT
+            .read(&mut empty, &ReadOptions::default())
+            .is_err());
+        assert!(Format::EMB
+            .write(&EmbPattern::new(), &mut empty, &WriteOptions::default())
+            .is_err());
+        assert!(Format::Unknown
+            .read(&mut empty, &ReadOptions::default())
+            .is_err());
+    }
+}
@@ -6,6 +6,23 @@
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 
+/// Most bytes preallocated for a read whose length comes from the file
+pub(crate) const MAX_PREALLOCATION: usize = 64 * 1024;
+
+/// Read exactly `count` bytes into a new buffer
+///
+/// At most [`MAX_PREALLOCATION`] bytes are reserved upfront; the rest is only
+/// allocated as data arrives, so header-driven lengths can't exhaust memory on
+/// short input.
+pub(crate) fn read_exact_vec<R: Read>(reader: &mut R, count: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+    reader.take(count as u64).read_to_end(&mut buffer)?;
+    if buffer.len() < count {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(buffer)
+}
+
 /// Helper for reading from binary streams
 pub struct ReadHelper<R: Read> {
     reader: R,
@@ -18,10 +35,11 @@ impl<R: Read> ReadHelper<R> {
     }
 
     /// Read exact number of bytes
+    ///
+    /// The buffer grows with the data actually read, so a corrupt length field
+    /// fails with `UnexpectedEof` instead of allocating `count` bytes upfront.
     pub fn read_bytes(&mut self, count: usize) -> io::Result<Vec<u8>> {
-        let mut buffer = vec![0u8; count];
-        self.reader.read_exact(&mut buffer)?;
-        Ok(buffer)
+        read_exact_vec(&mut self.reader, count)
     }
 
     /// Read a single byte
@@ -74,6 +92,11 @@ impl<R: Read> ReadHelper<R> {
         self.reader.read_i32::<BigEndian>()
     }
 
+    /// Read f32 little endian
+    pub fn read_f32_le(&mut self) -> io::Result<f32> {
+        self.reader.read_f32::<LittleEndian>()
+    }
+
     /// Read string of specified length
     pub fn read_string(&mut self, length: usize) -> io::Result<String> {
         let bytes = self.read_bytes(length)?;
@@ -97,6 +120,11 @@ impl<R: Read> ReadHelper<R> {
     pub fn into_inner(self) -> R {
         self.reader
     }
+
+    /// Get mutable reference to underlying reader
+    pub fn inner_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
 }
 
 /// Helper for writing to binary streams
@@ -261,3 +289,192 @@ impl<W: Write + Seek> WriteHelper<W> {
         self.writer.seek(pos)
     }
 }
+
+/// Fill `buf` from the reader, stopping early only at end of stream
+///
+/// Returns the number of bytes read; a result shorter than `buf` means the stream
+/// is exhausted. Used by readers that decode fixed-size records in blocks instead
+/// of issuing one `read_exact` call per record.
+pub fn read_block<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Capacity at which `TextBuffer::flush_if_full` hands data to the writer
+const TEXT_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Reusable output buffer for text-based writers
+///
+/// Writers append each record with the `push_*` methods and call `flush_if_full`
+/// once per record, so output reaches the underlying writer in large chunks instead
+/// of one `write` call per formatted field. Numbers are formatted with `itoa`/`ryu`
+/// but produce exactly the same text as the standard `{}` formatting.
+pub struct TextBuffer {
+    buf: Vec<u8>,
+    int: itoa::Buffer,
+    float: ryu::Buffer,
+}
+
+impl Default for TextBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextBuffer {
+    /// Create an empty buffer
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::with_capacity(TEXT_BUFFER_CAPACITY + 1024),
+            int: itoa::Buffer::new(),
+            float: ryu::Buffer::new(),
+        }
+    }
+
+    /// Append a string
+    #[inline]
+    pub fn push_str(&mut self, s: &str) {
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    /// Append an integer
+    #[inline]
+    pub fn push_int<I: itoa::Integer>(&mut self, value: I) {
+        self.buf
+            .extend_from_slice(self.int.format(value).as_bytes());
+    }
+
+    /// Append a float, formatted as `{}` would
+    #[inline]
+    pub fn push_f64(&mut self, value: f64) {
+        if value.fract() == 0.0 && value.abs() < 1e15 {
+            // Whole numbers print without a decimal point, keeping the sign of -0.0
+            if value == 0.0 && value.is_sign_negative() {
+                self.buf.push(b'-');
+            }
+            self.push_int(value as i64);
+        } else if (1e-4..1e15).contains(&value.abs()) {
+            // ryu switches to exponent notation outside this range, `{}` never does
+            self.buf
+                .extend_from_slice(self.float.format_finite(value).as_bytes());
+        } else {
+            write!(self.buf, "{}", value).expect("writing to a Vec cannot fail");
+        }
+    }
+
+    /// Append a float, formatted as `{}` would
+    #[inline]
+    pub fn push_f32(&mut self, value: f32) {
+        if value.fract() == 0.0 && value.abs() < 1e7 {
+            if value == 0.0 && value.is_sign_negative() {
+                self.buf.push(b'-');
+            }
+            self.push_int(value as i32);
+        } else if (1e-4..1e7).contains(&value.abs()) {
+            self.buf
+                .extend_from_slice(self.float.format_finite(value).as_bytes());
+        } else {
+            write!(self.buf, "{}", value).expect("writing to a Vec cannot fail");
+        }
+    }
+
+    /// Write the buffered data once it exceeds the flush threshold
+    #[inline]
+    pub fn flush_if_full<W: Write + ?Sized>(&mut self, writer: &mut W) -> io::Result<()> {
+        if self.buf.len() >= TEXT_BUFFER_CAPACITY {
+            self.flush_to(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Write all buffered data
+    pub fn flush_to<W: Write + ?Sized>(&mut self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl Write for TextBuffer {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_bytes_rejects_absurd_length() {
+        let mut helper = ReadHelper::new(io::Cursor::new(vec![1u8, 2, 3]));
+        let err = helper.read_bytes(usize::MAX).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut helper = ReadHelper::new(io::Cursor::new(vec![1u8, 2, 3]));
+        assert_eq!(helper.read_bytes(2).unwrap(), vec![1, 2]);
+        assert_eq!(helper.read_bytes(1).unwrap(), vec![3]);
+    }
+
+    #[test]
+    fn test_text_buffer_matches_display() {
+        let mut values = vec![
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            0.1,
+            0.25,
+            -0.5,
+            123.456,
+            1e-4,
+            9.99e-5,
+            1e-7,
+            1e15,
+            1e16,
+            123456789012.345,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        // Pseudo-random coordinates across magnitudes
+        let mut seed = 0x2545F4914F6CDD1Du64;
+        for _ in 0..2000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            values.push(f64::from_bits(seed));
+            values.push((seed % 200_001) as f64 / 10.0 - 10_000.0);
+        }
+
+        for value in values {
+            let mut buffer = TextBuffer::new();
+            buffer.push_f64(value);
+            buffer.push_str(",");
+            buffer.push_f32(value as f32);
+            let mut out = Vec::new();
+            buffer.flush_to(&mut out).unwrap();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                format!("{},{}", value, value as f32),
+                "{:e}",
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Round-trip fidelity checks
+//!
+//! Writes a pattern to a format, reads it back and measures what changed: how
+//! many needle penetrations were gained or lost, how far the surviving ones
+//! moved, which commands disappeared, and which threads and metadata keys the
+//! format could not hold. Use it to decide whether a conversion is safe, or
+//! archive to [`Format::BUTA`], which round trips without loss.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::formats::io::options::WriteOptions;
+//! use butabuti::formats::io::verify::verify_pattern;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(12.34, 5.0);
+//! pattern.end();
+//!
+//! let report = verify_pattern(&pattern, Format::DST, &WriteOptions::default())?;
+//! assert_eq!(report.stitch_count_drift(), 0);
+//! assert!(report.max_position_error > 0.3); // DST stores whole 0.1mm units
+//!
+//! let report = verify_pattern(&pattern, Format::BUTA, &WriteOptions::default())?;
+//! assert!(report.is_lossless());
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::{command_name, COMMAND_MASK, STITCH};
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::options::{ReadOptions, WriteOptions};
+use crate::formats::io::traits::{PatternReader, PatternWriter};
+use crate::formats::registry::FormatRegistry;
+use crate::utils::error::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Cursor, Seek, SeekFrom};
+
+/// Largest position error still considered exact (0.1mm units)
+const POSITION_TOLERANCE: f64 = 1e-9;
+
+/// Round-trip stitches searched ahead for the match of an original stitch
+///
+/// Writers may insert stitches (lock stitches, split long stitches, a start
+/// point), so each original stitch is matched to the nearest of the next few.
+const MATCH_WINDOW: usize = 16;
+
+/// A command that occurs fewer times after the round trip
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LostCommand {
+    /// Command name, e.g. "TRIM"
+    pub command: &'static str,
+    /// Occurrences in the original pattern
+    pub original: usize,
+    /// Occurrences after the round trip
+    pub round_trip: usize,
+}
+
+/// Differences between a pattern and its round trip through a format
+#[derive(Debug, Clone, PartialEq)]
+pub struct FidelityReport {
+    /// Format the pattern was written to and read back from
+    pub format: Format,
+    /// Needle penetrations (STITCH commands) in the original pattern
+    pub original_stitches: usize,
+    /// Needle penetrations after the round trip
+    pub round_trip_stitches: usize,
+    /// Largest distance between an original stitch and its match (0.1mm units)
+    ///
+    /// Infinite when stitches were written but none came back.
+    pub max_position_error: f64,
+    /// Commands that occur fewer times after the round trip
+    pub lost_commands: Vec<LostCommand>,
+    /// Threads missing from the round trip's thread list
+    pub lost_threads: usize,
+    /// Metadata keys missing or changed after the round trip, sorted
+    pub lost_metadata: Vec<String>,
+}
+
+impl FidelityReport {
+    /// Compare a pattern with its round trip through `format`
+    pub fn compare(format: Format, original: &EmbPattern, round_trip: &EmbPattern) -> Self {
+        let before = command_counts(original);
+        let after = command_counts(round_trip);
+        let lost_commands = before
+            .iter()
+            .filter_map(|(&command, &count)| {
+                let kept = after.get(command).copied().unwrap_or(0);
+                (kept < count).then_some(LostCommand {
+                    command,
+                    original: count,
+                    round_trip: kept,
+                })
+            })
+            .collect();
+
+        let mut lost_metadata: Vec<String> = original
+            .metadata()
+            .filter(|(key, value)| round_trip.get_metadata(key) != Some(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        lost_metadata.sort();
+
+        let original_points = stitch_points(original);
+        let round_trip_points = stitch_points(round_trip);
+
+        Self {
+            format,
+            original_stitches: original_points.len(),
+            round_trip_stitches: round_trip_points.len(),
+            max_position_error: max_position_error(&original_points, &round_trip_points),
+            lost_commands,
+            lost_threads: original
+                .threads()
+                .len()
+                .saturating_sub(round_trip.threads().len()),
+            lost_metadata,
+        }
+    }
+
+    /// Needle penetrations gained (positive) or lost (negative)
+    pub fn stitch_count_drift(&self) -> i64 {
+        self.round_trip_stitches as i64 - self.original_stitches as i64
+    }
+
+    /// Whether the round trip kept every stitch, command, thread and metadata key
+    pub fn is_lossless(&self) -> bool {
+        self.stitch_count_drift() == 0
+            && self.max_position_error <= POSITION_TOLERANCE
+            && self.lost_commands.is_empty()
+            && self.lost_threads == 0
+            && self.lost_metadata.is_empty()
+    }
+}
+
+impl fmt::Display for FidelityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} -> {} stitches ({:+}), max position error {:.2}",
+            self.format,
+            self.original_stitches,
+            self.round_trip_stitches,
+            self.stitch_count_drift(),
+            self.max_position_error
+        )?;
+        for lost in &self.lost_commands {
+            write!(
+                f,
+                ", {} {} -> {}",
+                lost.command, lost.original, lost.round_trip
+            )?;
+        }
+        if self.lost_threads > 0 {
+            write!(f, ", {} threads lost", self.lost_threads)?;
+        }
+        if !self.lost_metadata.is_empty() {
+            write!(f, ", metadata lost: {}", self.lost_metadata.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a pattern to `format` in memory, read it back and compare
+///
+/// Fails if the pattern exceeds the format's writer limits or the format
+/// cannot be both written and read.
+pub fn verify_pattern(
+    pattern: &EmbPattern,
+    format: Format,
+    options: &WriteOptions,
+) -> Result<FidelityReport> {
+    FormatRegistry::new().check_limits(pattern, format.name())?;
+
+    let mut buffer = Cursor::new(Vec::new());
+    format.write(pattern, &mut buffer, options)?;
+    buffer.seek(SeekFrom::Start(0))?;
+    let round_trip = format.read(&mut buffer, &ReadOptions::default())?;
+
+    Ok(FidelityReport::compare(format, pattern, &round_trip))
+}
+
+/// Read a file, detecting its format, and verify its round trip through `format`
+#[cfg(feature = "fs")]
+pub fn verify_file<P: AsRef<std::path::Path>>(path: P, format: Format) -> Result<FidelityReport> {
+    let path = path.as_ref();
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let (pattern, _) = FormatRegistry::new().read_pattern_detected(&mut reader, Some(path))?;
+    verify_pattern(&pattern, format, &WriteOptions::default())
+}
+
+/// Occurrences of each command, by name
+fn command_counts(pattern: &EmbPattern) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for stitch in pattern.stitches() {
+        *counts.entry(command_name(stitch.command)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Positions of the needle penetrations
+fn stitch_points(pattern: &EmbPattern) -> Vec<(f64, f64)> {
+    pattern
+        .stitches()
+        .iter()
+        .filter(|stitch| stitch.command & COMMAND_MASK == STITCH)
+        .map(|stitch| (stitch.x, stitch.y))
+        .collect()
+}
+
+/// Largest distance from an original stitch to its match in the round trip
+///
+/// Matches are taken in order: each original stitch pairs with the nearest of
+/// the next [`MATCH_WINDOW`] unmatched round-trip stitches. Original stitches
+/// left over once the round trip runs out are counted by the stitch drift only.
+fn max_position_error(original: &[(f64, f64)], round_trip: &[(f64, f64)]) -> f64 {
+    if original.is_empty() {
+        return 0.0;
+    }
+    if round_trip.is_empty() {
+        return f64::INFINITY;
+    }
+
+    let mut max_error: f64 = 0.0;
+    let mut next = 0;
+    for &(x, y) in original {
+        if next >= round_trip.len() {
+            break;
+        }
+        let end = (next + MATCH_WINDOW).min(round_trip.len());
+        let (index, distance) = round_trip[next..end]
+            .iter()
+            .enumerate()
+            .map(|(i, &(rx, ry))| (next + i, (rx - x).hypot(ry - y)))
+            .fold((next, f64::INFINITY), |best, candidate| {
+                if candidate.1 < best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+        max_error = max_error.max(distance);
+        next = index + 1;
+    }
+    max_error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Sample");
+        pattern.set_metadata("designer_note", "keep");
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.stitch_abs(10.0, 10.0);
+        pattern.stitch_abs(50.3, 20.0);
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(100.0, 60.0);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_archive_round_trip_is_lossless() {
+        let report = verify_pattern(&sample(), Format::BUTA, &WriteOptions::default()).unwrap();
+        assert!(report.is_lossless(), "{}", report);
+        assert_eq!(report.original_stitches, 3);
+    }
+
+    #[test]
+    fn test_reports_machine_format_losses() {
+        let report = verify_pattern(&sample(), Format::PES, &WriteOptions::default()).unwrap();
+        assert!(!report.is_lossless());
+        // PES adds a start stitch at the origin and has no trim command
+        assert_eq!(report.stitch_count_drift(), 1);
+        assert!((report.max_position_error - 0.3).abs() < 1e-9);
+        assert_eq!(
+            report.lost_commands,
+            vec![
+                LostCommand {
+                    command: "END",
+                    original: 1,
+                    round_trip: 0,
+                },
+                LostCommand {
+                    command: "TRIM",
+                    original: 1,
+                    round_trip: 0,
+                },
+            ]
+        );
+        assert!(report.lost_metadata.contains(&"designer_note".to_string()));
+        assert!(report.to_string().starts_with("PES: 3 -> 4 stitches (+1)"));
+    }
+
+    #[test]
+    fn test_position_matching() {
+        let original = [(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)];
+        // An inserted midpoint doesn't count as error
+        assert_eq!(
+            max_position_error(
+                &original,
+                &[(0.0, 0.0), (5.0, 0.0), (10.0, 0.0), (20.0, 0.0)]
+            ),
+            0.0
+        );
+        assert_eq!(
+            max_position_error(&original, &[(0.0, 0.0), (10.0, 3.0), (20.0, 0.0)]),
+            3.0
+        );
+        assert_eq!(max_position_error(&original, &[]), f64::INFINITY);
+        assert_eq!(max_position_error(&[], &original), 0.0);
+    }
+}
@@ -3,20 +3,36 @@
 //! Provides writers for 20+ embroidery file formats including DST, PES, JEF, VP3, and others.
 //! Each writer module exposes a `write()` function that encodes an `EmbPattern` to the target format.
 
+/// APNG animated stitch-out preview writer
+pub mod apng;
+/// BUTA lossless archive writer
+pub mod buta;
 pub mod col;
 pub mod csv;
+/// DSB (Barudan) format writer
+pub mod dsb;
 pub mod dst;
+/// DSZ (ZSK) format writer
+pub mod dsz;
 /// EDR (Embird Color) format writer
 pub mod edr;
 pub mod exp;
 pub mod gcode;
+/// HUS (Husqvarna Viking) format writer
+pub mod hus;
 pub mod inf;
 pub mod jef;
 pub mod json;
+/// PDF production worksheet writer
+pub mod pdf;
 pub mod pec;
 pub mod pes;
+/// PLT (HPGL) format writer
+pub mod plt;
 /// PNG (Portable Network Graphics) raster format writer
 pub mod png;
+/// SHV (Husqvarna Viking) format writer
+pub mod shv;
 /// SVG (Scalable Vector Graphics) format writer
 pub mod svg;
 pub mod tbf;
@@ -25,6 +41,8 @@ pub mod txt;
 pub mod u01;
 pub mod vp3;
 pub mod xxx;
+/// ZXY (ZSK TC) format writer
+pub mod zxy;
 
 // Additional writers to be implemented:
 // ... etc
@@ -0,0 +1,235 @@
+//! APNG animated stitch-out preview writer
+//!
+//! Animates the design being sewn, a batch of stitches per frame, so the stitching
+//! order, color sequence and travel can be checked before running the machine.
+//!
+//! Each frame after the first only stores the rectangle its stitches touched, and
+//! viewers without APNG support show the first frame as a still PNG.
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::io::writers::png::{compress_region, ihdr, png_chunk, PNG_SIGNATURE};
+use crate::render::{RenderOptions, Scene};
+use crate::utils::error::{Error, Result};
+use std::io::Write;
+
+/// APNG writer settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApngSettings {
+    /// How the design is drawn (default: [`RenderOptions::default`])
+    pub render: RenderOptions,
+    /// Stitches drawn per frame (default: 100)
+    pub stitches_per_frame: usize,
+    /// Delay between frames in milliseconds (default: 50)
+    pub frame_delay_ms: u16,
+    /// How long the finished design is shown in milliseconds (default: 2000)
+    pub final_delay_ms: u16,
+    /// Number of times to play, or 0 to loop forever (default: 0)
+    pub loops: u32,
+}
+
+impl Default for ApngSettings {
+    fn default() -> Self {
+        Self {
+            render: RenderOptions::default(),
+            stitches_per_frame: 100,
+            frame_delay_ms: 50,
+            final_delay_ms: 2000,
+            loops: 0,
+        }
+    }
+}
+
+/// Write an animated stitch-out preview as APNG
+pub fn write(pattern: &EmbPattern, file: &mut impl Write, settings: &ApngSettings) -> Result<()> {
+    if settings.stitches_per_frame == 0 {
+        return Err(Error::InvalidPattern(
+            "Stitches per frame must be at least 1".to_string(),
+        ));
+    }
+
+    let mut scene = Scene::new(pattern, &settings.render)?;
+    let (width, height) = (scene.image().width, scene.image().height);
+    let frame_count = scene
+        .remaining()
+        .div_ceil(settings.stitches_per_frame)
+        .max(1);
+
+    let mut png = Vec::new();
+    png.extend_from_slice(PNG_SIGNATURE);
+    png.extend_from_slice(&png_chunk(b"IHDR", &ihdr(width, height)));
+
+    let mut actl = Vec::with_capacity(8);
+    actl.extend_from_slice(&(frame_count as u32).to_be_bytes());
+    actl.extend_from_slice(&settings.loops.to_be_bytes());
+    png.extend_from_slice(&png_chunk(b"acTL", &actl));
+
+    let mut sequence = 0u32;
+    for frame in 0..frame_count {
+        let mut dirty: Option<(usize, usize, usize, usize)> = None;
+        for _ in 0..settings.stitches_per_frame {
+            let Some(rect) = scene.draw_next() else {
+                break;
+            };
+            dirty = Some(match dirty {
+                Some(d) => (
+                    d.0.min(rect.0),
+                    d.1.min(rect.1),
+                    d.2.max(rect.2),
+                    d.3.max(rect.3),
+                ),
+                None => rect,
+            });
+        }
+
+        // The first frame is the default image and must cover the whole canvas
+        let region = match dirty {
+            Some(rect) if frame > 0 && rect.2 > rect.0 && rect.3 > rect.1 => rect,
+            _ if frame > 0 => (0, 0, 1, 1),
+            _ => (0, 0, width, height),
+        };
+        let delay = if frame + 1 == frame_count {
+            settings.final_delay_ms
+        } else {
+            settings.frame_delay_ms
+        };
+        png.extend_from_slice(&png_chunk(b"fcTL", &frame_control(sequence, region, delay)));
+        sequence += 1;
+
+        let data = compress_region(&scene.image().pixels, width, region);
+        if frame == 0 {
+            png.extend_from_slice(&png_chunk(b"IDAT", &data));
+        } else {
+            let mut fdat = Vec::with_capacity(data.len() + 4);
+            fdat.extend_from_slice(&sequence.to_be_bytes());
+            fdat.extend_from_slice(&data);
+            png.extend_from_slice(&png_chunk(b"fdAT", &fdat));
+            sequence += 1;
+        }
+    }
+
+    png.extend_from_slice(&png_chunk(b"IEND", &[]));
+    file.write_all(&png)?;
+    Ok(())
+}
+
+/// fcTL chunk data: the frame replaces its region and stays in place afterwards
+fn frame_control(
+    sequence: u32,
+    (x0, y0, x1, y1): (usize, usize, usize, usize),
+    delay_ms: u16,
+) -> Vec<u8> {
+    let mut fctl = Vec::with_capacity(26);
+    fctl.extend_from_slice(&sequence.to_be_bytes());
+    fctl.extend_from_slice(&((x1 - x0) as u32).to_be_bytes());
+    fctl.extend_from_slice(&((y1 - y0) as u32).to_be_bytes());
+    fctl.extend_from_slice(&(x0 as u32).to_be_bytes());
+    fctl.extend_from_slice(&(y0 as u32).to_be_bytes());
+    fctl.extend_from_slice(&delay_ms.to_be_bytes());
+    fctl.extend_from_slice(&1000u16.to_be_bytes());
+    fctl.push(0); // dispose_op: none
+    fctl.push(0); // blend_op: source
+    fctl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+
+    /// (type, data) of each chunk after the signature
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut result = Vec::new();
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let tag = png[pos + 4..pos + 8].try_into().unwrap();
+            result.push((tag, png[pos + 8..pos + 8 + len].to_vec()));
+            pos += 12 + len;
+        }
+        result
+    }
+
+    fn be32(data: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_write_apng_frames() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::from_rgb(255, 0, 0));
+        for i in 0..=25 {
+            pattern.stitch_abs(i as f64 * 10.0, (i % 2) as f64 * 20.0);
+        }
+
+        let settings = ApngSettings {
+            stitches_per_frame: 10,
+            loops: 3,
+            ..Default::default()
+        };
+        let mut output = Vec::new();
+        write(&pattern, &mut output, &settings).unwrap();
+        assert_eq!(&output[..8], PNG_SIGNATURE);
+
+        let chunks = chunks(&output);
+        let tags: Vec<&[u8]> = chunks.iter().map(|(t, _)| &t[..]).collect();
+        assert_eq!(
+            tags,
+            vec![
+                &b"IHDR"[..],
+                b"acTL",
+                b"fcTL",
+                b"IDAT",
+                b"fcTL",
+                b"fdAT",
+                b"fcTL",
+                b"fdAT",
+                b"IEND"
+            ]
+        );
+
+        // 25 stitches at 10 per frame, played 3 times
+        assert_eq!(be32(&chunks[1].1, 0), 3);
+        assert_eq!(be32(&chunks[1].1, 4), 3);
+
+        let (width, height) = (be32(&chunks[0].1, 0), be32(&chunks[0].1, 4));
+        let mut expected_sequence = 0;
+        for (tag, data) in &chunks {
+            match tag {
+                b"fcTL" => {
+                    assert_eq!(be32(data, 0), expected_sequence);
+                    let (w, h, x, y) =
+                        (be32(data, 4), be32(data, 8), be32(data, 12), be32(data, 16));
+                    assert!(w > 0 && h > 0 && x + w <= width && y + h <= height);
+                    expected_sequence += 1;
+                }
+                b"fdAT" => {
+                    assert_eq!(be32(data, 0), expected_sequence);
+                    expected_sequence += 1;
+                }
+                _ => {}
+            }
+        }
+
+        // First frame covers the canvas; later ones only their stitches
+        assert_eq!(be32(&chunks[2].1, 4), width);
+        assert!(be32(&chunks[4].1, 4) < width);
+
+        // The last frame holds
+        let last_fctl = &chunks[6].1;
+        assert_eq!(u16::from_be_bytes([last_fctl[20], last_fctl[21]]), 2000);
+    }
+
+    #[test]
+    fn test_write_apng_empty_and_invalid() {
+        let mut output = Vec::new();
+        write(&EmbPattern::new(), &mut output, &ApngSettings::default()).unwrap();
+        let chunks = chunks(&output);
+        assert_eq!(be32(&chunks[1].1, 0), 1);
+
+        let settings = ApngSettings {
+            stitches_per_frame: 0,
+            ..Default::default()
+        };
+        assert!(write(&EmbPattern::new(), &mut Vec::new(), &settings).is_err());
+    }
+}
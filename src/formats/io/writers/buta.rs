@@ -0,0 +1,32 @@
+//! BUTA archive writer
+//!
+//! BUTA is the crate's native lossless container: a 4-byte `BUTA` signature, a
+//! version byte and the zlib-compressed JSON serialization of the whole
+//! [`EmbPattern`]. Unlike the machine formats it keeps every stitch coordinate
+//! bit for bit, every command and flag, and all thread fields and metadata, so
+//! it is suited to archiving designs before converting them.
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
+use crate::utils::error::Result;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+/// File signature
+pub const BUTA_SIGNATURE: [u8; 4] = *b"BUTA";
+
+/// Container version written by this crate
+pub const BUTA_VERSION: u8 = 1;
+
+/// Write a pattern as a BUTA archive
+pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<WriteReport> {
+    writer.write_all(&BUTA_SIGNATURE)?;
+    writer.write_all(&[BUTA_VERSION])?;
+
+    let mut encoder = ZlibEncoder::new(writer, Compression::default());
+    serde_json::to_writer(&mut encoder, pattern)?;
+    encoder.finish()?.flush()?;
+    Ok(WriteReport::new(Format::BUTA))
+}
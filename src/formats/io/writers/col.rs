@@ -4,6 +4,8 @@
 //! of a count line followed by index,R,G,B entries for each thread.
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use std::io::Write;
 
@@ -27,7 +29,7 @@ use std::io::Write;
 /// let mut file = File::create("colors.col").unwrap();
 /// butabuti::formats::io::writers::col::write(&pattern, &mut file).unwrap();
 /// ```
-pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
     let threads = pattern.threads();
 
     // Write thread count
@@ -45,7 +47,12 @@ pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
         )?;
     }
 
-    Ok(())
+    // Only the threads are stored
+    let mut report = WriteReport::new(Format::COL);
+    report.drop_stitches(pattern);
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 #[cfg(test)]
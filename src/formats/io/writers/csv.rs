@@ -5,6 +5,9 @@
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::TextBuffer;
 use crate::utils::error::Result;
 use std::io::Write;
 
@@ -20,86 +23,135 @@ pub enum CsvVersion {
 }
 
 /// Write pattern to CSV format
-pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern, version: CsvVersion) -> Result<()> {
+///
+/// Only [`CsvVersion::Full`] keeps metadata and threads.
+pub fn write<W: Write>(
+    writer: &mut W,
+    pattern: &EmbPattern,
+    version: CsvVersion,
+) -> Result<WriteReport> {
+    let mut report = WriteReport::new(Format::CSV);
     match version {
-        CsvVersion::Default => write_default(writer, pattern),
-        CsvVersion::Delta => write_delta(writer, pattern),
-        CsvVersion::Full => write_full(writer, pattern),
+        CsvVersion::Default => write_default(writer, pattern)?,
+        CsvVersion::Delta => write_delta(writer, pattern)?,
+        CsvVersion::Full => write_full(writer, pattern)?,
     }
+    if version != CsvVersion::Full {
+        report.drop_metadata(pattern, &[]);
+        report.drop_threads(pattern.threads().len());
+    }
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write default CSV format: command, x, y
 fn write_default<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+    let mut out = TextBuffer::new();
+
     // Write header
-    writeln!(writer, "command,x,y")?;
+    out.push_str("command,x,y\n");
 
     // Write stitches
     for stitch in pattern.stitches() {
-        let command_name = command_name(stitch.command & COMMAND_MASK);
-        writeln!(writer, "{},{},{}", command_name, stitch.x, stitch.y)?;
+        out.push_str(command_name(stitch.command & COMMAND_MASK));
+        out.push_str(",");
+        out.push_f64(stitch.x);
+        out.push_str(",");
+        out.push_f64(stitch.y);
+        out.push_str("\n");
+        out.flush_if_full(writer)?;
     }
 
+    out.flush_to(writer)?;
     Ok(())
 }
 
 /// Write delta CSV format: command, dx, dy
 fn write_delta<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+    let mut out = TextBuffer::new();
+
     // Write header
-    writeln!(writer, "command,dx,dy")?;
+    out.push_str("command,dx,dy\n");
 
     let mut prev_x = 0.0;
     let mut prev_y = 0.0;
 
     // Write stitches
     for stitch in pattern.stitches() {
-        let command_name = command_name(stitch.command & COMMAND_MASK);
-        let dx = stitch.x - prev_x;
-        let dy = stitch.y - prev_y;
-
-        writeln!(writer, "{},{},{}", command_name, dx, dy)?;
+        out.push_str(command_name(stitch.command & COMMAND_MASK));
+        out.push_str(",");
+        out.push_f64(stitch.x - prev_x);
+        out.push_str(",");
+        out.push_f64(stitch.y - prev_y);
+        out.push_str("\n");
+        out.flush_if_full(writer)?;
 
         prev_x = stitch.x;
         prev_y = stitch.y;
     }
 
+    out.flush_to(writer)?;
     Ok(())
 }
 
 /// Write full CSV format: includes all data
 fn write_full<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+    let mut out = TextBuffer::new();
+
     // Write metadata
-    writeln!(writer, "# Metadata")?;
+    writeln!(out, "# Metadata")?;
     for (key, value) in pattern.extras() {
-        writeln!(writer, "# {}: {}", key, value)?;
+        writeln!(out, "# {}: {}", key, value)?;
     }
-    writeln!(writer)?;
+    writeln!(out)?;
 
     // Write threads
-    writeln!(writer, "# Threads")?;
+    writeln!(out, "# Threads")?;
     for (i, thread) in pattern.threads().iter().enumerate() {
-        writeln!(writer, "# Thread {}: #{:06X}", i, thread.color & 0xFFFFFF)?;
+        writeln!(out, "# Thread {}: #{:06X}", i, thread.color & 0xFFFFFF)?;
+    }
+    writeln!(out)?;
+
+    // Write operator notes next to the stitch index of their stop
+    let notes = pattern.stop_notes();
+    if !notes.is_empty() {
+        let positions = pattern.stop_positions();
+        writeln!(out, "# Stops")?;
+        for (index, note) in notes {
+            match positions.get(index) {
+                Some(position) => writeln!(out, "# Stop {} at {}: {}", index, position, note)?,
+                None => writeln!(out, "# Stop {}: {}", index, note)?,
+            }
+        }
+        writeln!(out)?;
     }
-    writeln!(writer)?;
 
     // Write header
-    writeln!(writer, "index,command,x,y,dx,dy,color_index")?;
+    writeln!(out, "index,command,x,y,dx,dy,color_index")?;
 
     let mut prev_x = 0.0;
     let mut prev_y = 0.0;
-    let mut color_index = 0;
+    let mut color_index = 0usize;
 
     // Write stitches
     for (i, stitch) in pattern.stitches().iter().enumerate() {
         let command = stitch.command & COMMAND_MASK;
-        let command_name = command_name(command);
-        let dx = stitch.x - prev_x;
-        let dy = stitch.y - prev_y;
 
-        writeln!(
-            writer,
-            "{},{},{},{},{},{},{}",
-            i, command_name, stitch.x, stitch.y, dx, dy, color_index
-        )?;
+        out.push_int(i);
+        out.push_str(",");
+        out.push_str(command_name(command));
+        out.push_str(",");
+        out.push_f64(stitch.x);
+        out.push_str(",");
+        out.push_f64(stitch.y);
+        out.push_str(",");
+        out.push_f64(stitch.x - prev_x);
+        out.push_str(",");
+        out.push_f64(stitch.y - prev_y);
+        out.push_str(",");
+        out.push_int(color_index);
+        out.push_str("\n");
+        out.flush_if_full(writer)?;
 
         // Track color changes
         if command == COLOR_CHANGE {
@@ -110,19 +162,23 @@ fn write_full<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
         prev_y = stitch.y;
     }
 
+    out.flush_to(writer)?;
     Ok(())
 }
 
 /// Write CSV file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern, version: CsvVersion) -> Result<()> {
     let file = std::fs::File::create(path)?;
     let mut writer = std::io::BufWriter::new(file);
-    write(&mut writer, pattern, version)
+    write(&mut writer, pattern, version)?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::stop_note::StopNote;
     use crate::core::thread::EmbThread;
     use std::io::Cursor;
 
@@ -187,6 +243,22 @@ mod tests {
         assert!(output.contains("COLOR_CHANGE"));
     }
 
+    #[test]
+    fn test_csv_full_stop_notes() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 5.0, 5.0);
+        pattern.stop_with_note(StopNote::place_applique("Green felt"));
+        pattern.add_stitch_absolute(STITCH, 15.0, 15.0);
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &pattern, CsvVersion::Full).unwrap();
+
+        let output = String::from_utf8(buffer.into_inner()).unwrap();
+        assert!(output.contains("# Stop 0 at 1: Place applique: Green felt"));
+        assert!(output.contains("1,STOP,"));
+    }
+
     #[test]
     fn test_csv_empty_pattern() {
         let pattern = EmbPattern::new();
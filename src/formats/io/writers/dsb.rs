@@ -0,0 +1,137 @@
+//! Barudan DSB format writer
+//!
+//! Writes a DST header followed by Barudan B-stitch records: a control byte,
+//! then the dy and dx magnitudes. Thread changes become needle selections
+//! (needles 1-15), and trims and stops get their own records.
+
+use crate::core::constants::*;
+use crate::core::encoder::{EncoderSettings, Transcoder};
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::readers::dsb::{DSB_END, DSB_STOP, DSB_TRIM};
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::WriteHelper;
+use crate::formats::io::writers::dst;
+use crate::utils::error::Result;
+use crate::utils::functions::decode_embroidery_command;
+use std::io::Write;
+
+/// Needles a DSB file can select
+pub const DSB_NEEDLES: u8 = 15;
+
+/// Default encoder settings for DSB format
+pub fn default_settings() -> EncoderSettings {
+    EncoderSettings {
+        max_stitch: 127.0,
+        max_jump: 127.0,
+        full_jump: false,
+        thread_change_command: NEEDLE_SET,
+        explicit_trim: true,
+        sequin_contingency: CONTINGENCY_SEQUIN_JUMP,
+        ..Default::default()
+    }
+}
+
+/// Write DSB format embroidery file
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
+    let mut encoded = EmbPattern::new();
+    let mut transcoder = Transcoder::with_settings(default_settings());
+    transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::DSB,
+        changes: transcoder.changes(),
+    };
+
+    let mut helper = WriteHelper::new(file);
+    dst::write_header(
+        &mut helper,
+        &encoded,
+        dst::DstHeaderFields::NONE,
+        &mut report,
+    )?;
+
+    let (mut xx, mut yy) = (0.0, 0.0);
+    let mut needle = 1;
+    for stitch in encoded.stitches() {
+        let data = stitch.command & COMMAND_MASK;
+        let record = match data {
+            STITCH | JUMP => {
+                let dx = (stitch.x - xx).round() as i32;
+                let dy = (stitch.y - yy).round() as i32;
+                xx += dx as f64;
+                yy += dy as f64;
+
+                let mut ctrl = 0x80 | u8::from(data == JUMP);
+                if dy > 0 {
+                    ctrl |= 0x40;
+                }
+                if dx < 0 {
+                    ctrl |= 0x20;
+                }
+                [ctrl, dy.unsigned_abs() as u8, dx.unsigned_abs() as u8]
+            }
+            TRIM => [DSB_TRIM, 0, 0],
+            STOP => [DSB_STOP, 0, 0],
+            NEEDLE_SET | COLOR_CHANGE => {
+                needle = match decode_embroidery_command(stitch.command).2 {
+                    Some(n) if data == NEEDLE_SET && n > 0 => n,
+                    _ => needle + 1,
+                };
+                needle = (needle - 1) % DSB_NEEDLES + 1;
+                [DSB_STOP + needle, 0, 0]
+            }
+            END => break,
+            _ => {
+                report.drop_command(data);
+                continue;
+            }
+        };
+        helper.write_bytes(&record)?;
+    }
+
+    helper.write_bytes(&[DSB_END, 0, 0])?;
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::readers;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_dsb_round_trip() {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Logo");
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, -50.0);
+        pattern.stitch_abs(-20.0, 200.0);
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(-20.0, 210.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).unwrap();
+        assert_eq!(output.len() % 3, 512 % 3);
+        assert_eq!(&output[output.len() - 3..], &[DSB_END, 0, 0]);
+
+        let mut read = EmbPattern::new();
+        readers::dsb::read(&mut Cursor::new(output), &mut read).unwrap();
+        assert_eq!(read.get_metadata("name").map(|s| s.as_str()), Some("Logo"));
+        assert_eq!(read.count_stitches(), pattern.count_stitches());
+        assert_eq!(read.bounds(), pattern.bounds());
+        // The color change selects the second needle
+        let needles: Vec<Option<u8>> = read
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+            .map(|s| decode_embroidery_command(s.command).2)
+            .collect();
+        assert_eq!(needles, vec![Some(2)]);
+    }
+}
@@ -1,16 +1,95 @@
 //! Tajima DST format writer
 //!
 //! Writes DST format with 512-byte header and 3-byte stitch records using bit-encoded
-//! coordinates. Supports stitches, jumps, color changes, and trim commands.
+//! coordinates. Supports stitches, jumps, color changes, trim and sequin commands.
+//!
+//! The header starts with the standard fields at fixed offsets: the design
+//! title in a 16-byte `LA` field, then counts and extents. Extended fields
+//! (`AU` author, `CP` copyright, `LI`/`LU`/`LP` license, `TC` threads) follow,
+//! selected with [`DstHeaderFields`]. Control characters are dropped from
+//! metadata, text is cut at a character boundary to fit, and fields that no
+//! longer fit the 512-byte header are left out.
+//!
+//! Sequin ejects and jumps are the same record, told apart by the machine's sequin
+//! mode. The writer toggles sequin mode on before an eject and off before a jump or
+//! trim, so patterns don't need their own `SEQUIN_MODE` commands.
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::{WriteReport, LICENSE_KEYS};
 use crate::formats::io::utils::WriteHelper;
 use crate::utils::error::Result;
 use std::io::Write;
 
 const DST_HEADER_SIZE: usize = 512;
 
+/// Bytes of the `LA` label field
+const DST_LABEL_SIZE: usize = 16;
+
+/// Extended header fields written after the standard DST fields
+///
+/// Machines skip these lines; the DST reader maps them back to metadata and
+/// threads. Enable or disable all of them with `WriteOptions::dst_extended_header`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DstHeaderFields {
+    /// `AU`: the `author` metadata
+    pub author: bool,
+    /// `CP`: the `copyright` metadata
+    pub copyright: bool,
+    /// `LI`, `LU`, `LP`: the pattern license
+    pub license: bool,
+    /// `TC`: color, description and catalog number of each thread
+    pub threads: bool,
+}
+
+impl DstHeaderFields {
+    /// Every extended field
+    pub const ALL: Self = Self {
+        author: true,
+        copyright: true,
+        license: true,
+        threads: true,
+    };
+
+    /// Only the standard header
+    pub const NONE: Self = Self {
+        author: false,
+        copyright: false,
+        license: false,
+        threads: false,
+    };
+}
+
+impl Default for DstHeaderFields {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Metadata text for a header field, at most `max_len` bytes
+///
+/// Control characters would end the line or the header, so they are dropped.
+fn header_text(value: &str, max_len: usize) -> String {
+    let mut text = String::new();
+    for c in value.chars().filter(|c| !c.is_control()) {
+        if text.len() + c.len_utf8() > max_len {
+            break;
+        }
+        text.push(c);
+    }
+    text
+}
+
+/// Header text for a metadata value, recording a cut value in the report
+fn header_field(value: &str, max_len: usize, key: &str, report: &mut WriteReport) -> String {
+    let text = header_text(value, max_len);
+    if text.chars().count() < value.chars().filter(|c| !c.is_control()).count() {
+        report.truncate_metadata(key, text.len());
+    }
+    text
+}
+
 /// Set a bit at position
 #[inline]
 fn bit(b: u8) -> u8 {
@@ -139,18 +218,25 @@ fn encode_record(x: i32, y: i32, flags: u32) -> Result<[u8; 3]> {
 }
 
 /// Write DST header
-fn write_header<W: Write>(
+///
+/// Records the metadata and threads the header leaves out or cuts in `report`.
+pub(crate) fn write_header<W: Write>(
     writer: &mut WriteHelper<W>,
     pattern: &EmbPattern,
-    extended_header: bool,
+    fields: DstHeaderFields,
+    report: &mut WriteReport,
 ) -> Result<()> {
-    let name = pattern
-        .get_metadata("name")
-        .map(|s| s.as_str())
-        .unwrap_or("Untitled");
+    // The label is padded to a fixed byte width so the other fields keep their offsets
+    let label = header_field(
+        pattern.title().unwrap_or(""),
+        DST_LABEL_SIZE,
+        "name",
+        report,
+    );
+    let padding = " ".repeat(DST_LABEL_SIZE - label.len());
 
     // Write basic header fields
-    writer.write_string(&format!("LA:{:<16}\r", name))?;
+    writer.write_string(&format!("LA:{}{}\r", label, padding))?;
     writer.write_string(&format!("ST:{:>7}\r", pattern.count_stitches()))?;
     writer.write_string(&format!("CO:{:>3}\r", pattern.count_color_changes()))?;
 
@@ -182,20 +268,57 @@ fn write_header<W: Write>(
     writer.write_string(&format!("MY:+{:>5}\r", 0))?;
     writer.write_string(&format!("PD:{:>6}\r", "******"))?;
 
-    // Extended header with metadata and threads
-    if extended_header {
-        if let Some(author) = pattern.get_metadata("author") {
-            writer.write_string(&format!("AU:{}\r", author))?;
+    // Extended header with metadata and threads, as far as it fits
+    let mut stored = vec!["name"];
+    let mut lines = Vec::new();
+    if fields.author {
+        stored.push("author");
+        if let Some(author) = pattern.author() {
+            lines.push(("AU", "author", author.to_string()));
         }
-        if let Some(copyright) = pattern.get_metadata("copyright") {
-            writer.write_string(&format!("CP:{}\r", copyright))?;
+    }
+    if fields.copyright {
+        stored.push("copyright");
+        if let Some(copyright) = pattern.copyright() {
+            lines.push(("CP", "copyright", copyright.to_string()));
+        }
+    }
+    if fields.license {
+        stored.extend(LICENSE_KEYS);
+        if let Some(license) = pattern.license() {
+            lines.push(("LI", "license", license.name.clone()));
+            if !license.allowed_uses.is_empty() {
+                lines.push(("LU", "license_uses", license.encode_uses()));
+            }
+            if let Some(purchaser_id) = license.purchaser_id {
+                lines.push(("LP", "license_purchaser_id", purchaser_id));
+            }
+        }
+    }
+    for (prefix, key, value) in lines {
+        // Room for "XX:" and "\r" before the end of text marker
+        let room = (DST_HEADER_SIZE - 1).saturating_sub(writer.bytes_written() + 4);
+        let text = header_field(&value, room, key, report);
+        if !text.is_empty() {
+            writer.write_string(&format!("{}:{}\r", prefix, text))?;
         }
+    }
+    let mut threads_written = 0;
+    if fields.threads {
         for thread in pattern.threads() {
-            let desc = thread.description.as_deref().unwrap_or("");
-            let cat = thread.catalog_number.as_deref().unwrap_or("");
-            writer.write_string(&format!("TC:{},{},{}\r", thread.hex_color(), desc, cat))?;
+            let desc = header_text(thread.description.as_deref().unwrap_or(""), usize::MAX);
+            let cat = header_text(thread.catalog_number.as_deref().unwrap_or(""), usize::MAX);
+            let line = format!("TC:{},{},{}\r", thread.hex_color(), desc, cat);
+            // A partial thread line would change its color, so it is all or nothing
+            if writer.bytes_written() + line.len() >= DST_HEADER_SIZE {
+                break;
+            }
+            writer.write_string(&line)?;
+            threads_written += 1;
         }
     }
+    report.drop_metadata(pattern, &stored);
+    report.drop_threads(pattern.threads().len() - threads_written);
 
     // End of text marker
     writer.write_u8(0x1A)?;
@@ -215,21 +338,55 @@ pub fn write<W: Write>(
     pattern: &EmbPattern,
     extended_header: bool,
     trim_at: usize,
-) -> Result<()> {
+) -> Result<WriteReport> {
+    let fields = if extended_header {
+        DstHeaderFields::ALL
+    } else {
+        DstHeaderFields::NONE
+    };
+    write_with_fields(writer, pattern, fields, trim_at)
+}
+
+/// Write DST file with a choice of extended header fields
+pub fn write_with_fields<W: Write>(
+    writer: &mut W,
+    pattern: &EmbPattern,
+    fields: DstHeaderFields,
+    trim_at: usize,
+) -> Result<WriteReport> {
     let mut helper = WriteHelper::new(writer);
+    let mut report = WriteReport::new(Format::DST);
 
-    write_header(&mut helper, pattern, extended_header)?;
+    write_header(&mut helper, pattern, fields, &mut report)?;
 
     let mut xx = 0.0;
     let mut yy = 0.0;
+    let mut sequin_mode = false;
 
     for stitch in pattern.stitches() {
         let x = stitch.x;
         let y = stitch.y;
         let data = stitch.command & COMMAND_MASK;
 
-        let dx = (x - xx).round() as i32;
-        let dy = (y - yy).round() as i32;
+        let wants_sequin_mode = match data {
+            SEQUIN_EJECT => Some(true),
+            JUMP | TRIM => Some(false),
+            _ => None,
+        };
+        if wants_sequin_mode.is_some_and(|wanted| wanted != sequin_mode) {
+            helper.write_bytes(&encode_record(0, 0, SEQUIN_MODE)?)?;
+            sequin_mode = !sequin_mode;
+        }
+        if data == SEQUIN_MODE {
+            sequin_mode = !sequin_mode;
+        }
+
+        // Only move records carry a displacement; others leave it to the next move
+        let (dx, dy) = if data == STITCH || data == JUMP || data == SEQUIN_EJECT {
+            ((x - xx).round() as i32, (y - yy).round() as i32)
+        } else {
+            (0, 0)
+        };
 
         xx += dx as f64;
         yy += dy as f64;
@@ -249,14 +406,17 @@ pub fn write<W: Write>(
         }
     }
 
-    Ok(())
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write DST file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern, extended_header: bool) -> Result<()> {
     let file = std::fs::File::create(path)?;
     let mut writer = std::io::BufWriter::new(file);
-    write(&mut writer, pattern, extended_header, 3)
+    write(&mut writer, pattern, extended_header, 3)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -289,6 +449,153 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_dst_sequin_mode_round_trip() {
+        use crate::formats::io::readers::dst;
+        use std::io::Cursor;
+
+        let mut original = EmbPattern::new();
+        original.stitch_abs(0.0, 0.0);
+        original.add_stitch_absolute(SEQUIN_EJECT, 30.0, 0.0);
+        original.add_stitch_absolute(SEQUIN_EJECT, 60.0, 0.0);
+        original.stitch_abs(60.0, 10.0);
+        original.jump_abs(100.0, 10.0);
+        original.stitch_abs(100.0, 20.0);
+        original.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &original, false, 3).unwrap();
+        buffer.set_position(0);
+        let read_back = dst::read(&mut buffer, None).unwrap();
+
+        let records: Vec<(u32, f64, f64)> = read_back
+            .stitches()
+            .iter()
+            .map(|s| (s.command & COMMAND_MASK, s.x, s.y))
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (STITCH, 0.0, 0.0),
+                (SEQUIN_MODE, 0.0, 0.0),
+                (SEQUIN_EJECT, 30.0, 0.0),
+                (SEQUIN_EJECT, 60.0, 0.0),
+                (STITCH, 60.0, 10.0),
+                (SEQUIN_MODE, 60.0, 10.0),
+                (JUMP, 100.0, 10.0),
+                (STITCH, 100.0, 20.0),
+                (END, 100.0, 20.0),
+            ]
+        );
+
+        // Explicit toggles are kept, not doubled
+        let mut again = Cursor::new(Vec::new());
+        write(&mut again, &read_back, false, 3).unwrap();
+        assert_eq!(again.get_ref()[512..], buffer.get_ref()[512..]);
+    }
+
+    #[test]
+    fn test_dst_license_in_extended_header() {
+        use crate::core::pattern::License;
+        use crate::formats::io::readers::dst;
+        use std::io::Cursor;
+
+        let license = License::new("Personal")
+            .with_use("personal")
+            .with_use("gifts, up to 10")
+            .with_purchaser_id("C-12");
+        let mut original = EmbPattern::new();
+        original.set_license(&license);
+        original.add_stitch_absolute(STITCH, 0.0, 0.0);
+        original.add_stitch_absolute(STITCH, 10.0, 0.0);
+        original.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &original, true, 127).unwrap();
+        buffer.set_position(0);
+        let read_back = dst::read(&mut buffer, None).unwrap();
+
+        assert_eq!(read_back.license(), Some(license));
+    }
+
+    #[test]
+    fn test_dst_header_metadata_round_trip() {
+        use crate::formats::io::readers::dst;
+        use std::io::Cursor;
+
+        let mut original = EmbPattern::new();
+        original.set_title("Rosé garden border");
+        original.set_author("Jane\rDoe");
+        original.set_metadata("copyright", "© 2024 Studio");
+        original.add_thread(
+            crate::core::thread::EmbThread::from_rgb(255, 0, 0)
+                .with_description("Red")
+                .with_catalog_number("1147"),
+        );
+        original.stitch_abs(0.0, 0.0);
+        original.stitch_abs(10.0, 0.0);
+        original.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &original, true, 3).unwrap();
+        // The label keeps its 16 bytes, cut at a character boundary
+        assert_eq!(&buffer.get_ref()[..20], "LA:Rosé garden bor\r".as_bytes());
+        assert_eq!(&buffer.get_ref()[20..23], b"ST:");
+
+        buffer.set_position(0);
+        let read_back = dst::read(&mut buffer, None).unwrap();
+        assert_eq!(read_back.title(), Some("Rosé garden bor"));
+        assert_eq!(read_back.author(), Some("JaneDoe"));
+        assert_eq!(read_back.copyright(), Some("© 2024 Studio"));
+        assert_eq!(read_back.threads().len(), 1);
+        assert_eq!(read_back.threads()[0].description.as_deref(), Some("Red"));
+        assert_eq!(
+            read_back.threads()[0].catalog_number.as_deref(),
+            Some("1147")
+        );
+
+        // Selected fields only, and no label without a title
+        original.remove_metadata("name");
+        let fields = DstHeaderFields {
+            author: false,
+            ..DstHeaderFields::ALL
+        };
+        let mut buffer = Cursor::new(Vec::new());
+        write_with_fields(&mut buffer, &original, fields, 3).unwrap();
+        buffer.set_position(0);
+        let read_back = dst::read(&mut buffer, None).unwrap();
+        assert_eq!(read_back.title(), None);
+        assert_eq!(read_back.author(), None);
+        assert_eq!(read_back.copyright(), Some("© 2024 Studio"));
+    }
+
+    #[test]
+    fn test_dst_header_overflow_truncated() {
+        use crate::formats::io::readers::dst;
+        use std::io::Cursor;
+
+        let mut original = EmbPattern::new();
+        original.set_author("A".repeat(1000));
+        original.set_metadata("copyright", "Studio");
+        for i in 0..40 {
+            original.add_thread(crate::core::thread::EmbThread::new(i * 0x010203));
+        }
+        original.stitch_abs(0.0, 0.0);
+        original.stitch_abs(10.0, 0.0);
+        original.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &original, true, 3).unwrap();
+        assert!(buffer.get_ref()[..DST_HEADER_SIZE].contains(&0x1A));
+
+        buffer.set_position(0);
+        let read_back = dst::read(&mut buffer, None).unwrap();
+        let author = read_back.author().unwrap();
+        assert!(author.len() < 512 && author.chars().all(|c| c == 'A'));
+        assert_eq!(read_back.copyright(), None);
+        assert_eq!(read_back.count_stitches(), 2);
+    }
+
     #[test]
     fn test_dst_round_trip() {
         use crate::formats::io::readers::dst;
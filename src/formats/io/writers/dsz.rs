@@ -0,0 +1,137 @@
+//! ZSK DSZ format writer
+//!
+//! Writes a DST header followed by ZSK Z-stitch records: the dy and dx
+//! magnitudes, then a control byte. Thread changes become needle selections
+//! (needles 1-12), and trims and stops get their own records. The format has
+//! no end marker; stitch data runs to the end of the file.
+
+use crate::core::constants::*;
+use crate::core::encoder::{EncoderSettings, Transcoder};
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::readers::dsz::{DSZ_NEEDLE, DSZ_STOP, DSZ_TRIM};
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::WriteHelper;
+use crate::formats::io::writers::dst;
+use crate::utils::error::Result;
+use crate::utils::functions::decode_embroidery_command;
+use std::io::Write;
+
+/// Needles a DSZ file can select
+pub const DSZ_NEEDLES: u8 = 12;
+
+/// Default encoder settings for DSZ format
+pub fn default_settings() -> EncoderSettings {
+    EncoderSettings {
+        max_stitch: 127.0,
+        max_jump: 127.0,
+        full_jump: false,
+        thread_change_command: NEEDLE_SET,
+        explicit_trim: true,
+        sequin_contingency: CONTINGENCY_SEQUIN_JUMP,
+        ..Default::default()
+    }
+}
+
+/// Write DSZ format embroidery file
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
+    let mut encoded = EmbPattern::new();
+    let mut transcoder = Transcoder::with_settings(default_settings());
+    transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::DSZ,
+        changes: transcoder.changes(),
+    };
+
+    let mut helper = WriteHelper::new(file);
+    dst::write_header(
+        &mut helper,
+        &encoded,
+        dst::DstHeaderFields::NONE,
+        &mut report,
+    )?;
+
+    let (mut xx, mut yy) = (0.0, 0.0);
+    let mut needle = 1;
+    for stitch in encoded.stitches() {
+        let data = stitch.command & COMMAND_MASK;
+        let record = match data {
+            STITCH | JUMP => {
+                let dx = (stitch.x - xx).round() as i32;
+                let dy = (stitch.y - yy).round() as i32;
+                xx += dx as f64;
+                yy += dy as f64;
+
+                let mut ctrl = u8::from(data == JUMP);
+                if dx < 0 {
+                    ctrl |= 0x40;
+                }
+                if dy > 0 {
+                    ctrl |= 0x20;
+                }
+                [dy.unsigned_abs() as u8, dx.unsigned_abs() as u8, ctrl]
+            }
+            TRIM => [0, 0, DSZ_TRIM],
+            STOP => [0, 0, DSZ_STOP],
+            NEEDLE_SET | COLOR_CHANGE => {
+                needle = match decode_embroidery_command(stitch.command).2 {
+                    Some(n) if data == NEEDLE_SET && n > 0 => n,
+                    _ => needle + 1,
+                };
+                needle = (needle - 1) % DSZ_NEEDLES + 1;
+                [0, 0, DSZ_NEEDLE + 2 * (needle - 1)]
+            }
+            END => break,
+            _ => {
+                report.drop_command(data);
+                continue;
+            }
+        };
+        helper.write_bytes(&record)?;
+    }
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::io::readers;
+    use crate::utils::functions::encode_thread_change;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_dsz_round_trip() {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Cap");
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, -50.0);
+        pattern.stitch_abs(-20.0, 200.0);
+        pattern.trim();
+        pattern.add_command(
+            encode_thread_change(NEEDLE_SET, None, Some(14), None),
+            -20.0,
+            200.0,
+        );
+        pattern.stitch_abs(-20.0, 210.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).unwrap();
+
+        let mut read = EmbPattern::new();
+        readers::dsz::read(&mut Cursor::new(output), &mut read).unwrap();
+        assert_eq!(read.get_metadata("name").map(|s| s.as_str()), Some("Cap"));
+        assert_eq!(read.count_stitches(), pattern.count_stitches());
+        assert_eq!(read.bounds(), pattern.bounds());
+        assert_eq!(read.count_trims(), 1);
+        // Needle 14 wraps around to needle 2
+        let needles: Vec<Option<u8>> = read
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+            .map(|s| decode_embroidery_command(s.command).2)
+            .collect();
+        assert_eq!(needles.last(), Some(&Some(2)));
+    }
+}
@@ -4,6 +4,8 @@
 //! storing thread color information in a simple binary structure.
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use std::io::Write;
 
@@ -11,13 +13,18 @@ use std::io::Write;
 ///
 /// EDR is a simple color list format with RGB values.
 /// Each thread is stored as 4 bytes: [RED, GREEN, BLUE, 0x00]
-pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
     // Write all threads
     for thread in pattern.threads() {
         file.write_all(&[thread.red(), thread.green(), thread.blue(), 0])?;
     }
 
-    Ok(())
+    // Only the threads are stored
+    let mut report = WriteReport::new(Format::EDR);
+    report.drop_stitches(pattern);
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 #[cfg(test)]
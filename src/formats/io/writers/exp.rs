@@ -5,13 +5,18 @@
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::formats::io::utils::WriteHelper;
 use crate::utils::error::Result;
 use std::io::Write;
 
 /// Write EXP file
-pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+///
+/// EXP has no header, so metadata and threads are not stored.
+pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<WriteReport> {
     let mut helper = WriteHelper::new(writer);
+    let mut report = WriteReport::new(Format::EXP);
 
     let mut xx = 0.0;
     let mut yy = 0.0;
@@ -51,20 +56,23 @@ pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
             END => {
                 // END doesn't write anything in EXP
             }
-            _ => {
-                // Other commands ignored
-            }
+            _ => report.drop_command(data),
         }
     }
 
-    Ok(())
+    report.drop_metadata(pattern, &[]);
+    report.drop_threads(pattern.threads().len());
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write EXP file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern) -> Result<()> {
     let file = std::fs::File::create(path)?;
     let mut writer = std::io::BufWriter::new(file);
-    write(&mut writer, pattern)
+    write(&mut writer, pattern)?;
+    Ok(())
 }
 
 #[cfg(test)]
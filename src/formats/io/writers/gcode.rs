@@ -1,14 +1,174 @@
 //! G-code embroidery format writer
 //!
-//! Writes G-code CNC machine language adapted for embroidery with configurable Z-axis
-//! travel, G00/G01 for movement/stitches, M00/M01 for color changes, and thread metadata comments.
+//! Writes G-code for CNC machines and 3D printers converted to embroidery, with
+//! thread metadata comments. The dialect is set by a [`GcodeProfile`]: how the
+//! needle is driven (Z-axis travel or servo commands), feed rates, and the codes
+//! used for thread changes and the program end. The default profile writes G00
+//! moves with an incrementing Z axis and M00 color changes.
 
 use crate::core::constants::*;
 use crate::core::encoder::EncoderSettings;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use crate::utils::functions::decode_embroidery_command;
-use std::io::Write;
+use std::io::{BufWriter, Write};
+
+/// How the needle is driven for each stitch
+#[derive(Debug, Clone, PartialEq)]
+pub enum NeedleControl {
+    /// The needle shaft is on the Z axis; each stitch advances Z by `travel`
+    /// (one full needle cycle)
+    ZAxis {
+        /// Z increment per stitch
+        travel: f64,
+    },
+    /// The needle is driven by a servo or solenoid; each stitch sends `down`
+    /// then `up` (e.g. `M280 P0 S90` and `M280 P0 S0`)
+    Servo {
+        /// Command lowering the needle
+        down: String,
+        /// Command raising the needle
+        up: String,
+    },
+}
+
+/// Machine dialect for G-code output
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::io::writers::gcode::GcodeProfile;
+///
+/// let profile = GcodeProfile::marlin().feed_rate(Some(3000.0)).thread_change("M0");
+/// assert_eq!(profile.thread_change, "M0");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct GcodeProfile {
+    /// Lines written before the first move (units, positioning mode)
+    pub preamble: Vec<String>,
+    /// Command for XY moves (`G00`, `G0` or `G1`)
+    pub move_command: String,
+    /// XY feed rate in mm/min, appended to moves as `F`
+    pub feed_rate: Option<f64>,
+    /// Needle drive
+    pub needle: NeedleControl,
+    /// Z feed rate in mm/min for [`NeedleControl::ZAxis`] needle cycles
+    pub needle_feed_rate: Option<f64>,
+    /// Pause written for color changes and stops (`M00`, `M0`, `M600`)
+    pub thread_change: String,
+    /// Program end command
+    pub end: String,
+}
+
+impl Default for GcodeProfile {
+    fn default() -> Self {
+        Self {
+            preamble: Vec::new(),
+            move_command: "G00".to_string(),
+            feed_rate: None,
+            needle: NeedleControl::ZAxis { travel: 10.0 },
+            needle_feed_rate: None,
+            thread_change: "M00".to_string(),
+            end: "M30".to_string(),
+        }
+    }
+}
+
+impl GcodeProfile {
+    /// Marlin-based embroidery conversions: needle on the Z axis, `M600`
+    /// filament change for threads and `M84` to release the steppers
+    ///
+    /// Marlin treats `M30` as "delete SD file", so it is never used here.
+    pub fn marlin() -> Self {
+        Self {
+            preamble: vec!["G21".to_string(), "G90".to_string()],
+            move_command: "G1".to_string(),
+            feed_rate: Some(6000.0),
+            needle: NeedleControl::ZAxis { travel: 10.0 },
+            needle_feed_rate: Some(3000.0),
+            thread_change: "M600".to_string(),
+            end: "M84".to_string(),
+        }
+    }
+
+    /// Grbl controllers: needle on the Z axis, `M0` pause for threads
+    pub fn grbl() -> Self {
+        Self {
+            preamble: vec!["G21".to_string(), "G90".to_string()],
+            move_command: "G1".to_string(),
+            feed_rate: Some(3000.0),
+            needle: NeedleControl::ZAxis { travel: 10.0 },
+            needle_feed_rate: Some(2000.0),
+            thread_change: "M0".to_string(),
+            end: "M2".to_string(),
+        }
+    }
+
+    /// PR-series style needle control: rapid XY moves and a servo-driven
+    /// needle (`M280 P0`), `M0` pause for threads
+    pub fn pr_series() -> Self {
+        Self {
+            preamble: vec!["G21".to_string(), "G90".to_string()],
+            move_command: "G0".to_string(),
+            feed_rate: None,
+            needle: NeedleControl::Servo {
+                down: "M280 P0 S90".to_string(),
+                up: "M280 P0 S0".to_string(),
+            },
+            needle_feed_rate: None,
+            thread_change: "M0".to_string(),
+            end: "M2".to_string(),
+        }
+    }
+
+    /// Set the lines written before the first move
+    pub fn preamble<I, S>(mut self, lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.preamble = lines.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the command used for XY moves
+    pub fn move_command(mut self, command: impl Into<String>) -> Self {
+        self.move_command = command.into();
+        self
+    }
+
+    /// Set the XY feed rate in mm/min
+    pub fn feed_rate(mut self, feed_rate: Option<f64>) -> Self {
+        self.feed_rate = feed_rate;
+        self
+    }
+
+    /// Set the needle drive
+    pub fn needle(mut self, needle: NeedleControl) -> Self {
+        self.needle = needle;
+        self
+    }
+
+    /// Set the Z feed rate in mm/min for Z-axis needle cycles
+    pub fn needle_feed_rate(mut self, feed_rate: Option<f64>) -> Self {
+        self.needle_feed_rate = feed_rate;
+        self
+    }
+
+    /// Set the pause command for color changes and stops
+    pub fn thread_change(mut self, command: impl Into<String>) -> Self {
+        self.thread_change = command.into();
+        self
+    }
+
+    /// Set the program end command
+    pub fn end(mut self, command: impl Into<String>) -> Self {
+        self.end = command.into();
+        self
+    }
+}
 
 /// Get default encoder settings for G-code format
 pub fn default_settings() -> EncoderSettings {
@@ -40,7 +200,7 @@ pub fn default_settings() -> EncoderSettings {
 /// let mut file = File::create("output.gcode").unwrap();
 /// butabuti::formats::io::writers::gcode::write(&pattern, &mut file).unwrap();
 /// ```
-pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
     write_with_settings(pattern, file, 10.0)
 }
 
@@ -55,20 +215,54 @@ pub fn write_with_settings(
     pattern: &EmbPattern,
     file: &mut impl Write,
     stitch_z_travel: f64,
-) -> Result<()> {
+) -> Result<WriteReport> {
+    let profile = GcodeProfile::default().needle(NeedleControl::ZAxis {
+        travel: stitch_z_travel,
+    });
+    write_with_profile(pattern, file, &profile)
+}
+
+/// Write G-code in the dialect of a machine profile
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::formats::io::writers::gcode::{self, GcodeProfile};
+///
+/// let mut pattern = EmbPattern::new();
+/// pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+/// pattern.add_stitch_relative(0.0, 0.0, COLOR_CHANGE);
+/// pattern.end();
+///
+/// let mut output = Vec::new();
+/// gcode::write_with_profile(&pattern, &mut output, &GcodeProfile::marlin()).unwrap();
+/// assert!(String::from_utf8(output).unwrap().contains("M600"));
+/// ```
+pub fn write_with_profile(
+    pattern: &EmbPattern,
+    file: &mut impl Write,
+    profile: &GcodeProfile,
+) -> Result<WriteReport> {
+    // Two short lines per stitch; batch them for unbuffered writers
+    let mut out = BufWriter::with_capacity(64 * 1024, file);
+
     // Write header comments with pattern data
-    write_header(pattern, file)?;
+    write_header(pattern, &mut out)?;
 
     // Write metadata comments
-    write_metadata(pattern, file)?;
+    write_metadata(pattern, &mut out)?;
 
     // Write thread information
-    write_threads(pattern, file)?;
+    write_threads(pattern, &mut out)?;
 
     // Write stitch data as G-code commands
-    write_stitches(pattern, file, stitch_z_travel)?;
+    write_stitches(pattern, &mut out, profile)?;
 
-    Ok(())
+    out.flush()?;
+    let mut report = WriteReport::new(Format::GCODE);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write header with pattern statistics
@@ -108,7 +302,7 @@ fn write_header(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
     writeln!(file, "(EXTENTS_HEIGHT: {:.3})", height)?;
 
     // Write command statistics
-    let mut command_counts = std::collections::HashMap::new();
+    let mut command_counts = std::collections::BTreeMap::new();
     for stitch in pattern.stitches() {
         let cmd = stitch.command & COMMAND_MASK;
         *command_counts.entry(cmd).or_insert(0) += 1;
@@ -149,7 +343,18 @@ fn write_threads(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
 }
 
 /// Write stitch data as G-code commands
-fn write_stitches(pattern: &EmbPattern, file: &mut impl Write, stitch_z_travel: f64) -> Result<()> {
+fn write_stitches(
+    pattern: &EmbPattern,
+    file: &mut impl Write,
+    profile: &GcodeProfile,
+) -> Result<()> {
+    for line in &profile.preamble {
+        writeln!(file, "{}", line)?;
+    }
+
+    let feed = |rate: Option<f64>| rate.map(|f| format!(" F{:.0}", f)).unwrap_or_default();
+    let xy_feed = feed(profile.feed_rate);
+    let z_feed = feed(profile.needle_feed_rate);
     let mut z = 0.0;
 
     for stitch in pattern.stitches() {
@@ -161,10 +366,22 @@ fn write_stitches(pattern: &EmbPattern, file: &mut impl Write, stitch_z_travel:
 
         match command {
             STITCH => {
-                // G00 rapid move to position, then increment Z
-                writeln!(file, "G00 X{:.3} Y{:.3}", x, y)?;
-                writeln!(file, "G00 Z{:.1}", z)?;
-                z += stitch_z_travel;
+                // Move to position, then cycle the needle
+                writeln!(
+                    file,
+                    "{} X{:.3} Y{:.3}{}",
+                    profile.move_command, x, y, xy_feed
+                )?;
+                match &profile.needle {
+                    NeedleControl::ZAxis { travel } => {
+                        writeln!(file, "{} Z{:.1}{}", profile.move_command, z, z_feed)?;
+                        z += travel;
+                    }
+                    NeedleControl::Servo { down, up } => {
+                        writeln!(file, "{}", down)?;
+                        writeln!(file, "{}", up)?;
+                    }
+                }
             }
             JUMP => {
                 // Jumps are just skipped in G-code
@@ -175,12 +392,11 @@ fn write_stitches(pattern: &EmbPattern, file: &mut impl Write, stitch_z_travel:
                 continue;
             }
             COLOR_CHANGE | STOP => {
-                // M00 - Program stop (for color change)
-                writeln!(file, "M00")?;
+                // Pause for the thread change
+                writeln!(file, "{}", profile.thread_change)?;
             }
             END => {
-                // M30 - Program end
-                writeln!(file, "M30")?;
+                writeln!(file, "{}", profile.end)?;
                 break;
             }
             _ => {
@@ -290,4 +506,78 @@ mod tests {
             .collect();
         assert!(commands.contains(&COLOR_CHANGE));
     }
+
+    fn profile_output(profile: &GcodeProfile) -> String {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 50.0);
+        pattern.add_stitch_relative(0.0, 0.0, COLOR_CHANGE);
+        pattern.add_stitch_absolute(STITCH, 200.0, 50.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        write_with_profile(&pattern, &mut output, profile).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_default_profile_matches_write() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 10.0, 20.0);
+        pattern.end();
+
+        let mut plain = Vec::new();
+        write(&pattern, &mut plain).unwrap();
+        let mut profiled = Vec::new();
+        write_with_profile(&pattern, &mut profiled, &GcodeProfile::default()).unwrap();
+        assert_eq!(plain, profiled);
+    }
+
+    #[test]
+    fn test_marlin_profile() {
+        let result = profile_output(&GcodeProfile::marlin());
+
+        assert!(result.contains("G21\nG90\n"));
+        assert!(result.contains("G1 X10.000 Y5.000 F6000\nG1 Z10.0 F3000\n"));
+        assert!(result.contains("M600"));
+        assert!(result.trim_end().ends_with("M84"));
+        assert!(!result.contains("M30"));
+    }
+
+    #[test]
+    fn test_grbl_profile() {
+        let result = profile_output(&GcodeProfile::grbl());
+
+        assert!(result.contains("G1 X20.000 Y5.000 F3000\nG1 Z20.0 F2000\n"));
+        assert!(result.contains("\nM0\n"));
+        assert!(result.trim_end().ends_with("M2"));
+    }
+
+    #[test]
+    fn test_servo_needle_profile() {
+        let profile = GcodeProfile::pr_series().feed_rate(Some(1500.0));
+        let result = profile_output(&profile);
+
+        assert!(result.contains("G0 X10.000 Y5.000 F1500\nM280 P0 S90\nM280 P0 S0\n"));
+        assert_eq!(result.matches("M280 P0 S90").count(), 3);
+        assert!(!result.contains(" Z"));
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        use crate::formats::io::readers::gcode;
+
+        for profile in [
+            GcodeProfile::marlin(),
+            GcodeProfile::grbl(),
+            GcodeProfile::pr_series(),
+        ] {
+            let result = profile_output(&profile);
+            let mut read_back = EmbPattern::new();
+            gcode::read(&mut Cursor::new(result), &mut read_back).unwrap();
+
+            assert_eq!(read_back.count_stitches(), 3);
+            assert_eq!(read_back.count_color_changes(), 1);
+        }
+    }
 }
@@ -0,0 +1,156 @@
+//! Husqvarna Viking HUS format writer
+//!
+//! Writes the 42-byte HUS header, the thread palette as indices into the
+//! 29-color HUS palette (nearest match), then the command, X and Y streams,
+//! each compressed with [`compress::compress`]. Stitches are limited to
+//! ±127 units; Y is stored flipped.
+
+use crate::core::constants::*;
+use crate::core::encoder::{EncoderSettings, Transcoder};
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::WriteHelper;
+use crate::palettes::thread_hus;
+use crate::utils::compress;
+use crate::utils::error::Result;
+use std::io::Write;
+
+/// HUS file signature
+pub const HUS_MAGIC: u32 = 0x00C8_AF5B;
+
+/// Size of the fixed header before the thread palette
+const HEADER_SIZE: usize = 0x2A;
+
+/// Default encoder settings for HUS format
+pub fn default_settings() -> EncoderSettings {
+    EncoderSettings {
+        max_stitch: 127.0,
+        max_jump: 127.0,
+        full_jump: false,
+        sequin_contingency: CONTINGENCY_SEQUIN_JUMP,
+        ..Default::default()
+    }
+}
+
+/// Write HUS format embroidery file
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
+    let mut encoded = EmbPattern::new();
+    let mut transcoder = Transcoder::with_settings(default_settings());
+    transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::HUS,
+        changes: transcoder.changes(),
+    };
+
+    let mut commands = Vec::new();
+    let mut xs = Vec::new();
+    let mut ys = Vec::new();
+    let (mut xx, mut yy) = (0.0, 0.0);
+    for stitch in encoded.stitches() {
+        let data = stitch.command & COMMAND_MASK;
+        let command = match data {
+            STITCH => 0x80,
+            JUMP => 0x81,
+            COLOR_CHANGE => 0x84,
+            TRIM => 0x88,
+            END => break,
+            _ => {
+                report.drop_command(data);
+                continue;
+            }
+        };
+        let (dx, dy) = if data == STITCH || data == JUMP {
+            let dx = (stitch.x - xx).round() as i32;
+            let dy = (stitch.y - yy).round() as i32;
+            xx += dx as f64;
+            yy += dy as f64;
+            (dx, dy)
+        } else {
+            (0, 0)
+        };
+        commands.push(command);
+        xs.push(dx as i8 as u8);
+        ys.push((-dy) as i8 as u8);
+    }
+    commands.push(0x90);
+    xs.push(0);
+    ys.push(0);
+
+    let stitch_count = commands.len();
+    let commands = compress::compress(&commands);
+    let xs = compress::compress(&xs);
+    let ys = compress::compress(&ys);
+
+    let palette = thread_hus::get_thread_set();
+    let threads = encoded.threads();
+    let command_offset = HEADER_SIZE + 2 * threads.len();
+    let x_offset = command_offset + commands.len();
+    let y_offset = x_offset + xs.len();
+
+    let (min_x, min_y, max_x, max_y) = if encoded.stitches().is_empty() {
+        (0.0, 0.0, 0.0, 0.0)
+    } else {
+        encoded.bounds()
+    };
+
+    let mut helper = WriteHelper::new(file);
+    helper.write_bytes(&HUS_MAGIC.to_le_bytes())?;
+    helper.write_bytes(&(stitch_count as u32).to_le_bytes())?;
+    helper.write_bytes(&(threads.len() as u32).to_le_bytes())?;
+    for extent in [max_x, -min_y, min_x, -max_y] {
+        helper.write_bytes(&(extent.round() as i16).to_le_bytes())?;
+    }
+    for offset in [command_offset, x_offset, y_offset] {
+        helper.write_bytes(&(offset as u32).to_le_bytes())?;
+    }
+    helper.write_bytes(&[0; 8])?;
+    helper.write_bytes(&0u16.to_le_bytes())?;
+    for thread in threads {
+        let index = thread.find_nearest_color_index(&palette).unwrap_or(0);
+        helper.write_bytes(&(index as u16).to_le_bytes())?;
+    }
+    helper.write_bytes(&commands)?;
+    helper.write_bytes(&xs)?;
+    helper.write_bytes(&ys)?;
+
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::readers;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_hus_round_trip() {
+        let palette = thread_hus::get_thread_set();
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(palette[3].clone());
+        pattern.add_thread(EmbThread::new(palette[10].color ^ 0x010101));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, -50.0);
+        pattern.stitch_abs(-20.0, 60.0);
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(-40.0, 70.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).unwrap();
+        assert_eq!(&output[..4], &HUS_MAGIC.to_le_bytes());
+
+        let mut read = EmbPattern::new();
+        readers::hus::read(&mut Cursor::new(output), &mut read).unwrap();
+        assert_eq!(read.count_stitches(), pattern.count_stitches());
+        assert_eq!(read.bounds(), pattern.bounds());
+        assert_eq!(read.count_trims(), 1);
+        assert_eq!(read.count_color_changes(), 1);
+        let colors: Vec<u32> = read.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![palette[3].color, palette[10].color]);
+    }
+}
@@ -4,6 +4,8 @@
 //! descriptions, and chart references. Thread-only format with no stitch data.
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use byteorder::{BigEndian, WriteBytesExt};
 use std::io::{Seek, SeekFrom, Write};
@@ -31,7 +33,7 @@ use std::io::{Seek, SeekFrom, Write};
 /// let mut file = File::create("threads.inf").unwrap();
 /// butabuti::formats::io::writers::inf::write(&pattern, &mut file).unwrap();
 /// ```
-pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<WriteReport> {
     let threads = pattern.threads();
 
     // Write header
@@ -71,7 +73,12 @@ pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<()>
     file.write_u32::<BigEndian>(offset)?;
     file.seek(SeekFrom::Start(current_pos))?;
 
-    Ok(())
+    // Only the threads are stored
+    let mut report = WriteReport::new(Format::INF);
+    report.drop_stitches(pattern);
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 #[cfg(test)]
@@ -5,34 +5,194 @@
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::formats::io::utils::WriteHelper;
 use crate::palettes::thread_jef::JEF_THREADS;
-use crate::utils::error::Result;
+use crate::utils::error::{Error, Result};
 use std::io::Write;
 
-// Hoop size constants (in mm, embroidery units are 1/10 mm)
-const HOOP_110X110: i32 = 0;
-const HOOP_50X50: i32 = 1;
-const HOOP_140X200: i32 = 2;
-const HOOP_126X110: i32 = 3;
-const HOOP_200X200: i32 = 4;
-
-/// Get JEF hoop size based on design dimensions
-fn get_jef_hoop_size(width: i32, height: i32) -> i32 {
-    // Select smallest hoop that fits the design
-    if width < 500 && height < 500 {
-        return HOOP_50X50;
+/// Bytes of the header date field
+const JEF_DATE_LENGTH: usize = 14;
+
+/// Janome hoop recorded in the JEF header
+///
+/// Machines select the hoop from this code when loading the design. Hoop
+/// letters differ between machine generations: the 126 x 110 mm hoop is A on
+/// current machines, where the 110 x 110 mm one was A on older ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JefHoop {
+    /// 110 x 110 mm
+    Hoop110x110,
+    /// 50 x 50 mm, hoop C
+    Hoop50x50,
+    /// 140 x 200 mm, hoop B
+    Hoop140x200,
+    /// 126 x 110 mm, hoop A
+    Hoop126x110,
+    /// 200 x 200 mm, hoop SQ20
+    Hoop200x200,
+}
+
+impl JefHoop {
+    /// Hoop A
+    pub const A: Self = JefHoop::Hoop126x110;
+    /// Hoop B
+    pub const B: Self = JefHoop::Hoop140x200;
+    /// Hoop C
+    pub const C: Self = JefHoop::Hoop50x50;
+
+    /// Hoops tried by [`for_size`](Self::for_size), smallest first
+    const BY_SIZE: [Self; 4] = [
+        JefHoop::Hoop50x50,
+        JefHoop::Hoop126x110,
+        JefHoop::Hoop140x200,
+        JefHoop::Hoop200x200,
+    ];
+
+    /// Code stored in the header
+    pub fn code(&self) -> i32 {
+        match self {
+            JefHoop::Hoop110x110 => 0,
+            JefHoop::Hoop50x50 => 1,
+            JefHoop::Hoop140x200 => 2,
+            JefHoop::Hoop126x110 => 3,
+            JefHoop::Hoop200x200 => 4,
+        }
+    }
+
+    /// Hoop of a header code
+    pub fn from_code(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(JefHoop::Hoop110x110),
+            1 => Some(JefHoop::Hoop50x50),
+            2 => Some(JefHoop::Hoop140x200),
+            3 => Some(JefHoop::Hoop126x110),
+            4 => Some(JefHoop::Hoop200x200),
+            _ => None,
+        }
+    }
+
+    /// Sewing field width and height (0.1mm)
+    pub fn size(&self) -> (i32, i32) {
+        match self {
+            JefHoop::Hoop110x110 => (1100, 1100),
+            JefHoop::Hoop50x50 => (500, 500),
+            JefHoop::Hoop140x200 => (1400, 2000),
+            JefHoop::Hoop126x110 => (1260, 1100),
+            JefHoop::Hoop200x200 => (2000, 2000),
+        }
+    }
+
+    /// Whether a design of the given width and height (0.1mm) fits
+    pub fn fits(&self, width: i32, height: i32) -> bool {
+        let (hoop_width, hoop_height) = self.size();
+        width < hoop_width && height < hoop_height
+    }
+
+    /// Smallest hoop a design of the given width and height (0.1mm) fits
+    ///
+    /// Designs too large for every hoop get the 110 x 110 mm code, which
+    /// machines treat as the default.
+    pub fn for_size(width: i32, height: i32) -> Self {
+        Self::BY_SIZE
+            .into_iter()
+            .find(|hoop| hoop.fits(width, height))
+            .unwrap_or(JefHoop::Hoop110x110)
+    }
+
+    /// Hoop of a name like `126x110`, as stored in the `jef_hoop` metadata
+    pub fn from_name(name: &str) -> Option<Self> {
+        (0..5)
+            .filter_map(Self::from_code)
+            .find(|hoop| hoop.to_string() == name.trim())
+    }
+}
+
+impl std::fmt::Display for JefHoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (width, height) = self.size();
+        write!(f, "{}x{}", width / 10, height / 10)
+    }
+}
+
+/// Options for writing JEF files
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::io::writers::jef::{JefHoop, JefWriteOptions};
+///
+/// let options = JefWriteOptions::new()
+///     .hoop(Some(JefHoop::B))
+///     .trims(true, 3)
+///     .date("20250101120000");
+/// assert_eq!(options.hoop, Some(JefHoop::Hoop140x200));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct JefWriteOptions {
+    /// Hoop recorded in the header
+    ///
+    /// `None` keeps the hoop of the `jef_hoop` metadata when the design fits
+    /// it, and otherwise picks the smallest hoop the design fits.
+    pub hoop: Option<JefHoop>,
+    /// Encode trims as trim records
+    pub trims: bool,
+    /// Trim records written for each trim
+    pub trim_records: usize,
+    /// Date stored in the header, as `YYYYMMDDHHmmss` (at most 14 bytes;
+    /// default: `20250101000000`, so output doesn't depend on the clock)
+    pub date: String,
+}
+
+impl Default for JefWriteOptions {
+    fn default() -> Self {
+        Self {
+            hoop: None,
+            trims: true,
+            trim_records: 100,
+            date: "20250101000000".to_string(),
+        }
+    }
+}
+
+impl JefWriteOptions {
+    /// Create default JEF options
+    pub fn new() -> Self {
+        Self::default()
     }
-    if width < 1260 && height < 1100 {
-        return HOOP_126X110;
+
+    /// Set the hoop, or `None` to select it from the design
+    pub fn hoop(mut self, hoop: Option<JefHoop>) -> Self {
+        self.hoop = hoop;
+        self
     }
-    if width < 1400 && height < 2000 {
-        return HOOP_140X200;
+
+    /// Set whether trims are written, and how many records each takes
+    pub fn trims(mut self, trims: bool, records: usize) -> Self {
+        self.trims = trims;
+        self.trim_records = records;
+        self
     }
-    if width < 2000 && height < 2000 {
-        return HOOP_200X200;
+
+    /// Set the date stored in the header
+    ///
+    /// Writing fails if `date` is longer than 14 bytes.
+    pub fn date(mut self, date: impl Into<String>) -> Self {
+        self.date = date.into();
+        self
+    }
+
+    /// Hoop written for a design of the given width and height (0.1mm)
+    fn hoop_for(&self, pattern: &EmbPattern, width: i32, height: i32) -> JefHoop {
+        self.hoop.unwrap_or_else(|| {
+            pattern
+                .get_metadata("jef_hoop")
+                .and_then(|name| JefHoop::from_name(name))
+                .filter(|hoop| hoop.fits(width, height))
+                .unwrap_or_else(|| JefHoop::for_size(width, height))
+        })
     }
-    HOOP_110X110
 }
 
 /// Write hoop edge distances
@@ -153,11 +313,18 @@ fn count_points(pattern: &EmbPattern, trims: bool, trim_at: usize) -> i32 {
 pub fn write<W: Write>(
     writer: &mut W,
     pattern: &EmbPattern,
-    trims: bool,
-    trim_at: usize,
-    date_string: &str,
-) -> Result<()> {
+    options: &JefWriteOptions,
+) -> Result<WriteReport> {
+    let (trims, trim_at) = (options.trims, options.trim_records);
+    let date_bytes = options.date.as_bytes();
+    if date_bytes.len() > JEF_DATE_LENGTH {
+        return Err(
+            Error::limit_exceeded("JEF date length", JEF_DATE_LENGTH, date_bytes.len())
+                .in_format(Format::JEF),
+        );
+    }
     let mut helper = WriteHelper::new(writer);
+    let mut report = WriteReport::new(Format::JEF);
 
     // Build palette
     let palette = build_palette(pattern);
@@ -169,10 +336,8 @@ pub fn write<W: Write>(
     helper.write_i32_le(0x14)?;
 
     // Write date string (14 bytes)
-    let date_bytes = date_string.as_bytes();
-    let len = date_bytes.len().min(14);
-    helper.write_bytes(&date_bytes[..len])?;
-    for _ in len..14 {
+    helper.write_bytes(date_bytes)?;
+    for _ in date_bytes.len()..JEF_DATE_LENGTH {
         helper.write_u8(0)?;
     }
     helper.write_u8(0)?;
@@ -188,7 +353,11 @@ pub fn write<W: Write>(
     let design_width = (bounds.2 - bounds.0).round() as i32;
     let design_height = (bounds.3 - bounds.1).round() as i32;
 
-    helper.write_i32_le(get_jef_hoop_size(design_width, design_height))?;
+    helper.write_i32_le(
+        options
+            .hoop_for(pattern, design_width, design_height)
+            .code(),
+    )?;
 
     let half_width = design_width / 2;
     let half_height = design_height / 2;
@@ -257,17 +426,20 @@ pub fn write<W: Write>(
                 helper.write_i8((-dy) as i8)?;
             }
             END => break,
-            _ => {}
+            _ => report.drop_command(data),
         }
     }
 
     // Write end marker
     helper.write_bytes(&[0x80, 0x10])?;
 
-    Ok(())
+    report.drop_metadata(pattern, &["jef_hoop"]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write JEF file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern) -> Result<()> {
     let file = std::fs::File::create(path)?;
     let mut writer = std::io::BufWriter::new(file);
@@ -275,7 +447,12 @@ pub fn write_file(path: &str, pattern: &EmbPattern) -> Result<()> {
     // Get current date in JEF format (YYYYMMDDHHmmss)
     let date_string = chrono::Local::now().format("%Y%m%d%H%M%S").to_string();
 
-    write(&mut writer, pattern, false, 3, &date_string)
+    write(
+        &mut writer,
+        pattern,
+        &JefWriteOptions::new().trims(false, 3).date(date_string),
+    )?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -284,11 +461,51 @@ mod tests {
 
     #[test]
     fn test_jef_hoop_sizes() {
-        assert_eq!(get_jef_hoop_size(400, 400), HOOP_50X50);
-        assert_eq!(get_jef_hoop_size(1000, 1000), HOOP_126X110);
-        assert_eq!(get_jef_hoop_size(1300, 1900), HOOP_140X200);
-        assert_eq!(get_jef_hoop_size(1900, 1900), HOOP_200X200);
-        assert_eq!(get_jef_hoop_size(2500, 2500), HOOP_110X110);
+        assert_eq!(JefHoop::for_size(400, 400), JefHoop::Hoop50x50);
+        assert_eq!(JefHoop::for_size(1000, 1000), JefHoop::Hoop126x110);
+        assert_eq!(JefHoop::for_size(1300, 1900), JefHoop::Hoop140x200);
+        assert_eq!(JefHoop::for_size(1900, 1900), JefHoop::Hoop200x200);
+        assert_eq!(JefHoop::for_size(2500, 2500), JefHoop::Hoop110x110);
+
+        for code in 0..5 {
+            let hoop = JefHoop::from_code(code).unwrap();
+            assert_eq!(hoop.code(), code);
+            assert_eq!(JefHoop::from_name(&hoop.to_string()), Some(hoop));
+        }
+        assert_eq!(JefHoop::from_code(5), None);
+    }
+
+    #[test]
+    fn test_jef_hoop_round_trip() {
+        use crate::formats::io::readers::jef;
+        use std::io::Cursor;
+
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 100.0);
+        pattern.end();
+
+        let write_hoop = |pattern: &EmbPattern, options: &JefWriteOptions| {
+            let mut buffer = Vec::new();
+            write(&mut buffer, pattern, options).unwrap();
+            assert_eq!(&buffer[4..8], &[0x14, 0, 0, 0]);
+            jef::read(&mut Cursor::new(buffer), None).unwrap()
+        };
+
+        // Selected from the bounds, then kept through a round trip
+        let read = write_hoop(&pattern, &JefWriteOptions::new());
+        assert_eq!(read.get_metadata("jef_hoop").unwrap(), "50x50");
+
+        let read = write_hoop(&pattern, &JefWriteOptions::new().hoop(Some(JefHoop::B)));
+        assert_eq!(read.get_metadata("jef_hoop").unwrap(), "140x200");
+        let read = write_hoop(&read, &JefWriteOptions::new());
+        assert_eq!(read.get_metadata("jef_hoop").unwrap(), "140x200");
+
+        // A recorded hoop the design no longer fits is replaced
+        let mut large = read.clone();
+        large.stitch_abs(1500.0, 100.0);
+        let read = write_hoop(&large, &JefWriteOptions::new());
+        assert_eq!(read.get_metadata("jef_hoop").unwrap(), "200x200");
     }
 
     #[test]
@@ -300,9 +517,23 @@ mod tests {
         pattern.end();
 
         let mut buffer = Vec::new();
-        let result = write(&mut buffer, &pattern, false, 3, "20251008120000");
+        let result = write(
+            &mut buffer,
+            &pattern,
+            &JefWriteOptions::new()
+                .trims(false, 3)
+                .date("20251008120000"),
+        );
         assert!(result.is_ok());
         assert!(buffer.len() > 100); // JEF has a header
+        assert_eq!(&buffer[8..22], b"20251008120000");
+
+        let mut buffer = Vec::new();
+        write(&mut buffer, &pattern, &JefWriteOptions::default()).unwrap();
+        assert_eq!(&buffer[8..22], b"20250101000000");
+
+        let long = JefWriteOptions::new().date("2025-10-08 12:00:00");
+        assert!(write(&mut Vec::new(), &pattern, &long).is_err());
     }
 
     #[test]
@@ -325,9 +556,11 @@ mod tests {
         let original_stitch_count = original.count_stitches();
         let original_thread_count = original.threads().len();
 
-        // Write to buffer (trims=false, trim_at=127, date)
         let mut buffer = Cursor::new(Vec::new());
-        write(&mut buffer, &original, false, 127, "20251011120000").unwrap();
+        let options = JefWriteOptions::new()
+            .trims(false, 127)
+            .date("20251011120000");
+        write(&mut buffer, &original, &options).unwrap();
 
         // Verify buffer has data
         assert!(!buffer.get_ref().is_empty());
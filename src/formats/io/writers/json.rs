@@ -3,12 +3,16 @@
 //! Writes lossless interchange format preserving all pattern data including stitches,
 //! threads, extras, and metadata in human-readable JSON structure.
 
+use crate::core::attributes::Attributes;
 use crate::core::constants::*;
-use crate::core::pattern::EmbPattern;
+use crate::core::pattern::{EmbPattern, License};
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{BufWriter, Write};
 
 /// JSON representation of an embroidery pattern
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,6 +20,9 @@ struct JsonPattern {
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     metadata: HashMap<String, String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<License>,
+
     #[serde(skip_serializing_if = "Vec::is_empty")]
     threads: Vec<JsonThread>,
 
@@ -44,22 +51,43 @@ struct JsonThread {
 /// JSON representation of a stitch
 #[derive(Debug, Serialize, Deserialize)]
 struct JsonStitch {
-    command: String,
+    command: Cow<'static, str>,
     x: f64,
     y: f64,
+
+    /// Extended command bits (thread, needle, order, chenille loop height)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<u32>,
+
+    /// Extended attributes of the stitch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<Attributes>,
 }
 
 /// Write an embroidery pattern to JSON
-pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<WriteReport> {
     let json_pattern = to_json_pattern(pattern);
-    serde_json::to_writer_pretty(writer, &json_pattern)?;
-    Ok(())
+
+    // The serializer issues many tiny writes; batch them for unbuffered writers
+    let mut out = BufWriter::with_capacity(64 * 1024, writer);
+    serde_json::to_writer_pretty(&mut out, &json_pattern)?;
+    out.flush()?;
+    Ok(WriteReport::new(Format::JSON))
 }
 
+/// Metadata keys backing `EmbPattern::license`
+const LICENSE_KEYS: [&str; 3] = ["license", "license_uses", "license_purchaser_id"];
+
 /// Convert EmbPattern to JSON representation
 fn to_json_pattern(pattern: &EmbPattern) -> JsonPattern {
+    let license = pattern.license();
+
+    // License keys are serialized as a structured object instead
     let mut metadata = HashMap::new();
     for (key, value) in pattern.metadata() {
+        if license.is_some() && LICENSE_KEYS.contains(&key.as_str()) {
+            continue;
+        }
         metadata.insert(key.clone(), value.clone());
     }
 
@@ -78,44 +106,57 @@ fn to_json_pattern(pattern: &EmbPattern) -> JsonPattern {
     let stitches = pattern
         .stitches()
         .iter()
-        .map(|stitch| JsonStitch {
-            command: command_to_string(stitch.command),
+        .enumerate()
+        .map(|(index, stitch)| JsonStitch {
+            command: command_to_string(stitch.command & COMMAND_MASK),
             x: stitch.x,
             y: stitch.y,
+            flags: Some(stitch.command & !COMMAND_MASK).filter(|&f| f != 0),
+            attributes: pattern.stitch_attributes(index).cloned(),
         })
         .collect();
 
     JsonPattern {
         metadata,
+        license,
         threads,
         stitches,
     }
 }
 
 /// Convert command constant to string
-fn command_to_string(command: u32) -> String {
+///
+/// Known commands borrow a static name, so no string is allocated per stitch.
+fn command_to_string(command: u32) -> Cow<'static, str> {
     match command {
-        STITCH => "STITCH".to_string(),
-        JUMP => "JUMP".to_string(),
-        TRIM => "TRIM".to_string(),
-        COLOR_CHANGE => "COLOR_CHANGE".to_string(),
-        NEEDLE_SET => "NEEDLE_SET".to_string(),
-        STOP => "STOP".to_string(),
-        END => "END".to_string(),
-        SEQUENCE_BREAK => "SEQUENCE_BREAK".to_string(),
-        COLOR_BREAK => "COLOR_BREAK".to_string(),
-        SLOW => "SLOW".to_string(),
-        FAST => "FAST".to_string(),
-        SEQUIN_MODE => "SEQUIN_MODE".to_string(),
-        SEQUIN_EJECT => "SEQUIN_EJECT".to_string(),
-        _ => format!("UNKNOWN_{}", command),
+        STITCH => Cow::Borrowed("STITCH"),
+        JUMP => Cow::Borrowed("JUMP"),
+        TRIM => Cow::Borrowed("TRIM"),
+        COLOR_CHANGE => Cow::Borrowed("COLOR_CHANGE"),
+        NEEDLE_SET => Cow::Borrowed("NEEDLE_SET"),
+        STOP => Cow::Borrowed("STOP"),
+        END => Cow::Borrowed("END"),
+        SEQUENCE_BREAK => Cow::Borrowed("SEQUENCE_BREAK"),
+        COLOR_BREAK => Cow::Borrowed("COLOR_BREAK"),
+        SLOW => Cow::Borrowed("SLOW"),
+        FAST => Cow::Borrowed("FAST"),
+        SEQUIN_MODE => Cow::Borrowed("SEQUIN_MODE"),
+        SEQUIN_EJECT => Cow::Borrowed("SEQUIN_EJECT"),
+        CHENILLE_LOOP_HEIGHT => Cow::Borrowed("CHENILLE_LOOP_HEIGHT"),
+        CORDING_ON => Cow::Borrowed("CORDING_ON"),
+        CORDING_OFF => Cow::Borrowed("CORDING_OFF"),
+        BORER_ON => Cow::Borrowed("BORER_ON"),
+        BORER_OFF => Cow::Borrowed("BORER_OFF"),
+        _ => Cow::Owned(format!("UNKNOWN_{}", command)),
     }
 }
 
 /// Write JSON file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern) -> Result<()> {
     let mut file = std::fs::File::create(path)?;
-    write(&mut file, pattern)
+    write(&mut file, pattern)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -160,6 +201,56 @@ mod tests {
         assert!(json_str.contains("{}") || json_str.contains("{\n}"));
     }
 
+    #[test]
+    fn test_license_roundtrip() {
+        use crate::formats::io::readers;
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_metadata("name", "Licensed");
+        pattern.set_license(
+            &License::new("Commercial")
+                .with_use("sell-finished-items")
+                .with_purchaser_id("ORD-99"),
+        );
+
+        let mut output = Vec::new();
+        write(&mut output, &pattern).unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(value["license"]["name"], "Commercial");
+        assert_eq!(value["license"]["purchaser_id"], "ORD-99");
+        assert!(value["metadata"].get("license").is_none());
+
+        let read_back = readers::json::read(&mut output.as_slice()).unwrap();
+        assert_eq!(read_back.license(), pattern.license());
+    }
+
+    #[test]
+    fn test_decoration_commands_roundtrip() {
+        use crate::formats::io::readers;
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_command(encode_chenille_loop_height(35), 0.0, 0.0);
+        pattern.add_command(CORDING_ON, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 10.0, 0.0);
+        pattern.add_command(CORDING_OFF, 10.0, 0.0);
+
+        let mut output = Vec::new();
+        write(&mut output, &pattern).unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(value["stitches"][1]["command"], "CHENILLE_LOOP_HEIGHT");
+        assert_eq!(value["stitches"][1]["flags"], 35 << 8);
+        assert!(value["stitches"][0].get("flags").is_none());
+
+        let read_back = readers::json::read(&mut output.as_slice()).unwrap();
+        let commands: Vec<u32> = read_back.stitches().iter().map(|s| s.command).collect();
+        let original: Vec<u32> = pattern.stitches().iter().map(|s| s.command).collect();
+        assert_eq!(commands, original);
+        assert_eq!(chenille_loop_height(commands[1]), Some(35));
+    }
+
     #[test]
     fn test_write_thread_details() {
         let mut pattern = EmbPattern::new();
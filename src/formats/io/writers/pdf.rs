@@ -0,0 +1,620 @@
+//! PDF production worksheet writer
+//!
+//! Writes the sheet that goes to the machine operator with a design: a rendered
+//! preview with hoop placement crosshairs, the design's size, stitch count and
+//! estimated run time, the color sequence with thread brand and catalog numbers, and
+//! any notes attached to STOP commands.
+//!
+//! The PDF is written by hand, using only the standard Helvetica fonts, so no font
+//! files are embedded. Text outside Latin-1 is replaced with `?`.
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::formats::io::writers::png::compress_zlib;
+use crate::render::{render_to_rgba, RenderOptions, RgbaImage};
+use crate::utils::error::{Error, Result};
+use crate::utils::simulate::{simulate, TimingProfile};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// Points per millimeter
+const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// Page margin in points
+const MARGIN: f64 = 36.0;
+
+/// Height of a color table row in points
+const ROW_HEIGHT: f64 = 16.0;
+
+/// Largest preview image side in pixels
+const MAX_IMAGE_SIDE: f64 = 2400.0;
+
+/// Paper size of the worksheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    /// ISO A4, 210 x 297mm
+    A4,
+    /// US Letter, 8.5 x 11in
+    Letter,
+}
+
+impl PageSize {
+    /// Width and height in points
+    fn dimensions(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+/// PDF worksheet settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfSettings {
+    /// Paper size (default: A4)
+    pub page_size: PageSize,
+    /// Machine timing for the run time estimate (default: 800 spm top speed)
+    pub timing: TimingProfile,
+    /// Resolution of the preview image (default: 150)
+    pub dpi: f64,
+    /// How the preview is drawn; the scale is chosen from `dpi` (default: shaded on white)
+    pub render: RenderOptions,
+}
+
+impl Default for PdfSettings {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            timing: TimingProfile::default(),
+            dpi: 150.0,
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+/// One color block of the sewing sequence
+struct ColorStop {
+    thread: EmbThread,
+    stitches: usize,
+}
+
+/// Write a production worksheet as PDF
+pub fn write(pattern: &EmbPattern, file: &mut impl Write, settings: &PdfSettings) -> Result<()> {
+    if !(settings.dpi > 0.0 && settings.dpi.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Worksheet DPI must be positive, got {}",
+            settings.dpi
+        )));
+    }
+
+    let (page_width, page_height) = settings.page_size.dimensions();
+    let stats = pattern.calculate_statistics(settings.timing.max_speed_spm);
+    let run_time = simulate(pattern, &settings.timing).total_minutes();
+    let stops = color_sequence(pattern);
+
+    let mut pages: Vec<String> = Vec::new();
+    let mut content = String::new();
+
+    // Title
+    let title = pattern.title().unwrap_or("Embroidery Worksheet");
+    text(
+        &mut content,
+        "F2",
+        18.0,
+        MARGIN,
+        page_height - MARGIN - 18.0,
+        title,
+    );
+
+    // Preview with crosshairs, at 100% when it fits
+    let box_top = page_height - MARGIN - 32.0;
+    let box_height = (page_height * 0.42).floor();
+    let box_width = page_width - 2.0 * MARGIN;
+    let box_bottom = box_top - box_height;
+    let _ = writeln!(
+        content,
+        "0.8 G 0.5 w {:.2} {:.2} {:.2} {:.2} re S",
+        MARGIN, box_bottom, box_width, box_height
+    );
+
+    let mut image = None;
+    if stats.stitch_count > 0 {
+        let design_width = stats.width_mm.max(0.1) * PT_PER_MM;
+        let design_height = stats.height_mm.max(0.1) * PT_PER_MM;
+        let fit = ((box_width - 24.0) / design_width)
+            .min((box_height - 24.0) / design_height)
+            .min(1.0);
+
+        // Render at the requested DPI for the printed size
+        let px_per_mm = (settings.dpi / 25.4 * fit)
+            .min(MAX_IMAGE_SIDE / stats.width_mm.max(stats.height_mm).max(0.1));
+        let options = settings.render.clone().scale(px_per_mm).margin(0);
+        let rgba = render_to_rgba(pattern, &options)?;
+
+        // The image includes padding for the thread width; size it so 1mm stays 1mm
+        let pt_per_px = PT_PER_MM * fit / px_per_mm;
+        let (img_w, img_h) = (
+            rgba.width as f64 * pt_per_px,
+            rgba.height as f64 * pt_per_px,
+        );
+        let img_x = MARGIN + (box_width - img_w) / 2.0;
+        let img_y = box_bottom + (box_height - img_h) / 2.0;
+        let _ = writeln!(
+            content,
+            "q {:.3} 0 0 {:.3} {:.3} {:.3} cm /Im1 Do Q",
+            img_w, img_h, img_x, img_y
+        );
+
+        // Crosshairs through the design center; the padding is even on all sides
+        let (center_x, center_y) = (img_x + img_w / 2.0, img_y + img_h / 2.0);
+        let _ = writeln!(
+            content,
+            "0.85 0 0 RG 0.6 w [4 3] 0 d {:.2} {:.2} m {:.2} {:.2} l S {:.2} {:.2} m {:.2} {:.2} l S [] 0 d",
+            MARGIN + 4.0,
+            center_y,
+            MARGIN + box_width - 4.0,
+            center_y,
+            center_x,
+            box_bottom + 4.0,
+            center_x,
+            box_top - 4.0
+        );
+        let _ = writeln!(
+            content,
+            "{:.2} {:.2} 6 6 re S",
+            center_x - 3.0,
+            center_y - 3.0
+        );
+
+        let scale_note = format!("Scale {:.0}%", fit * 100.0);
+        text(
+            &mut content,
+            "F1",
+            8.0,
+            MARGIN + 4.0,
+            box_bottom + 4.0,
+            &scale_note,
+        );
+        image = Some(rgba);
+    } else {
+        text(
+            &mut content,
+            "F1",
+            10.0,
+            MARGIN + 8.0,
+            box_bottom + box_height / 2.0,
+            "No stitches",
+        );
+    }
+
+    // Statistics
+    let mut y = box_bottom - 24.0;
+    let facts = [
+        (
+            "Size",
+            format!(
+                "{:.1} x {:.1} mm ({:.2} x {:.2} in)",
+                stats.width_mm,
+                stats.height_mm,
+                stats.width_mm / 25.4,
+                stats.height_mm / 25.4
+            ),
+        ),
+        ("Stitches", stats.stitch_count.to_string()),
+        ("Colors", stops.len().to_string()),
+        ("Color changes", stats.color_change_count.to_string()),
+        ("Trims", stats.trim_count.to_string()),
+        ("Jumps", stats.jump_count.to_string()),
+        (
+            "Run time",
+            format!(
+                "{} (at {:.0} spm)",
+                format_minutes(run_time),
+                settings.timing.max_speed_spm
+            ),
+        ),
+        (
+            "Thread length",
+            format!("{:.2} m", stats.total_length_mm / 1000.0),
+        ),
+    ];
+    let half = facts.len().div_ceil(2);
+    for (i, (label, value)) in facts.iter().enumerate() {
+        let column_x = MARGIN + if i < half { 0.0 } else { box_width / 2.0 };
+        let row_y = y - (i % half) as f64 * 14.0;
+        text(&mut content, "F2", 10.0, column_x, row_y, label);
+        text(&mut content, "F1", 10.0, column_x + 80.0, row_y, value);
+    }
+    y -= half as f64 * 14.0 + 18.0;
+
+    // Color sequence, continued on further pages as needed
+    let columns = [0.0, 24.0, 52.0, 250.0, 360.0, 450.0];
+    let header = |content: &mut String, y: f64| {
+        for (label, x) in ["#", "", "Color", "Brand", "Catalog", "Stitches"]
+            .iter()
+            .zip(columns)
+        {
+            text(content, "F2", 10.0, MARGIN + x, y, label);
+        }
+        let _ = writeln!(
+            content,
+            "0 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S",
+            MARGIN,
+            y - 4.0,
+            MARGIN + box_width,
+            y - 4.0
+        );
+    };
+    text(&mut content, "F2", 12.0, MARGIN, y, "Color Sequence");
+    y -= 20.0;
+    header(&mut content, y);
+    y -= ROW_HEIGHT + 2.0;
+
+    for (i, stop) in stops.iter().enumerate() {
+        if y < MARGIN {
+            pages.push(std::mem::take(&mut content));
+            y = page_height - MARGIN - 12.0;
+            header(&mut content, y);
+            y -= ROW_HEIGHT + 2.0;
+        }
+        let thread = &stop.thread;
+        let _ = writeln!(
+            content,
+            "{:.3} {:.3} {:.3} rg 0 G 0.5 w {:.2} {:.2} 18 10 re B",
+            thread.red() as f64 / 255.0,
+            thread.green() as f64 / 255.0,
+            thread.blue() as f64 / 255.0,
+            MARGIN + columns[1],
+            y - 2.0
+        );
+        let name = format!("{} {}", thread.display_name(), thread.hex_color());
+        let cells = [
+            (i + 1).to_string(),
+            String::new(),
+            name,
+            thread.brand.clone().unwrap_or_default(),
+            thread.catalog_number.clone().unwrap_or_default(),
+            stop.stitches.to_string(),
+        ];
+        let _ = writeln!(content, "0 g");
+        for (cell, x) in cells.iter().zip(columns) {
+            if !cell.is_empty() {
+                text(&mut content, "F1", 10.0, MARGIN + x, y, cell);
+            }
+        }
+        y -= ROW_HEIGHT;
+    }
+
+    // Operator notes at STOP commands
+    let notes = pattern.stop_notes();
+    if !notes.is_empty() {
+        let positions = pattern.stop_positions();
+        y -= 12.0;
+        if y < MARGIN + 20.0 + ROW_HEIGHT {
+            pages.push(std::mem::take(&mut content));
+            y = page_height - MARGIN - 12.0;
+        }
+        text(&mut content, "F2", 12.0, MARGIN, y, "Stops");
+        y -= 20.0;
+
+        for (index, note) in &notes {
+            if y < MARGIN {
+                pages.push(std::mem::take(&mut content));
+                y = page_height - MARGIN - 12.0;
+            }
+            let label = match positions.get(*index) {
+                Some(position) => format!("Stop {} (stitch {})", index + 1, position + 1),
+                None => format!("Stop {}", index + 1),
+            };
+            text(&mut content, "F2", 10.0, MARGIN, y, &label);
+            text(
+                &mut content,
+                "F1",
+                10.0,
+                MARGIN + 120.0,
+                y,
+                &note.to_string(),
+            );
+            y -= ROW_HEIGHT;
+        }
+    }
+    pages.push(content);
+
+    file.write_all(&build_pdf(&pages, image.as_ref(), page_width, page_height))?;
+    Ok(())
+}
+
+/// Color blocks in sewing order with their stitch counts
+fn color_sequence(pattern: &EmbPattern) -> Vec<ColorStop> {
+    let mut counts = vec![0usize];
+    for stitch in pattern.stitches() {
+        match stitch.command & COMMAND_MASK {
+            STITCH => *counts.last_mut().unwrap() += 1,
+            COLOR_CHANGE => counts.push(0),
+            _ => {}
+        }
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, stitches)| stitches > 0)
+        .map(|(index, stitches)| ColorStop {
+            thread: pattern
+                .threads()
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| EmbThread::new(0x000000)),
+            stitches,
+        })
+        .collect()
+}
+
+/// "1 h 05 min", "12 min 30 s" or "45 s"
+fn format_minutes(minutes: f64) -> String {
+    let seconds = (minutes * 60.0).round().max(0.0) as u64;
+    let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if h > 0 {
+        format!("{} h {:02} min", h, m)
+    } else if m > 0 {
+        format!("{} min {:02} s", m, s)
+    } else {
+        format!("{} s", s)
+    }
+}
+
+/// Append a text line to a content stream
+fn text(content: &mut String, font: &str, size: f64, x: f64, y: f64, value: &str) {
+    let _ = writeln!(
+        content,
+        "BT /{} {} Tf {:.2} {:.2} Td ({}) Tj ET",
+        font,
+        size,
+        x,
+        y,
+        escape(value)
+    );
+}
+
+/// Escape a PDF literal string, replacing characters outside Latin-1
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => {
+                let _ = write!(out, "\\{:03o}", c as u32);
+            }
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+/// Assemble the PDF objects, xref table and trailer
+fn build_pdf(
+    pages: &[String],
+    image: Option<&RgbaImage>,
+    page_width: f64,
+    page_height: f64,
+) -> Vec<u8> {
+    // Objects: 1 catalog, 2 page tree, 3-4 fonts, 5 image (optional), then page/content pairs
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    let first_page = 6;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", first_page + i * 2))
+        .collect();
+
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+    );
+    objects.push(
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    );
+    objects.push(
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    );
+
+    match image {
+        Some(image) => {
+            // Flatten onto white; RGB only
+            let mut rgb = Vec::with_capacity(image.width * image.height * 3);
+            for px in image.pixels.chunks_exact(4) {
+                let alpha = px[3] as u32;
+                for &c in &px[..3] {
+                    rgb.push(((c as u32 * alpha + 255 * (255 - alpha)) / 255) as u8);
+                }
+            }
+            let data = compress_zlib(&rgb);
+            let mut object = format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+                 /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>\nstream\n",
+                image.width,
+                image.height,
+                data.len()
+            )
+            .into_bytes();
+            object.extend_from_slice(&data);
+            object.extend_from_slice(b"\nendstream");
+            objects.push(object);
+        }
+        None => objects.push(b"null".to_vec()),
+    }
+
+    let xobject = if image.is_some() {
+        " /XObject << /Im1 5 0 R >>"
+    } else {
+        ""
+    };
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >>{} >> /Contents {} 0 R >>",
+                page_width,
+                page_height,
+                xobject,
+                first_page + i * 2 + 1
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend_from_slice(content.as_bytes());
+        stream.extend_from_slice(b"endstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(table, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        table,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    );
+    pdf.extend_from_slice(table.as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::stop_note::StopNote;
+
+    fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        haystack.windows(needle.len()).position(|w| w == needle)
+    }
+
+    fn sample(colors: usize) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Rose (small)");
+        for i in 0..colors {
+            pattern.add_thread(
+                EmbThread::new(0x10 * i as u32)
+                    .with_description("Red")
+                    .with_brand("Madeira")
+                    .with_catalog_number(format!("18{:02}", i)),
+            );
+            if i > 0 {
+                pattern.add_command(COLOR_CHANGE, 0.0, 0.0);
+            }
+            pattern.stitch_abs(0.0, 0.0);
+            pattern.stitch_abs(500.0, 300.0);
+        }
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_write_pdf_worksheet() {
+        let mut output = Vec::new();
+        write(&sample(3), &mut output, &PdfSettings::default()).unwrap();
+
+        assert!(output.starts_with(b"%PDF-1.4"));
+        assert!(output.ends_with(b"%%EOF\n"));
+        assert!(find(&output, b"(Rose \\(small\\)) Tj").is_some());
+        assert!(find(&output, b"(Madeira) Tj").is_some());
+        assert!(find(&output, b"(1802) Tj").is_some());
+        assert!(find(&output, b"(Red #000010) Tj").is_some());
+        assert!(find(&output, b"(50.0 x 30.0 mm \\(1.97 x 1.18 in\\)) Tj").is_some());
+        assert!(find(&output, b"/Im1 Do").is_some());
+        assert!(find(&output, b"/Count 1").is_some());
+
+        // Every xref entry points at its object
+        let startxref = find(&output, b"startxref\n").unwrap() + 10;
+        let xref: usize = std::str::from_utf8(&output[startxref..])
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let table = std::str::from_utf8(&output[xref..]).unwrap();
+        for (i, line) in table
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .enumerate()
+        {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(output[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_pdf_long_color_list_spans_pages() {
+        let mut output = Vec::new();
+        write(&sample(60), &mut output, &PdfSettings::default()).unwrap();
+        assert!(find(&output, b"/Count 2").is_some());
+        assert!(find(&output, b"(60) Tj").is_some());
+    }
+
+    #[test]
+    fn test_pdf_stop_notes() {
+        let mut pattern = sample(2);
+        pattern.stop_with_note(StopNote::place_applique("Red cotton"));
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output, &PdfSettings::default()).unwrap();
+        assert!(find(&output, b"(Stops) Tj").is_some());
+        assert!(find(&output, b"(Stop 1 \\(stitch 7\\)) Tj").is_some());
+        assert!(find(&output, b"(Place applique: Red cotton) Tj").is_some());
+
+        let mut output = Vec::new();
+        write(&sample(2), &mut output, &PdfSettings::default()).unwrap();
+        assert!(find(&output, b"(Stops) Tj").is_none());
+    }
+
+    #[test]
+    fn test_pdf_empty_pattern_and_helpers() {
+        let mut output = Vec::new();
+        let settings = PdfSettings {
+            page_size: PageSize::Letter,
+            ..Default::default()
+        };
+        write(&EmbPattern::new(), &mut output, &settings).unwrap();
+        assert!(find(&output, b"(No stitches) Tj").is_some());
+        assert!(find(&output, b"/Im1").is_none());
+        assert!(find(&output, b"612.00 792.00").is_some());
+
+        // Threads without a description are named like in the other outputs
+        let mut pattern = EmbPattern::new();
+        let thread = EmbThread::new(0xFF0000);
+        pattern.add_thread(thread.clone());
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+        let mut output = Vec::new();
+        write(&pattern, &mut output, &settings).unwrap();
+        let name = format!("({} #ff0000) Tj", thread.display_name());
+        assert!(find(&output, name.as_bytes()).is_some());
+
+        assert_eq!(format_minutes(0.75), "45 s");
+        assert_eq!(format_minutes(12.5), "12 min 30 s");
+        assert_eq!(format_minutes(65.0), "1 h 05 min");
+        assert_eq!(escape("caf\u{e9} \u{2605}"), "caf\\351 ?");
+    }
+}
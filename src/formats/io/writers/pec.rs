@@ -2,10 +2,17 @@
 //!
 //! Writes PEC format with graphics section for LCD preview and thread colors
 //! mapped to the 64-color PEC palette. Includes thumbnail generation.
+//!
+//! The color table has one entry per color block, as machines step through
+//! it at each color change; blocks beyond the pattern's threads reuse them
+//! in order. Each block gets a thumbnail of its stitches, after an overall
+//! thumbnail of the design.
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::formats::io::utils::WriteHelper;
 use crate::palettes::thread_pec::PEC_THREADS;
 use crate::utils::error::Result;
@@ -19,24 +26,39 @@ const FLAG_LONG: u8 = 0b10000000;
 const PEC_ICON_WIDTH: usize = 48;
 const PEC_ICON_HEIGHT: usize = 38;
 
-/// PEC graphics blank template (48x38 bitmap with border)
-const PEC_BLANK: [u8; 234] = [
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x08, 0x00, 0x00, 0x00,
-    0x00, 0x10, 0x04, 0x00, 0x00, 0x00, 0x00, 0x20, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00,
-    0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40,
-    0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00,
-    0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00,
-    0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40,
-    0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00,
-    0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00,
-    0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40,
-    0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00,
-    0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00,
-    0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40,
-    0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x02, 0x00, 0x00, 0x00,
-    0x00, 0x40, 0x02, 0x00, 0x00, 0x00, 0x00, 0x40, 0x04, 0x00, 0x00, 0x00, 0x00, 0x20, 0x08, 0x00,
-    0x00, 0x00, 0x00, 0x10, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F,
-];
+/// Bytes per thumbnail row
+const PEC_ICON_STRIDE: usize = PEC_ICON_WIDTH / 8;
+
+/// Bytes per thumbnail bitmap
+pub const PEC_ICON_SIZE: usize = PEC_ICON_STRIDE * PEC_ICON_HEIGHT;
+
+/// Pixels kept clear between the frame and the drawing
+const PEC_ICON_MARGIN: f64 = 5.0;
+
+/// Characters of the pattern name the label holds, one byte each
+const PEC_LABEL_LENGTH: usize = 8;
+
+/// Blank 48x38 thumbnail with the rounded frame shown by Brother machines
+fn pec_blank() -> Vec<u8> {
+    let mut graphic = vec![0u8; PEC_ICON_SIZE];
+    let last = PEC_ICON_HEIGHT - 2;
+    for (y, row) in graphic.chunks_exact_mut(PEC_ICON_STRIDE).enumerate() {
+        let (left, right) = match y {
+            0 => continue,
+            y if y == 1 || y == last => {
+                row[1..PEC_ICON_STRIDE - 1].fill(0xFF);
+                (0xF0, 0x0F)
+            }
+            y if y == 2 || y == last - 1 => (0x08, 0x10),
+            y if y == 3 || y == last - 2 => (0x04, 0x20),
+            y if y < last => (0x02, 0x40),
+            _ => continue,
+        };
+        row[0] |= left;
+        row[PEC_ICON_STRIDE - 1] |= right;
+    }
+    graphic
+}
 
 /// Build unique color palette for PEC
 fn build_pec_palette(threads: &[EmbThread]) -> Vec<u8> {
@@ -74,7 +96,12 @@ fn write_pec_header<W: Write>(
         .get_metadata("name")
         .map(|s| s.as_str())
         .unwrap_or("Untitled");
-    let truncated_name = if name.len() > 8 { &name[..8] } else { name };
+    // The label is a fixed-width ASCII field; replace anything else
+    let truncated_name: String = name
+        .chars()
+        .take(PEC_LABEL_LENGTH)
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .collect();
 
     // Write label
     let label = format!("LA:{:16}\r", truncated_name);
@@ -84,36 +111,37 @@ fn write_pec_header<W: Write>(
     helper.write_bytes(&[0x20; 12])?;
     helper.write_u8(0xFF)?;
     helper.write_u8(0x00)?;
-    helper.write_u8((PEC_ICON_WIDTH / 8) as u8)?; // byte stride
+    helper.write_u8(PEC_ICON_STRIDE as u8)?; // byte stride
     helper.write_u8(PEC_ICON_HEIGHT as u8)?; // icon height
 
-    // Build color palette
-    let color_indices = build_pec_palette(pattern.threads());
+    // Build color palette; machines expect at least one color
+    let default_thread = [EmbThread::new(0x000000)];
+    let threads = if pattern.threads().is_empty() {
+        &default_thread[..]
+    } else {
+        pattern.threads()
+    };
+    let palette = build_pec_palette(threads);
+    let block_count = color_blocks(pattern).len().max(palette.len());
+    let color_indices: Vec<u8> = (0..block_count)
+        .map(|block| palette[block % palette.len()])
+        .collect();
     let thread_count = color_indices.len();
 
-    if thread_count > 0 {
-        // Write padding
-        helper.write_bytes(&[0x20; 12])?;
+    // Write padding
+    helper.write_bytes(&[0x20; 12])?;
 
-        // Write thread count - 1 as first byte
-        helper.write_u8((thread_count - 1) as u8)?;
+    // Write thread count - 1 as first byte
+    helper.write_u8((thread_count - 1) as u8)?;
 
-        // Write color indices
-        for &index in &color_indices {
-            helper.write_u8(index)?;
-        }
+    // Write color indices
+    for &index in &color_indices {
+        helper.write_u8(index)?;
+    }
 
-        // Pad to 463 bytes total
-        for _ in (thread_count + 1)..463 {
-            helper.write_u8(0x20)?;
-        }
-    } else {
-        // Write default if no threads
-        helper.write_bytes(&[0x20, 0x20, 0x20, 0x20, 0x64, 0x20, 0x00, 0x20])?;
-        helper.write_bytes(&[0x00, 0x20, 0x20, 0x20, 0xFF])?;
-        for _ in 13..463 {
-            helper.write_u8(0x20)?;
-        }
+    // Pad the color table to 463 entries
+    for _ in thread_count..463 {
+        helper.write_u8(0x20)?;
     }
 
     Ok(color_indices)
@@ -161,7 +189,11 @@ fn write_stitch<W: Write>(helper: &mut WriteHelper<W>, dx: i32, dy: i32) -> Resu
 }
 
 /// Encode stitches in PEC format
-fn pec_encode<W: Write>(helper: &mut WriteHelper<W>, pattern: &EmbPattern) -> Result<()> {
+fn pec_encode<W: Write>(
+    helper: &mut WriteHelper<W>,
+    pattern: &EmbPattern,
+    report: &mut WriteReport,
+) -> Result<()> {
     let mut color_two = true;
     let mut jumping = true;
     let mut init = true;
@@ -213,7 +245,9 @@ fn pec_encode<W: Write>(helper: &mut WriteHelper<W>, pattern: &EmbPattern) -> Re
                 helper.write_u8(0xFF)?;
                 break;
             }
-            _ => {} // STOP, TRIM ignored
+            // Trims are carried by the trim bit of the following jump
+            TRIM => {}
+            _ => report.drop_command(data),
         }
         init = false;
     }
@@ -221,125 +255,177 @@ fn pec_encode<W: Write>(helper: &mut WriteHelper<W>, pattern: &EmbPattern) -> Re
     Ok(())
 }
 
-/// Mark a bit in the graphics bitmap
-fn graphic_mark_bit(graphic: &mut [u8], x: i32, y: i32, stride: usize) {
-    if x >= 0 && y >= 0 {
-        let x = x as usize;
-        let y = y as usize;
-        let byte_x = x / 8;
-        let bit_x = x % 8;
-        let index = (y * stride) + byte_x;
-
-        if index < graphic.len() {
-            graphic[index] |= 1 << bit_x;
+/// Mark a bit in the graphics bitmap (least significant bit is the leftmost pixel)
+fn graphic_mark_bit(graphic: &mut [u8], x: i32, y: i32) {
+    if (0..PEC_ICON_WIDTH as i32).contains(&x) && (0..PEC_ICON_HEIGHT as i32).contains(&y) {
+        let (x, y) = (x as usize, y as usize);
+        graphic[y * PEC_ICON_STRIDE + x / 8] |= 1 << (x % 8);
+    }
+}
+
+/// Draw a line between two pixels
+fn graphic_line(graphic: &mut [u8], (x0, y0): (i32, i32), (x1, y1): (i32, i32)) {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+    loop {
+        graphic_mark_bit(graphic, x, y);
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
         }
     }
 }
 
-/// Draw stitches scaled to fit in the graphics area
-fn draw_scaled(
-    bounds: (f64, f64, f64, f64),
-    stitches: &[(f64, f64)],
-    graphic: &mut [u8],
-    stride: usize,
-    buffer: i32,
-) {
+/// Draw stitch runs scaled to fit the thumbnail, centered on the design bounds
+fn draw_scaled(bounds: (f64, f64, f64, f64), runs: &[Vec<(f64, f64)>], graphic: &mut [u8]) {
     let (left, top, right, bottom) = bounds;
+    let diagram_width = (right - left).max(1.0);
+    let diagram_height = (bottom - top).max(1.0);
 
-    let diagram_width = right - left;
-    let diagram_height = bottom - top;
+    let graphic_width = PEC_ICON_WIDTH as f64;
+    let graphic_height = PEC_ICON_HEIGHT as f64;
+    let scale = ((graphic_width - 2.0 * PEC_ICON_MARGIN) / diagram_width)
+        .min((graphic_height - 2.0 * PEC_ICON_MARGIN) / diagram_height);
 
-    let graphic_width = (stride * 8) as f64;
-    let graphic_height = (graphic.len() / stride) as f64;
-
-    let diagram_width = if diagram_width == 0.0 {
-        1.0
-    } else {
-        diagram_width
-    };
-    let diagram_height = if diagram_height == 0.0 {
-        1.0
-    } else {
-        diagram_height
+    let cx = (right + left) / 2.0;
+    let cy = (bottom + top) / 2.0;
+    let to_pixel = |(x, y): (f64, f64)| {
+        (
+            ((x - cx) * scale + graphic_width / 2.0).round() as i32,
+            ((y - cy) * scale + graphic_height / 2.0).round() as i32,
+        )
     };
 
-    let scale_x = (graphic_width - buffer as f64) / diagram_width;
-    let scale_y = (graphic_height - buffer as f64) / diagram_height;
-    let scale = scale_x.min(scale_y);
+    for run in runs {
+        let mut previous = None;
+        for &point in run {
+            let pixel = to_pixel(point);
+            graphic_line(graphic, previous.unwrap_or(pixel), pixel);
+            previous = Some(pixel);
+        }
+    }
+}
 
-    let cx = (right + left) / 2.0;
-    let cy = (bottom + top) / 2.0;
+/// Needle-down runs of each color block; jumps and trims break a run
+///
+/// A color change before the first stitch doesn't start a new block.
+fn color_blocks(pattern: &EmbPattern) -> Vec<Vec<Vec<(f64, f64)>>> {
+    let mut blocks: Vec<Vec<Vec<(f64, f64)>>> = vec![Vec::new()];
+    let mut run: Vec<(f64, f64)> = Vec::new();
+    for stitch in pattern.stitches() {
+        match stitch.command & COMMAND_MASK {
+            STITCH => {
+                run.push((stitch.x, stitch.y));
+                continue;
+            }
+            END => break,
+            _ => {}
+        }
+        if !run.is_empty() {
+            blocks.last_mut().unwrap().push(std::mem::take(&mut run));
+        }
+        if stitch.command & COMMAND_MASK == COLOR_CHANGE
+            && (blocks.len() > 1 || !blocks[0].is_empty())
+        {
+            blocks.push(Vec::new());
+        }
+    }
+    if !run.is_empty() {
+        blocks.last_mut().unwrap().push(run);
+    }
+    blocks
+}
 
-    let translate_x = (-cx * scale) + (graphic_width / 2.0);
-    let translate_y = (-cy * scale) + (graphic_height / 2.0);
+/// Generate the PEC thumbnails of a pattern
+///
+/// Returns `color_count + 1` bitmaps of `PEC_ICON_SIZE` bytes (48x38 pixels,
+/// 1 bit per pixel): the whole design first, then one per color block. Blocks
+/// beyond `color_count` are merged into the last thumbnail, and missing blocks
+/// get an empty frame, so the count always matches the PEC color table.
+///
+/// Thumbnails are scaled to the stitches alone, so a long jump to the hoop
+/// corner doesn't shrink the drawing.
+pub fn thumbnails(pattern: &EmbPattern, color_count: usize) -> Vec<Vec<u8>> {
+    let mut blocks = color_blocks(pattern);
+    let color_count = color_count.max(1);
+    while blocks.len() > color_count {
+        let extra = blocks.pop().unwrap();
+        blocks.last_mut().unwrap().extend(extra);
+    }
 
-    for &(x, y) in stitches {
-        let px = ((x * scale) + translate_x).floor() as i32;
-        let py = ((y * scale) + translate_y).floor() as i32;
-        graphic_mark_bit(graphic, px, py, stride);
+    let bounds = blocks.iter().flatten().flatten().fold(
+        (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+        |(left, top, right, bottom), &(x, y)| {
+            (left.min(x), top.min(y), right.max(x), bottom.max(y))
+        },
+    );
+    let mut icons = Vec::with_capacity(color_count + 1);
+    let mut overall = pec_blank();
+    for runs in &blocks {
+        draw_scaled(bounds, runs, &mut overall);
+    }
+    icons.push(overall);
+    for index in 0..color_count {
+        let mut icon = pec_blank();
+        if let Some(runs) = blocks.get(index) {
+            draw_scaled(bounds, runs, &mut icon);
+        }
+        icons.push(icon);
     }
+    icons
 }
 
 /// Write PEC graphics section
 fn write_pec_graphics<W: Write>(
     helper: &mut WriteHelper<W>,
     pattern: &EmbPattern,
-    bounds: (f64, f64, f64, f64),
+    color_count: usize,
 ) -> Result<()> {
-    // Write overall pattern graphic
-    let mut blank = PEC_BLANK.to_vec();
-    let all_stitches: Vec<(f64, f64)> = pattern
-        .stitches()
-        .iter()
-        .filter(|s| (s.command & COMMAND_MASK) == STITCH)
-        .map(|s| (s.x, s.y))
-        .collect();
-    draw_scaled(bounds, &all_stitches, &mut blank, 6, 4);
-    helper.write_bytes(&blank)?;
-
-    // Write per-color graphics
-    let mut current_stitches = Vec::new();
-    for stitch in pattern.stitches() {
-        let cmd = stitch.command & COMMAND_MASK;
-        if cmd == STITCH {
-            current_stitches.push((stitch.x, stitch.y));
-        } else if cmd == COLOR_CHANGE || cmd == END {
-            if !current_stitches.is_empty() {
-                let mut color_blank = PEC_BLANK.to_vec();
-                draw_scaled(bounds, &current_stitches, &mut color_blank, 6, 5);
-                helper.write_bytes(&color_blank)?;
-                current_stitches.clear();
-            }
-            if cmd == END {
-                break;
-            }
-        }
+    for icon in thumbnails(pattern, color_count) {
+        helper.write_bytes(&icon)?;
     }
+    Ok(())
+}
 
-    // Write final color if any
-    if !current_stitches.is_empty() {
-        let mut color_blank = PEC_BLANK.to_vec();
-        draw_scaled(bounds, &current_stitches, &mut color_blank, 6, 5);
-        helper.write_bytes(&color_blank)?;
+/// Record a pattern name longer than the PEC label
+pub(crate) fn report_label(pattern: &EmbPattern, report: &mut WriteReport) {
+    let length = pattern
+        .get_metadata("name")
+        .map_or(0, |name| name.chars().count());
+    if length > PEC_LABEL_LENGTH {
+        report.truncate_metadata("name", PEC_LABEL_LENGTH);
     }
-
-    Ok(())
 }
 
 /// Write PEC section (used by both standalone PEC and PES files)
-pub fn write_pec_section<W: Write + Seek>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+///
+/// The report lists the commands the section leaves out.
+pub fn write_pec_section<W: Write + Seek>(
+    writer: &mut W,
+    pattern: &EmbPattern,
+) -> Result<WriteReport> {
     let mut helper = WriteHelper::new(writer);
+    let mut report = WriteReport::new(Format::PEC);
 
     // Write header
-    write_pec_header(&mut helper, pattern)?;
+    let color_indices = write_pec_header(&mut helper, pattern)?;
 
     // Get bounds
     let bounds = pattern.bounds();
     let width = (bounds.2 - bounds.0).round() as i32;
     let height = (bounds.3 - bounds.1).round() as i32;
 
-    // Remember position for block length
-    let stitch_block_start = helper.bytes_written();
+    // Remember position for block length; the section may start mid-stream
+    let stitch_block_start = helper.seek(SeekFrom::Current(0))?;
 
     // Placeholder for block info
     helper.write_u16_le(0)?;
@@ -355,41 +441,48 @@ pub fn write_pec_section<W: Write + Seek>(writer: &mut W, pattern: &EmbPattern)
     helper.write_i16_le(0x1B0)?;
 
     // Encode stitches
-    pec_encode(&mut helper, pattern)?;
+    pec_encode(&mut helper, pattern, &mut report)?;
 
     // Calculate block length and write it back
-    let stitch_block_end = helper.bytes_written();
+    let stitch_block_end = helper.seek(SeekFrom::Current(0))?;
     let block_length = stitch_block_end - stitch_block_start;
 
     // Seek back and write block length
-    helper.seek(SeekFrom::Start((stitch_block_start + 2) as u64))?;
+    helper.seek(SeekFrom::Start(stitch_block_start + 2))?;
     helper.write_u8((block_length & 0xFF) as u8)?;
     helper.write_u8(((block_length >> 8) & 0xFF) as u8)?;
     helper.write_u8(((block_length >> 16) & 0xFF) as u8)?;
-    helper.seek(SeekFrom::Start(stitch_block_end as u64))?;
+    helper.seek(SeekFrom::Start(stitch_block_end))?;
 
     // Write graphics
-    write_pec_graphics(&mut helper, pattern, bounds)?;
+    write_pec_graphics(&mut helper, pattern, color_indices.len())?;
 
-    Ok(())
+    Ok(report)
 }
 
 /// Write standalone PEC file
-pub fn write<W: Write + Seek>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+pub fn write<W: Write + Seek>(writer: &mut W, pattern: &EmbPattern) -> Result<WriteReport> {
     writer.write_all(b"#PEC0001")?;
-    write_pec_section(writer, pattern)
+    let mut report = write_pec_section(writer, pattern)?;
+    report_label(pattern, &mut report);
+    report.drop_metadata(pattern, &["name"]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write PEC file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern) -> Result<()> {
     let file = std::fs::File::create(path)?;
     let mut writer = std::io::BufWriter::new(file);
-    write(&mut writer, pattern)
+    write(&mut writer, pattern)?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::pattern::Stitch;
     use std::io::Cursor;
 
     #[test]
@@ -407,4 +500,77 @@ mod tests {
         assert!(data.len() > 500); // PEC has header + graphics
         assert_eq!(&data[0..8], b"#PEC0001");
     }
+
+    #[test]
+    fn test_thumbnails() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 0.0);
+        pattern.add_stitch_absolute(COLOR_CHANGE, 100.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 0.0, 100.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 100.0);
+        pattern.end();
+
+        let icons = thumbnails(&pattern, 2);
+        assert_eq!(icons.len(), 3);
+        assert!(icons.iter().all(|icon| icon.len() == PEC_ICON_SIZE));
+
+        // Frame border on row 1, empty first row
+        assert_eq!(&icons[0][..PEC_ICON_STRIDE], &[0; PEC_ICON_STRIDE]);
+        assert_eq!(
+            &icons[0][PEC_ICON_STRIDE..2 * PEC_ICON_STRIDE],
+            &[0xF0, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F]
+        );
+
+        // Each color icon holds one of the two horizontal lines
+        let pixel = |icon: &[u8], x: usize, y: usize| {
+            icon[y * PEC_ICON_STRIDE + x / 8] & (1 << (x % 8)) != 0
+        };
+        // 100 units scale to 28 rows centered on row 19
+        let (top, bottom) = (5, 33);
+        assert!(pixel(&icons[0], 24, top) && pixel(&icons[0], 24, bottom));
+        assert!(pixel(&icons[1], 24, top) && !pixel(&icons[1], 24, bottom));
+        assert!(!pixel(&icons[2], 24, top) && pixel(&icons[2], 24, bottom));
+
+        // Extra color blocks are merged into the last icon
+        let icons = thumbnails(&pattern, 1);
+        assert_eq!(icons.len(), 2);
+        assert!(pixel(&icons[1], 24, top) && pixel(&icons[1], 24, bottom));
+
+        // A far jump doesn't change the scale
+        let mut jumped = pattern.clone();
+        jumped.splice_stitches(2, 0, vec![Stitch::new(1000.0, 1000.0, JUMP)]);
+        assert_eq!(thumbnails(&jumped, 2), thumbnails(&pattern, 2));
+    }
+
+    #[test]
+    fn test_color_table_per_block() {
+        // One thread, reused for three color blocks
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        for y in [0.0, 50.0, 100.0] {
+            if y > 0.0 {
+                pattern.color_change(0.0, 0.0);
+            }
+            pattern.stitch_abs(0.0, y);
+            pattern.stitch_abs(100.0, y);
+        }
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &pattern).unwrap();
+        let data = buffer.into_inner();
+
+        // Color count - 1 follows the label, icon header and padding
+        let table = 8 + 20 + 12 + 4 + 12;
+        assert_eq!(data[table], 2);
+        assert_eq!(data[table + 1], data[table + 2]);
+        assert_eq!(data[table + 2], data[table + 3]);
+        assert_eq!(data[table + 4], 0x20);
+
+        // Overall icon plus one per block
+        assert!(data.len() >= 4 * PEC_ICON_SIZE);
+        let read = crate::formats::io::readers::pec::read(&mut Cursor::new(data)).unwrap();
+        assert_eq!(read.threads().len(), 3);
+    }
 }
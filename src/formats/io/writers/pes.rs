@@ -1,14 +1,22 @@
 //! Brother PES format writer
 //!
-//! Writes PES format (versions 1 and 6) with embedded PEC section for machine
-//! compatibility. Includes design metadata and thread color information.
+//! Writes PES format (versions 1, 6 and 7) with embedded PEC section for machine
+//! compatibility. Versions 6 and 7 include design metadata, the thread table and
+//! PEC thumbnails for the machine's preview screen.
+//!
+//! Header and thread strings are written in a configurable [`TextEncoding`]
+//! (UTF-8 by default). Strings that still match the original bytes kept by the
+//! reader (`<key>_raw`) are written with those bytes instead.
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
 use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+use crate::formats::io::report::{WriteReport, LICENSE_KEYS};
 use crate::formats::io::utils::WriteHelper;
 use crate::formats::io::writers::pec;
 use crate::utils::error::Result;
+use crate::utils::string::{decode_text, encode_text, hex_to_bytes, TextEncoding};
 use std::io::{Seek, SeekFrom, Write};
 
 /// PES version 1 file signature
@@ -17,9 +25,18 @@ pub const PES_VERSION_1_SIGNATURE: &str = "#PES0001";
 /// PES version 6 file signature
 pub const PES_VERSION_6_SIGNATURE: &str = "#PES0060";
 
+/// PES version 7 file signature
+pub const PES_VERSION_7_SIGNATURE: &str = "#PES0070";
+
 const EMB_ONE: &str = "CEmbOne";
 const EMB_SEG: &str = "CSewSeg";
 
+/// Metadata keys of the version 6 header; the license goes into the comments
+const HEADER_METADATA: [&str; 5] = ["name", "category", "author", "keywords", "comments"];
+
+/// Longest header string in bytes
+const MAX_STRING_LENGTH: usize = 255;
+
 /// Version of PES format to write
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PesVersion {
@@ -27,6 +44,8 @@ pub enum PesVersion {
     V1,
     /// PES version 6 (includes metadata support)
     V6,
+    /// PES version 7 (same header layout as version 6)
+    V7,
 }
 
 impl PesVersion {
@@ -34,8 +53,14 @@ impl PesVersion {
         match self {
             PesVersion::V1 => PES_VERSION_1_SIGNATURE,
             PesVersion::V6 => PES_VERSION_6_SIGNATURE,
+            PesVersion::V7 => PES_VERSION_7_SIGNATURE,
         }
     }
+
+    /// Whether the version uses the version 6 header with metadata and threads
+    fn has_v6_header(&self) -> bool {
+        matches!(self, PesVersion::V6 | PesVersion::V7)
+    }
 }
 
 /// Write a PES embroidery file
@@ -44,19 +69,54 @@ pub fn write_pes<W: Write + Seek>(
     writer: &mut W,
     version: PesVersion,
     truncated: bool,
-) -> Result<()> {
-    if truncated {
-        write_truncated(pattern, writer, version)
+) -> Result<WriteReport> {
+    write_pes_with_encoding(pattern, writer, version, truncated, TextEncoding::Utf8)
+}
+
+/// Write a PES embroidery file with header and thread strings in `encoding`
+///
+/// [`TextEncoding::Auto`] writes UTF-8. Characters the encoding can't represent
+/// are written as `?`.
+pub fn write_pes_with_encoding<W: Write + Seek>(
+    pattern: &EmbPattern,
+    writer: &mut W,
+    version: PesVersion,
+    truncated: bool,
+    encoding: TextEncoding,
+) -> Result<WriteReport> {
+    let mut report = WriteReport::new(Format::PES);
+    let section = if truncated {
+        write_truncated(pattern, writer, version, encoding, &mut report)?
+    } else {
+        write_full(pattern, writer, version, encoding, &mut report)?
+    };
+    report.changes.extend(section.changes);
+
+    if version.has_v6_header() {
+        let stored: Vec<&str> = HEADER_METADATA
+            .iter()
+            .chain(&["notes"])
+            .chain(&LICENSE_KEYS)
+            .copied()
+            .collect();
+        report.drop_metadata(pattern, &stored);
     } else {
-        write_full(pattern, writer, version)
+        // Version 1 only has the PEC label
+        pec::report_label(pattern, &mut report);
+        report.drop_metadata(pattern, &["name"]);
     }
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
+/// Write the header and PEC section only, returning the PEC section's report
 fn write_truncated<W: Write + Seek>(
     pattern: &EmbPattern,
     writer: &mut W,
     version: PesVersion,
-) -> Result<()> {
+    encoding: TextEncoding,
+    report: &mut WriteReport,
+) -> Result<WriteReport> {
     let mut w = WriteHelper::new(writer);
 
     match version {
@@ -65,13 +125,13 @@ fn write_truncated<W: Write + Seek>(
             w.write_bytes(&[
                 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
             ])?;
-            pec::write_pec_section(w.inner_mut(), pattern)?;
+            pec::write_pec_section(w.inner_mut(), pattern)
         }
-        PesVersion::V6 => {
-            w.write_string_utf8(PES_VERSION_6_SIGNATURE)?;
+        PesVersion::V6 | PesVersion::V7 => {
+            w.write_string_utf8(version.signature())?;
             let placeholder_pec_block = w.bytes_written();
             w.write_i32_le(0)?; // Placeholder for PEC BLOCK
-            write_pes_header_v6(pattern, &mut w, 0)?;
+            write_pes_header_v6(pattern, &mut w, 0, encoding, report)?;
             w.write_bytes(&[0x00, 0x00, 0x00, 0x00, 0x00])?;
             w.write_i16_le(0x0000)?;
             w.write_i16_le(0x0000)?;
@@ -83,19 +143,21 @@ fn write_truncated<W: Write + Seek>(
             w.inner_mut()
                 .seek(SeekFrom::Start(current_position as u64))?;
 
-            pec::write_pec_section(w.inner_mut(), pattern)?;
+            let section = pec::write_pec_section(w.inner_mut(), pattern)?;
             w.write_i16_le(0x0000)?;
+            Ok(section)
         }
     }
-
-    Ok(())
 }
 
+/// Write the full file, returning the PEC section's report
 fn write_full<W: Write + Seek>(
     pattern: &EmbPattern,
     writer: &mut W,
     version: PesVersion,
-) -> Result<()> {
+    encoding: TextEncoding,
+    report: &mut WriteReport,
+) -> Result<WriteReport> {
     let mut w = WriteHelper::new(writer);
 
     w.write_string_utf8(version.signature())?;
@@ -117,8 +179,8 @@ fn write_full<W: Write + Seek>(
         PesVersion::V1 => {
             write_pes_header_v1(&mut w, distinct_blocks)?;
         }
-        PesVersion::V6 => {
-            write_pes_header_v6(pattern, &mut w, distinct_blocks)?;
+        PesVersion::V6 | PesVersion::V7 => {
+            write_pes_header_v6(pattern, &mut w, distinct_blocks, encoding, report)?;
         }
     }
 
@@ -130,8 +192,8 @@ fn write_full<W: Write + Seek>(
         w.write_i16_le(0x0000)?;
         let colorlog = write_pes_blocks(&mut w, pattern, left, top, right, bottom, cx, cy)?;
 
-        if version == PesVersion::V6 {
-            // Version 6 has node/tree/order data
+        if version.has_v6_header() {
+            // Version 6 and later have node/tree/order data
             w.write_i32_le(0)?;
             w.write_i32_le(0)?;
             for i in 0..colorlog.len() {
@@ -148,13 +210,13 @@ fn write_full<W: Write + Seek>(
     w.inner_mut()
         .seek(SeekFrom::Start(current_position as u64))?;
 
-    pec::write_pec_section(w.inner_mut(), pattern)?;
+    let section = pec::write_pec_section(w.inner_mut(), pattern)?;
 
-    if version == PesVersion::V6 {
+    if version.has_v6_header() {
         w.write_i16_le(0x0000)?;
     }
 
-    Ok(())
+    Ok(section)
 }
 
 fn write_pes_header_v1<W: Write>(
@@ -171,15 +233,15 @@ fn write_pes_header_v6<W: Write>(
     pattern: &EmbPattern,
     w: &mut WriteHelper<W>,
     distinct_block_objects: i16,
+    encoding: TextEncoding,
+    report: &mut WriteReport,
 ) -> Result<()> {
     w.write_i16_le(0x01)?; // 0 = 100x100, 130x180 hoop
     w.write_bytes(b"02")?; // 2-digit ascii number
 
-    write_pes_string_8(w, pattern.extras().get("name"))?;
-    write_pes_string_8(w, pattern.extras().get("category"))?;
-    write_pes_string_8(w, pattern.extras().get("author"))?;
-    write_pes_string_8(w, pattern.extras().get("keywords"))?;
-    write_pes_string_8(w, pattern.extras().get("comments"))?;
+    for key in HEADER_METADATA {
+        write_pes_metadata_string(w, pattern, key, encoding, report)?;
+    }
 
     w.write_i16_le(0)?; // OptimizeHoopChange = False
     w.write_i16_le(0)?; // Design Page Is Custom = False
@@ -215,23 +277,79 @@ fn write_pes_header_v6<W: Write>(
     let count_thread = pattern.threads().len();
     w.write_i16_le(count_thread as i16)?;
     for thread in pattern.threads() {
-        write_pes_thread(w, thread)?;
+        write_pes_thread(w, thread, encoding)?;
     }
 
     w.write_i16_le(distinct_block_objects)?;
     Ok(())
 }
 
-fn write_pes_string_8<W: Write>(w: &mut WriteHelper<W>, s: Option<&String>) -> Result<()> {
-    match s {
-        None => {
-            w.write_i8(0)?;
-        }
-        Some(string) => {
-            let len = string.len().min(255);
-            w.write_i8(len as i8)?;
-            w.write_string_utf8(&string[..len])?;
+/// Write a header string, reusing the original bytes (`raw`, as hex) captured
+/// by the reader when they still decode to `value`
+///
+/// Returns the number of bytes written when `value` had to be cut to fit.
+fn write_pes_string_8<W: Write>(
+    w: &mut WriteHelper<W>,
+    value: Option<&String>,
+    raw: Option<&String>,
+    encoding: TextEncoding,
+) -> Result<Option<usize>> {
+    let Some(value) = value else {
+        w.write_u8(0)?;
+        return Ok(None);
+    };
+
+    let raw = raw
+        .and_then(|hex| hex_to_bytes(hex))
+        .filter(|bytes| bytes.len() <= MAX_STRING_LENGTH)
+        .filter(|bytes| {
+            decode_text(bytes, encoding).text == *value
+                || decode_text(bytes, TextEncoding::Auto).text == *value
+        });
+    if let Some(bytes) = raw {
+        w.write_u8(bytes.len() as u8)?;
+        w.write_bytes(&bytes)?;
+        return Ok(None);
+    }
+
+    // Cut between characters, so a multi-byte character is never split
+    let mut bytes = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 4];
+    for c in value.chars() {
+        let (encoded, _) = encode_text(c.encode_utf8(&mut buf), encoding);
+        if bytes.len() + encoded.len() > MAX_STRING_LENGTH {
+            truncated = true;
+            break;
         }
+        bytes.extend(encoded);
+    }
+    w.write_u8(bytes.len() as u8)?;
+    w.write_bytes(&bytes)?;
+    Ok(truncated.then_some(bytes.len()))
+}
+
+/// Write a metadata field, reusing the original bytes (`<key>_raw`) captured by
+/// the reader when they still decode to the current value
+fn write_pes_metadata_string<W: Write>(
+    w: &mut WriteHelper<W>,
+    pattern: &EmbPattern,
+    key: &str,
+    encoding: TextEncoding,
+    report: &mut WriteReport,
+) -> Result<()> {
+    // Stamp usage rights into the free-text comments field
+    let stamped;
+    let value = if key == "comments" {
+        stamped = pattern.comments_with_license();
+        stamped.as_ref()
+    } else {
+        pattern.extras().get(key)
+    };
+    let raw = pattern.extras().get(&format!("{}_raw", key));
+
+    if let Some(length) = write_pes_string_8(w, value, raw, encoding)? {
+        report.truncate_metadata(key, length);
     }
     Ok(())
 }
@@ -243,8 +361,16 @@ fn write_pes_string_16<W: Write>(w: &mut WriteHelper<W>, s: &str) -> Result<()>
     Ok(())
 }
 
-fn write_pes_thread<W: Write>(w: &mut WriteHelper<W>, thread: &EmbThread) -> Result<()> {
-    write_pes_string_8(w, thread.catalog_number.as_ref())?;
+fn write_pes_thread<W: Write>(
+    w: &mut WriteHelper<W>,
+    thread: &EmbThread,
+    encoding: TextEncoding,
+) -> Result<()> {
+    let string = |w: &mut WriteHelper<W>, value: Option<&String>, field: &str| {
+        let raw = thread.attributes.get(&format!("{}_raw", field));
+        write_pes_string_8(w, value, raw, encoding)
+    };
+    string(w, thread.catalog_number.as_ref(), "catalog_number")?;
 
     // Extract RGB components from u32 color
     let r = ((thread.color >> 16) & 0xFF) as i8;
@@ -256,9 +382,9 @@ fn write_pes_thread<W: Write>(w: &mut WriteHelper<W>, thread: &EmbThread) -> Res
     w.write_i8(b)?;
     w.write_i8(0)?; // unknown
     w.write_i32_le(0xA)?; // A is custom color
-    write_pes_string_8(w, thread.description.as_ref())?;
-    write_pes_string_8(w, thread.brand.as_ref())?;
-    write_pes_string_8(w, thread.chart.as_ref())?;
+    string(w, thread.description.as_ref(), "description")?;
+    string(w, thread.brand.as_ref(), "brand")?;
+    string(w, thread.chart.as_ref(), "chart")?;
     Ok(())
 }
 
@@ -513,6 +639,7 @@ fn find_nearest_pec_color(thread: &EmbThread) -> u8 {
 mod tests {
     use super::*;
     use crate::formats::io::readers::pes;
+    use crate::formats::io::report::WriteChange;
     use std::io::Cursor;
 
     #[test]
@@ -582,6 +709,112 @@ mod tests {
         assert_eq!(&buffer.get_ref()[0..8], b"#PES0060");
     }
 
+    #[test]
+    fn test_write_pes_preserves_raw_metadata() {
+        use crate::utils::string::{bytes_to_hex, encode_text};
+
+        let (sjis, _) = encode_text("花の刺繍", TextEncoding::ShiftJis);
+        let mut pattern = EmbPattern::new();
+        pattern.set_metadata("name", "花の刺繍");
+        pattern.set_metadata("name_raw", bytes_to_hex(&sjis));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 50.0, 50.0);
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write_pes(&pattern, &mut buffer, PesVersion::V6, false).unwrap();
+        assert!(buffer
+            .get_ref()
+            .windows(sjis.len())
+            .any(|window| window == sjis.as_slice()));
+    }
+
+    #[test]
+    fn test_write_pes_preserves_raw_thread_strings() {
+        use crate::utils::string::{bytes_to_hex, encode_text};
+
+        let (sjis, _) = encode_text("赤い糸", TextEncoding::ShiftJis);
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(
+            EmbThread::new(0xFF0000)
+                .with_description("赤い糸")
+                .with_attribute("description_raw", bytes_to_hex(&sjis)),
+        );
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write_pes(&pattern, &mut buffer, PesVersion::V6, false).unwrap();
+        assert!(buffer
+            .get_ref()
+            .windows(sjis.len())
+            .any(|window| window == sjis.as_slice()));
+    }
+
+    #[test]
+    fn test_write_pes_text_encoding() {
+        use crate::formats::io::options::{ReadOptions, WriteOptions};
+        use crate::formats::io::readers;
+        use crate::formats::io::traits::PatternWriter;
+
+        let mut pattern = EmbPattern::new();
+        pattern.set_metadata("name", "Café");
+        pattern.add_thread(EmbThread::new(0x00FF00).with_description("Vert forêt"));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 50.0, 50.0);
+        pattern.end();
+
+        let options = WriteOptions::new()
+            .pes_version(PesVersion::V6)
+            .text_encoding(TextEncoding::Latin1);
+        let mut buffer = Cursor::new(Vec::new());
+        Format::PES
+            .write_with_report(&pattern, &mut buffer, &options)
+            .unwrap();
+        let data = buffer.into_inner();
+        assert!(data.windows(5).any(|window| window == b"\x04Caf\xe9"));
+        assert!(data
+            .windows(11)
+            .any(|window| window == b"\x0aVert for\xeat"));
+
+        let mut read = EmbPattern::new();
+        let read_options = ReadOptions::new().text_encoding(TextEncoding::Latin1);
+        readers::pes::read_with_options(&mut Cursor::new(data), &mut read, &read_options).unwrap();
+        assert_eq!(read.get_metadata("name"), Some(&"Café".to_string()));
+        assert_eq!(read.threads()[0].description.as_deref(), Some("Vert forêt"));
+    }
+
+    #[test]
+    fn test_write_pes_string_truncated_between_characters() {
+        let mut pattern = EmbPattern::new();
+        // 86 three-byte characters: 258 bytes in UTF-8, 172 in Shift-JIS
+        pattern.set_metadata("name", "刺".repeat(86));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        let report = write_pes(&pattern, &mut buffer, PesVersion::V6, false).unwrap();
+        assert!(report.changes.contains(&WriteChange::MetadataTruncated {
+            key: "name".to_string(),
+            length: 255,
+        }));
+
+        let mut buffer = Cursor::new(Vec::new());
+        let report = write_pes_with_encoding(
+            &pattern,
+            &mut buffer,
+            PesVersion::V6,
+            false,
+            TextEncoding::ShiftJis,
+        )
+        .unwrap();
+        assert!(!report
+            .changes
+            .iter()
+            .any(|change| matches!(change, WriteChange::MetadataTruncated { .. })));
+    }
+
     #[test]
     fn test_write_pes_v1_structure() {
         let mut pattern = EmbPattern::new();
@@ -606,4 +839,33 @@ mod tests {
             pec_pos
         );
     }
+
+    #[test]
+    fn test_write_pes_v7_roundtrip() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 0.0);
+        pattern.add_stitch_absolute(COLOR_CHANGE, 100.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 20.0, 50.0);
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write_pes(&pattern, &mut buffer, PesVersion::V7, false).unwrap();
+        assert_eq!(&buffer.get_ref()[0..8], b"#PES0070");
+
+        buffer.set_position(0);
+        let mut read_pattern = EmbPattern::new();
+        pes::read(&mut buffer, &mut read_pattern).unwrap();
+        assert_eq!(read_pattern.threads().len(), 2);
+        let stitches: Vec<_> = read_pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| (s.x, s.y))
+            .collect();
+        assert_eq!(stitches, vec![(0.0, 0.0), (100.0, 0.0), (20.0, 50.0)]);
+        assert!(read_pattern.get_metadata("pec_graphic_2").is_some());
+    }
 }
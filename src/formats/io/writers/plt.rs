@@ -0,0 +1,134 @@
+//! PLT (HPGL) format writer
+//!
+//! Writes stitches as HPGL pen-down moves and jumps as pen-up moves, one
+//! instruction per line. Each color block is drawn with its own pen (`SP1`, `SP2`,
+//! ...), which sequin devices treat as a color or head change.
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::readers::plt::PLT_UNITS_PER_MM;
+use crate::formats::io::report::WriteReport;
+use crate::utils::error::{Error, Result};
+use crate::utils::functions::decode_embroidery_command;
+use std::io::{BufWriter, Write};
+
+/// Write a PLT file in standard plotter units (0.025mm)
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::prelude::*;
+/// use std::fs::File;
+///
+/// let mut pattern = EmbPattern::new();
+/// pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+/// pattern.add_stitch_absolute(STITCH, 10.0, 10.0);
+/// pattern.end();
+///
+/// let mut file = File::create("output.plt").unwrap();
+/// butabuti::formats::io::writers::plt::write(&pattern, &mut file).unwrap();
+/// ```
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
+    write_with_scale(pattern, file, PLT_UNITS_PER_MM)
+}
+
+/// Write a PLT file using `units_per_mm` file units per millimeter
+///
+/// Coordinates are rounded to whole file units.
+pub fn write_with_scale(
+    pattern: &EmbPattern,
+    file: &mut impl Write,
+    units_per_mm: f64,
+) -> Result<WriteReport> {
+    if !(units_per_mm > 0.0 && units_per_mm.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "PLT: Units per mm must be positive, got {}",
+            units_per_mm
+        )));
+    }
+
+    // One short line per stitch; batch them for unbuffered writers
+    let mut out = BufWriter::with_capacity(64 * 1024, file);
+
+    // 0.1mm to file units; HPGL's Y axis points up
+    let scale = units_per_mm / 10.0;
+    let mut pen = 1;
+    let mut report = WriteReport::new(Format::PLT);
+    writeln!(out, "IN;")?;
+    writeln!(out, "SP{};", pen)?;
+
+    for stitch in pattern.stitches() {
+        let x = (stitch.x * scale).round() as i64;
+        let y = (-stitch.y * scale).round() as i64;
+
+        let (command, _, _, _) = decode_embroidery_command(stitch.command);
+        match command {
+            STITCH => writeln!(out, "PD{},{};", x, y)?,
+            JUMP => writeln!(out, "PU{},{};", x, y)?,
+            TRIM => writeln!(out, "PU;")?,
+            COLOR_CHANGE | NEEDLE_SET => {
+                pen += 1;
+                writeln!(out, "PU;")?;
+                writeln!(out, "SP{};", pen)?;
+            }
+            END => break,
+            _ => report.drop_command(command),
+        }
+    }
+
+    writeln!(out, "PU;")?;
+    writeln!(out, "SP0;")?;
+    out.flush()?;
+
+    // Pens are numbered, without colors
+    report.drop_metadata(pattern, &[]);
+    report.drop_threads(pattern.threads().len());
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::io::readers::plt;
+    use std::io::Cursor;
+
+    fn sample() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(JUMP, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 50.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 0.0, 50.0);
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_write_plt() {
+        let mut output = Vec::new();
+        write(&sample(), &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert_eq!(
+            text,
+            "IN;\nSP1;\nPU0,0;\nPD400,0;\nPD400,-200;\nPU;\nSP2;\nPU0,-200;\nPD0,0;\nPU;\nSP0;\n"
+        );
+    }
+
+    #[test]
+    fn test_plt_round_trip_with_scale() {
+        let original = sample();
+        for units_per_mm in [PLT_UNITS_PER_MM, 10.0] {
+            let mut output = Vec::new();
+            write_with_scale(&original, &mut output, units_per_mm).unwrap();
+
+            let mut read_back = EmbPattern::new();
+            plt::read_with_scale(&mut Cursor::new(output), &mut read_back, units_per_mm).unwrap();
+            assert_eq!(read_back.stitches(), original.stitches());
+        }
+
+        assert!(write_with_scale(&original, &mut Vec::new(), -1.0).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Husqvarna Viking SHV format writer
+//!
+//! Writes the 0x56-byte header text, the design name, an empty preview
+//! bitmap and one record per color block giving its stitch count and an index
+//! into the 43-color SHV palette (nearest match). Stitches are signed byte
+//! pairs; jumps start with a `0x80 0x01` long move and end with `0x80 0x02`.
+//! Color changes are implied by the per-color stitch counts, so the format
+//! keeps no trims or stops.
+
+use crate::core::constants::*;
+use crate::core::encoder::{EncoderSettings, Transcoder};
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::WriteHelper;
+use crate::palettes::thread_shv;
+use crate::utils::error::Result;
+use std::io::Write;
+
+/// Header text at the start of every SHV file (0x56 bytes)
+pub const SHV_HEADER: &[u8; 0x56] =
+    b"Embroidery disk created using software licensed from Viking Sewing Machines AB, Sweden";
+
+/// Colors the single-byte color count can hold
+pub const SHV_MAX_COLORS: usize = 255;
+
+/// Default encoder settings for SHV format
+pub fn default_settings() -> EncoderSettings {
+    EncoderSettings {
+        max_stitch: 127.0,
+        max_jump: 127.0,
+        full_jump: false,
+        sequin_contingency: CONTINGENCY_SEQUIN_JUMP,
+        ..Default::default()
+    }
+}
+
+/// Stitch records of one color block and the units they count for
+#[derive(Default)]
+struct ColorBlock {
+    data: Vec<u8>,
+    units: u32,
+    in_jump: bool,
+}
+
+/// Write SHV format embroidery file
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
+    let mut encoded = EmbPattern::new();
+    let mut transcoder = Transcoder::with_settings(default_settings());
+    transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::SHV,
+        changes: transcoder.changes(),
+    };
+
+    let mut blocks = vec![ColorBlock::default()];
+    let (mut xx, mut yy) = (0.0, 0.0);
+    for stitch in encoded.stitches() {
+        let data = stitch.command & COMMAND_MASK;
+        match data {
+            STITCH | JUMP => {
+                let dx = (stitch.x - xx).round() as i32;
+                let dy = (stitch.y - yy).round() as i32;
+                xx += dx as f64;
+                yy += dy as f64;
+
+                let Some(block) = blocks.last_mut() else {
+                    continue;
+                };
+                if data == JUMP {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+                    block.data.extend_from_slice(&[0x80, 0x01]);
+                    block.data.extend_from_slice(&(dx as i16).to_be_bytes());
+                    block.data.extend_from_slice(&(dy as i16).to_be_bytes());
+                    block.units += 3;
+                    block.in_jump = true;
+                } else {
+                    if block.in_jump {
+                        block.data.extend_from_slice(&[0x80, 0x02]);
+                        block.units += 1;
+                        block.in_jump = false;
+                    }
+                    block
+                        .data
+                        .extend_from_slice(&[dx as i8 as u8, dy as i8 as u8]);
+                    block.units += 1;
+                }
+            }
+            COLOR_CHANGE | NEEDLE_SET => blocks.push(ColorBlock::default()),
+            END => break,
+            _ => report.drop_command(data),
+        }
+    }
+
+    // Threads follow the blocks; empty blocks would desynchronise the counts
+    let used = blocks.iter().filter(|block| block.units > 0).count();
+    report.drop_threads(used.saturating_sub(SHV_MAX_COLORS));
+    let palette = thread_shv::get_thread_set();
+    let blocks: Vec<(ColorBlock, u8)> = blocks
+        .into_iter()
+        .enumerate()
+        .filter(|(_, block)| block.units > 0)
+        .map(|(index, block)| {
+            let thread = encoded
+                .threads()
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| EmbThread::new(0x000000));
+            let code = thread.find_nearest_color_index(&palette).unwrap_or(0);
+            (block, code as u8)
+        })
+        .take(SHV_MAX_COLORS)
+        .collect();
+
+    let mut helper = WriteHelper::new(file);
+    helper.write_bytes(SHV_HEADER)?;
+    let name = encoded.get_metadata("name").map_or("", |s| s.as_str());
+    if name.len() > 255 {
+        report.truncate_metadata("name", 255);
+    }
+    let name = &name.as_bytes()[..name.len().min(255)];
+    helper.write_u8(name.len() as u8)?;
+    helper.write_bytes(name)?;
+
+    // Empty preview bitmap: width, height, then 4 bytes before the colors
+    helper.write_bytes(&[0, 0, 0, 0, 0, 0])?;
+    helper.write_u8(blocks.len() as u8)?;
+    helper.write_bytes(&[0; 18])?;
+
+    for (index, (block, code)) in blocks.iter().enumerate() {
+        helper.write_bytes(&block.units.to_be_bytes())?;
+        helper.write_u8(*code)?;
+        // The stitch data overlaps the last two bytes of the final record
+        let padding = if index + 1 == blocks.len() { 7 } else { 9 };
+        helper.write_bytes(&[0; 9][..padding])?;
+    }
+    for (block, _) in &blocks {
+        helper.write_bytes(&block.data)?;
+    }
+
+    report.drop_metadata(pattern, &["name"]);
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::io::readers;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_shv_round_trip() {
+        let palette = thread_shv::get_thread_set();
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Rose");
+        pattern.add_thread(palette[5].clone());
+        pattern.add_thread(palette[12].clone());
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, -50.0);
+        pattern.stitch_abs(-20.0, 60.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 80.0, 60.0);
+        pattern.stitch_abs(90.0, 70.0);
+        pattern.stitch_abs(95.0, 75.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).unwrap();
+        assert_eq!(&output[..0x56], SHV_HEADER);
+
+        let mut read = EmbPattern::new();
+        readers::shv::read(&mut Cursor::new(output), &mut read).unwrap();
+        assert_eq!(read.get_metadata("name").map(|s| s.as_str()), Some("Rose"));
+        assert_eq!(read.count_stitches(), pattern.count_stitches());
+        assert_eq!(read.bounds(), pattern.bounds());
+        assert_eq!(read.count_color_changes(), 1);
+        let colors: Vec<u32> = read.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![palette[5].color, palette[12].color]);
+    }
+}
@@ -8,10 +8,12 @@
 //! - **Realistic stitches**: Uses stitch icons with gradients and rotation (opt-in)
 
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::TextBuffer;
 use crate::utils::error::Result;
 use crate::utils::stitch_renderer::{
-    calculate_stitch_angle, create_colored_stitch_symbol, create_stitch_use_element,
-    StitchRenderQuality,
+    calculate_stitch_angle, create_colored_stitch_symbol, StitchRenderQuality,
 };
 use std::io::Write;
 
@@ -19,7 +21,7 @@ use std::io::Write;
 ///
 /// This is the default export method for backward compatibility.
 /// Uses simple stroke paths for fast rendering.
-pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
     write_with_quality(pattern, file, StitchRenderQuality::Low)
 }
 
@@ -56,7 +58,7 @@ pub fn write_with_quality(
     pattern: &EmbPattern,
     file: &mut impl Write,
     quality: StitchRenderQuality,
-) -> Result<()> {
+) -> Result<WriteReport> {
     // Get pattern bounds
     let bounds = pattern.bounds();
     let min_x = bounds.0;
@@ -67,26 +69,28 @@ pub fn write_with_quality(
     let width = max_x - min_x;
     let height = max_y - min_y;
 
+    let mut out = TextBuffer::new();
+
     // Write SVG header
-    writeln!(file, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
     writeln!(
-        file,
+        out,
         "<svg version=\"1.1\" xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" xmlns:ev=\"http://www.w3.org/2001/xml-events\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
         width, height, min_x, min_y, width, height
     )?;
 
     // If using realistic stitch icons, define symbols in <defs>
     if quality.use_stitch_icons() {
-        writeln!(file, "  <defs>")?;
+        writeln!(out, "  <defs>")?;
 
         // Create a colored stitch symbol for each thread
         for (i, thread) in pattern.threads().iter().enumerate() {
             let symbol_id = format!("stitch_{}", i);
             let symbol = create_colored_stitch_symbol(thread, &symbol_id);
-            writeln!(file, "    {}", symbol)?;
+            writeln!(out, "    {}", symbol)?;
         }
 
-        writeln!(file, "  </defs>")?;
+        writeln!(out, "  </defs>")?;
     }
 
     // Get stitch blocks
@@ -99,32 +103,48 @@ pub fn write_with_quality(
         }
 
         if quality.use_stitch_icons() {
-            // Realistic rendering: use stitch icons
-            render_block_with_icons(file, block, block_idx)?;
+            // Realistic rendering: use stitch icons, grouped so the block carries a name
+            writeln!(out, "  <g>")?;
+            writeln!(
+                out,
+                "  <title>{}</title>",
+                escape_xml(&thread.display_name())
+            )?;
+            render_block_with_icons(file, &mut out, block, block_idx)?;
+            writeln!(out, "  </g>")?;
         } else {
             // Simple rendering: use paths
-            render_block_with_paths(file, block, thread, &quality)?;
+            render_block_with_paths(file, &mut out, block, thread, &quality)?;
         }
     }
 
     // Close SVG
-    writeln!(file, "</svg>")?;
+    writeln!(out, "</svg>")?;
+    out.flush_to(file)?;
 
-    Ok(())
+    let mut report = WriteReport::new(Format::SVG);
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Render a stitch block as a simple path
 fn render_block_with_paths(
     file: &mut impl Write,
+    out: &mut TextBuffer,
     block: &[(f64, f64)],
     thread: &crate::core::thread::EmbThread,
     quality: &StitchRenderQuality,
 ) -> Result<()> {
     // Start path with M (move to)
-    let mut path_data = String::from("M");
+    out.push_str("  <path d=\"M");
 
     for stitch in block {
-        path_data.push_str(&format!(" {},{}", stitch.0, stitch.1));
+        out.push_str(" ");
+        out.push_f64(stitch.0);
+        out.push_str(",");
+        out.push_f64(stitch.1);
+        out.flush_if_full(file)?;
     }
 
     // Get thread color
@@ -137,12 +157,16 @@ fn render_block_with_paths(
         _ => "round",
     };
 
-    // Write path element
+    // Finish path element, titled with the thread name for hover tooltips
     writeln!(
-        file,
-        "  <path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"{}\"/>",
-        path_data, color, stroke_width, stroke_cap
+        out,
+        "\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"{}\"><title>{}</title></path>",
+        color,
+        stroke_width,
+        stroke_cap,
+        escape_xml(&thread.display_name())
     )?;
+    out.flush_if_full(file)?;
 
     Ok(())
 }
@@ -150,6 +174,7 @@ fn render_block_with_paths(
 /// Render a stitch block with realistic stitch icons
 fn render_block_with_icons(
     file: &mut impl Write,
+    out: &mut TextBuffer,
     block: &[(f64, f64)],
     thread_idx: usize,
 ) -> Result<()> {
@@ -170,14 +195,34 @@ fn render_block_with_icons(
             0.0 // Single stitch, no rotation
         };
 
-        // Create <use> element for this stitch
-        let use_elem = create_stitch_use_element(&symbol_id, x as f32, y as f32, angle);
-        writeln!(file, "  {}", use_elem)?;
+        // <use> element for this stitch, as built by `create_stitch_use_element`
+        let (x, y) = (x as f32, y as f32);
+        out.push_str("  <use xlink:href=\"#");
+        out.push_str(&symbol_id);
+        out.push_str("\" x=\"");
+        out.push_f32(x);
+        out.push_str("\" y=\"");
+        out.push_f32(y);
+        out.push_str("\" transform=\"rotate(");
+        out.push_f32(angle);
+        out.push_str(" ");
+        out.push_f32(x);
+        out.push_str(" ");
+        out.push_f32(y);
+        out.push_str(")\" />\n");
+        out.flush_if_full(file)?;
     }
 
     Ok(())
 }
 
+/// Escape text for use in XML content
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,6 +281,44 @@ mod tests {
         assert!(svg_content.matches("<path").count() >= 2);
     }
 
+    #[test]
+    fn test_svg_titles_use_thread_names() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0x000080));
+        pattern.add_thread(EmbThread::new(0xFF0000).with_description("Poppy & Co"));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 10.0, 10.0);
+        pattern.add_command(COLOR_CHANGE, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 20.0, 20.0);
+        pattern.add_stitch_absolute(STITCH, 30.0, 30.0);
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).unwrap();
+        let svg_content = String::from_utf8(output).unwrap();
+
+        assert!(svg_content.contains("<title>Navy Blue</title>"));
+        assert!(svg_content.contains("<title>Poppy &amp; Co</title>"));
+    }
+
+    #[test]
+    fn test_icon_elements_match_stitch_renderer() {
+        use crate::utils::stitch_renderer::create_stitch_use_element;
+
+        let block = [(0.5, -1.25), (10.0, 3.0)];
+        let mut out = TextBuffer::new();
+        render_block_with_icons(&mut std::io::sink(), &mut out, &block, 2).unwrap();
+        let mut output = Vec::new();
+        out.flush_to(&mut output).unwrap();
+
+        let angle = calculate_stitch_angle(0.5, -1.25, 10.0, 3.0);
+        let expected = format!(
+            "  {}\n  {}\n",
+            create_stitch_use_element("stitch_2", 0.5, -1.25, angle),
+            create_stitch_use_element("stitch_2", 10.0, 3.0, angle)
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
     #[test]
     fn test_svg_viewbox() {
         let mut pattern = EmbPattern::new();
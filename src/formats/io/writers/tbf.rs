@@ -6,6 +6,8 @@
 use crate::core::constants::*;
 use crate::core::encoder::{EncoderSettings, Transcoder};
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use crate::utils::functions::decode_embroidery_command;
 use std::io::{Seek, Write};
@@ -48,24 +50,30 @@ pub fn default_settings() -> EncoderSettings {
 /// let mut file = File::create("output.tbf").unwrap();
 /// butabuti::formats::io::writers::tbf::write(&pattern, &mut file).unwrap();
 /// ```
-pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<WriteReport> {
     // Encode pattern with TBF settings
     let mut transcoder = Transcoder::new();
     *transcoder.settings_mut() = default_settings();
 
     let mut encoded = EmbPattern::new();
     transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::TBF,
+        changes: transcoder.changes(),
+    };
 
     // Write header
     write_header(&encoded, file)?;
 
     // Write stitch data
-    write_stitches(&encoded, file)?;
+    write_stitches(&encoded, file, &mut report)?;
 
     // Terminal character
     file.write_all(b"\x1a")?;
 
-    Ok(())
+    report.drop_metadata(pattern, &["name", "tp"]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write the TBF file header (0x600 bytes)
@@ -158,7 +166,11 @@ fn write_header(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<
 }
 
 /// Write stitch data
-fn write_stitches(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+fn write_stitches(
+    pattern: &EmbPattern,
+    file: &mut impl Write,
+    report: &mut WriteReport,
+) -> Result<()> {
     let mut xx = 0.0;
     let mut yy = 0.0;
 
@@ -183,7 +195,10 @@ fn write_stitches(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
                 file.write_all(&[dx as i8 as u8, (-dy) as i8 as u8, 0x8F])?;
                 break;
             }
-            _ => continue, // Skip unknown commands
+            _ => {
+                report.drop_command(command);
+                continue;
+            }
         };
 
         file.write_all(&[dx as i8 as u8, (-dy) as i8 as u8, cmd])?;
@@ -7,6 +7,9 @@
 
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::TextBuffer;
 use crate::utils::error::Result;
 use std::io::Write;
 
@@ -23,7 +26,7 @@ pub struct TxtSettings {
 /// Two variants:
 /// - Default: Shows all stitch data with color and command names
 /// - Mimic (embroidermodder): Simplified numeric format
-pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
     write_with_settings(pattern, file, TxtSettings::default())
 }
 
@@ -32,16 +35,21 @@ pub fn write_with_settings(
     pattern: &EmbPattern,
     file: &mut impl Write,
     settings: TxtSettings,
-) -> Result<()> {
+) -> Result<WriteReport> {
     if settings.mimic {
-        write_mimic(pattern, file)
+        write_mimic(pattern, file)?;
     } else {
-        write_normal(pattern, file)
+        write_normal(pattern, file)?;
     }
+    let mut report = WriteReport::new(Format::TXT);
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write in embroidermodder-compatible format
 fn write_mimic(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+    let mut out = TextBuffer::new();
     let mut color = 0;
 
     for stitch in pattern.stitches() {
@@ -64,14 +72,17 @@ fn write_mimic(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
             _ => 0,
         };
 
-        writeln!(file, "{:.1},{:.1} color:{} flags:{}", x, y, color, flags)?;
+        writeln!(out, "{:.1},{:.1} color:{} flags:{}", x, y, color, flags)?;
+        out.flush_if_full(file)?;
     }
 
+    out.flush_to(file)?;
     Ok(())
 }
 
 /// Write in normal detailed format
 fn write_normal(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+    let mut out = TextBuffer::new();
     let mut color_index = 0;
     let mut color = if pattern.threads().is_empty() {
         0
@@ -79,6 +90,8 @@ fn write_normal(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
         pattern.threads()[0].color
     };
 
+    let mut stop_index = 0;
+
     for stitch in pattern.stitches() {
         let x = stitch.x;
         let y = stitch.y;
@@ -93,19 +106,29 @@ fn write_normal(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
 
         let command_name = command_name(command);
 
-        writeln!(
-            file,
+        write!(
+            out,
             "{:.1},{:.1} color:{} command:{} flags:{}",
             x, y, color, command_name, command
         )?;
+        if command == STOP {
+            if let Some(note) = pattern.stop_note(stop_index) {
+                write!(out, " note:{}", note)?;
+            }
+            stop_index += 1;
+        }
+        writeln!(out)?;
+        out.flush_if_full(file)?;
     }
 
+    out.flush_to(file)?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::stop_note::StopNote;
     use crate::core::thread::EmbThread;
 
     #[test]
@@ -128,6 +151,23 @@ mod tests {
         assert!(text.contains("END"));
     }
 
+    #[test]
+    fn test_write_stop_notes_txt() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.stop();
+        pattern.stop_with_note(StopNote::trim_applique("Leave 1 mm"));
+        pattern.end();
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).expect("Failed to write TXT");
+
+        let text = String::from_utf8(output).expect("Invalid UTF-8");
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[1].ends_with("command:STOP flags:3"));
+        assert!(lines[2].ends_with("command:STOP flags:3 note:Trim applique: Leave 1 mm"));
+    }
+
     #[test]
     fn test_write_mimic_txt() {
         let mut pattern = EmbPattern::new();
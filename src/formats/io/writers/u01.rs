@@ -9,10 +9,15 @@
 //!
 //! Writes U01 format with FAST/SLOW speed commands and byte-encoded coordinates
 //! for industrial Barudan embroidery machines.
+//!
+//! Chenille loop height and cording commands have no known U01 encoding and are
+//! dropped.
 
 use crate::core::constants::*;
 use crate::core::encoder::{EncoderSettings, Transcoder};
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use crate::utils::functions::decode_embroidery_command;
 use std::io::Write;
@@ -31,7 +36,7 @@ pub fn default_settings() -> EncoderSettings {
 }
 
 /// Write U01 format embroidery file
-pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
     write_with_settings(pattern, file, default_settings())
 }
 
@@ -40,11 +45,19 @@ pub fn write_with_settings(
     pattern: &EmbPattern,
     file: &mut impl Write,
     settings: EncoderSettings,
-) -> Result<()> {
+) -> Result<WriteReport> {
     // Encode the pattern
     let mut encoded = EmbPattern::new();
     let mut transcoder = Transcoder::with_settings(settings);
     transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::U01,
+        changes: transcoder.changes(),
+    };
+    // The header has no room for metadata or threads
+    report.drop_metadata(pattern, &[]);
+    report.drop_threads(pattern.threads().len());
+    report.drop_attributes(pattern);
 
     // Write first 128 bytes of padding
     for _ in 0..0x80 {
@@ -53,7 +66,7 @@ pub fn write_with_settings(
 
     let stitches = encoded.stitches();
     if stitches.is_empty() {
-        return Ok(());
+        return Ok(report);
     }
 
     // Calculate bounds
@@ -87,16 +100,14 @@ pub fn write_with_settings(
     let mut trigger_slow = false;
 
     for stitch in stitches {
+        let data = stitch.command & COMMAND_MASK;
         let x = stitch.x;
         let y = stitch.y;
-        let data = stitch.command & COMMAND_MASK;
 
         let dx = (x - xx).round() as i32;
         let dy = (y - yy).round() as i32;
-        xx += dx as f64;
-        yy += dy as f64;
 
-        // Handle FAST/SLOW triggers
+        // Handle FAST/SLOW triggers; their movement is carried by the next record
         if data == SLOW {
             trigger_slow = true;
             continue;
@@ -106,15 +117,7 @@ pub fn write_with_settings(
             continue;
         }
 
-        let mut cmd: u8 = 0x80;
-
-        // Set direction flags
-        if dy >= 0 {
-            cmd |= 0x40;
-        }
-        if dx <= 0 {
-            cmd |= 0x20;
-        }
+        let mut cmd: u8 = 0x80 | direction_flags(dx, dy);
 
         let delta_x = dx.unsigned_abs() as u8;
         let delta_y = dy.unsigned_abs() as u8;
@@ -158,20 +161,39 @@ pub fn write_with_settings(
                     needle = (needle % 15) + 1;
                 }
                 cmd |= 0x08;
-                cmd += needle as u8;
+                cmd += needle;
                 file.write_all(&[cmd, delta_y, delta_x])?;
             }
             END => {
                 break;
             }
-            _ => {}
+            // Nothing was written, so the position does not advance
+            _ => {
+                report.drop_command(data);
+                continue;
+            }
         }
+
+        xx += dx as f64;
+        yy += dy as f64;
     }
 
     // Write end marker
     file.write_all(&[0xF8, 0x00, 0x00])?;
 
-    Ok(())
+    Ok(report)
+}
+
+/// Direction flags of a U01 movement record
+fn direction_flags(dx: i32, dy: i32) -> u8 {
+    let mut flags = 0;
+    if dy >= 0 {
+        flags |= 0x40;
+    }
+    if dx <= 0 {
+        flags |= 0x20;
+    }
+    flags
 }
 
 fn write_i16_le(file: &mut impl Write, value: i16) -> Result<()> {
@@ -251,4 +273,36 @@ mod tests {
 
         assert!(buffer.len() > 0x100);
     }
+
+    #[test]
+    fn test_u01_drops_chenille_commands() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 10.0, 0.0);
+        pattern.add_command(CORDING_ON, 40.0, 10.0);
+        pattern.add_stitch_absolute(STITCH, 60.0, 10.0);
+        pattern.add_command(encode_chenille_loop_height(40), 70.0, 0.0);
+        pattern.add_command(CORDING_OFF, 70.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 80.0, 0.0);
+        pattern.add_stitch_absolute(END, 80.0, 0.0);
+
+        let mut buffer = Vec::new();
+        let report = write(&pattern, &mut buffer).unwrap();
+        assert_eq!(report.dropped_commands(), 3);
+
+        let mut pattern2 = EmbPattern::new();
+        u01_reader::read(&mut Cursor::new(buffer), &mut pattern2).unwrap();
+
+        // Dropped commands must not shift the stitch positions
+        let stitches: Vec<(f64, f64)> = pattern2
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| (s.x, s.y))
+            .collect();
+        assert_eq!(
+            stitches,
+            vec![(0.0, 0.0), (10.0, 0.0), (60.0, 10.0), (80.0, 0.0)]
+        );
+    }
 }
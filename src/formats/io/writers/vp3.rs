@@ -2,9 +2,15 @@
 //!
 //! Writes VP3 format with metadata sections for hoops, colors, and design information
 //! in Pfaff's proprietary structured binary format.
+//!
+//! [`write_blocks`] writes the designs of a collection as design blocks of one
+//! file, each at its offset in the hoop.
 
+use crate::core::collection::EmbPatternCollection;
 use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::report::{WriteReport, LICENSE_KEYS};
 use crate::formats::io::utils::WriteHelper;
 use crate::utils::error::Result;
 use std::io::Write;
@@ -12,9 +18,13 @@ use std::io::Write;
 /// VP3 file signature
 const VP3_SIGNATURE: &[u8] = b"%vsm%";
 
+/// Metadata keys the VP3 sections store; the license goes into the comments
+const STORED_METADATA: [&str; 5] = ["name", "author", "copyright", "comments", "notes"];
+
 /// Write a VP3 file to a writer
-pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
+pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<WriteReport> {
     let mut helper = WriteHelper::new(writer);
+    let mut report = WriteReport::new(Format::VP3);
 
     // Write signature
     helper.write_bytes(VP3_SIGNATURE)?;
@@ -26,7 +36,53 @@ pub fn write<W: Write>(writer: &mut W, pattern: &EmbPattern) -> Result<()> {
     write_metadata_section(&mut helper, pattern, "comments", b"%com%")?;
 
     // Write stitch data section
-    write_stitch_section(&mut helper, pattern)?;
+    write_stitch_section(&mut helper, pattern, &mut report)?;
+
+    let stored: Vec<&str> = STORED_METADATA
+        .iter()
+        .chain(&LICENSE_KEYS)
+        .copied()
+        .collect();
+    report.drop_metadata(pattern, &stored);
+    report.drop_threads(pattern.threads().len());
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+/// Write the designs of a collection as design blocks of one VP3 file
+///
+/// Blocks are written in name order, each at its
+/// [offset](EmbPatternCollection::offset) from the hoop center. The file's
+/// metadata is taken from the first block.
+pub fn write_blocks<W: Write>(writer: &mut W, collection: &EmbPatternCollection) -> Result<()> {
+    let mut names: Vec<&String> = collection.names().collect();
+    names.sort();
+
+    let mut helper = WriteHelper::new(writer);
+    helper.write_bytes(VP3_SIGNATURE)?;
+
+    if let Some(first) = names.first().and_then(|name| collection.get(name)) {
+        write_metadata_section(&mut helper, first, "name", b"%nam%")?;
+        write_metadata_section(&mut helper, first, "author", b"%aut%")?;
+        write_metadata_section(&mut helper, first, "copyright", b"%cop%")?;
+        write_metadata_section(&mut helper, first, "comments", b"%com%")?;
+    }
+
+    for name in names {
+        let (pattern, (x, y)) = match (collection.get(name), collection.offset(name)) {
+            (Some(pattern), Some(offset)) => (pattern, offset),
+            _ => continue,
+        };
+
+        // Block header: offset (0.1mm) and name
+        helper.write_bytes(b"%blk%")?;
+        helper.write_u16_le((8 + name.len()) as u16)?;
+        helper.write_i32_le(x.round() as i32)?;
+        helper.write_i32_le(y.round() as i32)?;
+        helper.write_bytes(name.as_bytes())?;
+
+        write_stitch_section(&mut helper, pattern, &mut WriteReport::new(Format::VP3))?;
+    }
 
     Ok(())
 }
@@ -38,7 +94,14 @@ fn write_metadata_section<W: Write>(
     key: &str,
     marker: &[u8],
 ) -> Result<()> {
-    if let Some(value) = pattern.get_metadata(key) {
+    // Stamp usage rights into the free-text comments section
+    let value = if key == "comments" {
+        pattern.comments_with_license()
+    } else {
+        pattern.get_metadata(key).cloned()
+    };
+
+    if let Some(value) = value {
         // Write section marker
         helper.write_bytes(marker)?;
 
@@ -54,7 +117,11 @@ fn write_metadata_section<W: Write>(
 }
 
 /// Write the stitch data section
-fn write_stitch_section<W: Write>(helper: &mut WriteHelper<W>, pattern: &EmbPattern) -> Result<()> {
+fn write_stitch_section<W: Write>(
+    helper: &mut WriteHelper<W>,
+    pattern: &EmbPattern,
+    report: &mut WriteReport,
+) -> Result<()> {
     // Write stitch section marker
     helper.write_bytes(b"%xxs%")?;
 
@@ -71,9 +138,14 @@ fn write_stitch_section<W: Write>(helper: &mut WriteHelper<W>, pattern: &EmbPatt
         let dx = (stitch.x - prev_x).round() as i8;
         let dy = (stitch.y - prev_y).round() as i8;
 
+        let command = stitch.command & COMMAND_MASK;
+        let code = encode_vp3_command(command);
+        if code == 0x00 && command != STITCH {
+            report.convert_command(command, STITCH);
+        }
         helper.write_i8(dx)?;
         helper.write_i8(dy)?;
-        helper.write_u8(encode_vp3_command(stitch.command & COMMAND_MASK))?;
+        helper.write_u8(code)?;
 
         prev_x += dx as f64;
         prev_y += dy as f64;
@@ -95,10 +167,12 @@ fn encode_vp3_command(command: u32) -> u8 {
 }
 
 /// Write VP3 file to path
+#[cfg(feature = "fs")]
 pub fn write_file(path: &str, pattern: &EmbPattern) -> Result<()> {
     let file = std::fs::File::create(path)?;
     let mut writer = std::io::BufWriter::new(file);
-    write(&mut writer, pattern)
+    write(&mut writer, pattern)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -154,6 +228,23 @@ mod tests {
         assert!(data_str.contains("%aut%"));
     }
 
+    #[test]
+    fn test_vp3_write_stamps_license() {
+        use crate::core::pattern::License;
+
+        let mut pattern = EmbPattern::new();
+        pattern.set_license(&License::new("Commercial").with_purchaser_id("ORD-5"));
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        pattern.end();
+
+        let mut buffer = Cursor::new(Vec::new());
+        write(&mut buffer, &pattern).unwrap();
+
+        let data_str = String::from_utf8_lossy(buffer.get_ref()).to_string();
+        assert!(data_str.contains("%com%"));
+        assert!(data_str.contains("License: Commercial; Purchaser: ORD-5"));
+    }
+
     #[test]
     fn test_vp3_roundtrip() {
         let mut pattern = EmbPattern::new();
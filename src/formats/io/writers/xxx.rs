@@ -2,14 +2,32 @@
 //!
 //! Writes XXX format with variable-length encoding (2 or 5 bytes per stitch),
 //! maximum stitch distance of ±124 units, and colors stored at end after stitches.
+//!
+//! The 0x100-byte header ends with a pointer to the end-of-stitches marker,
+//! and the color table follows that marker: two zero bytes, one `00 RR GG BB`
+//! entry per thread padded to 21 slots, then `FF FF FF 00 00 01`. Control
+//! records start with 0x7F: `7F 01` moves, `7F 03` trims and `7F 08` changes
+//! to the next color of the table. Stitches and moves beyond ±124 units use the
+//! long forms, 0x7D and 0x7E, with 16-bit displacements.
 
 use crate::core::constants::*;
 use crate::core::encoder::{EncoderSettings, Transcoder};
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::readers::xxx::{
+    XXX_COLOR_CHANGE, XXX_CONTROL, XXX_END, XXX_JUMP, XXX_LONG_JUMP, XXX_LONG_STITCH, XXX_TRIM,
+};
+use crate::formats::io::report::WriteReport;
 use crate::utils::error::Result;
 use byteorder::{LittleEndian, WriteBytesExt};
 use std::io::{Seek, Write};
 
+/// Offset of the end-of-stitches pointer, the last field of the header
+const END_POINTER_OFFSET: u64 = 0xFC;
+
+/// Slots of the color table, filled with zeros past the last thread
+const COLOR_SLOTS: usize = 21;
+
 /// Get default encoder settings for XXX format
 pub fn default_settings() -> EncoderSettings {
     EncoderSettings {
@@ -46,40 +64,44 @@ pub fn default_settings() -> EncoderSettings {
 /// let mut file = File::create("output.xxx").unwrap();
 /// butabuti::formats::io::writers::xxx::write(&pattern, &mut file).unwrap();
 /// ```
-pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<()> {
+pub fn write(pattern: &EmbPattern, file: &mut (impl Write + Seek)) -> Result<WriteReport> {
     // Encode pattern with XXX settings
     let mut transcoder = Transcoder::new();
     *transcoder.settings_mut() = default_settings();
 
     let mut encoded = EmbPattern::new();
     transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::XXX,
+        changes: transcoder.changes(),
+    };
 
-    // Write header (0x100 bytes)
+    // Write header (0x100 bytes, the end-of-stitches pointer last)
     write_header(&encoded, file)?;
-
-    // Remember position for end-of-stitches pointer
-    let placeholder_pos = file.stream_position()?;
-    file.write_u32::<LittleEndian>(0)?; // Placeholder
+    let start = file.stream_position()?;
+    let placeholder_pos = start - (0x100 - END_POINTER_OFFSET);
 
     // Write stitches
-    write_stitches(&encoded, file)?;
+    write_stitches(&encoded, file, &mut report)?;
 
     // Write end marker
     let end_pos = file.stream_position()?;
-    file.write_u8(0x7F)?;
-    file.write_u8(0x7F)?;
+    file.write_u8(XXX_CONTROL)?;
+    file.write_u8(XXX_END)?;
     file.write_u8(0x02)?;
     file.write_u8(0x14)?;
 
-    // Go back and fill in end-of-stitches pointer
+    // Go back and fill in end-of-stitches pointer, relative to the header
     file.seek(std::io::SeekFrom::Start(placeholder_pos))?;
-    file.write_u32::<LittleEndian>(end_pos as u32)?;
+    file.write_u32::<LittleEndian>((end_pos - (start - 0x100)) as u32)?;
     file.seek(std::io::SeekFrom::Start(end_pos + 4))?; // After end marker
 
     // Write colors
     write_colors(&encoded, file)?;
 
-    Ok(())
+    report.drop_metadata(pattern, &[]);
+    report.drop_attributes(pattern);
+    Ok(report)
 }
 
 /// Write the XXX file header
@@ -128,17 +150,22 @@ fn write_header(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
     file.write_u16::<LittleEndian>((-bounds.0) as i16 as u16)?;
     file.write_u16::<LittleEndian>(bounds.3 as i16 as u16)?;
 
-    // Fill rest of header with zeros up to 0x100
+    // Fill the header with zeros up to the end-of-stitches pointer
     let bytes_written = 0x17 + 4 + 0x0C + 4 + 2 + 2 + 2 + 2 + 2 + 2 + 2;
-    for _ in bytes_written..0x100 {
+    for _ in bytes_written..END_POINTER_OFFSET as usize {
         file.write_u8(0)?;
     }
+    file.write_u32::<LittleEndian>(0)?; // Filled in after the stitches
 
     Ok(())
 }
 
 /// Write stitch data
-fn write_stitches(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
+fn write_stitches(
+    pattern: &EmbPattern,
+    file: &mut impl Write,
+    report: &mut WriteReport,
+) -> Result<()> {
     let mut xx = 0.0;
     let mut yy = 0.0;
 
@@ -153,48 +180,54 @@ fn write_stitches(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
         yy += dy as f64;
 
         match command {
-            COLOR_CHANGE | STOP => {
-                file.write_u8(0x7F)?;
-                file.write_u8(0x08)?;
-                file.write_u8(dx as i8 as u8)?;
-                file.write_u8((-dy) as i8 as u8)?;
-            }
+            COLOR_CHANGE | STOP => control_record(file, XXX_COLOR_CHANGE, dx, dy)?,
             END => {
                 break;
             }
-            STITCH => {
-                // Check if it fits in short encoding
-                if (-124..124).contains(&dx) && (-124..124).contains(&dy) {
-                    file.write_u8(dx as i8 as u8)?;
-                    file.write_u8((-dy) as i8 as u8)?;
-                } else {
-                    // Long stitch encoding
-                    file.write_u8(0x7D)?;
-                    file.write_u16::<LittleEndian>(dx as i16 as u16)?;
-                    file.write_u16::<LittleEndian>((-dy) as i16 as u16)?;
-                }
-            }
-            TRIM => {
-                file.write_u8(0x7F)?;
-                file.write_u8(0x03)?;
-                file.write_u8(dx as i8 as u8)?;
-                file.write_u8((-dy) as i8 as u8)?;
-            }
-            JUMP => {
-                file.write_u8(0x7F)?;
-                file.write_u8(0x01)?;
-                file.write_u8(dx as i8 as u8)?;
-                file.write_u8((-dy) as i8 as u8)?;
-            }
-            _ => {
-                // Unknown command, skip
-            }
+            STITCH => movement_record(file, XXX_LONG_STITCH, None, dx, dy)?,
+            TRIM => control_record(file, XXX_TRIM, dx, dy)?,
+            JUMP => movement_record(file, XXX_LONG_JUMP, Some(XXX_JUMP), dx, dy)?,
+            _ => report.drop_command(command),
         }
     }
 
     Ok(())
 }
 
+/// Write a stitch or move, in the long form when it is beyond ±124 units
+///
+/// Short moves are `control` records; short stitches have no prefix.
+fn movement_record(
+    file: &mut impl Write,
+    long: u8,
+    control: Option<u8>,
+    dx: i32,
+    dy: i32,
+) -> Result<()> {
+    if (-124..124).contains(&dx) && (-124..124).contains(&dy) {
+        if let Some(control) = control {
+            file.write_u8(XXX_CONTROL)?;
+            file.write_u8(control)?;
+        }
+        file.write_u8(dx as i8 as u8)?;
+        file.write_u8((-dy) as i8 as u8)?;
+    } else {
+        file.write_u8(long)?;
+        file.write_u16::<LittleEndian>(dx as i16 as u16)?;
+        file.write_u16::<LittleEndian>((-dy) as i16 as u16)?;
+    }
+    Ok(())
+}
+
+/// Write a 4-byte control record
+fn control_record(file: &mut impl Write, code: u8, dx: i32, dy: i32) -> Result<()> {
+    file.write_u8(XXX_CONTROL)?;
+    file.write_u8(code)?;
+    file.write_u8(dx as i8 as u8)?;
+    file.write_u8((-dy) as i8 as u8)?;
+    Ok(())
+}
+
 /// Write color data
 fn write_colors(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
     file.write_u8(0)?;
@@ -212,8 +245,8 @@ fn write_colors(pattern: &EmbPattern, file: &mut impl Write) -> Result<()> {
         count += 1;
     }
 
-    // Fill remaining slots (up to 21 total)
-    for _ in count..21 {
+    // Fill remaining slots
+    for _ in count..COLOR_SLOTS {
         file.write_u32::<LittleEndian>(0)?;
     }
 
@@ -246,6 +279,10 @@ mod tests {
 
         // Check color count in header (at offset 0x27)
         assert_eq!(data[0x27], 1); // 1 thread
+
+        // End-of-stitches pointer at 0xFC, to the end marker
+        let end = u32::from_le_bytes(data[0xFC..0x100].try_into().unwrap()) as usize;
+        assert_eq!(&data[end..end + 4], &[0x7F, 0x7F, 0x02, 0x14]);
     }
 
     #[test]
@@ -288,10 +325,71 @@ mod tests {
         let mut read_back = EmbPattern::new();
         xxx::read(&mut buffer, &mut read_back).unwrap();
 
-        // Verify thread count
-        assert_eq!(read_back.threads().len(), original.threads().len());
+        // Verify thread colors
+        let colors: Vec<u32> = read_back.threads().iter().map(|t| t.color).collect();
+        assert_eq!(colors, vec![0xFF0000, 0x0000FF]);
+
+        // Stitches start right after the header
+        let first = read_back.stitches()[0];
+        assert_eq!((first.x, first.y), (0.0, 0.0));
 
         // Verify we have stitches
         assert!(!read_back.stitches().is_empty());
     }
+
+    #[test]
+    fn test_xxx_long_records_round_trip() {
+        use crate::formats::io::readers::xxx;
+
+        let mut original = EmbPattern::new();
+        original.add_thread(crate::core::thread::EmbThread::from_rgb(255, 0, 0));
+        original.add_stitch_absolute(STITCH, 0.0, 0.0);
+        original.add_stitch_absolute(STITCH, 300.0, -200.0);
+        original.add_stitch_absolute(TRIM, 300.0, -200.0);
+        original.add_stitch_absolute(JUMP, 600.0, 100.0);
+        original.add_stitch_absolute(STITCH, 610.0, 100.0);
+        original.end();
+
+        let positions = |pattern: &EmbPattern, command: u32| -> Vec<(f64, f64)> {
+            pattern
+                .stitches()
+                .iter()
+                .filter(|s| s.command & COMMAND_MASK == command)
+                .map(|s| (s.x, s.y))
+                .collect()
+        };
+
+        // Long records written as they are
+        let mut records = Vec::new();
+        write_stitches(&original, &mut records, &mut WriteReport::new(Format::XXX)).unwrap();
+        assert_eq!(&records[2..7], &[0x7D, 0x2C, 0x01, 0xC8, 0x00]);
+        let mut data = vec![0u8; 0x100];
+        data.extend(records);
+        data.extend_from_slice(&[0x7F, 0x7F, 0x02, 0x14]);
+        let mut read_back = EmbPattern::new();
+        xxx::read(&mut Cursor::new(data), &mut read_back).unwrap();
+        for command in [STITCH, JUMP, TRIM] {
+            assert_eq!(
+                positions(&read_back, command),
+                positions(&original, command)
+            );
+        }
+
+        // Through the encoder, the long stitch is split but stays sewn
+        let mut buffer = Cursor::new(Vec::new());
+        write(&original, &mut buffer).unwrap();
+        buffer.set_position(0);
+        let mut read_back = EmbPattern::new();
+        xxx::read(&mut buffer, &mut read_back).unwrap();
+        let stitches = positions(&read_back, STITCH);
+        assert!(stitches.contains(&(300.0, -200.0)));
+        assert!(stitches.contains(&(610.0, 100.0)));
+        assert_eq!(positions(&read_back, TRIM), vec![(300.0, -200.0)]);
+        assert!(read_back
+            .stitches()
+            .windows(2)
+            .filter(|pair| pair[1].command & COMMAND_MASK == STITCH)
+            .all(|pair| (pair[1].x - pair[0].x).abs() <= 124.0
+                && (pair[1].y - pair[0].y).abs() <= 124.0));
+    }
 }
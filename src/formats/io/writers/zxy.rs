@@ -0,0 +1,155 @@
+//! ZSK TC (ZXY) format writer
+//!
+//! Writes an empty header (offset 1 holds its length, zero) followed by
+//! 3-byte records: a control byte with the direction and move bits, then the
+//! x and y magnitudes. Thread changes become needle change functions, and the
+//! file ends with the end function. Other machine functions are not written.
+
+use crate::core::constants::*;
+use crate::core::encoder::{EncoderSettings, Transcoder};
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::readers::zxy::{
+    ZXY_END, ZXY_FUNCTION, ZXY_MOVE, ZXY_NEEDLE, ZXY_X_SIGN, ZXY_Y_SIGN,
+};
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::utils::WriteHelper;
+use crate::utils::error::Result;
+use crate::utils::functions::decode_embroidery_command;
+use std::io::Write;
+
+/// Default encoder settings for ZXY format
+pub fn default_settings() -> EncoderSettings {
+    EncoderSettings {
+        max_stitch: 127.0,
+        max_jump: 127.0,
+        full_jump: false,
+        thread_change_command: NEEDLE_SET,
+        sequin_contingency: CONTINGENCY_SEQUIN_JUMP,
+        ..Default::default()
+    }
+}
+
+/// Write ZXY (ZSK TC) format embroidery file
+pub fn write(pattern: &EmbPattern, file: &mut impl Write) -> Result<WriteReport> {
+    let mut encoded = EmbPattern::new();
+    let mut transcoder = Transcoder::with_settings(default_settings());
+    transcoder.transcode(pattern, &mut encoded)?;
+    let mut report = WriteReport {
+        format: Format::ZXY,
+        changes: transcoder.changes(),
+    };
+
+    let mut helper = WriteHelper::new(file);
+    helper.write_bytes(&[0, 0, 0])?;
+
+    let (mut xx, mut yy) = (0.0, 0.0);
+    let mut needle = 1;
+    for stitch in encoded.stitches() {
+        let data = stitch.command & COMMAND_MASK;
+        let dx = (stitch.x - xx).round() as i32;
+        let dy = (stitch.y - yy).round() as i32;
+        let function = match data {
+            STITCH | JUMP => {
+                xx += dx as f64;
+                yy += dy as f64;
+                let ctrl = if data == JUMP { ZXY_MOVE } else { 0 };
+                helper.write_bytes(&movement_record(ctrl, dx, dy))?;
+                continue;
+            }
+            NEEDLE_SET | COLOR_CHANGE => {
+                needle = match decode_embroidery_command(stitch.command).2 {
+                    Some(n) if data == NEEDLE_SET && n > 0 => n,
+                    _ => needle.wrapping_add(1).max(1),
+                };
+                [ZXY_FUNCTION, ZXY_NEEDLE, needle]
+            }
+            END => break,
+            _ => {
+                report.drop_command(data);
+                continue;
+            }
+        };
+        helper.write_bytes(&function)?;
+    }
+    helper.write_bytes(&[ZXY_FUNCTION, ZXY_END, 0])?;
+
+    report.drop_metadata(pattern, &[]);
+    report.drop_threads(pattern.threads().len());
+    report.drop_attributes(pattern);
+    Ok(report)
+}
+
+/// Stitch or move record for a displacement within one record's range
+fn movement_record(mut ctrl: u8, dx: i32, dy: i32) -> [u8; 3] {
+    if dx < 0 {
+        ctrl |= ZXY_X_SIGN;
+    }
+    if dy > 0 {
+        ctrl |= ZXY_Y_SIGN;
+    }
+    [ctrl, dx.unsigned_abs() as u8, dy.unsigned_abs() as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::io::readers;
+    use crate::utils::functions::encode_thread_change;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_zxy_round_trip() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, -50.0);
+        pattern.stitch_abs(-20.0, 60.0);
+        pattern.add_command(
+            encode_thread_change(NEEDLE_SET, None, Some(7), None),
+            -20.0,
+            60.0,
+        );
+        pattern.stitch_abs(-10.0, 60.0);
+        pattern.stitch_abs(210.0, 70.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        write(&pattern, &mut output).unwrap();
+
+        let mut read = EmbPattern::new();
+        readers::zxy::read(&mut Cursor::new(output), &mut read).unwrap();
+        assert_eq!(read.count_stitches(), pattern.count_stitches());
+        assert_eq!(read.bounds(), pattern.bounds());
+
+        let needles: Vec<Option<u8>> = read
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+            .map(|s| decode_embroidery_command(s.command).2)
+            .collect();
+        // The encoder numbers needles by thread change
+        assert_eq!(needles, vec![Some(2)]);
+    }
+
+    #[test]
+    fn test_zxy_drops_borer_commands() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.add_command(BORER_ON, 0.0, 0.0);
+        pattern.stitch_abs(10.0, 0.0);
+        pattern.add_command(BORER_OFF, 10.0, 0.0);
+        pattern.end();
+
+        let mut output = Vec::new();
+        let report = write(&pattern, &mut output).unwrap();
+        assert!(!report.is_lossless());
+
+        let mut read = EmbPattern::new();
+        readers::zxy::read(&mut Cursor::new(output), &mut read).unwrap();
+        assert_eq!(read.count_stitches(), pattern.count_stitches());
+        assert!(read
+            .stitches()
+            .iter()
+            .all(|s| s.command & COMMAND_MASK != NEEDLE_SET));
+    }
+}
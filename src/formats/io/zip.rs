@@ -0,0 +1,392 @@
+//! ZIP archives of designs
+//!
+//! Design collections are usually distributed as `.zip` files. This module
+//! reads the central directory of an archive and extracts stored and deflated
+//! entries, and writes archives with deflated entries. Encrypted, multi-disk
+//! and ZIP64 archives are not supported.
+//!
+//! Entry sizes come from the archive and are checked against the data, so a
+//! corrupt or hostile archive can't make extraction allocate more than
+//! [`MAX_ENTRY_SIZE`] per entry.
+
+use crate::formats::io::utils::read_exact_vec;
+use crate::utils::error::{Error, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Largest uncompressed entry extracted
+pub const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Local file header signature
+const LOCAL_HEADER: u32 = 0x0403_4B50;
+/// Central directory file header signature
+const CENTRAL_HEADER: u32 = 0x0201_4B50;
+/// End of central directory record signature
+const END_OF_DIRECTORY: u32 = 0x0605_4B50;
+/// Size of the end of central directory record without its comment
+const END_OF_DIRECTORY_SIZE: u64 = 22;
+/// General purpose flag: entry is encrypted
+const FLAG_ENCRYPTED: u16 = 0x0001;
+/// General purpose flag: name is UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+/// Compression method: stored
+const METHOD_STORED: u16 = 0;
+/// Compression method: deflate
+const METHOD_DEFLATE: u16 = 8;
+/// DOS date of written entries (1980-01-01), for reproducible archives
+const DOS_DATE: u16 = 0x0021;
+
+/// Whether data starts with a ZIP local file header
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(&LOCAL_HEADER.to_le_bytes())
+}
+
+/// An entry in a ZIP archive's central directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipEntry {
+    /// Path inside the archive, `/`-separated
+    pub name: String,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    method: u16,
+    flags: u16,
+    crc: u32,
+    compressed_size: u64,
+    header_offset: u64,
+}
+
+impl ZipEntry {
+    /// Whether the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// A ZIP archive open for reading
+pub struct ZipArchive<R: Read + Seek> {
+    reader: R,
+    entries: Vec<ZipEntry>,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    /// Open an archive and read its central directory
+    pub fn new(mut reader: R) -> Result<Self> {
+        let end = find_end_of_directory(&mut reader)?;
+        reader.seek(SeekFrom::Start(end + 10))?;
+        let count = reader.read_u16::<LittleEndian>()?;
+        let _directory_size = reader.read_u32::<LittleEndian>()?;
+        let directory_offset = reader.read_u32::<LittleEndian>()?;
+        if count == u16::MAX || directory_offset == u32::MAX {
+            return Err(Error::Unsupported(
+                "ZIP64 archives are not supported".into(),
+            ));
+        }
+
+        reader.seek(SeekFrom::Start(directory_offset as u64))?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            if reader.read_u32::<LittleEndian>()? != CENTRAL_HEADER {
+                return Err(Error::parse("ZIP central directory is corrupt"));
+            }
+            let mut fields = [0u8; 42];
+            reader.read_exact(&mut fields)?;
+            let u16_at = |i: usize| u16::from_le_bytes([fields[i], fields[i + 1]]);
+            let u32_at = |i: usize| {
+                u32::from_le_bytes([fields[i], fields[i + 1], fields[i + 2], fields[i + 3]])
+            };
+            let name = read_exact_vec(&mut reader, u16_at(24) as usize)?;
+            let skip = u16_at(26) as i64 + u16_at(28) as i64;
+            reader.seek(SeekFrom::Current(skip))?;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                size: u32_at(20) as u64,
+                method: u16_at(6),
+                flags: u16_at(4),
+                crc: u32_at(12),
+                compressed_size: u32_at(16) as u64,
+                header_offset: u32_at(38) as u64,
+            });
+        }
+        Ok(Self { reader, entries })
+    }
+
+    /// Entries in directory order
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// Extract an entry's contents
+    ///
+    /// Fails for encrypted entries, unsupported compression methods, entries
+    /// larger than [`MAX_ENTRY_SIZE`] and data that doesn't match the
+    /// recorded size or CRC.
+    pub fn read(&mut self, entry: &ZipEntry) -> Result<Vec<u8>> {
+        let context = |message: &str| format!("ZIP entry {}: {}", entry.name, message);
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            return Err(Error::Unsupported(context("encrypted")));
+        }
+        if entry.size > MAX_ENTRY_SIZE {
+            return Err(Error::limit_exceeded(
+                context("uncompressed size"),
+                MAX_ENTRY_SIZE as usize,
+                entry.size as usize,
+            ));
+        }
+
+        self.reader.seek(SeekFrom::Start(entry.header_offset))?;
+        if self.reader.read_u32::<LittleEndian>()? != LOCAL_HEADER {
+            return Err(Error::parse(context("missing local header")));
+        }
+        self.reader.seek(SeekFrom::Current(22))?;
+        let name_length = self.reader.read_u16::<LittleEndian>()?;
+        let extra_length = self.reader.read_u16::<LittleEndian>()?;
+        self.reader
+            .seek(SeekFrom::Current(name_length as i64 + extra_length as i64))?;
+
+        let compressed = (&mut self.reader).take(entry.compressed_size);
+        // One byte past the recorded size detects entries that inflate further
+        let limit = entry.size + 1;
+        let mut data = Vec::new();
+        match entry.method {
+            METHOD_STORED => compressed.take(limit).read_to_end(&mut data)?,
+            METHOD_DEFLATE => DeflateDecoder::new(compressed)
+                .take(limit)
+                .read_to_end(&mut data)?,
+            method => {
+                return Err(Error::Unsupported(context(&format!(
+                    "compression method {}",
+                    method
+                ))))
+            }
+        };
+
+        if data.len() as u64 != entry.size {
+            return Err(Error::parse(context(&format!(
+                "expected {} bytes, found {}",
+                entry.size,
+                data.len()
+            ))));
+        }
+        let mut crc = Crc::new();
+        crc.update(&data);
+        if crc.sum() != entry.crc {
+            return Err(Error::parse(context("CRC mismatch")));
+        }
+        Ok(data)
+    }
+}
+
+/// Offset of the end of central directory record
+fn find_end_of_directory<R: Read + Seek>(reader: &mut R) -> Result<u64> {
+    let length = reader.seek(SeekFrom::End(0))?;
+    // The record is followed by a comment of at most 64 KiB
+    let tail_length = length.min(END_OF_DIRECTORY_SIZE + u16::MAX as u64);
+    reader.seek(SeekFrom::Start(length - tail_length))?;
+    let tail = read_exact_vec(reader, tail_length as usize)?;
+
+    let signature = END_OF_DIRECTORY.to_le_bytes();
+    (0..tail
+        .len()
+        .saturating_sub(END_OF_DIRECTORY_SIZE as usize - 1))
+        .rev()
+        .find(|&i| tail[i..i + 4] == signature)
+        .map(|i| length - tail_length + i as u64)
+        .ok_or_else(|| Error::parse("Not a ZIP archive: no end of central directory"))
+}
+
+/// A ZIP archive being written
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    offset: u64,
+    entries: Vec<ZipEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Start an archive
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Add a deflated entry
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(data);
+
+        let entry = ZipEntry {
+            name: name.to_string(),
+            size: data.len() as u64,
+            method: METHOD_DEFLATE,
+            flags: FLAG_UTF8,
+            crc: crc.sum(),
+            compressed_size: compressed.len() as u64,
+            header_offset: self.offset,
+        };
+        to_u32(self.offset, "archive size")?;
+        self.writer.write_u32::<LittleEndian>(LOCAL_HEADER)?;
+        write_entry_fields(&mut self.writer, &entry)?;
+        self.writer.write_u16::<LittleEndian>(0)?; // extra field length
+        self.writer.write_all(entry.name.as_bytes())?;
+        self.writer.write_all(&compressed)?;
+
+        self.offset += 30 + entry.name.len() as u64 + compressed.len() as u64;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        let directory_offset = self.offset;
+        let mut directory_size = 0u64;
+        for entry in &self.entries {
+            self.writer.write_u32::<LittleEndian>(CENTRAL_HEADER)?;
+            self.writer.write_u16::<LittleEndian>(20)?; // version made by
+            write_entry_fields(&mut self.writer, entry)?;
+            self.writer.write_u16::<LittleEndian>(0)?; // extra field length
+            self.writer.write_u16::<LittleEndian>(0)?; // comment length
+            self.writer.write_u16::<LittleEndian>(0)?; // disk number
+            self.writer.write_u16::<LittleEndian>(0)?; // internal attributes
+            self.writer.write_u32::<LittleEndian>(0)?; // external attributes
+            self.writer
+                .write_u32::<LittleEndian>(to_u32(entry.header_offset, "archive size")?)?;
+            self.writer.write_all(entry.name.as_bytes())?;
+            directory_size += 46 + entry.name.len() as u64;
+        }
+
+        let count = u16::try_from(self.entries.len())
+            .map_err(|_| Error::Unsupported("ZIP archives hold at most 65535 entries".into()))?;
+        self.writer.write_u32::<LittleEndian>(END_OF_DIRECTORY)?;
+        self.writer.write_u16::<LittleEndian>(0)?; // this disk
+        self.writer.write_u16::<LittleEndian>(0)?; // directory disk
+        self.writer.write_u16::<LittleEndian>(count)?;
+        self.writer.write_u16::<LittleEndian>(count)?;
+        self.writer
+            .write_u32::<LittleEndian>(to_u32(directory_size, "directory size")?)?;
+        self.writer
+            .write_u32::<LittleEndian>(to_u32(directory_offset, "archive size")?)?;
+        self.writer.write_u16::<LittleEndian>(0)?; // comment length
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Fields shared by local and central headers, from "version needed" to the
+/// name length
+fn write_entry_fields<W: Write>(writer: &mut W, entry: &ZipEntry) -> Result<()> {
+    writer.write_u16::<LittleEndian>(20)?; // version needed
+    writer.write_u16::<LittleEndian>(entry.flags)?;
+    writer.write_u16::<LittleEndian>(entry.method)?;
+    writer.write_u16::<LittleEndian>(0)?; // time
+    writer.write_u16::<LittleEndian>(DOS_DATE)?;
+    writer.write_u32::<LittleEndian>(entry.crc)?;
+    writer.write_u32::<LittleEndian>(to_u32(entry.compressed_size, "entry size")?)?;
+    writer.write_u32::<LittleEndian>(to_u32(entry.size, "entry size")?)?;
+    let name_length = u16::try_from(entry.name.len())
+        .map_err(|_| Error::Unsupported(format!("ZIP entry name too long: {}", entry.name)))?;
+    writer.write_u16::<LittleEndian>(name_length)?;
+    Ok(())
+}
+
+/// A size that must fit the 32-bit fields of a non-ZIP64 archive
+fn to_u32(value: u64, what: &str) -> Result<u32> {
+    u32::try_from(value).map_err(|_| Error::Unsupported(format!("ZIP {} exceeds 4 GiB", what)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Vec::new());
+        for (name, data) in entries {
+            writer.add(name, data).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let big: Vec<u8> = (0..5000u32).map(|i| (i % 13) as u8).collect();
+        let bytes = archive(&[("a.dst", b"hello"), ("set/b.pes", &big), ("empty", b"")]);
+        assert!(is_zip(&bytes));
+
+        let mut zip = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let entries = zip.entries().to_vec();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a.dst", "set/b.pes", "empty"]);
+        assert_eq!(zip.read(&entries[0]).unwrap(), b"hello");
+        assert_eq!(zip.read(&entries[1]).unwrap(), big);
+        assert!(zip.read(&entries[2]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_stored_entry_with_comment() {
+        // Hand-built archive with one stored entry and an archive comment
+        let data = b"stored";
+        let mut crc = Crc::new();
+        crc.update(data);
+        let entry = ZipEntry {
+            name: "x.txt".into(),
+            size: data.len() as u64,
+            method: METHOD_STORED,
+            flags: 0,
+            crc: crc.sum(),
+            compressed_size: data.len() as u64,
+            header_offset: 0,
+        };
+        let mut bytes = Vec::new();
+        bytes.write_u32::<LittleEndian>(LOCAL_HEADER).unwrap();
+        write_entry_fields(&mut bytes, &entry).unwrap();
+        bytes.write_u16::<LittleEndian>(0).unwrap();
+        bytes.extend_from_slice(b"x.txt");
+        bytes.extend_from_slice(data);
+        let directory_offset = bytes.len() as u32;
+        bytes.write_u32::<LittleEndian>(CENTRAL_HEADER).unwrap();
+        bytes.write_u16::<LittleEndian>(20).unwrap();
+        write_entry_fields(&mut bytes, &entry).unwrap();
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(b"x.txt");
+        let directory_size = bytes.len() as u32 - directory_offset;
+        bytes.write_u32::<LittleEndian>(END_OF_DIRECTORY).unwrap();
+        bytes.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        bytes.write_u32::<LittleEndian>(directory_size).unwrap();
+        bytes.write_u32::<LittleEndian>(directory_offset).unwrap();
+        bytes.write_u16::<LittleEndian>(7).unwrap();
+        bytes.extend_from_slice(b"comment");
+
+        let mut zip = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let entry = zip.entries()[0].clone();
+        assert_eq!(zip.read(&entry).unwrap(), data);
+    }
+
+    #[test]
+    fn test_rejects_corrupt_archives() {
+        assert!(ZipArchive::new(Cursor::new(b"not a zip".to_vec())).is_err());
+
+        // Flip a byte of the compressed data: CRC or inflate error
+        let mut bytes = archive(&[("a.dst", b"some design data")]);
+        bytes[40] ^= 0xFF;
+        let mut zip = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let entry = zip.entries()[0].clone();
+        assert!(zip.read(&entry).is_err());
+
+        // A recorded size beyond the limit is refused before inflating
+        let mut zip = ZipArchive::new(Cursor::new(archive(&[("a", b"x")]))).unwrap();
+        let mut entry = zip.entries()[0].clone();
+        entry.size = MAX_ENTRY_SIZE + 1;
+        assert_eq!(
+            zip.read(&entry).unwrap_err().code().as_str(),
+            "parse.limit_exceeded"
+        );
+    }
+}
@@ -4,8 +4,16 @@
 //!
 //! This module contains readers and writers for various embroidery file formats.
 
+/// Built-in format identifiers and capabilities
+pub mod format;
+
 /// File I/O operations
 pub mod io;
 
+/// Plugin interface for third-party formats
+pub mod plugin;
+
 /// Format registry system
 pub mod registry;
+
+pub use format::Format;
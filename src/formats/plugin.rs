@@ -0,0 +1,297 @@
+//! Plugin interface for third-party format crates
+//!
+//! Formats that don't belong in butabuti itself (vintage punch tape dialects,
+//! in-house machine formats) can be implemented in a separate crate and registered
+//! with a [`FormatRegistry`](crate::formats::registry::FormatRegistry), either on one
+//! registry instance or process-wide with [`register_global`].
+//!
+//! This module is kept deliberately small and follows semver strictly: the
+//! [`EmbFormat`] trait only gains provided methods, and [`FormatCaps`] is
+//! `#[non_exhaustive]` and built through its constructor, so plugins keep compiling
+//! across minor releases.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::formats::plugin::{EmbFormat, FormatCaps};
+//! use butabuti::formats::registry::FormatRegistry;
+//! use std::io::{Cursor, Read, Write};
+//!
+//! /// One `x y` stitch per line
+//! struct Tape;
+//!
+//! impl EmbFormat for Tape {
+//!     fn caps(&self) -> FormatCaps {
+//!         FormatCaps::new("TAPE", &["tape"]).with_description("Punch tape listing")
+//!     }
+//!
+//!     fn read(&self, reader: &mut dyn Read) -> Result<EmbPattern> {
+//!         let mut text = String::new();
+//!         reader.read_to_string(&mut text)?;
+//!         let mut pattern = EmbPattern::new();
+//!         for line in text.lines() {
+//!             let mut fields = line.split_whitespace().map(str::parse::<f64>);
+//!             if let (Some(Ok(x)), Some(Ok(y))) = (fields.next(), fields.next()) {
+//!                 pattern.stitch_abs(x, y);
+//!             }
+//!         }
+//!         Ok(pattern)
+//!     }
+//!
+//!     fn write(&self, pattern: &EmbPattern, writer: &mut dyn Write) -> Result<()> {
+//!         for stitch in pattern.stitches() {
+//!             writeln!(writer, "{} {}", stitch.x, stitch.y)?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut registry = FormatRegistry::new();
+//! registry.register(Tape);
+//!
+//! let mut file = Cursor::new(b"0 0\n10 5\n".to_vec());
+//! let pattern = registry.read_pattern(&mut file, "tape")?;
+//! assert_eq!(pattern.stitches().len(), 2);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::registry::{FormatInfo, WriterLimits};
+use crate::utils::error::{Error, Result};
+use lazy_static::lazy_static;
+use std::io::{Read, Write};
+use std::sync::{Arc, RwLock};
+
+/// Capabilities a plugin format declares to the registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FormatCaps {
+    /// Format name (e.g., "TAPE"), matched case-insensitively
+    pub name: &'static str,
+    /// File extensions without the dot
+    pub extensions: &'static [&'static str],
+    /// Human-readable description
+    pub description: &'static str,
+    /// Whether `EmbFormat::read` is implemented
+    pub can_read: bool,
+    /// Whether `EmbFormat::write` is implemented
+    pub can_write: bool,
+    /// Limits checked before `EmbFormat::write` is called
+    pub limits: WriterLimits,
+}
+
+impl FormatCaps {
+    /// Readable and writable format without limits
+    pub fn new(name: &'static str, extensions: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            extensions,
+            description: "",
+            can_read: true,
+            can_write: true,
+            limits: WriterLimits::UNLIMITED,
+        }
+    }
+
+    /// Set the description
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Set the writer limits
+    pub fn with_limits(mut self, limits: WriterLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Mark the format as read-only
+    pub fn read_only(mut self) -> Self {
+        self.can_read = true;
+        self.can_write = false;
+        self
+    }
+
+    /// Mark the format as write-only
+    pub fn write_only(mut self) -> Self {
+        self.can_read = false;
+        self.can_write = true;
+        self
+    }
+}
+
+impl From<&FormatCaps> for FormatInfo {
+    fn from(caps: &FormatCaps) -> Self {
+        FormatInfo {
+            name: caps.name,
+            extensions: caps.extensions,
+            can_read: caps.can_read,
+            can_write: caps.can_write,
+            description: caps.description,
+            limits: caps.limits,
+        }
+    }
+}
+
+/// An embroidery format implemented outside butabuti
+///
+/// Only `caps` is required; `read` and `write` default to an unsupported-format
+/// error, so read-only and write-only formats implement just one of them.
+pub trait EmbFormat: Send + Sync {
+    /// Name, extensions and capabilities of the format
+    fn caps(&self) -> FormatCaps;
+
+    /// Read a pattern
+    fn read(&self, reader: &mut dyn Read) -> Result<EmbPattern> {
+        let _ = reader;
+        Err(Error::UnsupportedFormat(format!(
+            "{} format does not support reading",
+            self.caps().name
+        )))
+    }
+
+    /// Write a pattern
+    fn write(&self, pattern: &EmbPattern, writer: &mut dyn Write) -> Result<()> {
+        let _ = (pattern, writer);
+        Err(Error::UnsupportedFormat(format!(
+            "{} format does not support writing",
+            self.caps().name
+        )))
+    }
+}
+
+lazy_static! {
+    static ref GLOBAL_FORMATS: RwLock<Vec<Arc<dyn EmbFormat>>> = RwLock::new(Vec::new());
+}
+
+/// Register a format with every `FormatRegistry` created afterwards
+///
+/// Registering a format with the same name as an earlier one replaces it.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::plugin::{register_global, EmbFormat, FormatCaps};
+/// use butabuti::formats::registry::FormatRegistry;
+///
+/// struct Legacy;
+///
+/// impl EmbFormat for Legacy {
+///     fn caps(&self) -> FormatCaps {
+///         FormatCaps::new("LEGACY", &["lgc"]).read_only()
+///     }
+/// }
+///
+/// register_global(Legacy);
+/// register_global(Legacy);
+///
+/// let registry = FormatRegistry::new();
+/// assert_eq!(registry.get_format_by_extension("lgc").unwrap().name, "LEGACY");
+/// assert_eq!(registry.readable_formats().iter().filter(|f| f.name == "LEGACY").count(), 1);
+/// ```
+pub fn register_global<F: EmbFormat + 'static>(format: F) {
+    let format: Arc<dyn EmbFormat> = Arc::new(format);
+    let name = format.caps().name;
+    let mut formats = GLOBAL_FORMATS.write().unwrap_or_else(|e| e.into_inner());
+    formats.retain(|f| !f.caps().name.eq_ignore_ascii_case(name));
+    formats.push(format);
+}
+
+/// Formats registered with `register_global`, in registration order
+pub fn global_formats() -> Vec<Arc<dyn EmbFormat>> {
+    GLOBAL_FORMATS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+    use crate::formats::registry::FormatRegistry;
+    use std::io::Cursor;
+
+    /// Writes one byte per stitch: the X coordinate
+    struct ByteTape;
+
+    impl EmbFormat for ByteTape {
+        fn caps(&self) -> FormatCaps {
+            FormatCaps::new("BYTETAPE", &["btp"])
+                .write_only()
+                .with_limits(WriterLimits {
+                    max_stitches: Some(3),
+                    ..WriterLimits::UNLIMITED
+                })
+        }
+
+        fn write(&self, pattern: &EmbPattern, writer: &mut dyn Write) -> Result<()> {
+            let bytes: Vec<u8> = pattern.stitches().iter().map(|s| s.x as u8).collect();
+            writer.write_all(&bytes)?;
+            Ok(())
+        }
+    }
+
+    /// Replaces the built-in DST reader
+    struct FakeDst;
+
+    impl EmbFormat for FakeDst {
+        fn caps(&self) -> FormatCaps {
+            FormatCaps::new("DST", &["dst"]).read_only()
+        }
+
+        fn read(&self, _reader: &mut dyn Read) -> Result<EmbPattern> {
+            let mut pattern = EmbPattern::new();
+            pattern.add_stitch_absolute(STITCH, 42.0, 0.0);
+            Ok(pattern)
+        }
+    }
+
+    #[test]
+    fn test_plugin_write_and_limits() {
+        let mut registry = FormatRegistry::new();
+        registry.register(ByteTape);
+
+        let info = registry.get_format_by_extension("BTP").unwrap();
+        assert_eq!(info.name, "BYTETAPE");
+        assert!(!info.can_read && info.can_write);
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 1.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 2.0, 0.0);
+
+        let mut output = Cursor::new(Vec::new());
+        registry
+            .write_pattern(&pattern, &mut output, "btp")
+            .unwrap();
+        assert_eq!(output.into_inner(), vec![1, 2]);
+
+        let err = registry
+            .read_pattern(&mut Cursor::new(vec![1u8]), "bytetape")
+            .unwrap_err();
+        assert!(err.to_string().contains("does not support reading"));
+
+        pattern.add_stitch_absolute(STITCH, 3.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 4.0, 0.0);
+        let err = registry
+            .write_pattern(&pattern, &mut Cursor::new(Vec::new()), "btp")
+            .unwrap_err();
+        assert!(err.to_string().contains("4 stitches (max 3)"));
+    }
+
+    #[test]
+    fn test_plugin_overrides_builtin() {
+        let mut registry = FormatRegistry::new();
+        let builtin_count = registry.all_formats().len();
+        registry.register(FakeDst);
+
+        assert_eq!(registry.all_formats().len(), builtin_count);
+        assert!(!registry.get_format("dst").unwrap().can_write);
+
+        let pattern = registry
+            .read_pattern(&mut Cursor::new(Vec::new()), "DST")
+            .unwrap();
+        assert_eq!(pattern.stitches()[0].x, 42.0);
+    }
+}
@@ -2,11 +2,157 @@
 //!
 //! Provides a registry for dynamic format discovery and handler lookup.
 //! Simplifies format detection, reader/writer selection, and extensible format handling.
+//!
+//! Third-party formats implementing [`EmbFormat`](crate::formats::plugin::EmbFormat)
+//! can be added with [`FormatRegistry::register`].
 
+use crate::core::constants::*;
 use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::detector::detect_format;
+use crate::formats::io::options::{ReadOptions, WriteOptions};
+use crate::formats::io::traits::{PatternReader, PatternWriter};
+use crate::formats::plugin::{self, EmbFormat};
 use crate::utils::error::{Error, Result};
-use std::io::{Read, Seek, Write};
+use crate::utils::functions::decode_embroidery_command;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::Arc;
+
+/// Hard limits of what a format writer can encode
+///
+/// `None` means the format imposes no practical limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriterLimits {
+    /// Maximum number of colors (thread table entries or color blocks)
+    pub max_colors: Option<usize>,
+    /// Maximum number of needles addressable by needle-set commands
+    pub max_needles: Option<usize>,
+    /// Maximum number of stitch records
+    pub max_stitches: Option<usize>,
+}
+
+impl WriterLimits {
+    /// No limits
+    pub const UNLIMITED: Self = Self {
+        max_colors: None,
+        max_needles: None,
+        max_stitches: None,
+    };
+
+    /// Check a pattern against these limits and return every violated limit
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    /// use butabuti::formats::registry::{LimitViolation, WriterLimits};
+    ///
+    /// let limits = WriterLimits { max_colors: Some(1), ..WriterLimits::UNLIMITED };
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.add_thread(EmbThread::new(0xFF0000));
+    /// pattern.add_thread(EmbThread::new(0x00FF00));
+    ///
+    /// assert_eq!(
+    ///     limits.violations(&pattern),
+    ///     vec![LimitViolation::Colors { count: 2, max: 1 }]
+    /// );
+    /// ```
+    pub fn violations(&self, pattern: &EmbPattern) -> Vec<LimitViolation> {
+        let mut violations = Vec::new();
+
+        if let Some(max) = self.max_colors {
+            let count = color_count(pattern);
+            if count > max {
+                violations.push(LimitViolation::Colors { count, max });
+            }
+        }
+        if let Some(max) = self.max_needles {
+            let count = needle_count(pattern);
+            if count > max {
+                violations.push(LimitViolation::Needles { count, max });
+            }
+        }
+        if let Some(max) = self.max_stitches {
+            let count = pattern.stitches().len();
+            if count > max {
+                violations.push(LimitViolation::Stitches { count, max });
+            }
+        }
+
+        violations
+    }
+}
+
+/// A writer limit exceeded by a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitViolation {
+    /// Too many colors
+    Colors {
+        /// Colors used by the pattern
+        count: usize,
+        /// Format maximum
+        max: usize,
+    },
+    /// Needle number beyond what the format can address
+    Needles {
+        /// Needles used by the pattern
+        count: usize,
+        /// Format maximum
+        max: usize,
+    },
+    /// Too many stitch records
+    Stitches {
+        /// Stitch records in the pattern
+        count: usize,
+        /// Format maximum
+        max: usize,
+    },
+}
+
+impl fmt::Display for LimitViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitViolation::Colors { count, max } => write!(f, "{} colors (max {})", count, max),
+            LimitViolation::Needles { count, max } => {
+                write!(f, "{} needles (max {})", count, max)
+            }
+            LimitViolation::Stitches { count, max } => {
+                write!(f, "{} stitches (max {})", count, max)
+            }
+        }
+    }
+}
+
+/// Number of colors used: thread table size or color blocks, whichever is larger
+fn color_count(pattern: &EmbPattern) -> usize {
+    let mut blocks = 0;
+    let mut has_stitches = false;
+    for stitch in pattern.stitches() {
+        match stitch.command & COMMAND_MASK {
+            COLOR_CHANGE | NEEDLE_SET => blocks += 1,
+            STITCH => has_stitches = true,
+            _ => {}
+        }
+    }
+    if has_stitches {
+        blocks += 1;
+    }
+    pattern.threads().len().max(blocks)
+}
+
+/// Highest needle number addressed by needle-set commands
+fn needle_count(pattern: &EmbPattern) -> usize {
+    pattern
+        .stitches()
+        .iter()
+        .filter(|s| s.command & COMMAND_MASK == NEEDLE_SET)
+        .filter_map(|s| decode_embroidery_command(s.command).2)
+        .map(|needle| needle as usize + 1)
+        .max()
+        .unwrap_or(0)
+}
 
 /// Information about a supported format
 #[derive(Debug, Clone)]
@@ -21,141 +167,84 @@ pub struct FormatInfo {
     pub can_write: bool,
     /// Human-readable description
     pub description: &'static str,
+    /// Limits enforced before writing
+    pub limits: WriterLimits,
+}
+
+impl From<Format> for FormatInfo {
+    fn from(format: Format) -> Self {
+        Self {
+            name: format.name(),
+            extensions: format.extensions(),
+            can_read: format.can_read(),
+            can_write: format.can_write(),
+            description: format.description(),
+            limits: format.writer_limits(),
+        }
+    }
 }
 
 /// Registry for managing format information
 pub struct FormatRegistry {
     formats: Vec<FormatInfo>,
+    plugins: Vec<Arc<dyn EmbFormat>>,
 }
 
 impl FormatRegistry {
     /// Create a new registry with all built-in formats
+    ///
+    /// Formats registered with [`plugin::register_global`] are included.
     pub fn new() -> Self {
+        let mut registry = Self::builtin();
+        for format in plugin::global_formats() {
+            registry.register_arc(format);
+        }
+        registry
+    }
+
+    /// Create a registry with only the built-in formats
+    pub fn builtin() -> Self {
         Self {
-            formats: vec![
-                FormatInfo {
-                    name: "DST",
-                    extensions: &["dst"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Tajima DST format",
-                },
-                FormatInfo {
-                    name: "PES",
-                    extensions: &["pes"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Brother PES format",
-                },
-                FormatInfo {
-                    name: "JEF",
-                    extensions: &["jef"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Janome JEF format",
-                },
-                FormatInfo {
-                    name: "EXP",
-                    extensions: &["exp"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Melco EXP format",
-                },
-                FormatInfo {
-                    name: "VP3",
-                    extensions: &["vp3"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Pfaff VP3 format",
-                },
-                FormatInfo {
-                    name: "PEC",
-                    extensions: &["pec"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Brother PEC format",
-                },
-                FormatInfo {
-                    name: "XXX",
-                    extensions: &["xxx"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Singer XXX format",
-                },
-                FormatInfo {
-                    name: "U01",
-                    extensions: &["u01"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Barudan U01 format",
-                },
-                FormatInfo {
-                    name: "TBF",
-                    extensions: &["tbf"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Tajima TBF format",
-                },
-                FormatInfo {
-                    name: "COL",
-                    extensions: &["col"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Thread color list",
-                },
-                FormatInfo {
-                    name: "EDR",
-                    extensions: &["edr"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Embird color format",
-                },
-                FormatInfo {
-                    name: "INF",
-                    extensions: &["inf"],
-                    can_read: true,
-                    can_write: true,
-                    description: "Thread information format",
-                },
-                FormatInfo {
-                    name: "JSON",
-                    extensions: &["json"],
-                    can_read: true,
-                    can_write: true,
-                    description: "JSON embroidery data",
-                },
-                FormatInfo {
-                    name: "CSV",
-                    extensions: &["csv"],
-                    can_read: true,
-                    can_write: true,
-                    description: "CSV embroidery data",
-                },
-                FormatInfo {
-                    name: "GCODE",
-                    extensions: &["gcode", "nc"],
-                    can_read: true,
-                    can_write: true,
-                    description: "G-code embroidery format",
-                },
-                FormatInfo {
-                    name: "SVG",
-                    extensions: &["svg"],
-                    can_read: false,
-                    can_write: true,
-                    description: "SVG vector graphics (write-only)",
-                },
-                FormatInfo {
-                    name: "TXT",
-                    extensions: &["txt"],
-                    can_read: false,
-                    can_write: true,
-                    description: "Human-readable text (write-only)",
-                },
-            ],
+            plugins: Vec::new(),
+            formats: Format::ALL
+                .iter()
+                .filter(|format| format.can_read() || format.can_write())
+                .map(|&format| FormatInfo::from(format))
+                .collect(),
         }
     }
 
+    /// Register a third-party format
+    ///
+    /// A format with the same name as a registered one (built-in or plugin)
+    /// replaces it.
+    pub fn register<F: EmbFormat + 'static>(&mut self, format: F) {
+        self.register_arc(Arc::new(format));
+    }
+
+    fn register_arc(&mut self, format: Arc<dyn EmbFormat>) {
+        let caps = format.caps();
+        self.formats
+            .retain(|f| !f.name.eq_ignore_ascii_case(caps.name));
+        self.plugins
+            .retain(|p| !p.caps().name.eq_ignore_ascii_case(caps.name));
+        self.formats.push(FormatInfo::from(&caps));
+        self.plugins.push(format);
+    }
+
+    /// Find the plugin handling a format name or extension
+    fn plugin(&self, format: &str) -> Option<&Arc<dyn EmbFormat>> {
+        let format = format.trim_start_matches('.');
+        self.plugins.iter().find(|p| {
+            let caps = p.caps();
+            caps.name.eq_ignore_ascii_case(format)
+                || caps
+                    .extensions
+                    .iter()
+                    .any(|e| e.eq_ignore_ascii_case(format))
+        })
+    }
+
     /// Get format info by name
     pub fn get_format(&self, name: &str) -> Option<&FormatInfo> {
         let name_lower = name.to_lowercase();
@@ -195,69 +284,179 @@ impl FormatRegistry {
         self.formats.iter().filter(|f| f.can_write).collect()
     }
 
+    /// Get the writer limits of a format
+    pub fn writer_limits(&self, format: &str) -> Option<WriterLimits> {
+        self.get_format(format).map(|f| f.limits)
+    }
+
+    /// Check that a pattern fits within a format's writer limits
+    ///
+    /// Returns an encoding error listing every violated limit. Unknown formats are
+    /// not checked here; `write_pattern` reports them separately.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    /// use butabuti::formats::registry::FormatRegistry;
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// for i in 0..300 {
+    ///     pattern.add_thread(EmbThread::new(i));
+    /// }
+    ///
+    /// let registry = FormatRegistry::new();
+    /// assert!(registry.check_limits(&pattern, "pes").is_err());
+    /// assert!(registry.check_limits(&pattern, "dst").is_ok());
+    /// ```
+    pub fn check_limits(&self, pattern: &EmbPattern, format: &str) -> Result<()> {
+        match self.get_format(format) {
+            Some(info) => Self::limit_error(info, pattern).map_or(Ok(()), Err),
+            None => Ok(()),
+        }
+    }
+
+    /// Encoding error listing every limit of `info` violated by the pattern
+    fn limit_error(info: &FormatInfo, pattern: &EmbPattern) -> Option<Error> {
+        let violations = info.limits.violations(pattern);
+        if violations.is_empty() {
+            return None;
+        }
+
+        let details = violations
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Some(Error::encoding(format!(
+            "{} writer limits exceeded: {}",
+            info.name, details
+        )))
+    }
+
     /// Read a pattern from a file using the appropriate format
     pub fn read_pattern<R: Read + Seek>(&self, file: &mut R, format: &str) -> Result<EmbPattern> {
-        let format_lower = format.to_lowercase();
-        match format_lower.as_str() {
-            "dst" => crate::formats::io::readers::dst::read(file, None),
-            "pes" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::pes::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "jef" => crate::formats::io::readers::jef::read(file, None),
-            "exp" => crate::formats::io::readers::exp::read(file),
-            "vp3" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::vp3::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "pec" => crate::formats::io::readers::pec::read(file),
-            "xxx" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::xxx::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "u01" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::u01::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "tbf" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::tbf::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "col" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::col::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "edr" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::edr::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "inf" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::inf::read(file, &mut pattern)?;
-                Ok(pattern)
-            }
-            "json" => crate::formats::io::readers::json::read(file),
-            "csv" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::csv::read(file, &mut pattern)?;
-                Ok(pattern)
+        self.read_pattern_with_options(file, format, &ReadOptions::default())
+    }
+
+    /// Read a pattern using the appropriate format, with reader options
+    ///
+    /// Plugin formats don't take options.
+    pub fn read_pattern_with_options<R: Read + Seek>(
+        &self,
+        file: &mut R,
+        format: &str,
+        options: &ReadOptions,
+    ) -> Result<EmbPattern> {
+        if let Some(plugin) = self.plugin(format) {
+            return plugin.read(file);
+        }
+
+        builtin_format(format)?.read(file, options)
+    }
+
+    /// Choose the format to read a stream as from its file name and magic bytes
+    ///
+    /// Returns the format to try first and, when the extension and the content
+    /// disagree, the other candidate. The extension wins unless it is missing or
+    /// unknown, or the content has an unambiguous signature (PES, PEC, VP3, EMB).
+    /// The reader position is left unchanged.
+    pub fn resolve_read_format<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        path: Option<&Path>,
+    ) -> Result<(&'static str, Option<&'static str>)> {
+        let by_extension = path
+            .and_then(|path| self.get_format_from_path(path))
+            .filter(|info| info.can_read)
+            .map(|info| info.name);
+        let detected = detect_format(reader).ok().and_then(|format| {
+            let name = format.registry_name()?;
+            let readable = self.get_format(name).is_some_and(|f| f.can_read);
+            readable.then_some((name, format.has_signature()))
+        });
+
+        match (by_extension, detected) {
+            (Some(ext), Some((sig, true))) if !ext.eq_ignore_ascii_case(sig) => {
+                Ok((sig, Some(ext)))
             }
-            "gcode" => {
-                let mut pattern = EmbPattern::new();
-                crate::formats::io::readers::gcode::read(file, &mut pattern)?;
-                Ok(pattern)
+            (Some(ext), Some((sig, _))) if !ext.eq_ignore_ascii_case(sig) => Ok((ext, Some(sig))),
+            (Some(ext), _) => Ok((ext, None)),
+            (None, Some((sig, _))) => Ok((sig, None)),
+            (None, None) => Err(Error::UnsupportedFormat(match path {
+                Some(path) => format!("Unable to detect the format of {}", path.display()),
+                None => "Unable to detect the input format".to_string(),
+            })),
+        }
+    }
+
+    /// Read a pattern, detecting its format from the file name and magic bytes
+    ///
+    /// Uses [`resolve_read_format`](Self::resolve_read_format). If the first
+    /// candidate fails or yields no stitches, the second one is tried and the
+    /// first error is reported when both fail. Returns the pattern together with
+    /// the name of the format it was read as.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use butabuti::prelude::*;
+    /// use butabuti::formats::registry::FormatRegistry;
+    /// use std::io::Cursor;
+    /// use std::path::Path;
+    ///
+    /// let mut pattern = EmbPattern::new();
+    /// pattern.add_thread(EmbThread::new(0xFF0000));
+    /// pattern.stitch_abs(0.0, 0.0);
+    /// pattern.stitch_abs(50.0, 50.0);
+    /// pattern.end();
+    ///
+    /// let registry = FormatRegistry::new();
+    /// let mut bytes = Cursor::new(Vec::new());
+    /// registry.write_pattern(&pattern, &mut bytes, "pes")?;
+    ///
+    /// // A PES file saved with the wrong extension is read by its signature
+    /// bytes.set_position(0);
+    /// let (read, format) = registry.read_pattern_detected(&mut bytes, Some(Path::new("design.dst")))?;
+    /// assert_eq!(format, "pes");
+    /// assert_eq!(read.count_stitches(), 2);
+    /// # Ok::<(), butabuti::utils::error::Error>(())
+    /// ```
+    pub fn read_pattern_detected<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        path: Option<&Path>,
+    ) -> Result<(EmbPattern, &'static str)> {
+        self.read_pattern_detected_with_options(reader, path, &ReadOptions::default())
+    }
+
+    /// Read a pattern, detecting its format, with reader options
+    ///
+    /// See [`read_pattern_detected`](Self::read_pattern_detected).
+    pub fn read_pattern_detected_with_options<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        path: Option<&Path>,
+        options: &ReadOptions,
+    ) -> Result<(EmbPattern, &'static str)> {
+        let start = reader.stream_position()?;
+        let (first, fallback) = self.resolve_read_format(reader, path)?;
+
+        let result = self.read_pattern_with_options(reader, first, options);
+        let Some(fallback) = fallback else {
+            return result.map(|pattern| (pattern, first));
+        };
+
+        // A failed or empty read means the stream wasn't in the first format
+        match result {
+            Ok(pattern) if !pattern.stitches().is_empty() => Ok((pattern, first)),
+            result => {
+                reader.seek(SeekFrom::Start(start))?;
+                match self.read_pattern_with_options(reader, fallback, options) {
+                    Ok(pattern) if !pattern.stitches().is_empty() => Ok((pattern, fallback)),
+                    _ => result.map(|pattern| (pattern, first)),
+                }
             }
-            _ => Err(Error::UnsupportedFormat(format!(
-                "Unsupported format: {}",
-                format
-            ))),
         }
     }
 
@@ -268,56 +467,27 @@ impl FormatRegistry {
         file: &mut W,
         format: &str,
     ) -> Result<()> {
-        let format_lower = format.to_lowercase();
-        match format_lower.as_str() {
-            "dst" => crate::formats::io::writers::dst::write(file, pattern, true, 512),
-            "pes" => crate::formats::io::writers::pes::write_pes(
-                pattern,
-                file,
-                crate::formats::io::writers::pes::PesVersion::V1,
-                false,
-            ),
-            "jef" => {
-                crate::formats::io::writers::jef::write(file, pattern, true, 100, "2025-01-01")
-            }
-            "exp" => crate::formats::io::writers::exp::write(file, pattern),
-            "vp3" => crate::formats::io::writers::vp3::write(file, pattern),
-            "pec" => crate::formats::io::writers::pec::write(file, pattern),
-            "xxx" => crate::formats::io::writers::xxx::write(pattern, file),
-            "u01" => crate::formats::io::writers::u01::write(pattern, file),
-            "tbf" => crate::formats::io::writers::tbf::write(pattern, file),
-            "col" => crate::formats::io::writers::col::write(pattern, file),
-            "edr" => crate::formats::io::writers::edr::write(pattern, file),
-            "inf" => crate::formats::io::writers::inf::write(pattern, file),
-            "json" => crate::formats::io::writers::json::write(file, pattern),
-            "csv" => crate::formats::io::writers::csv::write(
-                file,
-                pattern,
-                crate::formats::io::writers::csv::CsvVersion::Default,
-            ),
-            "gcode" => crate::formats::io::writers::gcode::write(pattern, file),
-            "svg" => {
-                // SVG doesn't require Seek
-                let mut buf = Vec::new();
-                crate::formats::io::writers::svg::write(pattern, &mut buf)?;
-                file.write_all(&buf)?;
-                Ok(())
-            }
-            "txt" => {
-                // TXT doesn't require Seek either
-                let mut buf = Vec::new();
-                crate::formats::io::writers::txt::write(pattern, &mut buf)?;
-                file.write_all(&buf)?;
-                Ok(())
-            }
-            _ => Err(Error::UnsupportedFormat(format!(
-                "Unsupported format: {}",
-                format
-            ))),
+        if let Some(plugin) = self.plugin(format) {
+            let info = FormatInfo::from(&plugin.caps());
+            return match Self::limit_error(&info, pattern) {
+                Some(err) => Err(err),
+                None => plugin.write(pattern, file),
+            };
         }
+
+        self.check_limits(pattern, format)?;
+
+        builtin_format(format)?.write(pattern, file, &WriteOptions::default())
     }
 }
 
+/// Built-in format with the given name or extension
+fn builtin_format(format: &str) -> Result<Format> {
+    format
+        .parse::<Format>()
+        .map_err(|_| Error::UnsupportedFormat(format!("Unsupported format: {}", format)))
+}
+
 impl Default for FormatRegistry {
     fn default() -> Self {
         Self::new()
@@ -398,16 +568,110 @@ mod tests {
         let writable = registry.writable_formats();
         assert!(!writable.is_empty());
 
-        // SVG and TXT should be write-only
-        let svg = registry.get_format("SVG").unwrap();
-        assert!(!svg.can_read);
-        assert!(svg.can_write);
+        // TXT is write-only, SVG outlines can be imported
+        let txt = registry.get_format("TXT").unwrap();
+        assert!(!txt.can_read);
+        assert!(txt.can_write);
+        assert!(registry.get_format("SVG").unwrap().can_read);
+    }
+
+    #[test]
+    fn test_writer_limits_declared() {
+        let registry = FormatRegistry::new();
+
+        assert_eq!(registry.writer_limits("pes").unwrap().max_colors, Some(256));
+        assert_eq!(registry.writer_limits("u01").unwrap().max_needles, Some(15));
+        assert_eq!(
+            registry.writer_limits("json"),
+            Some(WriterLimits::UNLIMITED)
+        );
+        assert!(registry.writer_limits("xyz").is_none());
+    }
+
+    #[test]
+    fn test_write_fails_fast_on_limits() {
+        use crate::core::thread::EmbThread;
+        use crate::utils::functions::encode_thread_change;
+        use std::io::Cursor;
+
+        let registry = FormatRegistry::new();
+        let mut pattern = EmbPattern::new();
+        for i in 0..300 {
+            pattern.add_thread(EmbThread::new(i));
+        }
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_command(
+            encode_thread_change(NEEDLE_SET, None, Some(20), None),
+            0.0,
+            0.0,
+        );
+        pattern.add_stitch_absolute(STITCH, 10.0, 0.0);
+
+        let mut output = Cursor::new(Vec::new());
+        let err = registry
+            .write_pattern(&pattern, &mut output, "PES")
+            .unwrap_err();
+        assert!(err.to_string().contains("300 colors (max 256)"));
+        assert!(output.get_ref().is_empty());
+
+        let err = registry
+            .write_pattern(&pattern, &mut output, "u01")
+            .unwrap_err();
+        assert!(err.to_string().contains("21 needles (max 15)"));
+
+        assert!(registry
+            .write_pattern(&pattern, &mut output, "json")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_read_pattern_detected() {
+        use crate::core::thread::EmbThread;
+        use std::io::Cursor;
+
+        let registry = FormatRegistry::new();
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 40.0, 20.0);
+        pattern.add_command(END, 40.0, 20.0);
+
+        let mut pes = Cursor::new(Vec::new());
+        registry.write_pattern(&pattern, &mut pes, "pes").unwrap();
+
+        // Signature wins over a mismatched extension, extension kept as fallback
+        pes.set_position(0);
+        let choice = registry
+            .resolve_read_format(&mut pes, Some(Path::new("design.dst")))
+            .unwrap();
+        assert_eq!(choice, ("pes", Some("DST")));
+        assert_eq!(pes.position(), 0);
+
+        // No file name at all
+        let (read, format) = registry.read_pattern_detected(&mut pes, None).unwrap();
+        assert_eq!(format, "pes");
+        assert_eq!(read.count_stitches(), 2);
+
+        // Extension without a signature in the content is trusted
+        let mut dst = Cursor::new(Vec::new());
+        registry.write_pattern(&pattern, &mut dst, "dst").unwrap();
+        dst.set_position(0);
+        let (_, format) = registry
+            .read_pattern_detected(&mut dst, Some(Path::new("design.DST")))
+            .unwrap();
+        assert_eq!(format, "DST");
+
+        let mut unknown = Cursor::new(vec![0xFFu8; 64]);
+        assert!(registry
+            .read_pattern_detected(&mut unknown, Some(Path::new("design.bin")))
+            .is_err());
     }
 
     #[test]
     fn test_format_count() {
         let registry = FormatRegistry::new();
-        // Should have all 17 formats (15 bidirectional + 2 write-only)
-        assert_eq!(registry.all_formats().len(), 17);
+        // Should have all 27 formats (23 bidirectional, EMB, TAP and THR read-only,
+        // TXT write-only)
+        assert_eq!(registry.all_formats().len(), 27);
     }
 }
@@ -0,0 +1,333 @@
+//! Planar geometry helpers for outlines derived from patterns
+//!
+//! Provides outline extraction (convex hull of the stitches) and polyline/polygon
+//! offsetting, used to generate cut lines with seam allowance and placement lines
+//! for in-the-hoop projects.
+//!
+//! Points are `(x, y)` tuples in pattern units (0.1mm); offset distances are given in
+//! millimeters.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::geometry::{offset_polyline, pattern_outline};
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 0.0);
+//! pattern.stitch_abs(100.0, 100.0);
+//! pattern.stitch_abs(0.0, 100.0);
+//!
+//! // Cut line with 5mm seam allowance around the design
+//! let outline = pattern_outline(&pattern);
+//! let cut_line = offset_polyline(&outline, 5.0, true);
+//! assert!(cut_line.contains(&(-50.0, -50.0)));
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+
+/// A point in pattern units (0.1mm)
+pub type Point = (f64, f64);
+
+/// Corners sharper than this miter ratio are beveled instead of mitered
+const MITER_LIMIT: f64 = 4.0;
+
+/// Points closer than this (0.1mm units) are treated as duplicates
+const EPSILON: f64 = 1e-9;
+
+/// Signed area of a closed polygon
+///
+/// Positive when the vertices run counter-clockwise in a Y-up coordinate system.
+pub fn polygon_area(points: &[Point]) -> f64 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+    let mut sum = 0.0;
+    for i in 0..points.len() {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % points.len()];
+        sum += x1 * y2 - x2 * y1;
+    }
+    sum / 2.0
+}
+
+/// Whether a point lies inside a closed polygon (even-odd rule)
+///
+/// Points exactly on an edge may fall either side.
+pub fn point_in_polygon(point: Point, polygon: &[Point]) -> bool {
+    let (px, py) = point;
+    let mut inside = false;
+    let mut j = polygon.len().wrapping_sub(1);
+    for (i, &(xi, yi)) in polygon.iter().enumerate() {
+        let (xj, yj) = polygon[j];
+        if (yi > py) != (yj > py) && px < (xj - xi) * (py - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Convex hull of a point set, counter-clockwise (Y-up), without repeated endpoint
+///
+/// Non-finite points are ignored.
+pub fn convex_hull(points: &[Point]) -> Vec<Point> {
+    let mut sorted: Vec<Point> = points
+        .iter()
+        .copied()
+        .filter(|(x, y)| x.is_finite() && y.is_finite())
+        .collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    sorted.dedup();
+
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    let cross =
+        |o: Point, a: Point, b: Point| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+
+    // Andrew's monotone chain
+    let mut hull: Vec<Point> = Vec::with_capacity(sorted.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &Point>> = if pass == 0 {
+            Box::new(sorted.iter())
+        } else {
+            Box::new(sorted.iter().rev())
+        };
+        for &p in iter {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Outline of a pattern: the convex hull of its needle-down stitches
+pub fn pattern_outline(pattern: &EmbPattern) -> Vec<Point> {
+    let points: Vec<Point> = pattern
+        .stitches()
+        .iter()
+        .filter(|s| s.command & COMMAND_MASK == STITCH)
+        .map(|s| (s.x, s.y))
+        .collect();
+    convex_hull(&points)
+}
+
+/// Offset a polyline or polygon by `distance_mm`
+///
+/// For closed polygons (`closed = true`), positive distances inflate and negative
+/// distances deflate, regardless of vertex order. For open polylines, positive
+/// distances offset along the right-hand normal `(dy, -dx)` of each segment.
+///
+/// Corners use miter joins; corners sharper than a 4:1 miter ratio are beveled with
+/// two points. Deflating by more than the polygon's inner radius produces
+/// self-intersecting output, which is not clipped.
+pub fn offset_polyline(points: &[Point], distance_mm: f64, closed: bool) -> Vec<Point> {
+    let mut pts: Vec<Point> = Vec::with_capacity(points.len());
+    for &p in points {
+        if !p.0.is_finite() || !p.1.is_finite() {
+            continue;
+        }
+        if pts.last().is_none_or(|&q| !same_point(p, q)) {
+            pts.push(p);
+        }
+    }
+    if closed && pts.len() > 1 && same_point(pts[0], pts[pts.len() - 1]) {
+        pts.pop();
+    }
+
+    let min_points = if closed { 3 } else { 2 };
+    if pts.len() < min_points || !distance_mm.is_finite() {
+        return pts;
+    }
+
+    let mut distance = distance_mm * 10.0;
+    if closed && polygon_area(&pts) < 0.0 {
+        // Right-hand normals point inward on clockwise polygons
+        distance = -distance;
+    }
+
+    let n = pts.len();
+    let segment_count = if closed { n } else { n - 1 };
+    let normals: Vec<Point> = (0..segment_count)
+        .map(|i| right_normal(pts[i], pts[(i + 1) % n]))
+        .collect();
+
+    let mut result = Vec::with_capacity(n + 4);
+    for i in 0..n {
+        let (px, py) = pts[i];
+        let incoming = if closed {
+            Some(normals[(i + segment_count - 1) % segment_count])
+        } else if i > 0 {
+            Some(normals[i - 1])
+        } else {
+            None
+        };
+        let outgoing = if closed || i < segment_count {
+            Some(normals[i % segment_count])
+        } else {
+            None
+        };
+
+        match (incoming, outgoing) {
+            (Some(n1), Some(n2)) => {
+                let (mx, my) = (n1.0 + n2.0, n1.1 + n2.1);
+                let len = mx.hypot(my);
+                let cos_half = if len > EPSILON {
+                    (mx * n1.0 + my * n1.1) / len
+                } else {
+                    0.0
+                };
+
+                if cos_half > 1.0 / MITER_LIMIT {
+                    let scale = distance / (len * cos_half);
+                    result.push((px + mx * scale, py + my * scale));
+                } else {
+                    result.push((px + n1.0 * distance, py + n1.1 * distance));
+                    result.push((px + n2.0 * distance, py + n2.1 * distance));
+                }
+            }
+            (Some(normal), None) | (None, Some(normal)) => {
+                result.push((px + normal.0 * distance, py + normal.1 * distance));
+            }
+            (None, None) => {}
+        }
+    }
+    result
+}
+
+fn same_point(a: Point, b: Point) -> bool {
+    (a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON
+}
+
+/// Unit right-hand normal `(dy, -dx)` of the segment a→b
+fn right_normal(a: Point, b: Point) -> Point {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = dx.hypot(dy);
+    (dy / len, -dx / len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_points(actual: &[Point], expected: &[Point]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a.0 - e.0).abs() < 1e-9 && (a.1 - e.1).abs() < 1e-9,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_point_in_polygon() {
+        // Concave "L" shape
+        let polygon = [
+            (0.0, 0.0),
+            (20.0, 0.0),
+            (20.0, 10.0),
+            (10.0, 10.0),
+            (10.0, 20.0),
+            (0.0, 20.0),
+        ];
+        assert!(point_in_polygon((5.0, 5.0), &polygon));
+        assert!(point_in_polygon((5.0, 15.0), &polygon));
+        assert!(!point_in_polygon((15.0, 15.0), &polygon));
+        assert!(!point_in_polygon((-1.0, 5.0), &polygon));
+        assert!(!point_in_polygon((5.0, 5.0), &[]));
+    }
+
+    #[test]
+    fn test_inflate_and_deflate_square_either_orientation() {
+        let ccw = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)];
+        let cw: Vec<Point> = ccw.iter().rev().copied().collect();
+
+        assert_points(
+            &offset_polyline(&ccw, 1.0, true),
+            &[
+                (-10.0, -10.0),
+                (110.0, -10.0),
+                (110.0, 110.0),
+                (-10.0, 110.0),
+            ],
+        );
+        assert_points(
+            &offset_polyline(&cw, 1.0, true),
+            &[
+                (-10.0, 110.0),
+                (110.0, 110.0),
+                (110.0, -10.0),
+                (-10.0, -10.0),
+            ],
+        );
+        assert_points(
+            &offset_polyline(&ccw, -2.0, true),
+            &[(20.0, 20.0), (80.0, 20.0), (80.0, 80.0), (20.0, 80.0)],
+        );
+    }
+
+    #[test]
+    fn test_open_polyline_and_closing_point() {
+        let line = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0)];
+        assert_points(
+            &offset_polyline(&line, 1.0, false),
+            &[(0.0, -10.0), (110.0, -10.0), (110.0, 100.0)],
+        );
+
+        // Repeated closing point is ignored
+        let closed = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 0.0)];
+        assert_eq!(offset_polyline(&closed, 1.0, true).len(), 3);
+    }
+
+    #[test]
+    fn test_sharp_corner_is_beveled() {
+        let spike = [(0.0, 0.0), (100.0, 0.0), (0.0, 5.0)];
+        let result = offset_polyline(&spike, 1.0, true);
+        assert!(result.len() > 3);
+        for (x, y) in result {
+            assert!(x.abs() < 200.0 && y.abs() < 200.0);
+        }
+    }
+
+    #[test]
+    fn test_degenerate_input() {
+        assert!(offset_polyline(&[], 1.0, true).is_empty());
+        assert_eq!(offset_polyline(&[(1.0, 1.0)], 1.0, false), vec![(1.0, 1.0)]);
+        assert_eq!(polygon_area(&[(0.0, 0.0), (1.0, 1.0)]), 0.0);
+    }
+
+    #[test]
+    fn test_pattern_outline() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(JUMP, 500.0, 500.0);
+        for &(x, y) in &[
+            (0.0, 0.0),
+            (50.0, 10.0),
+            (100.0, 0.0),
+            (100.0, 100.0),
+            (0.0, 100.0),
+        ] {
+            pattern.add_stitch_absolute(STITCH, x, y);
+        }
+
+        let outline = pattern_outline(&pattern);
+        assert_points(
+            &outline,
+            &[(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)],
+        );
+        assert_eq!(polygon_area(&outline), 10_000.0);
+    }
+}
@@ -15,7 +15,7 @@
 //! ## Quick Start
 //!
 //! ```rust,no_run
-//! use rusty_petal::prelude::*;
+//! use butabuti::prelude::*;
 //!
 //! // Read an embroidery file
 //! let pattern = EmbPattern::read("design.pes")?;
@@ -50,9 +50,20 @@
 // Core modules
 pub mod core;
 pub mod formats;
+pub mod geometry;
 pub mod palettes;
+pub mod render;
+pub mod service;
 pub mod utils;
 
+// C ABI (enabled with ffi feature flag)
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+// Python bindings (enabled with python feature flag)
+#[cfg(feature = "python")]
+pub mod python;
+
 // WASM bindings (enabled with wasm feature flag)
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
@@ -68,8 +79,10 @@ pub use utils::error::Error;
 pub mod prelude {
     pub use crate::core::constants::{StitchType, *};
     pub use crate::core::matrix::EmbMatrix;
-    pub use crate::core::pattern::{EmbPattern, StitchCommand};
+    pub use crate::core::pattern::{Bounds, EmbPattern, License, MetadataKey, StitchCommand};
     pub use crate::core::thread::EmbThread;
+    pub use crate::formats::Format;
+    #[cfg(feature = "fs")]
     pub use crate::utils::batch::{
         BatchConverter, ConversionResult, ConversionResults, MultiFormatExporter,
     };
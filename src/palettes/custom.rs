@@ -0,0 +1,462 @@
+//! Custom thread palettes loaded from files
+//!
+//! Reads thread charts from three kinds of files:
+//!
+//! - **CSV**: one thread per row. With a header row, columns are picked by name
+//!   (`catalog`/`code`, `name`/`description`, `hex`/`color`, `r`/`g`/`b`,
+//!   `brand`, `chart`, `weight`). Without one, rows are `catalog,name,color` where
+//!   the color is `#RRGGBB` or three fields `R,G,B`.
+//! - **JSON**: an array of thread objects, or an object with a `threads` array and
+//!   an optional `name`. Thread objects use the field names of
+//!   [`EmbThread`](crate::core::thread::EmbThread); the color is a `"#RRGGBB"`
+//!   string, a number, or `r`/`g`/`b` fields.
+//! - **GS** (Great Notions chart export): one thread per line as a code, a name
+//!   and an RGB triple, separated by tabs, semicolons or commas. Lines without a
+//!   color, such as titles, are skipped.
+//!
+//! Loaded palettes can be registered under a name and looked up at runtime, also
+//! through [`PaletteLibrary::get_by_name`](crate::utils::palette::PaletteLibrary::get_by_name).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::palettes::custom::{self, CustomPaletteFormat};
+//! use std::io::Cursor;
+//!
+//! let csv = "catalog,name,hex\n1000,Black,#000000\n1147,Christmas Red,#C8102E\n";
+//! let threads = custom::load(&mut Cursor::new(csv), CustomPaletteFormat::Csv)?;
+//! assert_eq!(threads[1].color, 0xC8102E);
+//!
+//! custom::register("Shop Reds", threads);
+//! assert_eq!(custom::get("shop reds").unwrap().len(), 2);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::thread::{parse_color_string, EmbThread};
+use crate::utils::error::{Error, Result};
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::Read;
+#[cfg(feature = "fs")]
+use std::path::Path;
+use std::sync::RwLock;
+
+lazy_static! {
+    /// Registered palettes by lowercase name, with the name as given
+    static ref REGISTRY: RwLock<BTreeMap<String, (String, Vec<EmbThread>)>> =
+        RwLock::new(BTreeMap::new());
+}
+
+/// Custom palette file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomPaletteFormat {
+    /// Comma-separated thread chart
+    Csv,
+    /// JSON thread list
+    Json,
+    /// Great Notions thread chart export
+    Gs,
+}
+
+impl CustomPaletteFormat {
+    /// Detect format from file extension
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            "gs" => Some(Self::Gs),
+            _ => None,
+        }
+    }
+}
+
+/// Load a thread chart, detecting the format from the file extension
+#[cfg(feature = "fs")]
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Vec<EmbThread>> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .ok_or_else(|| Error::Parse("Palette file has no extension".to_string()))?;
+    let format = CustomPaletteFormat::from_extension(ext)
+        .ok_or_else(|| Error::UnsupportedFormat(format!("Unknown palette format: .{}", ext)))?;
+    load(&mut File::open(path)?, format)
+}
+
+/// Load a thread chart in the given format
+///
+/// Fails if the chart contains no threads.
+pub fn load(reader: &mut impl Read, format: CustomPaletteFormat) -> Result<Vec<EmbThread>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let threads = match format {
+        CustomPaletteFormat::Csv => parse_csv(&text)?,
+        CustomPaletteFormat::Json => parse_json(&text)?,
+        CustomPaletteFormat::Gs => parse_gs(&text)?,
+    };
+    if threads.is_empty() {
+        return Err(Error::Parse("Palette file contains no threads".to_string()));
+    }
+    Ok(threads)
+}
+
+/// Register a palette under a name, replacing any palette of the same name
+///
+/// Names are matched case-insensitively.
+pub fn register(name: impl Into<String>, threads: Vec<EmbThread>) {
+    let name = name.into();
+    REGISTRY
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_lowercase(), (name, threads));
+}
+
+/// Load a thread chart and register it under its file name
+///
+/// Returns the registered name.
+#[cfg(feature = "fs")]
+pub fn register_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let threads = load_file(path)?;
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Custom")
+        .to_string();
+    register(name.clone(), threads);
+    Ok(name)
+}
+
+/// Threads of a registered palette
+pub fn get(name: &str) -> Option<Vec<EmbThread>> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&name.trim().to_lowercase())
+        .map(|(_, threads)| threads.clone())
+}
+
+/// Remove a registered palette, returning whether it existed
+pub fn unregister(name: &str) -> bool {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&name.trim().to_lowercase())
+        .is_some()
+}
+
+/// Names of the registered palettes, sorted case-insensitively
+pub fn names() -> Vec<String> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Split a delimited line, honouring double quotes
+fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+fn is_comment(line: &str) -> bool {
+    line.is_empty() || line.starts_with('#') || line.starts_with("//")
+}
+
+/// Color from an explicit `#RRGGBB` or `0xRRGGBB` field
+fn prefixed_hex(field: &str) -> Option<u32> {
+    let hex = field
+        .strip_prefix('#')
+        .or_else(|| field.strip_prefix("0x"))
+        .or_else(|| field.strip_prefix("0X"))?;
+    (hex.len() == 6)
+        .then(|| u32::from_str_radix(hex, 16).ok())
+        .flatten()
+}
+
+/// Thread from `code, name, color` fields, the color being a hex field or R, G, B
+fn positional_thread(fields: &[String]) -> Option<EmbThread> {
+    let component = |f: &String| f.parse::<u8>().ok();
+    let (color, text_fields) =
+        if let Some(i) = fields.iter().position(|f| prefixed_hex(f).is_some()) {
+            (prefixed_hex(&fields[i])?, &fields[..i])
+        } else {
+            let i = (0..fields.len().saturating_sub(2))
+                .rev()
+                .find(|&i| fields[i..i + 3].iter().all(|f| component(f).is_some()))?;
+            let (r, g, b) = (
+                component(&fields[i])?,
+                component(&fields[i + 1])?,
+                component(&fields[i + 2])?,
+            );
+            (u32::from_be_bytes([0, r, g, b]), &fields[..i])
+        };
+
+    let mut thread = EmbThread::new(color);
+    let mut text = text_fields.iter().filter(|f| !f.is_empty());
+    if let Some(code) = text.next() {
+        thread = thread.with_catalog_number(code.as_str());
+    }
+    if let Some(name) = text.next() {
+        thread = thread.with_description(name.as_str());
+    }
+    Some(thread)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<EmbThread>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim().trim_start_matches('\u{feff}')))
+        .filter(|(_, line)| !is_comment(line))
+        .peekable();
+
+    let header: Option<Vec<String>> = lines.peek().and_then(|(_, line)| {
+        let fields: Vec<String> = split_fields(line, ',')
+            .into_iter()
+            .map(|f| f.to_lowercase())
+            .collect();
+        fields
+            .iter()
+            .any(|f| matches!(f.as_str(), "hex" | "color" | "colour" | "rgb" | "r" | "red"))
+            .then_some(fields)
+    });
+    if header.is_some() {
+        lines.next();
+    }
+
+    let mut threads = Vec::new();
+    for (line_number, line) in lines {
+        let fields = split_fields(line, ',');
+        let thread = match &header {
+            Some(header) => header_thread(header, &fields, line_number)?,
+            None => positional_thread(&fields).ok_or_else(|| {
+                Error::Parse(format!("CSV palette: No color on line {}", line_number))
+            })?,
+        };
+        threads.push(thread);
+    }
+    Ok(threads)
+}
+
+/// Thread from a CSV row with named columns
+fn header_thread(header: &[String], fields: &[String], line_number: usize) -> Result<EmbThread> {
+    let column = |names: &[&str]| {
+        header
+            .iter()
+            .position(|h| names.contains(&h.as_str()))
+            .and_then(|i| fields.get(i))
+            .filter(|f| !f.is_empty())
+    };
+    let component = |names: &[&str]| -> Result<Option<u8>> {
+        column(names)
+            .map(|f| {
+                f.parse::<u8>().map_err(|_| {
+                    Error::Parse(format!(
+                        "CSV palette: Invalid color component '{}' on line {}",
+                        f, line_number
+                    ))
+                })
+            })
+            .transpose()
+    };
+
+    let color = if let Some(hex) = column(&["hex", "color", "colour", "rgb"]) {
+        parse_color_string(hex).map_err(|_| {
+            Error::Parse(format!(
+                "CSV palette: Invalid color '{}' on line {}",
+                hex, line_number
+            ))
+        })?
+    } else {
+        match (
+            component(&["r", "red"])?,
+            component(&["g", "green"])?,
+            component(&["b", "blue"])?,
+        ) {
+            (Some(r), Some(g), Some(b)) => u32::from_be_bytes([0, r, g, b]),
+            _ => {
+                return Err(Error::Parse(format!(
+                    "CSV palette: No color on line {}",
+                    line_number
+                )))
+            }
+        }
+    };
+
+    let mut thread = EmbThread::new(color);
+    thread.catalog_number = column(&["catalog", "catalog_number", "code", "number", "id"]).cloned();
+    thread.description = column(&["name", "description"]).cloned();
+    thread.brand = column(&["brand", "manufacturer"]).cloned();
+    thread.chart = column(&["chart"]).cloned();
+    thread.weight = column(&["weight"]).cloned();
+    Ok(thread)
+}
+
+fn parse_json(text: &str) -> Result<Vec<EmbThread>> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| Error::Parse(format!("JSON palette: {}", e)))?;
+    let entries = match &value {
+        Value::Array(entries) => entries,
+        Value::Object(object) => match object.get("threads") {
+            Some(Value::Array(entries)) => entries,
+            _ => {
+                return Err(Error::Parse(
+                    "JSON palette: Expected a 'threads' array".to_string(),
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::Parse(
+                "JSON palette: Expected an array of threads".to_string(),
+            ))
+        }
+    };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            json_thread(entry).ok_or_else(|| {
+                Error::Parse(format!("JSON palette: Thread {} has no valid color", i + 1))
+            })
+        })
+        .collect()
+}
+
+fn json_thread(entry: &Value) -> Option<EmbThread> {
+    let text = |names: &[&str]| {
+        names.iter().find_map(|name| match entry.get(name)? {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+    };
+    let component = |name: &str| entry.get(name)?.as_u64().and_then(|v| u8::try_from(v).ok());
+
+    let color = match entry.get("color").or_else(|| entry.get("hex")) {
+        Some(Value::String(s)) => parse_color_string(s).ok()?,
+        Some(Value::Number(n)) => u32::try_from(n.as_u64()?).ok().filter(|&c| c <= 0xFFFFFF)?,
+        _ => u32::from_be_bytes([0, component("r")?, component("g")?, component("b")?]),
+    };
+
+    let mut thread = EmbThread::new(color);
+    thread.catalog_number = text(&["catalog_number", "catalog", "code"]);
+    thread.description = text(&["description", "name"]);
+    thread.brand = text(&["brand"]);
+    thread.chart = text(&["chart"]);
+    thread.weight = text(&["weight"]);
+    Some(thread)
+}
+
+fn parse_gs(text: &str) -> Result<Vec<EmbThread>> {
+    Ok(text
+        .lines()
+        .map(|line| line.trim().trim_start_matches('\u{feff}'))
+        .filter(|line| !is_comment(line))
+        .filter_map(|line| {
+            let delimiter = ['\t', ';', ','].into_iter().find(|&d| line.contains(d))?;
+            positional_thread(&split_fields(line, delimiter))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn load_str(text: &str, format: CustomPaletteFormat) -> Result<Vec<EmbThread>> {
+        load(&mut Cursor::new(text), format)
+    }
+
+    #[test]
+    fn test_csv_with_and_without_header() {
+        let csv = "Code,Name,R,G,B,Brand\n\
+                   1801,\"Red, Bright\",200,16,46,Madeira\n\
+                   1800,Black,0,0,0,Madeira\n";
+        let threads = load_str(csv, CustomPaletteFormat::Csv).unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].color, 0xC8102E);
+        assert_eq!(threads[0].description.as_deref(), Some("Red, Bright"));
+        assert_eq!(threads[0].catalog_number.as_deref(), Some("1801"));
+        assert_eq!(threads[0].brand.as_deref(), Some("Madeira"));
+
+        let plain = "# My chart\n100,Navy,#000080\n101,Gold,255,215,0\n";
+        let threads = load_str(plain, CustomPaletteFormat::Csv).unwrap();
+        assert_eq!(threads[0].color, 0x000080);
+        assert_eq!(threads[1].color, 0xFFD700);
+        assert_eq!(threads[1].description.as_deref(), Some("Gold"));
+
+        let bad = "name,hex\nRed,zzz\n";
+        assert!(load_str(bad, CustomPaletteFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn test_json_palette() {
+        let json = r##"{
+            "name": "Studio",
+            "threads": [
+                {"color": "#112233", "name": "Slate", "catalog": 42},
+                {"r": 255, "g": 0, "b": 0, "description": "Red", "brand": "Isacord"},
+                {"color": 65280}
+            ]
+        }"##;
+        let threads = load_str(json, CustomPaletteFormat::Json).unwrap();
+        assert_eq!(threads.len(), 3);
+        assert_eq!(threads[0].color, 0x112233);
+        assert_eq!(threads[0].catalog_number.as_deref(), Some("42"));
+        assert_eq!(threads[1].brand.as_deref(), Some("Isacord"));
+        assert_eq!(threads[2].color, 0x00FF00);
+
+        assert!(load_str(r#"[{"name": "No color"}]"#, CustomPaletteFormat::Json).is_err());
+        assert!(load_str("[]", CustomPaletteFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_gs_palette() {
+        let gs = "Great Notions Rayon 40\n\
+                  \n\
+                  1001\tWhite\t255\t255\t255\n\
+                  1005;Black;0;0;0\n\
+                  1147,Christmas Red,200,16,46\n";
+        let threads = load_str(gs, CustomPaletteFormat::Gs).unwrap();
+        assert_eq!(threads.len(), 3);
+        assert_eq!(threads[0].color, 0xFFFFFF);
+        assert_eq!(threads[1].catalog_number.as_deref(), Some("1005"));
+        assert_eq!(threads[2].description.as_deref(), Some("Christmas Red"));
+    }
+
+    #[test]
+    fn test_registry() {
+        register("Test Registry Palette", vec![EmbThread::new(0x123456)]);
+        assert!(names().contains(&"Test Registry Palette".to_string()));
+        assert_eq!(get(" test registry palette ").unwrap()[0].color, 0x123456);
+        assert!(unregister("TEST REGISTRY PALETTE"));
+        assert!(get("Test Registry Palette").is_none());
+        assert!(!unregister("Test Registry Palette"));
+    }
+}
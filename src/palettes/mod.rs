@@ -1,17 +1,33 @@
 //! Thread color palettes for various embroidery machine brands
 //!
 //! This module contains pre-defined thread color palettes for different
-//! embroidery machine brands and models.
+//! embroidery machine brands and models, charts of commercial thread lines,
+//! and a loader for custom palettes.
+
+/// Custom thread palettes loaded from CSV, JSON and GS files
+pub mod custom;
 
 /// Husqvarna Viking HUS palette (29 colors)
 pub mod thread_hus;
 
+/// Isacord 40 thread chart (44 colors)
+pub mod thread_isacord;
+
 /// Janome JEF palette (79 colors)
 pub mod thread_jef;
 
+/// Madeira Polyneon 40 thread chart (40 colors)
+pub mod thread_madeira_polyneon;
+
+/// Madeira Classic Rayon 40 thread chart (40 colors)
+pub mod thread_madeira_rayon;
+
 /// Brother PEC palette (64 colors)
 pub mod thread_pec;
 
+/// Robison-Anton Super Strength Rayon thread chart (34 colors)
+pub mod thread_robison_anton;
+
 /// Janome SEW palette (79 colors)
 pub mod thread_sew;
 
@@ -0,0 +1,89 @@
+//! Isacord 40 thread chart
+//!
+//! Contains 44 threads covering the core colors of Amann's Isacord No. 40 polyester line,
+//! with catalog numbers and RGB values approximating the printed shade card.
+
+use crate::core::thread::EmbThread;
+
+/// Get the Isacord 40 thread palette
+pub fn get_thread_set() -> Vec<EmbThread> {
+    [
+        ("0010", "Silky White", 0xF6F5EE),
+        ("0015", "White", 0xFFFFFF),
+        ("0020", "Black", 0x000000),
+        ("0111", "Whale", 0x6D6F72),
+        ("0132", "Dark Pewter", 0x4A4D52),
+        ("0142", "Sterling", 0x9EA2A6),
+        ("0150", "Mystik Grey", 0xBFC1C2),
+        ("0600", "Citrus", 0xF5E23A),
+        ("0605", "Daffodil", 0xF7D43E),
+        ("0700", "Bright Yellow", 0xFFD200),
+        ("0824", "Sunflower", 0xF5B21A),
+        ("0940", "Autumn Leaf", 0xE37F22),
+        ("1102", "Pumpkin", 0xEE7623),
+        ("1106", "Orange Peel", 0xF26B21),
+        ("1300", "Dark Rust", 0xA03A22),
+        ("1703", "Poppy", 0xE03C31),
+        ("1902", "Poinsettia", 0xC8102E),
+        ("1903", "Lipstick", 0xBA0C2F),
+        ("1904", "Cardinal", 0xA6192E),
+        ("2011", "Fire Engine", 0xD22630),
+        ("2115", "Beet Red", 0x7D1E3A),
+        ("2222", "Burgundy", 0x6A1C2B),
+        ("2520", "Garden Rose", 0xF18AAD),
+        ("2560", "Iced Pink", 0xF8CCD8),
+        ("2711", "Dark Current", 0x3B2147),
+        ("2905", "Purple", 0x5B2C83),
+        ("3110", "Cachet", 0x9A86C4),
+        ("3541", "Ocean Blue", 0x3D6FB6),
+        ("3600", "Nordic Blue", 0x1E3F8C),
+        ("3622", "Imperial Blue", 0x1A2E6B),
+        ("3644", "Dark Navy", 0x14213D),
+        ("3815", "Reef Blue", 0x7FB3DB),
+        ("3910", "Crystal Blue", 0xA8D0EC),
+        ("4111", "Turquoise", 0x00A5B5),
+        ("4515", "Teal", 0x00707A),
+        ("5233", "Bright Mint", 0x7CCFA2),
+        ("5324", "Bright Green", 0x3AA935),
+        ("5335", "Irish Green", 0x007A3D),
+        ("5374", "Forest Green", 0x1F4D2E),
+        ("5833", "Lime", 0xA4C639),
+        ("1154", "Ginger", 0xB06A2C),
+        ("1352", "Stone", 0xC8B79A),
+        ("1876", "Chocolate", 0x4B2E22),
+        ("0853", "Pecan", 0x8A5A33),
+    ]
+    .into_iter()
+    .map(|(catalog, name, color)| {
+        EmbThread::new(color)
+            .with_description(name)
+            .with_catalog_number(catalog)
+            .with_brand("Isacord")
+            .with_chart("Isacord 40")
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_isacord_palette() {
+        let palette = get_thread_set();
+        assert_eq!(palette.len(), 44);
+
+        let catalog: HashSet<_> = palette.iter().map(|t| t.catalog_number.clone()).collect();
+        assert_eq!(catalog.len(), palette.len());
+        assert!(palette
+            .iter()
+            .all(|t| t.brand.as_deref() == Some("Isacord")));
+
+        let thread = palette
+            .iter()
+            .find(|t| t.catalog_number.as_deref() == Some("1902"))
+            .unwrap();
+        assert_eq!(thread.color, 0xC8102E);
+    }
+}
@@ -0,0 +1,86 @@
+//! Madeira Polyneon 40 thread chart
+//!
+//! Contains 40 threads covering the core colors of Madeira's Polyneon No. 40 polyester line, which
+//! matches the Classic Rayon shade card with catalog numbers 800 higher,
+//! with catalog numbers and RGB values approximating the printed shade card.
+
+use crate::core::thread::EmbThread;
+
+/// Get the Madeira Polyneon 40 thread palette
+pub fn get_thread_set() -> Vec<EmbThread> {
+    [
+        ("1800", "Black", 0x000000),
+        ("1801", "White", 0xFFFFFF),
+        ("1802", "Snow White", 0xF4F4EE),
+        ("1805", "Ecru", 0xE8DFC8),
+        ("1810", "Silver Grey", 0xC2C4C6),
+        ("1811", "Steel Grey", 0x8C9094),
+        ("1841", "Dark Grey", 0x55585C),
+        ("1823", "Yellow", 0xFCD116),
+        ("1824", "Goldenrod", 0xF0A81E),
+        ("1825", "Mine Gold", 0xD19A2A),
+        ("1861", "Pale Yellow", 0xF9EB9C),
+        ("1865", "Orange", 0xF47920),
+        ("1878", "Tangerine", 0xF26A21),
+        ("1821", "Light Orange", 0xF9A65A),
+        ("1837", "Red", 0xD0202E),
+        ("1947", "Christmas Red", 0xC8102E),
+        ("1981", "Dark Red", 0x8F1A2C),
+        ("1835", "Burgundy", 0x6E1E2C),
+        ("1908", "Pink", 0xF4A6C0),
+        ("1909", "Hot Pink", 0xE2418A),
+        ("1917", "Baby Pink", 0xF8C8D4),
+        ("1832", "Purple", 0x5C2D82),
+        ("1833", "Lilac", 0xA58FC4),
+        ("1880", "Violet", 0x7E4E9E),
+        ("1934", "Sky Blue", 0x8CC3E6),
+        ("1876", "Royal Blue", 0x1F4FA3),
+        ("1943", "Navy", 0x1D2A4D),
+        ("1966", "Denim", 0x4B6E9C),
+        ("1896", "Turquoise", 0x00A3AD),
+        ("1890", "Teal", 0x00737A),
+        ("1849", "Grass Green", 0x3F9E3A),
+        ("1851", "Christmas Green", 0x00693C),
+        ("1903", "Dark Green", 0x1E4D2B),
+        ("1969", "Mint", 0x9AD3B4),
+        ("2048", "Lime", 0xA8CF45),
+        ("1856", "Tan", 0xC49A6C),
+        ("1857", "Brown", 0x7B4A2A),
+        ("1859", "Dark Brown", 0x4A2C1D),
+        ("1884", "Beige", 0xD9C3A0),
+        ("1955", "Coffee", 0x6F4E37),
+    ]
+    .into_iter()
+    .map(|(catalog, name, color)| {
+        EmbThread::new(color)
+            .with_description(name)
+            .with_catalog_number(catalog)
+            .with_brand("Madeira")
+            .with_chart("Polyneon 40")
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_polyneon_palette() {
+        let palette = get_thread_set();
+        assert_eq!(palette.len(), 40);
+
+        let catalog: HashSet<_> = palette.iter().map(|t| t.catalog_number.clone()).collect();
+        assert_eq!(catalog.len(), palette.len());
+        assert!(palette
+            .iter()
+            .all(|t| t.brand.as_deref() == Some("Madeira")));
+
+        let thread = palette
+            .iter()
+            .find(|t| t.catalog_number.as_deref() == Some("1947"))
+            .unwrap();
+        assert_eq!(thread.color, 0xC8102E);
+    }
+}
@@ -0,0 +1,85 @@
+//! Madeira Classic Rayon 40 thread chart
+//!
+//! Contains 40 threads covering the core colors of Madeira's Classic Rayon No. 40 line,
+//! with catalog numbers and RGB values approximating the printed shade card.
+
+use crate::core::thread::EmbThread;
+
+/// Get the Madeira Classic Rayon 40 thread palette
+pub fn get_thread_set() -> Vec<EmbThread> {
+    [
+        ("1000", "Black", 0x000000),
+        ("1001", "White", 0xFFFFFF),
+        ("1002", "Snow White", 0xF4F4EE),
+        ("1005", "Ecru", 0xE8DFC8),
+        ("1010", "Silver Grey", 0xC2C4C6),
+        ("1011", "Steel Grey", 0x8C9094),
+        ("1041", "Dark Grey", 0x55585C),
+        ("1023", "Yellow", 0xFCD116),
+        ("1024", "Goldenrod", 0xF0A81E),
+        ("1025", "Mine Gold", 0xD19A2A),
+        ("1061", "Pale Yellow", 0xF9EB9C),
+        ("1065", "Orange", 0xF47920),
+        ("1078", "Tangerine", 0xF26A21),
+        ("1021", "Light Orange", 0xF9A65A),
+        ("1037", "Red", 0xD0202E),
+        ("1147", "Christmas Red", 0xC8102E),
+        ("1181", "Dark Red", 0x8F1A2C),
+        ("1035", "Burgundy", 0x6E1E2C),
+        ("1108", "Pink", 0xF4A6C0),
+        ("1109", "Hot Pink", 0xE2418A),
+        ("1117", "Baby Pink", 0xF8C8D4),
+        ("1032", "Purple", 0x5C2D82),
+        ("1033", "Lilac", 0xA58FC4),
+        ("1080", "Violet", 0x7E4E9E),
+        ("1134", "Sky Blue", 0x8CC3E6),
+        ("1076", "Royal Blue", 0x1F4FA3),
+        ("1143", "Navy", 0x1D2A4D),
+        ("1166", "Denim", 0x4B6E9C),
+        ("1096", "Turquoise", 0x00A3AD),
+        ("1090", "Teal", 0x00737A),
+        ("1049", "Grass Green", 0x3F9E3A),
+        ("1051", "Christmas Green", 0x00693C),
+        ("1103", "Dark Green", 0x1E4D2B),
+        ("1169", "Mint", 0x9AD3B4),
+        ("1248", "Lime", 0xA8CF45),
+        ("1056", "Tan", 0xC49A6C),
+        ("1057", "Brown", 0x7B4A2A),
+        ("1059", "Dark Brown", 0x4A2C1D),
+        ("1084", "Beige", 0xD9C3A0),
+        ("1155", "Coffee", 0x6F4E37),
+    ]
+    .into_iter()
+    .map(|(catalog, name, color)| {
+        EmbThread::new(color)
+            .with_description(name)
+            .with_catalog_number(catalog)
+            .with_brand("Madeira")
+            .with_chart("Classic Rayon 40")
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_madeira_rayon_palette() {
+        let palette = get_thread_set();
+        assert_eq!(palette.len(), 40);
+
+        let catalog: HashSet<_> = palette.iter().map(|t| t.catalog_number.clone()).collect();
+        assert_eq!(catalog.len(), palette.len());
+        assert!(palette
+            .iter()
+            .all(|t| t.brand.as_deref() == Some("Madeira")));
+
+        let thread = palette
+            .iter()
+            .find(|t| t.catalog_number.as_deref() == Some("1147"))
+            .unwrap();
+        assert_eq!(thread.color, 0xC8102E);
+    }
+}
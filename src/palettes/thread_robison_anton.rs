@@ -0,0 +1,79 @@
+//! Robison-Anton Super Strength Rayon thread chart
+//!
+//! Contains 34 threads covering the core colors of Robison-Anton's Super Strength Rayon No. 40 line,
+//! with catalog numbers and RGB values approximating the printed shade card.
+
+use crate::core::thread::EmbThread;
+
+/// Get the Robison-Anton Super Strength Rayon thread palette
+pub fn get_thread_set() -> Vec<EmbThread> {
+    [
+        ("2296", "Black", 0x000000),
+        ("2297", "White", 0xFFFFFF),
+        ("2298", "Snow White", 0xF5F5EF),
+        ("2244", "Silver", 0xC0C2C4),
+        ("2245", "Grey", 0x8B8E92),
+        ("2246", "Charcoal", 0x4C4F53),
+        ("2211", "Lemon", 0xF8E71C),
+        ("2218", "Canary", 0xFFD400),
+        ("2273", "Gold", 0xE4A62B),
+        ("2274", "Old Gold", 0xC79A3A),
+        ("2271", "Tangerine", 0xF47A20),
+        ("2268", "Orange", 0xF26522),
+        ("2222", "Red", 0xD32030),
+        ("2223", "Scarlet", 0xC41E3A),
+        ("2241", "Burgundy", 0x731F2F),
+        ("2248", "Rust", 0xA7421E),
+        ("2221", "Pink", 0xF4A7C2),
+        ("2233", "Fuchsia", 0xD6337A),
+        ("2264", "Rose", 0xE87A9D),
+        ("2254", "Purple", 0x5E2F86),
+        ("2256", "Lilac", 0xB59BCB),
+        ("2277", "Sky Blue", 0x8FC5E8),
+        ("2288", "Light Blue", 0xB5D7EE),
+        ("2224", "Royal Blue", 0x1E50A2),
+        ("2225", "Navy", 0x1B2A4C),
+        ("2253", "Turquoise", 0x00A6B0),
+        ("2230", "Kelly Green", 0x2E9E45),
+        ("2232", "Emerald", 0x00774A),
+        ("2251", "Lime", 0xA9CF46),
+        ("2266", "Mint", 0x9CD4B5),
+        ("2263", "Jade", 0x00806A),
+        ("2234", "Brown", 0x6E4328),
+        ("2235", "Tan", 0xC59C6D),
+        ("2226", "Beige", 0xDAC6A3),
+    ]
+    .into_iter()
+    .map(|(catalog, name, color)| {
+        EmbThread::new(color)
+            .with_description(name)
+            .with_catalog_number(catalog)
+            .with_brand("Robison-Anton")
+            .with_chart("Super Strength Rayon 40")
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_robison_anton_palette() {
+        let palette = get_thread_set();
+        assert_eq!(palette.len(), 34);
+
+        let catalog: HashSet<_> = palette.iter().map(|t| t.catalog_number.clone()).collect();
+        assert_eq!(catalog.len(), palette.len());
+        assert!(palette
+            .iter()
+            .all(|t| t.brand.as_deref() == Some("Robison-Anton")));
+
+        let thread = palette
+            .iter()
+            .find(|t| t.catalog_number.as_deref() == Some("2296"))
+            .unwrap();
+        assert_eq!(thread.color, 0x000000);
+    }
+}
@@ -0,0 +1,450 @@
+//! Python bindings for Butabuti
+//!
+//! Exposes patterns, threads, file I/O and transforms to Python through PyO3,
+//! following pyembroidery's names and argument order so existing scripts can
+//! switch backends with few changes:
+//!
+//! - `read(filename)` / `write(pattern, filename)` choose the format from the
+//!   extension; `read_bytes` / `write_bytes` work on in-memory data.
+//! - `EmbPattern.stitches` is a list of `(x, y, command)` tuples and
+//!   `EmbPattern.threadlist` a list of `EmbThread`. Both are copies: change a
+//!   pattern through its methods.
+//! - Command constants (`STITCH`, `JUMP`, `TRIM`, ...) have pyembroidery's values.
+//!
+//! Build the extension module with `maturin build --features python`.
+//!
+//! # Example
+//!
+//! ```python
+//! import butabuti
+//!
+//! pattern = butabuti.read("design.pes")
+//! pattern.move_center_to_origin()
+//! pattern.rotate(90)
+//! print(pattern.count_stitches(), pattern.bounds())
+//! butabuti.write(pattern, "design.dst")
+//! ```
+
+// PyO3's generated wrappers convert `PyResult` errors into `PyErr` again
+#![allow(clippy::useless_conversion)]
+
+use crate::core::constants;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, ErrorKind};
+use pyo3::exceptions::{PyIOError, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+impl From<Error> for PyErr {
+    fn from(err: Error) -> PyErr {
+        match err.kind() {
+            ErrorKind::Io(_) => PyIOError::new_err(err.to_string()),
+            ErrorKind::ThreadIndexOutOfBounds(_) => PyIndexError::new_err(err.to_string()),
+            _ => PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+/// Color from an int (0xRRGGBB) or a string ("#RRGGBB", "red", ...)
+fn color_from_py(color: &Bound<'_, PyAny>) -> PyResult<u32> {
+    if let Ok(value) = color.extract::<u32>() {
+        return Ok(value & 0xFFFFFF);
+    }
+    let text: String = color.extract()?;
+    Ok(EmbThread::from_string(&text)?.color)
+}
+
+/// Thread from an `EmbThread`, an int or a color string
+fn thread_from_py(thread: &Bound<'_, PyAny>) -> PyResult<EmbThread> {
+    if let Ok(thread) = thread.extract::<PyRef<'_, PyEmbThread>>() {
+        return Ok(thread.inner.clone());
+    }
+    Ok(EmbThread::new(color_from_py(thread)?))
+}
+
+/// Thread color and catalog information
+#[pyclass(name = "EmbThread", module = "butabuti")]
+#[derive(Clone)]
+pub struct PyEmbThread {
+    /// Wrapped thread
+    pub inner: EmbThread,
+}
+
+#[pymethods]
+impl PyEmbThread {
+    #[new]
+    #[pyo3(signature = (color = None, description = None, catalog_number = None))]
+    fn new(
+        color: Option<&Bound<'_, PyAny>>,
+        description: Option<String>,
+        catalog_number: Option<String>,
+    ) -> PyResult<Self> {
+        let mut inner = EmbThread::new(color.map(color_from_py).transpose()?.unwrap_or(0));
+        inner.description = description;
+        inner.catalog_number = catalog_number;
+        Ok(Self { inner })
+    }
+
+    /// Color as 0xRRGGBB
+    #[getter]
+    fn color(&self) -> u32 {
+        self.inner.color
+    }
+
+    #[setter(color)]
+    fn set_color_value(&mut self, color: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.color = color_from_py(color)?;
+        Ok(())
+    }
+
+    /// Set the color from red, green and blue components
+    fn set_color(&mut self, r: u8, g: u8, b: u8) {
+        self.inner.color = EmbThread::from_rgb(r, g, b).color;
+    }
+
+    /// Set the color from a hex string
+    fn set_hex_color(&mut self, hex: &str) -> PyResult<()> {
+        Ok(self.inner.set_hex_color(hex)?)
+    }
+
+    /// Color as "#RRGGBB"
+    fn hex_color(&self) -> String {
+        self.inner.hex_color()
+    }
+
+    fn get_red(&self) -> u8 {
+        self.inner.red()
+    }
+
+    fn get_green(&self) -> u8 {
+        self.inner.green()
+    }
+
+    fn get_blue(&self) -> u8 {
+        self.inner.blue()
+    }
+
+    #[getter]
+    fn description(&self) -> Option<String> {
+        self.inner.description.clone()
+    }
+
+    #[setter]
+    fn set_description(&mut self, value: Option<String>) {
+        self.inner.description = value;
+    }
+
+    #[getter]
+    fn catalog_number(&self) -> Option<String> {
+        self.inner.catalog_number.clone()
+    }
+
+    #[setter]
+    fn set_catalog_number(&mut self, value: Option<String>) {
+        self.inner.catalog_number = value;
+    }
+
+    #[getter]
+    fn brand(&self) -> Option<String> {
+        self.inner.brand.clone()
+    }
+
+    #[setter]
+    fn set_brand(&mut self, value: Option<String>) {
+        self.inner.brand = value;
+    }
+
+    #[getter]
+    fn chart(&self) -> Option<String> {
+        self.inner.chart.clone()
+    }
+
+    #[setter]
+    fn set_chart(&mut self, value: Option<String>) {
+        self.inner.chart = value;
+    }
+
+    #[getter]
+    fn weight(&self) -> Option<String> {
+        self.inner.weight.clone()
+    }
+
+    #[setter]
+    fn set_weight(&mut self, value: Option<String>) {
+        self.inner.weight = value;
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.inner.description {
+            Some(description) => {
+                format!("EmbThread('{}', '{}')", self.inner.hex_color(), description)
+            }
+            None => format!("EmbThread('{}')", self.inner.hex_color()),
+        }
+    }
+}
+
+/// Embroidery pattern: stitches, threads and metadata
+#[pyclass(name = "EmbPattern", module = "butabuti")]
+#[derive(Clone)]
+pub struct PyEmbPattern {
+    /// Wrapped pattern
+    pub inner: EmbPattern,
+}
+
+#[pymethods]
+impl PyEmbPattern {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: EmbPattern::new(),
+        }
+    }
+
+    /// Read a file, choosing the format from its extension
+    #[staticmethod]
+    fn read(filename: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: EmbPattern::read(filename)?,
+        })
+    }
+
+    /// Write to a file, choosing the format from its extension
+    fn write(&self, filename: &str) -> PyResult<()> {
+        Ok(self.inner.write(filename)?)
+    }
+
+    /// Read a pattern of the given format from bytes
+    #[staticmethod]
+    fn from_bytes(data: &[u8], format: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: crate::formats::io::read_from_bytes(data, format)?,
+        })
+    }
+
+    /// Encode the pattern in the given format
+    fn to_bytes<'py>(&self, py: Python<'py>, format: &str) -> PyResult<Bound<'py, PyBytes>> {
+        let data = crate::formats::io::write_to_bytes(&self.inner, format)?;
+        Ok(PyBytes::new_bound(py, &data))
+    }
+
+    /// Copy of the stitches as (x, y, command) tuples
+    #[getter]
+    fn stitches(&self) -> Vec<(f64, f64, u32)> {
+        self.inner
+            .stitches()
+            .iter()
+            .map(|s| (s.x, s.y, s.command))
+            .collect()
+    }
+
+    /// Copy of the threads
+    #[getter]
+    fn threadlist(&self) -> Vec<PyEmbThread> {
+        self.inner
+            .threads()
+            .iter()
+            .map(|t| PyEmbThread { inner: t.clone() })
+            .collect()
+    }
+
+    /// Add a thread given as an EmbThread, an int or a color string
+    fn add_thread(&mut self, thread: &Bound<'_, PyAny>) -> PyResult<()> {
+        self.inner.add_thread(thread_from_py(thread)?);
+        Ok(())
+    }
+
+    /// Replace the thread at an index
+    fn set_thread(&mut self, index: usize, thread: &Bound<'_, PyAny>) -> PyResult<()> {
+        let thread = thread_from_py(thread)?;
+        let slot = self
+            .inner
+            .threads_mut()
+            .get_mut(index)
+            .ok_or_else(|| PyIndexError::new_err(format!("No thread at index {}", index)))?;
+        *slot = thread;
+        Ok(())
+    }
+
+    #[pyo3(signature = (command, x = 0.0, y = 0.0))]
+    fn add_stitch_absolute(&mut self, command: u32, x: f64, y: f64) {
+        self.inner.add_stitch_absolute(command, x, y);
+    }
+
+    #[pyo3(signature = (command, dx = 0.0, dy = 0.0))]
+    fn add_stitch_relative(&mut self, command: u32, dx: f64, dy: f64) {
+        self.inner.add_stitch_relative(dx, dy, command);
+    }
+
+    #[pyo3(signature = (command, x = 0.0, y = 0.0))]
+    fn add_command(&mut self, command: u32, x: f64, y: f64) {
+        self.inner.add_command(command, x, y);
+    }
+
+    #[pyo3(signature = (dx = 0.0, dy = 0.0))]
+    fn stitch(&mut self, dx: f64, dy: f64) {
+        self.inner.stitch(dx, dy);
+    }
+
+    fn stitch_abs(&mut self, x: f64, y: f64) {
+        self.inner.stitch_abs(x, y);
+    }
+
+    #[pyo3(name = "move", signature = (dx = 0.0, dy = 0.0))]
+    fn move_(&mut self, dx: f64, dy: f64) {
+        self.inner.jump(dx, dy);
+    }
+
+    fn move_abs(&mut self, x: f64, y: f64) {
+        self.inner.jump_abs(x, y);
+    }
+
+    fn trim(&mut self) {
+        self.inner.trim();
+    }
+
+    #[pyo3(signature = (dx = 0.0, dy = 0.0))]
+    fn color_change(&mut self, dx: f64, dy: f64) {
+        self.inner.color_change(dx, dy);
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn end(&mut self) {
+        self.inner.end();
+    }
+
+    /// (min_x, min_y, max_x, max_y) in 0.1mm
+    fn bounds(&self) -> (f64, f64, f64, f64) {
+        self.inner.bounds()
+    }
+
+    fn count_stitches(&self) -> usize {
+        self.inner.count_stitches()
+    }
+
+    fn count_color_changes(&self) -> usize {
+        self.inner.count_color_changes()
+    }
+
+    fn count_threads(&self) -> usize {
+        self.inner.threads().len()
+    }
+
+    #[pyo3(signature = (name, default = None))]
+    fn get_metadata(&self, name: &str, default: Option<String>) -> Option<String> {
+        self.inner.get_metadata(name).cloned().or(default)
+    }
+
+    fn set_metadata(&mut self, name: String, value: String) {
+        self.inner.set_metadata(name, value);
+    }
+
+    fn translate(&mut self, dx: f64, dy: f64) {
+        self.inner.translate(dx, dy);
+    }
+
+    fn move_center_to_origin(&mut self) {
+        self.inner.move_center_to_origin();
+    }
+
+    /// Rotate by degrees around the origin, or around (cx, cy)
+    #[pyo3(signature = (angle, cx = None, cy = None))]
+    fn rotate(&mut self, angle: f64, cx: Option<f64>, cy: Option<f64>) {
+        match (cx, cy) {
+            (None, None) => self.inner.rotate(angle),
+            (cx, cy) => self
+                .inner
+                .rotate_around_point(angle, cx.unwrap_or(0.0), cy.unwrap_or(0.0)),
+        }
+    }
+
+    /// Scale by sx and sy, or uniformly when sy is omitted
+    #[pyo3(signature = (sx, sy = None))]
+    fn scale(&mut self, sx: f64, sy: Option<f64>) {
+        self.inner.scale(sx, sy.unwrap_or(sx));
+    }
+
+    fn flip_horizontal(&mut self) {
+        self.inner.flip_horizontal();
+    }
+
+    fn flip_vertical(&mut self) {
+        self.inner.flip_vertical();
+    }
+
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    fn __len__(&self) -> usize {
+        self.inner.stitches().len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "EmbPattern({} stitches, {} threads)",
+            self.inner.count_stitches(),
+            self.inner.threads().len()
+        )
+    }
+}
+
+/// Read a file, choosing the format from its extension
+#[pyfunction]
+fn read(filename: &str) -> PyResult<PyEmbPattern> {
+    PyEmbPattern::read(filename)
+}
+
+/// Write a pattern to a file, choosing the format from its extension
+#[pyfunction]
+fn write(pattern: &PyEmbPattern, filename: &str) -> PyResult<()> {
+    pattern.write(filename)
+}
+
+/// Read a pattern of the given format from bytes
+#[pyfunction]
+fn read_bytes(data: &[u8], format: &str) -> PyResult<PyEmbPattern> {
+    PyEmbPattern::from_bytes(data, format)
+}
+
+/// Encode a pattern in the given format
+#[pyfunction]
+fn write_bytes<'py>(
+    py: Python<'py>,
+    pattern: &PyEmbPattern,
+    format: &str,
+) -> PyResult<Bound<'py, PyBytes>> {
+    pattern.to_bytes(py, format)
+}
+
+/// Python module definition
+#[pymodule]
+fn butabuti(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEmbPattern>()?;
+    m.add_class::<PyEmbThread>()?;
+    m.add_function(wrap_pyfunction!(read, m)?)?;
+    m.add_function(wrap_pyfunction!(write, m)?)?;
+    m.add_function(wrap_pyfunction!(read_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(write_bytes, m)?)?;
+
+    m.add("NO_COMMAND", -1)?;
+    m.add("STITCH", constants::STITCH)?;
+    m.add("JUMP", constants::JUMP)?;
+    m.add("TRIM", constants::TRIM)?;
+    m.add("STOP", constants::STOP)?;
+    m.add("END", constants::END)?;
+    m.add("COLOR_CHANGE", constants::COLOR_CHANGE)?;
+    m.add("SEQUIN_MODE", constants::SEQUIN_MODE)?;
+    m.add("SEQUIN_EJECT", constants::SEQUIN_EJECT)?;
+    m.add("NEEDLE_SET", constants::NEEDLE_SET)?;
+    m.add("COMMAND_MASK", constants::COMMAND_MASK)?;
+    Ok(())
+}
@@ -0,0 +1,633 @@
+//! Raster rendering of embroidery patterns
+//!
+//! Draws each stitch as an anti-aliased thread segment with round ends. Line width
+//! follows the thread weight, and optional shading darkens the edges and ends of
+//! each stitch so the preview reads like stitched thread rather than a line plot.
+//!
+//! Rendering is dependency-free. [`render_to_rgba`] returns the pixels in memory for
+//! compositing in GUIs and servers; the PNG writer
+//! ([`crate::formats::io::writers::png`]) encodes the same image.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::render::RenderOptions;
+//! use butabuti::formats::io::writers::png;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::from_rgb(200, 30, 30));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(100.0, 50.0);
+//!
+//! // 300 DPI preview on a transparent background
+//! let options = RenderOptions::default().dpi(300.0).background(None);
+//! let mut output = Vec::new();
+//! png::write_with_options(&pattern, &mut output, &options)?;
+//! assert_eq!(&output[..4], b"\x89PNG");
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::density::DensityMap;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, Result};
+
+/// Largest image the renderer will allocate, in pixels
+const MAX_RENDER_PIXELS: usize = 1 << 28;
+
+/// Thread weight that renders at exactly `thread_width`
+const REFERENCE_WEIGHT: f64 = 40.0;
+
+/// Options for rendering a pattern to pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    /// Pixels per millimeter (default: 10.0)
+    pub scale: f64,
+    /// Background color, or `None` for transparent (default: white)
+    pub background: Option<EmbThread>,
+    /// Width of a 40wt thread in millimeters (default: 0.4)
+    pub thread_width: f64,
+    /// Scale each thread's width by its weight, so 30wt renders thicker and 60wt
+    /// thinner (default: true)
+    pub weight_scaling: bool,
+    /// Shade stitches to look like twisted thread (default: true)
+    pub shading: bool,
+    /// Extra space around the design in pixels (default: 4)
+    pub margin: u32,
+    /// Draw 5mm ruler ticks along the top and left edges (default: false)
+    pub guides: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            scale: 10.0,
+            background: Some(EmbThread::from_rgb(255, 255, 255)),
+            thread_width: 0.4,
+            weight_scaling: true,
+            shading: true,
+            margin: 4,
+            guides: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Set the scale in pixels per millimeter
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the scale from a print resolution in dots per inch
+    pub fn dpi(mut self, dpi: f64) -> Self {
+        self.scale = dpi / 25.4;
+        self
+    }
+
+    /// Set the background color, or `None` for transparent
+    pub fn background(mut self, background: Option<EmbThread>) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Set the width of a 40wt thread in millimeters
+    pub fn thread_width(mut self, thread_width: f64) -> Self {
+        self.thread_width = thread_width;
+        self
+    }
+
+    /// Enable or disable per-thread weight scaling
+    pub fn weight_scaling(mut self, weight_scaling: bool) -> Self {
+        self.weight_scaling = weight_scaling;
+        self
+    }
+
+    /// Enable or disable thread shading
+    pub fn shading(mut self, shading: bool) -> Self {
+        self.shading = shading;
+        self
+    }
+
+    /// Set the margin in pixels
+    pub fn margin(mut self, margin: u32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Enable or disable ruler ticks
+    pub fn guides(mut self, guides: bool) -> Self {
+        self.guides = guides;
+        self
+    }
+
+    /// Rendered width of a thread in pixels
+    fn line_width(&self, thread: &EmbThread) -> f64 {
+        let weight = if self.weight_scaling {
+            thread
+                .weight
+                .as_deref()
+                .and_then(parse_weight)
+                .unwrap_or(REFERENCE_WEIGHT)
+        } else {
+            REFERENCE_WEIGHT
+        };
+        self.thread_width * self.scale * REFERENCE_WEIGHT / weight
+    }
+}
+
+/// Leading number of a thread weight such as "40wt" or "60"
+fn parse_weight(weight: &str) -> Option<f64> {
+    let digits: String = weight
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    digits
+        .parse::<f64>()
+        .ok()
+        .filter(|w| *w > 0.0)
+        .map(|w| w.clamp(10.0, 120.0))
+}
+
+/// Rendered RGBA image
+///
+/// Pixels are 8-bit RGBA with straight (non-premultiplied) alpha, stored row by
+/// row from the top left, ready for image libraries and GUI toolkits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    /// Width in pixels
+    pub width: usize,
+    /// Height in pixels
+    pub height: usize,
+    /// `width * height * 4` bytes of pixel data
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// RGBA value of the pixel at `(x, y)`, or `None` outside the image
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = (y * self.width + x) * 4;
+        self.pixels[idx..idx + 4].try_into().ok()
+    }
+
+    /// Take the pixel data, dropping the dimensions
+    pub fn into_raw(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Decode a PNG, JPEG or other image supported by the `image` crate
+    #[cfg(feature = "graphics")]
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let decoded = image::load_from_memory(data)
+            .map_err(|e| Error::parse(format!("Cannot decode image: {}", e)))?
+            .into_rgba8();
+        Ok(Self {
+            width: decoded.width() as usize,
+            height: decoded.height() as usize,
+            pixels: decoded.into_raw(),
+        })
+    }
+
+    fn new(width: usize, height: usize, background: Option<&EmbThread>) -> Self {
+        let fill = match background {
+            Some(bg) => [bg.red(), bg.green(), bg.blue(), 255],
+            None => [0, 0, 0, 0],
+        };
+        Self {
+            width,
+            height,
+            pixels: fill.repeat(width * height),
+        }
+    }
+
+    /// Composite `color` at `coverage` over the pixel
+    fn blend(&mut self, x: usize, y: usize, color: [f64; 3], coverage: f64) {
+        let idx = (y * self.width + x) * 4;
+        let dst_a = self.pixels[idx + 3] as f64 / 255.0;
+        let out_a = coverage + dst_a * (1.0 - coverage);
+        if out_a <= 0.0 {
+            return;
+        }
+        for (c, &src) in color.iter().enumerate() {
+            let dst = self.pixels[idx + c] as f64;
+            let value = (src * coverage + dst * dst_a * (1.0 - coverage)) / out_a;
+            self.pixels[idx + c] = value.round().clamp(0.0, 255.0) as u8;
+        }
+        self.pixels[idx + 3] = (out_a * 255.0).round() as u8;
+    }
+
+    /// Draw an anti-aliased segment with round ends
+    ///
+    /// Returns the touched pixel rectangle as `(x0, y0, x1, y1)`, end-exclusive.
+    fn draw_segment(
+        &mut self,
+        from: (f64, f64),
+        to: (f64, f64),
+        width: f64,
+        color: [f64; 3],
+        shading: bool,
+    ) -> (usize, usize, usize, usize) {
+        let half = (width / 2.0).max(0.5);
+        let reach = half + 1.0;
+        let x0 = (from.0.min(to.0) - reach).floor().max(0.0) as usize;
+        let y0 = (from.1.min(to.1) - reach).floor().max(0.0) as usize;
+        let x1 = ((from.0.max(to.0) + reach).ceil() as usize).min(self.width);
+        let y1 = ((from.1.max(to.1) + reach).ceil() as usize).min(self.height);
+
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length_sq = dx * dx + dy * dy;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (cx, cy) = (x as f64 + 0.5, y as f64 + 0.5);
+                let along = if length_sq > 0.0 {
+                    (((cx - from.0) * dx + (cy - from.1) * dy) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.5
+                };
+                let distance = (cx - (from.0 + dx * along)).hypot(cy - (from.1 + dy * along));
+                // Thin lines keep their weight as lower coverage
+                let coverage = (half + 0.5 - distance).clamp(0.0, 1.0) * width.min(1.0);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let shaded = if shading {
+                    shade(color, distance / half, along)
+                } else {
+                    color
+                };
+                self.blend(x, y, shaded, coverage);
+            }
+        }
+        (x0, y0, x1.max(x0), y1.max(y0))
+    }
+}
+
+/// Light the middle of a stitch and darken its edges and ends
+fn shade(color: [f64; 3], across: f64, along: f64) -> [f64; 3] {
+    let across = across.min(1.0);
+    let ends = (along * std::f64::consts::PI).sin();
+    let light = 0.55 + 0.6 * (1.0 - across * across) * (0.7 + 0.3 * ends);
+
+    // Black thread would stay black; lift it so the highlight shows
+    let base = if color.iter().all(|&c| c < 40.0) {
+        [40.0, 40.0, 40.0]
+    } else {
+        color
+    };
+    base.map(|c| {
+        if light > 1.0 {
+            c + (255.0 - c) * (light - 1.0)
+        } else {
+            c * light
+        }
+    })
+}
+
+/// Render a pattern to an in-memory RGBA image
+///
+/// The image covers the pattern's bounds plus room for the thread width and
+/// [`RenderOptions::margin`]; an empty pattern renders as a single background pixel.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::render::{render_to_rgba, RenderOptions};
+///
+/// let mut pattern = EmbPattern::new();
+/// pattern.stitch_abs(0.0, 0.0);
+/// pattern.stitch_abs(100.0, 0.0);
+///
+/// let image = render_to_rgba(&pattern, &RenderOptions::default())?;
+/// assert_eq!(image.pixels.len(), image.width * image.height * 4);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn render_to_rgba(pattern: &EmbPattern, options: &RenderOptions) -> Result<RgbaImage> {
+    let mut scene = Scene::new(pattern, options)?;
+    while scene.draw_next().is_some() {}
+    Ok(scene.into_image())
+}
+
+/// Render a density map as a heat map, `cell_pixels` wide per cell
+///
+/// Cells shade from green through yellow to red as their density approaches
+/// `max_density` stitches per cm²; denser cells are red and empty cells
+/// transparent, so the image can be laid over a rendered pattern of the same
+/// scale.
+pub fn render_density_map(
+    map: &DensityMap,
+    max_density: f64,
+    cell_pixels: usize,
+) -> Result<RgbaImage> {
+    let cell_pixels = cell_pixels.max(1);
+    let width = map.columns.max(1).saturating_mul(cell_pixels);
+    let height = map.rows.max(1).saturating_mul(cell_pixels);
+    if width.saturating_mul(height) > MAX_RENDER_PIXELS {
+        return Err(Error::InvalidPattern(format!(
+            "Rendered image would be {}x{} pixels; reduce the cell size",
+            width, height
+        )));
+    }
+
+    let mut image = RgbaImage::new(width, height, None);
+    for row in 0..map.rows {
+        for column in 0..map.columns {
+            let density = map.density(column, row).unwrap_or(0.0);
+            if density <= 0.0 {
+                continue;
+            }
+            let color = heat_color(density / max_density);
+            for y in row * cell_pixels..(row + 1) * cell_pixels {
+                let start = (y * width + column * cell_pixels) * 4;
+                for pixel in image.pixels[start..start + cell_pixels * 4].chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&color);
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Green at 0, yellow at 0.5 and red from 1 up
+fn heat_color(level: f64) -> [u8; 4] {
+    const GREEN: [f64; 3] = [0.0, 176.0, 80.0];
+    const YELLOW: [f64; 3] = [255.0, 220.0, 0.0];
+    const RED: [f64; 3] = [220.0, 0.0, 0.0];
+    let level = if level.is_nan() {
+        1.0
+    } else {
+        level.clamp(0.0, 1.0)
+    };
+    let (from, to, t) = if level < 0.5 {
+        (GREEN, YELLOW, level * 2.0)
+    } else {
+        (YELLOW, RED, level * 2.0 - 1.0)
+    };
+    let mix = |i: usize| (from[i] + (to[i] - from[i]) * t).round() as u8;
+    [mix(0), mix(1), mix(2), 255]
+}
+
+/// Thread segment in pixel coordinates
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    from: (f64, f64),
+    to: (f64, f64),
+    width: f64,
+    color: [f64; 3],
+}
+
+/// Image being drawn one stitch at a time, in sewing order
+pub(crate) struct Scene {
+    image: RgbaImage,
+    segments: Vec<Segment>,
+    next: usize,
+    shading: bool,
+}
+
+impl Scene {
+    /// Size the image for the pattern and draw the background and guides
+    pub(crate) fn new(pattern: &EmbPattern, options: &RenderOptions) -> Result<Self> {
+        if !(options.scale > 0.0 && options.scale.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Render scale must be positive, got {}",
+                options.scale
+            )));
+        }
+        if !(options.thread_width > 0.0 && options.thread_width.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Thread width must be positive, got {}",
+                options.thread_width
+            )));
+        }
+
+        let blocks = pattern.get_as_stitchblock();
+        if blocks.is_empty() {
+            return Ok(Self {
+                image: RgbaImage::new(1, 1, options.background.as_ref()),
+                segments: Vec::new(),
+                next: 0,
+                shading: options.shading,
+            });
+        }
+
+        let (min_x, min_y, max_x, max_y) = pattern.bounds();
+        let widest = blocks
+            .iter()
+            .map(|(_, thread)| options.line_width(thread))
+            .fold(0.0, f64::max);
+        let pad = (widest / 2.0).ceil() + 1.0 + options.margin as f64;
+        let px = |units: f64| units / 10.0 * options.scale;
+
+        let width = (px(max_x - min_x) + 2.0 * pad).ceil() as usize;
+        let height = (px(max_y - min_y) + 2.0 * pad).ceil() as usize;
+        if width.saturating_mul(height) > MAX_RENDER_PIXELS {
+            return Err(Error::InvalidPattern(format!(
+                "Rendered image would be {}x{} pixels; reduce the scale",
+                width, height
+            )));
+        }
+
+        let to_pixel = |(x, y): (f64, f64)| (px(x - min_x) + pad, px(y - min_y) + pad);
+        let mut segments = Vec::new();
+        for (block, thread) in &blocks {
+            let color = [
+                thread.red() as f64,
+                thread.green() as f64,
+                thread.blue() as f64,
+            ];
+            let width = options.line_width(thread);
+            if block.len() == 1 {
+                let p = to_pixel(block[0]);
+                segments.push(Segment {
+                    from: p,
+                    to: p,
+                    width,
+                    color,
+                });
+            }
+            segments.extend(block.windows(2).map(|pair| Segment {
+                from: to_pixel(pair[0]),
+                to: to_pixel(pair[1]),
+                width,
+                color,
+            }));
+        }
+
+        let mut image = RgbaImage::new(width, height, options.background.as_ref());
+        if options.guides {
+            draw_guides(&mut image, options.scale);
+        }
+        Ok(Self {
+            image,
+            segments,
+            next: 0,
+            shading: options.shading,
+        })
+    }
+
+    /// Image drawn so far
+    pub(crate) fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    /// Number of stitches left to draw
+    pub(crate) fn remaining(&self) -> usize {
+        self.segments.len() - self.next
+    }
+
+    /// Draw the next stitch, returning the pixel rectangle it touched
+    pub(crate) fn draw_next(&mut self) -> Option<(usize, usize, usize, usize)> {
+        let segment = *self.segments.get(self.next)?;
+        self.next += 1;
+        Some(self.image.draw_segment(
+            segment.from,
+            segment.to,
+            segment.width,
+            segment.color,
+            self.shading,
+        ))
+    }
+
+    pub(crate) fn into_image(self) -> RgbaImage {
+        self.image
+    }
+}
+
+/// Ruler ticks every 5mm along the top and left edges
+fn draw_guides(canvas: &mut RgbaImage, scale: f64) {
+    let step = 5.0 * scale;
+    let length = 3.0 * scale;
+    let black = [0.0, 0.0, 0.0];
+
+    let mut x = step;
+    while x < canvas.width as f64 {
+        canvas.draw_segment((x, 0.0), (x, length), 1.0, black, false);
+        x += step;
+    }
+    let mut y = step;
+    while y < canvas.height as f64 {
+        canvas.draw_segment((0.0, y), (length, y), 1.0, black, false);
+        y += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(canvas: &RgbaImage, x: usize, y: usize) -> [u8; 4] {
+        canvas.pixel(x, y).unwrap()
+    }
+
+    fn line(thread: EmbThread) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(thread);
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+        pattern
+    }
+
+    #[test]
+    fn test_render_size_and_colors() {
+        let options = RenderOptions::default().shading(false).margin(0);
+        let canvas = render_to_rgba(&line(EmbThread::from_rgb(255, 0, 0)), &options).unwrap();
+
+        // 10mm at 10px/mm plus a 3px pad on each side for the 4px thread
+        assert_eq!((canvas.width, canvas.height), (106, 6));
+        assert_eq!(pixel(&canvas, 53, 3), [255, 0, 0, 255]);
+        assert_eq!(pixel(&canvas, 53, 0), [255, 255, 255, 255]);
+
+        // Anti-aliased edges mix thread and background
+        let canvas = render_to_rgba(
+            &line(EmbThread::from_rgb(255, 0, 0)),
+            &options.thread_width(0.45),
+        )
+        .unwrap();
+        let edge = (0..canvas.height)
+            .map(|y| pixel(&canvas, 53, y))
+            .find(|p| p[1] > 0 && p[1] < 255);
+        assert!(edge.is_some());
+    }
+
+    #[test]
+    fn test_transparent_background() {
+        let options = RenderOptions::default().background(None);
+        let canvas = render_to_rgba(&line(EmbThread::from_rgb(0, 0, 255)), &options).unwrap();
+        assert_eq!(pixel(&canvas, 0, 0)[3], 0);
+        assert_eq!(pixel(&canvas, canvas.width / 2, canvas.height / 2)[3], 255);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn test_decode_rendered_png() {
+        let options = RenderOptions::default().shading(false).margin(0);
+        let pattern = line(EmbThread::from_rgb(255, 0, 0));
+        let mut png = Vec::new();
+        crate::formats::io::writers::png::write_with_options(&pattern, &mut png, &options).unwrap();
+
+        let decoded = RgbaImage::decode(&png).unwrap();
+        assert_eq!(decoded, render_to_rgba(&pattern, &options).unwrap());
+        assert!(RgbaImage::decode(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_dpi_and_weight_scaling() {
+        let options = RenderOptions::default().dpi(254.0).margin(0);
+        assert!((options.scale - 10.0).abs() < 1e-9);
+
+        let thick = render_to_rgba(&line(EmbThread::new(0).with_weight("20wt")), &options).unwrap();
+        let thin = render_to_rgba(&line(EmbThread::new(0).with_weight("60wt")), &options).unwrap();
+        assert!(thick.height > thin.height);
+
+        let flat = options.weight_scaling(false);
+        let unscaled = render_to_rgba(&line(EmbThread::new(0).with_weight("20wt")), &flat).unwrap();
+        assert_eq!(unscaled.height, 6);
+    }
+
+    #[test]
+    fn test_shading_darkens_edges() {
+        let options = RenderOptions::default().thread_width(1.0).margin(0);
+        let canvas = render_to_rgba(&line(EmbThread::from_rgb(0, 160, 0)), &options).unwrap();
+        let middle = canvas.height / 2;
+        let center = pixel(&canvas, 53, middle);
+        let edge = pixel(&canvas, 53, middle - 4);
+        assert!(center[1] > edge[1]);
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let pattern = line(EmbThread::new(0));
+        assert!(render_to_rgba(&pattern, &RenderOptions::default().scale(0.0)).is_err());
+        assert!(render_to_rgba(&pattern, &RenderOptions::default().thread_width(-1.0)).is_err());
+        assert!(render_to_rgba(&pattern, &RenderOptions::default().scale(1e6)).is_err());
+
+        let empty = render_to_rgba(&EmbPattern::new(), &RenderOptions::default()).unwrap();
+        assert_eq!((empty.width, empty.height), (1, 1));
+        assert_eq!(empty.pixel(0, 0), Some([255, 255, 255, 255]));
+        assert_eq!(empty.pixel(1, 0), None);
+        assert_eq!(empty.into_raw(), vec![255; 4]);
+    }
+
+    #[test]
+    fn test_render_density_map() {
+        let mut pattern = EmbPattern::new();
+        for _ in 0..4 {
+            pattern.stitch_abs(0.0, 0.0);
+        }
+        pattern.stitch_abs(25.0, 0.0);
+        let map = pattern.density_map(10.0).unwrap();
+
+        let image = render_density_map(&map, 400.0, 2).unwrap();
+        assert_eq!((image.width, image.height), (6, 2));
+        assert_eq!(pixel(&image, 1, 1), [220, 0, 0, 255]);
+        assert_eq!(pixel(&image, 2, 0), [0, 0, 0, 0]);
+        // 100 stitches/cm² is a quarter of the way to red
+        assert_eq!(pixel(&image, 5, 1), [128, 198, 40, 255]);
+    }
+}
@@ -0,0 +1,476 @@
+//! In-memory conversion service
+//!
+//! Provides [`convert_bytes`], a single entry point that converts an embroidery file
+//! held in memory into another format with all safety limits applied. It is intended
+//! to be dropped directly into an HTTP handler (axum, actix-web, etc.): input and
+//! output are plain byte buffers, and [`ConvertError::is_client_error`] tells the
+//! caller whether a failure was caused by the request or by the server.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::service::{convert_bytes, ConvertOptions};
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+//! pattern.add_stitch_absolute(STITCH, 10.0, 10.0);
+//! pattern.end();
+//!
+//! let mut json = Vec::new();
+//! butabuti::formats::io::writers::json::write(&mut json, &pattern)?;
+//!
+//! let dst = convert_bytes(&json, Some("json"), "dst", &ConvertOptions::default())?;
+//! assert!(dst.len() >= 512);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::formats::format::Format;
+use crate::formats::io::detector::FormatDetector;
+use crate::formats::io::options::ReadOptions;
+use crate::formats::io::traits::PatternReader;
+use crate::formats::registry::FormatRegistry;
+use crate::utils::error::{Error, ErrorKind};
+use std::fmt;
+use std::io::Cursor;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Default maximum input size (32 MiB)
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 32 * 1024 * 1024;
+
+/// Default maximum output size (64 MiB)
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Default maximum stitch count
+pub const DEFAULT_MAX_STITCHES: usize = 1_000_000;
+
+/// Default maximum thread count
+pub const DEFAULT_MAX_THREADS: usize = 1_000;
+
+/// Limits and reader options for [`convert_bytes`]
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    /// Maximum accepted input size in bytes
+    pub max_input_bytes: usize,
+    /// Maximum produced output size in bytes
+    pub max_output_bytes: usize,
+    /// Maximum number of stitches in the decoded pattern
+    pub max_stitches: usize,
+    /// Maximum number of threads in the decoded pattern
+    pub max_threads: usize,
+    /// Options passed to readers that support them
+    pub read_options: ReadOptions,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            max_stitches: DEFAULT_MAX_STITCHES,
+            max_threads: DEFAULT_MAX_THREADS,
+            read_options: ReadOptions::default(),
+        }
+    }
+}
+
+impl ConvertOptions {
+    /// Create options with default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum input size in bytes
+    pub fn max_input_bytes(mut self, limit: usize) -> Self {
+        self.max_input_bytes = limit;
+        self
+    }
+
+    /// Set the maximum output size in bytes
+    pub fn max_output_bytes(mut self, limit: usize) -> Self {
+        self.max_output_bytes = limit;
+        self
+    }
+
+    /// Set the maximum stitch count
+    pub fn max_stitches(mut self, limit: usize) -> Self {
+        self.max_stitches = limit;
+        self
+    }
+
+    /// Set the maximum thread count
+    pub fn max_threads(mut self, limit: usize) -> Self {
+        self.max_threads = limit;
+        self
+    }
+
+    /// Set the reader options
+    pub fn read_options(mut self, options: ReadOptions) -> Self {
+        self.read_options = options;
+        self
+    }
+}
+
+/// Errors returned by [`convert_bytes`]
+#[derive(Debug, Clone)]
+pub enum ConvertError {
+    /// Input exceeds `max_input_bytes`
+    InputTooLarge {
+        /// Input size in bytes
+        size: usize,
+        /// Configured limit
+        limit: usize,
+    },
+    /// Input format could not be determined from the hint or content
+    UnknownInputFormat,
+    /// Input format is known but cannot be read
+    UnsupportedInputFormat(String),
+    /// Output format is unknown or cannot be written
+    UnsupportedOutputFormat(String),
+    /// Decoded pattern exceeds `max_stitches`
+    TooManyStitches {
+        /// Stitch count of the decoded pattern
+        count: usize,
+        /// Configured limit
+        limit: usize,
+    },
+    /// Decoded pattern exceeds `max_threads`
+    TooManyThreads {
+        /// Thread count of the decoded pattern
+        count: usize,
+        /// Configured limit
+        limit: usize,
+    },
+    /// Output exceeds `max_output_bytes`
+    OutputTooLarge {
+        /// Output size in bytes
+        size: usize,
+        /// Configured limit
+        limit: usize,
+    },
+    /// Reading the input failed
+    Read(Error),
+    /// Writing the output failed
+    Write(Error),
+    /// A reader or writer panicked; the input is most likely malformed
+    Internal(String),
+}
+
+impl ConvertError {
+    /// Whether the error was caused by the request (bad input, unsupported format,
+    /// limits exceeded) rather than by the server
+    ///
+    /// Handlers can map `true` to a 4xx status and `false` to a 5xx status.
+    /// Write errors count as the request's when the design doesn't fit the
+    /// output format (writer limits, unsupported content), and as the server's
+    /// when output itself failed.
+    pub fn is_client_error(&self) -> bool {
+        match self {
+            ConvertError::Write(e) => !matches!(e.kind(), ErrorKind::Io(_) | ErrorKind::Json(_)),
+            ConvertError::Internal(_) => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::InputTooLarge { size, limit } => {
+                write!(f, "Input too large: {} bytes (limit {})", size, limit)
+            }
+            ConvertError::UnknownInputFormat => write!(f, "Unable to detect input format"),
+            ConvertError::UnsupportedInputFormat(format) => {
+                write!(f, "Unsupported input format: {}", format)
+            }
+            ConvertError::UnsupportedOutputFormat(format) => {
+                write!(f, "Unsupported output format: {}", format)
+            }
+            ConvertError::TooManyStitches { count, limit } => {
+                write!(f, "Too many stitches: {} (limit {})", count, limit)
+            }
+            ConvertError::TooManyThreads { count, limit } => {
+                write!(f, "Too many threads: {} (limit {})", count, limit)
+            }
+            ConvertError::OutputTooLarge { size, limit } => {
+                write!(f, "Output too large: {} bytes (limit {})", size, limit)
+            }
+            ConvertError::Read(e) => write!(f, "Failed to read input: {}", e),
+            ConvertError::Write(e) => write!(f, "Failed to write output: {}", e),
+            ConvertError::Internal(msg) => write!(f, "Internal conversion error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Convert an in-memory embroidery file to another format
+///
+/// # Arguments
+///
+/// * `input` - Raw bytes of the source file
+/// * `from_hint` - Optional format name (`"pes"`), extension (`".pes"`) or file name
+///   (`"design.pes"`); when absent or unrecognized the format is detected from content
+/// * `to` - Target format name or extension (e.g., `"dst"`)
+/// * `options` - Safety limits and reader options
+///
+/// # Errors
+///
+/// Returns a [`ConvertError`] if the input is too large, the formats are unknown or
+/// unsupported, the pattern exceeds the configured limits, or decoding/encoding fails.
+/// Panics inside readers or writers are caught and reported as
+/// [`ConvertError::Internal`].
+pub fn convert_bytes(
+    input: &[u8],
+    from_hint: Option<&str>,
+    to: &str,
+    options: &ConvertOptions,
+) -> std::result::Result<Vec<u8>, ConvertError> {
+    if input.len() > options.max_input_bytes {
+        return Err(ConvertError::InputTooLarge {
+            size: input.len(),
+            limit: options.max_input_bytes,
+        });
+    }
+
+    let registry = FormatRegistry::new();
+    let output_format = resolve_format_name(&registry, to)
+        .filter(|name| registry.get_format(name).is_some_and(|f| f.can_write))
+        .ok_or_else(|| ConvertError::UnsupportedOutputFormat(to.to_string()))?;
+
+    let input_format = match from_hint.and_then(|hint| resolve_format_name(&registry, hint)) {
+        Some(name) => name,
+        None => detect_format_name(input)?,
+    };
+    if !registry
+        .get_format(&input_format)
+        .is_some_and(|f| f.can_read)
+    {
+        return Err(ConvertError::UnsupportedInputFormat(input_format));
+    }
+
+    let pattern = guarded(|| read_pattern(input, &input_format, &options.read_options))?
+        .map_err(ConvertError::Read)?;
+
+    let stitch_count = pattern.stitches().len();
+    if stitch_count > options.max_stitches {
+        return Err(ConvertError::TooManyStitches {
+            count: stitch_count,
+            limit: options.max_stitches,
+        });
+    }
+    let thread_count = pattern.threads().len();
+    if thread_count > options.max_threads {
+        return Err(ConvertError::TooManyThreads {
+            count: thread_count,
+            limit: options.max_threads,
+        });
+    }
+
+    let output = guarded(|| {
+        let mut output = Cursor::new(Vec::new());
+        registry
+            .write_pattern(&pattern, &mut output, &output_format)
+            .map(|_| output.into_inner())
+    })?
+    .map_err(ConvertError::Write)?;
+
+    if output.len() > options.max_output_bytes {
+        return Err(ConvertError::OutputTooLarge {
+            size: output.len(),
+            limit: options.max_output_bytes,
+        });
+    }
+
+    Ok(output)
+}
+
+/// Run a reader/writer call, converting panics into `ConvertError::Internal`
+fn guarded<T>(f: impl FnOnce() -> T) -> std::result::Result<T, ConvertError> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let msg = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic while converting".to_string());
+        ConvertError::Internal(msg)
+    })
+}
+
+/// Convert an in-memory embroidery file without blocking the async runtime
+///
+/// Runs [`convert_bytes`] on tokio's blocking thread pool; see it for the
+/// arguments and errors. The arguments are owned so the conversion can outlive
+/// the request handler's borrows.
+#[cfg(feature = "tokio")]
+pub async fn convert_bytes_async(
+    input: Vec<u8>,
+    from_hint: Option<String>,
+    to: String,
+    options: ConvertOptions,
+) -> std::result::Result<Vec<u8>, ConvertError> {
+    tokio::task::spawn_blocking(move || convert_bytes(&input, from_hint.as_deref(), &to, &options))
+        .await
+        .map_err(|e| ConvertError::Internal(e.to_string()))?
+}
+
+/// Resolve a format name, extension or file name to a registry format name
+fn resolve_format_name(registry: &FormatRegistry, hint: &str) -> Option<String> {
+    let hint = hint.trim();
+    registry
+        .get_format(hint)
+        .or_else(|| registry.get_format_by_extension(hint))
+        .or_else(|| registry.get_format_from_path(hint))
+        .map(|f| f.name.to_lowercase())
+}
+
+/// Detect the input format from its content
+fn detect_format_name(input: &[u8]) -> std::result::Result<String, ConvertError> {
+    let format = FormatDetector::detect_from_content(&mut Cursor::new(input))
+        .map_err(|_| ConvertError::UnknownInputFormat)?;
+
+    match format.registry_name() {
+        Some(name) if format.can_read() => Ok(name.to_string()),
+        Some(name) => Err(ConvertError::UnsupportedInputFormat(name.to_string())),
+        None => Err(ConvertError::UnknownInputFormat),
+    }
+}
+
+/// Read a pattern, passing read options to readers that accept them
+fn read_pattern(
+    input: &[u8],
+    format: &str,
+    options: &ReadOptions,
+) -> crate::utils::error::Result<EmbPattern> {
+    let mut reader = Cursor::new(input);
+    match format.parse::<Format>() {
+        Ok(builtin) if builtin.can_read() => builtin.read(&mut reader, options),
+        _ => FormatRegistry::new().read_pattern(&mut reader, format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+    use crate::core::thread::EmbThread;
+    use crate::formats::io::{readers, writers};
+
+    fn sample_json() -> Vec<u8> {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0x00FF00));
+        for i in 0..10 {
+            pattern.add_stitch_absolute(STITCH, i as f64 * 10.0, 0.0);
+        }
+        pattern.end();
+
+        let mut json = Vec::new();
+        writers::json::write(&mut json, &pattern).unwrap();
+        json
+    }
+
+    #[test]
+    fn test_convert_with_hint_and_detection() {
+        let json = sample_json();
+        let options = ConvertOptions::default();
+
+        let dst = convert_bytes(&json, Some("design.json"), "dst", &options).unwrap();
+        assert!(dst.len() >= 512);
+
+        // Detected from content, output given as extension
+        let exp = convert_bytes(&json, None, ".exp", &options).unwrap();
+        assert!(!exp.is_empty());
+
+        // DST back to JSON via content detection
+        let back = convert_bytes(&dst, None, "json", &options).unwrap();
+        let pattern = readers::json::read(&mut back.as_slice()).unwrap();
+        assert!(pattern.count_stitches() >= 10);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_convert_bytes_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let json = sample_json();
+        let expected = convert_bytes(&json, None, "dst", &ConvertOptions::default()).unwrap();
+        let dst = runtime
+            .block_on(convert_bytes_async(
+                json,
+                None,
+                "dst".to_string(),
+                ConvertOptions::default(),
+            ))
+            .unwrap();
+        assert_eq!(dst, expected);
+    }
+
+    #[test]
+    fn test_convert_limits() {
+        let json = sample_json();
+
+        let err = convert_bytes(
+            &json,
+            None,
+            "dst",
+            &ConvertOptions::new().max_input_bytes(8),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConvertError::InputTooLarge { .. }));
+        assert!(err.is_client_error());
+
+        let err =
+            convert_bytes(&json, None, "dst", &ConvertOptions::new().max_stitches(5)).unwrap_err();
+        assert!(matches!(err, ConvertError::TooManyStitches { .. }));
+
+        let err = convert_bytes(
+            &json,
+            None,
+            "dst",
+            &ConvertOptions::new().max_output_bytes(16),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ConvertError::OutputTooLarge { .. }));
+
+        // More colors than PES holds is the design's fault
+        let mut pattern = EmbPattern::new();
+        for i in 0..300 {
+            pattern.add_thread(EmbThread::new(i));
+            pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+            pattern.add_command(COLOR_CHANGE, 0.0, 0.0);
+        }
+        pattern.end();
+        let mut json = Vec::new();
+        writers::json::write(&mut json, &pattern).unwrap();
+        let options = ConvertOptions::new().max_threads(1000);
+        let err = convert_bytes(&json, None, "pes", &options).unwrap_err();
+        assert!(matches!(err, ConvertError::Write(_)));
+        assert!(err.is_client_error());
+
+        let err = ConvertError::Write(Error::io("disk full"));
+        assert!(!err.is_client_error());
+    }
+
+    #[test]
+    fn test_convert_format_errors() {
+        let json = sample_json();
+        let options = ConvertOptions::default();
+
+        let err = convert_bytes(&json, None, "doc", &options).unwrap_err();
+        assert!(matches!(err, ConvertError::UnsupportedOutputFormat(_)));
+
+        let err = convert_bytes(&json, Some("txt"), "dst", &options).unwrap_err();
+        assert!(matches!(err, ConvertError::UnsupportedInputFormat(_)));
+
+        let err =
+            convert_bytes(&[0x01, 0x02, 0x03, 0x04, 0x05], None, "dst", &options).unwrap_err();
+        assert!(matches!(err, ConvertError::UnknownInputFormat));
+
+        let err = convert_bytes(b"not json at all", Some("json"), "dst", &options).unwrap_err();
+        assert!(matches!(err, ConvertError::Read(_)));
+        assert!(err.to_string().starts_with("Failed to read input"));
+    }
+}
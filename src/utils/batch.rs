@@ -10,29 +10,37 @@
 //!
 //! The batch converter is designed for safe concurrent operation:
 //!
-//! - **Mutex Protection**: Results are collected in an `Arc<Mutex<Vec<ConversionResult>>>` to ensure
-//!   thread-safe access during parallel processing. The mutex is only held during result insertion,
-//!   minimizing contention.
+//! - **Bounded Pool**: Files are converted by a fixed number of scoped worker
+//!   threads (one per available core unless set with `BatchConverter::threads`)
+//!   that take the next file from a shared counter, so a library of thousands
+//!   of files never spawns more threads than the pool size.
 //!
-//! - **Arc Sharing**: Configuration data (target format, output directory) is wrapped in `Arc` to enable
-//!   safe sharing across threads without cloning. This is read-only after initialization.
+//! - **Ordered Results**: Each worker stores its result in the slot of its input
+//!   file, so results come back in input order whatever order files finish in.
 //!
-//! - **Thread Panic Handling**: Worker threads are isolated - if one panics during conversion, others
-//!   continue processing. Failed conversions are captured as `ConversionResult::Failed` rather than
-//!   propagating panics.
+//! - **Panic Isolation**: A panic while converting one file is caught and
+//!   reported as `ConversionResult::Failed`; the other files keep converting.
 //!
-//! - **Rayon Guarantees**: When `parallel` feature is enabled, uses Rayon's `par_iter()` which:
-//!   - Automatically manages thread pool sizing
-//!   - Ensures all spawned threads complete before returning
-//!   - Provides work-stealing for load balancing
-//!   - Handles thread panics gracefully with `catch_unwind`
+//! - **Progress Reporting**: The `on_progress` callback is called from the
+//!   worker threads as each file starts and finishes, so it must be `Send + Sync`.
+//!   `progress_sender` forwards the same updates to an `mpsc` channel.
 //!
-//! - **Mutex Poisoning**: In the rare case of mutex poisoning (panic while lock held), operations
-//!   gracefully degrade to serial processing or return accumulated results rather than panicking.
+//! - **Cancellation**: A [`CancellationToken`] shared with another thread stops
+//!   workers from starting new files; the rest are reported as skipped.
+//!
+//! - **Timeouts**: With `BatchConverter::timeout` each file is converted on its
+//!   own thread and reported as failed once the timeout passes. The worker
+//!   moves on; the abandoned thread is left to finish or hang in the background.
+//!
+//! - **Mutex Poisoning**: Result slots are recovered from a poisoned mutex
+//!   rather than lost, so a failed file never hides the results of others.
 //!
 //! ## Supported Input Formats
 //!
-//! The batch converter supports automatic format detection for:
+//! Input files are read by their extension. Files with a missing or unknown
+//! extension, or whose magic bytes identify another format (for example a PES
+//! file saved as `.dst`), are read by their content instead. The batch converter
+//! supports:
 //! - **dst** - Tajima DST
 //! - **pes** - Brother PES
 //! - **exp** - Melco EXP
@@ -41,30 +49,65 @@
 //! - **pec** - Brother PEC
 //! - **xxx** - Singer XXX
 //! - **u01** - Barudan U01
+//! - **dsb** - Barudan DSB
+//! - **dsz** - ZSK DSZ
+//! - **zxy** - ZSK TC
+//! - **hus** - Husqvarna Viking HUS
+//! - **shv** - Husqvarna Viking SHV
+//! - **tap** - Happy TAP
 //! - **tbf** - Tajima TBF
+//! - **thr** - ThredWorks THR
 //! - **col** - Embroidery Thread Color
 //! - **edr** - Embird Color
 //! - **inf** - Embroidery Thread Info
 //! - **gcode** - GCode embroidery data
 //! - **json** - JSON embroidery data
+//! - **buta** - ButaButi lossless archive
 //! - **csv** - CSV embroidery data
 //!
+//! ## ZIP Archives
+//!
+//! A `.zip` file among the inputs, or found while scanning the input directory,
+//! is opened and each design inside becomes an input of its own, reported as
+//! `archive.zip/path/in/archive.pes`. Entries in folders of the archive are
+//! only converted when scanning recursively; images, documents and other
+//! non-design entries are skipped. Without an output directory, designs from
+//! an archive are written next to it.
+//!
+//! `BatchConverter::output_zip` collects the converted files into one archive
+//! instead of a directory, written in input order once all files are done.
+//!
+//! ## Manifests
+//!
+//! `BatchConverter::manifest` writes a JSON or CSV listing of every converted
+//! file: input and output path, SHA-256 of the output, stitch count, width and
+//! height in millimeters, and color count.
+//!
 //! ## Supported Output Formats
 //!
 //! The batch converter can export to any format supported by the writers module,
-//! including: dst, pes, jef, vp3, exp, pec, xxx, u01, tbf, col, edr, inf, gcode, json, csv, svg, png, txt.
+//! including: dst, pes, jef, vp3, exp, pec, xxx, u01, dsb, dsz, zxy, hus, shv, tbf, col, edr, inf, gcode, json, buta, csv, svg, png, txt.
+//!
+//! Files are converted on a bounded worker pool; progress can be followed with
+//! `BatchConverter::on_progress` or over a channel with
+//! `BatchConverter::progress_sender`.
+//!
+//! Writer options such as the PES version, DST header style and trim encoding
+//! are set per output format with a [`WriteSettings`] passed to
+//! `BatchConverter::write_settings` or `MultiFormatExporter::write_settings`.
 //!
 //! # Examples
 //!
 //! ## Batch convert multiple files
 //!
 //! ```no_run
+//! use butabuti::formats::Format;
 //! use butabuti::utils::batch::{BatchConverter, ConversionResult};
 //!
 //! let converter = BatchConverter::new()
 //!     .input_dir("./designs")
 //!     .output_dir("./output")
-//!     .target_format("dst")
+//!     .target_format(Format::DST)
 //!     .build();
 //!
 //! let results = converter.convert_all()?;
@@ -84,6 +127,7 @@
 //! ## Export to multiple formats
 //!
 //! ```no_run
+//! use butabuti::formats::Format;
 //! use butabuti::utils::batch::MultiFormatExporter;
 //! use butabuti::prelude::*;
 //!
@@ -93,7 +137,7 @@
 //! let exporter = MultiFormatExporter::new()
 //!     .output_dir("./exports")
 //!     .base_name("my_design")
-//!     .formats(&["dst", "pes", "jef", "vp3", "exp"])
+//!     .formats(&[Format::DST, Format::PES, Format::JEF, Format::VP3, Format::EXP])
 //!     .build();
 //!
 //! let results = exporter.export(&pattern)?;
@@ -101,14 +145,31 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
-use crate::core::pattern::EmbPattern;
-use crate::formats::io::{readers, writers};
+use crate::core::collection::{archive_design_format, entry_name};
+use crate::core::pattern::{EmbPattern, MetadataKey};
+use crate::formats::format::Format;
+use crate::formats::io::color_file::{load_color_file, write_color_file};
+use crate::formats::io::detector::detect_format;
+use crate::formats::io::options::{ReadOptions, ReadWarning, WriteOptions};
+use crate::formats::io::report::WriteReport;
+use crate::formats::io::traits::{PatternReader, PatternWriter};
+use crate::formats::io::writers::pes::PesVersion;
+use crate::formats::io::zip::{ZipArchive, ZipWriter};
+use crate::formats::registry::FormatRegistry;
 use crate::utils::error::{Error, Result};
+use crate::utils::substitution::quote_csv_field;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Represents the result of a single conversion operation
 #[derive(Debug, Clone)]
@@ -123,6 +184,9 @@ pub enum ConversionResult {
         duration_ms: u128,
         /// Output file size in bytes
         file_size: u64,
+        /// Lossy adjustments made while reading or writing (clamped colors,
+        /// split stitches, dropped metadata)
+        warnings: Vec<String>,
     },
     /// Conversion failed
     Failed {
@@ -186,6 +250,16 @@ impl ConversionResults {
             .count()
     }
 
+    /// Count successful conversions that reported warnings
+    pub fn warning_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(
+                |r| matches!(r, ConversionResult::Success { warnings, .. } if !warnings.is_empty()),
+            )
+            .count()
+    }
+
     /// Count failed conversions
     pub fn failed_count(&self) -> usize {
         self.results
@@ -232,6 +306,7 @@ impl ConversionResults {
         println!("\n=== Conversion Summary ===");
         println!("Total files processed: {}", self.total_count());
         println!("  ✓ Successful: {}", self.success_count());
+        println!("    ⚠ With warnings: {}", self.warning_count());
         println!("  ✗ Failed: {}", self.failed_count());
         println!("  ⊘ Skipped: {}", self.skipped_count());
         println!("Success rate: {:.1}%", self.success_rate() * 100.0);
@@ -249,16 +324,201 @@ impl Default for ConversionResults {
     }
 }
 
+/// Writer options for batch exports, per output format
+///
+/// Formats without their own options use the defaults, which write DST files
+/// with the plain header and 3 jumps per trim, JEF files without trim records
+/// or a date, and PES version 1. The convenience setters change the defaults.
+///
+/// Options given with [`format`](Self::format) replace the defaults for that
+/// format as a whole, not field by field: `WriteOptions::new()` brings back the
+/// library defaults, such as the extended DST header. To change a few fields
+/// and keep the batch defaults, start from [`options_for`](Self::options_for).
+///
+/// # Example
+///
+/// ```
+/// use butabuti::formats::Format;
+/// use butabuti::formats::io::options::WriteOptions;
+/// use butabuti::formats::io::writers::pes::PesVersion;
+/// use butabuti::utils::batch::WriteSettings;
+///
+/// let settings = WriteSettings::new().pes_version(PesVersion::V6);
+/// let dst = settings.options_for(Format::DST).clone().dst_trim_jumps(5);
+/// let settings = settings
+///     .format(Format::DST, dst)
+///     .format(Format::JEF, WriteOptions::new());
+///
+/// assert_eq!(settings.options_for(Format::PES).pes_version, PesVersion::V6);
+/// assert_eq!(settings.options_for(Format::DST).dst_trim_jumps, 5);
+/// assert!(!settings.options_for(Format::DST).dst_extended_header);
+/// // JEF files get the library defaults, trim records included
+/// assert!(settings.options_for(Format::JEF).jef.trims);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriteSettings {
+    defaults: WriteOptions,
+    formats: HashMap<Format, WriteOptions>,
+}
+
+impl WriteSettings {
+    /// Create settings with the batch defaults
+    pub fn new() -> Self {
+        Self {
+            defaults: WriteOptions::new()
+                .dst_extended_header(false)
+                .dst_trim_jumps(3)
+                .jef_trims(false, 0)
+                .jef_date(""),
+            formats: HashMap::new(),
+        }
+    }
+
+    /// Replace the options used by formats without their own
+    pub fn defaults(mut self, options: WriteOptions) -> Self {
+        self.defaults = options;
+        self
+    }
+
+    /// Use the given options for one format
+    ///
+    /// `options` replaces the defaults for `format` entirely; later changes to
+    /// the defaults don't reach it.
+    pub fn format(mut self, format: Format, options: WriteOptions) -> Self {
+        self.formats.insert(format, options);
+        self
+    }
+
+    /// Set how trims are written
+    ///
+    /// DST trims become `records` jump records (at least 2) and JEF trims
+    /// `records` trim records; 0 leaves trims out of JEF files.
+    pub fn trims(mut self, records: usize) -> Self {
+        self.defaults.dst_trim_jumps = records.max(2);
+        self.defaults.jef.trims = records > 0;
+        self.defaults.jef.trim_records = records;
+        self
+    }
+
+    /// Set the PES file version
+    pub fn pes_version(mut self, version: PesVersion) -> Self {
+        self.defaults.pes_version = version;
+        self
+    }
+
+    /// Options used when writing the given format
+    pub fn options_for(&self, format: Format) -> &WriteOptions {
+        self.formats.get(&format).unwrap_or(&self.defaults)
+    }
+}
+
+impl Default for WriteSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Status of one file in a batch conversion
+#[derive(Debug, Clone)]
+pub enum FileStatus {
+    /// A worker started converting the file
+    Started,
+    /// The file was converted, skipped or failed
+    Finished(ConversionResult),
+}
+
+/// Progress update for one file of a batch conversion
+#[derive(Debug, Clone)]
+pub struct BatchProgress {
+    /// Input file path
+    pub input: PathBuf,
+    /// Position of the file in the batch
+    pub index: usize,
+    /// Number of files finished so far, including this one once it finishes
+    pub completed: usize,
+    /// Number of files in the batch
+    pub total: usize,
+    /// What happened to the file
+    pub status: FileStatus,
+}
+
+impl BatchProgress {
+    /// Fraction of the batch finished (0.0 to 1.0)
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.completed as f64 / self.total as f64
+        }
+    }
+}
+
+/// Callback receiving batch progress updates from the worker threads
+pub type ProgressCallback = Box<dyn Fn(&BatchProgress) + Send + Sync>;
+
+/// Handle for cancelling a running batch conversion
+///
+/// Clones share the same flag, so a GUI can keep one clone and pass another to
+/// [`BatchConverter::cancellation_token`].
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::utils::batch::{BatchConverter, CancellationToken};
+///
+/// let token = CancellationToken::new();
+/// let converter = BatchConverter::new()
+///     .input_dir("./designs")
+///     .output_dir("./output")
+///     .cancellation_token(token.clone())
+///     .build();
+///
+/// let job = std::thread::spawn(move || converter.convert_all());
+/// token.cancel(); // e.g. from a Cancel button
+/// let results = job.join().unwrap()?;
+/// println!("{} files skipped", results.skipped_count());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every conversion using this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
 /// Builder for batch file conversion operations
 pub struct BatchConverter {
     input_dir: Option<PathBuf>,
     output_dir: Option<PathBuf>,
+    output_zip: Option<PathBuf>,
+    manifest: Option<PathBuf>,
     input_files: Vec<PathBuf>,
-    target_format: Option<String>,
+    target_format: Option<Format>,
     overwrite: bool,
     recursive: bool,
     input_extensions: Vec<String>,
     parallel: bool,
+    threads: Option<usize>,
+    strip_metadata: Option<Vec<MetadataKey>>,
+    write_settings: WriteSettings,
+    progress: Option<ProgressCallback>,
+    cancellation: Option<CancellationToken>,
+    timeout: Option<Duration>,
 }
 
 impl BatchConverter {
@@ -267,12 +527,20 @@ impl BatchConverter {
         Self {
             input_dir: None,
             output_dir: None,
+            output_zip: None,
+            manifest: None,
             input_files: Vec::new(),
             target_format: None,
             overwrite: false,
             recursive: false,
             input_extensions: Vec::new(),
             parallel: true,
+            threads: None,
+            strip_metadata: None,
+            write_settings: WriteSettings::new(),
+            progress: None,
+            cancellation: None,
+            timeout: None,
         }
     }
 
@@ -288,15 +556,39 @@ impl BatchConverter {
         self
     }
 
+    /// Write converted files into a ZIP archive instead of a directory
+    ///
+    /// Takes precedence over `output_dir`. An existing archive is only
+    /// replaced with `overwrite(true)`.
+    pub fn output_zip<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output_zip = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Write a manifest of the converted files to `path`
+    ///
+    /// One entry per successful conversion with the input and output paths,
+    /// the SHA-256 of the written bytes, stitch count, dimensions and color
+    /// count, so converted files can be verified and indexed without reading
+    /// them again. A `.csv` path writes CSV; anything else writes JSON.
+    pub fn manifest<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.manifest = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Add specific input files to convert
+    ///
+    /// ZIP archives are expanded into the designs they contain.
     pub fn input_files(mut self, files: &[PathBuf]) -> Self {
         self.input_files.extend_from_slice(files);
         self
     }
 
-    /// Set the target output format (e.g., "dst", "pes", "jef")
-    pub fn target_format(mut self, format: &str) -> Self {
-        self.target_format = Some(format.to_lowercase());
+    /// Set the target output format (default: DST)
+    ///
+    /// Names parse into a [`Format`]: `"pes".parse()?`.
+    pub fn target_format(mut self, format: Format) -> Self {
+        self.target_format = Some(format);
         self
     }
 
@@ -324,6 +616,88 @@ impl BatchConverter {
         self
     }
 
+    /// Set the number of worker threads (default: available cores)
+    ///
+    /// Only used with parallel processing; values below 1 count as 1.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    /// Stop the conversion when `token` is cancelled
+    ///
+    /// Files already converting finish; files not yet started are reported as
+    /// [`ConversionResult::Skipped`].
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Fail files that take longer than `timeout` to convert (default: no limit)
+    ///
+    /// A timed-out file is reported as [`ConversionResult::Failed`] and the
+    /// batch moves on. Its conversion thread cannot be stopped and keeps running
+    /// in the background until it finishes, but writes no output. The timeout
+    /// covers reading and converting: a file already being written when it
+    /// expires gets as long again to finish, unless the batch is cancelled.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Call `callback` as each file starts and finishes converting
+    ///
+    /// The callback runs on the worker threads; keep it short.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use butabuti::utils::batch::{BatchConverter, FileStatus};
+    ///
+    /// let converter = BatchConverter::new()
+    ///     .input_dir("./designs")
+    ///     .output_dir("./output")
+    ///     .on_progress(|progress| {
+    ///         if let FileStatus::Finished(_) = progress.status {
+    ///             println!("{:.0}% done", progress.fraction() * 100.0);
+    ///         }
+    ///     })
+    ///     .build();
+    /// converter.convert_all()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&BatchProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Send progress updates to a channel
+    ///
+    /// Replaces any [`on_progress`](Self::on_progress) callback. Updates are
+    /// dropped once the receiver hangs up; the conversion carries on.
+    pub fn progress_sender(self, sender: Sender<BatchProgress>) -> Self {
+        self.on_progress(move |progress| {
+            let _ = sender.send(progress.clone());
+        })
+    }
+
+    /// Strip metadata on export, keeping only the given groups
+    ///
+    /// See `EmbPattern::strip_metadata`. Pass an empty slice to remove everything.
+    pub fn strip_metadata(mut self, keep: &[MetadataKey]) -> Self {
+        self.strip_metadata = Some(keep.to_vec());
+        self
+    }
+
+    /// Set the writer options (default: [`WriteSettings::new`])
+    pub fn write_settings(mut self, settings: WriteSettings) -> Self {
+        self.write_settings = settings;
+        self
+    }
+
     /// Build and execute the batch conversion
     pub fn build(self) -> BatchConverterExecutor {
         BatchConverterExecutor { config: self }
@@ -357,71 +731,227 @@ impl BatchConverterExecutor {
         }
 
         // Ensure output directory exists
-        if let Some(ref output_dir) = self.config.output_dir {
+        if let Some(ref output_zip) = self.config.output_zip {
+            if output_zip.exists() && !self.config.overwrite {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("Output archive {} already exists", output_zip.display()),
+                )
+                .into());
+            }
+        } else if let Some(ref output_dir) = self.config.output_dir {
             fs::create_dir_all(output_dir)?;
         }
+        let outputs = SharedOutputs {
+            archive: self.config.output_zip.as_ref().map(|_| Default::default()),
+            manifest: self.config.manifest.as_ref().map(|_| Default::default()),
+        };
+
+        // Convert files on a bounded pool, keeping results in input order
+        let total = input_files.len();
+        let next = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+        let slots = Mutex::new(vec![None; total]);
+
+        let work = || loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(input_file) = input_files.get(index) else {
+                break;
+            };
+            let result = if self.is_cancelled() {
+                ConversionResult::Skipped {
+                    input: input_file.clone(),
+                    reason: "Conversion cancelled".to_string(),
+                }
+            } else {
+                let started = completed.load(Ordering::Relaxed);
+                self.report(input_file, index, started, total, FileStatus::Started);
+                self.convert_one(input_file, &outputs)
+            };
 
-        // Convert files
-        if self.config.parallel {
-            // Parallel processing with Arc to avoid cloning config strings
-            let results_arc = Arc::new(Mutex::new(ConversionResults::new()));
-            let target_format_arc = Arc::new(self.config.target_format.clone());
-            let output_dir_arc = Arc::new(self.config.output_dir.clone());
-            let overwrite = self.config.overwrite;
-
-            let handles: Vec<_> = input_files
-                .into_iter()
-                .map(|input_file| {
-                    let results_clone = Arc::clone(&results_arc);
-                    let target_format = Arc::clone(&target_format_arc);
-                    let output_dir = Arc::clone(&output_dir_arc);
-
-                    std::thread::spawn(move || {
-                        let result = Self::convert_single_file(
-                            &input_file,
-                            target_format.as_ref().as_deref(),
-                            output_dir.as_ref().as_deref(),
-                            overwrite,
-                        );
-                        if let Ok(mut results) = results_clone.lock() {
-                            results.add(result);
-                        }
-                    })
-                })
-                .collect();
-
-            // Wait for all threads
-            for handle in handles {
-                let _ = handle.join(); // Ignore thread panic errors
-            }
+            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            let status = FileStatus::Finished(result.clone());
+            self.report(input_file, index, done, total, status);
+            slots.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+        };
 
-            results = Arc::try_unwrap(results_arc)
-                .ok()
-                .and_then(|m| m.into_inner().ok())
-                .unwrap_or_default();
+        let workers = self.worker_count().min(total);
+        if workers <= 1 {
+            work();
         } else {
-            // Sequential processing
-            for input_file in input_files {
-                let result = Self::convert_single_file(
-                    &input_file,
-                    self.config.target_format.as_deref(),
-                    self.config.output_dir.as_deref(),
-                    self.config.overwrite,
-                );
-                results.add(result);
-            }
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(work);
+                }
+            });
+        }
+
+        let mut converted: Vec<ConversionResult> = slots
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .into_iter()
+            .flatten()
+            .collect();
+        if let (Some(path), Some(archive)) = (&self.config.output_zip, &outputs.archive) {
+            write_output_zip(path, archive, self.target_format(), &mut converted)?;
+        }
+        if let (Some(path), Some(manifest)) = (&self.config.manifest, &outputs.manifest) {
+            write_manifest(path, manifest, &converted)?;
+        }
+        for result in converted {
+            results.add(result);
         }
 
         results.set_total_duration(start.elapsed().as_millis());
         Ok(results)
     }
 
+    /// Number of worker threads to convert with
+    fn worker_count(&self) -> usize {
+        if !self.config.parallel {
+            return 1;
+        }
+        self.config
+            .threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()))
+    }
+
+    /// Pass a progress update to the configured callback
+    fn report(
+        &self,
+        input: &Path,
+        index: usize,
+        completed: usize,
+        total: usize,
+        status: FileStatus,
+    ) {
+        if let Some(callback) = &self.config.progress {
+            callback(&BatchProgress {
+                input: input.to_path_buf(),
+                index,
+                completed,
+                total,
+                status,
+            });
+        }
+    }
+
+    /// Convert one file with the configured timeout, if any
+    fn convert_one(&self, input_file: &Path, outputs: &SharedOutputs) -> ConversionResult {
+        match self.config.timeout {
+            Some(timeout) => self.convert_with_timeout(input_file, timeout, outputs),
+            None => self.convert_isolated(input_file, outputs),
+        }
+    }
+
+    /// Convert one file, reporting a panic as a failed conversion
+    fn convert_isolated(&self, input_file: &Path, outputs: &SharedOutputs) -> ConversionResult {
+        let start = Instant::now();
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            Self::convert_single_file(
+                input_file,
+                self.target_format(),
+                self.config.output_dir.as_deref(),
+                self.config.overwrite,
+                self.config.strip_metadata.as_deref(),
+                self.write_options(),
+                outputs,
+                None,
+            )
+        }))
+        .unwrap_or_else(|_| ConversionResult::Failed {
+            input: input_file.to_path_buf(),
+            error: "Conversion panicked".to_string(),
+            duration_ms: start.elapsed().as_millis(),
+        })
+    }
+
+    /// Convert one file on its own thread, failing it after `timeout`
+    ///
+    /// The timeout covers reading and converting. A worker that times out is
+    /// left running but writes nothing; one already writing when the timeout
+    /// expires gets as long again to finish.
+    fn convert_with_timeout(
+        &self,
+        input_file: &Path,
+        timeout: Duration,
+        outputs: &SharedOutputs,
+    ) -> ConversionResult {
+        let start = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+        let input = input_file.to_path_buf();
+        let target_format = self.target_format();
+        let output_dir = self.config.output_dir.clone();
+        let overwrite = self.config.overwrite;
+        let strip_metadata = self.config.strip_metadata.clone();
+        let options = self.write_options().clone();
+        let outputs = outputs.clone();
+        let stage = Arc::new(Mutex::new(Stage::Converting));
+        let worker_stage = Arc::clone(&stage);
+
+        let spawned = thread::Builder::new()
+            .name("butabuti-convert".to_string())
+            .spawn(move || {
+                let result = Self::convert_single_file(
+                    &input,
+                    target_format,
+                    output_dir.as_deref(),
+                    overwrite,
+                    strip_metadata.as_deref(),
+                    &options,
+                    &outputs,
+                    Some(&worker_stage),
+                );
+                let _ = sender.send(result);
+            });
+
+        let waited = match spawned {
+            Ok(_) => await_conversion(input_file, &receiver, &stage, timeout, || {
+                self.is_cancelled()
+            }),
+            Err(e) => Err(format!("Failed to start conversion thread: {}", e)),
+        };
+        let error = match waited {
+            Ok(result) => return result,
+            Err(error) => error,
+        };
+        ConversionResult::Failed {
+            input: input_file.to_path_buf(),
+            error,
+            duration_ms: start.elapsed().as_millis(),
+        }
+    }
+
+    /// Whether the conversion was cancelled
+    fn is_cancelled(&self) -> bool {
+        self.config
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    /// Target format, DST unless configured
+    fn target_format(&self) -> Format {
+        self.config.target_format.unwrap_or(Format::DST)
+    }
+
+    /// Writer options for the target format
+    fn write_options(&self) -> &WriteOptions {
+        self.config.write_settings.options_for(self.target_format())
+    }
+
     /// Collect all input files based on configuration
     fn collect_input_files(&self) -> Result<Vec<PathBuf>> {
         let mut files = Vec::new();
 
         // Add explicitly specified files
-        files.extend(self.config.input_files.iter().cloned());
+        for path in &self.config.input_files {
+            if is_zip_path(path) && path.is_file() {
+                self.collect_archive_entries(path, &mut files);
+            } else {
+                files.push(path.clone());
+            }
+        }
 
         // Scan input directory if specified
         if let Some(ref input_dir) = self.config.input_dir {
@@ -441,7 +971,9 @@ impl BatchConverterExecutor {
             let entry = entry?;
             let path = entry.path();
 
-            if path.is_file() && self.matches_extension(&path) {
+            if path.is_file() && is_zip_path(&path) {
+                self.collect_archive_entries(&path, files);
+            } else if path.is_file() && self.matches_extension(&path) {
                 files.push(path);
             }
         }
@@ -454,7 +986,9 @@ impl BatchConverterExecutor {
             let entry = entry?;
             let path = entry.path();
 
-            if path.is_file() && self.matches_extension(&path) {
+            if path.is_file() && is_zip_path(&path) {
+                self.collect_archive_entries(&path, files);
+            } else if path.is_file() && self.matches_extension(&path) {
                 files.push(path);
             } else if path.is_dir() {
                 self.collect_files_recursive(&path, files)?;
@@ -463,24 +997,95 @@ impl BatchConverterExecutor {
         Ok(())
     }
 
+    /// Add the designs in a ZIP archive as `archive/entry` inputs
+    ///
+    /// Entries in folders of the archive are only added when scanning
+    /// recursively. An unreadable archive is added as it is, to be reported
+    /// as a failed conversion.
+    fn collect_archive_entries(&self, archive: &Path, files: &mut Vec<PathBuf>) {
+        match self.archive_designs(archive) {
+            Ok(entries) => files.extend(entries.iter().map(|entry| archive.join(entry))),
+            Err(_) => files.push(archive.to_path_buf()),
+        }
+    }
+
+    /// Names of the design entries of an archive that pass the filters
+    fn archive_designs(&self, path: &Path) -> Result<Vec<String>> {
+        let registry = FormatRegistry::new();
+        let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+        let mut designs = Vec::new();
+        for entry in archive.entries().to_vec() {
+            if entry.is_dir() || (!self.config.recursive && entry.name.contains('/')) {
+                continue;
+            }
+            let extension = Path::new(&entry.name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_lowercase());
+
+            let format = archive
+                .read(&entry)
+                .ok()
+                .and_then(|data| archive_design_format(&registry, &data, &entry.name));
+            let Some(format) = format else {
+                continue;
+            };
+
+            let filter = &self.config.input_extensions;
+            if filter.is_empty()
+                || extension.as_ref().is_some_and(|ext| filter.contains(ext))
+                || filter.iter().any(|ext| ext.eq_ignore_ascii_case(format))
+            {
+                designs.push(entry.name);
+            }
+        }
+        Ok(designs)
+    }
+
     /// Check if file matches extension filter
+    ///
+    /// Files without a known extension match when their content is detected as
+    /// one of the filtered formats.
     fn matches_extension(&self, path: &Path) -> bool {
         if self.config.input_extensions.is_empty() {
             return true;
         }
 
-        path.extension()
+        let extension = path
+            .extension()
             .and_then(|ext| ext.to_str())
-            .map(|ext| self.config.input_extensions.contains(&ext.to_lowercase()))
-            .unwrap_or(false)
+            .map(|ext| ext.to_lowercase());
+        if let Some(extension) = &extension {
+            if self.config.input_extensions.contains(extension) {
+                return true;
+            }
+            if FormatRegistry::new()
+                .get_format_by_extension(extension)
+                .is_some()
+            {
+                return false;
+            }
+        }
+
+        // Missing or unknown extension: match on the detected format
+        File::open(path)
+            .ok()
+            .and_then(|mut file| detect_format(&mut file).ok())
+            .and_then(|format| format.registry_name())
+            .is_some_and(|name| self.config.input_extensions.iter().any(|ext| ext == name))
     }
 
     /// Convert a single file
+    #[allow(clippy::too_many_arguments)]
     fn convert_single_file(
         input_path: &Path,
-        target_format: Option<&str>,
+        target_format: Format,
         output_dir: Option<&Path>,
         overwrite: bool,
+        strip_metadata: Option<&[MetadataKey]>,
+        options: &WriteOptions,
+        outputs: &SharedOutputs,
+        stage: Option<&Mutex<Stage>>,
     ) -> ConversionResult {
         let start = Instant::now();
 
@@ -488,7 +1093,7 @@ impl BatchConverterExecutor {
         let output_path = Self::determine_output_path(input_path, target_format, output_dir);
 
         // Check if output already exists and overwrite is disabled
-        if output_path.exists() && !overwrite {
+        if outputs.archive.is_none() && output_path.exists() && !overwrite {
             return ConversionResult::Skipped {
                 input: input_path.to_path_buf(),
                 reason: "Output file already exists".to_string(),
@@ -496,8 +1101,16 @@ impl BatchConverterExecutor {
         }
 
         // Perform conversion
-        match Self::perform_conversion(input_path, &output_path) {
-            Ok(()) => {
+        match Self::perform_conversion(
+            input_path,
+            &output_path,
+            target_format,
+            strip_metadata,
+            options,
+            outputs,
+            stage,
+        ) {
+            Ok(warnings) => {
                 let duration = start.elapsed().as_millis();
                 let file_size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
 
@@ -506,6 +1119,7 @@ impl BatchConverterExecutor {
                     output: output_path,
                     duration_ms: duration,
                     file_size,
+                    warnings,
                 }
             }
             Err(e) => ConversionResult::Failed {
@@ -548,7 +1162,7 @@ impl BatchConverterExecutor {
     /// Determine the output file path
     fn determine_output_path(
         input_path: &Path,
-        target_format: Option<&str>,
+        target_format: Format,
         output_dir: Option<&Path>,
     ) -> PathBuf {
         let file_stem = input_path
@@ -557,26 +1171,89 @@ impl BatchConverterExecutor {
             .map(Self::sanitize_filename)
             .unwrap_or_else(|| "output".to_string());
 
-        let extension = target_format.unwrap_or("dst");
+        let extension = output_extension(target_format);
 
         let output_filename = format!("{}.{}", file_stem, extension);
 
         if let Some(dir) = output_dir {
             dir.join(output_filename)
+        } else if let Some((archive, _)) = archive_member(input_path) {
+            archive.with_file_name(output_filename)
         } else {
             input_path.with_file_name(output_filename)
         }
     }
 
-    /// Perform the actual conversion
-    fn perform_conversion(input_path: &Path, output_path: &Path) -> Result<()> {
+    /// Perform the actual conversion, returning read and write warnings
+    fn perform_conversion(
+        input_path: &Path,
+        output_path: &Path,
+        format: Format,
+        strip_metadata: Option<&[MetadataKey]>,
+        options: &WriteOptions,
+        outputs: &SharedOutputs,
+        stage: Option<&Mutex<Stage>>,
+    ) -> Result<Vec<String>> {
         // Read the input file
-        let pattern = read_embroidery_file(input_path)?;
+        let (mut pattern, read_warnings) = read_embroidery_file_with_warnings(input_path)?;
 
-        // Write the output file
-        write_embroidery_file(&pattern, output_path)?;
+        if let Some(keep) = strip_metadata {
+            pattern.strip_metadata(keep);
+        }
 
-        Ok(())
+        // A timed out conversion must not write its output after all
+        if let Some(stage) = stage {
+            let mut stage = stage.lock().unwrap_or_else(|e| e.into_inner());
+            if *stage == Stage::Abandoned {
+                return Err(Error::io("Conversion abandoned after timeout"));
+            }
+            *stage = Stage::Writing;
+        }
+
+        // Write the output file, or hold it for the output archive
+        let report = if outputs.archive.is_none() && outputs.manifest.is_none() {
+            write_embroidery_file(&pattern, output_path, format, options)?
+        } else {
+            let (data, report) = write_embroidery_bytes(&pattern, format, options)?;
+            if let Some(manifest) = &outputs.manifest {
+                let (min_x, min_y, max_x, max_y) = pattern.bounds();
+                let entry = ManifestEntry {
+                    input: input_path.display().to_string(),
+                    output: output_path.display().to_string(),
+                    sha256: sha256_hex(&data),
+                    stitch_count: pattern.count_stitches(),
+                    width_mm: (max_x - min_x) / 10.0,
+                    height_mm: (max_y - min_y) / 10.0,
+                    color_count: pattern.threads().len(),
+                };
+                manifest
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(input_path.to_path_buf(), entry);
+            }
+            match &outputs.archive {
+                Some(archive) => {
+                    archive
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(input_path.to_path_buf(), data);
+                }
+                None => {
+                    if let Some(parent) = output_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(output_path, data)?;
+                }
+            }
+            report
+        };
+
+        let mut warnings: Vec<String> = read_warnings
+            .iter()
+            .map(|w| format!("read: {}", w))
+            .collect();
+        warnings.extend(write_warnings(&report));
+        Ok(warnings)
     }
 }
 
@@ -584,8 +1261,10 @@ impl BatchConverterExecutor {
 pub struct MultiFormatExporter {
     output_dir: Option<PathBuf>,
     base_name: Option<String>,
-    formats: Vec<String>,
+    formats: Vec<Format>,
     overwrite: bool,
+    strip_metadata: Option<Vec<MetadataKey>>,
+    write_settings: WriteSettings,
 }
 
 impl MultiFormatExporter {
@@ -596,6 +1275,8 @@ impl MultiFormatExporter {
             base_name: None,
             formats: Vec::new(),
             overwrite: false,
+            strip_metadata: None,
+            write_settings: WriteSettings::new(),
         }
     }
 
@@ -612,8 +1293,8 @@ impl MultiFormatExporter {
     }
 
     /// Set the target formats to export
-    pub fn formats(mut self, formats: &[&str]) -> Self {
-        self.formats = formats.iter().map(|s| s.to_lowercase()).collect();
+    pub fn formats(mut self, formats: &[Format]) -> Self {
+        self.formats = formats.to_vec();
         self
     }
 
@@ -623,6 +1304,20 @@ impl MultiFormatExporter {
         self
     }
 
+    /// Strip metadata from exported files, keeping only the given groups
+    ///
+    /// The source pattern is not modified.
+    pub fn strip_metadata(mut self, keep: &[MetadataKey]) -> Self {
+        self.strip_metadata = Some(keep.to_vec());
+        self
+    }
+
+    /// Set the writer options (default: [`WriteSettings::new`])
+    pub fn write_settings(mut self, settings: WriteSettings) -> Self {
+        self.write_settings = settings;
+        self
+    }
+
     /// Build and execute the export
     pub fn build(self) -> MultiFormatExporterExecutor {
         MultiFormatExporterExecutor { config: self }
@@ -654,14 +1349,26 @@ impl MultiFormatExporterExecutor {
 
         let base_name = self.config.base_name.as_deref().unwrap_or("pattern");
 
+        let stripped;
+        let pattern = match &self.config.strip_metadata {
+            Some(keep) => {
+                let mut copy = pattern.clone();
+                copy.strip_metadata(keep);
+                stripped = copy;
+                &stripped
+            }
+            None => pattern,
+        };
+
         // Ensure output directory exists
         if let Some(ref output_dir) = self.config.output_dir {
             fs::create_dir_all(output_dir)?;
         }
 
         // Export to each format
-        for format in &self.config.formats {
-            let output_filename = format!("{}.{}", base_name, format);
+        for &format in &self.config.formats {
+            let extension = output_extension(format);
+            let output_filename = format!("{}.{}", base_name, extension);
             let output_path = if let Some(ref dir) = self.config.output_dir {
                 dir.join(output_filename)
             } else {
@@ -673,23 +1380,25 @@ impl MultiFormatExporterExecutor {
             // Check if file exists and overwrite is disabled
             if output_path.exists() && !self.config.overwrite {
                 results.add(ConversionResult::Skipped {
-                    input: PathBuf::from(format!("pattern.{}", format)),
+                    input: PathBuf::from(format!("pattern.{}", extension)),
                     reason: "Output file already exists".to_string(),
                 });
                 continue;
             }
 
             // Export to format
-            match write_embroidery_file(pattern, &output_path) {
-                Ok(()) => {
+            let options = self.config.write_settings.options_for(format);
+            match write_embroidery_file(pattern, &output_path, format, options) {
+                Ok(report) => {
                     let duration = export_start.elapsed().as_millis();
                     let file_size = fs::metadata(&output_path).map(|m| m.len()).unwrap_or(0);
 
                     results.add(ConversionResult::Success {
                         input: PathBuf::from(base_name),
-                        output: output_path,
+                        output: output_path.clone(),
                         duration_ms: duration,
                         file_size,
+                        warnings: write_warnings(&report).collect(),
                     });
                 }
                 Err(e) => {
@@ -707,86 +1416,597 @@ impl MultiFormatExporterExecutor {
     }
 }
 
-/// Read an embroidery file, auto-detecting the format
-fn read_embroidery_file(path: &Path) -> Result<EmbPattern> {
-    let extension = path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .map(|s| s.to_lowercase())
-        .ok_or_else(|| Error::UnsupportedFormat("No file extension".to_string()))?;
+/// Converts designs as they arrive in a hot folder
+///
+/// Watches the input directory of a [`BatchConverter`] and converts new or
+/// changed files with its configuration: target format, output directory,
+/// extension filter, write settings, timeout and progress callback. A file is
+/// converted once no change has been seen for the debounce period, so copies
+/// still in progress are left alone. Failed conversions are retried after a
+/// delay before being reported.
+///
+/// Files are converted one at a time. Progress updates count the files queued
+/// so far as the total. `output_zip` and `manifest` only apply to
+/// [`BatchConverterExecutor::convert_all`] and are ignored here.
+///
+/// # Example
+///
+/// ```no_run
+/// use butabuti::formats::Format;
+/// use butabuti::utils::batch::{BatchConverter, CancellationToken, WatchConverter};
+/// use std::time::Duration;
+///
+/// let token = CancellationToken::new();
+/// let converter = BatchConverter::new()
+///     .input_dir("./hot")
+///     .output_dir("./converted")
+///     .target_format(Format::DST)
+///     .overwrite(true)
+///     .cancellation_token(token.clone());
+///
+/// // Runs until the token is cancelled
+/// WatchConverter::new(converter)
+///     .debounce(Duration::from_secs(2))
+///     .run()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "watch")]
+pub struct WatchConverter {
+    converter: BatchConverter,
+    debounce: Duration,
+    max_retries: usize,
+    retry_delay: Duration,
+    process_existing: bool,
+}
 
-    let mut file = BufReader::new(File::open(path)?);
+#[cfg(feature = "watch")]
+impl WatchConverter {
+    /// Watch with the configuration of `converter`, which needs an input directory
+    pub fn new(converter: BatchConverter) -> Self {
+        Self {
+            converter,
+            debounce: Duration::from_millis(500),
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+            process_existing: true,
+        }
+    }
 
-    match extension.as_str() {
-        "dst" => readers::dst::read(&mut file, None),
-        "pes" => {
-            let mut pattern = EmbPattern::new();
-            readers::pes::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+    /// Time a file must go unchanged before it is converted (default: 500 ms)
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Times a failed conversion is retried before it is reported (default: 3)
+    pub fn max_retries(mut self, retries: usize) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Wait between retries of a failed conversion (default: 1 s)
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Convert files already in the input directory on start (default: true)
+    pub fn process_existing(mut self, process: bool) -> Self {
+        self.process_existing = process;
+        self
+    }
+
+    /// Watch and convert until the converter's cancellation token is cancelled
+    ///
+    /// Without a cancellation token this runs until the watcher fails.
+    pub fn run(self) -> Result<()> {
+        use notify::{EventKind, RecursiveMode, Watcher};
+
+        let input_dir = self.converter.input_dir.clone().ok_or_else(|| {
+            Error::InvalidPattern("WatchConverter needs an input directory".to_string())
+        })?;
+        let executor = self.converter.build();
+        let watch_error = |e: notify::Error| Error::io(format!("Watching input directory: {}", e));
+
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+        let mode = if executor.config.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(&input_dir, mode).map_err(watch_error)?;
+
+        let mut queue = WatchQueue::new(self.debounce);
+        if self.process_existing {
+            for file in executor.collect_input_files()? {
+                queue.touch(file, Instant::now());
+            }
         }
-        "exp" => readers::exp::read(&mut file),
-        "jef" => readers::jef::read(&mut file, None),
-        "vp3" => {
-            let mut pattern = EmbPattern::new();
-            readers::vp3::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+
+        let poll = self.debounce.min(WATCH_POLL_INTERVAL);
+        let (mut queued, mut completed) = (0, 0);
+        while !executor.is_cancelled() {
+            match events.recv_timeout(poll) {
+                Ok(Ok(event)) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in &event.paths {
+                            for file in executor.watched_files(path) {
+                                queue.touch(file, Instant::now());
+                            }
+                        }
+                    }
+                }
+                // Events may have been lost, so look at everything again
+                Ok(Err(_)) => {
+                    for file in executor.collect_input_files().unwrap_or_default() {
+                        queue.touch(file, Instant::now());
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::io("File watcher stopped"));
+                }
+            }
+
+            for file in queue.ready(Instant::now()) {
+                if executor.is_cancelled() {
+                    break;
+                }
+                if !file.is_file() && archive_member(&file).is_none() {
+                    queue.forget(&file);
+                    continue;
+                }
+                if !queue.is_retry(&file) {
+                    queued += 1;
+                }
+                let index = queued - 1;
+                executor.report(&file, index, completed, queued, FileStatus::Started);
+                let result = executor.convert_one(&file, &SharedOutputs::default());
+                if matches!(result, ConversionResult::Failed { .. })
+                    && queue.retry(&file, Instant::now() + self.retry_delay, self.max_retries)
+                {
+                    continue;
+                }
+                queue.forget(&file);
+                completed += 1;
+                let status = FileStatus::Finished(result);
+                executor.report(&file, index, completed, queued, status);
+            }
         }
-        "pec" => readers::pec::read(&mut file),
-        "json" => readers::json::read(&mut file),
-        "csv" => {
-            let mut pattern = EmbPattern::new();
-            readers::csv::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+        Ok(())
+    }
+}
+
+/// Longest wait for file events before checking for ready files and cancellation
+#[cfg(feature = "watch")]
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[cfg(feature = "watch")]
+impl BatchConverterExecutor {
+    /// Inputs to convert for a changed path in the watched directory
+    ///
+    /// Skips the output directory and files that would be converted onto
+    /// themselves, so the converter's own output does not trigger it again.
+    fn watched_files(&self, path: &Path) -> Vec<PathBuf> {
+        let in_output_dir = self
+            .config
+            .output_dir
+            .as_ref()
+            .is_some_and(|dir| path.starts_with(dir));
+        if in_output_dir || !path.is_file() {
+            return Vec::new();
         }
-        "xxx" => {
-            let mut pattern = EmbPattern::new();
-            readers::xxx::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+        let mut files = Vec::new();
+        if is_zip_path(path) {
+            self.collect_archive_entries(path, &mut files);
+        } else if self.matches_extension(path) {
+            files.push(path.to_path_buf());
         }
-        "u01" => {
-            let mut pattern = EmbPattern::new();
-            readers::u01::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+        let output_dir = self.config.output_dir.as_deref();
+        files.retain(|file| {
+            Self::determine_output_path(file, self.target_format(), output_dir) != *file
+        });
+        files
+    }
+}
+
+/// Files waiting to be converted by a [`WatchConverter`]
+#[cfg(feature = "watch")]
+struct WatchQueue {
+    debounce: Duration,
+    /// When each file is due for conversion
+    due: HashMap<PathBuf, Instant>,
+    /// Retries used by files that failed to convert
+    retries: HashMap<PathBuf, usize>,
+}
+
+#[cfg(feature = "watch")]
+impl WatchQueue {
+    fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            due: HashMap::new(),
+            retries: HashMap::new(),
         }
-        "tbf" => {
-            let mut pattern = EmbPattern::new();
-            readers::tbf::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+    }
+
+    /// Record a change to `path`, restarting its debounce and its retries
+    fn touch(&mut self, path: PathBuf, now: Instant) {
+        self.retries.remove(&path);
+        self.due.insert(path, now + self.debounce);
+    }
+
+    /// Take the files due by `now`, in path order
+    fn ready(&mut self, now: Instant) -> Vec<PathBuf> {
+        let mut ready: Vec<PathBuf> = self
+            .due
+            .iter()
+            .filter(|(_, due)| **due <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready.sort();
+        for path in &ready {
+            self.due.remove(path);
         }
-        "col" => {
-            let mut pattern = EmbPattern::new();
-            readers::col::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+        ready
+    }
+
+    /// Whether `path` is being retried after a failure
+    fn is_retry(&self, path: &Path) -> bool {
+        self.retries.contains_key(path)
+    }
+
+    /// Schedule another attempt at `at` unless `max_retries` are used up
+    fn retry(&mut self, path: &Path, at: Instant, max_retries: usize) -> bool {
+        let used = self.retries.entry(path.to_path_buf()).or_default();
+        if *used >= max_retries {
+            return false;
         }
-        "edr" => {
-            let mut pattern = EmbPattern::new();
-            readers::edr::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+        *used += 1;
+        self.due.insert(path.to_path_buf(), at);
+        true
+    }
+
+    /// Drop the retry count of a file that is done
+    fn forget(&mut self, path: &Path) {
+        self.retries.remove(path);
+    }
+}
+
+/// Converted files awaiting the output archive, by input path
+type ArchiveBuffer = Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>;
+
+/// Manifest entries of converted files, by input path
+type ManifestBuffer = Arc<Mutex<HashMap<PathBuf, ManifestEntry>>>;
+
+/// How far a conversion running under a timeout has got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    /// Reading and converting the input
+    Converting,
+    /// Writing the output, which gets extra time past the timeout
+    Writing,
+    /// Timed out; the worker must not write
+    Abandoned,
+}
+
+/// Longest wait for a timed conversion before checking for cancellation
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Wait for the result of a conversion running under `timeout`
+///
+/// A conversion still reading or converting when the timeout expires or the
+/// batch is cancelled is abandoned, so its worker won't write. One already
+/// writing gets another `timeout`, unless the batch is cancelled; a write that
+/// outlasts it may leave a partial file. Returns the error of a failed wait.
+fn await_conversion(
+    input: &Path,
+    receiver: &mpsc::Receiver<ConversionResult>,
+    stage: &Mutex<Stage>,
+    timeout: Duration,
+    is_cancelled: impl Fn() -> bool,
+) -> std::result::Result<ConversionResult, String> {
+    let mut deadline = Instant::now() + timeout;
+    let mut extended = false;
+    loop {
+        let wait = deadline
+            .saturating_duration_since(Instant::now())
+            .min(TIMEOUT_POLL_INTERVAL);
+        match receiver.recv_timeout(wait) {
+            Ok(result) => return Ok(result),
+            Err(RecvTimeoutError::Disconnected) => return Err("Conversion panicked".to_string()),
+            Err(RecvTimeoutError::Timeout) => {}
         }
-        "inf" => {
-            let mut pattern = EmbPattern::new();
-            readers::inf::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+        let cancelled = is_cancelled();
+        if !cancelled && Instant::now() < deadline {
+            continue;
         }
-        "gcode" => {
-            let mut pattern = EmbPattern::new();
-            readers::gcode::read(&mut file, &mut pattern)?;
-            Ok(pattern)
+
+        let mut stage = stage.lock().unwrap_or_else(|e| e.into_inner());
+        match (*stage, cancelled) {
+            (Stage::Writing, true) => {
+                return Err("Conversion cancelled while writing output".to_string())
+            }
+            (Stage::Writing, false) if !extended => {
+                extended = true;
+                deadline = Instant::now() + timeout;
+            }
+            (Stage::Writing, false) => {
+                return Err(format!(
+                    "Writing output timed out after {:.1}s",
+                    timeout.as_secs_f64()
+                ))
+            }
+            (_, true) => {
+                *stage = Stage::Abandoned;
+                return Ok(ConversionResult::Skipped {
+                    input: input.to_path_buf(),
+                    reason: "Conversion cancelled".to_string(),
+                });
+            }
+            (_, false) => {
+                // The worker checks this before writing and leaves no output
+                *stage = Stage::Abandoned;
+                return Err(format!(
+                    "Conversion timed out after {:.1}s",
+                    timeout.as_secs_f64()
+                ));
+            }
         }
-        _ => Err(Error::UnsupportedFormat(format!(
-            "Unsupported input format: {}",
-            extension
-        ))),
     }
 }
 
-/// Write an embroidery file, auto-detecting the format from extension
-fn write_embroidery_file(pattern: &EmbPattern, path: &Path) -> Result<()> {
-    let extension = path
+/// Where conversions leave output beyond their result, shared across workers
+#[derive(Clone, Default)]
+struct SharedOutputs {
+    /// Set when writing an output archive
+    archive: Option<ArchiveBuffer>,
+    /// Set when writing a manifest
+    manifest: Option<ManifestBuffer>,
+}
+
+/// One converted file in the batch manifest
+#[derive(Debug, Clone, Serialize)]
+struct ManifestEntry {
+    input: String,
+    output: String,
+    /// Lowercase hex SHA-256 of the written bytes
+    sha256: String,
+    stitch_count: usize,
+    width_mm: f64,
+    height_mm: f64,
+    color_count: usize,
+}
+
+/// Column order of the CSV manifest
+const MANIFEST_COLUMNS: &str = "input,output,sha256,stitch_count,width_mm,height_mm,color_count";
+
+/// Write the manifest of the successful conversions, in result order
+///
+/// Output paths come from the results, so entries packed into an output
+/// archive point inside it.
+fn write_manifest(
+    path: &Path,
+    manifest: &ManifestBuffer,
+    results: &[ConversionResult],
+) -> Result<()> {
+    let mut entries = manifest.lock().unwrap_or_else(|e| e.into_inner());
+    let rows: Vec<ManifestEntry> = results
+        .iter()
+        .filter_map(|result| match result {
+            ConversionResult::Success { input, output, .. } => {
+                let mut entry = entries.remove(input.as_path())?;
+                entry.output = output.display().to_string();
+                Some(entry)
+            }
+            _ => None,
+        })
+        .collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    let is_csv = path
         .extension()
         .and_then(|ext| ext.to_str())
-        .map(|s| s.to_lowercase())
-        .ok_or_else(|| Error::UnsupportedFormat("No file extension".to_string()))?;
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        writeln!(writer, "{}", MANIFEST_COLUMNS)?;
+        for row in &rows {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{}",
+                quote_csv_field(&row.input),
+                quote_csv_field(&row.output),
+                row.sha256,
+                row.stitch_count,
+                row.width_mm,
+                row.height_mm,
+                row.color_count
+            )?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut writer, &rows)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Lowercase hex SHA-256 digest
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Write the successful conversions into a ZIP archive at `path`
+///
+/// Entries are added in result order and named after the output files,
+/// with a numeric suffix where two inputs share a name. Each result's
+/// output path and size are updated to the archive entry.
+fn write_output_zip(
+    path: &Path,
+    archive: &ArchiveBuffer,
+    format: Format,
+    results: &mut [ConversionResult],
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut buffers = archive.lock().unwrap_or_else(|e| e.into_inner());
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let mut used = HashSet::new();
+    for result in results.iter_mut() {
+        let ConversionResult::Success {
+            input,
+            output,
+            file_size,
+            ..
+        } = result
+        else {
+            continue;
+        };
+        let Some(data) = buffers.remove(input.as_path()) else {
+            continue;
+        };
+        let name = output
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("output");
+        let entry = entry_name(name, output_extension(format), &mut used);
+        zip.add(&entry, &data)?;
+        *output = path.join(&entry);
+        *file_size = data.len() as u64;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Whether a path names a ZIP archive
+fn is_zip_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Split an `archive.zip/entry` input path into the archive and entry name
+fn archive_member(path: &Path) -> Option<(&Path, String)> {
+    if path.exists() {
+        return None;
+    }
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| is_zip_path(ancestor) && ancestor.is_file())?;
+    let entry = path
+        .strip_prefix(archive)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((archive, entry))
+}
+
+/// Read one entry of a ZIP archive
+fn read_archive_member(archive: &Path, name: &str) -> Result<Vec<u8>> {
+    let mut zip = ZipArchive::new(BufReader::new(File::open(archive)?))?;
+    let entry = zip
+        .entries()
+        .iter()
+        .find(|entry| entry.name == name)
+        .cloned()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found in {}", name, archive.display()),
+            )
+        })?;
+    zip.read(&entry)
+}
+
+/// Read an embroidery file, auto-detecting the format
+#[cfg(test)]
+fn read_embroidery_file(path: &Path) -> Result<EmbPattern> {
+    read_embroidery_file_with_warnings(path).map(|(pattern, _)| pattern)
+}
+
+/// Read an embroidery file, collecting reader warnings where the format reports them
+///
+/// The format is resolved like [`EmbPattern::read`]: by extension, with the
+/// magic bytes taking over for missing or mismatched extensions. A path
+/// inside a ZIP archive (`designs.zip/rose.pes`) reads that entry.
+///
+/// Files are read through a buffer even with the `mmap` feature: inputs may
+/// still be copied in or replaced while they are converted, which a memory
+/// map doesn't survive.
+fn read_embroidery_file_with_warnings(path: &Path) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+    if let Some((archive, entry)) = archive_member(path) {
+        let data = read_archive_member(archive, &entry)?;
+        return read_detected(&mut Cursor::new(data), path);
+    }
+    let mut file = BufReader::new(File::open(path)?);
+    let (mut pattern, warnings) = read_detected(&mut file, path)?;
+    // As with the default read options, pick up thread colors from a sibling
+    load_color_file(&mut pattern, path)?;
+    Ok((pattern, warnings))
+}
+
+/// Read a stream, resolving its format from `path` and the magic bytes
+fn read_detected<R: Read + Seek>(
+    file: &mut R,
+    path: &Path,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+    let registry = FormatRegistry::new();
+    let (first, fallback) = registry.resolve_read_format(file, Some(path))?;
+
+    let result = read_as(&registry, file, first);
+    let Some(fallback) = fallback else {
+        return result;
+    };
+    match result {
+        Ok((pattern, warnings)) if !pattern.stitches().is_empty() => Ok((pattern, warnings)),
+        result => {
+            file.seek(SeekFrom::Start(0))?;
+            match read_as(&registry, file, fallback) {
+                Ok((pattern, warnings)) if !pattern.stitches().is_empty() => {
+                    Ok((pattern, warnings))
+                }
+                _ => result,
+            }
+        }
+    }
+}
+
+/// Read a stream in the given format with the batch reader options
+fn read_as<R: Read + Seek>(
+    registry: &FormatRegistry,
+    file: &mut R,
+    format: &str,
+) -> Result<(EmbPattern, Vec<ReadWarning>)> {
+    match format.parse::<Format>() {
+        Ok(builtin) if builtin.can_read() => builtin.read_with_warnings(file, &ReadOptions::new()),
+        _ => Ok((registry.read_pattern(file, format)?, Vec::new())),
+    }
+}
+
+/// File extension written for a format
+fn output_extension(format: Format) -> &'static str {
+    format.extensions().first().copied().unwrap_or("dat")
+}
+
+/// Write an embroidery file in the given format
+fn write_embroidery_file(
+    pattern: &EmbPattern,
+    path: &Path,
+    format: Format,
+    options: &WriteOptions,
+) -> Result<WriteReport> {
+    check_writable(pattern, format)?;
 
     // Ensure parent directory exists
     if let Some(parent) = path.parent() {
@@ -796,36 +2016,49 @@ fn write_embroidery_file(pattern: &EmbPattern, path: &Path) -> Result<()> {
     let file = File::create(path)?;
     let mut writer = BufWriter::new(file);
 
-    match extension.as_str() {
-        "dst" => writers::dst::write(&mut writer, pattern, false, 3),
-        "pes" => writers::pes::write_pes(pattern, &mut writer, writers::pes::PesVersion::V1, false),
-        "exp" => writers::exp::write(&mut writer, pattern),
-        "jef" => writers::jef::write(&mut writer, pattern, false, 0, ""),
-        "vp3" => writers::vp3::write(&mut writer, pattern),
-        "xxx" => writers::xxx::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "u01" => writers::u01::write(pattern, &mut writer),
-        "pec" => writers::pec::write(&mut writer, pattern),
-        "tbf" => writers::tbf::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "col" => writers::col::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "edr" => writers::edr::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "inf" => writers::inf::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "json" => writers::json::write(&mut writer, pattern),
-        "csv" => writers::csv::write(&mut writer, pattern, writers::csv::CsvVersion::Default),
-        "txt" => writers::txt::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "svg" => writers::svg::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string())),
-        "gcode" => {
-            writers::gcode::write(pattern, &mut writer).map_err(|e| Error::Parse(e.to_string()))
-        }
-        _ => Err(Error::UnsupportedFormat(format!(
+    let report = format.write_with_report(pattern, &mut writer, options)?;
+    writer.flush()?;
+    if let Some(color_format) = options.color_file {
+        write_color_file(pattern, path, color_format)?;
+    }
+    Ok(report)
+}
+
+/// Write a pattern in the given format to memory
+fn write_embroidery_bytes(
+    pattern: &EmbPattern,
+    format: Format,
+    options: &WriteOptions,
+) -> Result<(Vec<u8>, WriteReport)> {
+    check_writable(pattern, format)?;
+    let mut buffer = Cursor::new(Vec::new());
+    let report = format.write_with_report(pattern, &mut buffer, options)?;
+    Ok((buffer.into_inner(), report))
+}
+
+/// Batch warnings for the adjustments of a write
+fn write_warnings(report: &WriteReport) -> impl Iterator<Item = String> + '_ {
+    report
+        .changes
+        .iter()
+        .map(move |change| format!("write: {} ({})", change, report.format))
+}
+
+/// Reject formats without a writer and patterns the format cannot hold
+fn check_writable(pattern: &EmbPattern, format: Format) -> Result<()> {
+    if !format.can_write() {
+        return Err(Error::UnsupportedFormat(format!(
             "Unsupported output format: {}",
-            extension
-        ))),
+            format
+        )));
     }
+    FormatRegistry::new().check_limits(pattern, format.name())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::constants::STITCH;
 
     #[test]
     fn test_sanitize_filename() {
@@ -887,6 +2120,7 @@ mod tests {
             output: PathBuf::from("test.pes"),
             duration_ms: 100,
             file_size: 1024,
+            warnings: vec!["write: metadata not stored in PES: company".to_string()],
         });
 
         results.add(ConversionResult::Failed {
@@ -897,6 +2131,7 @@ mod tests {
 
         assert_eq!(results.success_count(), 1);
         assert_eq!(results.failed_count(), 1);
+        assert_eq!(results.warning_count(), 1);
         assert_eq!(results.success_rate(), 0.5);
     }
 
@@ -905,13 +2140,13 @@ mod tests {
         let converter = BatchConverter::new()
             .input_dir("./test")
             .output_dir("./output")
-            .target_format("dst")
+            .target_format(Format::DST)
             .overwrite(true)
             .build();
 
         assert!(converter.config.input_dir.is_some());
         assert!(converter.config.output_dir.is_some());
-        assert_eq!(converter.config.target_format, Some("dst".to_string()));
+        assert_eq!(converter.config.target_format, Some(Format::DST));
         assert!(converter.config.overwrite);
     }
 
@@ -920,11 +2155,722 @@ mod tests {
         let exporter = MultiFormatExporter::new()
             .output_dir("./exports")
             .base_name("design")
-            .formats(&["dst", "pes", "jef"])
+            .formats(&[Format::DST, Format::PES, Format::JEF])
             .build();
 
         assert!(exporter.config.output_dir.is_some());
         assert_eq!(exporter.config.base_name, Some("design".to_string()));
         assert_eq!(exporter.config.formats.len(), 3);
     }
+
+    #[test]
+    fn test_export_strips_metadata() {
+        let dir = std::env::temp_dir().join(format!("butabuti_strip_{}", std::process::id()));
+
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Rose");
+        pattern.set_author("Jane Doe");
+        pattern.add_stitch_absolute(crate::core::constants::STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(crate::core::constants::STITCH, 10.0, 10.0);
+
+        let results = MultiFormatExporter::new()
+            .output_dir(&dir)
+            .base_name("shared")
+            .formats(&[Format::JSON])
+            .overwrite(true)
+            .strip_metadata(&[MetadataKey::Name])
+            .build()
+            .export(&pattern)
+            .unwrap();
+        assert_eq!(results.success_count(), 1);
+
+        let exported = read_embroidery_file(&dir.join("shared.json")).unwrap();
+        assert_eq!(exported.title(), Some("Rose"));
+        assert!(exported.author().is_none());
+        assert_eq!(pattern.author(), Some("Jane Doe"));
+
+        let results = BatchConverter::new()
+            .input_files(&[dir.join("shared.json")])
+            .output_dir(dir.join("out"))
+            .target_format(Format::JSON)
+            .parallel(false)
+            .strip_metadata(&[])
+            .build()
+            .convert_all()
+            .unwrap();
+        assert_eq!(results.success_count(), 1);
+
+        let converted = read_embroidery_file(&dir.join("out").join("shared.json")).unwrap();
+        assert_eq!(converted.metadata().count(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_settings_reach_writers() {
+        let dir = std::env::temp_dir().join(format!("butabuti_settings_{}", std::process::id()));
+
+        let mut pattern = EmbPattern::new();
+        pattern.set_author("Jane Doe");
+        pattern.add_thread(crate::core::thread::EmbThread::new(0x00FF00));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 20.0, 20.0);
+        pattern.end();
+
+        let settings = WriteSettings::new()
+            .pes_version(PesVersion::V6)
+            .format(Format::DST, WriteOptions::new().dst_extended_header(true));
+        let results = MultiFormatExporter::new()
+            .output_dir(&dir)
+            .base_name("settings")
+            .formats(&[Format::PES, Format::DST])
+            .overwrite(true)
+            .write_settings(settings.clone())
+            .build()
+            .export(&pattern)
+            .unwrap();
+        assert_eq!(results.success_count(), 2);
+
+        let pes = fs::read(dir.join("settings.pes")).unwrap();
+        assert_eq!(&pes[..8], b"#PES0060");
+        let dst = fs::read(dir.join("settings.dst")).unwrap();
+        assert!(String::from_utf8_lossy(&dst[..512]).contains("AU:Jane Doe"));
+
+        // Batch conversion applies the target format's options
+        let results = BatchConverter::new()
+            .input_files(&[dir.join("settings.dst")])
+            .output_dir(dir.join("out"))
+            .target_format(Format::PES)
+            .parallel(true)
+            .write_settings(settings.pes_version(PesVersion::V1))
+            .build()
+            .convert_all()
+            .unwrap();
+        assert_eq!(results.success_count(), 1);
+        let pes = fs::read(dir.join("out").join("settings.pes")).unwrap();
+        assert_eq!(&pes[..8], b"#PES0001");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_color_files_pair_with_designs() {
+        let dir = std::env::temp_dir().join(format!("butabuti_colors_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(crate::core::thread::EmbThread::new(0x3366CC));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 20.0, 20.0);
+        pattern.end();
+
+        // A plain DST with its colors in a COL file
+        let settings = WriteSettings::new().format(
+            Format::DST,
+            WriteOptions::new()
+                .dst_extended_header(false)
+                .color_file(Some(Format::COL)),
+        );
+        let results = MultiFormatExporter::new()
+            .output_dir(&dir)
+            .base_name("tulip")
+            .formats(&[Format::DST])
+            .write_settings(settings)
+            .build()
+            .export(&pattern)
+            .unwrap();
+        assert_eq!(results.success_count(), 1);
+        assert!(dir.join("tulip.col").is_file());
+
+        let results = BatchConverter::new()
+            .input_files(&[dir.join("tulip.dst")])
+            .output_dir(dir.join("out"))
+            .target_format(Format::JSON)
+            .build()
+            .convert_all()
+            .unwrap();
+        assert_eq!(results.success_count(), 1);
+        let json = read_embroidery_file_with_warnings(&dir.join("out").join("tulip.json"))
+            .unwrap()
+            .0;
+        assert_eq!(json.threads()[0].color, 0x3366CC);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reads_by_content_without_extension() {
+        let dir = std::env::temp_dir().join(format!("butabuti_detect_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(crate::core::thread::EmbThread::new(0x0000FF));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 30.0, 10.0);
+        pattern.end();
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        FormatRegistry::new()
+            .write_pattern(&pattern, &mut bytes, "pes")
+            .unwrap();
+        fs::write(dir.join("design"), bytes.get_ref()).unwrap();
+        fs::write(dir.join("renamed.dst"), bytes.get_ref()).unwrap();
+        fs::write(dir.join("notes.txt"), "not a design").unwrap();
+
+        assert_eq!(
+            read_embroidery_file(&dir.join("renamed.dst"))
+                .unwrap()
+                .count_stitches(),
+            2
+        );
+
+        let results = BatchConverter::new()
+            .input_dir(&dir)
+            .input_extensions(&["pes"])
+            .output_dir(dir.join("out"))
+            .target_format(Format::JSON)
+            .parallel(false)
+            .build()
+            .convert_all()
+            .unwrap();
+        assert_eq!(results.success_count(), 1);
+        assert!(dir.join("out").join("design.json").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bounded_pool_reports_progress() {
+        let dir = std::env::temp_dir().join(format!("butabuti_pool_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 30.0, 10.0);
+        pattern.end();
+        let mut files = Vec::new();
+        for i in 0..6 {
+            let path = dir.join(format!("design{}.json", i));
+            write_embroidery_file(&pattern, &path, Format::JSON, &WriteOptions::new()).unwrap();
+            files.push(path);
+        }
+        files.push(dir.join("missing.json"));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let results = BatchConverter::new()
+            .input_files(&files)
+            .output_dir(dir.join("out"))
+            .target_format(Format::CSV)
+            .threads(2)
+            .progress_sender(sender)
+            .build()
+            .convert_all()
+            .unwrap();
+
+        // Results come back in input order
+        assert_eq!(results.success_count(), 6);
+        assert_eq!(results.failed_count(), 1);
+        let inputs: Vec<_> = results
+            .results()
+            .iter()
+            .map(|r| match r {
+                ConversionResult::Success { input, .. }
+                | ConversionResult::Failed { input, .. }
+                | ConversionResult::Skipped { input, .. } => input.clone(),
+            })
+            .collect();
+        assert_eq!(inputs, files);
+
+        // Every file starts and finishes once
+        let updates: Vec<BatchProgress> = receiver.iter().collect();
+        assert_eq!(updates.len(), 14);
+        let finished: Vec<_> = updates
+            .iter()
+            .filter(|u| matches!(u.status, FileStatus::Finished(_)))
+            .collect();
+        assert_eq!(finished.len(), 7);
+        assert!(finished.iter().all(|u| u.total == 7));
+        assert_eq!(finished.iter().map(|u| u.completed).max(), Some(7));
+        assert!(finished.iter().any(|u| u.fraction() == 1.0));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sequential_progress_callback() {
+        let dir = std::env::temp_dir().join(format!("butabuti_progress_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = [dir.join("a.dst"), dir.join("b.dst")];
+
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let log = std::sync::Arc::clone(&seen);
+        let results = BatchConverter::new()
+            .input_files(&files)
+            .output_dir(&dir)
+            .parallel(false)
+            .on_progress(move |p| {
+                let started = matches!(p.status, FileStatus::Started);
+                log.lock().unwrap().push((p.index, started, p.completed));
+            })
+            .build()
+            .convert_all()
+            .unwrap();
+
+        assert_eq!(results.failed_count(), 2);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(0, true, 0), (0, false, 1), (1, true, 1), (1, false, 2)]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cancellation_skips_remaining_files() {
+        let dir = std::env::temp_dir().join(format!("butabuti_cancel_{}", std::process::id()));
+        let files: Vec<_> = (0..4).map(|i| dir.join(format!("{}.dst", i))).collect();
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        let results = BatchConverter::new()
+            .input_files(&files)
+            .output_dir(&dir)
+            .parallel(false)
+            .cancellation_token(token.clone())
+            .on_progress(move |p| {
+                if p.completed == 1 {
+                    cancel.cancel();
+                }
+            })
+            .build()
+            .convert_all()
+            .unwrap();
+
+        assert!(token.is_cancelled());
+        assert_eq!(results.failed_count(), 1);
+        assert_eq!(results.skipped_count(), 3);
+        assert!(matches!(
+            &results.results()[3],
+            ConversionResult::Skipped { reason, .. } if reason.contains("cancelled")
+        ));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Reader for `.stuck` files that blocks until the test releases it
+    struct StuckFormat;
+
+    static STUCK_RELEASED: (Mutex<bool>, std::sync::Condvar) =
+        (Mutex::new(false), std::sync::Condvar::new());
+    static STUCK_RETURNED: AtomicBool = AtomicBool::new(false);
+
+    impl crate::formats::plugin::EmbFormat for StuckFormat {
+        fn caps(&self) -> crate::formats::plugin::FormatCaps {
+            crate::formats::plugin::FormatCaps::new("STUCK", &["stuck"]).read_only()
+        }
+
+        fn read(&self, _reader: &mut dyn Read) -> Result<EmbPattern> {
+            let (released, wake) = &STUCK_RELEASED;
+            let mut released = released.lock().unwrap();
+            while !*released {
+                released = wake.wait(released).unwrap();
+            }
+            STUCK_RETURNED.store(true, Ordering::SeqCst);
+            let mut pattern = EmbPattern::new();
+            pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+            Ok(pattern)
+        }
+    }
+
+    #[test]
+    fn test_timeout_fails_hanging_file() {
+        let dir = std::env::temp_dir().join(format!("butabuti_timeout_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        crate::formats::plugin::register_global(StuckFormat);
+
+        let stuck = dir.join("hanging.stuck");
+        fs::write(&stuck, b"").unwrap();
+        let good = dir.join("good.json");
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        write_embroidery_file(&pattern, &good, Format::JSON, &WriteOptions::new()).unwrap();
+
+        let results = BatchConverter::new()
+            .input_files(&[stuck, good])
+            .output_dir(dir.join("out"))
+            .parallel(false)
+            .timeout(Duration::from_millis(200))
+            .build()
+            .convert_all()
+            .unwrap();
+
+        assert!(matches!(
+            &results.results()[0],
+            ConversionResult::Failed { error, .. } if error.contains("timed out")
+        ));
+        assert!(matches!(
+            results.results()[1],
+            ConversionResult::Success { .. }
+        ));
+
+        // Once released, the abandoned worker finishes without writing
+        *STUCK_RELEASED.0.lock().unwrap() = true;
+        STUCK_RELEASED.1.notify_all();
+        while !STUCK_RETURNED.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        thread::sleep(Duration::from_millis(100));
+        assert!(!dir.join("out").join("hanging.dst").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_timeout_bounds_hanging_write() {
+        let input = Path::new("slow.json");
+        let (_sender, receiver) = mpsc::channel();
+        let stage = Mutex::new(Stage::Writing);
+        let timeout = Duration::from_millis(50);
+
+        // A write that never finishes gets one extra timeout
+        let start = Instant::now();
+        let error = await_conversion(input, &receiver, &stage, timeout, || false).unwrap_err();
+        assert!(error.contains("Writing output timed out"));
+        assert!(start.elapsed() >= timeout * 2);
+
+        let error = await_conversion(input, &receiver, &stage, timeout, || true).unwrap_err();
+        assert!(error.contains("cancelled"));
+
+        // Cancelling before the write abandons the conversion
+        let stage = Mutex::new(Stage::Converting);
+        let result = await_conversion(input, &receiver, &stage, Duration::from_secs(60), || true);
+        assert!(matches!(result, Ok(ConversionResult::Skipped { .. })));
+        assert_eq!(*stage.lock().unwrap(), Stage::Abandoned);
+    }
+
+    #[test]
+    fn test_zip_inputs_and_output_zip() {
+        let dir = std::env::temp_dir().join(format!("butabuti_zip_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(crate::core::thread::EmbThread::new(0x00FF00));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 30.0, 10.0);
+        pattern.end();
+        let (design, _) =
+            write_embroidery_bytes(&pattern, Format::JSON, &WriteOptions::new()).unwrap();
+        let mut zip = ZipWriter::new(File::create(dir.join("set.zip")).unwrap());
+        zip.add("rose.json", &design).unwrap();
+        zip.add("extra/leaf.json", &design).unwrap();
+        zip.add("readme.pdf", b"%PDF-1.4").unwrap();
+        zip.finish().unwrap();
+        write_embroidery_file(
+            &pattern,
+            &dir.join("rose.json"),
+            Format::JSON,
+            &WriteOptions::new(),
+        )
+        .unwrap();
+
+        // Entries in archive folders need a recursive scan; non-designs are skipped
+        let flat = BatchConverter::new().input_dir(&dir).build();
+        let mut files = flat.collect_input_files().unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![dir.join("rose.json"), dir.join("set.zip").join("rose.json")]
+        );
+        let recursive = BatchConverter::new()
+            .input_files(&[dir.join("set.zip")])
+            .recursive(true)
+            .build();
+        assert_eq!(recursive.collect_input_files().unwrap().len(), 2);
+
+        let results = BatchConverter::new()
+            .input_dir(&dir)
+            .output_zip(dir.join("out").join("converted.zip"))
+            .target_format(Format::DST)
+            .threads(2)
+            .build()
+            .convert_all()
+            .unwrap();
+        assert_eq!(results.success_count(), 2);
+        assert!(!dir.join("rose.dst").exists());
+
+        let outputs: Vec<_> = results
+            .results()
+            .iter()
+            .filter_map(|r| match r {
+                ConversionResult::Success { output, .. } => output.file_name(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(outputs.len(), 2);
+        assert_ne!(outputs[0], outputs[1]);
+
+        let collection =
+            crate::core::collection::EmbPatternCollection::read(dir.join("out/converted.zip"))
+                .unwrap();
+        assert_eq!(collection.len(), 2);
+        for (_, design) in collection.iter() {
+            assert_eq!(design.count_stitches(), 2);
+        }
+
+        // An existing archive is only replaced with overwrite
+        let again = BatchConverter::new()
+            .input_dir(&dir)
+            .output_zip(dir.join("out").join("converted.zip"))
+            .target_format(Format::DST)
+            .build()
+            .convert_all();
+        assert!(again.is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_manifest() {
+        let dir = std::env::temp_dir().join(format!("butabuti_manifest_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(crate::core::thread::EmbThread::new(0xFF0000));
+        pattern.add_thread(crate::core::thread::EmbThread::new(0x0000FF));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 200.0, 50.0);
+        pattern.color_change(0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 100.0);
+        pattern.end();
+        let inputs = vec![dir.join("a,b.json"), dir.join("c.json")];
+        for input in &inputs {
+            write_embroidery_file(&pattern, input, Format::JSON, &WriteOptions::new()).unwrap();
+        }
+        let missing = dir.join("missing.json");
+
+        let converter = |manifest: &str| {
+            BatchConverter::new()
+                .input_files(&[inputs[0].clone(), missing.clone(), inputs[1].clone()])
+                .output_dir(dir.join("out"))
+                .target_format(Format::DST)
+                .overwrite(true)
+                .manifest(dir.join(manifest))
+                .build()
+                .convert_all()
+                .unwrap()
+        };
+
+        let results = converter("manifest.json");
+        assert_eq!(results.success_count(), 2);
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("manifest.json")).unwrap()).unwrap();
+        let entries = json.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        let output = dir.join("out").join("c.dst");
+        assert_eq!(entries[1]["input"], inputs[1].display().to_string());
+        assert_eq!(entries[1]["output"], output.display().to_string());
+        assert_eq!(
+            entries[1]["sha256"],
+            sha256_hex(&fs::read(&output).unwrap()).as_str()
+        );
+        assert_eq!(entries[1]["stitch_count"], 3);
+        assert_eq!(entries[1]["width_mm"], 20.0);
+        assert_eq!(entries[1]["height_mm"], 10.0);
+        assert_eq!(entries[1]["color_count"], 2);
+
+        converter("manifest.csv");
+        let csv = fs::read_to_string(dir.join("manifest.csv")).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], MANIFEST_COLUMNS);
+        assert!(lines[1].starts_with(&format!("\"{}\",", inputs[0].display())));
+        assert!(lines[2].ends_with(",3,20,10,2"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch_queue_debounces_and_retries() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let path = PathBuf::from("design.pes");
+        let mut queue = WatchQueue::new(ms(100));
+
+        queue.touch(path.clone(), start);
+        queue.touch(path.clone(), start + ms(50));
+        assert!(queue.ready(start + ms(100)).is_empty());
+        assert_eq!(queue.ready(start + ms(150)), vec![path.clone()]);
+        assert!(queue.ready(start + ms(500)).is_empty());
+
+        assert!(queue.retry(&path, start + ms(200), 2));
+        assert!(queue.is_retry(&path));
+        assert_eq!(queue.ready(start + ms(200)), vec![path.clone()]);
+        assert!(queue.retry(&path, start + ms(300), 2));
+        assert!(!queue.retry(&path, start + ms(400), 2));
+
+        // A new change earns fresh retries
+        queue.touch(path.clone(), start + ms(400));
+        assert!(!queue.is_retry(&path));
+        assert!(queue.retry(&path, start + ms(600), 2));
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_watch_converts_arriving_files() {
+        let dir = std::env::temp_dir().join(format!("butabuti_watch_{}", std::process::id()));
+        let hot = dir.join("hot");
+        fs::create_dir_all(&hot).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 30.0, 10.0);
+        pattern.end();
+        write_embroidery_file(
+            &pattern,
+            &hot.join("early.json"),
+            Format::JSON,
+            &WriteOptions::new(),
+        )
+        .unwrap();
+
+        let token = CancellationToken::new();
+        let (sender, updates) = mpsc::channel();
+        let converter = BatchConverter::new()
+            .input_dir(&hot)
+            .output_dir(dir.join("out"))
+            .target_format(Format::DST)
+            .overwrite(true)
+            .cancellation_token(token.clone())
+            .progress_sender(sender);
+        let watcher = WatchConverter::new(converter)
+            .debounce(Duration::from_millis(50))
+            .max_retries(1)
+            .retry_delay(Duration::from_millis(20));
+        let handle = thread::spawn(move || watcher.run());
+
+        let finished = |name: &str| loop {
+            let update = updates.recv_timeout(Duration::from_secs(10)).unwrap();
+            if let FileStatus::Finished(result) = update.status {
+                if update.input.file_name().unwrap() == name {
+                    return result;
+                }
+            }
+        };
+        assert!(matches!(
+            finished("early.json"),
+            ConversionResult::Success { .. }
+        ));
+
+        write_embroidery_file(
+            &pattern,
+            &hot.join("late.json"),
+            Format::JSON,
+            &WriteOptions::new(),
+        )
+        .unwrap();
+        assert!(matches!(
+            finished("late.json"),
+            ConversionResult::Success { .. }
+        ));
+        assert!(dir.join("out").join("late.dst").exists());
+
+        fs::write(hot.join("broken.json"), "{").unwrap();
+        assert!(matches!(
+            finished("broken.json"),
+            ConversionResult::Failed { .. }
+        ));
+
+        token.cancel();
+        handle.join().unwrap().unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_warnings() {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Rose");
+        pattern.set_author("Jane Doe");
+        pattern.set_metadata("author_raw", "4A616E65");
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 300.0, 0.0);
+
+        let warnings = |format| {
+            let (_, report) =
+                write_embroidery_bytes(&pattern, format, &WriteOptions::new()).unwrap();
+            write_warnings(&report).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            warnings(Format::U01),
+            vec![
+                "write: 1 stitches longer than 127 split (U01)",
+                "write: 3 JUMP inserted (U01)",
+                "write: metadata not stored: author, name (U01)",
+            ]
+        );
+
+        assert_eq!(
+            warnings(Format::DSB)[2],
+            "write: metadata not stored: author (DSB)"
+        );
+        assert_eq!(
+            warnings(Format::DST),
+            vec![
+                "write: 1 stitches longer than 121 split (DST)",
+                "write: 3 JUMP inserted (DST)",
+            ]
+        );
+
+        assert!(warnings(Format::JSON).is_empty());
+    }
+
+    #[test]
+    fn test_export_reports_dropped_metadata() {
+        let dir = std::env::temp_dir().join(format!("butabuti_warn_{}", std::process::id()));
+
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Rose");
+        pattern.set_metadata("company", "Acme");
+        pattern.add_stitch_absolute(crate::core::constants::STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(crate::core::constants::STITCH, 10.0, 10.0);
+
+        let results = MultiFormatExporter::new()
+            .output_dir(&dir)
+            .formats(&[Format::DST, Format::JSON])
+            .overwrite(true)
+            .build()
+            .export(&pattern)
+            .unwrap();
+        let warnings: Vec<&[String]> = results
+            .results
+            .iter()
+            .map(|result| match result {
+                ConversionResult::Success { warnings, .. } => warnings.as_slice(),
+                other => panic!("unexpected result {:?}", other),
+            })
+            .collect();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].iter().any(|w| w.contains("company")));
+        assert!(warnings[1].is_empty());
+        assert_eq!(results.warning_count(), 1);
+
+        let results = BatchConverter::new()
+            .input_files(&[dir.join("pattern.json")])
+            .output_dir(dir.join("out"))
+            .target_format(Format::DST)
+            .parallel(false)
+            .build()
+            .convert_all()
+            .unwrap();
+        assert_eq!(results.warning_count(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
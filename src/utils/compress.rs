@@ -11,20 +11,243 @@ pub fn expand(data: &[u8], uncompressed_size: Option<usize>) -> Result<Vec<u8>>
     decompressor.decompress(uncompressed_size)
 }
 
-/// Compress data (basic header + raw data)
-#[allow(dead_code)]
+/// Compress data into the block Huffman stream read by [`expand`]
+///
+/// Bytes are emitted as literals coded with a per-block Huffman table,
+/// followed by an end marker. No lookback references are produced.
 pub fn compress(data: &[u8]) -> Vec<u8> {
-    let size = data.len();
-    let mut result = vec![
-        (size & 0xFF) as u8,
-        ((size >> 8) & 0xFF) as u8,
-        0x02,
-        0xA0,
-        0x01,
-        0xFE,
-    ];
-    result.extend_from_slice(data);
-    result
+    let mut tokens: Vec<usize> = data.iter().map(|&b| b as usize).collect();
+    tokens.push(END_TOKEN);
+
+    let mut writer = BitWriter::default();
+    for block in tokens.chunks(MAX_BLOCK_ELEMENTS) {
+        write_block(&mut writer, block);
+    }
+    writer.finish()
+}
+
+/// Token marking the end of the compressed stream
+const END_TOKEN: usize = 510;
+
+/// Size of the character alphabet (literals, lookback lengths and END)
+const CHARACTER_COUNT: usize = 511;
+
+/// Size of the alphabet used to transmit character code lengths
+const LENGTH_SYMBOL_COUNT: usize = 19;
+
+/// Tokens per block are stored in 16 bits
+const MAX_BLOCK_ELEMENTS: usize = 0xFFFF;
+
+/// Longest code the decoder's lookup table can resolve
+const MAX_CODE_LENGTH: usize = 16;
+
+/// MSB-first bit writer
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    filled: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bit_count: usize) {
+        for i in (0..bit_count).rev() {
+            self.current = (self.current << 1) | ((value >> i) & 1) as u8;
+            self.filled += 1;
+            if self.filled == 8 {
+                self.bytes.push(self.current);
+                self.current = 0;
+                self.filled = 0;
+            }
+        }
+    }
+
+    fn write_variable_length(&mut self, length: usize) {
+        if length < 7 {
+            self.write(length as u32, 3);
+            return;
+        }
+        self.write(7, 3);
+        for _ in 7..length {
+            self.write(1, 1);
+        }
+        if length - 7 < 13 {
+            self.write(0, 1);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.filled > 0 {
+            self.bytes.push(self.current << (8 - self.filled));
+        }
+        self.bytes
+    }
+}
+
+/// Write one block: header tables followed by the coded tokens
+fn write_block(writer: &mut BitWriter, block: &[usize]) {
+    writer.write(block.len() as u32, 16);
+
+    let mut frequencies = vec![0usize; CHARACTER_COUNT];
+    for &token in block {
+        frequencies[token] += 1;
+    }
+    let lengths = code_lengths(&frequencies);
+    let count = lengths.iter().rposition(|&l| l > 0).map_or(0, |i| i + 1);
+
+    // Run-length code the character lengths: 0 = one zero, 1 = 3..=18 zeros,
+    // 2 = 20..=531 zeros, otherwise length + 2
+    let mut symbols: Vec<(usize, u32, usize)> = Vec::new();
+    let mut index = 0;
+    while index < count {
+        if lengths[index] > 0 {
+            symbols.push((lengths[index] + 2, 0, 0));
+            index += 1;
+            continue;
+        }
+        let run = lengths[index..count]
+            .iter()
+            .take_while(|&&l| l == 0)
+            .count();
+        match run {
+            3..=18 => {
+                symbols.push((1, (run - 3) as u32, 4));
+                index += run;
+            }
+            20.. => {
+                let run = run.min(20 + 511);
+                symbols.push((2, (run - 20) as u32, 9));
+                index += run;
+            }
+            _ => {
+                symbols.push((0, 0, 0));
+                index += 1;
+            }
+        }
+    }
+
+    let mut symbol_frequencies = vec![0usize; LENGTH_SYMBOL_COUNT];
+    for &(symbol, _, _) in &symbols {
+        symbol_frequencies[symbol] += 1;
+    }
+    let symbol_lengths = code_lengths(&symbol_frequencies);
+    let used: Vec<usize> = (0..LENGTH_SYMBOL_COUNT)
+        .filter(|&s| symbol_frequencies[s] > 0)
+        .collect();
+
+    // Character length table
+    if used.len() == 1 {
+        writer.write(0, 5);
+        writer.write(used[0] as u32, 5);
+    } else {
+        let symbol_count = used.last().map_or(0, |&s| s + 1);
+        writer.write(symbol_count as u32, 5);
+        let mut index = 0;
+        while index < symbol_count {
+            if index == 3 {
+                let skip = symbol_lengths[3..symbol_count]
+                    .iter()
+                    .take(3)
+                    .take_while(|&&l| l == 0)
+                    .count();
+                writer.write(skip as u32, 2);
+                index += skip;
+            }
+            if index < symbol_count {
+                writer.write_variable_length(symbol_lengths[index]);
+                index += 1;
+            }
+        }
+    }
+
+    // Character table
+    let symbol_codes = canonical_codes(&symbol_lengths);
+    writer.write(count as u32, 9);
+    for &(symbol, extra, extra_bits) in &symbols {
+        if used.len() > 1 {
+            writer.write(symbol_codes[symbol], symbol_lengths[symbol]);
+        }
+        writer.write(extra, extra_bits);
+    }
+
+    // Distance table (unused: no lookback references)
+    writer.write(0, 5);
+    writer.write(0, 5);
+
+    let codes = canonical_codes(&lengths);
+    for &token in block {
+        writer.write(codes[token], lengths[token]);
+    }
+}
+
+/// Huffman code lengths for the given frequencies, limited to [`MAX_CODE_LENGTH`]
+fn code_lengths(frequencies: &[usize]) -> Vec<usize> {
+    let mut weights = frequencies.to_vec();
+    loop {
+        let lengths = huffman_lengths(&weights);
+        if lengths.iter().all(|&l| l <= MAX_CODE_LENGTH) {
+            return lengths;
+        }
+        // Flatten the distribution until the tree is shallow enough
+        for weight in weights.iter_mut().filter(|w| **w > 0) {
+            *weight = weight.div_ceil(2);
+        }
+    }
+}
+
+fn huffman_lengths(weights: &[usize]) -> Vec<usize> {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    let mut lengths = vec![0; weights.len()];
+    let used: Vec<usize> = (0..weights.len()).filter(|&s| weights[s] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+
+    let mut parents = vec![usize::MAX; used.len()];
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &symbol)| Reverse((weights[symbol], node)))
+        .collect();
+    while heap.len() > 1 {
+        let (Some(Reverse((wa, a))), Some(Reverse((wb, b)))) = (heap.pop(), heap.pop()) else {
+            break;
+        };
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[a] = node;
+        parents[b] = node;
+        heap.push(Reverse((wa + wb, node)));
+    }
+
+    for (leaf, &symbol) in used.iter().enumerate() {
+        let mut node = leaf;
+        while parents[node] != usize::MAX {
+            node = parents[node];
+            lengths[symbol] += 1;
+        }
+    }
+    lengths
+}
+
+/// Canonical codes in the order the decoder lays out its lookup table
+fn canonical_codes(lengths: &[usize]) -> Vec<u32> {
+    let mut order: Vec<usize> = (0..lengths.len()).filter(|&s| lengths[s] > 0).collect();
+    order.sort_by_key(|&s| (lengths[s], s));
+
+    let mut codes = vec![0u32; lengths.len()];
+    let mut code = 0u32;
+    let mut previous = order.first().map_or(0, |&s| lengths[s]);
+    for symbol in order {
+        code <<= lengths[symbol] - previous;
+        previous = lengths[symbol];
+        codes[symbol] = code;
+        code += 1;
+    }
+    codes
 }
 
 /// Huffman table for decoding
@@ -78,7 +301,7 @@ impl Huffman {
             return;
         }
 
-        self.table = vec![0; table_size];
+        self.table = Vec::with_capacity(table_size);
 
         for bit_length in 1..=self.table_width {
             let size = 1usize
@@ -92,7 +315,7 @@ impl Huffman {
             for (len_index, &length) in self.lengths.iter().enumerate() {
                 if length == bit_length {
                     for _ in 0..size {
-                        if self.table.len() < self.table.capacity() {
+                        if self.table.len() < table_size {
                             self.table.push(len_index);
                         }
                     }
@@ -164,24 +387,19 @@ impl EmbCompress {
             return 0;
         }
 
-        // Collect bytes spanning the bit range
-        let mut value: u32 = 0;
-        for i in start_pos_in_bytes..=end_pos_in_bytes.min(self.input_data.len().saturating_sub(1))
-        {
+        // Collect bytes spanning the bit range, padding past the end with zeros
+        let mut value: u64 = 0;
+        for i in start_pos_in_bytes..=end_pos_in_bytes {
             value <<= 8;
-            if i < self.input_data.len() {
-                value |= self.input_data[i] as u32;
+            if let Some(&byte) = self.input_data.get(i) {
+                value |= byte as u64;
             }
         }
 
         // Extract the exact bits requested by masking and shifting
         let unused_bits_right = (8 - (end_pos_in_bits + 1) % 8) % 8;
-        let mask = if length == 32 {
-            u32::MAX
-        } else {
-            (1u32 << length) - 1
-        };
-        (value >> unused_bits_right) & mask
+        let mask = (1u64 << length) - 1;
+        ((value >> unused_bits_right) & mask) as u32
     }
 
     fn pop(&mut self, bit_count: usize) -> u32 {
@@ -327,9 +545,10 @@ impl EmbCompress {
         let mut output_data = Vec::new();
         let bits_total = self.input_data.len().saturating_mul(8);
 
-        // Pre-allocate if we know the size
+        // Pre-allocate if we know the size, but no more than one byte per input
+        // bit: the size comes from the file header and may be corrupt
         if let Some(size) = uncompressed_size {
-            output_data.reserve(size);
+            output_data.reserve(size.min(bits_total));
         }
 
         while bits_total > self.bit_position {
@@ -396,20 +615,26 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_compress_basic() {
-        let data = vec![1, 2, 3, 4, 5];
+    fn test_compress_round_trip() {
+        let data: Vec<u8> = (0..2000u32).map(|i| (i * i % 251) as u8).collect();
         let compressed = compress(&data);
+        assert_eq!(expand(&compressed, None).unwrap(), data);
+        assert_eq!(expand(&compressed, Some(data.len())).unwrap(), data);
+    }
 
-        // Check header
-        assert_eq!(compressed[0], 5); // Size low byte
-        assert_eq!(compressed[1], 0); // Size high byte
-        assert_eq!(compressed[2], 0x02);
-        assert_eq!(compressed[3], 0xA0);
-        assert_eq!(compressed[4], 0x01);
-        assert_eq!(compressed[5], 0xFE);
+    #[test]
+    fn test_compress_edge_cases() {
+        for data in [vec![], vec![7], vec![0x80; 300], vec![1, 2, 3, 4, 5]] {
+            assert_eq!(expand(&compress(&data), None).unwrap(), data);
+        }
 
-        // Check data
-        assert_eq!(&compressed[6..], &data);
+        // Spans several blocks and skewed enough to need length limiting
+        let mut data = Vec::new();
+        for (i, byte) in (0u8..24).enumerate() {
+            data.extend(std::iter::repeat_n(byte, 1 << (i.min(17))));
+        }
+        assert!(data.len() > MAX_BLOCK_ELEMENTS);
+        assert_eq!(expand(&compress(&data), None).unwrap(), data);
     }
 
     #[test]
@@ -431,4 +656,14 @@ mod tests {
         let result = expand(&compressed, Some(10));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_expand_absurd_size() {
+        // The expected size is only a hint and must not be preallocated
+        let compressed = compress(&[1, 2, 3]);
+        assert_eq!(
+            expand(&compressed, Some(usize::MAX)).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
 }
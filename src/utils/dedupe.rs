@@ -0,0 +1,432 @@
+//! Duplicate design detection
+//!
+//! Design libraries collect the same design many times over: saved in another
+//! format, moved on the hoop, renamed. [`dedupe`] reads every design in a
+//! directory and groups the duplicates by a [`DesignSignature`]:
+//!
+//! - Designs with the same [`content_hash`](EmbPattern::content_hash) have
+//!   the same needle points and are [`Similarity::Identical`].
+//! - Designs whose hashes differ but have the same number of colors and
+//!   nearly the same stitch count and size are [`Similarity::Similar`]:
+//!   candidates for review, such as a re-digitized or slightly edited copy.
+//!
+//! Signatures are small and can be stored; [`group_duplicates`] groups stored
+//! signatures without reading the files again.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use butabuti::utils::dedupe::{dedupe, DedupeOptions};
+//!
+//! let report = dedupe("designs", &DedupeOptions::default())?;
+//! for group in &report.groups {
+//!     println!("{:?}:", group.similarity);
+//!     for design in &group.designs {
+//!         println!("  {}", design.path.display());
+//!     }
+//! }
+//! println!("{} duplicates in {} designs", report.duplicate_count(), report.scanned);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+use crate::formats::registry::FormatRegistry;
+use crate::utils::error::Result;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Options for [`dedupe`] and [`group_duplicates`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DedupeOptions {
+    /// Scan subdirectories too (default: true)
+    pub recursive: bool,
+    /// Group similar designs, not only identical ones (default: true)
+    pub near_duplicates: bool,
+    /// Largest stitch count difference of similar designs, relative to the larger count (default: 0.02)
+    pub stitch_count_tolerance: f64,
+    /// Largest width and height difference of similar designs (0.1mm, default: 10.0)
+    pub size_tolerance: f64,
+}
+
+impl Default for DedupeOptions {
+    fn default() -> Self {
+        Self {
+            recursive: true,
+            near_duplicates: true,
+            stitch_count_tolerance: 0.02,
+            size_tolerance: 10.0,
+        }
+    }
+}
+
+impl DedupeOptions {
+    /// Create default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether subdirectories are scanned
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Set whether similar designs are grouped
+    pub fn near_duplicates(mut self, near_duplicates: bool) -> Self {
+        self.near_duplicates = near_duplicates;
+        self
+    }
+
+    /// Set the stitch count tolerance of similar designs
+    pub fn stitch_count_tolerance(mut self, tolerance: f64) -> Self {
+        self.stitch_count_tolerance = tolerance;
+        self
+    }
+
+    /// Set the size tolerance of similar designs (0.1mm)
+    pub fn size_tolerance(mut self, tolerance: f64) -> Self {
+        self.size_tolerance = tolerance;
+        self
+    }
+}
+
+/// What identifies a design for duplicate detection
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesignSignature {
+    /// File the design was read from
+    pub path: PathBuf,
+    /// Format the file was read as
+    pub format: &'static str,
+    /// [`EmbPattern::content_hash`] of the design
+    pub hash: u64,
+    /// Number of stitches
+    pub stitch_count: usize,
+    /// Number of color blocks with stitches
+    pub color_count: usize,
+    /// Width and height of the stitches (0.1mm)
+    pub size: (f64, f64),
+}
+
+impl DesignSignature {
+    /// Signature of a pattern read from `path`
+    pub fn from_pattern(
+        path: impl Into<PathBuf>,
+        format: &'static str,
+        pattern: &EmbPattern,
+    ) -> Self {
+        let (min_x, min_y, max_x, max_y) = pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .fold(
+                (
+                    f64::INFINITY,
+                    f64::INFINITY,
+                    f64::NEG_INFINITY,
+                    f64::NEG_INFINITY,
+                ),
+                |(x0, y0, x1, y1), s| (x0.min(s.x), y0.min(s.y), x1.max(s.x), y1.max(s.y)),
+            );
+        let size = if min_x <= max_x {
+            (max_x - min_x, max_y - min_y)
+        } else {
+            (0.0, 0.0)
+        };
+        Self {
+            path: path.into(),
+            format,
+            hash: pattern.content_hash(),
+            stitch_count: pattern.count_stitches(),
+            color_count: pattern
+                .blocks()
+                .iter()
+                .filter(|b| b.stitch_count() > 0)
+                .count(),
+            size,
+        }
+    }
+
+    /// Read a design file and compute its signature
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut file = BufReader::new(File::open(path)?);
+        let (pattern, format) =
+            FormatRegistry::new().read_pattern_detected(&mut file, Some(path))?;
+        Ok(Self::from_pattern(path, format, &pattern))
+    }
+
+    /// Whether two designs are close enough to be similar
+    fn is_near(&self, other: &Self, options: &DedupeOptions) -> bool {
+        let larger = self.stitch_count.max(other.stitch_count) as f64;
+        self.color_count == other.color_count
+            && self.stitch_count.abs_diff(other.stitch_count) as f64
+                <= larger * options.stitch_count_tolerance
+            && (self.size.0 - other.size.0).abs() <= options.size_tolerance
+            && (self.size.1 - other.size.1).abs() <= options.size_tolerance
+    }
+}
+
+/// How the designs of a group match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Similarity {
+    /// All designs have the same content hash
+    Identical,
+    /// Some designs only have close signatures
+    Similar,
+}
+
+/// Designs that duplicate each other
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// How the designs match
+    pub similarity: Similarity,
+    /// The designs, sorted by path
+    pub designs: Vec<DesignSignature>,
+}
+
+/// Outcome of [`dedupe`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DedupeReport {
+    /// Design files read
+    pub scanned: usize,
+    /// Groups of duplicates, sorted by their first path
+    pub groups: Vec<DuplicateGroup>,
+    /// Design files that could not be read, with the error
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl DedupeReport {
+    /// Number of designs that duplicate another one, keeping one per group
+    pub fn duplicate_count(&self) -> usize {
+        self.groups.iter().map(|g| g.designs.len() - 1).sum()
+    }
+}
+
+/// Find duplicate designs in a directory
+///
+/// Files are picked by the extensions of readable formats; others are
+/// skipped. Files that fail to read are listed in the report, not returned as
+/// an error. With the `parallel` feature, files are read on the rayon pool.
+pub fn dedupe<P: AsRef<Path>>(dir: P, options: &DedupeOptions) -> Result<DedupeReport> {
+    let registry = FormatRegistry::new();
+    let mut paths = Vec::new();
+    collect_designs(dir.as_ref(), &registry, options.recursive, &mut paths)?;
+    paths.sort();
+
+    #[cfg(feature = "parallel")]
+    let results: Vec<Result<DesignSignature>> = {
+        use rayon::prelude::*;
+        paths.par_iter().map(DesignSignature::read).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let results: Vec<Result<DesignSignature>> = paths.iter().map(DesignSignature::read).collect();
+
+    let mut report = DedupeReport::default();
+    let mut signatures = Vec::with_capacity(paths.len());
+    for (path, result) in paths.into_iter().zip(results) {
+        match result {
+            Ok(signature) => signatures.push(signature),
+            Err(error) => report.failed.push((path, error.to_string())),
+        }
+    }
+    report.scanned = signatures.len();
+    report.groups = group_duplicates(signatures, options);
+    Ok(report)
+}
+
+/// Add the readable design files under `dir`
+fn collect_designs(
+    dir: &Path,
+    registry: &FormatRegistry,
+    recursive: bool,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                collect_designs(&path, registry, recursive, paths)?;
+            }
+        } else if registry
+            .get_format_from_path(&path)
+            .is_some_and(|format| format.can_read)
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Group signatures into duplicates
+///
+/// Designs with the same hash are grouped first. With
+/// `options.near_duplicates`, groups whose designs are similar are then
+/// merged, transitively. Designs without a duplicate are left out.
+pub fn group_duplicates(
+    signatures: Vec<DesignSignature>,
+    options: &DedupeOptions,
+) -> Vec<DuplicateGroup> {
+    // Designs sharing a hash, in first-seen order
+    let mut by_hash: HashMap<u64, usize> = HashMap::new();
+    let mut exact: Vec<Vec<DesignSignature>> = Vec::new();
+    for signature in signatures {
+        let next = exact.len();
+        let index = *by_hash.entry(signature.hash).or_insert(next);
+        if index == next {
+            exact.push(Vec::new());
+        }
+        exact[index].push(signature);
+    }
+
+    // Union similar hash groups through their first design
+    let mut parent: Vec<usize> = (0..exact.len()).collect();
+    if options.near_duplicates {
+        let mut order: Vec<usize> = (0..exact.len()).collect();
+        order.sort_by_key(|&i| exact[i][0].stitch_count);
+        for (k, &i) in order.iter().enumerate() {
+            let a = &exact[i][0];
+            let limit = a.stitch_count as f64 * (1.0 + options.stitch_count_tolerance);
+            for &j in order[k + 1..]
+                .iter()
+                .take_while(|&&j| exact[j][0].stitch_count as f64 <= limit)
+            {
+                if a.is_near(&exact[j][0], options) {
+                    let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                    parent[ri.max(rj)] = ri.min(rj);
+                }
+            }
+        }
+    }
+
+    let mut clusters: Vec<Vec<usize>> = vec![Vec::new(); exact.len()];
+    for i in 0..exact.len() {
+        let root = find(&mut parent, i);
+        clusters[root].push(i);
+    }
+    let mut groups: Vec<DuplicateGroup> = clusters
+        .into_iter()
+        .filter(|members| !members.is_empty())
+        .filter_map(|members| {
+            let similarity = if members.len() == 1 {
+                Similarity::Identical
+            } else {
+                Similarity::Similar
+            };
+            let mut designs: Vec<DesignSignature> = members
+                .into_iter()
+                .flat_map(|i| std::mem::take(&mut exact[i]))
+                .collect();
+            if designs.len() < 2 {
+                return None;
+            }
+            designs.sort_by(|a, b| a.path.cmp(&b.path));
+            Some(DuplicateGroup {
+                similarity,
+                designs,
+            })
+        })
+        .collect();
+    groups.sort_by(|a, b| a.designs[0].path.cmp(&b.designs[0].path));
+    groups
+}
+
+/// Root of a union-find set, compressing the path
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn design(path: &str, hash: u64, stitch_count: usize, size: (f64, f64)) -> DesignSignature {
+        DesignSignature {
+            path: PathBuf::from(path),
+            format: "dst",
+            hash,
+            stitch_count,
+            color_count: 2,
+            size,
+        }
+    }
+
+    #[test]
+    fn test_group_duplicates() {
+        let signatures = vec![
+            design("rose.dst", 1, 1000, (500.0, 400.0)),
+            design("lily.pes", 2, 5000, (800.0, 800.0)),
+            design("rose copy.pes", 1, 1000, (500.0, 400.0)),
+            design("rose v2.jef", 3, 1010, (504.0, 400.0)),
+            design("tulip.dst", 4, 1015, (900.0, 100.0)),
+        ];
+
+        let groups = group_duplicates(signatures.clone(), &DedupeOptions::default());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].similarity, Similarity::Similar);
+        let paths: Vec<_> = groups[0]
+            .designs
+            .iter()
+            .map(|d| d.path.to_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["rose copy.pes", "rose v2.jef", "rose.dst"]);
+
+        let exact = DedupeOptions::new().near_duplicates(false);
+        let groups = group_duplicates(signatures, &exact);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].similarity, Similarity::Identical);
+        assert_eq!(groups[0].designs.len(), 2);
+    }
+
+    #[test]
+    fn test_dedupe_directory() {
+        use crate::formats::io::memory::write_to_bytes;
+
+        let dir = std::env::temp_dir().join(format!("butabuti_dedupe_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("old")).unwrap();
+
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(100.0, 0.0);
+        pattern.stitch_abs(100.0, 100.0);
+        pattern.end();
+        let mut moved = pattern.clone();
+        moved.translate(30.0, 30.0);
+        let mut other = EmbPattern::new();
+        other.stitch_abs(0.0, 0.0);
+        other.stitch_abs(0.0, 300.0);
+        other.end();
+
+        fs::write(
+            dir.join("star.dst"),
+            write_to_bytes(&pattern, "dst").unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("old/star_moved.exp"),
+            write_to_bytes(&moved, "exp").unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("line.dst"), write_to_bytes(&other, "dst").unwrap()).unwrap();
+        fs::write(dir.join("broken.dst"), b"not a design").unwrap();
+        fs::write(dir.join("notes.txt"), b"skip me").unwrap();
+
+        let report = dedupe(&dir, &DedupeOptions::default()).unwrap();
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.groups[0].similarity, Similarity::Identical);
+        assert_eq!(report.duplicate_count(), 1);
+        assert_eq!(report.scanned + report.failed.len(), 4);
+
+        let flat = dedupe(&dir, &DedupeOptions::new().recursive(false)).unwrap();
+        assert!(flat.groups.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
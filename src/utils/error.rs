@@ -32,6 +32,29 @@
 //!   - Processing design.dst
 //! ```
 //!
+//! # Error Codes and Locations
+//!
+//! Every error has a stable [`ErrorCode`] for services that branch on the kind
+//! of failure or log it, and readers attach a [`SourceLocation`] (format, byte
+//! offset, stitch index) where they know it:
+//!
+//! ```rust
+//! use butabuti::formats::Format;
+//! use butabuti::utils::error::{Error, ErrorCode};
+//!
+//! let err = Error::unexpected_value("DST record", "end marker", "0x12")
+//!     .in_format(Format::DST)
+//!     .at_offset(0x200);
+//!
+//! assert_eq!(err.code(), ErrorCode::UnexpectedValue);
+//! assert_eq!(err.code().as_str(), "parse.unexpected_value");
+//! assert_eq!(err.location().offset, Some(0x200));
+//! assert_eq!(
+//!     err.to_string(),
+//!     "Parse error: Invalid DST record: expected end marker, got 0x12 (DST, offset 0x200)"
+//! );
+//! ```
+//!
 //! # Error Type Usage Guidelines
 //!
 //! Choose the appropriate error variant based on the failure context:
@@ -60,6 +83,20 @@
 //! }
 //! ```
 //!
+//! Prefer the structured parse errors when they fit, and attach the location
+//! with `in_format`, `at_offset` and `at_stitch`:
+//! - `Error::truncated`: the data ends before a complete structure
+//! - `Error::unexpected_value`: a field holds a value the format doesn't allow
+//! - `Error::limit_exceeded`: a count is over a safety or format limit
+//!
+//! ```rust,ignore
+//! if stitch_count > MAX_STITCHES {
+//!     return Err(Error::limit_exceeded("DST stitch count", MAX_STITCHES, stitch_count)
+//!         .in_format(Format::DST)
+//!         .at_offset(offset));
+//! }
+//! ```
+//!
 //! ## `Error::UnsupportedFormat`
 //! - **When**: File extension or format type is not recognized
 //! - **Examples**: Unknown file extension, unsupported format variant
@@ -136,6 +173,7 @@
 //! - **Auto-converted**: From `serde_json::Error` via `?` operator
 //! - **Usage**: Generally handled automatically
 
+use crate::formats::format::Format;
 use std::fmt;
 use std::io;
 
@@ -146,6 +184,105 @@ pub struct Error {
     kind: ErrorKind,
     /// Stack of contextual information (innermost first)
     context: Vec<String>,
+    /// Where in the source data the error occurred, if known
+    location: Option<Box<SourceLocation>>,
+}
+
+/// Where in the source data an error occurred
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Format being read or written
+    pub format: Option<Format>,
+    /// Byte offset in the source data
+    pub offset: Option<u64>,
+    /// Index of the stitch being read or written
+    pub stitch_index: Option<usize>,
+}
+
+impl SourceLocation {
+    /// Whether no location is known
+    pub fn is_empty(&self) -> bool {
+        self.format.is_none() && self.offset.is_none() && self.stitch_index.is_none()
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(format) = self.format {
+            parts.push(format.to_string());
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("offset 0x{:X}", offset));
+        }
+        if let Some(index) = self.stitch_index {
+            parts.push(format!("stitch {}", index));
+        }
+        f.write_str(&parts.join(", "))
+    }
+}
+
+/// Stable identifier of an error kind
+///
+/// Codes don't change between releases, so they can be stored, logged or sent
+/// to other services. Parse errors share the `parse` prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// [`ErrorKind::Io`]
+    Io,
+    /// [`ErrorKind::Parse`]
+    Parse,
+    /// [`ErrorKind::Truncated`]
+    Truncated,
+    /// [`ErrorKind::UnexpectedValue`]
+    UnexpectedValue,
+    /// [`ErrorKind::LimitExceeded`]
+    LimitExceeded,
+    /// [`ErrorKind::UnsupportedFormat`]
+    UnsupportedFormat,
+    /// [`ErrorKind::InvalidPattern`]
+    InvalidPattern,
+    /// [`ErrorKind::ThreadIndexOutOfBounds`]
+    ThreadIndexOutOfBounds,
+    /// [`ErrorKind::InvalidColor`]
+    InvalidColor,
+    /// [`ErrorKind::Encoding`]
+    Encoding,
+    /// [`ErrorKind::Unsupported`]
+    Unsupported,
+    /// [`ErrorKind::Json`]
+    Json,
+}
+
+impl ErrorCode {
+    /// The code as a string, e.g. `"parse.truncated"`
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Io => "io",
+            ErrorCode::Parse => "parse",
+            ErrorCode::Truncated => "parse.truncated",
+            ErrorCode::UnexpectedValue => "parse.unexpected_value",
+            ErrorCode::LimitExceeded => "parse.limit_exceeded",
+            ErrorCode::UnsupportedFormat => "unsupported_format",
+            ErrorCode::InvalidPattern => "invalid_pattern",
+            ErrorCode::ThreadIndexOutOfBounds => "thread_index_out_of_bounds",
+            ErrorCode::InvalidColor => "invalid_color",
+            ErrorCode::Encoding => "encoding",
+            ErrorCode::Unsupported => "unsupported",
+            ErrorCode::Json => "json",
+        }
+    }
+
+    /// Whether the code is one of the parse errors
+    pub fn is_parse(self) -> bool {
+        self.as_str().starts_with("parse")
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Different kinds of errors that can occur
@@ -157,6 +294,29 @@ pub enum ErrorKind {
     /// Error parsing embroidery file
     Parse(String),
 
+    /// The data ended while reading the named structure
+    Truncated(String),
+
+    /// A field held a value the format does not allow
+    UnexpectedValue {
+        /// The field that was read
+        field: String,
+        /// What the format allows
+        expected: String,
+        /// What was found
+        found: String,
+    },
+
+    /// A count exceeded a safety or format limit
+    LimitExceeded {
+        /// What was counted
+        what: String,
+        /// The largest allowed value
+        limit: u64,
+        /// The value found
+        found: u64,
+    },
+
     /// Unsupported file format
     UnsupportedFormat(String),
 
@@ -185,6 +345,7 @@ impl Error {
         Self {
             kind,
             context: Vec::new(),
+            location: None,
         }
     }
 
@@ -198,6 +359,33 @@ impl Error {
         Self::new(ErrorKind::Parse(msg.into()))
     }
 
+    /// Create an error for data that ends while reading `what`
+    pub fn truncated<S: Into<String>>(what: S) -> Self {
+        Self::new(ErrorKind::Truncated(what.into()))
+    }
+
+    /// Create an error for a field holding a value the format does not allow
+    pub fn unexpected_value(
+        field: impl Into<String>,
+        expected: impl Into<String>,
+        found: impl Into<String>,
+    ) -> Self {
+        Self::new(ErrorKind::UnexpectedValue {
+            field: field.into(),
+            expected: expected.into(),
+            found: found.into(),
+        })
+    }
+
+    /// Create an error for a count over a safety or format limit
+    pub fn limit_exceeded(what: impl Into<String>, limit: usize, found: usize) -> Self {
+        Self::new(ErrorKind::LimitExceeded {
+            what: what.into(),
+            limit: limit as u64,
+            found: found as u64,
+        })
+    }
+
     /// Create an unsupported format error
     pub fn unsupported_format<S: Into<String>>(msg: S) -> Self {
         Self::new(ErrorKind::UnsupportedFormat(msg.into()))
@@ -242,6 +430,54 @@ impl Error {
     pub fn context(&self) -> &[String] {
         &self.context
     }
+
+    /// Get the stable code of the error kind
+    pub fn code(&self) -> ErrorCode {
+        match self.kind {
+            ErrorKind::Io(_) => ErrorCode::Io,
+            ErrorKind::Parse(_) => ErrorCode::Parse,
+            ErrorKind::Truncated(_) => ErrorCode::Truncated,
+            ErrorKind::UnexpectedValue { .. } => ErrorCode::UnexpectedValue,
+            ErrorKind::LimitExceeded { .. } => ErrorCode::LimitExceeded,
+            ErrorKind::UnsupportedFormat(_) => ErrorCode::UnsupportedFormat,
+            ErrorKind::InvalidPattern(_) => ErrorCode::InvalidPattern,
+            ErrorKind::ThreadIndexOutOfBounds(_) => ErrorCode::ThreadIndexOutOfBounds,
+            ErrorKind::InvalidColor(_) => ErrorCode::InvalidColor,
+            ErrorKind::Encoding(_) => ErrorCode::Encoding,
+            ErrorKind::Unsupported(_) => ErrorCode::Unsupported,
+            ErrorKind::Json(_) => ErrorCode::Json,
+        }
+    }
+
+    /// Get where in the source data the error occurred
+    ///
+    /// Fields are `None` where the location is unknown.
+    pub fn location(&self) -> &SourceLocation {
+        static UNKNOWN: SourceLocation = SourceLocation {
+            format: None,
+            offset: None,
+            stitch_index: None,
+        };
+        self.location.as_deref().unwrap_or(&UNKNOWN)
+    }
+
+    /// Record the format being read or written
+    pub fn in_format(mut self, format: Format) -> Self {
+        self.location.get_or_insert_with(Box::default).format = Some(format);
+        self
+    }
+
+    /// Record the byte offset of the error in the source data
+    pub fn at_offset(mut self, offset: u64) -> Self {
+        self.location.get_or_insert_with(Box::default).offset = Some(offset);
+        self
+    }
+
+    /// Record the index of the stitch being read or written
+    pub fn at_stitch(mut self, index: usize) -> Self {
+        self.location.get_or_insert_with(Box::default).stitch_index = Some(index);
+        self
+    }
 }
 
 /// Trait for adding contextual information to errors
@@ -328,6 +564,23 @@ impl fmt::Display for ErrorKind {
         match self {
             ErrorKind::Io(msg) => write!(f, "I/O error: {}", msg),
             ErrorKind::Parse(msg) => write!(f, "Parse error: {}", msg),
+            ErrorKind::Truncated(what) => {
+                write!(f, "Parse error: Unexpected end of data reading {}", what)
+            }
+            ErrorKind::UnexpectedValue {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Parse error: Invalid {}: expected {}, got {}",
+                field, expected, found
+            ),
+            ErrorKind::LimitExceeded { what, limit, found } => write!(
+                f,
+                "Parse error: {} {} exceeds the limit of {}",
+                what, found, limit
+            ),
             ErrorKind::UnsupportedFormat(msg) => write!(f, "Unsupported format: {}", msg),
             ErrorKind::InvalidPattern(msg) => write!(f, "Invalid pattern: {}", msg),
             ErrorKind::ThreadIndexOutOfBounds(idx) => {
@@ -345,6 +598,9 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // Write the main error message
         write!(f, "{}", self.kind)?;
+        if let Some(location) = self.location.as_deref().filter(|l| !l.is_empty()) {
+            write!(f, " ({})", location)?;
+        }
 
         // Add context if present
         if !self.context.is_empty() {
@@ -362,8 +618,12 @@ impl std::error::Error for Error {}
 
 // Automatic conversions from common error types
 impl From<io::Error> for Error {
+    /// Converts an I/O error, treating an unexpected end of file as truncated data
     fn from(err: io::Error) -> Self {
-        Self::new(ErrorKind::Io(err.to_string()))
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Self::truncated("input"),
+            _ => Self::new(ErrorKind::Io(err.to_string())),
+        }
     }
 }
 
@@ -517,6 +777,46 @@ mod tests {
         let _ = Error::Unsupported("msg".to_string());
     }
 
+    #[test]
+    fn test_error_codes_and_location() {
+        let err = Error::limit_exceeded("DST stitch count", 10, 11)
+            .in_format(Format::DST)
+            .at_offset(0x21E)
+            .at_stitch(10)
+            .with_context("Reading design.dst");
+
+        assert_eq!(err.code(), ErrorCode::LimitExceeded);
+        assert!(err.code().is_parse());
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::LimitExceeded {
+                limit: 10,
+                found: 11,
+                ..
+            }
+        ));
+        assert_eq!(
+            *err.location(),
+            SourceLocation {
+                format: Some(Format::DST),
+                offset: Some(0x21E),
+                stitch_index: Some(10),
+            }
+        );
+        assert!(err.to_string().starts_with(
+            "Parse error: DST stitch count 11 exceeds the limit of 10 (DST, offset 0x21E, stitch 10)\n"
+        ));
+
+        let plain = Error::truncated("PES header");
+        assert_eq!(plain.code().as_str(), "parse.truncated");
+        assert!(plain.location().is_empty());
+        assert_eq!(
+            plain.to_string(),
+            "Parse error: Unexpected end of data reading PES header"
+        );
+        assert!(!Error::encoding("x").code().is_parse());
+    }
+
     #[test]
     fn test_from_io_error() {
         let io_err = io::Error::new(io::ErrorKind::NotFound, "file not found");
@@ -524,6 +824,15 @@ mod tests {
         assert!(matches!(err.kind, ErrorKind::Io(_)));
     }
 
+    #[test]
+    fn test_from_unexpected_eof() {
+        let mut reader: &[u8] = &[1, 2];
+        let err: Error = io::Read::read_exact(&mut reader, &mut [0; 4])
+            .unwrap_err()
+            .into();
+        assert_eq!(err.code(), ErrorCode::Truncated);
+    }
+
     #[test]
     fn test_from_json_error() {
         let json_err = serde_json::from_str::<serde_json::Value>("{invalid").unwrap_err();
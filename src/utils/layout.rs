@@ -0,0 +1,296 @@
+//! Repeat layouts
+//!
+//! Duplicates a motif into a grid ([`repeat`]) or around a circle
+//! ([`repeat_circular`]) and sews the copies as one pattern, for borders and
+//! all-over designs. Alternate copies can be mirrored. Copies are joined with
+//! [`EmbPattern::append_pattern`], so identical threads are sewn together by
+//! default.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::utils::layout::{repeat, LayoutOptions, Mirror};
+//!
+//! let mut leaf = EmbPattern::new();
+//! leaf.add_thread(EmbThread::new(0x228B22));
+//! leaf.stitch_abs(0.0, 0.0);
+//! leaf.stitch_abs(40.0, 10.0);
+//! leaf.stitch_abs(0.0, 20.0);
+//! leaf.end();
+//!
+//! // A border of 6 leaves 5mm apart, every other one flipped
+//! let options = LayoutOptions::new().mirror(Mirror::Horizontal);
+//! let border = repeat(&leaf, 1, 6, 50.0, 0.0, &options)?;
+//! assert_eq!(border.count_stitches(), 18);
+//! assert_eq!(border.threads().len(), 1);
+//! assert_eq!(border.width(), 290.0);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::compose::ColorStrategy;
+use crate::core::constants::*;
+use crate::core::matrix::EmbMatrix;
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::{Error, Result};
+
+/// Axis alternate copies are mirrored across
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mirror {
+    /// Copies are identical
+    #[default]
+    None,
+    /// Alternate copies are flipped left to right
+    Horizontal,
+    /// Alternate copies are flipped top to bottom
+    Vertical,
+}
+
+/// Options for [`repeat`] and [`repeat_circular`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutOptions {
+    /// Mirroring of alternate copies, in a checkerboard for grids (default: none)
+    pub mirror: Mirror,
+    /// How the copies' threads are joined (default: merge identical threads)
+    pub colors: ColorStrategy,
+    /// Turn circular copies to face outward, rather than keeping them upright
+    /// (default: true)
+    pub rotate_copies: bool,
+    /// Angle of the first circular copy in degrees, clockwise from +x
+    /// (default: 0.0)
+    pub start_angle: f64,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            mirror: Mirror::None,
+            colors: ColorStrategy::MergeIdentical,
+            rotate_copies: true,
+            start_angle: 0.0,
+        }
+    }
+}
+
+impl LayoutOptions {
+    /// Create default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the mirroring of alternate copies
+    pub fn mirror(mut self, mirror: Mirror) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Set how the copies' threads are joined
+    pub fn colors(mut self, colors: ColorStrategy) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Set whether circular copies turn to face outward
+    pub fn rotate_copies(mut self, rotate: bool) -> Self {
+        self.rotate_copies = rotate;
+        self
+    }
+
+    /// Set the angle of the first circular copy in degrees
+    pub fn start_angle(mut self, degrees: f64) -> Self {
+        self.start_angle = degrees;
+        self
+    }
+}
+
+/// Repeat a motif in a grid of `rows` by `cols` copies
+///
+/// Copy (row, col) is moved by (`col * dx`, `row * dy`) (0.1mm units) and
+/// sewn row by row. With mirroring, copies where `row + col` is odd are
+/// flipped in place. The result keeps the motif's metadata.
+///
+/// Fails if the grid is empty, the spacing isn't finite or the motif has no
+/// stitches.
+pub fn repeat(
+    pattern: &EmbPattern,
+    rows: usize,
+    cols: usize,
+    dx: f64,
+    dy: f64,
+    options: &LayoutOptions,
+) -> Result<EmbPattern> {
+    if rows == 0 || cols == 0 {
+        return Err(Error::InvalidPattern(format!(
+            "Repeat grid must have at least one copy, got {} x {}",
+            rows, cols
+        )));
+    }
+    if !(dx.is_finite() && dy.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Repeat spacing must be finite, got ({}, {})",
+            dx, dy
+        )));
+    }
+    let center = motif_center(pattern)?;
+
+    let mut result = empty_like(pattern);
+    for row in 0..rows {
+        for col in 0..cols {
+            let mut placement = EmbMatrix::new();
+            placement.post_translate(col as f64 * dx, row as f64 * dy);
+            if (row + col) % 2 == 1 {
+                mirror(&mut placement, options.mirror, center);
+            }
+            result.append_pattern(pattern, &placement, options.colors);
+        }
+    }
+    Ok(result)
+}
+
+/// Repeat a motif `count` times around a circle centered on the origin
+///
+/// The motif's center is placed on the circle of `radius` (0.1mm units) at
+/// equal angles from `start_angle`. Copies turn with their angle unless
+/// `rotate_copies` is off, so a motif pointing along +x points outward. With
+/// mirroring, odd copies are flipped in place before turning.
+///
+/// Fails if `count` is zero, the radius isn't finite or the motif has no
+/// stitches.
+pub fn repeat_circular(
+    pattern: &EmbPattern,
+    count: usize,
+    radius: f64,
+    options: &LayoutOptions,
+) -> Result<EmbPattern> {
+    if count == 0 {
+        return Err(Error::InvalidPattern(
+            "Circular repeat needs at least one copy".to_string(),
+        ));
+    }
+    if !(radius.is_finite() && options.start_angle.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Circular repeat radius and start angle must be finite, got {} and {}",
+            radius, options.start_angle
+        )));
+    }
+    let (cx, cy) = motif_center(pattern)?;
+
+    let mut result = empty_like(pattern);
+    for index in 0..count {
+        let angle = options.start_angle + 360.0 * index as f64 / count as f64;
+        let (sin, cos) = angle.to_radians().sin_cos();
+
+        // Applied last to first: mirror, turn about the motif center, move it
+        // onto the circle
+        let mut placement = EmbMatrix::new();
+        placement.post_translate(radius * cos - cx, radius * sin - cy);
+        if options.rotate_copies {
+            placement.post_rotate(angle, cx, cy);
+        }
+        if index % 2 == 1 {
+            mirror(&mut placement, options.mirror, (cx, cy));
+        }
+        result.append_pattern(pattern, &placement, options.colors);
+    }
+    Ok(result)
+}
+
+/// Center of the motif's bounds, failing for motifs without stitches
+fn motif_center(pattern: &EmbPattern) -> Result<(f64, f64)> {
+    if !pattern
+        .stitches()
+        .iter()
+        .any(|s| s.command & COMMAND_MASK == STITCH)
+    {
+        return Err(Error::InvalidPattern(
+            "Cannot repeat a motif without stitches".to_string(),
+        ));
+    }
+    let (min_x, min_y, max_x, max_y) = pattern.bounds();
+    Ok(((min_x + max_x) / 2.0, (min_y + max_y) / 2.0))
+}
+
+/// The motif's metadata with no stitches or threads
+fn empty_like(pattern: &EmbPattern) -> EmbPattern {
+    let mut result = pattern.clone();
+    result.replace_stitches(Vec::new(), Vec::new());
+    result
+}
+
+/// Flip about the motif center, before the rest of the placement
+fn mirror(placement: &mut EmbMatrix, mirror: Mirror, (cx, cy): (f64, f64)) {
+    match mirror {
+        Mirror::None => {}
+        Mirror::Horizontal => placement.post_scale(-1.0, Some(1.0), cx, cy),
+        Mirror::Vertical => placement.post_scale(1.0, Some(-1.0), cx, cy),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+
+    /// An arrow pointing along +x, from (0, 0) to (40, 10)
+    fn arrow() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.set_title("Arrow");
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(40.0, 5.0);
+        pattern.stitch_abs(0.0, 10.0);
+        pattern.end();
+        pattern
+    }
+
+    fn needle_points(pattern: &EmbPattern) -> Vec<(f64, f64)> {
+        pattern
+            .stitches()
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == STITCH)
+            .map(|s| ((s.x * 1e6).round() / 1e6, (s.y * 1e6).round() / 1e6))
+            .collect()
+    }
+
+    #[test]
+    fn test_grid_with_mirroring() {
+        let options = LayoutOptions::new().mirror(Mirror::Horizontal);
+        let grid = repeat(&arrow(), 2, 2, 100.0, 50.0, &options).unwrap();
+
+        assert_eq!(grid.title(), Some("Arrow"));
+        assert_eq!(grid.threads().len(), 1);
+        assert_eq!(grid.count_color_changes(), 0);
+        assert_eq!(grid.count_trims(), 3);
+
+        let points = needle_points(&grid);
+        assert_eq!(points.len(), 12);
+        // Copy (0, 1) points the other way, copy (1, 1) doesn't
+        assert_eq!(&points[3..6], &[(140.0, 0.0), (100.0, 5.0), (140.0, 10.0)]);
+        assert_eq!(&points[9..], &[(100.0, 50.0), (140.0, 55.0), (100.0, 60.0)]);
+    }
+
+    #[test]
+    fn test_circular_copies_face_outward() {
+        let ring = repeat_circular(&arrow(), 4, 100.0, &LayoutOptions::new()).unwrap();
+        let points = needle_points(&ring);
+        assert_eq!(points.len(), 12);
+        // The tip of each copy lies 20 units further out than its center
+        assert_eq!(points[1], (120.0, 0.0));
+        assert_eq!(points[4], (0.0, 120.0));
+        assert_eq!(points[7], (-120.0, 0.0));
+        assert_eq!(points[10], (0.0, -120.0));
+
+        let upright = LayoutOptions::new().rotate_copies(false);
+        let ring = repeat_circular(&arrow(), 2, 100.0, &upright).unwrap();
+        assert_eq!(needle_points(&ring)[4], (-80.0, 0.0));
+    }
+
+    #[test]
+    fn test_invalid_layouts() {
+        let options = LayoutOptions::new();
+        assert!(repeat(&arrow(), 0, 3, 10.0, 10.0, &options).is_err());
+        assert!(repeat(&arrow(), 1, 3, f64::NAN, 10.0, &options).is_err());
+        assert!(repeat(&EmbPattern::new(), 1, 1, 0.0, 0.0, &options).is_err());
+        assert!(repeat_circular(&arrow(), 0, 10.0, &options).is_err());
+    }
+}
@@ -4,8 +4,13 @@
 //! pattern processing, and batch conversion operations.
 
 /// Batch conversion and multi-format export utilities
+#[cfg(feature = "fs")]
 pub mod batch;
 
+/// Duplicate design detection
+#[cfg(feature = "fs")]
+pub mod dedupe;
+
 /// Huffman compression for HUS format
 pub mod compress;
 
@@ -15,14 +20,35 @@ pub mod error;
 /// Helper functions for encoding/decoding
 pub mod functions;
 
+/// Grid and circular repeats of a motif
+pub mod layout;
+
 /// Thread palette management and color library access
 pub mod palette;
 
 /// Pattern processing utilities
 pub mod processing;
 
+/// Thread brand substitution tables
+pub mod substitution;
+
+/// Machine run-time simulation with a timing model
+pub mod simulate;
+
+/// Multi-hoop splitting of oversized designs
+pub mod split_hoop;
+
 /// Realistic stitch rendering for SVG/PNG/image exports
 pub mod stitch_renderer;
 
 /// UTF-8 string utilities for format handling
 pub mod string;
+
+/// Per-stitch timeline export for playback scrubbers
+pub mod timeline;
+
+/// Centerline tracing of line art into running stitches
+pub mod trace;
+
+/// Signature/watermark motif insertion
+pub mod watermark;
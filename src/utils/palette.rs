@@ -8,8 +8,10 @@ use crate::core::thread::EmbThread;
 use crate::formats::io::{readers, writers};
 use crate::utils::error::{Error, Result};
 use std::collections::HashMap;
+#[cfg(feature = "fs")]
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "fs")]
 use std::path::Path;
 
 /// Supported palette file formats
@@ -75,6 +77,7 @@ impl ThreadPalette {
     }
 
     /// Load palette from file (auto-detects format from extension)
+    #[cfg(feature = "fs")]
     pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path = path.as_ref();
         let ext = path
@@ -113,6 +116,7 @@ impl ThreadPalette {
     }
 
     /// Save palette to file (auto-detects format from extension)
+    #[cfg(feature = "fs")]
     pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let path = path.as_ref();
         let ext = path
@@ -136,8 +140,12 @@ impl ThreadPalette {
         }
 
         match format {
-            PaletteFormat::Edr => writers::edr::write(&pattern, writer)?,
-            PaletteFormat::Col => writers::col::write(&pattern, writer)?,
+            PaletteFormat::Edr => {
+                writers::edr::write(&pattern, writer)?;
+            }
+            PaletteFormat::Col => {
+                writers::col::write(&pattern, writer)?;
+            }
             PaletteFormat::Inf => {
                 // INF requires Seek, so we need to buffer the output
                 let mut buffer = Vec::new();
@@ -369,6 +377,38 @@ impl PaletteLibrary {
         ThreadPalette::from_threads("Janome SEW", crate::palettes::thread_sew::get_thread_set())
     }
 
+    /// Get Madeira Classic Rayon 40 thread chart (40 colors)
+    pub fn madeira_classic_rayon() -> ThreadPalette {
+        ThreadPalette::from_threads(
+            "Madeira Classic Rayon",
+            crate::palettes::thread_madeira_rayon::get_thread_set(),
+        )
+    }
+
+    /// Get Madeira Polyneon 40 thread chart (40 colors)
+    pub fn madeira_polyneon() -> ThreadPalette {
+        ThreadPalette::from_threads(
+            "Madeira Polyneon",
+            crate::palettes::thread_madeira_polyneon::get_thread_set(),
+        )
+    }
+
+    /// Get Isacord 40 thread chart (44 colors)
+    pub fn isacord() -> ThreadPalette {
+        ThreadPalette::from_threads(
+            "Isacord 40",
+            crate::palettes::thread_isacord::get_thread_set(),
+        )
+    }
+
+    /// Get Robison-Anton Super Strength Rayon thread chart (34 colors)
+    pub fn robison_anton_rayon() -> ThreadPalette {
+        ThreadPalette::from_threads(
+            "Robison-Anton Super Strength Rayon",
+            crate::palettes::thread_robison_anton::get_thread_set(),
+        )
+    }
+
     /// Get all available built-in palettes
     pub fn all_palettes() -> Vec<ThreadPalette> {
         vec![
@@ -377,13 +417,31 @@ impl PaletteLibrary {
             Self::husqvarna_shv(),
             Self::janome_jef(),
             Self::janome_sew(),
+            Self::madeira_classic_rayon(),
+            Self::madeira_polyneon(),
+            Self::isacord(),
+            Self::robison_anton_rayon(),
         ]
     }
 
     /// Get palette by name (case-insensitive)
+    ///
+    /// Palettes registered with [`crate::palettes::custom::register`] are matched
+    /// by their exact name first.
     pub fn get_by_name(name: &str) -> Option<ThreadPalette> {
+        if let Some(threads) = crate::palettes::custom::get(name) {
+            return Some(ThreadPalette::from_threads(name.trim(), threads));
+        }
         let name_lower = name.to_lowercase();
-        if name_lower.contains("pec") || name_lower.contains("brother") {
+        if name_lower.contains("polyneon") {
+            Some(Self::madeira_polyneon())
+        } else if name_lower.contains("madeira") {
+            Some(Self::madeira_classic_rayon())
+        } else if name_lower.contains("isacord") {
+            Some(Self::isacord())
+        } else if name_lower.contains("robison") || name_lower.contains("anton") {
+            Some(Self::robison_anton_rayon())
+        } else if name_lower.contains("pec") || name_lower.contains("brother") {
             Some(Self::brother_pec())
         } else if name_lower.contains("hus") && !name_lower.contains("shv") {
             Some(Self::husqvarna_hus())
@@ -600,13 +658,38 @@ mod tests {
         assert!(PaletteLibrary::get_by_name("shv").is_some());
         assert!(PaletteLibrary::get_by_name("jef").is_some());
         assert!(PaletteLibrary::get_by_name("sew").is_some());
+        assert_eq!(
+            PaletteLibrary::get_by_name("Madeira Polyneon")
+                .unwrap()
+                .name,
+            "Madeira Polyneon"
+        );
+        assert_eq!(
+            PaletteLibrary::get_by_name("madeira").unwrap().name,
+            "Madeira Classic Rayon"
+        );
+        assert!(PaletteLibrary::get_by_name("isacord").is_some());
+        assert!(PaletteLibrary::get_by_name("Robison-Anton").is_some());
         assert!(PaletteLibrary::get_by_name("invalid").is_none());
     }
 
+    #[test]
+    fn test_commercial_chart_delta_e_matching() {
+        let palette = PaletteLibrary::isacord();
+        let (index, distance) = EmbThread::new(0xC5122F)
+            .find_closest_delta_e(&palette.threads)
+            .unwrap();
+        assert_eq!(
+            palette.threads[index].catalog_number.as_deref(),
+            Some("1902")
+        );
+        assert!(distance < 2.0);
+    }
+
     #[test]
     fn test_palette_library_all_palettes() {
         let palettes = PaletteLibrary::all_palettes();
-        assert_eq!(palettes.len(), 5);
+        assert_eq!(palettes.len(), 9);
 
         let names: Vec<_> = palettes.iter().map(|p| p.name.as_str()).collect();
         assert!(names.contains(&"Brother PEC"));
@@ -614,6 +697,8 @@ mod tests {
         assert!(names.contains(&"Husqvarna SHV"));
         assert!(names.contains(&"Janome JEF"));
         assert!(names.contains(&"Janome SEW"));
+        assert!(names.contains(&"Madeira Classic Rayon"));
+        assert!(names.contains(&"Isacord 40"));
     }
 
     #[test]
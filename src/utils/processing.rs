@@ -3,8 +3,18 @@
 //! Provides functions for normalizing patterns, calculating statistics, interpolating stitches,
 //! and other common pattern manipulation operations used across different file formats.
 
+use crate::core::block::join_blocks;
 use crate::core::constants::*;
+use crate::core::density::DEFAULT_CELL_SIZE;
+use crate::core::path::{running_stitch, CORNER_ANGLE};
 use crate::core::pattern::{EmbPattern, Stitch};
+use crate::core::spatial::StitchIndex;
+use crate::core::thread::EmbThread;
+use crate::geometry::{offset_polyline, pattern_outline, Point};
+use crate::utils::error::{Error, Result};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
 
 /// Normalize pattern to start at (0, 0)
 ///
@@ -142,6 +152,763 @@ pub fn remove_duplicates(pattern: &mut EmbPattern) {
     *pattern = EmbPattern::from_stitches(new_stitches, pattern.threads().to_vec());
 }
 
+/// Remove stitches shorter than `min_length` (0.1mm), returning how many were removed
+///
+/// See [`EmbPattern::remove_small_stitches`]; chain after [`remove_duplicates`].
+pub fn remove_small_stitches(pattern: &mut EmbPattern, min_length: f64) -> Result<usize> {
+    pattern.remove_small_stitches(min_length)
+}
+
+/// Shape of a basting outline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineShape {
+    /// Rectangle around the design's bounds
+    BoundingBox,
+    /// Convex hull of the design's needle points
+    ConvexHull,
+}
+
+/// Settings for [`add_outline`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineSettings {
+    /// Outline shape (default: bounding box)
+    pub shape: OutlineShape,
+    /// Gap between the design and the outline in 0.1mm (default: 50.0)
+    pub offset: f64,
+    /// Running stitch length in 0.1mm (default: 40.0)
+    pub stitch_length: f64,
+    /// Thread for the outline's color block (default: black, "Basting")
+    pub thread: EmbThread,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            shape: OutlineShape::BoundingBox,
+            offset: 50.0,
+            stitch_length: 40.0,
+            thread: EmbThread::new(0x000000).with_description("Basting"),
+        }
+    }
+}
+
+/// Add a running-stitch outline around the design as its first color block
+///
+/// The outline is stitched before the design, for basting the fabric or checking
+/// the hooping, and ends with a trim and color change. Patterns without threads
+/// get a black thread for the design so the thread list matches the blocks.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::utils::processing::{add_outline, OutlineSettings};
+///
+/// let mut pattern = EmbPattern::new();
+/// pattern.stitch_abs(0.0, 0.0);
+/// pattern.stitch_abs(100.0, 100.0);
+///
+/// add_outline(&mut pattern, &OutlineSettings::default())?;
+///
+/// assert_eq!(pattern.bounds(), (-50.0, -50.0, 150.0, 150.0));
+/// assert_eq!(pattern.count_color_changes(), 1);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn add_outline(pattern: &mut EmbPattern, settings: &OutlineSettings) -> Result<()> {
+    if !(settings.offset >= 0.0 && settings.offset.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Outline offset must be non-negative, got {}",
+            settings.offset
+        )));
+    }
+    if pattern.count_stitches() == 0 {
+        return Err(Error::InvalidPattern(
+            "Cannot outline a pattern without stitches".to_string(),
+        ));
+    }
+
+    let mut shape: Vec<Point> = match settings.shape {
+        OutlineShape::BoundingBox => {
+            let (min_x, min_y, max_x, max_y) = stitch_bounds(pattern);
+            let d = settings.offset;
+            vec![
+                (min_x - d, min_y - d),
+                (max_x + d, min_y - d),
+                (max_x + d, max_y + d),
+                (min_x - d, max_y + d),
+            ]
+        }
+        OutlineShape::ConvexHull => {
+            offset_polyline(&pattern_outline(pattern), settings.offset / 10.0, true)
+        }
+    };
+    if shape.len() < 3 {
+        // Hull of a line or point: fall back to a box so there is something to stitch
+        let (min_x, min_y, max_x, max_y) = stitch_bounds(pattern);
+        let d = settings.offset.max(1.0);
+        shape = vec![
+            (min_x - d, min_y - d),
+            (max_x + d, min_y - d),
+            (max_x + d, max_y + d),
+            (min_x - d, max_y + d),
+        ];
+    }
+
+    let points = running_stitch(&shape, settings.stitch_length, true)?;
+    let mut block = Vec::with_capacity(points.len() + 1);
+    block.push(Stitch::new(points[0].0, points[0].1, JUMP));
+    block.extend(points.iter().map(|&(x, y)| Stitch::new(x, y, STITCH)));
+    pattern.prepend_color_block(&block, settings.thread.clone());
+    Ok(())
+}
+
+/// Bounds of the needle-down stitches
+fn stitch_bounds(pattern: &EmbPattern) -> (f64, f64, f64, f64) {
+    pattern
+        .stitches()
+        .iter()
+        .filter(|s| s.command & COMMAND_MASK == STITCH)
+        .fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(min_x, min_y, max_x, max_y), s| {
+                (
+                    min_x.min(s.x),
+                    min_y.min(s.y),
+                    max_x.max(s.x),
+                    max_y.max(s.y),
+                )
+            },
+        )
+}
+
+/// Settings for [`optimize_travel`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TravelSettings {
+    /// Improvement passes over each color block after the greedy ordering (default: 50)
+    pub max_iterations: usize,
+    /// Wall-clock limit for the whole pattern; `None` for no limit (default: 2 seconds)
+    pub time_limit: Option<Duration>,
+    /// Allow sewing a run backwards (default: false)
+    ///
+    /// Only safe when no run relies on its direction, e.g. underlay sewn before
+    /// the top stitches in the same run.
+    pub allow_reverse: bool,
+    /// Travel from this length on is trimmed, shorter travel is jumped (0.1mm, default: 30.0)
+    pub min_trim_length: f64,
+}
+
+impl Default for TravelSettings {
+    fn default() -> Self {
+        Self {
+            max_iterations: 50,
+            time_limit: Some(Duration::from_secs(2)),
+            allow_reverse: false,
+            min_trim_length: 30.0,
+        }
+    }
+}
+
+/// Outcome of [`optimize_travel`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TravelReport {
+    /// Stitch runs considered
+    pub runs: usize,
+    /// Travel between runs before optimizing (0.1mm)
+    pub travel_before: f64,
+    /// Travel between runs after optimizing (0.1mm)
+    pub travel_after: f64,
+    /// Trims before optimizing
+    pub trims_before: usize,
+    /// Trims after optimizing
+    pub trims_after: usize,
+}
+
+/// A run of consecutive needle-down stitches between travel
+struct Run {
+    /// Where the needle goes down when the run is reached by a jump elsewhere
+    landing: Option<Point>,
+    stitches: Vec<Stitch>,
+    bounds: (f64, f64, f64, f64),
+}
+
+impl Run {
+    fn new(landing: Option<Point>, stitches: Vec<Stitch>) -> Self {
+        let landing = landing.filter(|&(x, y)| (x, y) != (stitches[0].x, stitches[0].y));
+        let bounds = stitches.iter().map(|s| (s.x, s.y)).chain(landing).fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(x0, y0, x1, y1), (x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        );
+        Self {
+            landing,
+            stitches,
+            bounds,
+        }
+    }
+
+    /// First and last point when sewn in the given direction
+    fn ends(&self, reversed: bool) -> (Point, Point) {
+        let first = self.stitches[0];
+        let last = self.stitches[self.stitches.len() - 1];
+        let (a, b) = (self.landing.unwrap_or((first.x, first.y)), (last.x, last.y));
+        if reversed {
+            (b, a)
+        } else {
+            (a, b)
+        }
+    }
+
+    /// Stitches in sewing order, starting from a jump to the first point
+    fn sew(&self, reversed: bool, output: &mut Vec<Stitch>) {
+        if reversed {
+            output.extend(self.stitches.iter().rev());
+            output.extend(self.landing.map(|(x, y)| Stitch::new(x, y, STITCH)));
+        } else {
+            output.extend_from_slice(&self.stitches);
+        }
+    }
+
+    fn overlaps(&self, other: &Run) -> bool {
+        let (a, b) = (self.bounds, other.bounds);
+        a.0 <= b.2 && b.0 <= a.2 && a.1 <= b.3 && b.1 <= a.3
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Travel needed to sew runs in `order`, starting at `entry`
+fn travel_length(runs: &[Run], order: &[(usize, bool)], entry: Point) -> f64 {
+    let mut position = entry;
+    let mut total = 0.0;
+    for &(index, reversed) in order {
+        let (start, end) = runs[index].ends(reversed);
+        total += distance(position, start);
+        position = end;
+    }
+    total
+}
+
+/// Nearest-neighbour ordering that keeps overlapping runs in their original order
+fn greedy_order(
+    runs: &[Run],
+    overlaps: &[Vec<usize>],
+    entry: Point,
+    allow_reverse: bool,
+) -> Vec<(usize, bool)> {
+    // Earlier overlapping runs still to be sewn, per run
+    let mut waiting: Vec<usize> = (0..runs.len())
+        .map(|i| overlaps[i].iter().filter(|&&j| j < i).count())
+        .collect();
+    let mut placed = vec![false; runs.len()];
+    let mut order = Vec::with_capacity(runs.len());
+    let mut position = entry;
+    while order.len() < runs.len() {
+        let mut best: Option<(f64, usize, bool)> = None;
+        for (i, run) in runs.iter().enumerate() {
+            if placed[i] || waiting[i] > 0 {
+                continue;
+            }
+            for reversed in [false, true] {
+                if reversed && !allow_reverse {
+                    continue;
+                }
+                let d = distance(position, run.ends(reversed).0);
+                if best.is_none_or(|(best_d, _, _)| d < best_d) {
+                    best = Some((d, i, reversed));
+                }
+            }
+        }
+        // Overlaps only point backwards, so some run is always free
+        let (_, index, reversed) = best.expect("no free run");
+        placed[index] = true;
+        for &j in &overlaps[index] {
+            if j > index {
+                waiting[j] -= 1;
+            }
+        }
+        position = runs[index].ends(reversed).1;
+        order.push((index, reversed));
+    }
+    order
+}
+
+/// Move single runs to better places until nothing improves or the budget runs out
+fn improve_order(
+    runs: &[Run],
+    order: &mut Vec<(usize, bool)>,
+    entry: Point,
+    settings: &TravelSettings,
+    deadline: Option<Instant>,
+) {
+    let end_of = |order: &[(usize, bool)], k: usize| {
+        if k == 0 {
+            entry
+        } else {
+            let (index, reversed) = order[k - 1];
+            runs[index].ends(reversed).1
+        }
+    };
+    for _ in 0..settings.max_iterations {
+        let mut improved = false;
+        for i in 0..order.len() {
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return;
+            }
+            let (index, reversed) = order[i];
+            let run = &runs[index];
+            let (s, e) = run.ends(reversed);
+            let prev = end_of(order, i);
+            let next = order.get(i + 1).map(|&(j, r)| runs[j].ends(r).0);
+            let removed =
+                distance(prev, s) + next.map_or(0.0, |n| distance(e, n) - distance(prev, n));
+
+            // Candidate slots in the order without run i; stop at the first overlapping run
+            let mut candidates = Vec::new();
+            for k in (0..=i).rev() {
+                if k < i && run.overlaps(&runs[order[k].0]) {
+                    break;
+                }
+                candidates.push(k);
+            }
+            for k in i + 1..order.len() {
+                if run.overlaps(&runs[order[k].0]) {
+                    break;
+                }
+                candidates.push(k);
+            }
+
+            let mut best: Option<(f64, usize, bool)> = None;
+            for k in candidates {
+                // Slot k sits between without[k - 1] and without[k]
+                let before = if k == 0 {
+                    entry
+                } else {
+                    let j = if k <= i { k - 1 } else { k };
+                    runs[order[j].0].ends(order[j].1).1
+                };
+                let after_index = if k < i {
+                    Some(k)
+                } else {
+                    Some(k + 1).filter(|&j| j < order.len())
+                };
+                let after = after_index.map(|j| runs[order[j].0].ends(order[j].1).0);
+                for flip in [false, true] {
+                    if flip && !settings.allow_reverse {
+                        continue;
+                    }
+                    let (s, e) = run.ends(reversed != flip);
+                    let added = distance(before, s)
+                        + after.map_or(0.0, |a| distance(e, a) - distance(before, a));
+                    let delta = added - removed;
+                    if delta < -1e-9 && best.is_none_or(|(d, _, _)| delta < d) {
+                        best = Some((delta, k, reversed != flip));
+                    }
+                }
+            }
+            if let Some((_, k, new_reversed)) = best {
+                order.remove(i);
+                order.insert(k, (index, new_reversed));
+                improved = true;
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Reorder the stitch runs within each color block to shorten travel
+///
+/// A run is a stretch of needle-down stitches between jumps or trims. Runs
+/// are ordered nearest-neighbour first and then improved by moving single runs,
+/// within `settings.max_iterations` passes and `settings.time_limit`. Runs whose
+/// bounding boxes overlap keep their original order, so stitch layering is
+/// unchanged, and the result is never longer than the original order.
+///
+/// Travel between runs is rebuilt: it is trimmed from `settings.min_trim_length`
+/// on and jumped otherwise. Color blocks containing commands other than
+/// stitches, jumps and trims are left unchanged.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::utils::processing::{optimize_travel, TravelSettings};
+///
+/// // Three short dashes sewn left, right, middle
+/// let mut pattern = EmbPattern::new();
+/// for x in [0.0, 400.0, 200.0] {
+///     pattern.trim();
+///     pattern.jump_abs(x, 0.0);
+///     pattern.stitch_abs(x + 50.0, 0.0);
+/// }
+/// pattern.end();
+///
+/// let report = optimize_travel(&mut pattern, &TravelSettings::default())?;
+/// assert!(report.travel_after < report.travel_before);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn optimize_travel(
+    pattern: &mut EmbPattern,
+    settings: &TravelSettings,
+) -> Result<TravelReport> {
+    if settings.min_trim_length.is_nan() || settings.min_trim_length < 0.0 {
+        return Err(Error::InvalidPattern(format!(
+            "Minimum trim length must be non-negative, got {}",
+            settings.min_trim_length
+        )));
+    }
+    let deadline = settings.time_limit.map(|limit| Instant::now() + limit);
+    let count_trims = |stitches: &[Stitch]| {
+        stitches
+            .iter()
+            .filter(|s| s.command & COMMAND_MASK == TRIM)
+            .count()
+    };
+
+    let mut report = TravelReport {
+        trims_before: count_trims(pattern.stitches()),
+        ..Default::default()
+    };
+    let mut output: Vec<Stitch> = Vec::with_capacity(pattern.stitches().len());
+    let mut position: Option<Point> = None;
+    for block in pattern
+        .stitches()
+        .split_inclusive(|s| s.command & COMMAND_MASK == COLOR_CHANGE)
+    {
+        // The closing color change or END is copied as is
+        let closing = block
+            .iter()
+            .rev()
+            .take_while(|s| matches!(s.command & COMMAND_MASK, COLOR_CHANGE | END))
+            .count();
+        let (body, tail) = block.split_at(block.len() - closing);
+        let plain = body
+            .iter()
+            .all(|s| matches!(s.command & COMMAND_MASK, STITCH | JUMP | TRIM));
+        if !plain || !body.iter().any(|s| s.command & COMMAND_MASK == STITCH) {
+            output.extend_from_slice(block);
+            position = body
+                .iter()
+                .rev()
+                .find(|s| matches!(s.command & COMMAND_MASK, STITCH | JUMP))
+                .map(|s| (s.x, s.y))
+                .or(position);
+            continue;
+        }
+
+        let mut runs = Vec::new();
+        let mut current = Vec::new();
+        let mut landing = None;
+        for stitch in body {
+            match stitch.command & COMMAND_MASK {
+                STITCH => current.push(*stitch),
+                command => {
+                    if !current.is_empty() {
+                        runs.push(Run::new(landing.take(), std::mem::take(&mut current)));
+                    }
+                    if command == JUMP {
+                        landing = Some((stitch.x, stitch.y));
+                    }
+                }
+            }
+        }
+        if !current.is_empty() {
+            runs.push(Run::new(landing, current));
+        }
+
+        let entry = position.unwrap_or_else(|| runs[0].ends(false).0);
+        let original: Vec<(usize, bool)> = (0..runs.len()).map(|i| (i, false)).collect();
+        let mut overlaps = vec![Vec::new(); runs.len()];
+        for i in 0..runs.len() {
+            for j in i + 1..runs.len() {
+                if runs[i].overlaps(&runs[j]) {
+                    overlaps[i].push(j);
+                    overlaps[j].push(i);
+                }
+            }
+        }
+        let mut order = greedy_order(&runs, &overlaps, entry, settings.allow_reverse);
+        improve_order(&runs, &mut order, entry, settings, deadline);
+        let before = travel_length(&runs, &original, entry);
+        let mut after = travel_length(&runs, &order, entry);
+        if after > before {
+            order = original;
+            after = before;
+        }
+        report.runs += runs.len();
+        report.travel_before += before;
+        report.travel_after += after;
+
+        // Nothing to trim before the first run after a color change
+        let mut attached = false;
+        for (index, reversed) in order {
+            let (start, end) = runs[index].ends(reversed);
+            let gap = position.map(|p| distance(p, start));
+            if let (true, Some(p)) = (attached, position) {
+                if distance(p, start) >= settings.min_trim_length {
+                    output.push(Stitch::new(p.0, p.1, TRIM));
+                }
+            }
+            if gap.is_none_or(|d| d > 0.0) {
+                output.push(Stitch::new(start.0, start.1, JUMP));
+            }
+            runs[index].sew(reversed, &mut output);
+            attached = true;
+            position = Some(end);
+        }
+        // Keep a trim before the color change
+        if body
+            .last()
+            .is_some_and(|s| s.command & COMMAND_MASK == TRIM)
+        {
+            let (x, y) = position.unwrap();
+            output.push(Stitch::new(x, y, TRIM));
+        }
+        output.extend_from_slice(tail);
+    }
+
+    report.trims_after = count_trims(&output);
+    let threads = pattern.threads().to_vec();
+    pattern.replace_stitches(output, threads);
+    Ok(report)
+}
+
+/// Settings for [`connect_runs`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectSettings {
+    /// Gaps from this length on are left as they are (0.1mm, default: 100.0)
+    pub max_gap: f64,
+    /// Longest walk allowed for one gap (0.1mm, default: 300.0)
+    pub max_walk_length: f64,
+    /// Longest step the walk may take off existing stitching, between nearby
+    /// stitches or onto the next run (0.1mm, default: 10.0)
+    pub snap_distance: f64,
+    /// Length of the walk stitches (0.1mm, default: 25.0)
+    pub stitch_length: f64,
+}
+
+impl Default for ConnectSettings {
+    fn default() -> Self {
+        Self {
+            max_gap: 100.0,
+            max_walk_length: 300.0,
+            snap_distance: 10.0,
+            stitch_length: 25.0,
+        }
+    }
+}
+
+/// Outcome of [`connect_runs`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ConnectReport {
+    /// Gaps replaced with walk stitches
+    pub connections: usize,
+    /// Trims removed
+    pub trims_removed: usize,
+    /// Walk stitches added
+    pub walk_stitches: usize,
+    /// Total length of the walks (0.1mm)
+    pub walk_length: f64,
+}
+
+/// Replace short jumps between runs with walk stitches along existing stitching
+///
+/// Within each color block, a gap of jumps and trims between two runs is
+/// connected when the next run starts less than `settings.max_gap` from where
+/// the previous one ended. The walk follows stitches already sewn in the block,
+/// so it is hidden under them: the shortest route through the sewn stitches,
+/// found with a [`StitchIndex`], may step at most `settings.snap_distance` off
+/// the stitching and may be at most `settings.max_walk_length` long. Gaps
+/// without such a route keep their jumps and trims, as do gaps containing
+/// other commands.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::utils::processing::{connect_runs, ConnectSettings};
+///
+/// // A square outline, then a detail starting inside its second corner
+/// let mut pattern = EmbPattern::new();
+/// for (x, y) in [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0), (0.0, 0.0)] {
+///     pattern.stitch_abs(x, y);
+/// }
+/// pattern.trim();
+/// pattern.jump_abs(95.0, 5.0);
+/// pattern.stitch_abs(50.0, 50.0);
+/// pattern.end();
+///
+/// let report = connect_runs(&mut pattern, &ConnectSettings::default())?;
+/// assert_eq!(report.connections, 1);
+/// assert_eq!(pattern.count_trims(), 0);
+/// assert_eq!(pattern.count_jumps(), 0);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn connect_runs(pattern: &mut EmbPattern, settings: &ConnectSettings) -> Result<ConnectReport> {
+    for (name, value) in [
+        ("Maximum gap", settings.max_gap),
+        ("Maximum walk length", settings.max_walk_length),
+        ("Snap distance", settings.snap_distance),
+        ("Walk stitch length", settings.stitch_length),
+    ] {
+        if !(value > 0.0 && value.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "{} must be positive, got {}",
+                name, value
+            )));
+        }
+    }
+
+    let mut report = ConnectReport::default();
+    let mut blocks = pattern.blocks();
+    for block in &mut blocks {
+        let records = &block.stitches;
+        let mut sewn = EmbPattern::new();
+        sewn.replace_stitches(records.clone(), Vec::new());
+        let index = StitchIndex::new(&sewn);
+
+        let mut output = Vec::with_capacity(records.len());
+        let mut i = 0;
+        while i < records.len() {
+            let is_travel = |s: &Stitch| matches!(s.command & COMMAND_MASK, JUMP | TRIM);
+            if i == 0 || !is_travel(&records[i]) || records[i - 1].command & COMMAND_MASK != STITCH
+            {
+                output.push(records[i]);
+                i += 1;
+                continue;
+            }
+            let end = i + records[i..].iter().take_while(|s| is_travel(s)).count();
+            let gap = &records[i..end];
+            let next = records
+                .get(end)
+                .filter(|s| s.command & COMMAND_MASK == STITCH);
+            let Some(next) = next else {
+                output.extend_from_slice(gap);
+                i = end;
+                continue;
+            };
+            // A jump lands where the next run's needle first goes down
+            let target = gap
+                .iter()
+                .rev()
+                .find(|s| s.command & COMMAND_MASK == JUMP)
+                .map_or((next.x, next.y), |s| (s.x, s.y));
+            let from = &records[i - 1];
+            let route = if distance((from.x, from.y), target) < settings.max_gap {
+                walk_route(records, &index, i - 1, target, settings)
+            } else {
+                None
+            };
+            let Some(route) = route else {
+                output.extend_from_slice(gap);
+                i = end;
+                continue;
+            };
+
+            let mut walk = if route.len() > 1 {
+                running_stitch(&route, settings.stitch_length, false)?
+            } else {
+                route
+            };
+            if walk.last() == Some(&(next.x, next.y)) {
+                walk.pop();
+            }
+            report.connections += 1;
+            report.trims_removed += gap
+                .iter()
+                .filter(|s| s.command & COMMAND_MASK == TRIM)
+                .count();
+            report.walk_stitches += walk.len().saturating_sub(1);
+            report.walk_length += walk.windows(2).map(|w| distance(w[0], w[1])).sum::<f64>();
+            output.extend(walk.iter().skip(1).map(|&(x, y)| Stitch::new(x, y, STITCH)));
+            i = end;
+        }
+        block.stitches = output;
+    }
+
+    if report.connections > 0 {
+        let threads = pattern.threads().to_vec();
+        pattern.replace_stitches(join_blocks(&blocks), threads);
+    }
+    Ok(report)
+}
+
+/// Shortest route from stitch `start` to `target` over the stitches sewn up to `start`
+///
+/// Steps follow consecutive stitches of a run, or hop between stitches and onto
+/// the target within the snap distance. Returns the route's points, from the
+/// start stitch to the target, or `None` when the target cannot be reached
+/// within the maximum walk length.
+fn walk_route(
+    records: &[Stitch],
+    index: &StitchIndex,
+    start: usize,
+    target: Point,
+    settings: &ConnectSettings,
+) -> Option<Vec<Point>> {
+    let point = |i: usize| (records[i].x, records[i].y);
+    let is_stitch = |i: usize| records[i].command & COMMAND_MASK == STITCH;
+    let mut cost = vec![f64::INFINITY; start + 1];
+    let mut previous = vec![usize::MAX; start + 1];
+    // Non-negative floats order like their bit patterns
+    let mut heap = BinaryHeap::new();
+    cost[start] = 0.0;
+    heap.push(Reverse((0.0f64.to_bits(), start)));
+
+    let mut arrival: Option<(f64, usize)> = None;
+    while let Some(Reverse((bits, node))) = heap.pop() {
+        let walked = f64::from_bits(bits);
+        if walked > cost[node] {
+            continue;
+        }
+        if arrival.is_some_and(|(best, _)| walked >= best) {
+            break;
+        }
+        let (x, y) = point(node);
+        let hop = distance((x, y), target);
+        let total = walked + hop;
+        if hop <= settings.snap_distance
+            && total <= settings.max_walk_length
+            && arrival.is_none_or(|(best, _)| total < best)
+        {
+            arrival = Some((total, node));
+        }
+
+        let along = [node.checked_sub(1), Some(node + 1)]
+            .into_iter()
+            .flatten()
+            .filter(|&n| n <= start && is_stitch(n));
+        let nearby = index
+            .within_radius(x, y, settings.snap_distance)
+            .into_iter()
+            .filter(|&n| n <= start);
+        for neighbour in along.chain(nearby) {
+            let step = walked + distance((x, y), point(neighbour));
+            if step <= settings.max_walk_length && step < cost[neighbour] {
+                cost[neighbour] = step;
+                previous[neighbour] = node;
+                heap.push(Reverse((step.to_bits(), neighbour)));
+            }
+        }
+    }
+
+    let (_, mut node) = arrival?;
+    let mut route = vec![target];
+    loop {
+        if point(node) != route[route.len() - 1] {
+            route.push(point(node));
+        }
+        if node == start {
+            break;
+        }
+        node = previous[node];
+    }
+    route.reverse();
+    Some(route)
+}
+
 /// Calculate pattern statistics
 #[derive(Debug, Clone, PartialEq)]
 pub struct PatternStats {
@@ -216,6 +983,384 @@ pub fn calculate_stats(pattern: &EmbPattern) -> PatternStats {
     }
 }
 
+/// Settings for [`reduce_density`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensitySettings {
+    /// Density to thin over-dense cells down to, in stitches per cm² (default: 100.0)
+    pub target_density: f64,
+    /// Edge length of the density cells (0.1mm, default: 20.0)
+    pub cell_size: f64,
+    /// Longest stitch removing a stitch may create (0.1mm, default: 70.0)
+    pub max_stitch_length: f64,
+}
+
+impl Default for DensitySettings {
+    fn default() -> Self {
+        Self {
+            target_density: 100.0,
+            cell_size: DEFAULT_CELL_SIZE,
+            max_stitch_length: 70.0,
+        }
+    }
+}
+
+/// Outcome of [`reduce_density`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DensityReport {
+    /// Stitches removed
+    pub stitches_removed: usize,
+    /// Densest cell before thinning, in stitches per cm²
+    pub max_density_before: f64,
+    /// Densest cell after thinning, in stitches per cm²
+    pub max_density_after: f64,
+}
+
+/// Thin stitches in cells denser than the target density
+///
+/// Each over-dense cell drops stitches evenly spread over its sewing order,
+/// like removing every Nth stitch, until it reaches the target. Outlines are
+/// preserved: only stitches in the middle of a straight line are removed
+/// singly, which widens the spacing along fill rows and running stitches, and
+/// zig-zag stitches are removed in pairs, which widens the spacing of a satin
+/// column without moving its edges. Corners, the first and last stitch of every
+/// run and stitches next to other commands are never removed.
+///
+/// A stitch is kept when removing it would join its neighbours into a stitch
+/// longer than `max_stitch_length`, so cells may stay above the target.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::utils::processing::{reduce_density, DensitySettings};
+///
+/// // A 1cm square filled with rows 0.2mm apart
+/// let mut pattern = EmbPattern::new();
+/// for row in 0..50 {
+///     let y = row as f64 * 2.0;
+///     for column in 0..=20 {
+///         let x = column as f64 * 5.0;
+///         pattern.stitch_abs(if row % 2 == 0 { x } else { 100.0 - x }, y);
+///     }
+/// }
+///
+/// let report = reduce_density(&mut pattern, &DensitySettings::default())?;
+/// assert!(report.stitches_removed > 0);
+/// assert!(report.max_density_after < report.max_density_before);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn reduce_density(
+    pattern: &mut EmbPattern,
+    settings: &DensitySettings,
+) -> Result<DensityReport> {
+    if settings.target_density.is_nan() || settings.target_density < 0.0 {
+        return Err(Error::InvalidPattern(format!(
+            "Target density must be non-negative, got {}",
+            settings.target_density
+        )));
+    }
+    if settings.max_stitch_length.is_nan() || settings.max_stitch_length <= 0.0 {
+        return Err(Error::InvalidPattern(format!(
+            "Maximum stitch length must be positive, got {}",
+            settings.max_stitch_length
+        )));
+    }
+
+    let map = pattern.density_map(settings.cell_size)?;
+    let mut report = DensityReport {
+        max_density_before: map.max_density(),
+        max_density_after: map.max_density(),
+        ..Default::default()
+    };
+    let cell_area_cm2 = settings.cell_size * settings.cell_size / 10_000.0;
+    let allowed = (settings.target_density * cell_area_cm2).floor() as usize;
+
+    // Removable units (a stitch, or a zig-zag pair) of each over-dense cell,
+    // filed under the cell of their first stitch
+    let stitches = pattern.stitches();
+    let is_stitch = |i: usize| {
+        stitches
+            .get(i)
+            .is_some_and(|s| s.command & COMMAND_MASK == STITCH)
+    };
+    let interior = |i: usize| i > 0 && is_stitch(i - 1) && is_stitch(i) && is_stitch(i + 1);
+    let cell_of = |i: usize| {
+        map.cell_at(stitches[i].x, stitches[i].y)
+            .map(|(column, row)| row * map.columns + column)
+    };
+    let mut units: Vec<Vec<(usize, usize)>> = vec![Vec::new(); map.counts.len()];
+    let mut removable = vec![0; map.counts.len()];
+    let mut i = 1;
+    while i + 1 < stitches.len() {
+        let unit = match (interior(i), cell_of(i)) {
+            (true, Some(cell)) if map.counts[cell] > allowed => {
+                let turn = turn_angle(&stitches[i - 1], &stitches[i], &stitches[i + 1]);
+                if turn <= STRAIGHT_TURN {
+                    Some((cell, i, i))
+                } else if turn >= REVERSAL_TURN
+                    && interior(i + 1)
+                    && turn_angle(&stitches[i], &stitches[i + 1], &stitches[i + 2]) >= REVERSAL_TURN
+                {
+                    Some((cell, i, i + 1))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        match unit {
+            Some((cell, first, last)) => {
+                units[cell].push((first, last));
+                removable[cell] += last - first + 1;
+                i = last + 1;
+            }
+            None => i += 1,
+        }
+    }
+
+    // Spread the removals evenly over each cell's units
+    // Last stitch of each unit to remove, indexed by its first stitch
+    let mut remove: Vec<Option<usize>> = vec![None; stitches.len()];
+    for (cell, cell_units) in units.iter().enumerate() {
+        if cell_units.is_empty() {
+            continue;
+        }
+        let excess = map.counts[cell] - allowed;
+        let count = cell_units.len();
+        let target = (count * excess.min(removable[cell])).div_ceil(removable[cell]);
+        for (k, &(first, last)) in cell_units.iter().enumerate() {
+            if (k + 1) * target / count > k * target / count {
+                remove[first] = Some(last);
+            }
+        }
+    }
+
+    let mut kept: Vec<Stitch> = Vec::with_capacity(stitches.len());
+    let mut i = 0;
+    while i < stitches.len() {
+        let last = remove[i].unwrap_or(i);
+        let joins = remove[i].is_some()
+            && kept.last().is_some_and(|previous| {
+                previous.distance_to(&stitches[last + 1]) <= settings.max_stitch_length
+            });
+        if joins {
+            report.stitches_removed += last - i + 1;
+        } else {
+            kept.extend_from_slice(&stitches[i..=last]);
+        }
+        i = last + 1;
+    }
+
+    if report.stitches_removed > 0 {
+        let threads = pattern.threads().to_vec();
+        pattern.replace_stitches(kept, threads);
+        report.max_density_after = pattern.density_map(settings.cell_size)?.max_density();
+    }
+    Ok(report)
+}
+
+/// Settings for [`smooth`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmoothSettings {
+    /// Largest distance a removed stitch may lie from the simplified path (0.1mm, default: 2.0)
+    pub tolerance: f64,
+    /// Longest stitch removing stitches may create (0.1mm, default: 40.0)
+    pub max_stitch_length: f64,
+    /// Turns sharper than this are corners and always kept (degrees, default: 30.0)
+    pub corner_angle: f64,
+}
+
+impl Default for SmoothSettings {
+    fn default() -> Self {
+        Self {
+            tolerance: 2.0,
+            max_stitch_length: 40.0,
+            corner_angle: CORNER_ANGLE,
+        }
+    }
+}
+
+/// Outcome of [`smooth`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SmoothReport {
+    /// Stitches removed
+    pub stitches_removed: usize,
+    /// Largest distance of a removed stitch from the simplified path (0.1mm)
+    pub max_deviation: f64,
+}
+
+/// Simplify over-digitized stitch runs
+///
+/// Each run of consecutive stitches is simplified with Ramer-Douglas-Peucker:
+/// stitches are removed while every removed needle point stays within
+/// `tolerance` of the new path. The remaining stitches are chosen corner-aware:
+/// corners sharper than `corner_angle` and the ends of every run are always
+/// kept, so satin zig-zags and outlines keep their shape, and a stretch is split
+/// at the stitch nearest its middle when it would otherwise exceed
+/// `max_stitch_length`. Needle points are never moved, and jumps, trims and
+/// other commands are left alone.
+///
+/// # Example
+///
+/// ```
+/// use butabuti::prelude::*;
+/// use butabuti::utils::processing::{smooth, SmoothSettings};
+///
+/// // A 10mm line digitized with 0.2mm stitches
+/// let mut pattern = EmbPattern::new();
+/// for i in 0..=50 {
+///     pattern.stitch_abs(i as f64 * 2.0, 0.0);
+/// }
+///
+/// // Split into stitches of at most 4mm
+/// let report = smooth(&mut pattern, &SmoothSettings::default())?;
+/// assert_eq!(report.stitches_removed, 46);
+/// assert_eq!(pattern.count_stitches(), 5);
+/// # Ok::<(), butabuti::utils::error::Error>(())
+/// ```
+pub fn smooth(pattern: &mut EmbPattern, settings: &SmoothSettings) -> Result<SmoothReport> {
+    if !(settings.tolerance >= 0.0 && settings.tolerance.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Smoothing tolerance must be non-negative, got {}",
+            settings.tolerance
+        )));
+    }
+    if settings.max_stitch_length.is_nan() || settings.max_stitch_length <= 0.0 {
+        return Err(Error::InvalidPattern(format!(
+            "Maximum stitch length must be positive, got {}",
+            settings.max_stitch_length
+        )));
+    }
+    if !(0.0..=180.0).contains(&settings.corner_angle) {
+        return Err(Error::InvalidPattern(format!(
+            "Corner angle must be between 0 and 180 degrees, got {}",
+            settings.corner_angle
+        )));
+    }
+
+    let stitches = pattern.stitches();
+    let is_stitch = |i: usize| stitches[i].command & COMMAND_MASK == STITCH;
+    let mut keep = vec![true; stitches.len()];
+    let mut report = SmoothReport::default();
+
+    let mut i = 0;
+    while i < stitches.len() {
+        if !is_stitch(i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < stitches.len() && is_stitch(i) {
+            i += 1;
+        }
+        let deviation = simplify_run(&stitches[start..i], settings, &mut keep[start..i]);
+        report.max_deviation = report.max_deviation.max(deviation);
+    }
+
+    report.stitches_removed = keep.iter().filter(|&&k| !k).count();
+    if report.stitches_removed > 0 {
+        let (kept, origins): (Vec<Stitch>, Vec<Option<usize>>) = stitches
+            .iter()
+            .zip(&keep)
+            .enumerate()
+            .filter(|(_, (_, &k))| k)
+            .map(|(i, (s, _))| (*s, Some(i)))
+            .unzip();
+        pattern.rebuild_stitches(kept, &origins);
+    }
+    Ok(report)
+}
+
+/// Mark the stitches of one run to keep, returning the largest deviation of a removed one
+fn simplify_run(run: &[Stitch], settings: &SmoothSettings, keep: &mut [bool]) -> f64 {
+    let n = run.len();
+    if n < 3 {
+        return 0.0;
+    }
+    keep.fill(false);
+    keep[0] = true;
+    keep[n - 1] = true;
+    for k in 1..n - 1 {
+        if turn_angle(&run[k - 1], &run[k], &run[k + 1]) > settings.corner_angle {
+            keep[k] = true;
+        }
+    }
+
+    let anchors: Vec<usize> = (0..n).filter(|&k| keep[k]).collect();
+    let mut stack: Vec<(usize, usize)> = anchors.windows(2).map(|w| (w[0], w[1])).collect();
+    let mut max_deviation: f64 = 0.0;
+    while let Some((a, b)) = stack.pop() {
+        if b - a < 2 {
+            continue;
+        }
+        let (far, deviation) = (a + 1..b)
+            .map(|k| (k, segment_distance(&run[k], &run[a], &run[b])))
+            .fold((a + 1, -1.0), |best, d| if d.1 > best.1 { d } else { best });
+
+        let split = if deviation > settings.tolerance {
+            far
+        } else if run[a].distance_to(&run[b]) > settings.max_stitch_length {
+            arc_midpoint(&run[a..=b]) + a
+        } else {
+            max_deviation = max_deviation.max(deviation);
+            continue;
+        };
+        keep[split] = true;
+        stack.push((a, split));
+        stack.push((split, b));
+    }
+    max_deviation
+}
+
+/// Distance from `point` to the segment between `a` and `b`
+fn segment_distance(point: &Stitch, a: &Stitch, b: &Stitch) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((point.x - a.x) * dx + (point.y - a.y) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    (point.x - a.x - t * dx).hypot(point.y - a.y - t * dy)
+}
+
+/// Index of the inner stitch nearest half the path length
+fn arc_midpoint(path: &[Stitch]) -> usize {
+    let total: f64 = path.windows(2).map(|w| w[0].distance_to(&w[1])).sum();
+    let mut walked = 0.0;
+    let mut best = (1, f64::MAX);
+    for k in 1..path.len() - 1 {
+        walked += path[k - 1].distance_to(&path[k]);
+        let off = (walked - total / 2.0).abs();
+        if off < best.1 {
+            best = (k, off);
+        }
+    }
+    best.0
+}
+
+/// Largest change of direction for a stitch to count as mid-line (degrees)
+const STRAIGHT_TURN: f64 = 30.0;
+
+/// Smallest change of direction for a stitch to count as a zig-zag point (degrees)
+const REVERSAL_TURN: f64 = 150.0;
+
+/// Change of direction at `at` between the stitches before and after (degrees)
+///
+/// Zero-length stitches count as straight.
+fn turn_angle(before: &Stitch, at: &Stitch, after: &Stitch) -> f64 {
+    let (ax, ay) = (at.x - before.x, at.y - before.y);
+    let (bx, by) = (after.x - at.x, after.y - at.y);
+    let lengths = ax.hypot(ay) * bx.hypot(by);
+    if lengths == 0.0 {
+        return 0.0;
+    }
+    ((ax * bx + ay * by) / lengths)
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -254,6 +1399,78 @@ mod tests {
         assert!(pattern.threads().len() >= 2);
     }
 
+    #[test]
+    fn test_add_outline_box() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(200.0, 100.0);
+        let settings = OutlineSettings {
+            offset: 20.0,
+            ..Default::default()
+        };
+
+        add_outline(&mut pattern, &settings).unwrap();
+
+        assert_eq!(pattern.bounds(), (-20.0, -20.0, 220.0, 120.0));
+        assert_eq!(pattern.threads().len(), 2);
+        assert_eq!(pattern.threads()[0].description.as_deref(), Some("Basting"));
+        assert_eq!(pattern.threads()[1].color, 0xFF0000);
+        assert_eq!(pattern.stitches()[0].command, JUMP);
+
+        // Outline stitches all come before the color change
+        let change = pattern
+            .stitches()
+            .iter()
+            .position(|s| s.command == COLOR_CHANGE)
+            .unwrap();
+        let outline = &pattern.stitches()[..change - 1];
+        assert!(outline
+            .windows(2)
+            .all(|w| w[0].distance_to(&w[1]) <= 40.0 + 1e-9));
+        let design: Vec<_> = pattern.stitches()[change..]
+            .iter()
+            .filter(|s| s.command == STITCH)
+            .collect();
+        assert_eq!(design.len(), 2);
+    }
+
+    #[test]
+    fn test_add_outline_hull() {
+        let mut pattern = EmbPattern::new();
+        for &(x, y) in &[(0.0, 0.0), (100.0, 0.0), (50.0, 100.0), (50.0, 30.0)] {
+            pattern.stitch_abs(x, y);
+        }
+        let settings = OutlineSettings {
+            shape: OutlineShape::ConvexHull,
+            offset: 10.0,
+            stitch_length: 20.0,
+            ..Default::default()
+        };
+
+        add_outline(&mut pattern, &settings).unwrap();
+
+        // Design had no threads, so one is added for it after the basting thread
+        assert_eq!(pattern.threads().len(), 2);
+        let (min_x, min_y, max_x, max_y) = pattern.bounds();
+        assert!((min_y + 10.0).abs() < 1e-6);
+        assert!(min_x < -10.0 && max_x > 110.0 && max_y > 100.0);
+    }
+
+    #[test]
+    fn test_add_outline_invalid() {
+        let mut empty = EmbPattern::new();
+        assert!(add_outline(&mut empty, &OutlineSettings::default()).is_err());
+
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        let settings = OutlineSettings {
+            offset: -1.0,
+            ..Default::default()
+        };
+        assert!(add_outline(&mut pattern, &settings).is_err());
+    }
+
     #[test]
     fn test_remove_duplicates() {
         let mut pattern = EmbPattern::new();
@@ -305,4 +1522,332 @@ mod tests {
         // Should have intermediate jumps
         assert!(pattern.stitches().len() > 3);
     }
+
+    /// Horizontal dashes of 50 starting at the given x, each after a trim and jump
+    fn dashes(starts: &[f64]) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for &x in starts {
+            pattern.trim();
+            pattern.jump_abs(x, 0.0);
+            pattern.stitch_abs(x, 0.0);
+            pattern.stitch_abs(x + 50.0, 0.0);
+        }
+        pattern.end();
+        pattern
+    }
+
+    fn run_starts(pattern: &EmbPattern) -> Vec<f64> {
+        pattern
+            .stitches()
+            .windows(2)
+            .filter(|w| w[0].command == JUMP && w[1].command == STITCH)
+            .map(|w| w[1].x)
+            .collect()
+    }
+
+    #[test]
+    fn test_optimize_travel_reorders_runs() {
+        let mut pattern = dashes(&[0.0, 400.0, 60.0, 340.0, 120.0]);
+        let stitch_count = pattern.count_stitches();
+
+        let report = optimize_travel(&mut pattern, &TravelSettings::default()).unwrap();
+        assert_eq!(report.runs, 5);
+        assert_eq!(run_starts(&pattern), vec![0.0, 60.0, 120.0, 340.0, 400.0]);
+        assert!((report.travel_after - 200.0).abs() < 1e-9);
+        assert!(report.travel_after < report.travel_before);
+        assert_eq!(pattern.count_stitches(), stitch_count);
+
+        // Only the 17mm gap is long enough to trim
+        assert_eq!(report.trims_before, 5);
+        assert_eq!(report.trims_after, 1);
+        assert_eq!(pattern.stitches().last().unwrap().command, END);
+    }
+
+    #[test]
+    fn test_optimize_travel_keeps_layering_and_colors() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        // Fill, far away detail, then an outline over the fill
+        for (x0, x1) in [(0.0, 100.0), (1000.0, 1050.0), (0.0, 100.0)] {
+            pattern.trim();
+            pattern.jump_abs(x0, 0.0);
+            pattern.stitch_abs(x0, 0.0);
+            pattern.stitch_abs(x1, 0.0);
+        }
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.jump_abs(0.0, 100.0);
+        pattern.stitch_abs(50.0, 100.0);
+        pattern.end();
+
+        optimize_travel(&mut pattern, &TravelSettings::default()).unwrap();
+        let red: Vec<f64> = run_starts(&pattern).into_iter().take(3).collect();
+        assert_eq!(red, vec![0.0, 0.0, 1000.0]);
+
+        let change = pattern
+            .stitches()
+            .iter()
+            .position(|s| s.command == COLOR_CHANGE)
+            .unwrap();
+        assert_eq!(pattern.stitches()[change - 1].command, TRIM);
+        assert_eq!(pattern.count_color_changes(), 1);
+    }
+
+    #[test]
+    fn test_optimize_travel_reverse() {
+        // Out along one line and back along the next
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 0.0);
+        pattern.trim();
+        pattern.jump_abs(0.0, 10.0);
+        pattern.stitch_abs(0.0, 10.0);
+        pattern.stitch_abs(50.0, 10.0);
+        let settings = TravelSettings {
+            allow_reverse: true,
+            ..Default::default()
+        };
+
+        let report = optimize_travel(&mut pattern, &settings).unwrap();
+        assert!(report.travel_before > 50.0);
+        assert!((report.travel_after - 10.0).abs() < 1e-9);
+        assert_eq!(pattern.stitches().last().unwrap().x, 0.0);
+        assert_eq!(report.trims_after, 0);
+
+        let invalid = TravelSettings {
+            min_trim_length: f64::NAN,
+            ..Default::default()
+        };
+        assert!(optimize_travel(&mut pattern, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_connect_runs_walks_along_stitching() {
+        // An 8mm line, then a second line starting back near its start
+        let mut pattern = EmbPattern::new();
+        for i in 0..=4 {
+            pattern.stitch_abs(i as f64 * 20.0, 0.0);
+        }
+        pattern.trim();
+        pattern.jump_abs(0.0, 5.0);
+        pattern.stitch_abs(0.0, 50.0);
+        pattern.end();
+
+        let report = connect_runs(&mut pattern, &ConnectSettings::default()).unwrap();
+        assert_eq!(report.connections, 1);
+        assert_eq!(report.trims_removed, 1);
+        assert_eq!(pattern.count_trims(), 0);
+        assert_eq!(pattern.count_jumps(), 0);
+        // Back along the line and a half-millimetre step up
+        let walk = &pattern.stitches()[5..5 + report.walk_stitches];
+        assert!(walk[..walk.len() - 1].iter().all(|s| s.y == 0.0));
+        assert_eq!((walk[walk.len() - 1].x, walk[walk.len() - 1].y), (0.0, 5.0));
+        assert!((report.walk_length - 85.0).abs() < 1e-9);
+        assert_eq!(pattern.stitches()[5 + report.walk_stitches].y, 50.0);
+    }
+
+    #[test]
+    fn test_connect_runs_keeps_unreachable_gaps() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(50.0, 0.0);
+        // Off the stitching
+        pattern.trim();
+        pattern.jump_abs(50.0, 60.0);
+        pattern.stitch_abs(80.0, 60.0);
+        // Across a color change
+        pattern.color_change(0.0, 0.0);
+        pattern.stitch_abs(80.0, 65.0);
+        // Too far away
+        pattern.trim();
+        pattern.jump_abs(300.0, 0.0);
+        pattern.stitch_abs(300.0, 50.0);
+        pattern.end();
+        let before = pattern.stitches().to_vec();
+
+        let report = connect_runs(&mut pattern, &ConnectSettings::default()).unwrap();
+        assert_eq!(report, ConnectReport::default());
+        assert_eq!(pattern.stitches(), &before[..]);
+
+        let invalid = ConnectSettings {
+            snap_distance: 0.0,
+            ..Default::default()
+        };
+        assert!(connect_runs(&mut pattern, &invalid).is_err());
+    }
+
+    /// Rows 0.2mm apart across a 1cm square, stitches every 0.5mm
+    fn dense_fill() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        for row in 0..50 {
+            let y = row as f64 * 2.0;
+            for column in 0..=20 {
+                let x = column as f64 * 5.0;
+                pattern.stitch_abs(if row % 2 == 0 { x } else { 100.0 - x }, y);
+            }
+        }
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_reduce_density_thins_fill_rows() {
+        let mut pattern = dense_fill();
+        let before = pattern.count_stitches();
+        let report = reduce_density(&mut pattern, &DensitySettings::default()).unwrap();
+
+        assert_eq!(pattern.count_stitches(), before - report.stitches_removed);
+        assert!(report.max_density_after < report.max_density_before);
+        // Row ends are corners of the outline and stay
+        for row in 0..50 {
+            let y = row as f64 * 2.0;
+            for x in [0.0, 100.0] {
+                assert!(pattern.stitches().iter().any(|s| s.x == x && s.y == y));
+            }
+        }
+        assert!(pattern
+            .stitches()
+            .windows(2)
+            .all(|pair| pair[0].distance_to(&pair[1]) <= 70.0));
+    }
+
+    #[test]
+    fn test_reduce_density_keeps_satin_edges() {
+        let mut pattern = EmbPattern::new();
+        for i in 0..200 {
+            let x = if i % 2 == 0 { 0.0 } else { 30.0 };
+            pattern.stitch_abs(x, i as f64);
+        }
+
+        let report = reduce_density(&mut pattern, &DensitySettings::default()).unwrap();
+        assert!(report.stitches_removed > 0);
+        assert_eq!(report.stitches_removed % 2, 0);
+        // Remaining stitches still alternate between the two rails
+        for pair in pattern.stitches().windows(2) {
+            assert!(pair[0].x == 0.0 || pair[0].x == 30.0);
+            assert_ne!(pair[0].x, pair[1].x);
+        }
+    }
+
+    #[test]
+    fn test_reduce_density_limits() {
+        let mut pattern = dense_fill();
+        let short = DensitySettings {
+            max_stitch_length: 5.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            reduce_density(&mut pattern, &short)
+                .unwrap()
+                .stitches_removed,
+            0
+        );
+
+        let sparse = DensitySettings {
+            target_density: 1e6,
+            ..Default::default()
+        };
+        assert_eq!(
+            reduce_density(&mut pattern, &sparse)
+                .unwrap()
+                .stitches_removed,
+            0
+        );
+
+        let invalid = DensitySettings {
+            target_density: -1.0,
+            ..Default::default()
+        };
+        assert!(reduce_density(&mut pattern, &invalid).is_err());
+    }
+
+    #[test]
+    fn test_smooth_over_digitized_curve() {
+        // 1cm radius circle with 0.17mm stitches
+        let circle: Vec<Point> = (0..=360)
+            .map(|k| {
+                let a = (k as f64).to_radians();
+                (100.0 * a.cos(), 100.0 * a.sin())
+            })
+            .collect();
+        let mut pattern = EmbPattern::new();
+        for &(x, y) in &circle {
+            pattern.stitch_abs(x, y);
+        }
+        let original = pattern.stitches().to_vec();
+
+        let settings = SmoothSettings::default();
+        let report = smooth(&mut pattern, &settings).unwrap();
+        assert!(report.stitches_removed > 300, "{:?}", report);
+        assert!(report.max_deviation <= settings.tolerance);
+
+        let kept = pattern.stitches();
+        assert_eq!(kept.len(), original.len() - report.stitches_removed);
+        assert!(kept
+            .windows(2)
+            .all(|w| w[0].distance_to(&w[1]) <= settings.max_stitch_length));
+        for point in &original {
+            let deviation = kept
+                .windows(2)
+                .map(|w| segment_distance(point, &w[0], &w[1]))
+                .fold(f64::MAX, f64::min);
+            assert!(deviation <= settings.tolerance + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_smooth_keeps_corners_and_commands() {
+        let mut pattern = EmbPattern::new();
+        // Satin zig-zag: every stitch is a corner
+        for i in 0..20 {
+            pattern.stitch_abs(if i % 2 == 0 { 0.0 } else { 30.0 }, i as f64 * 4.0);
+        }
+        pattern.trim();
+        pattern.jump_abs(200.0, 0.0);
+        // Tatami row with 3mm stitches is not over-digitized
+        for i in 0..10 {
+            pattern.stitch_abs(200.0 + i as f64 * 30.0, 0.0);
+        }
+        pattern.color_change(0.0, 0.0);
+        // Dense straight run folds into its ends
+        for i in 0..=10 {
+            pattern.stitch_abs(500.0 + i as f64 * 2.0, 50.0);
+        }
+        pattern.end();
+        let before = pattern.stitches().len();
+
+        let report = smooth(&mut pattern, &SmoothSettings::default()).unwrap();
+        assert_eq!(report.stitches_removed, 9);
+        assert_eq!(report.max_deviation, 0.0);
+        assert_eq!(pattern.stitches().len(), before - 9);
+        assert_eq!(pattern.count_stitches(), 32);
+        assert_eq!(pattern.count_trims(), 1);
+        assert_eq!(pattern.count_jumps(), 1);
+        assert_eq!(pattern.count_color_changes(), 1);
+    }
+
+    #[test]
+    fn test_smooth_invalid_settings() {
+        let mut pattern = EmbPattern::new();
+        pattern.stitch_abs(0.0, 0.0);
+        for settings in [
+            SmoothSettings {
+                tolerance: -1.0,
+                ..Default::default()
+            },
+            SmoothSettings {
+                max_stitch_length: 0.0,
+                ..Default::default()
+            },
+            SmoothSettings {
+                corner_angle: 270.0,
+                ..Default::default()
+            },
+        ] {
+            assert!(smooth(&mut pattern, &settings).is_err());
+        }
+    }
 }
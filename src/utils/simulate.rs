@@ -0,0 +1,391 @@
+//! Machine run-time simulation
+//!
+//! Walks a pattern with a [`TimingProfile`] and estimates how long a real machine
+//! takes to sew it. Unlike a flat stitches-per-minute estimate, the model slows the
+//! machine down for stitches the frame cannot cover in one needle cycle, moves the
+//! frame through runs of jumps with a trapezoidal (accelerate, cruise, decelerate)
+//! velocity profile, and charges fixed times for trims and color changes.
+//!
+//! The same model drives the [`timeline`](crate::utils::timeline) and the run time
+//! of the [PDF worksheet](crate::formats::io::writers::pdf).
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::utils::simulate::{simulate, TimingProfile};
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(20.0, 0.0);
+//! pattern.trim();
+//! pattern.end();
+//!
+//! let simulation = simulate(&pattern, &TimingProfile::default());
+//! assert_eq!(simulation.blocks.len(), 1);
+//! assert!((simulation.total_seconds - (0.15 + 2.0)).abs() < 1e-9);
+//! ```
+
+use crate::core::constants::*;
+use crate::core::pattern::EmbPattern;
+
+/// Timing characteristics of an embroidery machine
+///
+/// Distances are in millimeters; pattern coordinates are converted from 0.1mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingProfile {
+    /// Top sewing speed in stitches per minute (default: 800)
+    pub max_speed_spm: f64,
+    /// Fastest the frame can move between two needle penetrations, in mm/s
+    /// (default: 150.0)
+    ///
+    /// Stitches longer than the frame can cover in one cycle at top speed slow
+    /// the machine down.
+    pub frame_speed: f64,
+    /// Frame speed for jumps, in mm/s (default: 300.0)
+    pub jump_speed: f64,
+    /// Frame acceleration for jumps, in mm/s² (default: 1500.0)
+    pub jump_acceleration: f64,
+    /// Time for a trim or cut in seconds (default: 2.0)
+    pub trim_seconds: f64,
+    /// Time for a color change or stop in seconds (default: 10.0)
+    pub color_change_seconds: f64,
+}
+
+impl Default for TimingProfile {
+    fn default() -> Self {
+        Self {
+            max_speed_spm: 800.0,
+            frame_speed: 150.0,
+            jump_speed: 300.0,
+            jump_acceleration: 1500.0,
+            trim_seconds: 2.0,
+            color_change_seconds: 10.0,
+        }
+    }
+}
+
+impl TimingProfile {
+    /// Create a profile with the given top sewing speed
+    pub fn new(max_speed_spm: f64) -> Self {
+        Self {
+            max_speed_spm,
+            ..Default::default()
+        }
+    }
+
+    /// Set the frame speed limit for stitches, in mm/s
+    pub fn frame_speed(mut self, mm_per_second: f64) -> Self {
+        self.frame_speed = mm_per_second;
+        self
+    }
+
+    /// Set the frame speed and acceleration for jumps
+    pub fn jump_motion(mut self, mm_per_second: f64, mm_per_second_squared: f64) -> Self {
+        self.jump_speed = mm_per_second;
+        self.jump_acceleration = mm_per_second_squared;
+        self
+    }
+
+    /// Set the trim duration in seconds
+    pub fn trim_seconds(mut self, seconds: f64) -> Self {
+        self.trim_seconds = seconds;
+        self
+    }
+
+    /// Set the color change duration in seconds
+    pub fn color_change_seconds(mut self, seconds: f64) -> Self {
+        self.color_change_seconds = seconds;
+        self
+    }
+
+    /// Time for one stitch of `length` mm
+    fn stitch_seconds(&self, length: f64) -> f64 {
+        let cycle = if self.max_speed_spm > 0.0 {
+            60.0 / self.max_speed_spm
+        } else {
+            0.0
+        };
+        let travel = if self.frame_speed > 0.0 {
+            length / self.frame_speed
+        } else {
+            0.0
+        };
+        cycle.max(travel)
+    }
+
+    /// Time for a frame move of `distance` mm that starts and ends at rest
+    fn move_seconds(&self, distance: f64) -> f64 {
+        let (v, a) = (self.jump_speed, self.jump_acceleration);
+        if distance <= 0.0 || v <= 0.0 {
+            return 0.0;
+        }
+        if a <= 0.0 {
+            return distance / v;
+        }
+        // Distance covered while reaching top speed and stopping again
+        let ramp = v * v / a;
+        if distance < ramp {
+            2.0 * (distance / a).sqrt()
+        } else {
+            distance / v + v / a
+        }
+    }
+}
+
+/// Simulated timing of one color block
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedBlock {
+    /// Index of the block's thread in the pattern's thread list
+    pub thread: usize,
+    /// Index of the first record in the block
+    pub start: usize,
+    /// Index one past the last record in the block
+    pub end: usize,
+    /// Elapsed time when the block starts, in seconds
+    pub start_seconds: f64,
+    /// Time spent in the block, in seconds (including the color change that
+    /// starts it)
+    pub seconds: f64,
+    /// Stitches sewn in the block
+    pub stitches: usize,
+    /// Jump records in the block
+    pub jumps: usize,
+    /// Trims and cuts in the block
+    pub trims: usize,
+}
+
+/// Result of [`simulate`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Simulation {
+    /// Estimated run time in seconds
+    pub total_seconds: f64,
+    /// Time spent sewing stitches
+    pub sewing_seconds: f64,
+    /// Time spent moving the frame through jumps
+    pub jump_seconds: f64,
+    /// Time spent trimming
+    pub trim_seconds: f64,
+    /// Time spent on color changes and stops
+    pub color_change_seconds: f64,
+    /// Color blocks in sewing order
+    pub blocks: Vec<SimulatedBlock>,
+    /// Elapsed time after each record before END, in seconds
+    ///
+    /// A run of jumps is one frame move, finished at its last jump.
+    pub elapsed: Vec<f64>,
+}
+
+impl Simulation {
+    /// Estimated run time in minutes
+    pub fn total_minutes(&self) -> f64 {
+        self.total_seconds / 60.0
+    }
+
+    fn add_to_block(&mut self, seconds: f64) {
+        self.total_seconds += seconds;
+        if let Some(block) = self.blocks.last_mut() {
+            block.seconds += seconds;
+        }
+    }
+
+    /// Charge the frame move of a run of jumps to its last jump
+    fn finish_move(&mut self, seconds: f64) {
+        self.jump_seconds += seconds;
+        self.add_to_block(seconds);
+        if let Some(last) = self.elapsed.last_mut() {
+            *last = self.total_seconds;
+        }
+    }
+}
+
+/// Simulate sewing a pattern on a machine
+///
+/// Consecutive jumps are treated as one frame move over their combined length.
+/// A new block starts at each color change, and at needle-set commands once the
+/// current block has been sewn.
+pub fn simulate(pattern: &EmbPattern, profile: &TimingProfile) -> Simulation {
+    let mut simulation = Simulation::default();
+    let mut block_sewn = false;
+    let mut pending_jump = 0.0;
+    let (mut x, mut y) = (0.0, 0.0);
+
+    for (index, stitch) in pattern.stitches().iter().enumerate() {
+        let command = stitch.command & COMMAND_MASK;
+        if command != JUMP && pending_jump > 0.0 {
+            simulation.finish_move(profile.move_seconds(pending_jump));
+            pending_jump = 0.0;
+        }
+        if command == END {
+            break;
+        }
+
+        let starts_block = match command {
+            COLOR_CHANGE => true,
+            NEEDLE_SET => block_sewn,
+            _ => false,
+        };
+        if starts_block || simulation.blocks.is_empty() {
+            if let Some(last) = simulation.blocks.last_mut() {
+                last.end = index;
+            }
+            simulation.blocks.push(SimulatedBlock {
+                thread: simulation.blocks.len(),
+                start: index,
+                end: index,
+                start_seconds: simulation.total_seconds,
+                seconds: 0.0,
+                stitches: 0,
+                jumps: 0,
+                trims: 0,
+            });
+            block_sewn = false;
+        }
+
+        let length = (stitch.x - x).hypot(stitch.y - y) / 10.0;
+        match command {
+            STITCH => {
+                let seconds = profile.stitch_seconds(length);
+                simulation.sewing_seconds += seconds;
+                simulation.add_to_block(seconds);
+                if let Some(block) = simulation.blocks.last_mut() {
+                    block.stitches += 1;
+                }
+                block_sewn = true;
+            }
+            JUMP => {
+                pending_jump += length;
+                if let Some(block) = simulation.blocks.last_mut() {
+                    block.jumps += 1;
+                }
+            }
+            TRIM | CUT => {
+                simulation.trim_seconds += profile.trim_seconds;
+                simulation.add_to_block(profile.trim_seconds);
+                if let Some(block) = simulation.blocks.last_mut() {
+                    block.trims += 1;
+                }
+            }
+            COLOR_CHANGE | STOP => {
+                simulation.color_change_seconds += profile.color_change_seconds;
+                simulation.add_to_block(profile.color_change_seconds);
+            }
+            NEEDLE_SET if starts_block => {
+                simulation.color_change_seconds += profile.color_change_seconds;
+                simulation.add_to_block(profile.color_change_seconds);
+            }
+            _ => {}
+        }
+        simulation.elapsed.push(simulation.total_seconds);
+        x = stitch.x;
+        y = stitch.y;
+    }
+
+    if pending_jump > 0.0 {
+        simulation.finish_move(profile.move_seconds(pending_jump));
+    }
+    let records = pattern
+        .stitches()
+        .iter()
+        .position(|s| s.command & COMMAND_MASK == END)
+        .unwrap_or(pattern.stitches().len());
+    if let Some(last) = simulation.blocks.last_mut() {
+        last.end = records;
+    }
+    simulation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_stitch_speed_limit() {
+        let profile = TimingProfile::new(600.0).frame_speed(100.0);
+        // 0.1s cycle covers up to 10mm
+        assert!(approx(profile.stitch_seconds(5.0), 0.1));
+        assert!(approx(profile.stitch_seconds(20.0), 0.2));
+    }
+
+    #[test]
+    fn test_jump_motion_profile() {
+        let profile = TimingProfile::default().jump_motion(100.0, 1000.0);
+        // Short move never reaches top speed: 2 * sqrt(d / a)
+        assert!(approx(profile.move_seconds(2.5), 0.1));
+        // Long move: d / v + v / a
+        assert!(approx(profile.move_seconds(100.0), 1.1));
+        assert_eq!(profile.move_seconds(0.0), 0.0);
+    }
+
+    #[test]
+    fn test_simulate_blocks() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.stitch_abs(0.0, 0.0);
+        pattern.stitch_abs(10.0, 0.0);
+        pattern.trim();
+        pattern.color_change(0.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 510.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 1010.0, 0.0);
+        pattern.stitch_abs(1020.0, 0.0);
+        pattern.end();
+
+        let profile = TimingProfile::new(600.0)
+            .jump_motion(100.0, 1000.0)
+            .trim_seconds(1.0)
+            .color_change_seconds(5.0);
+        let simulation = simulate(&pattern, &profile);
+
+        assert!(approx(simulation.sewing_seconds, 0.3));
+        // Both jumps form one 100mm move
+        assert!(approx(simulation.jump_seconds, 1.1));
+        assert!(approx(simulation.trim_seconds, 1.0));
+        assert!(approx(simulation.color_change_seconds, 5.0));
+        assert!(approx(simulation.total_seconds, 7.4));
+
+        assert_eq!(simulation.blocks.len(), 2);
+        let (first, second) = (&simulation.blocks[0], &simulation.blocks[1]);
+        assert_eq!((first.thread, first.start, first.end), (0, 0, 3));
+        assert_eq!((first.stitches, first.trims), (2, 1));
+        assert!(approx(first.seconds, 1.2));
+        assert_eq!((second.thread, second.start, second.end), (1, 3, 7));
+        assert_eq!((second.stitches, second.jumps), (1, 2));
+        assert!(approx(second.start_seconds, 1.2));
+        assert!(approx(second.seconds, 6.2));
+
+        assert_eq!(simulation.elapsed.len(), 7);
+        // The move finishes at the second jump
+        assert!(approx(simulation.elapsed[4], 6.2));
+        assert!(approx(simulation.elapsed[5], 7.3));
+    }
+
+    #[test]
+    fn test_jumps_cost_more_than_flat_estimate() {
+        let mut pattern = EmbPattern::new();
+        for i in 0..10 {
+            let x = i as f64 * 1000.0;
+            pattern.stitch_abs(x, 0.0);
+            pattern.add_stitch_absolute(JUMP, x + 1000.0, 0.0);
+            pattern.trim();
+        }
+        pattern.end();
+
+        let profile = TimingProfile::default();
+        let flat = pattern.calculate_statistics(profile.max_speed_spm);
+        let simulation = simulate(&pattern, &profile);
+        assert!(simulation.total_minutes() > flat.estimated_time_minutes * 10.0);
+    }
+
+    #[test]
+    fn test_simulate_empty() {
+        let simulation = simulate(&EmbPattern::new(), &TimingProfile::default());
+        assert_eq!(simulation, Simulation::default());
+    }
+}
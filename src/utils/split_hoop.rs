@@ -0,0 +1,569 @@
+//! Splitting oversized designs across several hoopings
+//!
+//! A design larger than the hoop is cut into a grid of hoop-sized sections that
+//! overlap by a few millimeters. Each stitch goes to the section whose seams
+//! enclose its midpoint, so stitches that cross a seam are sewn whole in one
+//! hooping and reach into the overlap rather than being cut. Stitches too long
+//! for the overlap are subdivided.
+//!
+//! Every section starts with small registration crosses on the seams it shares
+//! with its neighbours, sewn in their own color. Both sections of a seam carry the
+//! same crosses, so after re-hooping the fabric the next section can be lined up
+//! with the crosses already sewn.
+//!
+//! Section patterns are centered on their hoop: point (0, 0) of a section is the
+//! hoop center, and [`SectionAlignment::offset`] maps it back into the original
+//! design.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::utils::split_hoop::{split_hoop, SplitOptions};
+//!
+//! // 180mm wide design, 4x4" (100mm) hoop
+//! let mut pattern = EmbPattern::new();
+//! for i in 0..=60 {
+//!     pattern.stitch_abs(i as f64 * 30.0, (i % 2) as f64 * 400.0);
+//! }
+//! pattern.end();
+//!
+//! let split = split_hoop(&pattern, &SplitOptions::new(100.0, 100.0))?;
+//! assert_eq!((split.rows, split.columns), (1, 2));
+//! assert_eq!(split.patterns.len(), 2);
+//! assert!(split.patterns.iter().all(|p| p.width() <= 1000.0));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::constants::*;
+use crate::core::hoop::{Hoop, HoopShape};
+use crate::core::path::running_stitch;
+use crate::core::pattern::{Bounds, EmbPattern, Stitch};
+use crate::core::thread::EmbThread;
+use crate::utils::error::{Error, Result};
+
+/// Settings for splitting a design across hoopings
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitOptions {
+    /// Usable hoop width in millimeters
+    pub hoop_width_mm: f64,
+    /// Usable hoop height in millimeters
+    pub hoop_height_mm: f64,
+    /// Width of the band shared by neighbouring sections in millimeters (default: 10.0)
+    pub overlap_mm: f64,
+    /// Sew registration crosses on shared seams (default: true)
+    pub registration_marks: bool,
+    /// Width of a registration cross in millimeters, capped to the overlap (default: 6.0)
+    pub mark_size_mm: f64,
+}
+
+impl SplitOptions {
+    /// Split for a hoop of the given sewing field size
+    pub fn new(hoop_width_mm: f64, hoop_height_mm: f64) -> Self {
+        Self {
+            hoop_width_mm,
+            hoop_height_mm,
+            overlap_mm: 10.0,
+            registration_marks: true,
+            mark_size_mm: 6.0,
+        }
+    }
+
+    /// Split for a catalog hoop; round hoops use the square that fits inside them
+    pub fn for_hoop(hoop: &Hoop) -> Self {
+        match hoop.shape {
+            HoopShape::Rectangle => Self::new(hoop.width_mm, hoop.height_mm),
+            HoopShape::Round => {
+                let side = hoop.width_mm / std::f64::consts::SQRT_2;
+                Self::new(side, side)
+            }
+        }
+    }
+
+    /// Set the overlap between neighbouring sections (mm)
+    pub fn overlap(mut self, overlap_mm: f64) -> Self {
+        self.overlap_mm = overlap_mm;
+        self
+    }
+
+    /// Enable or disable registration crosses
+    pub fn registration_marks(mut self, enabled: bool) -> Self {
+        self.registration_marks = enabled;
+        self
+    }
+
+    /// Set the registration cross size (mm)
+    pub fn mark_size(mut self, mark_size_mm: f64) -> Self {
+        self.mark_size_mm = mark_size_mm;
+        self
+    }
+}
+
+/// Where a section belongs in the original design
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionAlignment {
+    /// Grid row, from the top
+    pub row: usize,
+    /// Grid column, from the left
+    pub column: usize,
+    /// Position of the section's origin (its hoop center) in the original design
+    pub offset: (f64, f64),
+    /// Area the hoop covers, in original design coordinates
+    pub region: Bounds,
+    /// Centers of the section's registration crosses, in section coordinates
+    pub registration_marks: Vec<(f64, f64)>,
+}
+
+/// A design split into sections, one per hooping, in sewing order
+#[derive(Debug, Clone)]
+pub struct HoopSplit {
+    /// Section patterns, row by row; grid cells without stitches are left out
+    pub patterns: Vec<EmbPattern>,
+    /// Placement of each pattern, in the same order
+    pub alignment: Vec<SectionAlignment>,
+    /// Number of grid rows
+    pub rows: usize,
+    /// Number of grid columns
+    pub columns: usize,
+}
+
+/// One axis of the section grid, in 0.1mm
+struct Axis {
+    start: f64,
+    step: f64,
+    size: f64,
+    count: usize,
+}
+
+impl Axis {
+    fn new(min: f64, max: f64, size: f64, overlap: f64) -> Self {
+        let extent = max - min;
+        let step = size - overlap;
+        let count = if extent <= size {
+            1
+        } else {
+            ((extent - overlap) / step).ceil() as usize
+        };
+        // Spread the slack evenly on both sides
+        let span = count as f64 * step + overlap;
+        Self {
+            start: min - (span - extent) / 2.0,
+            step,
+            size,
+            count,
+        }
+    }
+
+    /// Start and end of tile `i`
+    fn tile(&self, i: usize) -> (f64, f64) {
+        let start = self.start + i as f64 * self.step;
+        (start, start + self.size)
+    }
+
+    /// Seam between tile `i` and `i + 1`, in the middle of their overlap
+    fn seam(&self, i: usize) -> f64 {
+        self.tile(i + 1).0 + (self.size - self.step) / 2.0
+    }
+
+    /// Tile whose seams enclose `v`
+    fn cell(&self, v: f64) -> usize {
+        (0..self.count - 1)
+            .find(|&i| v < self.seam(i))
+            .unwrap_or(self.count - 1)
+    }
+}
+
+/// Stitches of one section while splitting
+struct Section {
+    pattern: EmbPattern,
+    color: Option<usize>,
+    last: Option<(f64, f64)>,
+    /// Thread was cut in the original; trim before sewing on
+    trimmed: bool,
+}
+
+/// Split a design into hoop-sized sections
+///
+/// Only needle-down stitches are split; jumps, trims and color changes are
+/// recreated per section as needed.
+pub fn split_hoop(pattern: &EmbPattern, options: &SplitOptions) -> Result<HoopSplit> {
+    let (hoop_w, hoop_h) = (options.hoop_width_mm * 10.0, options.hoop_height_mm * 10.0);
+    let overlap = options.overlap_mm * 10.0;
+    if !(hoop_w > 0.0 && hoop_h > 0.0 && hoop_w.is_finite() && hoop_h.is_finite()) {
+        return Err(Error::InvalidPattern(format!(
+            "Hoop size must be positive, got {} x {} mm",
+            options.hoop_width_mm, options.hoop_height_mm
+        )));
+    }
+    if !(overlap >= 0.0 && overlap < hoop_w.min(hoop_h)) {
+        return Err(Error::InvalidPattern(format!(
+            "Overlap must be at least 0 and smaller than the hoop, got {} mm",
+            options.overlap_mm
+        )));
+    }
+    if pattern.count_stitches() == 0 {
+        return Err(Error::InvalidPattern(
+            "Pattern has no stitches to split".to_string(),
+        ));
+    }
+
+    let (min_x, min_y, max_x, max_y) = stitch_bounds(pattern);
+    let columns = Axis::new(min_x, max_x, hoop_w, overlap);
+    let rows = Axis::new(min_y, max_y, hoop_h, overlap);
+    if rows.count * columns.count > 10_000 {
+        return Err(Error::InvalidPattern(format!(
+            "Splitting needs {} sections; check the hoop size units",
+            rows.count * columns.count
+        )));
+    }
+
+    let mut sections: Vec<Section> = (0..rows.count * columns.count)
+        .map(|_| Section {
+            pattern: EmbPattern::new(),
+            color: None,
+            last: None,
+            trimmed: false,
+        })
+        .collect();
+
+    // Longest piece whose ends stay within the tile owning its midpoint
+    let max_piece = if overlap > 0.0 {
+        overlap / 2.0
+    } else {
+        f64::MAX
+    };
+    let mut color = 0;
+    let mut position: Option<(f64, f64)> = None;
+    for stitch in pattern.stitches() {
+        let to = (stitch.x, stitch.y);
+        match stitch.command & COMMAND_MASK {
+            STITCH => {
+                let from = position.unwrap_or(to);
+                let length = (to.0 - from.0).hypot(to.1 - from.1);
+                let pieces = if owner_contains(&rows, &columns, from, to) {
+                    1
+                } else {
+                    (length / max_piece).ceil().max(1.0) as usize
+                };
+                for k in 0..pieces {
+                    let t0 = k as f64 / pieces as f64;
+                    let t1 = (k + 1) as f64 / pieces as f64;
+                    let a = lerp(from, to, t0);
+                    let b = if k + 1 == pieces {
+                        to
+                    } else {
+                        lerp(from, to, t1)
+                    };
+                    let mid = lerp(a, b, 0.5);
+                    let index = rows.cell(mid.1) * columns.count + columns.cell(mid.0);
+                    sew(&mut sections[index], pattern, color, a, b);
+                }
+            }
+            COLOR_CHANGE => color += 1,
+            TRIM => {
+                for section in &mut sections {
+                    if section.last.take().is_some() {
+                        section.trimmed = true;
+                    }
+                }
+            }
+            END => break,
+            _ => {}
+        }
+        position = Some(to);
+    }
+
+    let mut patterns = Vec::new();
+    let mut alignment = Vec::new();
+    let used: Vec<bool> = sections
+        .iter()
+        .map(|s| s.pattern.count_stitches() > 0)
+        .collect();
+    for (index, section) in sections.into_iter().enumerate() {
+        if !used[index] {
+            continue;
+        }
+        let (row, column) = (index / columns.count, index % columns.count);
+        let (x0, x1) = columns.tile(column);
+        let (y0, y1) = rows.tile(row);
+        let region = Bounds::new(x0, y0, x1, y1);
+
+        let mut section_pattern = section.pattern;
+        section_pattern.end();
+
+        let marks = if options.registration_marks {
+            mark_centers(
+                &rows,
+                &columns,
+                &used,
+                row,
+                column,
+                (min_x, min_y, max_x, max_y),
+            )
+        } else {
+            Vec::new()
+        };
+        if !marks.is_empty() {
+            let arm = (options.mark_size_mm * 10.0 / 2.0).min(overlap / 2.0);
+            let block = registration_block(&marks, arm)?;
+            section_pattern.prepend_color_block(
+                &block,
+                EmbThread::new(0x000000).with_description("Registration"),
+            );
+        }
+        if let Some(title) = pattern.title() {
+            section_pattern.set_title(format!("{} ({}-{})", title, row + 1, column + 1));
+        }
+
+        let offset = region.center();
+        section_pattern.translate(-offset.0, -offset.1);
+        alignment.push(SectionAlignment {
+            row,
+            column,
+            offset,
+            region,
+            registration_marks: marks
+                .iter()
+                .map(|&(x, y)| (x - offset.0, y - offset.1))
+                .collect(),
+        });
+        patterns.push(section_pattern);
+    }
+
+    Ok(HoopSplit {
+        patterns,
+        alignment,
+        rows: rows.count,
+        columns: columns.count,
+    })
+}
+
+/// Append a stitch from `a` to `b` to a section, traveling there first if needed
+fn sew(section: &mut Section, source: &EmbPattern, color: usize, a: (f64, f64), b: (f64, f64)) {
+    let pattern = &mut section.pattern;
+    let cut = section.last.is_some() || section.trimmed;
+    if section.color != Some(color) {
+        if section.color.is_some() {
+            if cut {
+                pattern.trim();
+            }
+            pattern.color_change(0.0, 0.0);
+        }
+        let thread = source
+            .threads()
+            .get(color)
+            .cloned()
+            .unwrap_or_else(|| EmbThread::new(0x000000));
+        pattern.add_thread(thread);
+        section.color = Some(color);
+        section.last = None;
+        section.trimmed = false;
+    }
+    let continues = section
+        .last
+        .is_some_and(|last| (last.0 - a.0).hypot(last.1 - a.1) < 0.01);
+    if !continues {
+        if section.last.is_some() || section.trimmed {
+            pattern.trim();
+        }
+        pattern.jump_abs(a.0, a.1);
+        pattern.stitch_abs(a.0, a.1);
+    }
+    if continues || a != b {
+        pattern.stitch_abs(b.0, b.1);
+    }
+    section.last = Some(b);
+    section.trimmed = false;
+}
+
+/// Whether both ends of a stitch lie in the tile that owns its midpoint
+fn owner_contains(rows: &Axis, columns: &Axis, a: (f64, f64), b: (f64, f64)) -> bool {
+    let mid = lerp(a, b, 0.5);
+    let (x0, x1) = columns.tile(columns.cell(mid.0));
+    let (y0, y1) = rows.tile(rows.cell(mid.1));
+    [a, b]
+        .iter()
+        .all(|p| p.0 >= x0 && p.0 <= x1 && p.1 >= y0 && p.1 <= y1)
+}
+
+/// Registration cross centers on the seams a section shares with used neighbours
+///
+/// Each seam gets two crosses, a quarter of the way in from either end of the
+/// part of the seam the design spans.
+fn mark_centers(
+    rows: &Axis,
+    columns: &Axis,
+    used: &[bool],
+    row: usize,
+    column: usize,
+    (min_x, min_y, max_x, max_y): (f64, f64, f64, f64),
+) -> Vec<(f64, f64)> {
+    let is_used = |r: usize, c: usize| used[r * columns.count + c];
+    let (x0, x1) = columns.tile(column);
+    let (y0, y1) = rows.tile(row);
+    let (ys, ye) = (y0.max(min_y), y1.min(max_y));
+    let (xs, xe) = (x0.max(min_x), x1.min(max_x));
+    let along = |start: f64, end: f64| [start + (end - start) * 0.25, start + (end - start) * 0.75];
+
+    let mut marks = Vec::new();
+    if column > 0 && is_used(row, column - 1) {
+        let x = columns.seam(column - 1);
+        marks.extend(along(ys, ye).map(|y| (x, y)));
+    }
+    if column + 1 < columns.count && is_used(row, column + 1) {
+        let x = columns.seam(column);
+        marks.extend(along(ys, ye).map(|y| (x, y)));
+    }
+    if row > 0 && is_used(row - 1, column) {
+        let y = rows.seam(row - 1);
+        marks.extend(along(xs, xe).map(|x| (x, y)));
+    }
+    if row + 1 < rows.count && is_used(row + 1, column) {
+        let y = rows.seam(row);
+        marks.extend(along(xs, xe).map(|x| (x, y)));
+    }
+    marks
+}
+
+/// Running stitch crosses at the given centers, with jumps between them
+fn registration_block(centers: &[(f64, f64)], arm: f64) -> Result<Vec<Stitch>> {
+    let mut block = Vec::new();
+    if arm <= 0.0 {
+        return Ok(block);
+    }
+    for &(x, y) in centers {
+        let cross = [
+            (x - arm, y),
+            (x + arm, y),
+            (x, y),
+            (x, y - arm),
+            (x, y + arm),
+        ];
+        let points = running_stitch(&cross, 20.0, false)?;
+        block.push(Stitch::new(points[0].0, points[0].1, JUMP));
+        block.extend(points.iter().map(|&(px, py)| Stitch::new(px, py, STITCH)));
+    }
+    Ok(block)
+}
+
+/// Bounds of needle-down stitches
+fn stitch_bounds(pattern: &EmbPattern) -> (f64, f64, f64, f64) {
+    pattern
+        .stitches()
+        .iter()
+        .filter(|s| s.command & COMMAND_MASK == STITCH)
+        .fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(x0, y0, x1, y1), s| (x0.min(s.x), y0.min(s.y), x1.max(s.x), y1.max(s.y)),
+        )
+}
+
+fn lerp(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Zig-zag fill over `width` x `height` (0.1mm) in two colors
+    fn wide_design(width: f64, height: f64) -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::from_rgb(255, 0, 0));
+        pattern.add_thread(EmbThread::from_rgb(0, 0, 255));
+        let mut x = 0.0;
+        let mut top = true;
+        while x <= width {
+            pattern.stitch_abs(x, if top { 0.0 } else { height });
+            top = !top;
+            x += 25.0;
+        }
+        pattern.color_change(0.0, 0.0);
+        pattern.jump_abs(0.0, height / 2.0);
+        pattern.stitch_abs(0.0, height / 2.0);
+        let mut x = 0.0;
+        while x < width {
+            x = (x + 50.0).min(width);
+            pattern.stitch_abs(x, height / 2.0);
+        }
+        pattern.end();
+        pattern
+    }
+
+    #[test]
+    fn test_sections_fit_hoop() {
+        let design = wide_design(2500.0, 1500.0);
+        let options = SplitOptions::new(100.0, 100.0).overlap(10.0);
+        let split = split_hoop(&design, &options).unwrap();
+        assert_eq!((split.rows, split.columns), (2, 3));
+        assert_eq!(split.patterns.len(), 6);
+
+        for (pattern, placement) in split.patterns.iter().zip(&split.alignment) {
+            let (min_x, min_y, max_x, max_y) = pattern.bounds();
+            assert!(min_x >= -500.0 && max_x <= 500.0, "{:?}", pattern.bounds());
+            assert!(min_y >= -500.0 && max_y <= 500.0, "{:?}", pattern.bounds());
+            assert_eq!(placement.region.width(), 1000.0);
+
+            // Registration thread first, then the design colors sewn here
+            assert!(pattern.threads().len() >= 2);
+            assert_eq!(
+                pattern.threads()[0].description.as_deref(),
+                Some("Registration")
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_stitch_is_lost() {
+        let design = wide_design(1800.0, 400.0);
+        let split = split_hoop(
+            &design,
+            &SplitOptions::new(100.0, 100.0).registration_marks(false),
+        )
+        .unwrap();
+        assert_eq!((split.rows, split.columns), (1, 2));
+
+        // Every original needle point is sewn in some section
+        for stitch in design.stitches().iter().filter(|s| s.command == STITCH) {
+            let found = split.patterns.iter().zip(&split.alignment).any(|(p, a)| {
+                p.stitches().iter().any(|s| {
+                    s.command == STITCH
+                        && (s.x + a.offset.0 - stitch.x).abs() < 1e-6
+                        && (s.y + a.offset.1 - stitch.y).abs() < 1e-6
+                })
+            });
+            assert!(found, "missing ({}, {})", stitch.x, stitch.y);
+        }
+    }
+
+    #[test]
+    fn test_registration_marks_line_up() {
+        let design = wide_design(1800.0, 400.0);
+        let split = split_hoop(&design, &SplitOptions::new(100.0, 100.0)).unwrap();
+        let (left, right) = (&split.alignment[0], &split.alignment[1]);
+        assert_eq!(left.registration_marks.len(), 2);
+
+        // The same crosses, seen from both hoopings
+        for (a, b) in left
+            .registration_marks
+            .iter()
+            .zip(&right.registration_marks)
+        {
+            assert!((a.0 + left.offset.0 - (b.0 + right.offset.0)).abs() < 1e-9);
+            assert!((a.1 + left.offset.1 - (b.1 + right.offset.1)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_small_design_and_invalid_options() {
+        let design = wide_design(500.0, 500.0);
+        let split = split_hoop(&design, &SplitOptions::new(100.0, 100.0)).unwrap();
+        assert_eq!(split.patterns.len(), 1);
+        assert!(split.alignment[0].registration_marks.is_empty());
+        assert_eq!(split.patterns[0].count_stitches(), design.count_stitches());
+
+        assert!(split_hoop(&design, &SplitOptions::new(0.0, 100.0)).is_err());
+        assert!(split_hoop(&design, &SplitOptions::new(100.0, 100.0).overlap(100.0)).is_err());
+        assert!(split_hoop(&EmbPattern::new(), &SplitOptions::new(100.0, 100.0)).is_err());
+    }
+}
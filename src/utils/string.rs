@@ -3,7 +3,8 @@
 //! This module provides string manipulation functions commonly needed when reading
 //! and writing embroidery file formats, which often use C-style null-terminated
 //! strings and fixed-width fields. Also includes a byte iterator with error handling
-//! for convenient format parsing.
+//! for convenient format parsing, and decoding/encoding of legacy (non-UTF-8)
+//! metadata strings such as Shift-JIS and Latin-1.
 
 use std::io::{Bytes, Read};
 
@@ -201,6 +202,200 @@ pub fn to_null_padded(s: &str, width: usize) -> Vec<u8> {
     result
 }
 
+/// Text encoding of metadata strings in legacy formats.
+///
+/// PES files produced by Japanese software commonly store design and thread
+/// names in Shift-JIS, while older European software uses Latin-1. JEF headers
+/// hold no text besides an ASCII date, so JEF needs no encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    /// Detect the encoding from the bytes (see [`detect_encoding`])
+    #[default]
+    Auto,
+    /// UTF-8 (plain ASCII is treated as UTF-8)
+    Utf8,
+    /// ISO-8859-1, one byte per character
+    Latin1,
+    /// Shift-JIS (Windows code page 932 variant)
+    ShiftJis,
+}
+
+impl TextEncoding {
+    /// Canonical lowercase name of the encoding
+    pub fn name(self) -> &'static str {
+        match self {
+            TextEncoding::Auto => "auto",
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Latin1 => "latin-1",
+            TextEncoding::ShiftJis => "shift_jis",
+        }
+    }
+
+    /// Parse an encoding name such as `"shift_jis"`, `"sjis"` or `"iso-8859-1"`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "auto" => Some(TextEncoding::Auto),
+            "utf-8" | "utf8" => Some(TextEncoding::Utf8),
+            "latin-1" | "latin1" | "iso-8859-1" => Some(TextEncoding::Latin1),
+            "shift-jis" | "sjis" | "cp932" | "windows-31j" => Some(TextEncoding::ShiftJis),
+            _ => None,
+        }
+    }
+}
+
+/// Result of decoding a legacy metadata string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedText {
+    /// Decoded text
+    pub text: String,
+    /// Encoding that was used (never `Auto`)
+    pub encoding: TextEncoding,
+    /// Whether every byte was decoded without replacement characters
+    pub lossless: bool,
+}
+
+/// Guess the encoding of a byte string.
+///
+/// Valid UTF-8 (including plain ASCII) is reported as UTF-8. Otherwise, bytes that
+/// decode cleanly as Shift-JIS are reported as Shift-JIS, and anything else falls
+/// back to Latin-1, which can represent every byte.
+///
+/// # Examples
+///
+/// ```
+/// use butabuti::utils::string::{detect_encoding, TextEncoding};
+///
+/// assert_eq!(detect_encoding(b"Rose"), TextEncoding::Utf8);
+/// assert_eq!(detect_encoding(&[0x93, 0xfa, 0x96, 0x7b]), TextEncoding::ShiftJis); // "日本"
+/// assert_eq!(detect_encoding(b"Caf\xe9"), TextEncoding::Latin1);
+/// ```
+pub fn detect_encoding(bytes: &[u8]) -> TextEncoding {
+    if std::str::from_utf8(bytes).is_ok() {
+        return TextEncoding::Utf8;
+    }
+
+    let (_, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes);
+    if !had_errors {
+        TextEncoding::ShiftJis
+    } else {
+        TextEncoding::Latin1
+    }
+}
+
+/// Decode bytes using the given encoding.
+///
+/// With [`TextEncoding::Auto`] the encoding is chosen by [`detect_encoding`].
+/// Unmappable bytes are replaced with U+FFFD and reported via `lossless`.
+///
+/// # Examples
+///
+/// ```
+/// use butabuti::utils::string::{decode_text, TextEncoding};
+///
+/// let decoded = decode_text(&[0x93, 0xfa, 0x96, 0x7b], TextEncoding::Auto);
+/// assert_eq!(decoded.text, "日本");
+/// assert_eq!(decoded.encoding, TextEncoding::ShiftJis);
+/// assert!(decoded.lossless);
+/// ```
+pub fn decode_text(bytes: &[u8], encoding: TextEncoding) -> DecodedText {
+    let encoding = match encoding {
+        TextEncoding::Auto => detect_encoding(bytes),
+        other => other,
+    };
+
+    let (text, lossless) = match encoding {
+        TextEncoding::Utf8 | TextEncoding::Auto => match std::str::from_utf8(bytes) {
+            Ok(s) => (s.to_string(), true),
+            Err(_) => (String::from_utf8_lossy(bytes).to_string(), false),
+        },
+        TextEncoding::Latin1 => (bytes.iter().map(|&b| b as char).collect(), true),
+        TextEncoding::ShiftJis => {
+            let (text, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes);
+            (text.into_owned(), !had_errors)
+        }
+    };
+
+    DecodedText {
+        text,
+        encoding,
+        lossless,
+    }
+}
+
+/// Encode text using the given encoding.
+///
+/// Characters that cannot be represented are replaced with `?`. Returns the
+/// encoded bytes and whether the conversion was lossless. `Auto` encodes as UTF-8.
+///
+/// # Examples
+///
+/// ```
+/// use butabuti::utils::string::{encode_text, TextEncoding};
+///
+/// assert_eq!(encode_text("Café", TextEncoding::Latin1), (b"Caf\xe9".to_vec(), true));
+/// assert_eq!(encode_text("日本", TextEncoding::Latin1), (b"??".to_vec(), false));
+/// ```
+pub fn encode_text(s: &str, encoding: TextEncoding) -> (Vec<u8>, bool) {
+    match encoding {
+        TextEncoding::Auto | TextEncoding::Utf8 => (s.as_bytes().to_vec(), true),
+        TextEncoding::Latin1 => {
+            let mut lossless = true;
+            let bytes = s
+                .chars()
+                .map(|c| {
+                    u8::try_from(u32::from(c)).unwrap_or_else(|_| {
+                        lossless = false;
+                        b'?'
+                    })
+                })
+                .collect();
+            (bytes, lossless)
+        }
+        TextEncoding::ShiftJis => {
+            let mut bytes = Vec::with_capacity(s.len());
+            let mut lossless = true;
+            let mut buf = [0u8; 4];
+            for c in s.chars() {
+                let (encoded, _, had_errors) =
+                    encoding_rs::SHIFT_JIS.encode(c.encode_utf8(&mut buf));
+                if had_errors {
+                    lossless = false;
+                    bytes.push(b'?');
+                } else {
+                    bytes.extend_from_slice(&encoded);
+                }
+            }
+            (bytes, lossless)
+        }
+    }
+}
+
+/// Format raw bytes as a lowercase hex string for storage in pattern metadata.
+///
+/// # Examples
+///
+/// ```
+/// use butabuti::utils::string::bytes_to_hex;
+///
+/// assert_eq!(bytes_to_hex(&[0x93, 0xfa, 0x00]), "93fa00");
+/// ```
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse a hex string produced by [`bytes_to_hex`] back into bytes.
+///
+/// Returns `None` if the string is not valid hex.
+pub fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -447,7 +642,7 @@ mod tests {
 
     #[test]
     fn test_byte_iterator_error_simulation() {
-        use std::io::{self, Error, ErrorKind, Read};
+        use std::io::{self, Error, Read};
 
         // Create a reader that will error after 3 bytes
         struct ErrorReader {
@@ -457,7 +652,7 @@ mod tests {
         impl Read for ErrorReader {
             fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
                 if self.count >= 3 {
-                    return Err(Error::new(ErrorKind::Other, "simulated error"));
+                    return Err(Error::other("simulated error"));
                 }
                 if buf.is_empty() {
                     return Ok(0);
@@ -528,4 +723,66 @@ mod tests {
         assert_eq!(iter.next(), None);
         assert!(iter.closed);
     }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding(b""), TextEncoding::Utf8);
+        assert_eq!(detect_encoding("日本".as_bytes()), TextEncoding::Utf8);
+        assert_eq!(
+            detect_encoding(&[0x93, 0xfa, 0x96, 0x7b]),
+            TextEncoding::ShiftJis
+        );
+        assert_eq!(detect_encoding(&[b'A', 0xff, b'B']), TextEncoding::Latin1);
+    }
+
+    #[test]
+    fn test_decode_text_explicit() {
+        let decoded = decode_text(&[0xc9, b't', b'e'], TextEncoding::Latin1);
+        assert_eq!(decoded.text, "Éte");
+        assert!(decoded.lossless);
+
+        let decoded = decode_text(&[0xc9, b't', b'e'], TextEncoding::Utf8);
+        assert!(!decoded.lossless);
+    }
+
+    #[test]
+    fn test_shift_jis_roundtrip() {
+        let (bytes, lossless) = encode_text("花の刺繍", TextEncoding::ShiftJis);
+        assert!(lossless);
+        let decoded = decode_text(&bytes, TextEncoding::ShiftJis);
+        assert_eq!(decoded.text, "花の刺繍");
+        assert!(decoded.lossless);
+    }
+
+    #[test]
+    fn test_encode_unmappable() {
+        let (bytes, lossless) = encode_text("a🎨b", TextEncoding::ShiftJis);
+        assert_eq!(bytes, b"a?b");
+        assert!(!lossless);
+    }
+
+    #[test]
+    fn test_encoding_names() {
+        for encoding in [
+            TextEncoding::Auto,
+            TextEncoding::Utf8,
+            TextEncoding::Latin1,
+            TextEncoding::ShiftJis,
+        ] {
+            assert_eq!(TextEncoding::from_name(encoding.name()), Some(encoding));
+        }
+        assert_eq!(
+            TextEncoding::from_name("SJIS"),
+            Some(TextEncoding::ShiftJis)
+        );
+        assert_eq!(TextEncoding::from_name("ebcdic"), None);
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = vec![0x00, 0x7f, 0x80, 0xff];
+        assert_eq!(hex_to_bytes(&bytes_to_hex(&bytes)), Some(bytes));
+        assert_eq!(hex_to_bytes("abc"), None);
+        assert_eq!(hex_to_bytes("zz"), None);
+    }
 }
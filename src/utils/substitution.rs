@@ -0,0 +1,461 @@
+//! Thread substitution tables for converting between thread brands
+//!
+//! A `SubstitutionTable` maps catalog numbers of one thread brand to equivalent
+//! catalog numbers of another, so shops that standardize on one brand can convert
+//! the thread callouts of incoming designs automatically.
+//!
+//! Tables can be loaded from and saved to CSV or JSON. The CSV layout is:
+//!
+//! ```text
+//! source_brand,source_catalog,target_brand,target_catalog,description,color
+//! Madeira,1147,Isacord,1902,Christmas Red,#C8102E
+//! ```
+//!
+//! The `description` and `color` columns are optional.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::utils::substitution::{Substitution, SubstitutionTable};
+//!
+//! let mut table = SubstitutionTable::new();
+//! table.add(Substitution::new("Madeira", "1147", "Isacord", "1902"));
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(
+//!     EmbThread::new(0xC8102E)
+//!         .with_brand("Madeira")
+//!         .with_catalog_number("1147"),
+//! );
+//!
+//! assert_eq!(table.apply_to(&mut pattern), 1);
+//! assert_eq!(pattern.threads()[0].brand.as_deref(), Some("Isacord"));
+//! assert_eq!(pattern.threads()[0].catalog_number.as_deref(), Some("1902"));
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::{parse_color_hex, EmbThread};
+use crate::utils::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "fs")]
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// CSV header written by `SubstitutionTable::save`
+const CSV_HEADER: &str =
+    "source_brand,source_catalog,target_brand,target_catalog,description,color";
+
+/// Supported substitution table file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubstitutionFormat {
+    /// Comma-separated values, one mapping per line
+    Csv,
+    /// JSON array of mappings
+    Json,
+}
+
+impl SubstitutionFormat {
+    /// Detect format from file extension
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "csv" => Some(SubstitutionFormat::Csv),
+            "json" => Some(SubstitutionFormat::Json),
+            _ => None,
+        }
+    }
+
+    /// Get file extension for format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubstitutionFormat::Csv => "csv",
+            SubstitutionFormat::Json => "json",
+        }
+    }
+}
+
+/// A single catalog mapping from one thread brand to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Substitution {
+    /// Brand of incoming threads (e.g., "Madeira")
+    pub source_brand: String,
+    /// Catalog number of incoming threads
+    pub source_catalog: String,
+    /// Brand to substitute
+    pub target_brand: String,
+    /// Catalog number to substitute
+    pub target_catalog: String,
+    /// Description of the substitute thread
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Color of the substitute thread (0xRRGGBB)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
+}
+
+impl Substitution {
+    /// Create a mapping between two catalog numbers
+    pub fn new(
+        source_brand: impl Into<String>,
+        source_catalog: impl Into<String>,
+        target_brand: impl Into<String>,
+        target_catalog: impl Into<String>,
+    ) -> Self {
+        Self {
+            source_brand: source_brand.into(),
+            source_catalog: source_catalog.into(),
+            target_brand: target_brand.into(),
+            target_catalog: target_catalog.into(),
+            description: None,
+            color: None,
+        }
+    }
+
+    /// Builder method: set the substitute's description
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Builder method: set the substitute's color
+    pub fn with_color(mut self, color: u32) -> Self {
+        self.color = Some(color & 0xFF_FFFF);
+        self
+    }
+
+    /// Rewrite a thread's callout to the substitute
+    ///
+    /// The color and description are only replaced when the mapping provides them.
+    fn apply(&self, thread: &mut EmbThread) {
+        thread.brand = Some(self.target_brand.clone());
+        thread.catalog_number = Some(self.target_catalog.clone());
+        if let Some(description) = &self.description {
+            thread.description = Some(description.clone());
+        }
+        if let Some(color) = self.color {
+            thread.color = color;
+        }
+    }
+}
+
+/// Lookup key: brand compared case-insensitively, catalog numbers trimmed
+fn lookup_key(brand: &str, catalog: &str) -> (String, String) {
+    (brand.trim().to_lowercase(), catalog.trim().to_lowercase())
+}
+
+/// Brand-to-brand catalog substitution table
+#[derive(Debug, Clone, Default)]
+pub struct SubstitutionTable {
+    entries: Vec<Substitution>,
+    index: HashMap<(String, String), usize>,
+}
+
+impl SubstitutionTable {
+    /// Create an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a table from a list of mappings
+    ///
+    /// Later mappings replace earlier ones with the same source.
+    pub fn from_entries(entries: impl IntoIterator<Item = Substitution>) -> Self {
+        let mut table = Self::new();
+        for entry in entries {
+            table.add(entry);
+        }
+        table
+    }
+
+    /// Add a mapping, replacing any existing mapping for the same source
+    pub fn add(&mut self, substitution: Substitution) {
+        let key = lookup_key(&substitution.source_brand, &substitution.source_catalog);
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i] = substitution,
+            None => {
+                self.index.insert(key, self.entries.len());
+                self.entries.push(substitution);
+            }
+        }
+    }
+
+    /// Find the mapping for a brand and catalog number
+    pub fn lookup(&self, brand: &str, catalog: &str) -> Option<&Substitution> {
+        self.index
+            .get(&lookup_key(brand, catalog))
+            .map(|&i| &self.entries[i])
+    }
+
+    /// All mappings in insertion order
+    pub fn entries(&self) -> &[Substitution] {
+        &self.entries
+    }
+
+    /// Number of mappings
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the table has no mappings
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Substitute the thread callouts of a pattern
+    ///
+    /// Only threads with both a brand and a catalog number are matched. Returns the
+    /// number of threads changed.
+    pub fn apply_to(&self, pattern: &mut EmbPattern) -> usize {
+        let mut changed = 0;
+        for thread in pattern.threads_mut() {
+            let substitution = match (&thread.brand, &thread.catalog_number) {
+                (Some(brand), Some(catalog)) => self.lookup(brand, catalog),
+                _ => None,
+            };
+            if let Some(substitution) = substitution {
+                substitution.apply(thread);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Load table from file (auto-detects format from extension)
+    #[cfg(feature = "fs")]
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let format = Self::format_for_path(path.as_ref())?;
+        let mut file = File::open(path)?;
+        Self::load(&mut file, format)
+    }
+
+    /// Load table from reader with specified format
+    pub fn load(reader: &mut impl Read, format: SubstitutionFormat) -> Result<Self> {
+        match format {
+            SubstitutionFormat::Csv => Self::read_csv(reader),
+            SubstitutionFormat::Json => {
+                let entries: Vec<Substitution> = serde_json::from_reader(reader)?;
+                Ok(Self::from_entries(entries))
+            }
+        }
+    }
+
+    /// Save table to file (auto-detects format from extension)
+    #[cfg(feature = "fs")]
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let format = Self::format_for_path(path.as_ref())?;
+        let mut file = File::create(path)?;
+        self.save(&mut file, format)
+    }
+
+    /// Save table to writer with specified format
+    pub fn save(&self, writer: &mut impl Write, format: SubstitutionFormat) -> Result<()> {
+        match format {
+            SubstitutionFormat::Csv => self.write_csv(writer),
+            SubstitutionFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, &self.entries)?;
+                writeln!(writer)?;
+                Ok(())
+            }
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    fn format_for_path(path: &Path) -> Result<SubstitutionFormat> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .ok_or_else(|| Error::Parse("Substitution file has no extension".to_string()))?;
+
+        SubstitutionFormat::from_extension(ext).ok_or_else(|| {
+            Error::UnsupportedFormat(format!("Unknown substitution table format: .{}", ext))
+        })
+    }
+
+    fn read_csv(reader: &mut impl Read) -> Result<Self> {
+        let mut table = Self::new();
+
+        for (line_number, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = split_csv_line(line);
+            if line_number == 0 && fields[0].eq_ignore_ascii_case("source_brand") {
+                continue;
+            }
+            if fields.len() < 4 {
+                return Err(Error::Parse(format!(
+                    "Line {}: expected at least 4 fields, found {}",
+                    line_number + 1,
+                    fields.len()
+                )));
+            }
+
+            let mut substitution =
+                Substitution::new(&fields[0], &fields[1], &fields[2], &fields[3]);
+            if let Some(description) = fields.get(4).filter(|d| !d.is_empty()) {
+                substitution = substitution.with_description(description.as_str());
+            }
+            if let Some(color) = fields.get(5).filter(|c| !c.is_empty()) {
+                let color = parse_color_hex(color)
+                    .map_err(|e| Error::Parse(format!("Line {}: {}", line_number + 1, e)))?;
+                substitution = substitution.with_color(color);
+            }
+            table.add(substitution);
+        }
+
+        Ok(table)
+    }
+
+    fn write_csv(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        for entry in &self.entries {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                quote_csv_field(&entry.source_brand),
+                quote_csv_field(&entry.source_catalog),
+                quote_csv_field(&entry.target_brand),
+                quote_csv_field(&entry.target_catalog),
+                quote_csv_field(entry.description.as_deref().unwrap_or("")),
+                entry
+                    .color
+                    .map(|c| format!("#{:06X}", c))
+                    .unwrap_or_default()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Split a CSV line, honoring double-quoted fields with `""` escapes
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Quote a CSV field if it contains separators or quotes
+pub(crate) fn quote_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn sample_table() -> SubstitutionTable {
+        SubstitutionTable::from_entries([
+            Substitution::new("Madeira", "1147", "Isacord", "1902")
+                .with_description("Christmas Red")
+                .with_color(0xC8102E),
+            Substitution::new("Madeira", "1000", "Isacord", "0020"),
+        ])
+    }
+
+    #[test]
+    fn test_apply_to_pattern() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(
+            EmbThread::new(0xFF0000)
+                .with_brand("MADEIRA")
+                .with_catalog_number(" 1147 "),
+        );
+        pattern.add_thread(
+            EmbThread::new(0x000000)
+                .with_brand("Madeira")
+                .with_catalog_number("1000")
+                .with_description("Black"),
+        );
+        pattern.add_thread(EmbThread::new(0x00FF00).with_catalog_number("1147"));
+
+        assert_eq!(sample_table().apply_to(&mut pattern), 2);
+
+        let threads = pattern.threads();
+        assert_eq!(threads[0].brand.as_deref(), Some("Isacord"));
+        assert_eq!(threads[0].catalog_number.as_deref(), Some("1902"));
+        assert_eq!(threads[0].description.as_deref(), Some("Christmas Red"));
+        assert_eq!(threads[0].color, 0xC8102E);
+        assert_eq!(threads[1].catalog_number.as_deref(), Some("0020"));
+        assert_eq!(threads[1].description.as_deref(), Some("Black"));
+        assert_eq!(threads[1].color, 0x000000);
+        // No brand: left untouched
+        assert_eq!(threads[2].catalog_number.as_deref(), Some("1147"));
+    }
+
+    #[test]
+    fn test_csv_roundtrip() {
+        let mut table = sample_table();
+        table.add(
+            Substitution::new("Robison-Anton", "2251", "Isacord", "5513")
+                .with_description("Kelly \"Irish\", Green"),
+        );
+
+        let mut output = Vec::new();
+        table.save(&mut output, SubstitutionFormat::Csv).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+        assert!(csv.starts_with(CSV_HEADER));
+        assert!(csv.contains("Madeira,1147,Isacord,1902,Christmas Red,#C8102E"));
+
+        let loaded =
+            SubstitutionTable::load(&mut Cursor::new(csv), SubstitutionFormat::Csv).unwrap();
+        assert_eq!(loaded.entries(), table.entries());
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let table = sample_table();
+        let mut output = Vec::new();
+        table.save(&mut output, SubstitutionFormat::Json).unwrap();
+
+        let loaded =
+            SubstitutionTable::load(&mut Cursor::new(output), SubstitutionFormat::Json).unwrap();
+        assert_eq!(loaded.entries(), table.entries());
+        assert_eq!(
+            loaded.lookup("madeira", "1000").unwrap().target_catalog,
+            "0020"
+        );
+    }
+
+    #[test]
+    fn test_csv_errors_and_duplicates() {
+        let csv = "Madeira,1147,Isacord,1902\nMadeira,1147,Isacord,1903\n";
+        let table =
+            SubstitutionTable::load(&mut Cursor::new(csv), SubstitutionFormat::Csv).unwrap();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.entries()[0].target_catalog, "1903");
+
+        let short = "Madeira,1147,Isacord\n";
+        assert!(SubstitutionTable::load(&mut Cursor::new(short), SubstitutionFormat::Csv).is_err());
+
+        let bad_color = "Madeira,1147,Isacord,1902,,#GGGGGG\n";
+        assert!(
+            SubstitutionTable::load(&mut Cursor::new(bad_color), SubstitutionFormat::Csv).is_err()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! Per-stitch timeline export for playback and progress scrubbers
+//!
+//! A `StitchTimeline` maps every stitch record index to its color block, position
+//! and elapsed machine time, simulated with a [`TimingProfile`]. Data is stored as
+//! parallel arrays so the JSON output maps directly onto JavaScript typed arrays
+//! (`Uint16Array`, `Float32Array`, `Uint32Array`) without per-stitch objects.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::utils::simulate::TimingProfile;
+//! use butabuti::utils::timeline::StitchTimeline;
+//!
+//! let mut pattern = EmbPattern::new();
+//! pattern.add_thread(EmbThread::new(0xFF0000));
+//! pattern.stitch_abs(0.0, 0.0);
+//! pattern.stitch_abs(10.0, 0.0);
+//!
+//! let timeline = StitchTimeline::from_pattern(&pattern, &TimingProfile::default());
+//! assert_eq!(timeline.len(), 2);
+//! assert_eq!(timeline.elapsed_ms[1], 150); // 800 stitches per minute
+//!
+//! let json = timeline.to_json()?;
+//! assert!(json.contains("\"elapsed_ms\":[75,150]"));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::pattern::EmbPattern;
+use crate::utils::error::Result;
+use crate::utils::simulate::{simulate, TimingProfile};
+use serde::Serialize;
+
+/// A contiguous run of records sewn with one thread
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimelineBlock {
+    /// Thread color as `#RRGGBB`
+    pub color: String,
+    /// Index of the first record in the block
+    pub start: usize,
+    /// Index one past the last record in the block
+    pub end: usize,
+    /// Elapsed time when the block starts, in milliseconds
+    pub start_ms: u32,
+}
+
+/// Per-stitch timeline of a pattern
+///
+/// All per-record arrays have the same length: one entry per stitch record,
+/// excluding the trailing END.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StitchTimeline {
+    /// Color block index of each record
+    pub block: Vec<u16>,
+    /// X position of each record (0.1mm units)
+    pub x: Vec<f32>,
+    /// Y position of each record (0.1mm units)
+    pub y: Vec<f32>,
+    /// Cumulative simulated time after each record, in milliseconds
+    pub elapsed_ms: Vec<u32>,
+    /// Color blocks in sewing order
+    pub blocks: Vec<TimelineBlock>,
+    /// Total simulated time in milliseconds
+    pub total_ms: u32,
+}
+
+impl StitchTimeline {
+    /// Build the timeline of a pattern
+    ///
+    /// Blocks are those of [`simulate`]: a new block starts at each color change,
+    /// and at needle-set commands once the current block has been sewn. Block
+    /// colors come from the thread list; blocks without a matching thread are
+    /// reported as black.
+    pub fn from_pattern(pattern: &EmbPattern, profile: &TimingProfile) -> Self {
+        let simulation = simulate(pattern, profile);
+        let ms = |seconds: f64| (seconds * 1000.0).round() as u32;
+
+        let mut timeline = Self::default();
+        for (index, block) in simulation.blocks.iter().enumerate() {
+            timeline.blocks.push(TimelineBlock {
+                color: block_color(pattern, block.thread),
+                start: block.start,
+                end: block.end,
+                start_ms: ms(block.start_seconds),
+            });
+            let id = index.min(u16::MAX as usize) as u16;
+            timeline.block.resize(block.end, id);
+        }
+        for (stitch, &elapsed) in pattern.stitches().iter().zip(&simulation.elapsed) {
+            timeline.x.push(stitch.x as f32);
+            timeline.y.push(stitch.y as f32);
+            timeline.elapsed_ms.push(ms(elapsed));
+        }
+        timeline.total_ms = ms(simulation.total_seconds);
+        timeline
+    }
+
+    /// Number of records in the timeline
+    pub fn len(&self) -> usize {
+        self.elapsed_ms.len()
+    }
+
+    /// Whether the timeline has no records
+    pub fn is_empty(&self) -> bool {
+        self.elapsed_ms.is_empty()
+    }
+
+    /// Index of the last record completed at `time_ms`
+    ///
+    /// Returns `None` before the first record has finished.
+    pub fn index_at(&self, time_ms: u32) -> Option<usize> {
+        self.elapsed_ms
+            .partition_point(|&t| t <= time_ms)
+            .checked_sub(1)
+    }
+
+    /// Serialize to compact JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Color of the thread used for a block, as `#RRGGBB`
+fn block_color(pattern: &EmbPattern, block: usize) -> String {
+    let color = pattern.threads().get(block).map_or(0, |t| t.color);
+    format!("#{:06X}", color & 0xFF_FFFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::constants::*;
+    use crate::core::thread::EmbThread;
+
+    fn two_color_pattern() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_thread(EmbThread::new(0x0000FF));
+        pattern.add_stitch_absolute(STITCH, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 10.0, 0.0);
+        pattern.add_command(TRIM, 10.0, 0.0);
+        pattern.add_command(COLOR_CHANGE, 10.0, 0.0);
+        pattern.add_stitch_absolute(JUMP, 20.0, 5.0);
+        pattern.add_stitch_absolute(STITCH, 30.0, 5.0);
+        pattern.add_command(END, 30.0, 5.0);
+        pattern
+    }
+
+    #[test]
+    fn test_timeline_blocks_and_time() {
+        let options = TimingProfile::new(600.0)
+            .trim_seconds(1.0)
+            .color_change_seconds(5.0);
+        let timeline = StitchTimeline::from_pattern(&two_color_pattern(), &options);
+
+        assert_eq!(timeline.len(), 6);
+        assert_eq!(timeline.block, vec![0, 0, 0, 1, 1, 1]);
+        // The 1.1mm jump is a short frame move: 2 * sqrt(1.118 / 1500) s
+        assert_eq!(timeline.elapsed_ms, vec![100, 200, 1200, 6200, 6255, 6355]);
+        assert_eq!(timeline.total_ms, 6355);
+        assert_eq!(timeline.x[5], 30.0);
+
+        assert_eq!(timeline.blocks.len(), 2);
+        assert_eq!(timeline.blocks[0].color, "#FF0000");
+        assert_eq!((timeline.blocks[0].start, timeline.blocks[0].end), (0, 3));
+        assert_eq!(timeline.blocks[1].color, "#0000FF");
+        assert_eq!((timeline.blocks[1].start, timeline.blocks[1].end), (3, 6));
+        assert_eq!(timeline.blocks[1].start_ms, 1200);
+    }
+
+    #[test]
+    fn test_index_at() {
+        let timeline =
+            StitchTimeline::from_pattern(&two_color_pattern(), &TimingProfile::new(600.0));
+
+        assert_eq!(timeline.index_at(0), None);
+        assert_eq!(timeline.index_at(100), Some(0));
+        assert_eq!(timeline.index_at(150), Some(0));
+        assert_eq!(timeline.index_at(u32::MAX), Some(5));
+    }
+
+    #[test]
+    fn test_leading_needle_set_does_not_split() {
+        let mut pattern = EmbPattern::new();
+        pattern.add_command(NEEDLE_SET, 0.0, 0.0);
+        pattern.add_stitch_absolute(STITCH, 1.0, 1.0);
+
+        let timeline = StitchTimeline::from_pattern(&pattern, &TimingProfile::default());
+        assert_eq!(timeline.block, vec![0, 0]);
+        assert_eq!(timeline.blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_empty_pattern() {
+        let timeline = StitchTimeline::from_pattern(&EmbPattern::new(), &TimingProfile::default());
+        assert!(timeline.is_empty());
+        assert!(timeline.blocks.is_empty());
+        assert_eq!(
+            timeline.to_json().unwrap(),
+            r#"{"block":[],"x":[],"y":[],"elapsed_ms":[],"blocks":[],"total_ms":0}"#
+        );
+    }
+}
@@ -0,0 +1,549 @@
+//! Centerline tracing of line art into running stitches
+//!
+//! Turns high-contrast line art, such as a scanned redwork drawing, into
+//! single-line running-stitch paths. Dark pixels are thinned to a one pixel wide
+//! skeleton (Zhang-Suen), the skeleton is split into branches at its junctions,
+//! short spurs left by thinning are pruned, and the branches are chained into as
+//! few paths as possible, smoothed and stitched.
+//!
+//! Filled shapes trace to their medial axis, not their outline; trace outlines with
+//! the SVG reader instead.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::render::RgbaImage;
+//! use butabuti::utils::trace::CenterlineTrace;
+//!
+//! // A 3 pixel wide horizontal bar on white
+//! let (width, height) = (40, 11);
+//! let mut pixels = Vec::new();
+//! for y in 0..height {
+//!     for _ in 0..width {
+//!         let v = if (4..7).contains(&y) { 0 } else { 255 };
+//!         pixels.extend_from_slice(&[v, v, v, 255]);
+//!     }
+//! }
+//! let image = RgbaImage { width, height, pixels };
+//!
+//! let trace = CenterlineTrace::new().scale(5.0);
+//! let paths = trace.paths(&image)?;
+//! assert_eq!(paths.len(), 1);
+//!
+//! let pattern = trace.generate(&image)?;
+//! assert!(pattern.count_stitches() > 5);
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::fill::append_run;
+use crate::core::path::running_stitch;
+use crate::core::pattern::EmbPattern;
+use crate::core::thread::EmbThread;
+use crate::geometry::Point;
+use crate::render::RgbaImage;
+use crate::utils::error::{Error, Result};
+use std::collections::HashSet;
+
+/// Largest image traced, in pixels
+const MAX_TRACE_PIXELS: usize = 1 << 26;
+
+/// Neighbor offsets, clockwise from north
+const NEIGHBORS: [(isize, isize); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Centerline tracing settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct CenterlineTrace {
+    /// Pattern units (0.1mm) per pixel (default: 1.0)
+    pub scale: f64,
+    /// Luminance below which a pixel is ink, 0 to 255 (default: 128)
+    pub threshold: u8,
+    /// Dead-end branches shorter than this are pruned, in 0.1mm (default: 20.0)
+    pub min_branch_length: f64,
+    /// Paths shorter than this are dropped as specks, in 0.1mm (default: 10.0)
+    pub min_path_length: f64,
+    /// Smoothing passes over each path (default: 2)
+    pub smoothing: usize,
+    /// Running stitch length in 0.1mm (default: 25.0)
+    pub stitch_length: f64,
+    /// Thread of the generated pattern (default: redwork red)
+    pub thread: EmbThread,
+}
+
+impl Default for CenterlineTrace {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            threshold: 128,
+            min_branch_length: 20.0,
+            min_path_length: 10.0,
+            smoothing: 2,
+            stitch_length: 25.0,
+            thread: EmbThread::from_rgb(178, 34, 34).with_description("Redwork Red"),
+        }
+    }
+}
+
+impl CenterlineTrace {
+    /// Create a tracer with default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the pattern units (0.1mm) per pixel
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the scale from the scan resolution, so the design keeps its printed size
+    pub fn dpi(mut self, dpi: f64) -> Self {
+        self.scale = 254.0 / dpi;
+        self
+    }
+
+    /// Set the ink threshold
+    pub fn threshold(mut self, threshold: u8) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set the shortest dead-end branch kept, in 0.1mm
+    pub fn min_branch_length(mut self, min_branch_length: f64) -> Self {
+        self.min_branch_length = min_branch_length;
+        self
+    }
+
+    /// Set the shortest path kept, in 0.1mm
+    pub fn min_path_length(mut self, min_path_length: f64) -> Self {
+        self.min_path_length = min_path_length;
+        self
+    }
+
+    /// Set the number of smoothing passes
+    pub fn smoothing(mut self, smoothing: usize) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    /// Set the running stitch length in 0.1mm
+    pub fn stitch_length(mut self, stitch_length: f64) -> Self {
+        self.stitch_length = stitch_length;
+        self
+    }
+
+    /// Set the thread of the generated pattern
+    pub fn thread(mut self, thread: EmbThread) -> Self {
+        self.thread = thread;
+        self
+    }
+
+    /// Trace the centerlines as polylines in pattern units
+    ///
+    /// Closed loops end back at their first point.
+    pub fn paths(&self, image: &RgbaImage) -> Result<Vec<Vec<Point>>> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(Error::InvalidPattern(format!(
+                "Trace scale must be positive, got {}",
+                self.scale
+            )));
+        }
+        if image.pixels.len() < image.width * image.height * 4 {
+            return Err(Error::InvalidPattern(format!(
+                "Image data too short for {}x{} pixels",
+                image.width, image.height
+            )));
+        }
+        if image.width * image.height > MAX_TRACE_PIXELS {
+            return Err(Error::limit_exceeded(
+                "Trace image pixels",
+                MAX_TRACE_PIXELS,
+                image.width * image.height,
+            ));
+        }
+
+        let mut grid = Grid::from_image(image, self.threshold);
+        grid.thin();
+        let mut branches = grid.branches();
+        prune_spurs(&mut branches, self.min_branch_length / self.scale);
+
+        let min_length = self.min_path_length / self.scale;
+        Ok(chain(branches)
+            .into_iter()
+            .filter(|path| path_length(path) >= min_length)
+            .map(|path| {
+                smooth(path, self.smoothing)
+                    .into_iter()
+                    .map(|(x, y)| ((x + 0.5) * self.scale, (y + 0.5) * self.scale))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Trace the centerlines into a running-stitch pattern in one thread
+    ///
+    /// Paths are joined with a trim and a jump.
+    pub fn generate(&self, image: &RgbaImage) -> Result<EmbPattern> {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(self.thread.clone());
+        for path in self.paths(image)? {
+            let closed = path.len() > 2 && path.first() == path.last();
+            let run = running_stitch(&path, self.stitch_length, closed)?;
+            append_run(&mut pattern, &run);
+        }
+        pattern.end();
+        Ok(pattern)
+    }
+}
+
+/// Ink mask with a one pixel blank border
+struct Grid {
+    width: usize,
+    ink: Vec<bool>,
+}
+
+/// Skeleton between two nodes (or around a loop), as pixel positions
+struct Branch {
+    points: Vec<Point>,
+    /// Node pixel indices at the ends, `None` for a loop without nodes
+    ends: Option<(usize, usize)>,
+}
+
+impl Grid {
+    fn from_image(image: &RgbaImage, threshold: u8) -> Self {
+        let (width, height) = (image.width + 2, image.height + 2);
+        let mut ink = vec![false; width * height];
+        for (i, p) in image.pixels[..image.width * image.height * 4]
+            .chunks_exact(4)
+            .enumerate()
+        {
+            let luminance = 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64;
+            if p[3] >= 128 && luminance < threshold as f64 {
+                let (x, y) = (i % image.width + 1, i / image.width + 1);
+                ink[y * width + x] = true;
+            }
+        }
+        Self { width, ink }
+    }
+
+    fn neighbor(&self, index: usize, (dx, dy): (isize, isize)) -> usize {
+        (index as isize + dy * self.width as isize + dx) as usize
+    }
+
+    /// Ink of the 8 neighbors, clockwise from north
+    fn ring(&self, index: usize) -> [bool; 8] {
+        NEIGHBORS.map(|offset| self.ink[self.neighbor(index, offset)])
+    }
+
+    /// Zhang-Suen thinning to a one pixel wide skeleton
+    fn thin(&mut self) {
+        loop {
+            let mut changed = false;
+            for pass in 0..2 {
+                let remove: Vec<usize> = (0..self.ink.len())
+                    .filter(|&i| self.ink[i] && self.removable(i, pass))
+                    .collect();
+                changed |= !remove.is_empty();
+                for i in remove {
+                    self.ink[i] = false;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    fn removable(&self, index: usize, pass: usize) -> bool {
+        let [n, ne, e, se, s, sw, w, nw] = self.ring(index);
+        let ring = [n, ne, e, se, s, sw, w, nw, n];
+        let count = ring[..8].iter().filter(|&&b| b).count();
+        let transitions = ring.windows(2).filter(|p| !p[0] && p[1]).count();
+        // First pass peels south-east boundaries, the second north-west ones
+        let side = if pass == 0 {
+            !(e && s && (n || w))
+        } else {
+            !(n && w && (e || s))
+        };
+        (2..=6).contains(&count) && transitions == 1 && side
+    }
+
+    /// Skeleton neighbors under m-adjacency
+    ///
+    /// Diagonal neighbors only count when no shared orthogonal neighbor already
+    /// links them, so staircase steps don't look like junctions.
+    fn links(&self, index: usize) -> Vec<usize> {
+        let ring = self.ring(index);
+        (0..8)
+            .filter(|&k| ring[k] && (k % 2 == 0 || (!ring[(k + 7) % 8] && !ring[(k + 1) % 8])))
+            .map(|k| self.neighbor(index, NEIGHBORS[k]))
+            .collect()
+    }
+
+    fn point(&self, index: usize) -> Point {
+        (
+            (index % self.width) as f64 - 1.0,
+            (index / self.width) as f64 - 1.0,
+        )
+    }
+
+    /// Split the skeleton into branches at endpoints and junctions
+    fn branches(&self) -> Vec<Branch> {
+        let links: Vec<Vec<usize>> = (0..self.ink.len())
+            .map(|i| {
+                if self.ink[i] {
+                    self.links(i)
+                } else {
+                    Vec::new()
+                }
+            })
+            .collect();
+        let is_node = |i: usize| self.ink[i] && links[i].len() != 2;
+
+        let mut walked: HashSet<(usize, usize)> = HashSet::new();
+        let mut branches = Vec::new();
+        let walk = |start: usize, first: usize, walked: &mut HashSet<(usize, usize)>| {
+            let mut pixels = vec![start];
+            let (mut previous, mut current) = (start, first);
+            walked.insert((previous, current));
+            walked.insert((current, previous));
+            while current != start && !is_node(current) {
+                pixels.push(current);
+                let Some(&next) = links[current].iter().find(|&&n| n != previous) else {
+                    break;
+                };
+                walked.insert((current, next));
+                walked.insert((next, current));
+                (previous, current) = (current, next);
+            }
+            pixels.push(current);
+            pixels
+        };
+
+        for start in (0..self.ink.len()).filter(|&i| is_node(i)) {
+            for &first in &links[start] {
+                if !walked.contains(&(start, first)) {
+                    let pixels = walk(start, first, &mut walked);
+                    let end = *pixels.last().unwrap();
+                    branches.push(Branch {
+                        points: pixels.iter().map(|&i| self.point(i)).collect(),
+                        ends: Some((start, end)),
+                    });
+                }
+            }
+        }
+
+        // What is left are loops without any node
+        for (start, link) in links.iter().enumerate() {
+            if self.ink[start] && link.len() == 2 && !walked.contains(&(start, link[0])) {
+                let pixels = walk(start, link[0], &mut walked);
+                branches.push(Branch {
+                    points: pixels.iter().map(|&i| self.point(i)).collect(),
+                    ends: None,
+                });
+            }
+        }
+        branches
+    }
+}
+
+/// Remove dead-end branches shorter than `min_length` pixels that hang off a junction
+///
+/// Repeats until stable, as pruning can turn a junction into a new dead end.
+fn prune_spurs(branches: &mut Vec<Branch>, min_length: f64) {
+    loop {
+        let mut degree = std::collections::HashMap::new();
+        for (a, b) in branches.iter().filter_map(|b| b.ends) {
+            *degree.entry(a).or_insert(0) += 1;
+            *degree.entry(b).or_insert(0) += 1;
+        }
+        let before = branches.len();
+        branches.retain(|branch| match branch.ends {
+            Some((a, b)) if a != b => {
+                let (da, db) = (degree[&a], degree[&b]);
+                let spur = (da == 1 && db >= 3) || (db == 1 && da >= 3);
+                !(spur && path_length(&branch.points) < min_length)
+            }
+            _ => true,
+        });
+        if branches.len() == before {
+            break;
+        }
+    }
+}
+
+/// Join branches that meet end to end into longer paths
+///
+/// Greedily continues each path with the nearest unused branch end within a
+/// pixel of either of its ends; otherwise a new path starts.
+fn chain(branches: Vec<Branch>) -> Vec<Vec<Point>> {
+    let mut pending: Vec<Vec<Point>> = branches.into_iter().map(|b| b.points).collect();
+    let mut paths = Vec::new();
+    while let Some(mut path) = pending.pop() {
+        // Grow from the end, then from the start
+        for _ in 0..2 {
+            path.reverse();
+            extend_path(&mut path, &mut pending);
+        }
+        if path.len() >= 2 {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+/// Append branches continuing from the end of `path` for as long as there are any
+fn extend_path(path: &mut Vec<Point>, pending: &mut Vec<Vec<Point>>) {
+    loop {
+        let end = *path.last().unwrap();
+        let next = pending
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                [
+                    (i, false, distance(end, p[0])),
+                    (i, true, distance(end, p[p.len() - 1])),
+                ]
+            })
+            .filter(|&(_, _, d)| d <= 1.5)
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        let Some((i, reversed, _)) = next else {
+            break;
+        };
+        let mut branch = pending.swap_remove(i);
+        if reversed {
+            branch.reverse();
+        }
+        if distance(end, branch[0]) == 0.0 {
+            branch.remove(0);
+        }
+        path.extend(branch);
+    }
+}
+
+/// Moving average over three points, keeping the ends (and closure) in place
+fn smooth(mut path: Vec<Point>, passes: usize) -> Vec<Point> {
+    let closed = path.len() > 3 && path.first() == path.last();
+    for _ in 0..passes {
+        if path.len() < 3 {
+            break;
+        }
+        let n = path.len();
+        let mut next = path.clone();
+        for i in 1..n - 1 {
+            next[i] = average(path[i - 1], path[i], path[i + 1]);
+        }
+        if closed {
+            next[0] = average(path[n - 2], path[0], path[1]);
+            next[n - 1] = next[0];
+        }
+        path = next;
+    }
+    path
+}
+
+fn average(a: Point, b: Point, c: Point) -> Point {
+    ((a.0 + b.0 + c.0) / 3.0, (a.1 + b.1 + c.1) / 3.0)
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn path_length(points: &[Point]) -> f64 {
+    points.windows(2).map(|w| distance(w[0], w[1])).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White image with black pixels where `ink` returns true
+    fn image(width: usize, height: usize, ink: impl Fn(usize, usize) -> bool) -> RgbaImage {
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let v = if ink(x, y) { 0 } else { 255 };
+                pixels.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        RgbaImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn test_thick_line_traces_to_centerline() {
+        let bar = image(60, 15, |x, y| {
+            (5..=60 - 6).contains(&x) && (5..10).contains(&y)
+        });
+        let paths = CenterlineTrace::new().paths(&bar).unwrap();
+        assert_eq!(paths.len(), 1);
+        // The centerline runs along the middle row
+        assert!(paths[0].iter().all(|p| (p.1 - 7.5).abs() < 1.0));
+        assert!(path_length(&paths[0]) > 40.0);
+    }
+
+    #[test]
+    fn test_ring_traces_to_closed_loop() {
+        let ring = image(41, 41, |x, y| {
+            let r = (x as f64 - 20.0).hypot(y as f64 - 20.0);
+            (13.0..=17.0).contains(&r)
+        });
+        let paths = CenterlineTrace::new().paths(&ring).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].first(), paths[0].last());
+        for p in &paths[0] {
+            let r = (p.0 - 20.5).hypot(p.1 - 20.5);
+            assert!((r - 15.0).abs() < 1.5, "radius {}", r);
+        }
+    }
+
+    #[test]
+    fn test_cross_chains_and_prunes() {
+        // A plus sign with a one pixel bump that thinning turns into a spur
+        let plus = image(41, 41, |x, y| {
+            (18..=22).contains(&y) && (2..=38).contains(&x)
+                || (18..=22).contains(&x) && (2..=38).contains(&y)
+                || (x == 30 && y == 23)
+        });
+        let trace = CenterlineTrace::new().min_branch_length(8.0);
+        let paths = trace.paths(&plus).unwrap();
+        // Four arms from the center chain into two paths
+        assert_eq!(paths.len(), 2);
+        let total: f64 = paths.iter().map(|p| path_length(p)).sum();
+        assert!(total > 55.0 && total < 80.0, "total {}", total);
+
+        let pattern = trace.scale(2.0).generate(&plus).unwrap();
+        assert_eq!(pattern.threads().len(), 1);
+        assert_eq!(pattern.count_trims(), 1);
+    }
+
+    #[test]
+    fn test_specks_and_errors() {
+        let speck = image(20, 20, |x, y| x == 10 && y == 10);
+        assert!(CenterlineTrace::new().paths(&speck).unwrap().is_empty());
+
+        let blank = image(20, 20, |_, _| false);
+        let pattern = CenterlineTrace::new().generate(&blank).unwrap();
+        assert_eq!(pattern.count_stitches(), 0);
+
+        assert!(CenterlineTrace::new().scale(0.0).paths(&blank).is_err());
+        let short = RgbaImage {
+            width: 4,
+            height: 4,
+            pixels: vec![0; 8],
+        };
+        assert!(CenterlineTrace::new().paths(&short).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+//! Signature/watermark motif insertion
+//!
+//! Appends a small user-provided motif (a digitizer's signature or logo) next to or
+//! inside a chosen corner of a design. The motif is sewn last: the design is trimmed,
+//! the motif's own threads (if any) are added with a color change, and the motif is
+//! trimmed before the final END.
+//!
+//! Placement is checked against an optional hoop and a density limit, so a
+//! watermark never pushes the design out of the sewing field or piles up stitches on
+//! top of dense fill.
+//!
+//! # Example
+//!
+//! ```
+//! use butabuti::prelude::*;
+//! use butabuti::utils::watermark::{add_watermark, WatermarkCorner, WatermarkOptions};
+//!
+//! let mut design = EmbPattern::new();
+//! design.stitch_abs(0.0, 0.0);
+//! design.stitch_abs(400.0, 300.0);
+//!
+//! let mut signature = EmbPattern::new();
+//! signature.stitch_abs(0.0, 0.0);
+//! signature.stitch_abs(40.0, 0.0);
+//! signature.stitch_abs(40.0, 20.0);
+//!
+//! let options = WatermarkOptions::new(WatermarkCorner::BottomRight).with_hoop(100.0, 100.0);
+//! let placed = add_watermark(&mut design, &signature, &options)?;
+//!
+//! // 2mm below the design, right-aligned with it
+//! assert_eq!(placed.as_tuple(), (360.0, 320.0, 400.0, 340.0));
+//! # Ok::<(), butabuti::utils::error::Error>(())
+//! ```
+
+use crate::core::anchor::{Anchor, AnchorPair};
+use crate::core::constants::*;
+use crate::core::pattern::{Bounds, EmbPattern};
+use crate::utils::error::{Error, Result};
+
+/// Default maximum stitch density under the watermark, in stitches per cm²
+pub const DEFAULT_MAX_DENSITY: f64 = 100.0;
+
+/// Corner of the design where the watermark is placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkCorner {
+    /// Minimum X and Y
+    TopLeft,
+    /// Maximum X, minimum Y
+    TopRight,
+    /// Minimum X, maximum Y
+    BottomLeft,
+    /// Maximum X and Y
+    BottomRight,
+}
+
+impl WatermarkCorner {
+    /// Design anchor at this corner
+    fn anchor(self) -> Anchor {
+        match self {
+            WatermarkCorner::TopLeft => Anchor::TopLeft,
+            WatermarkCorner::TopRight => Anchor::TopRight,
+            WatermarkCorner::BottomLeft => Anchor::BottomLeft,
+            WatermarkCorner::BottomRight => Anchor::BottomRight,
+        }
+    }
+
+    /// Motif anchor that meets the design corner when placed outside the design
+    ///
+    /// The motif sits above top corners and below bottom corners, aligned with
+    /// the design's left or right edge.
+    fn outside_anchor(self) -> Anchor {
+        match self {
+            WatermarkCorner::TopLeft => Anchor::BottomLeft,
+            WatermarkCorner::TopRight => Anchor::BottomRight,
+            WatermarkCorner::BottomLeft => Anchor::TopLeft,
+            WatermarkCorner::BottomRight => Anchor::TopRight,
+        }
+    }
+
+    fn is_top(self) -> bool {
+        matches!(self, WatermarkCorner::TopLeft | WatermarkCorner::TopRight)
+    }
+
+    fn is_left(self) -> bool {
+        matches!(self, WatermarkCorner::TopLeft | WatermarkCorner::BottomLeft)
+    }
+}
+
+/// Watermark placement and safety limits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatermarkOptions {
+    /// Corner of the design to mark (default: bottom-right)
+    pub corner: WatermarkCorner,
+    /// Place the motif inside the design's corner instead of beside it (default: false)
+    pub inside: bool,
+    /// Gap between the motif and the design edge in millimeters (default: 2.0)
+    pub margin_mm: f64,
+    /// Hoop sewing field as (width, height) in millimeters, centered on the origin
+    pub hoop_mm: Option<(f64, f64)>,
+    /// Maximum stitch density under the motif in stitches per cm², counting design and
+    /// motif stitches (default: `DEFAULT_MAX_DENSITY`); `None` disables the check
+    pub max_density: Option<f64>,
+}
+
+impl Default for WatermarkOptions {
+    fn default() -> Self {
+        Self {
+            corner: WatermarkCorner::BottomRight,
+            inside: false,
+            margin_mm: 2.0,
+            hoop_mm: None,
+            max_density: Some(DEFAULT_MAX_DENSITY),
+        }
+    }
+}
+
+impl WatermarkOptions {
+    /// Create options for the given corner
+    pub fn new(corner: WatermarkCorner) -> Self {
+        Self {
+            corner,
+            ..Default::default()
+        }
+    }
+
+    /// Place the motif inside the design's corner
+    pub fn inside(mut self, inside: bool) -> Self {
+        self.inside = inside;
+        self
+    }
+
+    /// Set the gap between motif and design edge in millimeters
+    pub fn margin_mm(mut self, margin_mm: f64) -> Self {
+        self.margin_mm = margin_mm;
+        self
+    }
+
+    /// Reject placements outside a hoop of this size (millimeters, centered on origin)
+    pub fn with_hoop(mut self, width_mm: f64, height_mm: f64) -> Self {
+        self.hoop_mm = Some((width_mm, height_mm));
+        self
+    }
+
+    /// Set the density limit in stitches per cm², or `None` to disable it
+    pub fn max_density(mut self, max_density: Option<f64>) -> Self {
+        self.max_density = max_density;
+        self
+    }
+
+    /// Motif anchor and offset (0.1mm units) for the configured placement
+    fn anchor_pair(&self) -> AnchorPair {
+        let margin = self.margin_mm * 10.0;
+        let target = self.corner.anchor();
+        if self.inside {
+            let dx = if self.corner.is_left() {
+                margin
+            } else {
+                -margin
+            };
+            let dy = if self.corner.is_top() {
+                margin
+            } else {
+                -margin
+            };
+            AnchorPair::new(target.clone(), target).with_offset(dx, dy)
+        } else {
+            let dy = if self.corner.is_top() {
+                -margin
+            } else {
+                margin
+            };
+            AnchorPair::new(self.corner.outside_anchor(), target).with_offset(0.0, dy)
+        }
+    }
+}
+
+/// Append a watermark motif at a corner of the design
+///
+/// The motif's needle-down stitches and commands are appended after a trim; END
+/// records in either pattern are dropped and a single END is written last. When
+/// the motif has its own threads they are added after a color change, otherwise
+/// the motif is sewn with the design's last color.
+///
+/// Returns the bounds of the placed motif. Fails without modifying the design if
+/// either pattern has no stitches, the motif would leave the hoop, or the density
+/// under the motif would exceed the limit.
+pub fn add_watermark(
+    pattern: &mut EmbPattern,
+    motif: &EmbPattern,
+    options: &WatermarkOptions,
+) -> Result<Bounds> {
+    if !has_needle_down(pattern) {
+        return Err(Error::InvalidPattern(
+            "Cannot watermark a pattern without stitches".to_string(),
+        ));
+    }
+    if !has_needle_down(motif) {
+        return Err(Error::InvalidPattern(
+            "Watermark motif has no stitches".to_string(),
+        ));
+    }
+
+    let mut placed = motif.clone();
+    placed.strip_trailing_end();
+    placed.align_to(pattern, &options.anchor_pair())?;
+    let bounds = Bounds::from(placed.bounds());
+
+    if let Some((width_mm, height_mm)) = options.hoop_mm {
+        let (half_w, half_h) = (width_mm * 5.0, height_mm * 5.0);
+        if bounds.min_x < -half_w
+            || bounds.max_x > half_w
+            || bounds.min_y < -half_h
+            || bounds.max_y > half_h
+        {
+            return Err(Error::InvalidPattern(format!(
+                "Watermark at ({:.1}, {:.1})-({:.1}, {:.1}) exceeds the {}x{}mm hoop",
+                bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y, width_mm, height_mm
+            )));
+        }
+    }
+
+    if let Some(max_density) = options.max_density {
+        let density = density_under(pattern, &placed, &bounds);
+        if density > max_density {
+            return Err(Error::InvalidPattern(format!(
+                "Watermark density {:.1} stitches/cm² exceeds limit of {:.1}",
+                density, max_density
+            )));
+        }
+    }
+
+    append_motif(pattern, &placed);
+    Ok(bounds)
+}
+
+fn has_needle_down(pattern: &EmbPattern) -> bool {
+    pattern
+        .stitches()
+        .iter()
+        .any(|s| s.command & COMMAND_MASK == STITCH)
+}
+
+/// Design and motif needle-down stitches per cm² within the motif's bounds
+///
+/// Each side of the area counts as at least 1mm, so line-shaped motifs do not
+/// report infinite density.
+fn density_under(pattern: &EmbPattern, motif: &EmbPattern, bounds: &Bounds) -> f64 {
+    let inside = |x: f64, y: f64| {
+        x >= bounds.min_x && x <= bounds.max_x && y >= bounds.min_y && y <= bounds.max_y
+    };
+    let count = pattern
+        .stitches()
+        .iter()
+        .chain(motif.stitches())
+        .filter(|s| s.command & COMMAND_MASK == STITCH && inside(s.x, s.y))
+        .count();
+
+    let area_cm2 = (bounds.width().max(10.0) / 100.0) * (bounds.height().max(10.0) / 100.0);
+    count as f64 / area_cm2
+}
+
+fn append_motif(pattern: &mut EmbPattern, motif: &EmbPattern) {
+    pattern.strip_trailing_end();
+
+    let (last_x, last_y) = pattern.stitches().last().map_or((0.0, 0.0), |s| (s.x, s.y));
+    let last_command = pattern.stitches().last().map(|s| s.command & COMMAND_MASK);
+    if last_command != Some(TRIM) {
+        pattern.add_command(TRIM, last_x, last_y);
+    }
+
+    if !motif.threads().is_empty() {
+        pattern.add_command(COLOR_CHANGE, last_x, last_y);
+        for thread in motif.threads() {
+            pattern.add_thread(thread.clone());
+        }
+    }
+
+    let mut records = motif
+        .stitches()
+        .iter()
+        .filter(|s| s.command & COMMAND_MASK != END)
+        .peekable();
+    if let Some(first) = records.peek() {
+        pattern.add_stitch_absolute(JUMP, first.x, first.y);
+    }
+    let mut last = None;
+    for stitch in records {
+        pattern.add_stitch_absolute(stitch.command, stitch.x, stitch.y);
+        last = Some(*stitch);
+    }
+
+    if let Some(last) = last {
+        if last.command & COMMAND_MASK != TRIM {
+            pattern.add_command(TRIM, last.x, last.y);
+        }
+        pattern.add_command(END, last.x, last.y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::thread::EmbThread;
+
+    fn design() -> EmbPattern {
+        let mut pattern = EmbPattern::new();
+        pattern.add_thread(EmbThread::new(0xFF0000));
+        pattern.add_stitch_absolute(STITCH, -100.0, -100.0);
+        pattern.add_stitch_absolute(STITCH, 100.0, 100.0);
+        pattern.add_command(END, 100.0, 100.0);
+        pattern
+    }
+
+    fn signature() -> EmbPattern {
+        let mut motif = EmbPattern::new();
+        motif.add_stitch_absolute(STITCH, 0.0, 0.0);
+        motif.add_stitch_absolute(STITCH, 30.0, 10.0);
+        motif.add_command(END, 30.0, 10.0);
+        motif
+    }
+
+    fn commands(pattern: &EmbPattern) -> Vec<u32> {
+        pattern
+            .stitches()
+            .iter()
+            .map(|s| s.command & COMMAND_MASK)
+            .collect()
+    }
+
+    #[test]
+    fn test_outside_corners() {
+        let mut pattern = design();
+        let placed =
+            add_watermark(&mut pattern, &signature(), &WatermarkOptions::default()).unwrap();
+        assert_eq!(placed.as_tuple(), (70.0, 120.0, 100.0, 130.0));
+
+        let mut pattern = design();
+        let options = WatermarkOptions::new(WatermarkCorner::TopLeft).margin_mm(1.0);
+        let placed = add_watermark(&mut pattern, &signature(), &options).unwrap();
+        assert_eq!(placed.as_tuple(), (-100.0, -120.0, -70.0, -110.0));
+    }
+
+    #[test]
+    fn test_records_and_threads() {
+        let mut pattern = design();
+        let mut motif = signature();
+        motif.add_thread(EmbThread::new(0x000080));
+
+        add_watermark(&mut pattern, &motif, &WatermarkOptions::default()).unwrap();
+
+        assert_eq!(
+            commands(&pattern),
+            vec![
+                STITCH,
+                STITCH,
+                TRIM,
+                COLOR_CHANGE,
+                JUMP,
+                STITCH,
+                STITCH,
+                TRIM,
+                END
+            ]
+        );
+        assert_eq!(pattern.threads().len(), 2);
+        assert_eq!(pattern.threads()[1].color, 0x000080);
+
+        // Without motif threads the design color is reused
+        let mut pattern = design();
+        add_watermark(&mut pattern, &signature(), &WatermarkOptions::default()).unwrap();
+        assert!(!commands(&pattern).contains(&COLOR_CHANGE));
+        assert_eq!(pattern.threads().len(), 1);
+    }
+
+    #[test]
+    fn test_hoop_bounds() {
+        let mut pattern = design();
+        let options = WatermarkOptions::default().with_hoop(25.0, 25.0);
+        let err = add_watermark(&mut pattern, &signature(), &options).unwrap_err();
+        assert!(err.to_string().contains("hoop"));
+        assert_eq!(pattern.stitches().len(), 3);
+
+        let options = WatermarkOptions::default().with_hoop(30.0, 30.0);
+        assert!(add_watermark(&mut pattern, &signature(), &options).is_ok());
+    }
+
+    #[test]
+    fn test_density_limit_inside() {
+        let mut pattern = design();
+        for i in 0..50 {
+            pattern.add_stitch_absolute(STITCH, 80.0 + (i % 5) as f64, 95.0);
+        }
+        let options = WatermarkOptions::default().inside(true).margin_mm(0.0);
+
+        let err = add_watermark(&mut pattern.clone(), &signature(), &options).unwrap_err();
+        assert!(err.to_string().contains("density"));
+
+        let relaxed = options.max_density(None);
+        let placed = add_watermark(&mut pattern, &signature(), &relaxed).unwrap();
+        assert_eq!(placed.as_tuple(), (70.0, 90.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn test_empty_patterns() {
+        let options = WatermarkOptions::default();
+        assert!(add_watermark(&mut EmbPattern::new(), &signature(), &options).is_err());
+        assert!(add_watermark(&mut design(), &EmbPattern::new(), &options).is_err());
+    }
+}
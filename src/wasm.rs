@@ -29,7 +29,6 @@
 //! ```
 
 use crate::core::pattern::EmbPattern;
-use crate::formats::io::readers;
 use crate::formats::io::writers;
 use crate::utils::error::{Error, Result};
 use std::io::Cursor;
@@ -41,79 +40,9 @@ pub fn init_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
-/// Internal helper: Unified reader that handles all format API variations
-///
-/// # API Inconsistency Note
-///
-/// Butabuti readers currently use two different patterns:
-/// - **Legacy API**: Returns `Result<EmbPattern>` (DST, JEF, EXP, PEC, JSON)
-/// - **Modern API**: Mutates `&mut EmbPattern` and returns `Result<()>` (PES, VP3, XXX, U01, TBF, etc.)
-///
-/// This helper abstracts the difference to provide a unified interface for WASM.
-/// Future refactor: unify all readers to use the mutation pattern.
+/// Internal helper: Read a pattern of any registered format
 fn read_pattern(data: &[u8], format: &str) -> Result<EmbPattern> {
-    let mut cursor = Cursor::new(data);
-    let mut pattern = EmbPattern::new();
-
-    match format.to_lowercase().as_str() {
-        // Formats that return EmbPattern (legacy API)
-        "dst" => {
-            pattern = readers::dst::read(&mut cursor, None)?;
-        }
-        "jef" => {
-            pattern = readers::jef::read(&mut cursor, None)?;
-        }
-        "exp" => {
-            pattern = readers::exp::read(&mut cursor)?;
-        }
-        "pec" => {
-            pattern = readers::pec::read(&mut cursor)?;
-        }
-        "json" => {
-            pattern = readers::json::read(&mut cursor)?;
-        }
-
-        // Formats that mutate pattern (modern API)
-        "pes" => {
-            readers::pes::read(&mut cursor, &mut pattern)?;
-        }
-        "vp3" => {
-            readers::vp3::read(&mut cursor, &mut pattern)?;
-        }
-        "xxx" => {
-            readers::xxx::read(&mut cursor, &mut pattern)?;
-        }
-        "u01" => {
-            readers::u01::read(&mut cursor, &mut pattern)?;
-        }
-        "tbf" => {
-            readers::tbf::read(&mut cursor, &mut pattern)?;
-        }
-        "csv" => {
-            readers::csv::read(&mut cursor, &mut pattern)?;
-        }
-        "col" => {
-            readers::col::read(&mut cursor, &mut pattern)?;
-        }
-        "edr" => {
-            readers::edr::read(&mut cursor, &mut pattern)?;
-        }
-        "inf" => {
-            readers::inf::read(&mut cursor, &mut pattern)?;
-        }
-        "gcode" => {
-            readers::gcode::read(&mut cursor, &mut pattern)?;
-        }
-
-        _ => {
-            return Err(Error::UnsupportedFormat(format!(
-                "Unsupported read format: {}",
-                format
-            )))
-        }
-    }
-
-    Ok(pattern)
+    crate::formats::io::read_from_bytes(data, format)
 }
 
 /// Internal helper: Unified writer that handles all format API variations
@@ -123,7 +52,7 @@ fn read_pattern(data: &[u8], format: &str) -> Result<EmbPattern> {
 /// Butabuti writers have varying signatures:
 /// - Some require `Seek` capability (PES, PEC, XXX, TBF, INF) - use Cursor
 /// - Some have extra parameters with defaults (DST, JEF, CSV)
-/// - Most accept `impl Write` (EXP, VP3, U01, SVG, JSON, TXT, COL, EDR)
+/// - Most accept `impl Write` (EXP, VP3, U01, DSB, DSZ, ZXY, HUS, SHV, SVG, JSON, TXT, COL, EDR)
 ///
 /// This helper uses appropriate defaults and Cursor wrappers as needed.
 fn write_pattern(pattern: &EmbPattern, format: &str) -> Result<Vec<u8>> {
@@ -135,7 +64,10 @@ fn write_pattern(pattern: &EmbPattern, format: &str) -> Result<Vec<u8>> {
             writers::dst::write(&mut output, pattern, false, 121)?;
         }
         "jef" => {
-            writers::jef::write(&mut output, pattern, true, 127, "")?;
+            let options = writers::jef::JefWriteOptions::new()
+                .trims(true, 127)
+                .date("");
+            writers::jef::write(&mut output, pattern, &options)?;
         }
         "csv" => {
             writers::csv::write(&mut output, pattern, writers::csv::CsvVersion::Default)?;
@@ -173,6 +105,21 @@ fn write_pattern(pattern: &EmbPattern, format: &str) -> Result<Vec<u8>> {
         "u01" => {
             writers::u01::write(pattern, &mut output)?;
         }
+        "dsb" => {
+            writers::dsb::write(pattern, &mut output)?;
+        }
+        "dsz" => {
+            writers::dsz::write(pattern, &mut output)?;
+        }
+        "zxy" => {
+            writers::zxy::write(pattern, &mut output)?;
+        }
+        "hus" => {
+            writers::hus::write(pattern, &mut output)?;
+        }
+        "shv" => {
+            writers::shv::write(pattern, &mut output)?;
+        }
         "svg" => {
             writers::svg::write(pattern, &mut output)?;
         }
@@ -285,7 +232,7 @@ pub fn get_pattern_info(input_data: &[u8], format: &str) -> std::result::Result<
                 "red": thread.red(),
                 "green": thread.green(),
                 "blue": thread.blue(),
-                "description": thread.description.as_deref().unwrap_or("Unknown")
+                "description": thread.display_name()
             })
         })
         .collect();
@@ -310,6 +257,33 @@ pub fn get_pattern_info(input_data: &[u8], format: &str) -> std::result::Result<
     Ok(info.to_string())
 }
 
+/// Export a per-stitch timeline for progress scrubbers
+///
+/// Returns compact JSON with parallel `block`, `x`, `y` and `elapsed_ms` arrays
+/// (one entry per stitch record) plus the color `blocks`, timed at
+/// a top speed of `stitches_per_minute`.
+///
+/// # Example
+///
+/// ```javascript
+/// const timeline = JSON.parse(export_timeline(dstBytes, 'dst', 800));
+/// const elapsed = Uint32Array.from(timeline.elapsed_ms);
+/// ```
+#[wasm_bindgen]
+pub fn export_timeline(
+    input_data: &[u8],
+    format: &str,
+    stitches_per_minute: f64,
+) -> std::result::Result<String, JsValue> {
+    let pattern = read_pattern(input_data, format)
+        .map_err(|e| JsValue::from_str(&format!("Failed to read {}: {}", format, e)))?;
+
+    let profile = crate::utils::simulate::TimingProfile::new(stitches_per_minute);
+    crate::utils::timeline::StitchTimeline::from_pattern(&pattern, &profile)
+        .to_json()
+        .map_err(|e| JsValue::from_str(&format!("Failed to export timeline: {}", e)))
+}
+
 /// Export pattern to SVG for visualization
 ///
 /// Converts embroidery pattern to SVG format for display in the browser.
@@ -212,10 +212,10 @@ proptest! {
 
         // Test transformations don't panic
         let mut p1 = pattern.clone();
-        let _ = p1.rotate(angle);
+        p1.rotate(angle);
 
         let mut p2 = pattern.clone();
-        let _ = p2.scale(scale_x, scale_y);
+        p2.scale(scale_x, scale_y);
 
         let mut p3 = pattern.clone();
         p3.flip_horizontal();
@@ -273,6 +273,23 @@ proptest! {
     }
 }
 
+// Test PES, VP3 and HUS readers with random data, strict and lenient
+// (the same reads as the cargo-fuzz targets under fuzz/)
+proptest! {
+    #[test]
+    fn fuzz_header_driven_readers_random(data in random_bytes()) {
+        use butabuti::formats::io::options::ReadOptions;
+        use butabuti::formats::io::traits::PatternReader;
+
+        for format in [Format::PES, Format::VP3, Format::HUS] {
+            for options in [ReadOptions::new(), ReadOptions::new().lenient(true)] {
+                // Should not panic
+                let _ = format.read_with_warnings(&mut Cursor::new(&data), &options);
+            }
+        }
+    }
+}
+
 // Test U01 format with random data
 proptest! {
     #[test]